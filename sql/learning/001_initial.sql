@@ -0,0 +1,45 @@
+-- Learned context: what was asked, what was suggested, and what worked
+
+CREATE TABLE IF NOT EXISTS queries (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    prompt TEXT NOT NULL UNIQUE,
+    category TEXT NOT NULL,
+    ask_count INTEGER NOT NULL DEFAULT 1,
+    first_asked TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    last_asked TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE IF NOT EXISTS suggestions (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    query_id INTEGER NOT NULL REFERENCES queries(id) ON DELETE CASCADE,
+    command TEXT NOT NULL,
+    source TEXT NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (query_id, command)
+);
+
+CREATE TABLE IF NOT EXISTS feedback (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    query_id INTEGER NOT NULL REFERENCES queries(id) ON DELETE CASCADE,
+    command TEXT NOT NULL,
+    success BOOLEAN NOT NULL,
+    exit_code INTEGER,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+-- One row per prompt/command pair; accepted once the user ran it successfully
+CREATE TABLE IF NOT EXISTS patterns (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    prompt TEXT NOT NULL,
+    command TEXT NOT NULL,
+    category TEXT NOT NULL,
+    accepted BOOLEAN NOT NULL DEFAULT FALSE,
+    hits INTEGER NOT NULL DEFAULT 1,
+    last_seen TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (prompt, command)
+);
+
+CREATE INDEX IF NOT EXISTS idx_queries_last_asked ON queries(last_asked);
+CREATE INDEX IF NOT EXISTS idx_feedback_created_at ON feedback(created_at);
+CREATE INDEX IF NOT EXISTS idx_patterns_category ON patterns(category, accepted, hits);
+CREATE INDEX IF NOT EXISTS idx_patterns_last_seen ON patterns(last_seen);
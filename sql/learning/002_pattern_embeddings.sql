@@ -0,0 +1,2 @@
+-- Vector for each pattern's prompt, used to find similar past queries
+ALTER TABLE patterns ADD COLUMN embedding BLOB;
@@ -0,0 +1,10 @@
+-- Compact rules the model wrote from older accepted patterns, which they replace
+CREATE TABLE IF NOT EXISTS rules (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    category TEXT NOT NULL,
+    rule TEXT NOT NULL,
+    patterns INTEGER NOT NULL DEFAULT 0,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_rules_category ON rules(category);
@@ -0,0 +1,12 @@
+-- Which arm of a prompt experiment answered a query, and what the user did with it
+CREATE TABLE IF NOT EXISTS experiment_runs (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    experiment TEXT NOT NULL,
+    arm TEXT NOT NULL,
+    prompt TEXT NOT NULL,
+    accepted BOOLEAN NOT NULL DEFAULT FALSE,
+    succeeded BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_experiment_runs_arm ON experiment_runs(experiment, arm);
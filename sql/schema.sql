@@ -12,11 +12,13 @@ CREATE TABLE IF NOT EXISTS suggestions (
     last_used TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
     use_count INTEGER DEFAULT 0,
     success_count INTEGER DEFAULT 0,
-    success_rate REAL DEFAULT 0.5
+    success_rate REAL DEFAULT 0.5,
+    warmed_at TIMESTAMP, -- set by `commandy cache warm`, served before it has been used
+    model TEXT NOT NULL DEFAULT '' -- fingerprint of the model that suggested it (see ModelIdentity)
 );
 
--- Create unique index on prompt_hash + suggestion combination
-CREATE UNIQUE INDEX IF NOT EXISTS idx_suggestions_unique ON suggestions(prompt_hash, suggestion);
+-- The unique index on model + prompt_hash + suggestion is created by the
+-- cache's migrations, once older tables have the model column
 
 -- Command execution history
 CREATE TABLE IF NOT EXISTS history (
@@ -29,6 +31,22 @@ CREATE TABLE IF NOT EXISTS history (
     context_snapshot TEXT -- JSON of environment at execution time
 );
 
+-- Command explanations, kept much longer than suggestions
+CREATE TABLE IF NOT EXISTS explanations (
+    command TEXT PRIMARY KEY, -- canonical command (see CommandNormalizer)
+    explanation TEXT NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    last_used TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    use_count INTEGER DEFAULT 0
+);
+
+-- Summaries of tools for the picker's info pane, from their man pages
+CREATE TABLE IF NOT EXISTS tool_summaries (
+    tool TEXT PRIMARY KEY,
+    summary TEXT NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
 -- Environment tracking
 CREATE TABLE IF NOT EXISTS environment (
     key TEXT PRIMARY KEY,
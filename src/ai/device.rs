@@ -0,0 +1,227 @@
+use anyhow::{bail, Context, Result};
+use std::fmt;
+use std::path::Path;
+use std::process::Command;
+
+/// Layers offloaded to a GPU; more than any model has, so all of them
+const ALL_LAYERS: &str = "999";
+
+/// A GPU backend llama.cpp can be built with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GpuBackend {
+    Cuda,
+    Metal,
+    Vulkan,
+    Rocm,
+}
+
+impl GpuBackend {
+    const ALL: [Self; 4] = [Self::Cuda, Self::Metal, Self::Vulkan, Self::Rocm];
+
+    /// Name in `[model] device`
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Cuda => "cuda",
+            Self::Metal => "metal",
+            Self::Vulkan => "vulkan",
+            Self::Rocm => "rocm",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Cuda => "CUDA",
+            Self::Metal => "Metal",
+            Self::Vulkan => "Vulkan",
+            Self::Rocm => "ROCm",
+        }
+    }
+
+    /// The backend of a device listed by `--list-devices`, such as `CUDA0` or `MTL0`
+    fn of_device(name: &str) -> Option<Self> {
+        let prefix = name.trim_end_matches(|c: char| c.is_ascii_digit());
+        match prefix {
+            "CUDA" => Some(Self::Cuda),
+            "Metal" | "MTL" => Some(Self::Metal),
+            "Vulkan" => Some(Self::Vulkan),
+            "ROCm" | "HIP" => Some(Self::Rocm),
+            _ => None,
+        }
+    }
+}
+
+/// Where llama.cpp runs the model, set with `[model] device`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Device {
+    Cpu,
+    Gpu { backend: GpuBackend, index: u32 },
+}
+
+impl Device {
+    /// Parses `cpu`, `metal`, or a backend with an optional index, like `cuda:1`
+    pub fn parse(text: &str) -> Result<Self> {
+        let text = text.trim().to_lowercase();
+        if text == "cpu" {
+            return Ok(Self::Cpu);
+        }
+        let (name, index) = match text.split_once(':') {
+            Some((name, index)) => (
+                name,
+                index
+                    .parse()
+                    .with_context(|| format!("Invalid device index in '{text}'"))?,
+            ),
+            None => (text.as_str(), 0),
+        };
+        match GpuBackend::ALL.into_iter().find(|b| b.as_str() == name) {
+            Some(backend) => Ok(Self::Gpu { backend, index }),
+            None => {
+                bail!("Unknown device '{text}' (expected cpu, metal, cuda:N, vulkan:N, or rocm:N)")
+            }
+        }
+    }
+
+    /// The name llama.cpp lists this device under, `None` for the CPU and Metal,
+    /// which are picked without one
+    pub fn llama_name(self) -> Option<String> {
+        match self {
+            Self::Cpu
+            | Self::Gpu {
+                backend: GpuBackend::Metal,
+                ..
+            } => None,
+            Self::Gpu { backend, index } => Some(format!("{}{index}", backend.label())),
+        }
+    }
+
+    /// llama.cpp arguments that run the whole model on this device
+    pub fn llama_args(self) -> Vec<String> {
+        let args: Vec<&str> = match (self, self.llama_name()) {
+            (Self::Cpu, _) => vec!["--device", "none", "-ngl", "0"],
+            (_, Some(name)) => {
+                return vec!["--device".into(), name, "-ngl".into(), ALL_LAYERS.into()]
+            }
+            (_, None) => vec!["-ngl", ALL_LAYERS],
+        };
+        args.into_iter().map(String::from).collect()
+    }
+}
+
+impl fmt::Display for Device {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Cpu => write!(f, "cpu"),
+            Self::Gpu {
+                backend: GpuBackend::Metal,
+                ..
+            } => write!(f, "metal"),
+            Self::Gpu { backend, index } => write!(f, "{}:{index}", backend.as_str()),
+        }
+    }
+}
+
+/// A GPU llama.cpp found, such as `CUDA0: NVIDIA GeForce RTX 4090 (24080 MiB, ...)`
+#[derive(Debug, Clone)]
+pub struct ListedDevice {
+    pub name: String,
+    pub description: String,
+}
+
+/// The GPU backends a llama.cpp binary was built with and the devices it sees
+#[derive(Debug, Clone, Default)]
+pub struct DeviceProbe {
+    pub devices: Vec<ListedDevice>,
+    pub backends: Vec<GpuBackend>,
+}
+
+impl DeviceProbe {
+    /// Asks the binary with `--list-devices`, or, for builds too old to have it,
+    /// looks for backend names in what `--version` prints
+    pub fn run(binary: &Path) -> Result<Self> {
+        let output = Command::new(binary)
+            .arg("--list-devices")
+            .output()
+            .context("Failed to execute llama.cpp binary")?;
+        if output.status.success() {
+            return Ok(Self::from_list(&String::from_utf8_lossy(&output.stdout)));
+        }
+
+        let output = Command::new(binary)
+            .arg("--version")
+            .output()
+            .context("Failed to execute llama.cpp binary")?;
+        let text = format!(
+            "{}{}",
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        );
+        let backends = GpuBackend::ALL
+            .into_iter()
+            .filter(|b| text.contains(b.label()))
+            .collect();
+        Ok(Self {
+            devices: Vec::new(),
+            backends,
+        })
+    }
+
+    fn from_list(text: &str) -> Self {
+        let devices: Vec<ListedDevice> = text
+            .lines()
+            .skip_while(|line| !line.starts_with("Available devices"))
+            .skip(1)
+            .filter_map(|line| line.trim().split_once(": "))
+            .map(|(name, description)| ListedDevice {
+                name: name.to_string(),
+                description: description.to_string(),
+            })
+            .collect();
+        let mut backends = Vec::new();
+        for backend in devices
+            .iter()
+            .filter_map(|d| GpuBackend::of_device(&d.name))
+        {
+            if !backends.contains(&backend) {
+                backends.push(backend);
+            }
+        }
+        Self { devices, backends }
+    }
+
+    pub fn is_cpu_only(&self) -> bool {
+        self.backends.is_empty()
+    }
+
+    /// Whether the binary can run on `device`; with only `--version` to go on,
+    /// any index of a built-in backend is assumed to exist
+    pub fn supports(&self, device: Device) -> bool {
+        match device {
+            Device::Cpu => true,
+            Device::Gpu { backend, .. } if self.devices.is_empty() => {
+                self.backends.contains(&backend)
+            }
+            Device::Gpu {
+                backend: GpuBackend::Metal,
+                ..
+            } => self.backends.contains(&GpuBackend::Metal),
+            Device::Gpu { .. } => self
+                .devices
+                .iter()
+                .any(|d| Some(&d.name) == device.llama_name().as_ref()),
+        }
+    }
+
+    /// A GPU on this machine that a llama.cpp build could use, whatever this one supports
+    pub fn host_gpu() -> Option<(GpuBackend, &'static str)> {
+        if cfg!(all(target_os = "macos", target_arch = "aarch64")) {
+            return Some((GpuBackend::Metal, "an Apple silicon GPU"));
+        }
+        if Path::new("/proc/driver/nvidia/version").exists() || which::which("nvidia-smi").is_ok() {
+            return Some((GpuBackend::Cuda, "an NVIDIA GPU"));
+        }
+        if Path::new("/dev/kfd").exists() {
+            return Some((GpuBackend::Rocm, "an AMD GPU"));
+        }
+        None
+    }
+}
@@ -0,0 +1,57 @@
+use crate::config::ExperimentConfig;
+
+/// Which prompt a query got in an experiment
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Arm {
+    /// The built-in suggestion prompt
+    Control,
+    /// The `[experiment] variant` instructions
+    Variant,
+}
+
+impl Arm {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Arm::Control => "control",
+            Arm::Variant => "variant",
+        }
+    }
+}
+
+/// A running `[experiment]`: each query is randomly given one of two prompts
+#[derive(Debug, Clone)]
+pub struct Experiment {
+    pub name: String,
+    share: f32,
+    variant: String,
+}
+
+impl Experiment {
+    /// `None` unless `[experiment]` has both a name and a variant
+    pub fn from_config(config: &ExperimentConfig) -> Option<Self> {
+        let name = config.name.as_deref().filter(|n| !n.trim().is_empty())?;
+        let variant = config.variant.as_deref().filter(|v| !v.trim().is_empty())?;
+        Some(Self {
+            name: name.to_string(),
+            share: config.share.clamp(0.0, 1.0),
+            variant: variant.to_string(),
+        })
+    }
+
+    /// Picks an arm for one query, the variant with probability `share`
+    pub fn assign(&self) -> Arm {
+        let roll = (uuid::Uuid::new_v4().as_u128() as u32) as f32 / u32::MAX as f32;
+        match roll < self.share {
+            true => Arm::Variant,
+            false => Arm::Control,
+        }
+    }
+
+    /// Instructions for the suggestion prompt in `arm`; `None` keeps the built-in ones
+    pub fn instructions(&self, arm: Arm) -> Option<&str> {
+        match arm {
+            Arm::Control => None,
+            Arm::Variant => Some(&self.variant),
+        }
+    }
+}
@@ -0,0 +1,162 @@
+use anyhow::{bail, Context, Result};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+/// Longest key or string value read; anything longer means the file isn't GGUF
+const MAX_STRING: u64 = 1 << 20;
+
+/// Value types of GGUF metadata, by their number in the file
+const STRING: u32 = 8;
+const ARRAY: u32 = 9;
+
+/// The metadata at the start of a GGUF model file, enough to tell how much
+/// memory the model takes without loading it
+///
+/// Only integer values and the architecture are kept. An integer array, such
+/// as a per-layer head count, is kept as its largest value.
+#[derive(Debug, Clone)]
+pub struct GgufHeader {
+    /// e.g. "llama" or "gemma3"; most keys are prefixed with it
+    pub architecture: String,
+    /// Size of the whole file, which is mostly the weights
+    pub file_size: u64,
+    values: HashMap<String, u64>,
+}
+
+impl GgufHeader {
+    pub fn read(path: &Path) -> Result<Self> {
+        let file =
+            File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+        let file_size = file.metadata()?.len();
+        let mut reader = BufReader::new(file);
+
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != b"GGUF" {
+            bail!("{} is not a GGUF file", path.display());
+        }
+        // Version 1 used 32-bit counts; nothing current writes it
+        let version = Self::u32(&mut reader)?;
+        if version < 2 {
+            bail!("GGUF version {version} is not supported");
+        }
+        let _tensors = Self::u64(&mut reader)?;
+        let count = Self::u64(&mut reader)?;
+
+        let mut architecture = String::new();
+        let mut values = HashMap::new();
+        for _ in 0..count {
+            let key = Self::string(&mut reader)?;
+            let kind = Self::u32(&mut reader)?;
+            match kind {
+                STRING if key == "general.architecture" => {
+                    architecture = Self::string(&mut reader)?;
+                }
+                ARRAY => {
+                    let item = Self::u32(&mut reader)?;
+                    let len = Self::u64(&mut reader)?;
+                    let mut largest = None;
+                    for _ in 0..len {
+                        if let Some(value) = Self::value(&mut reader, item)? {
+                            largest = largest.max(Some(value));
+                        }
+                    }
+                    if let Some(value) = largest {
+                        values.insert(key, value);
+                    }
+                }
+                _ => {
+                    if let Some(value) = Self::value(&mut reader, kind)? {
+                        values.insert(key, value);
+                    }
+                }
+            }
+        }
+
+        Ok(Self {
+            architecture,
+            file_size,
+            values,
+        })
+    }
+
+    /// An integer the architecture defines, such as "block_count" for "llama.block_count"
+    pub fn get(&self, name: &str) -> Option<u64> {
+        self.values
+            .get(&format!("{}.{name}", self.architecture))
+            .copied()
+    }
+
+    /// Reads one value, returning it when it is a non-negative integer and skipping it otherwise
+    fn value(reader: &mut BufReader<File>, kind: u32) -> Result<Option<u64>> {
+        let mut bytes = [0u8; 8];
+        let value = match kind {
+            0 | 1 | 7 => {
+                reader.read_exact(&mut bytes[..1])?;
+                (kind != 1 || bytes[0] < 0x80).then_some(u64::from(bytes[0]))
+            }
+            2 | 3 => {
+                reader.read_exact(&mut bytes[..2])?;
+                let value = u16::from_le_bytes([bytes[0], bytes[1]]);
+                (kind == 2 || value < 0x8000).then_some(u64::from(value))
+            }
+            4 | 5 => {
+                let value = Self::u32(reader)?;
+                (kind == 4 || value < 0x8000_0000).then_some(u64::from(value))
+            }
+            6 => {
+                reader.seek_relative(4)?;
+                None
+            }
+            10 | 11 => {
+                let value = Self::u64(reader)?;
+                (kind == 10 || value < 1 << 63).then_some(value)
+            }
+            12 => {
+                reader.seek_relative(8)?;
+                None
+            }
+            STRING => {
+                let len = Self::u64(reader)?;
+                if len > MAX_STRING {
+                    bail!("GGUF string of {len} bytes");
+                }
+                reader.seek_relative(len as i64)?;
+                None
+            }
+            ARRAY => {
+                let item = Self::u32(reader)?;
+                for _ in 0..Self::u64(reader)? {
+                    Self::value(reader, item)?;
+                }
+                None
+            }
+            _ => bail!("Unknown GGUF value type {kind}"),
+        };
+        Ok(value)
+    }
+
+    fn string(reader: &mut BufReader<File>) -> Result<String> {
+        let len = Self::u64(reader)?;
+        if len > MAX_STRING {
+            bail!("GGUF string of {len} bytes");
+        }
+        let mut bytes = vec![0u8; len as usize];
+        reader.read_exact(&mut bytes)?;
+        Ok(String::from_utf8_lossy(&bytes).into_owned())
+    }
+
+    fn u32(reader: &mut BufReader<File>) -> Result<u32> {
+        let mut bytes = [0u8; 4];
+        reader.read_exact(&mut bytes)?;
+        Ok(u32::from_le_bytes(bytes))
+    }
+
+    fn u64(reader: &mut BufReader<File>) -> Result<u64> {
+        let mut bytes = [0u8; 8];
+        reader.read_exact(&mut bytes)?;
+        Ok(u64::from_le_bytes(bytes))
+    }
+}
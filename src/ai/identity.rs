@@ -0,0 +1,99 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::fs::File;
+use std::io::Read;
+use std::path::PathBuf;
+
+use crate::config::Settings;
+
+/// Bytes of the model file read for its fingerprint; the GGUF header, with the
+/// architecture and quantization, sits at the start
+const HEADER_BYTES: u64 = 1024 * 1024;
+
+/// Which model produces suggestions, so answers cached from one model aren't
+/// taken for another's
+///
+/// The fingerprint covers the configured repo and the downloaded file's size
+/// and header, so a different quantization or a re-download that changed the
+/// file gets a new one, while the same file on another machine gets the same.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModelIdentity {
+    /// The configured model, e.g. ggml-org/gemma-3-1b-it-GGUF
+    pub model: String,
+    pub fingerprint: String,
+}
+
+impl ModelIdentity {
+    /// The main model `settings` configure
+    pub fn of(settings: &Settings) -> Self {
+        let model = settings.model.model_path.clone();
+        let mut hasher = Sha256::new();
+        hasher.update(model.as_bytes());
+        hasher.update([0]);
+
+        // A server's model is whatever it was started with
+        if let Some(url) = &settings.model.server_url {
+            hasher.update(url.trim_end_matches('/').as_bytes());
+        } else if let Some(path) = Self::downloaded_file(&model) {
+            if let Ok(mut file) = File::open(&path) {
+                let size = file.metadata().map(|m| m.len()).unwrap_or(0);
+                hasher.update(size.to_le_bytes());
+                let mut header = Vec::new();
+                let _ = (&mut file).take(HEADER_BYTES).read_to_end(&mut header);
+                hasher.update(&header);
+            }
+        }
+
+        let fingerprint = hasher.finalize()[..8]
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect();
+        Self { model, fingerprint }
+    }
+
+    /// Where llama.cpp keeps `-hf` downloads
+    pub fn download_dir() -> Option<PathBuf> {
+        std::env::var("LLAMA_CACHE")
+            .map(PathBuf::from)
+            .ok()
+            .or_else(|| dirs::cache_dir().map(|d| d.join("llama.cpp")))
+    }
+
+    /// The newest GGUF llama.cpp downloaded for `repo` ("owner/name" or
+    /// "owner/name:quant"), saved as owner_name_<file>.gguf
+    pub fn downloaded_file(repo: &str) -> Option<PathBuf> {
+        let (name, quant) = match repo.split_once(':') {
+            Some((name, quant)) => (name, Some(quant.to_lowercase())),
+            None => (repo, None),
+        };
+        let prefix = format!("{}_", name.replace('/', "_"));
+
+        std::fs::read_dir(Self::download_dir()?)
+            .ok()?
+            .flatten()
+            .filter_map(|entry| {
+                let file_name = entry.file_name().to_string_lossy().to_string();
+                let lower = file_name.to_lowercase();
+                let matches = file_name.starts_with(&prefix)
+                    && lower.ends_with(".gguf")
+                    && !lower.contains("mmproj")
+                    && quant
+                        .as_ref()
+                        .is_none_or(|quant| lower.contains(quant.as_str()));
+                if !matches {
+                    return None;
+                }
+                let modified = entry.metadata().ok()?.modified().ok()?;
+                Some((modified, entry.path()))
+            })
+            .max_by_key(|(modified, _)| *modified)
+            .map(|(_, path)| path)
+    }
+}
+
+impl fmt::Display for ModelIdentity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.model, self.fingerprint)
+    }
+}
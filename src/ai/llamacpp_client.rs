@@ -1,38 +1,296 @@
 use anyhow::{Context, Result};
-use log::{debug, info, warn};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, BufReader};
+use tracing::{debug, info, warn};
+
+use crate::ai::mock::{BackendMode, MockBackend};
+use crate::ai::replay::{GenerationRecord, GENERATION_EVENT, RESPONSE_EVENT};
+use crate::ai::{
+    Device, DeviceProbe, GpuBackend, MemoryGuard, ModelLock, Prompt, PromptBuilder, PromptCache,
+    ResponseParser,
+};
+use crate::cli::{Spinner, Suggestion, SuggestionSource};
+use crate::config::{GenerationMode, ModelConfig, ModelRuntimeOptions, Settings};
+use crate::context::{ContextData, LearnedPattern, ShellSubstitution};
+use crate::daemon::{DaemonFiles, DaemonState};
+use crate::server::AuthToken;
+use crate::update::{Flavor, LlamaInstaller};
+use crate::utils::{HttpClient, VersionShims};
+
+/// Longest multi-line command accepted, so a heredoc can carry a small file
+const MAX_MULTILINE_COMMAND_LEN: usize = 4000;
+
+/// Characters of a downloaded script included in the summary prompt
+const MAX_SCRIPT_EXCERPT: usize = 6000;
+
+/// Characters of a man page or `--help` included in the tool summary prompt
+///
+/// The name, synopsis, and first options are at the start.
+const MAX_DOCS_EXCERPT: usize = 6000;
+
+/// Rules kept from one summary of learned patterns
+const MAX_RULES: usize = 5;
+
+/// Longer lines are the model rambling rather than a rule
+const MAX_RULE_CHARS: usize = 200;
+
+/// Request words that call for a script, and so a larger token budget
+const SCRIPT_WORDS: &[&str] = &[
+    "script",
+    "loop",
+    "for each",
+    "for every",
+    "heredoc",
+    "multi-line",
+    "multiline",
+    "function",
+    "cron job",
+];
+
+/// Reserved words that open a compound command
+const SHELL_KEYWORDS: &[&str] = &["for", "while", "until", "if", "case"];
+
+/// Which configured model a request should run on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModelRole {
+    /// Small model that categorizes requests
+    Fast,
+    /// Main model for suggestion generation
+    Main,
+}
+
+/// How much of a prompt the model had to evaluate
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PromptEvalStats {
+    /// Tokens evaluated for this prompt
+    pub evaluated_tokens: u64,
+    /// Tokens whose state was reused from a cache instead
+    pub cached_tokens: u64,
+    /// Time spent evaluating the prompt, in milliseconds
+    pub eval_ms: f64,
+}
+
+impl PromptEvalStats {
+    /// Estimated time the reused tokens would have taken to evaluate
+    pub fn saved_ms(&self) -> f64 {
+        if self.evaluated_tokens == 0 {
+            return 0.0;
+        }
+        self.eval_ms / self.evaluated_tokens as f64 * self.cached_tokens as f64
+    }
+}
+
+/// How the latest suggestion generation went, step by step
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GenerationStats {
+    /// Time spent building the prompt
+    pub prompt_build: Duration,
+    /// Characters in the prompt
+    pub prompt_chars: usize,
+    /// Time until the backend's response was complete
+    pub backend: Duration,
+    /// Characters in the response
+    pub response_chars: usize,
+    /// Time spent parsing and checking the response
+    pub parse: Duration,
+    /// Non-empty lines in the response
+    pub lines: usize,
+    /// Commands that passed the checks
+    pub commands: usize,
+}
 
-use crate::cli::Suggestion;
-use crate::config::Settings;
-use crate::context::ContextData;
+/// What actually produces text for a generation
+enum Backend {
+    LlamaCpp(PathBuf),
+    /// A running llama-server, by base URL
+    Server(String),
+    Mock(MockBackend),
+    Record(PathBuf, MockBackend),
+}
 
 /// Client for interacting with llama.cpp binary for local inference
 pub struct LlamaCppClient {
-    binary_path: PathBuf,
+    backend: Backend,
     model_name: String,
-    max_tokens: u32,
+    fast_model_name: Option<String>,
+    main_runtime: ModelRuntimeOptions,
+    fast_runtime: ModelRuntimeOptions,
+    model_config: ModelConfig,
     temperature: f32,
+    seed: Option<u32>,
+    /// Unset lets llama.cpp pick the device
+    device: Option<Device>,
+    /// Tags this run's generations in the log for `commandy replay`
+    session_id: String,
+    generations: AtomicU64,
+    /// Unset when `[model] prompt_cache` is off
+    prompt_cache: Option<PromptCache>,
+    /// Prompt evaluation of the latest generation, when the backend reports it
+    last_prompt_eval: Mutex<Option<PromptEvalStats>>,
+    /// Timings of the latest generation, for --verbose
+    last_generation: Mutex<Option<GenerationStats>>,
+    /// Drop suggestions whose executable isn't in the local PATH; off for remote targets
+    check_path: bool,
+    /// Take the model lock before running llama.cpp; off when a server's queue
+    /// already decides how many run at once
+    model_lock: bool,
+    /// Send requests to a healthy `commandy daemon` instead of running llama.cpp
+    use_daemon: bool,
+    /// Aliases and history, so `ll` or `sudo !!` are checked as what they run
+    shell: OnceLock<ShellSubstitution>,
 }
 
+/// KV-cache types accepted by llama.cpp's --cache-type-k/v
+const VALID_CACHE_TYPES: [&str; 9] = [
+    "f32", "f16", "bf16", "q8_0", "q4_0", "q4_1", "iq4_nl", "q5_0", "q5_1",
+];
+
 impl LlamaCppClient {
     /// Creates a new LlamaCppClient instance with configuration from settings
     pub fn new(settings: &Settings) -> Result<Self> {
-        let binary_path = Self::detect_binary_path()?;
+        let backend = match BackendMode::from_env()? {
+            BackendMode::LlamaCpp => match &settings.model.server_url {
+                Some(url) => Backend::Server(url.trim_end_matches('/').to_string()),
+                None => Backend::LlamaCpp(Self::detect_binary_path(settings)?),
+            },
+            BackendMode::Mock => Backend::Mock(MockBackend::from_env()?),
+            BackendMode::Record => Backend::Record(
+                Self::detect_binary_path(settings)?,
+                MockBackend::from_env()?,
+            ),
+        };
         let model_name = settings.model.model_path.clone(); // Repurpose for model name
-        let max_tokens = settings.model.max_tokens;
+        let fast_model_name = settings.model.fast_model_path.clone();
+        let main_runtime = settings.model.runtime_for(&model_name);
+        let fast_runtime = fast_model_name
+            .as_deref()
+            .map(|m| settings.model.runtime_for(m))
+            .unwrap_or_else(|| main_runtime.clone());
         let temperature = settings.model.temperature;
+        let device =
+            settings
+                .model
+                .device
+                .as_deref()
+                .and_then(|device| match Device::parse(device) {
+                    Ok(device) => Some(device),
+                    Err(e) => {
+                        warn!("Ignoring [model] device: {e}");
+                        None
+                    }
+                });
+        let prompt_cache = match settings.model.prompt_cache {
+            true => PromptCache::from_home().ok(),
+            false => None,
+        };
 
         Ok(Self {
-            binary_path,
+            backend,
             model_name,
-            max_tokens,
+            fast_model_name,
+            main_runtime,
+            fast_runtime,
+            model_config: settings.model.clone(),
             temperature,
+            seed: settings.model.seed,
+            device,
+            session_id: uuid::Uuid::new_v4().simple().to_string()[..12].to_string(),
+            generations: AtomicU64::new(0),
+            prompt_cache,
+            last_prompt_eval: Mutex::new(None),
+            last_generation: Mutex::new(None),
+            check_path: true,
+            model_lock: true,
+            use_daemon: false,
+            shell: OnceLock::new(),
+        })
+    }
+
+    /// Id under which this run's generations are logged
+    pub fn session_id(&self) -> &str {
+        &self.session_id
+    }
+
+    /// Prompt evaluation of the latest generation, if the backend reported it
+    pub fn last_prompt_eval(&self) -> Option<PromptEvalStats> {
+        *self
+            .last_prompt_eval
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Timings and counts of the latest `generate_suggestions`
+    pub fn last_generation(&self) -> Option<GenerationStats> {
+        *self
+            .last_generation
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn shell(&self) -> &ShellSubstitution {
+        self.shell.get_or_init(|| match dirs::home_dir() {
+            Some(home) => ShellSubstitution::load(&home.join(".commandy")),
+            None => ShellSubstitution::default(),
         })
     }
 
+    /// Whether suggestions must name executables found in the local PATH
+    ///
+    /// Turned off when commands are meant for another machine.
+    pub fn set_check_path(&mut self, check_path: bool) {
+        self.check_path = check_path;
+    }
+
+    /// Whether llama.cpp waits for other commandy processes to finish with the model
+    pub fn set_model_lock(&mut self, model_lock: bool) {
+        self.model_lock = model_lock;
+    }
+
+    /// Whether generations go to the daemon's llama-server whenever it is healthy,
+    /// which keeps the model loaded between requests
+    pub fn set_use_daemon(&mut self, use_daemon: bool) {
+        self.use_daemon = use_daemon;
+    }
+
+    /// Whether generations run the llama.cpp binary, rather than a server or fixtures
+    pub fn runs_llama_cpp(&self) -> bool {
+        matches!(self.backend, Backend::LlamaCpp(_))
+    }
+
+    /// Returns true when a separate fast model is configured
+    pub fn has_fast_model(&self) -> bool {
+        self.fast_model_name.is_some()
+    }
+
+    /// Resolves the model used for a role, falling back to the main model
+    fn model_for(&self, role: ModelRole) -> &str {
+        match role {
+            ModelRole::Fast => self.fast_model_name.as_deref().unwrap_or(&self.model_name),
+            ModelRole::Main => &self.model_name,
+        }
+    }
+
     /// Detects the llama.cpp binary path in the system
-    fn detect_binary_path() -> Result<PathBuf> {
+    fn detect_binary_path(settings: &Settings) -> Result<PathBuf> {
+        // A chosen flavor is the only one used, so a missing build isn't masked by another
+        if let Some(flavor) = &settings.model.flavor {
+            let flavor = Flavor::parse(flavor)?;
+            let binary = LlamaInstaller::new()?.binary(flavor);
+            if !binary.exists() {
+                return Err(anyhow::anyhow!(
+                    "llama.cpp {} build not installed at {} (run: commandy update --binary --flavor {})",
+                    flavor.as_str(),
+                    binary.display(),
+                    flavor.as_str()
+                ));
+            }
+            return Ok(binary);
+        }
+
         // First, try the local installation path
         let home_dir = dirs::home_dir().context("Could not find home directory")?;
         let local_binary = home_dir.join(".commandy").join("bin").join("llama-cpp");
@@ -77,11 +335,26 @@ impl LlamaCppClient {
         ))
     }
 
-    /// Verifies that the llama.cpp binary is working
+    /// Verifies that the llama.cpp binary or server is working
     pub async fn verify_connection(&self) -> Result<()> {
-        debug!("Verifying llama.cpp binary at {:?}", self.binary_path);
+        let binary_path = match &self.backend {
+            Backend::LlamaCpp(path) | Backend::Record(path, _) => path,
+            Backend::Server(url) => {
+                let health = format!("{url}/health");
+                tokio::task::spawn_blocking(move || HttpClient::fetch_text(&health))
+                    .await?
+                    .context("llama-server is not reachable")?;
+                info!("llama-server reachable at {url}");
+                return Ok(());
+            }
+            Backend::Mock(mock) => {
+                info!("Using mock backend with fixtures in {:?}", mock.dir());
+                return Ok(());
+            }
+        };
+        debug!("Verifying llama.cpp binary at {binary_path:?}");
 
-        let output = Command::new(&self.binary_path)
+        let output = Command::new(binary_path)
             .arg("--version")
             .output()
             .context("Failed to execute llama.cpp binary")?;
@@ -99,6 +372,19 @@ impl LlamaCppClient {
         Ok(())
     }
 
+    /// The GPU support of the llama.cpp binary; `None` for a server or the mock backend
+    pub fn probe_devices(&self) -> Option<Result<DeviceProbe>> {
+        match &self.backend {
+            Backend::LlamaCpp(path) | Backend::Record(path, _) => Some(DeviceProbe::run(path)),
+            Backend::Server(_) | Backend::Mock(_) => None,
+        }
+    }
+
+    /// Whether generations go to a running llama-server
+    pub fn uses_server(&self) -> bool {
+        matches!(self.backend, Backend::Server(_))
+    }
+
     /// Generates command suggestions based on user prompt and context
     pub async fn generate_suggestions(
         &self,
@@ -107,140 +393,984 @@ impl LlamaCppClient {
         max_suggestions: usize,
     ) -> Result<Vec<Suggestion>> {
         debug!("Generating suggestions for prompt: {prompt}");
-
-        let enhanced_prompt = self.build_enhanced_prompt(prompt, context);
-        let response = self.generate_text(&enhanced_prompt).await?;
+        *self
+            .last_generation
+            .lock()
+            .unwrap_or_else(|e| e.into_inner()) = None;
+
+        let started = Instant::now();
+        let enhanced_prompt = PromptBuilder::new().suggestions(prompt, context);
+        let prompt_build = started.elapsed();
+        let mode = match Self::is_script_request(prompt) {
+            true => GenerationMode::Script,
+            false => GenerationMode::Suggest,
+        };
+        let started = Instant::now();
+        let response = self
+            .generate_text(
+                &enhanced_prompt,
+                ModelRole::Main,
+                self.model_config.token_budget(mode),
+                max_suggestions,
+            )
+            .await?;
+        let backend = started.elapsed();
+        let started = Instant::now();
         let suggestions = self.parse_response(&response, max_suggestions);
 
+        *self
+            .last_generation
+            .lock()
+            .unwrap_or_else(|e| e.into_inner()) = Some(GenerationStats {
+            prompt_build,
+            prompt_chars: enhanced_prompt.system.len() + enhanced_prompt.user.len(),
+            backend,
+            response_chars: response.len(),
+            parse: started.elapsed(),
+            lines: response.lines().filter(|l| !l.trim().is_empty()).count(),
+            commands: suggestions.len(),
+        });
+
         info!("Generated {} suggestions", suggestions.len());
         Ok(suggestions)
     }
 
+    /// Generates corrected versions of a command that failed or was mistyped
+    pub async fn generate_fixes(
+        &self,
+        failed_command: &str,
+        error_output: Option<&str>,
+        context: &ContextData,
+        max_suggestions: usize,
+    ) -> Result<Vec<Suggestion>> {
+        debug!("Generating fixes for: {failed_command}");
+
+        let fix_prompt = Prompt::user_only(
+            "fix",
+            self.build_fix_prompt(failed_command, error_output, context),
+        );
+        let response = self
+            .generate_text(
+                &fix_prompt,
+                ModelRole::Main,
+                self.model_config.token_budget(GenerationMode::Fix),
+                max_suggestions,
+            )
+            .await?;
+
+        Ok(self.parse_response(&response, max_suggestions))
+    }
+
+    /// Commands likely to follow one that just ran, from how it exited and what it printed
+    pub async fn generate_next_steps(
+        &self,
+        request: &str,
+        command: &str,
+        exit_code: Option<i32>,
+        output: &str,
+        context: &ContextData,
+        max_suggestions: usize,
+    ) -> Result<Vec<Suggestion>> {
+        debug!("Generating next steps after: {command}");
+
+        let next_prompt = Prompt::user_only(
+            "next",
+            self.build_next_steps_prompt(request, command, exit_code, output, context),
+        );
+        let response = self
+            .generate_text(
+                &next_prompt,
+                ModelRole::Main,
+                self.model_config.token_budget(GenerationMode::Suggest),
+                max_suggestions,
+            )
+            .await?;
+
+        Ok(self.parse_response(&response, max_suggestions))
+    }
+
+    /// Explains what a shell command does, one line per notable part, in
+    /// `language` if given and otherwise English
+    pub async fn explain_command(&self, command: &str, language: Option<&str>) -> Result<String> {
+        let language = language
+            .map(|language| format!("\nWrite the explanation in {language}."))
+            .unwrap_or_default();
+        let explain_prompt = Prompt::user_only(
+            "explain",
+            format!(
+                r#"Explain what this shell command does in plain language.
+Describe each flag and pipeline stage briefly, one per line, and mention any risk.{language}
+
+Command: {command}
+
+Explanation:"#
+            ),
+        );
+
+        let response = self
+            .generate_text(
+                &explain_prompt,
+                ModelRole::Main,
+                self.model_config.token_budget(GenerationMode::Explain),
+                0,
+            )
+            .await?;
+        Ok(response.trim().to_string())
+    }
+
+    /// Summarizes a downloaded script for someone deciding whether to run it
+    pub async fn summarize_script(&self, script: &str) -> Result<String> {
+        // The start of an installer says what it does; the rest is mostly helpers
+        let excerpt: String = script.chars().take(MAX_SCRIPT_EXCERPT).collect();
+        let summarize_prompt = Prompt::user_only(
+            "summarize",
+            format!(
+                r#"Summarize what this script does for someone deciding whether to run it.
+List what it downloads, installs, and changes, and anything that needs root, one short line each.
+
+Script:
+{excerpt}
+
+Summary:"#
+            ),
+        );
+
+        let response = self
+            .generate_text(
+                &summarize_prompt,
+                ModelRole::Main,
+                self.model_config.token_budget(GenerationMode::Explain),
+                0,
+            )
+            .await?;
+        Ok(response.trim().to_string())
+    }
+
+    /// Sums up what a command printed in a line or two, such as
+    /// "3 containers restarted, 1 failed: web-2"
+    pub async fn summarize_output(
+        &self,
+        command: &str,
+        exit_code: Option<i32>,
+        output: &str,
+    ) -> Result<String> {
+        let status = match exit_code {
+            Some(0) => "succeeded".to_string(),
+            Some(code) => format!("exited with code {code}"),
+            None => "was killed by a signal".to_string(),
+        };
+        let digest_prompt = Prompt::user_only(
+            "digest",
+            format!(
+                r#"Summarize the output of this shell command in one or two short lines.
+Give counts and name what failed or needs attention, like "3 containers restarted, 1 failed: web-2". Don't repeat the output.
+
+Command: {command}
+The command {status}.
+Output:
+{output}
+Summary:"#
+            ),
+        );
+
+        let response = self
+            .generate_text(
+                &digest_prompt,
+                ModelRole::Main,
+                self.model_config.token_budget(GenerationMode::Explain),
+                0,
+            )
+            .await?;
+        Ok(response.trim().to_string())
+    }
+
+    /// A tldr-style summary of a tool from its man page or `--help`: what it is
+    /// for, then a few common uses
+    pub async fn summarize_tool(&self, tool: &str, docs: &str) -> Result<String> {
+        let excerpt: String = docs.chars().take(MAX_DOCS_EXCERPT).collect();
+        let tldr_prompt = Prompt::user_only(
+            "tldr",
+            format!(
+                r#"Summarize this tool for someone checking a command that uses it, like a tldr page.
+First say what it does in one sentence. Then give up to 5 common uses, one per line as "- `command`: what it does".
+
+Tool: {tool}
+Documentation:
+{excerpt}
+
+Summary:"#
+            ),
+        );
+
+        let response = self
+            .generate_text(
+                &tldr_prompt,
+                ModelRole::Main,
+                self.model_config.token_budget(GenerationMode::Explain),
+                0,
+            )
+            .await?;
+        Ok(response.trim().to_string())
+    }
+
+    /// Condenses requests and the commands run for them into a few habits worth remembering
+    pub async fn summarize_patterns(
+        &self,
+        category: &str,
+        patterns: &[LearnedPattern],
+    ) -> Result<Vec<String>> {
+        let examples: Vec<String> = patterns
+            .iter()
+            .map(|p| format!("\"{}\" -> {}", p.prompt, p.command))
+            .collect();
+        let rules_prompt = Prompt::user_only(
+            "rules",
+            format!(
+                r#"These are {category} requests a user made and the commands they ran.
+Write at most {MAX_RULES} short rules about their habits that will help suggest commands for future requests:
+tools and flags they prefer, paths, hosts, and naming. One rule per line, starting with "- ".
+
+Requests:
+{}
+
+Rules:
+"#,
+                examples.join("\n")
+            ),
+        );
+
+        let response = self
+            .generate_text(
+                &rules_prompt,
+                ModelRole::Main,
+                self.model_config.token_budget(GenerationMode::Explain),
+                0,
+            )
+            .await?;
+        Ok(response
+            .lines()
+            .filter_map(|line| line.trim().strip_prefix(['-', '*']))
+            .map(|rule| rule.trim().to_string())
+            .filter(|rule| !rule.is_empty() && rule.len() <= MAX_RULE_CHARS)
+            .take(MAX_RULES)
+            .collect())
+    }
+
+    /// Rewrites a command written for the `from` shell in the syntax of `to`
+    pub async fn translate_command(&self, command: &str, from: &str, to: &str) -> Result<String> {
+        let translate_prompt = Prompt::user_only(
+            "translate",
+            format!(
+                r#"Translate this {from} command into {to} syntax.
+Keep what it does the same, use {to} built-ins and idioms, and output only the {to} command.
+
+Command: {command}
+
+{to}:"#
+            ),
+        );
+
+        let response = self
+            .generate_text(
+                &translate_prompt,
+                ModelRole::Main,
+                self.model_config.token_budget(GenerationMode::Translate),
+                0,
+            )
+            .await?;
+        // Small models like to wrap the answer in a code fence
+        let lines: Vec<&str> = response
+            .trim()
+            .lines()
+            .filter(|line| !line.trim_start().starts_with("```"))
+            .collect();
+        Ok(lines.join("\n").trim().to_string())
+    }
+
+    /// Puts a request written in `language` into English, which small models
+    /// turn into better commands
+    pub async fn translate_request(&self, request: &str, language: &str) -> Result<String> {
+        let english_prompt = Prompt::user_only(
+            "english",
+            format!(
+                r#"Translate this {language} request for a shell command into English.
+Keep file names, paths, commands, flags, and quoted text exactly as they are. Output only the English request.
+
+{language}: {request}
+English:"#
+            ),
+        );
+
+        let response = self
+            .generate_text(
+                &english_prompt,
+                ModelRole::Main,
+                self.model_config.token_budget(GenerationMode::Suggest),
+                0,
+            )
+            .await?;
+        Ok(response.trim().trim_matches('"').trim().to_string())
+    }
+
+    /// Classifies a prompt into one of the given categories using the fast model
+    pub async fn classify_prompt(
+        &self,
+        prompt: &str,
+        categories: &[&str],
+    ) -> Result<Option<String>> {
+        let classification_prompt = Prompt::user_only(
+            "classify",
+            format!(
+                "Classify this shell task into exactly one category.\n\
+            Categories: {}\n\
+            Task: {prompt}\n\
+            Category:",
+                categories.join(", ")
+            ),
+        );
+
+        let response = self
+            .generate_text(&classification_prompt, ModelRole::Fast, 8, 0)
+            .await?;
+        let response_lower = response.to_lowercase();
+
+        Ok(categories
+            .iter()
+            .find(|c| response_lower.contains(&c.to_lowercase()))
+            .map(|c| c.to_string()))
+    }
+
     /// Executes llama.cpp binary with the given prompt and returns the response
-    async fn generate_text(&self, prompt: &str) -> Result<String> {
-        debug!("Executing llama.cpp with prompt length: {}", prompt.len());
+    ///
+    /// The prompt's system part is shared with other runs; with the prompt
+    /// cache on, its evaluated state is saved and reused. A non-zero
+    /// `stop_after` ends generation once that many whole commands are out.
+    async fn generate_text(
+        &self,
+        prompt: &Prompt,
+        role: ModelRole,
+        max_tokens: u32,
+        stop_after: usize,
+    ) -> Result<String> {
+        let runtime = match role {
+            ModelRole::Fast => &self.fast_runtime,
+            ModelRole::Main => &self.main_runtime,
+        };
+        let cache_type = |cache_type: &Option<String>, flag: &str| match cache_type {
+            Some(t) if VALID_CACHE_TYPES.contains(&t.as_str()) => t.clone(),
+            Some(t) => {
+                warn!("Ignoring unsupported {flag} value: {t}");
+                String::new()
+            }
+            None => String::new(),
+        };
+
+        let mut record = GenerationRecord {
+            session: self.session_id.clone(),
+            sequence: self.generations.fetch_add(1, Ordering::Relaxed),
+            role: format!("{role:?}").to_lowercase(),
+            model: self.model_for(role).to_string(),
+            // llama.cpp would pick one itself, but then the run can't be replayed
+            seed: self
+                .seed
+                .unwrap_or_else(|| uuid::Uuid::new_v4().as_u128() as u32 & 0x7fff_ffff),
+            max_tokens,
+            stop_after,
+            stop: PromptBuilder::stop_sequences(prompt.template, &self.model_config.stop_sequences),
+            temperature: self.temperature,
+            context_size: runtime.context_size.unwrap_or(0),
+            cache_type_k: cache_type(&runtime.cache_type_k, "--cache-type-k"),
+            cache_type_v: cache_type(&runtime.cache_type_v, "--cache-type-v"),
+            device: self.device.map(|d| d.to_string()).unwrap_or_default(),
+            prompt_cache: String::new(),
+            system_len: prompt.system.len(),
+            prompt: prompt.text(),
+        };
+        let server = matches!(self.backend, Backend::Server(_));
+        if let (Some(cache), false, false) = (&self.prompt_cache, prompt.system.is_empty(), server)
+        {
+            match cache.file_for(&record, &prompt.system) {
+                Ok(path) => record.prompt_cache = path.display().to_string(),
+                Err(e) => warn!("Prompt cache unavailable: {e}"),
+            }
+        }
+
+        // The prompt holds history, the working directory, and attached files,
+        // so it is only written to disk when debug logging is asked for
+        info!(
+            session = %record.session,
+            sequence = record.sequence,
+            role = %record.role,
+            model = %record.model,
+            seed = record.seed,
+            prompt_len = record.prompt.len(),
+            "Starting generation"
+        );
+        debug!(
+            session = %record.session,
+            sequence = record.sequence,
+            role = %record.role,
+            model = %record.model,
+            seed = record.seed,
+            max_tokens = record.max_tokens,
+            stop_after = record.stop_after,
+            stop = %serde_json::to_string(&record.stop).unwrap_or_default(),
+            temperature = record.temperature,
+            context_size = record.context_size,
+            cache_type_k = %record.cache_type_k,
+            cache_type_v = %record.cache_type_v,
+            device = %record.device,
+            prompt_cache = %record.prompt_cache,
+            system_len = record.system_len,
+            prompt = %record.prompt,
+            "{}",
+            GENERATION_EVENT
+        );
+
+        let response = self.run_generation(&record).await?;
+
+        debug!(
+            session = %record.session,
+            sequence = record.sequence,
+            response = %response,
+            "{}",
+            RESPONSE_EVENT
+        );
+        Ok(response)
+    }
+
+    /// Re-runs a logged generation with the same model, seed, and prompt
+    pub async fn replay(
+        &self,
+        record: &GenerationRecord,
+        max_suggestions: usize,
+    ) -> Result<(String, Vec<Suggestion>)> {
+        let response = self.run_generation(record).await?;
+        let suggestions = self.parse_response(&response, max_suggestions);
+        Ok((response, suggestions))
+    }
+
+    /// Commands the parser finds in a recorded response
+    pub fn parse_recorded(&self, response: &str, max_suggestions: usize) -> Vec<Suggestion> {
+        self.parse_response(response, max_suggestions)
+    }
+
+    async fn run_generation(&self, record: &GenerationRecord) -> Result<String> {
+        *self
+            .last_prompt_eval
+            .lock()
+            .unwrap_or_else(|e| e.into_inner()) = None;
+        match &self.backend {
+            Backend::LlamaCpp(binary_path) => self.run_local(binary_path, record).await,
+            Backend::Server(url) => self.run_server(url, record).await,
+            Backend::Mock(mock) => mock.respond(record),
+            Backend::Record(binary_path, fixtures) => {
+                let response = self.run_local(binary_path, record).await?;
+                if let Err(e) = fixtures.record(record, &response) {
+                    warn!(error = %e, "Failed to record fixture");
+                }
+                Ok(response)
+            }
+        }
+    }
+
+    /// Runs llama.cpp once no other commandy is; while one is, a healthy
+    /// `commandy daemon` answers instead, or the request waits its turn
+    async fn run_local(&self, binary_path: &Path, record: &GenerationRecord) -> Result<String> {
+        if self.use_daemon {
+            if let Some(daemon) = Self::healthy_daemon() {
+                return self.run_server(&daemon.url, record).await;
+            }
+        }
+        if !self.model_lock {
+            self.check_memory(record)?;
+            return self.run_llama(binary_path, record).await;
+        }
+        let lock = match ModelLock::try_acquire()? {
+            Some(lock) => lock,
+            None => {
+                if let Some(daemon) = Self::healthy_daemon() {
+                    info!(url = %daemon.url, "Model busy; sending the request to the daemon");
+                    return self.run_server(&daemon.url, record).await;
+                }
+                let message = match ModelLock::holder() {
+                    Some(pid) => {
+                        format!("Waiting for commandy (pid {pid}) to finish with the model...")
+                    }
+                    None => "Waiting for another commandy to finish with the model...".to_string(),
+                };
+                info!("{message}");
+                let shown = Spinner::retitle(&message);
+                let lock = ModelLock::acquire().await;
+                if let Some(shown) = shown {
+                    Spinner::retitle(&shown);
+                }
+                lock?
+            }
+        };
+        // Checked once the lock is held, so the memory a process just freed counts
+        self.check_memory(record)?;
+        let response = self.run_llama(binary_path, record).await;
+        drop(lock);
+        response
+    }
+
+    fn healthy_daemon() -> Option<DaemonState> {
+        DaemonFiles::new()
+            .ok()
+            .and_then(|files| files.running())
+            .filter(|state| state.healthy)
+    }
+
+    /// Fails fast when the model won't fit in free memory, unless it runs on a
+    /// GPU with memory of its own
+    fn check_memory(&self, record: &GenerationRecord) -> Result<()> {
+        if !self.model_config.memory_guard {
+            return Ok(());
+        }
+        if let Some(Device::Gpu { backend, .. }) = self.device {
+            if backend != GpuBackend::Metal {
+                return Ok(());
+            }
+        }
+        MemoryGuard::check(&self.model_config, &record.model)
+    }
 
-        let mut command = Command::new(&self.binary_path);
+    async fn run_llama(&self, binary_path: &Path, record: &GenerationRecord) -> Result<String> {
+        let model = record.model.as_str();
+        debug!(
+            "Executing llama.cpp ({model}) with prompt length: {}",
+            record.prompt.len()
+        );
+
+        // Async so a server can keep accepting requests while llama.cpp runs
+        let mut command = tokio::process::Command::new(binary_path);
         command
             .arg("-hf")
-            .arg(&self.model_name)
+            .arg(model)
             .arg("-c")
-            .arg("0") // Use full context
-            .arg("-fa") // Flash attention
+            .arg(record.context_size.to_string()) // 0 uses full context
+            .arg("-fa") // Flash attention, also required for a quantized V cache
             .arg("-p")
-            .arg(prompt)
+            .arg(&record.prompt)
             .arg("-n")
-            .arg(self.max_tokens.to_string())
+            .arg(record.max_tokens.to_string())
             .arg("--temp")
-            .arg(self.temperature.to_string())
+            .arg(record.temperature.to_string())
+            .arg("--seed")
+            .arg(record.seed.to_string())
             .arg("--no-display-prompt") // Don't echo the prompt
             .stdin(Stdio::null())
             .stdout(Stdio::piped())
-            .stderr(Stdio::piped());
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+
+        for (flag, cache_type) in [
+            ("--cache-type-k", &record.cache_type_k),
+            ("--cache-type-v", &record.cache_type_v),
+        ] {
+            if !cache_type.is_empty() {
+                command.arg(flag).arg(cache_type);
+            }
+        }
+        if !record.device.is_empty() {
+            command.args(Device::parse(&record.device)?.llama_args());
+        }
 
-        debug!("Executing command: {:?}", command);
+        // The first run saves the prompt's state; later ones only read it, since
+        // every prompt with the same stable start gets the same file
+        let prompt_cache = (self.prompt_cache.is_some() && !record.prompt_cache.is_empty())
+            .then(|| PathBuf::from(&record.prompt_cache));
+        let cache_existed = prompt_cache.as_ref().is_some_and(|path| path.exists());
+        if let Some(path) = &prompt_cache {
+            command.arg("--prompt-cache").arg(path);
+            if cache_existed {
+                command.arg("--prompt-cache-ro");
+            }
+        }
 
-        let output = command.output().context("Failed to execute llama.cpp")?;
+        debug!("Executing command: {:?}", command);
 
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
+        let mut child = command.spawn().context("Failed to execute llama.cpp")?;
+        let mut stderr_pipe = child
+            .stderr
+            .take()
+            .context("llama.cpp stderr not captured")?;
+        let stderr_task = tokio::spawn(async move {
+            let mut stderr = Vec::new();
+            let _ = stderr_pipe.read_to_end(&mut stderr).await;
+            stderr
+        });
+
+        // Read as it's generated, so the run can end once enough commands are out
+        let mut stdout = BufReader::new(
+            child
+                .stdout
+                .take()
+                .context("llama.cpp stdout not captured")?,
+        );
+        let mut output = Vec::new();
+        let mut stopped = false;
+        while stdout.read_until(b'\n', &mut output).await? > 0 {
+            if let Some(end) = record.stop_position(&String::from_utf8_lossy(&output)) {
+                debug!("Stopping llama.cpp at a stop sequence");
+                output.truncate(end);
+                Self::interrupt(&mut child);
+                stopped = true;
+                break;
+            }
+            if record.stop_after > 0
+                && output.ends_with(b"\n")
+                && self.has_commands(&String::from_utf8_lossy(&output), record.stop_after)
+            {
+                debug!("Stopping llama.cpp after {} commands", record.stop_after);
+                Self::interrupt(&mut child);
+                stopped = true;
+                break;
+            }
+        }
+        let status = child.wait().await.context("Failed to wait for llama.cpp")?;
+        let stderr = stderr_task.await.unwrap_or_default();
+
+        if !status.success() && !stopped {
+            let stderr = String::from_utf8_lossy(&stderr);
+            warn!(
+                session = %record.session,
+                model,
+                status = %status,
+                stderr = %stderr.trim(),
+                "llama.cpp execution failed"
+            );
             return Err(anyhow::anyhow!("llama.cpp execution failed: {}", stderr));
         }
 
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        let response = stdout.trim().to_string();
+        if let (Some(cache), Some(path)) = (&self.prompt_cache, &prompt_cache) {
+            if cache_existed {
+                cache.touch(path);
+            } else {
+                cache.prune();
+            }
+        }
+
+        self.record_prompt_eval(Self::parse_prompt_eval(&String::from_utf8_lossy(&stderr)));
+
+        let response = String::from_utf8_lossy(&output).trim().to_string();
 
-        debug!("Generated response length: {}", response.len());
+        debug!(model, response_len = response.len(), "Generated response");
         Ok(response)
     }
 
-    /// Builds an enhanced prompt with context information for better command generation
-    fn build_enhanced_prompt(&self, user_prompt: &str, context: &ContextData) -> String {
-        let environment = &context.environment;
-        let recent_commands = &context.recent_commands;
-        let context_content = &context.content;
+    /// Ends a llama.cpp run early
+    ///
+    /// SIGINT makes llama.cpp print its timings before exiting, which a kill
+    /// would lose; the kill is only the fallback.
+    fn interrupt(child: &mut tokio::process::Child) {
+        let interrupted = child.id().is_some_and(|pid| {
+            Command::new("kill")
+                .args(["-INT", &pid.to_string()])
+                .stderr(Stdio::null())
+                .status()
+                .is_ok_and(|status| status.success())
+        });
+        if !interrupted {
+            let _ = child.start_kill();
+        }
+    }
 
-        let available_tools = environment
-            .get("available_tools")
-            .map_or("basic".to_string(), |v| {
-                v.split(',').take(20).collect::<Vec<_>>().join(", ")
-            });
+    /// The local auth token, for a llama-server on this machine such as the daemon's
+    ///
+    /// It isn't sent anywhere else, since it would let that host drive ours.
+    fn server_token(url: &str) -> Option<String> {
+        let authority = url.split("://").nth(1).unwrap_or(url);
+        let authority = authority.split('/').next().unwrap_or_default();
+        let host = match authority.strip_prefix('[') {
+            Some(rest) => rest.split(']').next().unwrap_or_default(),
+            None => authority.split(':').next().unwrap_or_default(),
+        };
+        let local = host == "localhost"
+            || host
+                .parse::<std::net::IpAddr>()
+                .is_ok_and(|ip| ip.is_loopback());
+        local.then(AuthToken::load).flatten()
+    }
+
+    /// Runs a generation on llama-server's chat endpoint
+    ///
+    /// The system part goes in its own message ahead of the request, so the
+    /// server's prefix cache (`cache_prompt`) reuses its KV state between
+    /// requests and only evaluates the short user message.
+    async fn run_server(&self, url: &str, record: &GenerationRecord) -> Result<String> {
+        let (system, user) = record
+            .prompt
+            .split_at(record.system_len.min(record.prompt.len()));
+        let mut messages = Vec::new();
+        if !system.is_empty() {
+            messages.push(serde_json::json!({"role": "system", "content": system.trim_end()}));
+        }
+        messages.push(serde_json::json!({"role": "user", "content": user.trim_start()}));
+
+        let body = serde_json::json!({
+            "model": record.model,
+            "messages": messages,
+            "max_tokens": record.max_tokens,
+            "temperature": record.temperature,
+            "seed": record.seed,
+            "cache_prompt": true,
+            "stop": record.stop,
+        })
+        .to_string();
+        debug!(
+            "Sending prompt to llama-server at {url} (system {} bytes, user {} bytes)",
+            system.len(),
+            user.len()
+        );
+
+        let endpoint = format!("{url}/v1/chat/completions");
+        let headers: Vec<String> = Self::server_token(url)
+            .map(|token| format!("Authorization: Bearer {token}"))
+            .into_iter()
+            .collect();
+        let reply = tokio::task::spawn_blocking(move || {
+            HttpClient::post_json_with_headers(&endpoint, &body, &headers)
+        })
+        .await?
+        .inspect_err(
+            |e| warn!(session = %record.session, error = %e, "llama-server request failed"),
+        )?;
+        let reply: serde_json::Value =
+            serde_json::from_str(&reply).context("llama-server returned invalid JSON")?;
+
+        let response = reply["choices"][0]["message"]["content"]
+            .as_str()
+            .context("llama-server reply has no message content")?
+            .trim()
+            .to_string();
+
+        let timings = &reply["timings"];
+        if let Some(evaluated_tokens) = timings["prompt_n"].as_u64() {
+            self.record_prompt_eval(Some(PromptEvalStats {
+                evaluated_tokens,
+                cached_tokens: timings["cache_n"].as_u64().unwrap_or(0),
+                eval_ms: timings["prompt_ms"].as_f64().unwrap_or(0.0),
+            }));
+        }
+
+        debug!(response_len = response.len(), "Generated response");
+        Ok(response)
+    }
+
+    fn record_prompt_eval(&self, stats: Option<PromptEvalStats>) {
+        if let Some(stats) = stats {
+            info!(
+                evaluated_tokens = stats.evaluated_tokens,
+                cached_tokens = stats.cached_tokens,
+                eval_ms = stats.eval_ms,
+                "Prompt evaluated"
+            );
+        }
+        *self
+            .last_prompt_eval
+            .lock()
+            .unwrap_or_else(|e| e.into_inner()) = stats;
+    }
+
+    /// Reads prompt evaluation timings from llama.cpp's stderr
+    ///
+    /// llama.cpp reports `prompt eval time = X ms / N tokens` for the tokens it
+    /// evaluated, and how much of a loaded `--prompt-cache` session matched.
+    fn parse_prompt_eval(stderr: &str) -> Option<PromptEvalStats> {
+        let number_before = |line: &str, unit: &str| -> Option<f64> {
+            let before = &line[..line.find(unit)?];
+            before.split_whitespace().last()?.parse().ok()
+        };
+
+        let mut stats = PromptEvalStats::default();
+        let mut timed = false;
+        let mut session_size = 0;
+        for line in stderr.lines() {
+            if line.contains("prompt eval time") {
+                let (time, tokens) = line.split_once('/')?;
+                stats.eval_ms = number_before(time, "ms")?;
+                stats.evaluated_tokens = number_before(tokens, "tokens")? as u64;
+                timed = true;
+            } else if line.contains("loaded a session with prompt size of") {
+                session_size = number_before(line, "tokens").unwrap_or(0.0) as u64;
+            } else if line.contains("session file matches") {
+                // "session file matches A / B tokens of prompt"
+                let matched = line.split("matches").nth(1)?.split('/').next()?;
+                stats.cached_tokens = matched.trim().parse().ok()?;
+            } else if line.contains("session file has exact match") {
+                // The last token is evaluated again to get fresh logits
+                stats.cached_tokens = session_size.saturating_sub(1);
+            }
+        }
+        timed.then_some(stats)
+    }
+
+    /// Builds a prompt asking the model for follow-up commands after one has run
+    fn build_next_steps_prompt(
+        &self,
+        request: &str,
+        command: &str,
+        exit_code: Option<i32>,
+        output: &str,
+        context: &ContextData,
+    ) -> String {
+        let environment = &context.environment;
+        let status = match exit_code {
+            Some(code) => format!("exited with code {code}"),
+            None => "was killed by a signal".to_string(),
+        };
 
         let mut prompt = format!(
-            r#"Generate ONLY valid shell commands for: {}
+            r#"The user asked for a shell command and ran it. Suggest the commands they are most likely to run next.
+
+Request: {request}
+Command: {command}
+The command {status}.
 
 System Information:
 - OS: {}
 - Shell: {}
-- Available executables: {}
-- Recent commands: {}
+"#,
+            environment.get("os").map_or("unknown", |v| v.as_str()),
+            environment.get("shell").map_or("unknown", |v| v.as_str()),
+        );
+
+        if !output.trim().is_empty() {
+            // The end of the output says how it went
+            let tail: Vec<&str> = output.lines().rev().take(30).collect();
+            let tail: Vec<&str> = tail.into_iter().rev().collect();
+            prompt.push_str(&format!("\nOutput:\n{}\n", tail.join("\n")));
+        }
 
-CRITICAL REQUIREMENTS:
-1. Commands MUST use only executables that exist in PATH
-2. Start with real command names, not pseudo-commands
-3. Use proper shell syntax
-4. Be directly executable
-5. Provide safe, practical solutions
+        prompt.push_str(
+            r#"
+REQUIREMENTS:
+1. Each command carries on from this one, like `git rebase origin/main` after `git fetch`, or fixes what went wrong
+2. Don't repeat the command that just ran
+3. Use names from the output instead of placeholders
+4. Use only executables that exist in PATH
 
 Output format: Return 1-3 shell commands, each on a new line.
-Example format:
-docker ps -a
-ls -la /var/log
-grep -r "error" /var/log/
 
-Commands for: {}"#,
-            user_prompt,
+Commands:"#,
+        );
+        prompt
+    }
+
+    /// Builds a prompt asking the model to correct a broken command
+    fn build_fix_prompt(
+        &self,
+        failed_command: &str,
+        error_output: Option<&str>,
+        context: &ContextData,
+    ) -> String {
+        let environment = &context.environment;
+
+        let mut prompt = format!(
+            r#"The following shell command is wrong or failed. Fix it.
+
+Command: {}
+
+System Information:
+- OS: {}
+- Shell: {}
+"#,
+            failed_command,
             environment.get("os").map_or("unknown", |v| v.as_str()),
             environment.get("shell").map_or("unknown", |v| v.as_str()),
-            available_tools,
-            recent_commands
-                .iter()
-                .take(3)
-                .map(|cmd| cmd.split_whitespace().next().unwrap_or(""))
-                .collect::<Vec<_>>()
-                .join(", "),
-            user_prompt
         );
 
-        // Add learned context if available
-        if !context_content.is_empty() {
-            let relevant_patterns: Vec<&str> = context_content
-                .lines()
-                .filter(|line| line.contains("→") || line.contains("✓"))
-                .take(5)
-                .collect();
-
-            if !relevant_patterns.is_empty() {
-                prompt.push_str("\n\nLearned patterns:\n");
-                prompt.push_str(&relevant_patterns.join("\n"));
-            }
+        if let Some(error) = error_output {
+            // Errors can be long; the tail usually has the cause
+            let tail: Vec<&str> = error.lines().rev().take(20).collect();
+            let tail: Vec<&str> = tail.into_iter().rev().collect();
+            prompt.push_str(&format!("\nError output:\n{}\n", tail.join("\n")));
         }
 
-        prompt.push_str("\n\nCommands:");
+        prompt.push_str(
+            r#"
+REQUIREMENTS:
+1. Keep the original intent of the command
+2. Correct typos, wrong flags, missing arguments, or missing privileges
+3. Use only executables that exist in PATH
+
+Output format: Return 1-3 corrected shell commands, each on a new line.
+
+Commands:"#,
+        );
+
         prompt
     }
 
     /// Parses the response from llama.cpp and extracts valid command suggestions
     fn parse_response(&self, response: &str, max_suggestions: usize) -> Vec<Suggestion> {
-        debug!("Parsing response: {}", response);
+        debug!(response, "Parsing model response");
 
+        let mut suggestions = self.model_commands(response, max_suggestions);
+
+        // If no commands found, try to extract from longer text
+        if suggestions.is_empty() {
+            suggestions = self.extract_commands_fallback(response, max_suggestions);
+        }
+
+        if suggestions.is_empty() && !response.trim().is_empty() {
+            warn!(
+                response_len = response.len(),
+                response = %response.chars().take(500).collect::<String>(),
+                "Model response contained no usable commands"
+            );
+        }
+
+        suggestions
+    }
+
+    /// Whether a partial response already holds `count` whole commands
+    fn has_commands(&self, response: &str, count: usize) -> bool {
+        let starts_command = |line: &str| !line.starts_with('#') && self.looks_like_command(line);
+        !ResponseParser::new().has_open_command(response, starts_command)
+            && self.model_commands(response, count).len() >= count
+    }
+
+    /// Request words that call for a loop or a small script rather than a one-liner
+    fn is_script_request(prompt: &str) -> bool {
+        let words: Vec<String> = prompt
+            .to_lowercase()
+            .split(|c: char| !c.is_alphanumeric() && c != '-')
+            .filter(|word| !word.is_empty())
+            .map(str::to_string)
+            .collect();
+        SCRIPT_WORDS.iter().any(|phrase| {
+            let len = phrase.split(' ').count();
+            words.windows(len).any(|window| window.join(" ") == *phrase)
+        })
+    }
+
+    /// Commands in the response, in order, up to `max_suggestions`
+    fn model_commands(&self, response: &str, max_suggestions: usize) -> Vec<Suggestion> {
         let mut suggestions = Vec::new();
 
-        // Split response into lines and extract potential commands
-        for line in response.lines() {
-            let line = line.trim();
+        // Split response into commands, keeping heredocs, continuations, and loops whole
+        let commands = ResponseParser::new().group_commands(response, |line| {
+            !line.starts_with('#') && self.looks_like_command(line)
+        });
+        for command in commands {
+            let first_line = command.lines().next().unwrap_or("");
 
-            // Skip empty lines, comments, or lines that are too long
-            if line.is_empty() || line.starts_with('#') || line.len() > 300 {
+            // Skip comments, or lines that are too long
+            if first_line.starts_with('#') || first_line.len() > 300 {
                 continue;
             }
 
             // Skip explanatory text (look for lines that start with command words)
-            if self.looks_like_command(line) && self.is_valid_command(line) {
+            if self.looks_like_command(first_line) && self.is_valid_command(&command) {
                 suggestions.push(Suggestion {
-                    command: line.to_string(),
+                    command,
                     explanation: None, // Could be enhanced to extract explanations
                     confidence: 0.8,
+                    source: SuggestionSource::Model,
+                    ranking: None,
                 });
 
                 if suggestions.len() >= max_suggestions {
@@ -248,12 +1378,6 @@ Commands for: {}"#,
                 }
             }
         }
-
-        // If no commands found, try to extract from longer text
-        if suggestions.is_empty() {
-            suggestions = self.extract_commands_fallback(response, max_suggestions);
-        }
-
         suggestions
     }
 
@@ -277,6 +1401,8 @@ Commands for: {}"#,
                         command: current_command.trim().to_string(),
                         explanation: None,
                         confidence: 0.6,
+                        source: SuggestionSource::Model,
+                        ranking: None,
                     });
 
                     if suggestions.len() >= max_suggestions {
@@ -293,10 +1419,12 @@ Commands for: {}"#,
                     if self.is_valid_command(&current_command) {
                         suggestions.push(Suggestion {
                             command: current_command
-                                .trim_end_matches(&['.', '!', '?'])
+                                .trim_end_matches(['.', '!', '?'])
                                 .to_string(),
                             explanation: None,
                             confidence: 0.6,
+                            source: SuggestionSource::Model,
+                            ranking: None,
                         });
 
                         if suggestions.len() >= max_suggestions {
@@ -314,6 +1442,8 @@ Commands for: {}"#,
                 command: current_command.trim().to_string(),
                 explanation: None,
                 confidence: 0.6,
+                source: SuggestionSource::Model,
+                ranking: None,
             });
         }
 
@@ -376,8 +1506,15 @@ Commands for: {}"#,
     fn looks_like_command(&self, line: &str) -> bool {
         let first_word = line.split_whitespace().next().unwrap_or("");
 
-        // Check if it starts with a known command
-        if self.is_command_starter(first_word) {
+        // Check if it starts with a known command or opens a loop or conditional
+        if self.is_command_starter(first_word) || SHELL_KEYWORDS.contains(&first_word) {
+            return true;
+        }
+
+        // The user's aliases, functions, and `!!` stand for commands
+        if self.check_path
+            && (self.shell().defines(first_word) || self.shell().resolve(first_word) != first_word)
+        {
             return true;
         }
 
@@ -386,19 +1523,36 @@ Commands for: {}"#,
     }
 
     /// Validates that a command is safe and executable
+    ///
+    /// Aliases and history designators are checked as what they expand to;
+    /// the command itself is resolved later, once it's known what `!!` refers to.
     fn is_valid_command(&self, command: &str) -> bool {
+        let resolved;
+        let command = match self.check_path {
+            true => {
+                resolved = self.shell().resolve(command);
+                resolved.as_str()
+            }
+            false => command,
+        };
+
         // Basic safety checks
         let dangerous_patterns = ["rm -rf /", "rm -rf *", "dd if=", "mkfs", "fdisk", "> /dev/"];
 
         for pattern in &dangerous_patterns {
             if command.contains(pattern) {
-                warn!("Rejected dangerous command: {}", command);
+                warn!(command, pattern, "Rejected dangerous command");
                 return false;
             }
         }
 
-        // Check length and basic format
-        if command.is_empty() || command.len() > 500 {
+        // Check length and basic format; heredocs may carry a whole file
+        let max_len = if command.contains('\n') {
+            MAX_MULTILINE_COMMAND_LEN
+        } else {
+            500
+        };
+        if command.is_empty() || command.len() > max_len {
             return false;
         }
 
@@ -412,6 +1566,14 @@ Commands for: {}"#,
         // Check if executable exists using 'which' command
         if let Ok(output) = Command::new("which").arg(first_word).output() {
             if output.status.success() {
+                // A version manager's shim is on PATH even where no version is selected
+                let path = String::from_utf8_lossy(&output.stdout);
+                if self.check_path
+                    && !VersionShims::selects_version(Path::new(path.trim()), first_word)
+                {
+                    debug!("Command '{}' has no version selected here", first_word);
+                    return false;
+                }
                 return true;
             }
         }
@@ -419,10 +1581,16 @@ Commands for: {}"#,
         // Allow shell built-ins and paths
         if first_word.contains('/')
             || matches!(first_word, "cd" | "echo" | "pwd" | "export" | "alias")
+            || SHELL_KEYWORDS.contains(&first_word)
         {
             return true;
         }
 
+        // `which` doesn't know the user's shell functions
+        if self.check_path && self.shell().defines(first_word) {
+            return true;
+        }
+
         // Reject pseudo-commands
         let pseudo_patterns = [" query ", " api ", " endpoint ", " service "];
         for pattern in &pseudo_patterns {
@@ -431,6 +1599,11 @@ Commands for: {}"#,
             }
         }
 
+        // Another machine's PATH can't be checked from here
+        if !self.check_path {
+            return true;
+        }
+
         debug!("Command '{}' not found in PATH", first_word);
         false
     }
@@ -0,0 +1,180 @@
+use anyhow::Result;
+use std::fmt;
+
+use crate::ai::{GgufHeader, ModelEntry, ModelIdentity, ModelRegistry};
+use crate::config::ModelConfig;
+use crate::utils::MemoryInfo;
+
+/// Memory llama.cpp takes besides the weights and KV cache: compute buffers and the program
+const OVERHEAD_MB: u64 = 300;
+
+/// Architectures whose layers mostly attend only within `sliding_window`
+/// tokens, with how many layers there are per full-context one
+const SLIDING_WINDOW_PATTERNS: [(&str, u64); 5] = [
+    ("gemma2", 2),
+    ("gemma3", 6),
+    ("gemma3n", 5),
+    ("cohere2", 4),
+    ("llama4", 4),
+];
+
+/// A downloaded model that fits in the memory that is free
+#[derive(Debug, Clone)]
+pub struct SmallerModel {
+    /// Registry name, for `commandy model use`
+    pub name: String,
+    pub repo: String,
+    pub needed_mb: u64,
+}
+
+/// Running the model would take more memory than is free, even with swap
+#[derive(Debug)]
+pub struct ModelTooLarge {
+    pub model: String,
+    pub needed_mb: u64,
+    pub available_mb: u64,
+    pub swap_free_mb: u64,
+    /// Downloaded models that would fit, largest first
+    pub smaller: Vec<SmallerModel>,
+}
+
+impl fmt::Display for ModelTooLarge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} needs about {} MB, but only {} MB of memory and {} MB of swap are free, \
+             so llama.cpp was not started. ",
+            self.model, self.needed_mb, self.available_mb, self.swap_free_mb
+        )?;
+        match self.smaller.first() {
+            Some(smaller) => write!(
+                f,
+                "{} is downloaded and needs about {} MB: commandy model use {}",
+                smaller.name, smaller.needed_mb, smaller.name
+            ),
+            None => write!(
+                f,
+                "Close other programs, lower [model] context_size, or pick a smaller \
+                 model from 'commandy model list --available'"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ModelTooLarge {}
+
+/// Keeps llama.cpp from loading a model that won't fit, so the OOM killer
+/// doesn't pick the user's editor or browser to make room for it
+pub struct MemoryGuard;
+
+impl MemoryGuard {
+    /// Fails with [`ModelTooLarge`] when `model` needs more than the free memory and swap
+    ///
+    /// Passes when the need or the free memory can't be told.
+    pub fn check(config: &ModelConfig, model: &str) -> Result<()> {
+        let (Some(memory), Some(needed_mb)) =
+            (MemoryInfo::detect(), Self::estimate_mb(config, model))
+        else {
+            return Ok(());
+        };
+        let free_mb = memory.available_mb + memory.swap_free_mb;
+        if needed_mb <= free_mb {
+            return Ok(());
+        }
+
+        let mut smaller: Vec<SmallerModel> = ModelRegistry::load()
+            .map(|registry| registry.models().to_vec())
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|entry| entry.repo != model)
+            .filter(|entry| ModelIdentity::downloaded_file(&entry.repo).is_some())
+            .filter_map(|entry| {
+                let needed_mb = Self::estimate_mb(config, &entry.repo)?;
+                (needed_mb <= free_mb).then_some(SmallerModel {
+                    name: entry.name,
+                    repo: entry.repo,
+                    needed_mb,
+                })
+            })
+            .collect();
+        smaller.sort_by_key(|m| std::cmp::Reverse(m.needed_mb));
+
+        Err(ModelTooLarge {
+            model: model.to_string(),
+            needed_mb,
+            available_mb: memory.available_mb,
+            swap_free_mb: memory.swap_free_mb,
+            smaller,
+        }
+        .into())
+    }
+
+    /// Megabytes `model` takes with its configured context and cache types:
+    /// from the downloaded file's GGUF header, else from the registry's figures
+    pub fn estimate_mb(config: &ModelConfig, model: &str) -> Option<u64> {
+        let runtime = config.runtime_for(model);
+        let header =
+            ModelIdentity::downloaded_file(model).and_then(|path| GgufHeader::read(&path).ok());
+        let Some(header) = header else {
+            let registry = ModelRegistry::load().ok()?;
+            let entry = registry.find(model)?;
+            return Some(
+                entry.estimate_memory_mb(
+                    runtime.context_size,
+                    runtime.cache_type_k.as_deref(),
+                    runtime.cache_type_v.as_deref(),
+                ) + OVERHEAD_MB,
+            );
+        };
+
+        let weights_mb = header.file_size / 1024 / 1024;
+        let kv_mb = Self::kv_cache_mb(
+            &header,
+            runtime.context_size,
+            runtime.cache_type_k.as_deref(),
+            runtime.cache_type_v.as_deref(),
+        )
+        .unwrap_or(0);
+        Some(weights_mb + kv_mb + OVERHEAD_MB)
+    }
+
+    /// Size of the KV cache, or `None` for models without attention heads
+    fn kv_cache_mb(
+        header: &GgufHeader,
+        context_size: Option<u32>,
+        cache_type_k: Option<&str>,
+        cache_type_v: Option<&str>,
+    ) -> Option<u64> {
+        let layers = header.get("block_count")?;
+        let context = match context_size {
+            Some(0) | None => header.get("context_length")?,
+            Some(size) => u64::from(size),
+        };
+        let heads = header.get("attention.head_count")?.max(1);
+        let kv_heads = header.get("attention.head_count_kv").unwrap_or(heads);
+        let key_length = match header.get("attention.key_length") {
+            Some(length) => length,
+            None => header.get("embedding_length")? / heads,
+        };
+        let value_length = header.get("attention.value_length").unwrap_or(key_length);
+
+        // Sliding-window layers only keep the window, not the whole context
+        let window = header.get("attention.sliding_window").unwrap_or(context);
+        let full_layers = match SLIDING_WINDOW_PATTERNS
+            .iter()
+            .find(|(architecture, _)| *architecture == header.architecture)
+        {
+            Some((_, pattern)) if window < context => layers.div_ceil(*pattern),
+            _ => layers,
+        };
+        let tokens = full_layers * context + (layers - full_layers) * window;
+
+        // f16 elements, two bytes each, scaled for quantized cache types
+        let bytes = tokens as f64
+            * kv_heads as f64
+            * 2.0
+            * (key_length as f64 * ModelEntry::cache_type_factor(cache_type_k)
+                + value_length as f64 * ModelEntry::cache_type_factor(cache_type_v));
+        Some((bytes / 1024.0 / 1024.0).ceil() as u64)
+    }
+}
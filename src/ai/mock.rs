@@ -0,0 +1,152 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::debug;
+
+use crate::ai::GenerationRecord;
+
+/// Selects the generation backend: unset for llama.cpp, `mock`, or `record`
+pub const BACKEND_ENV: &str = "COMMANDY_BACKEND";
+
+/// Fixture directory for the mock and record backends
+pub const FIXTURES_ENV: &str = "COMMANDY_FIXTURES";
+
+/// Where generations come from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackendMode {
+    LlamaCpp,
+    /// Canned responses from fixture files; no model or binary needed
+    Mock,
+    /// Real llama.cpp runs whose responses are saved as fixtures
+    Record,
+}
+
+impl BackendMode {
+    pub fn from_env() -> Result<Self> {
+        match std::env::var(BACKEND_ENV).as_deref() {
+            Err(_) | Ok("") | Ok("llama.cpp") => Ok(Self::LlamaCpp),
+            Ok("mock") => Ok(Self::Mock),
+            Ok("record") => Ok(Self::Record),
+            Ok(other) => Err(anyhow::anyhow!(
+                "Unknown {BACKEND_ENV} '{other}' (expected mock or record)"
+            )),
+        }
+    }
+}
+
+/// A canned model response
+///
+/// Recorded fixtures carry the exact prompt; hand-written ones can instead
+/// give a `match` substring, which is checked against the prompt.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Fixture {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt: Option<String>,
+    #[serde(default, rename = "match", skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>,
+    pub response: String,
+}
+
+/// Deterministic stand-in for llama.cpp, backed by a directory of JSON fixtures
+pub struct MockBackend {
+    dir: PathBuf,
+}
+
+impl MockBackend {
+    /// Uses $COMMANDY_FIXTURES, or ~/.commandy/fixtures
+    pub fn from_env() -> Result<Self> {
+        let dir = match std::env::var_os(FIXTURES_ENV) {
+            Some(dir) => PathBuf::from(dir),
+            None => dirs::home_dir()
+                .context("Could not find home directory")?
+                .join(".commandy")
+                .join("fixtures"),
+        };
+        Ok(Self { dir })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Answers from the recorded fixture for this exact prompt, else the first
+    /// `match` fixture (in file name order) whose pattern appears in the prompt
+    pub fn respond(&self, record: &GenerationRecord) -> Result<String> {
+        let key = Self::key(&record.model, &record.prompt);
+        let recorded = self.dir.join(format!("{key}.json"));
+        if recorded.exists() {
+            debug!("Mock response from {recorded:?}");
+            return Ok(Self::read(&recorded)?.response);
+        }
+
+        for path in self.fixture_files()? {
+            let fixture = Self::read(&path)?;
+            if fixture.model.as_ref().is_some_and(|m| *m != record.model) {
+                continue;
+            }
+            let matched = match (&fixture.prompt, &fixture.pattern) {
+                (Some(prompt), _) => *prompt == record.prompt,
+                (None, Some(pattern)) => record.prompt.contains(pattern.as_str()),
+                (None, None) => false,
+            };
+            if matched {
+                debug!("Mock response from {path:?}");
+                return Ok(fixture.response);
+            }
+        }
+
+        Err(anyhow::anyhow!(
+            "No mock fixture for {} prompt {key} in {} (record one with {BACKEND_ENV}=record)",
+            record.role,
+            self.dir.display()
+        ))
+    }
+
+    /// Saves a real response so the same prompt can be answered without a model
+    pub fn record(&self, record: &GenerationRecord, response: &str) -> Result<PathBuf> {
+        fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Failed to create {}", self.dir.display()))?;
+
+        let fixture = Fixture {
+            model: Some(record.model.clone()),
+            prompt: Some(record.prompt.clone()),
+            pattern: None,
+            response: response.to_string(),
+        };
+        let path = self
+            .dir
+            .join(format!("{}.json", Self::key(&record.model, &record.prompt)));
+        fs::write(&path, serde_json::to_string_pretty(&fixture)?)?;
+        debug!("Recorded fixture {path:?}");
+        Ok(path)
+    }
+
+    fn fixture_files(&self) -> Result<Vec<PathBuf>> {
+        if !self.dir.exists() {
+            return Ok(Vec::new());
+        }
+        let mut files: Vec<PathBuf> = fs::read_dir(&self.dir)?
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .collect();
+        files.sort();
+        Ok(files)
+    }
+
+    fn read(path: &Path) -> Result<Fixture> {
+        let content = fs::read_to_string(path)?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Invalid fixture {}", path.display()))
+    }
+
+    /// Stable file name for a model and prompt
+    fn key(model: &str, prompt: &str) -> String {
+        let digest = Sha256::digest(format!("{model}\n{prompt}").as_bytes());
+        digest[..8].iter().map(|b| format!("{b:02x}")).collect()
+    }
+}
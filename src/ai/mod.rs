@@ -1,7 +1,27 @@
+pub mod device;
+pub mod experiment;
+pub mod gguf;
+pub mod identity;
 pub mod llamacpp_client;
+pub mod memory_guard;
+pub mod mock;
+pub mod model_lock;
 pub mod prompt;
+pub mod prompt_cache;
+pub mod registry;
+pub mod replay;
 pub mod response;
 
-pub use llamacpp_client::LlamaCppClient;
-pub use prompt::PromptBuilder;
+pub use device::{Device, DeviceProbe, GpuBackend};
+pub use experiment::{Arm, Experiment};
+pub use gguf::GgufHeader;
+pub use identity::ModelIdentity;
+pub use llamacpp_client::{GenerationStats, LlamaCppClient, ModelRole, PromptEvalStats};
+pub use memory_guard::{MemoryGuard, ModelTooLarge, SmallerModel};
+pub use mock::{BackendMode, Fixture, MockBackend};
+pub use model_lock::ModelLock;
+pub use prompt::{Prompt, PromptBuilder};
+pub use prompt_cache::PromptCache;
+pub use registry::{ModelEntry, ModelRegistry};
+pub use replay::{GenerationRecord, RecordedGeneration};
 pub use response::ResponseParser;
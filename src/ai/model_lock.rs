@@ -0,0 +1,75 @@
+use anyhow::{Context, Result};
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// How often a waiting process checks whether the model is free
+const POLL: Duration = Duration::from_millis(200);
+
+/// Lets one commandy at a time run llama.cpp, so requests from two panes
+/// don't each load the model and push the machine into swap
+///
+/// The lock is an OS file lock on ~/.commandy/model.lock, released when it is
+/// dropped or when its process exits, however that happens.
+pub struct ModelLock {
+    /// Held open for as long as the lock is
+    _file: File,
+}
+
+impl ModelLock {
+    fn path() -> Result<PathBuf> {
+        Ok(dirs::home_dir()
+            .context("Could not find home directory")?
+            .join(".commandy")
+            .join("model.lock"))
+    }
+
+    /// The lock, or `None` while another process holds it
+    pub fn try_acquire() -> Result<Option<Self>> {
+        let path = Self::path()?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create {}", dir.display()))?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .read(true)
+            .write(true)
+            .open(&path)
+            .with_context(|| format!("Failed to open {}", path.display()))?;
+        match file.try_lock() {
+            Ok(()) => {}
+            Err(TryLockError::WouldBlock) => return Ok(None),
+            Err(TryLockError::Error(e)) => {
+                return Err(e).with_context(|| format!("Failed to lock {}", path.display()))
+            }
+        }
+
+        // Only read to say who is being waited for
+        file.set_len(0)?;
+        write!(file, "{}", std::process::id())?;
+        Ok(Some(Self { _file: file }))
+    }
+
+    /// Waits until the lock is free; checks rather than blocks, so the wait
+    /// ends when its future is dropped, as with Ctrl-C or `--timeout`
+    pub async fn acquire() -> Result<Self> {
+        loop {
+            if let Some(lock) = Self::try_acquire()? {
+                return Ok(lock);
+            }
+            tokio::time::sleep(POLL).await;
+        }
+    }
+
+    /// Process id of the commandy holding the lock, as it recorded
+    pub fn holder() -> Option<u32> {
+        fs::read_to_string(Self::path().ok()?)
+            .ok()?
+            .trim()
+            .parse()
+            .ok()
+    }
+}
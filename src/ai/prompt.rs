@@ -1,5 +1,62 @@
-use crate::context::ContextData;
+use std::collections::HashMap;
 
+use crate::context::{CiSystem, ContextData, FileTypeScanner, QuantityParser};
+
+/// Where each prompt template's output stops being useful
+///
+/// Small models tend to carry on with an explanation, or invent another
+/// request and answer that too.
+const DEFAULT_STOP_SEQUENCES: &[(&str, &[&str])] = &[
+    ("suggest", &["\nRequest:", "Explanation:", "\n\n\n"]),
+    ("refine", &["\nRequest:", "Explanation:", "\n\n\n"]),
+    (
+        "vary",
+        &["\nRequest:", "\nCommand:", "Explanation:", "\n\n\n"],
+    ),
+    ("fix", &["\nCommand:", "Explanation:", "\n\n\n"]),
+    (
+        "next",
+        &["\nCommand:", "\nRequest:", "Explanation:", "\n\n\n"],
+    ),
+    ("explain", &["\nCommand:", "\n\n\n"]),
+    ("translate", &["\nCommand:", "Explanation:", "\n\n\n"]),
+    ("english", &["\n"]),
+    ("summarize", &["\nScript:", "\n\n\n"]),
+    ("digest", &["\nCommand:", "\nOutput:", "\n\n\n"]),
+    ("tldr", &["\nTool:", "\nDocumentation:", "\n\n\n"]),
+    ("rules", &["\nRequests:", "\n\n"]),
+    ("classify", &["\n"]),
+];
+
+/// A prompt in two parts: a system part that rarely changes, and the request
+///
+/// The system part comes first in the text, so llama.cpp's prompt cache and
+/// llama-server's prefix cache can reuse its evaluated state across requests.
+#[derive(Debug, Clone, Default)]
+pub struct Prompt {
+    /// Template name, which picks the stop sequences
+    pub template: &'static str,
+    pub system: String,
+    pub user: String,
+}
+
+impl Prompt {
+    /// A prompt with nothing worth caching
+    pub fn user_only(template: &'static str, user: String) -> Self {
+        Self {
+            template,
+            system: String::new(),
+            user,
+        }
+    }
+
+    /// Both parts as one completion prompt
+    pub fn text(&self) -> String {
+        format!("{}{}", self.system, self.user)
+    }
+}
+
+/// Builds the prompts for suggestion requests
 pub struct PromptBuilder;
 
 impl Default for PromptBuilder {
@@ -13,8 +70,387 @@ impl PromptBuilder {
         Self
     }
 
-    pub fn build_enhanced_prompt(&self, user_prompt: &str, _context: &ContextData) -> String {
-        // This is handled by the Python layer, but we can do some preprocessing here
-        format!("User request: {user_prompt}")
+    /// Builds the suggestion prompt: machine details and rules, then the request and its context
+    pub fn suggestions(&self, user_prompt: &str, context: &ContextData) -> Prompt {
+        if let Some(base_command) = &context.base_command {
+            return Prompt::user_only(
+                "refine",
+                self.refinement(base_command, user_prompt, context),
+            );
+        }
+
+        if let Some(command) = &context.vary_command {
+            return Prompt {
+                template: "vary",
+                system: self.system(context),
+                user: self.variations(command, user_prompt, context),
+            };
+        }
+
+        // The request comes last so runs share as long a prefix as possible
+        let mut prompt = format!(
+            r#"
+Task category: {}{}{}
+Example format:
+{}"#,
+            context.prompt_category,
+            context
+                .prompt_category
+                .prompt_hint()
+                .map(|hint| format!("\nHint: {hint}"))
+                .unwrap_or_default(),
+            context
+                .working_directory
+                .as_ref()
+                .map(|dir| format!("\nWorking directory: {}", dir.describe()))
+                .unwrap_or_default(),
+            context.prompt_category.examples().join("\n"),
+        );
+
+        if !context.plugin_context.is_empty() {
+            prompt.push_str("\n\nAdditional context:\n");
+            prompt.push_str(&context.plugin_context.join("\n"));
+        }
+
+        // What the user was just doing, with where and whether it failed
+        if !context.recent_commands.is_empty() {
+            let now = chrono::Utc::now();
+            let lines: Vec<String> = context
+                .recent_commands
+                .iter()
+                .map(|command| format!("- {}", command.describe(now)))
+                .collect();
+            prompt.push_str("\n\nRecent commands (oldest first):\n");
+            prompt.push_str(&lines.join("\n"));
+        }
+
+        // Real names let the model fill in arguments instead of inventing them
+        if !context.entities.is_empty() {
+            let lines: Vec<String> = context
+                .entities
+                .iter()
+                .map(|set| format!("- {}: {}", set.kind.label(), set.values.join(", ")))
+                .collect();
+            prompt.push_str(
+                "\n\nNames that exist on this machine (use them instead of placeholders):\n",
+            );
+            prompt.push_str(&lines.join("\n"));
+        }
+
+        // Job names let "run the lint job locally" become a real act or gitlab-runner call
+        if !context.ci_jobs.is_empty() {
+            prompt.push_str("\n\nCI jobs in this project:");
+            let mut systems = Vec::new();
+            for job in &context.ci_jobs {
+                if !systems.contains(&job.system) {
+                    systems.push(job.system);
+                }
+            }
+            for system in systems {
+                let jobs: Vec<String> = context
+                    .ci_jobs
+                    .iter()
+                    .filter(|job| job.system == system)
+                    .map(|job| match system {
+                        CiSystem::GitHubActions => format!("{} ({})", job.name, job.file),
+                        CiSystem::GitLab => job.name.clone(),
+                    })
+                    .collect();
+                prompt.push_str(&format!(
+                    "\n- {}: {} (run locally with `{}`)",
+                    system.label(),
+                    jobs.join(", "),
+                    system.local_runner()
+                ));
+            }
+        }
+
+        // The project's own targets beat a reinvented build or test command
+        if !context.tasks.is_empty() {
+            let lines: Vec<String> = context
+                .tasks
+                .iter()
+                .map(|task| match &task.description {
+                    Some(description) => format!("- {}: {description}", task.command()),
+                    None => format!("- {}", task.command()),
+                })
+                .collect();
+            prompt.push_str("\n\nProject tasks (prefer these when one fits the request):\n");
+            prompt.push_str(&lines.join("\n"));
+        }
+
+        // What this directory activates beyond the global PATH
+        if let Some(toolchain) = &context.toolchain {
+            prompt.push_str("\n\nProject toolchain:\n");
+            let lines: Vec<String> = toolchain
+                .describe()
+                .iter()
+                .map(|line| format!("- {line}"))
+                .collect();
+            prompt.push_str(&lines.join("\n"));
+        }
+
+        // Where pip installs to, and which node runs
+        if !context.runtimes.is_empty() {
+            prompt.push_str("\n\nLanguage environments:\n");
+            let lines: Vec<String> = context
+                .runtimes
+                .describe()
+                .iter()
+                .map(|line| format!("- {line}"))
+                .collect();
+            prompt.push_str(&lines.join("\n"));
+        }
+
+        // Real dates, so "since Monday" doesn't become a guessed one
+        if let Some(time) = &context.time {
+            prompt.push_str("\n\nDate and time:\n");
+            let lines: Vec<String> = time
+                .describe()
+                .iter()
+                .map(|line| format!("- {line}"))
+                .collect();
+            prompt.push_str(&lines.join("\n"));
+        }
+
+        // Counts pick the formatter, linter, or build tool for "format everything"
+        if !context.file_types.is_empty() {
+            prompt.push_str(&format!(
+                "\n\nFiles in this directory: {}",
+                FileTypeScanner::describe(&context.file_types)
+            ));
+        }
+
+        // Set variables tell the model which tools are already configured
+        if !context.env_var_names.is_empty() {
+            prompt.push_str(&format!(
+                "\n\nSet in the environment: {}",
+                context.env_var_names.join(", ")
+            ));
+        }
+
+        // The user's own shortcuts, which commandy expands or runs in their shell
+        if !context.shell_definitions.is_empty() {
+            prompt.push_str(
+                "\n\nThe user's aliases and shell functions (use them where they fit):\n",
+            );
+            let lines: Vec<String> = context
+                .shell_definitions
+                .iter()
+                .map(|definition| format!("- {definition}"))
+                .collect();
+            prompt.push_str(&lines.join("\n"));
+        }
+
+        // Earlier turns let the model resolve "it", "that", "now ..." and so on
+        if !context.session_summary.is_empty() {
+            prompt.push_str("\n\nEarlier in this conversation:\n");
+            for line in &context.session_summary {
+                prompt.push_str(&format!("- {line}\n"));
+            }
+        }
+        if !context.session_turns.is_empty() {
+            prompt
+                .push_str("\n\nEarlier in this shell session (the request may refer to these):\n");
+            for turn in &context.session_turns {
+                match &turn.command {
+                    Some(command) => {
+                        prompt.push_str(&format!("- \"{}\" -> {}\n", turn.prompt, command))
+                    }
+                    None => prompt.push_str(&format!("- \"{}\"\n", turn.prompt)),
+                }
+            }
+        }
+
+        // Add learned context if available
+        if !context.learned_patterns.is_empty() {
+            let patterns: Vec<String> = context
+                .learned_patterns
+                .iter()
+                .map(|p| format!("\"{}\" → {}", p.prompt, p.command))
+                .collect();
+            prompt.push_str("\n\nLearned patterns:\n");
+            prompt.push_str(&patterns.join("\n"));
+        }
+        if !context.learned_rules.is_empty() {
+            prompt.push_str("\n\nThe user's habits:\n");
+            for rule in &context.learned_rules {
+                prompt.push_str(&format!("- {rule}\n"));
+            }
+        }
+
+        prompt.push_str(&self.attachments(context));
+        prompt.push_str(&self.already_suggested(context));
+        prompt.push_str(&format!(
+            "\n\nRequest: {user_prompt}{}\n\nCommands:",
+            self.quantities(context)
+        ));
+        Prompt {
+            template: "suggest",
+            system: self.system(context),
+            user: prompt,
+        }
+    }
+
+    /// Sizes, durations, and counts in the request, worked out for command flags
+    fn quantities(&self, context: &ContextData) -> String {
+        if context.quantities.is_empty() {
+            return String::new();
+        }
+        let lines: Vec<String> = QuantityParser::describe(&context.quantities)
+            .iter()
+            .map(|line| format!("- {line}"))
+            .collect();
+        format!("\nIn other words:\n{}", lines.join("\n"))
+    }
+
+    /// Commands on earlier pages of the picker, so a later page asks for alternatives
+    fn already_suggested(&self, context: &ContextData) -> String {
+        if context.exclude_commands.is_empty() {
+            return String::new();
+        }
+        let lines: Vec<String> = context
+            .exclude_commands
+            .iter()
+            .map(|command| format!("- {command}"))
+            .collect();
+        format!(
+            "\n\nAlready suggested (give different commands):\n{}",
+            lines.join("\n")
+        )
+    }
+
+    /// Asks for other versions of a suggestion whose approach is right but whose
+    /// flags may not be, after the usual system part
+    fn variations(&self, command: &str, user_prompt: &str, context: &ContextData) -> String {
+        format!(
+            r#"
+Request: {user_prompt}
+Command: {command}
+
+This command takes the right approach. Write other versions of it: different flags or options, or a closely related tool that does the same job. Keep each one a complete command for the request.{}
+
+Commands:"#,
+            self.already_suggested(context)
+        )
+    }
+
+    /// Stop sequences for a template, from `[model.stop_sequences]` or the defaults
+    pub fn stop_sequences(template: &str, overrides: &HashMap<String, Vec<String>>) -> Vec<String> {
+        if let Some(stops) = overrides.get(template) {
+            return stops.clone();
+        }
+        DEFAULT_STOP_SEQUENCES
+            .iter()
+            .find(|(name, _)| *name == template)
+            .map(|(_, stops)| stops.iter().map(|s| s.to_string()).collect())
+            .unwrap_or_default()
+    }
+
+    /// Start of the suggestion prompt that only changes with the machine
+    ///
+    /// Shared by every suggestion request, so its evaluated state can be reused.
+    fn system(&self, context: &ContextData) -> String {
+        let environment = &context.environment;
+        let available_tools = environment
+            .get("available_tools")
+            .map_or("basic".to_string(), |v| {
+                v.split(',').take(20).collect::<Vec<_>>().join(", ")
+            });
+
+        if let Some(instructions) = &context.instructions {
+            let system = instructions
+                .replace(
+                    "{os}",
+                    environment.get("os").map_or("unknown", |v| v.as_str()),
+                )
+                .replace(
+                    "{shell}",
+                    environment.get("shell").map_or("unknown", |v| v.as_str()),
+                )
+                .replace("{tools}", &available_tools);
+            return format!("{}\n", system.trim_end());
+        }
+
+        format!(
+            r#"Generate ONLY valid shell commands for the request at the end.
+
+System Information:
+- OS: {}
+- Shell: {}
+- Available executables: {}{}
+
+CRITICAL REQUIREMENTS:
+1. Commands MUST use only executables that exist in PATH
+2. Start with real command names, not pseudo-commands
+3. Use proper shell syntax
+4. Be directly executable
+5. Provide safe, practical solutions
+
+Output format: Return 1-3 shell commands, each on a new line.
+"#,
+            environment.get("os").map_or("unknown", |v| v.as_str()),
+            environment.get("shell").map_or("unknown", |v| v.as_str()),
+            available_tools,
+            match environment.contains_key("wsl") {
+                true =>
+                    "\n- WSL: Windows drives are under /mnt/<letter>; copy with clip.exe; \
+                    open files with wslview or explorer.exe",
+                false => "",
+            },
+        )
+    }
+
+    /// Files given with `-f`, right before the request that refers to them
+    fn attachments(&self, context: &ContextData) -> String {
+        let mut section = String::new();
+        if context.attachments.is_empty() {
+            return section;
+        }
+        section.push_str("\n\nFiles the user attached (use the names in them):");
+        for attachment in &context.attachments {
+            section.push_str(&format!(
+                "\n```{}\n{}",
+                attachment.name,
+                attachment.content.trim_end()
+            ));
+            if attachment.truncated {
+                section.push_str("\n[... rest of file not shown]");
+            }
+            section.push_str("\n```");
+        }
+        section
+    }
+
+    /// Builds a prompt asking the model to extend a command piped from another commandy
+    fn refinement(&self, base_command: &str, user_prompt: &str, context: &ContextData) -> String {
+        let environment = &context.environment;
+
+        format!(
+            r#"Refine an existing shell command according to a request.{}
+
+Existing command: {}
+Request: {}{}
+
+System Information:
+- OS: {}
+- Shell: {}
+
+REQUIREMENTS:
+1. Keep the existing command's intent and extend or modify it
+2. Prefer composing a pipeline (e.g. piping into grep, awk, jq, or xargs)
+3. Each result must be a complete command including the existing part
+4. Use proper shell syntax
+
+Output format: Return 1-3 shell commands, each on a new line.
+
+Commands:"#,
+            self.attachments(context),
+            base_command,
+            user_prompt,
+            self.quantities(context),
+            environment.get("os").map_or("unknown", |v| v.as_str()),
+            environment.get("shell").map_or("unknown", |v| v.as_str()),
+        )
     }
 }
@@ -0,0 +1,94 @@
+use anyhow::{Context, Result};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::PathBuf;
+use tracing::{debug, warn};
+
+use crate::ai::GenerationRecord;
+
+/// Cache files kept; each holds the KV state of one prompt start for one model
+const MAX_CACHE_FILES: usize = 8;
+
+/// llama.cpp `--prompt-cache` files for the part of a prompt that rarely changes
+///
+/// llama.cpp reloads the saved state and only evaluates the tokens after the
+/// longest prefix shared with the saved prompt. Files are named after the
+/// model, its runtime options, and the stable prefix, so a prompt only meets a
+/// cache whose saved prompt starts the same way.
+pub struct PromptCache {
+    dir: PathBuf,
+}
+
+impl PromptCache {
+    /// ~/.commandy/prompt-cache
+    pub fn from_home() -> Result<Self> {
+        let dir = dirs::home_dir()
+            .context("Could not find home directory")?
+            .join(".commandy")
+            .join("prompt-cache");
+        Ok(Self { dir })
+    }
+
+    /// Cache file for a generation whose prompt starts with `prefix`
+    pub fn file_for(&self, record: &GenerationRecord, prefix: &str) -> Result<PathBuf> {
+        fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Failed to create {}", self.dir.display()))?;
+
+        let digest = Sha256::digest(
+            format!(
+                "{}\n{}\n{}\n{}\n{prefix}",
+                record.model, record.context_size, record.cache_type_k, record.cache_type_v
+            )
+            .as_bytes(),
+        );
+        let key: String = digest[..8].iter().map(|b| format!("{b:02x}")).collect();
+        Ok(self.dir.join(format!("{key}.bin")))
+    }
+
+    /// Removes all but the most recently used cache files
+    pub fn prune(&self) {
+        let mut files: Vec<(std::time::SystemTime, PathBuf)> = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries
+                .flatten()
+                .filter_map(|entry| {
+                    let modified = entry.metadata().ok()?.modified().ok()?;
+                    Some((modified, entry.path()))
+                })
+                .collect(),
+            Err(_) => return,
+        };
+        files.sort_by_key(|(modified, _)| std::cmp::Reverse(*modified));
+
+        for (_, path) in files.into_iter().skip(MAX_CACHE_FILES) {
+            debug!("Removing old prompt cache {path:?}");
+            if let Err(e) = fs::remove_file(&path) {
+                warn!("Failed to remove prompt cache {}: {e}", path.display());
+            }
+        }
+    }
+
+    /// Marks a cache file as used, so pruning keeps it
+    pub fn touch(&self, path: &std::path::Path) {
+        let touched = fs::File::options()
+            .append(true)
+            .open(path)
+            .and_then(|file| file.set_modified(std::time::SystemTime::now()));
+        if let Err(e) = touched {
+            debug!("Failed to touch prompt cache {}: {e}", path.display());
+        }
+    }
+
+    /// Deletes every cache file, returning how many there were
+    pub fn clear(&self) -> Result<usize> {
+        if !self.dir.exists() {
+            return Ok(0);
+        }
+        let mut removed = 0;
+        for entry in fs::read_dir(&self.dir)?.flatten() {
+            fs::remove_file(entry.path())
+                .with_context(|| format!("Failed to remove {}", entry.path().display()))?;
+            removed += 1;
+        }
+        Ok(removed)
+    }
+}
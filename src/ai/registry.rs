@@ -0,0 +1,132 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use tracing::{debug, warn};
+
+/// Metadata for a model known to work with commandy
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelEntry {
+    pub name: String,
+    pub repo: String,
+    #[serde(default)]
+    pub description: String,
+    pub size_mb: u64,
+    pub quantization: String,
+    pub min_ram_mb: u64,
+    pub context_length: u32,
+    pub license: String,
+    pub chat_template: String,
+    #[serde(default)]
+    pub kv_mb_per_1k_ctx: Option<u64>,
+    #[serde(default)]
+    pub recommended: bool,
+}
+
+impl ModelEntry {
+    /// Estimates resident memory for the weights plus KV cache at the given settings
+    pub fn estimate_memory_mb(
+        &self,
+        context_size: Option<u32>,
+        cache_type_k: Option<&str>,
+        cache_type_v: Option<&str>,
+    ) -> u64 {
+        let context = match context_size {
+            Some(0) | None => self.context_length,
+            Some(size) => size,
+        } as f64;
+
+        let kv_f16_mb = self.kv_mb_per_1k_ctx.unwrap_or(0) as f64 * context / 1024.0;
+        // Keys and values are each half of the f16 KV cache
+        let kv_mb = kv_f16_mb / 2.0 * Self::cache_type_factor(cache_type_k)
+            + kv_f16_mb / 2.0 * Self::cache_type_factor(cache_type_v);
+
+        self.size_mb + kv_mb.ceil() as u64
+    }
+
+    /// Size of a KV-cache element relative to f16
+    pub fn cache_type_factor(cache_type: Option<&str>) -> f64 {
+        match cache_type.unwrap_or("f16") {
+            "f32" => 2.0,
+            "q8_0" => 0.53,
+            "q5_1" => 0.375,
+            "q5_0" => 0.34,
+            "q4_1" => 0.31,
+            "q4_0" | "iq4_nl" => 0.28,
+            _ => 1.0,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct RegistryFile {
+    #[serde(default)]
+    models: Vec<ModelEntry>,
+}
+
+/// Curated list of models, built in and optionally extended by the user
+pub struct ModelRegistry {
+    models: Vec<ModelEntry>,
+}
+
+impl ModelRegistry {
+    /// Loads the built-in registry merged with ~/.commandy/models.toml if present
+    pub fn load() -> Result<Self> {
+        let mut registry = Self::builtin()?;
+
+        let override_path = Self::get_override_path()?;
+        if override_path.exists() {
+            debug!("Loading model registry overrides from {override_path:?}");
+            let content = fs::read_to_string(&override_path)?;
+            match toml::from_str::<RegistryFile>(&content) {
+                Ok(file) => registry.merge(file.models),
+                Err(e) => warn!("Ignoring invalid model registry {override_path:?}: {e}"),
+            }
+        }
+
+        Ok(registry)
+    }
+
+    pub fn builtin() -> Result<Self> {
+        let file: RegistryFile = toml::from_str(include_str!("../../data/models.toml"))
+            .context("Built-in model registry is invalid")?;
+        Ok(Self {
+            models: file.models,
+        })
+    }
+
+    pub fn models(&self) -> &[ModelEntry] {
+        &self.models
+    }
+
+    /// Looks up a model by short name or Hugging Face repo
+    pub fn find(&self, name_or_repo: &str) -> Option<&ModelEntry> {
+        self.models
+            .iter()
+            .find(|m| m.name == name_or_repo || m.repo.eq_ignore_ascii_case(name_or_repo))
+    }
+
+    /// Picks the largest recommended model that fits in the given amount of RAM
+    pub fn recommended_for(&self, total_ram_mb: u64) -> Option<&ModelEntry> {
+        self.models
+            .iter()
+            .filter(|m| m.recommended && m.min_ram_mb <= total_ram_mb)
+            .max_by_key(|m| m.min_ram_mb)
+    }
+
+    fn merge(&mut self, overrides: Vec<ModelEntry>) {
+        for entry in overrides {
+            if let Some(existing) = self.models.iter_mut().find(|m| m.name == entry.name) {
+                *existing = entry;
+            } else {
+                self.models.push(entry);
+            }
+        }
+    }
+
+    fn get_override_path() -> Result<PathBuf> {
+        let home_dir =
+            dirs::home_dir().ok_or_else(|| anyhow::anyhow!("Could not find home directory"))?;
+        Ok(home_dir.join(".commandy").join("models.toml"))
+    }
+}
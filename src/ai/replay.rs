@@ -0,0 +1,144 @@
+use serde::Deserialize;
+
+use crate::utils::LogEntry;
+
+/// Message of the log event recorded before each llama.cpp run
+pub const GENERATION_EVENT: &str = "llama.cpp generation";
+
+/// Message of the log event holding a run's raw output
+pub const RESPONSE_EVENT: &str = "llama.cpp response";
+
+/// Everything needed to re-run one llama.cpp generation exactly
+#[derive(Debug, Clone, Deserialize)]
+pub struct GenerationRecord {
+    /// Shared by every generation of one commandy run
+    pub session: String,
+    /// Order of the generation within its session
+    #[serde(default)]
+    pub sequence: u64,
+    #[serde(default)]
+    pub role: String,
+    pub model: String,
+    pub seed: u32,
+    pub max_tokens: u32,
+    /// Generation ends once this many whole commands are out; 0 runs to `max_tokens`
+    #[serde(default)]
+    pub stop_after: usize,
+    /// Text that ends generation; it and anything after it is dropped
+    #[serde(default, deserialize_with = "stop_sequences")]
+    pub stop: Vec<String>,
+    pub temperature: f32,
+    /// 0 uses the model's full context
+    #[serde(default)]
+    pub context_size: u32,
+    /// Empty when llama.cpp's default is used
+    #[serde(default)]
+    pub cache_type_k: String,
+    #[serde(default)]
+    pub cache_type_v: String,
+    /// `[model] device`, empty when llama.cpp chooses
+    #[serde(default)]
+    pub device: String,
+    /// llama.cpp prompt-cache file, empty when none is used
+    #[serde(default)]
+    pub prompt_cache: String,
+    /// Length of the system part at the start of `prompt`, 0 when it has none
+    #[serde(default)]
+    pub system_len: usize,
+    pub prompt: String,
+}
+
+impl GenerationRecord {
+    /// Byte offset of the first stop sequence in `output`
+    pub fn stop_position(&self, output: &str) -> Option<usize> {
+        self.stop
+            .iter()
+            .filter(|stop| !stop.is_empty())
+            .filter_map(|stop| output.find(stop.as_str()))
+            .min()
+    }
+}
+
+/// Stop sequences are logged as a JSON string; a plain list is accepted too
+fn stop_sequences<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<String>, D::Error> {
+    match serde_json::Value::deserialize(deserializer)? {
+        serde_json::Value::String(json) => {
+            serde_json::from_str(&json).map_err(serde::de::Error::custom)
+        }
+        value => serde_json::from_value(value).map_err(serde::de::Error::custom),
+    }
+}
+
+/// A recorded generation and, if it finished, what the model said
+#[derive(Debug, Clone)]
+pub struct RecordedGeneration {
+    pub record: GenerationRecord,
+    pub response: Option<String>,
+}
+
+impl RecordedGeneration {
+    /// Generations of a session, in the order they ran
+    pub fn find(entries: &[LogEntry], session: &str) -> Vec<Self> {
+        let in_session = |entry: &&LogEntry, message: &str| {
+            entry.message() == message
+                && entry.fields.get("session").and_then(|s| s.as_str()) == Some(session)
+        };
+
+        let mut generations: Vec<Self> = entries
+            .iter()
+            .filter(|entry| in_session(entry, GENERATION_EVENT))
+            .filter_map(|entry| {
+                serde_json::from_value(serde_json::Value::Object(entry.fields.clone())).ok()
+            })
+            .map(|record| Self {
+                record,
+                response: None,
+            })
+            .collect();
+
+        for entry in entries
+            .iter()
+            .filter(|entry| in_session(entry, RESPONSE_EVENT))
+        {
+            let sequence = entry.fields.get("sequence").and_then(|s| s.as_u64());
+            let response = entry.fields.get("response").and_then(|r| r.as_str());
+            if let (Some(sequence), Some(response)) = (sequence, response) {
+                if let Some(generation) = generations
+                    .iter_mut()
+                    .find(|g| g.record.sequence == sequence)
+                {
+                    generation.response = Some(response.to_string());
+                }
+            }
+        }
+
+        generations.sort_by_key(|g| g.record.sequence);
+        generations
+    }
+
+    /// Id of the most recent session that ran a generation
+    pub fn latest_session(entries: &[LogEntry]) -> Option<&str> {
+        entries
+            .iter()
+            .rev()
+            .filter(|entry| entry.message() == GENERATION_EVENT)
+            .find_map(|entry| entry.fields.get("session").and_then(|s| s.as_str()))
+    }
+
+    /// Log entries from a session's first generation up to the next session's
+    pub fn session_log<'a>(entries: &'a [LogEntry], session: &str) -> &'a [LogEntry] {
+        let in_session = |entry: &LogEntry| {
+            entry.fields.get("session").and_then(|s| s.as_str()) == Some(session)
+        };
+        let Some(start) = entries.iter().position(in_session) else {
+            return &[];
+        };
+        let end = entries[start..]
+            .iter()
+            .position(|e| e.message() == GENERATION_EVENT && !in_session(e))
+            .map_or(entries.len(), |offset| start + offset);
+        &entries[start..end]
+    }
+}
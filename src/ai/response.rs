@@ -1,5 +1,10 @@
+use std::collections::VecDeque;
+
 use crate::cli::Suggestion;
 
+/// Lines a group may grow to before it is assumed to be prose with a stray quote
+const MAX_GROUP_LINES: usize = 60;
+
 pub struct ResponseParser;
 
 impl Default for ResponseParser {
@@ -21,4 +26,354 @@ impl ResponseParser {
             .cloned()
             .collect()
     }
+
+    /// Splits a model response into commands, keeping multi-line ones together
+    ///
+    /// Lines are joined while a command is still open: a trailing `\`, `|`, `&&`
+    /// or `||`, an unclosed quote, a heredoc waiting for its terminator, or a
+    /// `for`/`while`/`until`/`if`/`case` block missing its closing keyword.
+    /// Only lines accepted by `starts_command` may open a group, so prose is
+    /// passed through one line at a time. Code fences are dropped, and a group
+    /// still open at the end of the response falls back to its separate lines.
+    pub fn group_commands(
+        &self,
+        response: &str,
+        starts_command: impl Fn(&str) -> bool,
+    ) -> Vec<String> {
+        let (mut commands, open) = Self::split_groups(response, starts_command);
+        if let Some(open) = open {
+            commands.extend(open.abandon());
+        }
+        commands
+    }
+
+    /// Whether the last command of a partial response is still waiting for lines
+    ///
+    /// True inside a heredoc, an unclosed quote or block, or after a trailing
+    /// `\`, `|`, `&&` or `||`, where more output would join the same command.
+    pub fn has_open_command(&self, response: &str, starts_command: impl Fn(&str) -> bool) -> bool {
+        Self::split_groups(response, starts_command).1.is_some()
+    }
+
+    /// Finished commands, and the group still open at the end of the response
+    fn split_groups(
+        response: &str,
+        starts_command: impl Fn(&str) -> bool,
+    ) -> (Vec<String>, Option<Group>) {
+        let mut commands = Vec::new();
+        let mut group: Option<Group> = None;
+
+        for raw in response.lines() {
+            let line = raw.trim_end_matches('\r');
+
+            if let Some(open) = group.as_mut() {
+                if open.in_heredoc() {
+                    open.push_heredoc_line(line);
+                    if open.is_complete() {
+                        commands.push(group.take().unwrap().finish());
+                    }
+                    continue;
+                }
+
+                let stray = line.trim().starts_with("```")
+                    || (line.trim().is_empty() && open.quote.is_some())
+                    || open.lines.len() >= MAX_GROUP_LINES;
+                if stray {
+                    // Whatever was open is not going to close; start over from this line
+                    commands.extend(group.take().unwrap().abandon());
+                } else {
+                    if !line.trim().is_empty() {
+                        open.push_line(line.trim_end());
+                    }
+                    if open.is_complete() {
+                        commands.push(group.take().unwrap().finish());
+                    }
+                    continue;
+                }
+            }
+
+            let line = line.trim();
+            if line.is_empty() || line.starts_with("```") {
+                continue;
+            }
+            if !starts_command(line) {
+                commands.push(line.to_string());
+                continue;
+            }
+
+            let mut open = Group::default();
+            open.push_line(line);
+            if open.is_complete() {
+                commands.push(open.finish());
+            } else {
+                group = Some(open);
+            }
+        }
+
+        (commands, group)
+    }
+
+    /// Splits a command after the line that starts its first heredoc
+    ///
+    /// The body is file content rather than shell, so rewrites should leave it alone.
+    /// Returns the whole command and an empty body when there is no heredoc.
+    pub fn split_heredoc(command: &str) -> (&str, &str) {
+        let mut group = Group::default();
+        let mut offset = 0;
+        for line in command.split_inclusive('\n') {
+            offset += line.len();
+            group.push_line(line.trim_end());
+            if group.in_heredoc() {
+                return command.split_at(offset);
+            }
+        }
+        (command, "")
+    }
+}
+
+/// Lines of one command that may still be open
+#[derive(Default)]
+struct Group {
+    lines: Vec<String>,
+    /// Quote left open at the end of the last line
+    quote: Option<char>,
+    /// Blocks opened by `for`/`while`/`until`/`if`/`case` and not yet closed
+    depth: i32,
+    /// Heredoc terminators still to come, and whether leading tabs are stripped
+    heredocs: VecDeque<(String, bool)>,
+    /// The last line asked for another with `\`, `|`, `&&` or `||`
+    continued: bool,
+}
+
+impl Group {
+    fn in_heredoc(&self) -> bool {
+        !self.heredocs.is_empty()
+    }
+
+    fn is_complete(&self) -> bool {
+        self.quote.is_none() && self.depth <= 0 && self.heredocs.is_empty() && !self.continued
+    }
+
+    fn finish(self) -> String {
+        self.lines.join("\n")
+    }
+
+    /// Returns the lines as separate commands, the way they were before grouping
+    fn abandon(self) -> Vec<String> {
+        self.lines
+            .into_iter()
+            .map(|line| line.trim().to_string())
+            .filter(|line| !line.is_empty())
+            .collect()
+    }
+
+    /// Heredoc bodies are kept verbatim and never scanned for syntax
+    fn push_heredoc_line(&mut self, line: &str) {
+        self.lines.push(line.to_string());
+        if let Some((terminator, strip_tabs)) = self.heredocs.front() {
+            let candidate = if *strip_tabs {
+                line.trim_start_matches('\t')
+            } else {
+                line
+            };
+            if candidate.trim_end() == terminator {
+                self.heredocs.pop_front();
+            }
+        }
+    }
+
+    fn push_line(&mut self, line: &str) {
+        self.lines.push(line.to_string());
+        self.scan(line);
+
+        let trimmed = line.trim_end();
+        self.continued = self.quote.is_none()
+            && (trimmed.ends_with('\\')
+                || trimmed.ends_with("&&")
+                || trimmed.ends_with("||")
+                || (trimmed.ends_with('|') && !trimmed.ends_with(";;")));
+    }
+
+    /// Tracks quotes, heredoc operators, and block keywords in command position
+    fn scan(&mut self, line: &str) {
+        let chars: Vec<char> = line.chars().collect();
+        let mut word = String::new();
+        let mut command_position = self.quote.is_none();
+        let mut i = 0;
+
+        while i < chars.len() {
+            let c = chars[i];
+
+            if let Some(q) = self.quote {
+                if c == '\\' && q == '"' {
+                    i += 2;
+                    continue;
+                }
+                if c == q {
+                    self.quote = None;
+                }
+                i += 1;
+                continue;
+            }
+
+            match c {
+                '\\' => {
+                    word.push(c);
+                    if let Some(&next) = chars.get(i + 1) {
+                        word.push(next);
+                    }
+                    i += 2;
+                    continue;
+                }
+                '\'' | '"' => {
+                    self.quote = Some(c);
+                    word.push(c);
+                }
+                '#' if word.is_empty() => break,
+                '<' if chars.get(i + 1) == Some(&'<') && chars.get(i + 2) != Some(&'<') => {
+                    let (terminator, strip_tabs, end) = Self::heredoc_terminator(&chars, i + 2);
+                    if let Some(terminator) = terminator {
+                        self.heredocs.push_back((terminator, strip_tabs));
+                    }
+                    self.end_word(&mut word, command_position);
+                    command_position = false;
+                    i = end;
+                    continue;
+                }
+                ';' | '&' | '|' | '(' | ')' => {
+                    self.end_word(&mut word, command_position);
+                    command_position = true;
+                }
+                c if c.is_whitespace() => {
+                    command_position = self.end_word(&mut word, command_position);
+                }
+                _ => word.push(c),
+            }
+            i += 1;
+        }
+
+        if self.quote.is_none() {
+            self.end_word(&mut word, command_position);
+        }
+    }
+
+    /// Counts a finished word, returning whether the next word is in command position
+    fn end_word(&mut self, word: &mut String, command_position: bool) -> bool {
+        if word.is_empty() {
+            return command_position;
+        }
+        let next_is_command = if command_position {
+            match word.as_str() {
+                "for" | "while" | "until" | "select" | "if" | "case" => {
+                    self.depth += 1;
+                    word.as_str() != "for" && word.as_str() != "case" && word.as_str() != "select"
+                }
+                "done" | "fi" | "esac" => {
+                    self.depth -= 1;
+                    false
+                }
+                "then" | "do" | "else" | "elif" | "{" | "!" | "time" => true,
+                _ => false,
+            }
+        } else {
+            false
+        };
+        word.clear();
+        next_is_command
+    }
+
+    /// Reads the word after `<<`, returning it unquoted with whether `-` was given
+    fn heredoc_terminator(chars: &[char], start: usize) -> (Option<String>, bool, usize) {
+        let mut i = start;
+        let strip_tabs = chars.get(i) == Some(&'-');
+        if strip_tabs {
+            i += 1;
+        }
+        while chars.get(i).is_some_and(|c| *c == ' ' || *c == '\t') {
+            i += 1;
+        }
+
+        let mut terminator = String::new();
+        let mut quote: Option<char> = None;
+        while let Some(&c) = chars.get(i) {
+            match (c, quote) {
+                ('\'' | '"', None) => quote = Some(c),
+                (c, Some(q)) if c == q => quote = None,
+                ('\\', None) => {}
+                (c, None)
+                    if c.is_whitespace() || matches!(c, ';' | '&' | '|' | '<' | '>' | ')') =>
+                {
+                    break
+                }
+                _ => terminator.push(c),
+            }
+            i += 1;
+        }
+
+        let terminator = (!terminator.is_empty()).then_some(terminator);
+        (terminator, strip_tabs, i)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ResponseParser;
+
+    fn group(response: &str) -> Vec<String> {
+        ResponseParser::new().group_commands(response, |line| !line.starts_with("Here"))
+    }
+
+    #[test]
+    fn one_command_per_line() {
+        assert_eq!(
+            group("Here are some commands:\n```bash\nls -la\n\ndu -sh .\n```"),
+            ["Here are some commands:", "ls -la", "du -sh ."]
+        );
+    }
+
+    #[test]
+    fn continuations_and_pipes() {
+        assert_eq!(
+            group("find . -name '*.log' \\\n  -mtime +7\nls"),
+            ["find . -name '*.log' \\\n  -mtime +7", "ls"]
+        );
+        assert_eq!(
+            group("ps aux |\n  grep nginx\nmake build &&\n  make test"),
+            ["ps aux |\n  grep nginx", "make build &&\n  make test"]
+        );
+    }
+
+    #[test]
+    fn heredocs_keep_their_body() {
+        let response = "cat > notes.txt <<'EOF'\nfirst line\n\n  indented\nEOF\nls";
+        assert_eq!(
+            group(response),
+            [
+                "cat > notes.txt <<'EOF'\nfirst line\n\n  indented\nEOF",
+                "ls"
+            ]
+        );
+        assert_eq!(
+            ResponseParser::split_heredoc("cat <<EOF\nbody\nEOF"),
+            ("cat <<EOF\n", "body\nEOF")
+        );
+    }
+
+    #[test]
+    fn blocks_and_quotes() {
+        assert_eq!(
+            group("for f in *.txt; do\n  wc -l \"$f\"\ndone\nls"),
+            ["for f in *.txt; do\n  wc -l \"$f\"\ndone", "ls"]
+        );
+        assert_eq!(group("echo 'one\ntwo'\nls"), ["echo 'one\ntwo'", "ls"]);
+    }
+
+    #[test]
+    fn unfinished_groups_fall_back_to_lines() {
+        // The quote never closes, so each line stands alone
+        assert_eq!(group("echo 'oops\nls -la"), ["echo 'oops", "ls -la"]);
+        let parser = ResponseParser::new();
+        assert!(parser.has_open_command("for f in *; do\n  echo $f", |_| true));
+        assert!(!parser.has_open_command("ls -la\n", |_| true));
+    }
 }
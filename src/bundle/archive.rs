@@ -0,0 +1,102 @@
+use anyhow::{bail, Context, Result};
+use std::io::{Read, Write};
+
+const BLOCK_SIZE: usize = 512;
+const NAME_LEN: usize = 100;
+
+/// Writes regular files into a ustar archive
+///
+/// Bundles only hold a few small files with short names, so this covers just that
+/// subset of the format rather than pulling in a tar dependency.
+pub struct TarWriter<W: Write> {
+    inner: W,
+}
+
+impl<W: Write> TarWriter<W> {
+    pub fn new(inner: W) -> Self {
+        Self { inner }
+    }
+
+    pub fn append(&mut self, name: &str, data: &[u8], mtime: u64) -> Result<()> {
+        if name.len() >= NAME_LEN {
+            bail!("Archive entry name too long: {name}");
+        }
+
+        let mut header = [0u8; BLOCK_SIZE];
+        header[..name.len()].copy_from_slice(name.as_bytes());
+        write_octal(&mut header[100..108], 0o644);
+        write_octal(&mut header[108..116], 0);
+        write_octal(&mut header[116..124], 0);
+        write_octal(&mut header[124..136], data.len() as u64);
+        write_octal(&mut header[136..148], mtime);
+        header[156] = b'0';
+        header[257..263].copy_from_slice(b"ustar\0");
+        header[263..265].copy_from_slice(b"00");
+
+        // The checksum is computed with its own field filled with spaces
+        header[148..156].fill(b' ');
+        let checksum: u64 = header.iter().map(|&b| b as u64).sum();
+        write_octal(&mut header[148..155], checksum);
+
+        self.inner.write_all(&header)?;
+        self.inner.write_all(data)?;
+        let padding = (BLOCK_SIZE - data.len() % BLOCK_SIZE) % BLOCK_SIZE;
+        self.inner.write_all(&vec![0u8; padding])?;
+        Ok(())
+    }
+
+    /// Writes the end-of-archive marker and returns the underlying writer
+    pub fn finish(mut self) -> Result<W> {
+        self.inner.write_all(&[0u8; BLOCK_SIZE * 2])?;
+        Ok(self.inner)
+    }
+}
+
+/// Reads every regular file from a ustar archive into memory
+pub fn read_entries<R: Read>(mut reader: R) -> Result<Vec<(String, Vec<u8>)>> {
+    let mut entries = Vec::new();
+    let mut header = [0u8; BLOCK_SIZE];
+
+    loop {
+        reader
+            .read_exact(&mut header)
+            .context("Truncated archive")?;
+        if header.iter().all(|&b| b == 0) {
+            break;
+        }
+
+        let name_end = header[..NAME_LEN]
+            .iter()
+            .position(|&b| b == 0)
+            .unwrap_or(NAME_LEN);
+        let name = String::from_utf8_lossy(&header[..name_end]).to_string();
+        let size = parse_octal(&header[124..136])
+            .with_context(|| format!("Invalid size for archive entry {name}"))?;
+
+        let mut data = vec![0u8; size as usize];
+        reader.read_exact(&mut data)?;
+        let padding = (BLOCK_SIZE - data.len() % BLOCK_SIZE) % BLOCK_SIZE;
+        reader.read_exact(&mut vec![0u8; padding])?;
+
+        // Directories, links, and extension headers are never written by commandy
+        if matches!(header[156], b'0' | 0) {
+            entries.push((name, data));
+        }
+    }
+
+    Ok(entries)
+}
+
+/// Zero-padded octal followed by a NUL, filling `field`
+fn write_octal(field: &mut [u8], value: u64) {
+    let digits = field.len() - 1;
+    let text = format!("{value:0digits$o}");
+    field[..digits].copy_from_slice(text.as_bytes());
+    field[digits] = 0;
+}
+
+fn parse_octal(field: &[u8]) -> Result<u64> {
+    let text = String::from_utf8_lossy(field);
+    let text = text.trim_matches(|c: char| c == '\0' || c == ' ');
+    Ok(u64::from_str_radix(text, 8)?)
+}
@@ -0,0 +1,136 @@
+use anyhow::{Context, Result};
+use chrono::NaiveDateTime;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+use crate::ai::{Fixture, RecordedGeneration};
+use crate::bundle::zip::ZipWriter;
+use crate::utils::{LogEntry, Redactor};
+
+/// Config keys whose values are masked whatever they hold
+const SECRET_KEYS: &[&str] = &[
+    "token",
+    "secret",
+    "password",
+    "auth",
+    "api_key",
+    "credential",
+];
+
+/// A zip of text files for attaching to a GitHub issue
+///
+/// Nothing is sent anywhere: the files are redacted as they are added, and the
+/// archive is written locally so it can be read before it's shared.
+#[derive(Default)]
+pub struct BugReport {
+    files: Vec<(String, String)>,
+}
+
+impl BugReport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a file with secrets, the home directory, and user and host names redacted
+    pub fn add(&mut self, name: &str, text: &str) {
+        self.files
+            .push((name.to_string(), Redactor::personal(text)));
+    }
+
+    /// Adds config.toml with the values of token, password, and similar keys masked
+    pub fn add_config(&mut self, text: &str) {
+        let config = match toml::from_str::<toml::Value>(text) {
+            Ok(mut value) => {
+                Self::mask_secrets(&mut value);
+                toml::to_string_pretty(&value).unwrap_or_default()
+            }
+            Err(e) => format!("# config.toml does not parse: {e}\n"),
+        };
+        self.add("config.toml", &config);
+    }
+
+    /// Adds the session's log lines, one entry per line
+    pub fn add_log(&mut self, entries: &[LogEntry]) {
+        let lines: Vec<String> = entries
+            .iter()
+            .map(|entry| {
+                let extra = entry.extra_fields();
+                format!(
+                    "{} {:5} {}: {} {extra}",
+                    entry.timestamp,
+                    entry.level,
+                    entry.target,
+                    entry.message()
+                )
+                .trim_end()
+                .to_string()
+            })
+            .collect();
+        self.add("session.log", &lines.join("\n"));
+    }
+
+    /// Adds each generation that has a response as a mock fixture, so the
+    /// session can be reproduced with `COMMANDY_BACKEND=mock`
+    pub fn add_fixtures(&mut self, generations: &[RecordedGeneration]) -> Result<()> {
+        for (i, generation) in generations.iter().enumerate() {
+            let Some(response) = &generation.response else {
+                continue;
+            };
+            let request = generation
+                .record
+                .prompt
+                .lines()
+                .rev()
+                .find_map(|line| line.strip_prefix("Request:"))
+                .map(|request| format!("Request:{request}"));
+            let fixture = Fixture {
+                model: Some(generation.record.model.clone()),
+                prompt: None,
+                pattern: request,
+                response: response.clone(),
+            };
+            self.add(
+                &format!("fixtures/{:02}.json", i + 1),
+                &serde_json::to_string_pretty(&fixture)?,
+            );
+        }
+        Ok(())
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.files.iter().map(|(name, _)| name.as_str())
+    }
+
+    pub fn write(&self, path: &Path, created: NaiveDateTime) -> Result<()> {
+        let file =
+            File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+        let mut zip = ZipWriter::new(BufWriter::new(file), created);
+        for (name, text) in &self.files {
+            zip.append(name, text.as_bytes())?;
+        }
+        zip.finish()?.into_inner()?;
+        Ok(())
+    }
+
+    fn mask_secrets(value: &mut toml::Value) {
+        match value {
+            toml::Value::Table(table) => {
+                for (key, value) in table.iter_mut() {
+                    let key = key.to_lowercase();
+                    match value {
+                        toml::Value::String(text)
+                            if !text.is_empty()
+                                && SECRET_KEYS.iter().any(|secret| key.contains(secret)) =>
+                        {
+                            *text = "***".to_string()
+                        }
+                        _ => Self::mask_secrets(value),
+                    }
+                }
+            }
+            toml::Value::Array(items) => items.iter_mut().for_each(Self::mask_secrets),
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,31 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Bumped when the layout of a bundle changes incompatibly
+pub const BUNDLE_FORMAT_VERSION: u32 = 1;
+
+pub const MANIFEST_ENTRY: &str = "manifest.json";
+pub const SETTINGS_ENTRY: &str = "config.toml";
+pub const LEARNING_ENTRY: &str = "learning.json";
+pub const CACHE_ENTRY: &str = "cache.json";
+
+/// Describes what a bundle holds and which commandy wrote it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundleManifest {
+    pub format_version: u32,
+    pub commandy_version: String,
+    pub created_at: DateTime<Utc>,
+    /// Archive entries besides the manifest
+    pub entries: Vec<String>,
+}
+
+impl BundleManifest {
+    pub fn new(entries: Vec<String>) -> Self {
+        Self {
+            format_version: BUNDLE_FORMAT_VERSION,
+            commandy_version: env!("CARGO_PKG_VERSION").to_string(),
+            created_at: Utc::now(),
+            entries,
+        }
+    }
+}
@@ -0,0 +1,11 @@
+pub mod archive;
+pub mod bugreport;
+pub mod manifest;
+pub mod seed;
+pub mod transfer;
+pub mod zip;
+
+pub use bugreport::BugReport;
+pub use manifest::{BundleManifest, BUNDLE_FORMAT_VERSION};
+pub use seed::{SeedCache, SeedExport, SEED_FORMAT_VERSION};
+pub use transfer::{Bundle, SettingsOutcome};
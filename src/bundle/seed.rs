@@ -0,0 +1,133 @@
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+use crate::ai::ModelIdentity;
+use crate::context::{CacheManager, CachedSuggestion};
+use crate::utils::Redactor;
+
+/// Bumped when the layout of a seed cache changes incompatibly
+pub const SEED_FORMAT_VERSION: u32 = 1;
+
+/// Cached suggestions shared from one machine to others, e.g. a team's common
+/// tasks, as reviewable JSON
+///
+/// Cached answers are only good for the model that produced them, so the file
+/// names that model and is only imported where the same one is configured.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SeedCache {
+    pub format_version: u32,
+    pub commandy_version: String,
+    pub created_at: DateTime<Utc>,
+    pub model: ModelIdentity,
+    pub suggestions: Vec<CachedSuggestion>,
+}
+
+/// A seed cache and how many cached suggestions were left out of it
+pub struct SeedExport {
+    pub seed: SeedCache,
+    pub withheld: usize,
+}
+
+impl SeedCache {
+    /// The suggestions `model` made that have worked at least half the time
+    /// and been used `min_uses` times, sanitized for sharing
+    pub fn collect(
+        cache: &CacheManager,
+        model: ModelIdentity,
+        min_uses: u32,
+    ) -> Result<SeedExport> {
+        let all = cache.export_suggestions()?;
+        let total = all.len();
+        let suggestions: Vec<CachedSuggestion> = all
+            .into_iter()
+            .filter(|s| s.model == model.fingerprint)
+            .filter(|s| s.use_count >= min_uses && s.success_count * 2 >= s.use_count)
+            .filter_map(|s| Self::sanitize(&s))
+            .collect();
+
+        Ok(SeedExport {
+            withheld: total - suggestions.len(),
+            seed: Self {
+                format_version: SEED_FORMAT_VERSION,
+                commandy_version: env!("CARGO_PKG_VERSION").to_string(),
+                created_at: Utc::now(),
+                model,
+                suggestions,
+            },
+        })
+    }
+
+    /// The suggestion with the home directory as `~`, or `None` when it can't
+    /// be shared: it holds a secret, names this user or host, or answers a
+    /// request about attached files
+    fn sanitize(suggestion: &CachedSuggestion) -> Option<CachedSuggestion> {
+        let attached = Regex::new(r"\[[^\]]* [0-9a-f]{12}\]$").expect("valid regex");
+        if attached.is_match(&suggestion.prompt) {
+            return None;
+        }
+        for text in [&suggestion.prompt, &suggestion.command] {
+            if Redactor::secrets(text) != *text {
+                return None;
+            }
+        }
+
+        let command = Redactor::personal(&suggestion.command);
+        let personal = |text: &str| text.contains("<user>") || text.contains("<host>");
+        if personal(&command) && !personal(&suggestion.command) {
+            return None;
+        }
+
+        Some(CachedSuggestion {
+            prompt: Redactor::personal(&suggestion.prompt),
+            command,
+            explanation: suggestion.explanation.as_deref().map(Redactor::personal),
+            // The seed names its model once, for all of them
+            model: String::new(),
+            ..suggestion.clone()
+        })
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        fs::write(path, serde_json::to_vec_pretty(self)?)
+            .with_context(|| format!("Failed to write seed cache {}", path.display()))
+    }
+
+    pub fn read(path: &Path) -> Result<Self> {
+        let data = fs::read(path)
+            .with_context(|| format!("Failed to read seed cache {}", path.display()))?;
+        let seed: Self = serde_json::from_slice(&data)
+            .with_context(|| format!("{} is not a commandy seed cache", path.display()))?;
+        if seed.format_version > SEED_FORMAT_VERSION {
+            bail!(
+                "Seed cache format {} was written by commandy {}; update commandy to import it",
+                seed.format_version,
+                seed.commandy_version
+            );
+        }
+        Ok(seed)
+    }
+
+    /// Fails unless the seed was made with `model`, the one configured here
+    pub fn check_model(&self, model: &ModelIdentity) -> Result<()> {
+        if self.model == *model {
+            return Ok(());
+        }
+        if self.model.model == model.model {
+            bail!(
+                "Seed cache was made with another download of {} ({}, here {}), such as a different quantization; use --any-model to import it anyway",
+                model.model,
+                self.model.fingerprint,
+                model.fingerprint
+            );
+        }
+        bail!(
+            "Seed cache was made with {}, but {} is configured; use --any-model to import it anyway",
+            self.model.model,
+            model.model
+        )
+    }
+}
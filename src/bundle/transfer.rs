@@ -0,0 +1,195 @@
+use anyhow::{bail, Context, Result};
+use chrono::Utc;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::path::Path;
+
+use crate::bundle::archive::{self, TarWriter};
+use crate::bundle::manifest::{
+    BundleManifest, BUNDLE_FORMAT_VERSION, CACHE_ENTRY, LEARNING_ENTRY, MANIFEST_ENTRY,
+    SETTINGS_ENTRY,
+};
+use crate::context::{CacheManager, CachedSuggestion, LearnedPattern, LearningStore};
+
+/// zstd level for bundles; they are small, so favour ratio over speed
+const COMPRESSION_LEVEL: i32 = 10;
+
+/// What happened to the local config.toml on import
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SettingsOutcome {
+    /// There was no local config, so the bundle's was installed
+    Installed,
+    /// Keys missing locally were added; existing values were kept
+    Merged,
+    /// The local config was replaced (and backed up)
+    Replaced,
+    /// The bundle has no settings
+    Absent,
+}
+
+/// Settings, learned context, and optionally the cache, packed into a `.tar.zst` archive
+pub struct Bundle {
+    pub manifest: BundleManifest,
+    entries: HashMap<String, Vec<u8>>,
+}
+
+impl Bundle {
+    /// Collects the current machine's personalization
+    pub fn collect(
+        config_path: &Path,
+        learning: Option<&LearningStore>,
+        cache: Option<&CacheManager>,
+    ) -> Result<Self> {
+        let mut entries = HashMap::new();
+
+        if config_path.exists() {
+            entries.insert(SETTINGS_ENTRY.to_string(), fs::read(config_path)?);
+        }
+        if let Some(learning) = learning {
+            let patterns = learning.patterns()?;
+            entries.insert(
+                LEARNING_ENTRY.to_string(),
+                serde_json::to_vec_pretty(&patterns)?,
+            );
+        }
+        if let Some(cache) = cache {
+            let suggestions = cache.export_suggestions()?;
+            entries.insert(
+                CACHE_ENTRY.to_string(),
+                serde_json::to_vec_pretty(&suggestions)?,
+            );
+        }
+
+        let mut names: Vec<String> = entries.keys().cloned().collect();
+        names.sort();
+        Ok(Self {
+            manifest: BundleManifest::new(names),
+            entries,
+        })
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let file = File::create(path)
+            .with_context(|| format!("Failed to create bundle {}", path.display()))?;
+        let encoder = zstd::Encoder::new(file, COMPRESSION_LEVEL)?;
+        let mut tar = TarWriter::new(encoder);
+
+        let mtime = Utc::now().timestamp().max(0) as u64;
+        tar.append(
+            MANIFEST_ENTRY,
+            &serde_json::to_vec_pretty(&self.manifest)?,
+            mtime,
+        )?;
+        for name in &self.manifest.entries {
+            tar.append(name, &self.entries[name], mtime)?;
+        }
+
+        tar.finish()?.finish()?;
+        Ok(())
+    }
+
+    pub fn read(path: &Path) -> Result<Self> {
+        let file = File::open(path)
+            .with_context(|| format!("Failed to open bundle {}", path.display()))?;
+        let decoder = zstd::Decoder::new(file)?;
+        let mut entries: HashMap<String, Vec<u8>> =
+            archive::read_entries(decoder)?.into_iter().collect();
+
+        let manifest: BundleManifest = entries
+            .remove(MANIFEST_ENTRY)
+            .context("Not a commandy bundle: manifest.json is missing")
+            .and_then(|data| Ok(serde_json::from_slice(&data)?))?;
+        if manifest.format_version > BUNDLE_FORMAT_VERSION {
+            bail!(
+                "Bundle format {} was written by commandy {}; update commandy to import it",
+                manifest.format_version,
+                manifest.commandy_version
+            );
+        }
+
+        Ok(Self { manifest, entries })
+    }
+
+    pub fn has_cache(&self) -> bool {
+        self.entries.contains_key(CACHE_ENTRY)
+    }
+
+    /// Applies the bundle's settings, keeping local values unless `replace` is set
+    pub fn apply_settings(
+        &self,
+        config_path: &Path,
+        backup_dir: &Path,
+        replace: bool,
+    ) -> Result<SettingsOutcome> {
+        let Some(incoming) = self.entries.get(SETTINGS_ENTRY) else {
+            return Ok(SettingsOutcome::Absent);
+        };
+        let incoming =
+            String::from_utf8(incoming.clone()).context("Bundle settings are not UTF-8")?;
+        let incoming_table: toml::Table =
+            toml::from_str(&incoming).context("Bundle settings are not valid TOML")?;
+
+        if !config_path.exists() {
+            if let Some(parent) = config_path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(config_path, incoming)?;
+            return Ok(SettingsOutcome::Installed);
+        }
+
+        // Keep a copy of what was there before touching it
+        fs::create_dir_all(backup_dir)?;
+        let timestamp = Utc::now().format("%Y%m%d_%H%M%S");
+        fs::copy(
+            config_path,
+            backup_dir.join(format!("config_{timestamp}.toml")),
+        )?;
+
+        if replace {
+            fs::write(config_path, incoming)?;
+            return Ok(SettingsOutcome::Replaced);
+        }
+
+        let mut local: toml::Table = toml::from_str(&fs::read_to_string(config_path)?)?;
+        merge_missing(&mut local, incoming_table);
+        fs::write(config_path, toml::to_string_pretty(&local)?)?;
+        Ok(SettingsOutcome::Merged)
+    }
+
+    /// Merges learned patterns into the store, returning how many were read
+    pub fn import_learning(&self, learning: &LearningStore) -> Result<usize> {
+        match self.entries.get(LEARNING_ENTRY) {
+            Some(data) => {
+                let patterns: Vec<LearnedPattern> = serde_json::from_slice(data)?;
+                learning.import_patterns(&patterns)
+            }
+            None => Ok(0),
+        }
+    }
+
+    /// Merges cached suggestions, returning how many were read
+    pub fn import_cache(&self, cache: &mut CacheManager) -> Result<usize> {
+        match self.entries.get(CACHE_ENTRY) {
+            Some(data) => {
+                let suggestions: Vec<CachedSuggestion> = serde_json::from_slice(data)?;
+                cache.import_suggestions(&suggestions)
+            }
+            None => Ok(0),
+        }
+    }
+}
+
+/// Adds keys from `incoming` that `local` lacks, recursing into tables
+fn merge_missing(local: &mut toml::Table, incoming: toml::Table) {
+    for (key, value) in incoming {
+        match (local.get_mut(&key), value) {
+            (Some(toml::Value::Table(local_table)), toml::Value::Table(incoming_table)) => {
+                merge_missing(local_table, incoming_table);
+            }
+            (Some(_), _) => {}
+            (None, value) => {
+                local.insert(key, value);
+            }
+        }
+    }
+}
@@ -0,0 +1,119 @@
+use anyhow::{bail, Result};
+use chrono::{Datelike, NaiveDateTime, Timelike};
+use std::io::Write;
+
+const LOCAL_HEADER: u32 = 0x0403_4b50;
+const CENTRAL_HEADER: u32 = 0x0201_4b50;
+const END_OF_DIRECTORY: u32 = 0x0605_4b50;
+
+/// Version 2.0, the first with directories and the stored method
+const VERSION: u16 = 20;
+
+/// Names are UTF-8
+const UTF8_NAMES: u16 = 1 << 11;
+
+/// Writes files uncompressed into a zip archive
+///
+/// Bug reports are attached to GitHub issues, which take zip files but not
+/// tar.zst bundles. They're a few text files, so storing them uncompressed
+/// keeps this to the CRC and the headers.
+pub struct ZipWriter<W: Write> {
+    inner: W,
+    offset: u32,
+    central: Vec<u8>,
+    entries: u16,
+    modified: (u16, u16),
+}
+
+impl<W: Write> ZipWriter<W> {
+    /// Every entry gets `modified` as its time stamp
+    pub fn new(inner: W, modified: NaiveDateTime) -> Self {
+        Self {
+            inner,
+            offset: 0,
+            central: Vec::new(),
+            entries: 0,
+            modified: dos_time(modified),
+        }
+    }
+
+    pub fn append(&mut self, name: &str, data: &[u8]) -> Result<()> {
+        let (Ok(name_len), Ok(size)) = (u16::try_from(name.len()), u32::try_from(data.len()))
+        else {
+            bail!("Archive entry too large: {name}");
+        };
+        let crc = crc32(data);
+        let (time, date) = self.modified;
+
+        let mut local = Vec::with_capacity(30 + name.len());
+        local.extend(LOCAL_HEADER.to_le_bytes());
+        for field in [VERSION, UTF8_NAMES, 0, time, date] {
+            local.extend(field.to_le_bytes());
+        }
+        for field in [crc, size, size] {
+            local.extend(field.to_le_bytes());
+        }
+        local.extend(name_len.to_le_bytes());
+        local.extend(0u16.to_le_bytes());
+        local.extend(name.as_bytes());
+
+        self.central.extend(CENTRAL_HEADER.to_le_bytes());
+        for field in [VERSION, VERSION, UTF8_NAMES, 0, time, date] {
+            self.central.extend(field.to_le_bytes());
+        }
+        for field in [crc, size, size] {
+            self.central.extend(field.to_le_bytes());
+        }
+        // Name, extra field, and comment lengths, disk, and attributes
+        for field in [name_len, 0, 0, 0, 0] {
+            self.central.extend(field.to_le_bytes());
+        }
+        self.central.extend(0u32.to_le_bytes());
+        self.central.extend(self.offset.to_le_bytes());
+        self.central.extend(name.as_bytes());
+
+        self.inner.write_all(&local)?;
+        self.inner.write_all(data)?;
+        self.offset += (local.len() + data.len()) as u32;
+        self.entries += 1;
+        Ok(())
+    }
+
+    /// Writes the central directory and returns the underlying writer
+    pub fn finish(mut self) -> Result<W> {
+        self.inner.write_all(&self.central)?;
+        let mut end = Vec::with_capacity(22);
+        end.extend(END_OF_DIRECTORY.to_le_bytes());
+        for field in [0, 0, self.entries, self.entries] {
+            end.extend(field.to_le_bytes());
+        }
+        end.extend((self.central.len() as u32).to_le_bytes());
+        end.extend(self.offset.to_le_bytes());
+        end.extend(0u16.to_le_bytes());
+        self.inner.write_all(&end)?;
+        Ok(self.inner)
+    }
+}
+
+/// MS-DOS time and date, which start in 1980 and count seconds in twos
+fn dos_time(time: NaiveDateTime) -> (u16, u16) {
+    let year = time.year().clamp(1980, 2107) as u16;
+    let clock = (time.hour() << 11 | time.minute() << 5 | (time.second() / 2)) as u16;
+    let date = (year - 1980) << 9 | (time.month() as u16) << 5 | time.day() as u16;
+    (clock, date)
+}
+
+/// CRC-32 as zip uses it (IEEE, reflected)
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = match crc & 1 {
+                1 => (crc >> 1) ^ 0xEDB8_8320,
+                _ => crc >> 1,
+            };
+        }
+    }
+    !crc
+}
@@ -1,4 +1,7 @@
 use clap::{Parser, Subcommand};
+use std::path::PathBuf;
+
+use crate::context::Attachment;
 
 #[derive(Parser)]
 #[command(name = "commandy")]
@@ -16,17 +19,89 @@ pub struct Cli {
     #[arg(short, long)]
     pub explain: bool,
 
-    /// Number of suggestions to show
-    #[arg(short = 'n', long, default_value = "3")]
-    pub suggestions: usize,
+    /// Number of suggestions to show [default: [output] max_suggestions]
+    #[arg(short = 'n', long)]
+    pub suggestions: Option<usize>,
 
     /// Skip cache and force fresh inference
     #[arg(long)]
     pub no_cache: bool,
 
+    /// Include this file's contents as context (repeatable)
+    #[arg(short = 'f', long = "file", value_name = "PATH")]
+    pub files: Vec<PathBuf>,
+
+    /// Paste a block of text (an error, a config snippet) as context, ended with Ctrl-D
+    #[arg(long)]
+    pub paste: bool,
+
     /// Verbose output
     #[arg(short, long)]
     pub verbose: bool,
+
+    /// Plain line-oriented output without colors, spinners, or full-screen menus
+    #[arg(long, global = true)]
+    pub plain: bool,
+
+    /// Regenerate suggestions live as the query is typed
+    #[arg(short, long)]
+    pub watch: bool,
+
+    /// Send the selected command to the terminal with OSC 52 (works over SSH and in tmux)
+    #[arg(long)]
+    pub emit_osc: bool,
+
+    /// Show a diff and ask before running a command that edits files in place
+    #[arg(long)]
+    pub confirm_edits: bool,
+
+    /// Run the chosen command in a throwaway container first, then ask before running it here
+    #[arg(long)]
+    pub sandbox: bool,
+
+    /// Download the script a `curl | sh` command would run and show it before running the saved copy
+    #[arg(long)]
+    pub unpack_scripts: bool,
+
+    /// Suggest commands for this SSH host and run the chosen one there
+    #[arg(long, value_name = "USER@HOST")]
+    pub target: Option<String>,
+
+    /// Never run, copy, save, or learn anything: for demos and untrusted automation
+    #[arg(long, global = true)]
+    pub read_only: bool,
+
+    /// Apply the execution limits of this `[profiles]` entry
+    #[arg(long, global = true, env = "COMMANDY_PROFILE", value_name = "NAME")]
+    pub profile: Option<String>,
+
+    /// Write logs to this file instead of ~/.commandy/logs
+    #[arg(long, global = true, value_name = "PATH")]
+    pub log_file: Option<PathBuf>,
+
+    /// Take the Nth suggestion (1 is the top one) without showing the picker
+    #[arg(long, value_name = "N")]
+    pub pick: Option<usize>,
+
+    /// Print the chosen suggestion, and nothing else, to stdout instead of running it
+    #[arg(long)]
+    pub print_only: bool,
+
+    /// Run the suggestion taken with --pick, which is otherwise only printed
+    #[arg(long, requires = "pick")]
+    pub execute: bool,
+
+    /// Print only the chosen command, or nothing, and report how it went in the exit code
+    #[arg(short, long)]
+    pub quiet: bool,
+
+    /// Print the top suggestion for another commandy to refine, as in `commandy --pipe ... | commandy ...`
+    #[arg(long, conflicts_with_all = ["pick", "print_only", "quiet"])]
+    pub pipe: bool,
+
+    /// Give up on generating suggestions after this many seconds
+    #[arg(long, value_name = "SECS")]
+    pub timeout: Option<u64>,
 }
 
 #[derive(Subcommand)]
@@ -41,6 +116,211 @@ pub enum Commands {
         /// Update the binary
         #[arg(long)]
         binary: bool,
+        /// llama.cpp build to install and switch to: cpu, avx2, cuda, metal, or vulkan
+        #[arg(long, requires = "binary")]
+        flavor: Option<String>,
+        /// Compile llama.cpp from source, for platforms without a published build
+        #[arg(long, requires = "binary")]
+        build: bool,
+    },
+    /// Manage models
+    Model {
+        #[command(subcommand)]
+        action: ModelCommands,
+    },
+    /// Search shell and commandy history for a previously run command
+    Recall {
+        /// Description of the command you are looking for
+        query: String,
+        /// Maximum number of matches to show
+        #[arg(long, default_value = "5")]
+        limit: usize,
+    },
+    /// Manage team-shared pattern packs
+    Packs {
+        #[command(subcommand)]
+        action: PackCommands,
+    },
+    /// Pre-generate suggestions for frequent requests
+    Cache {
+        #[command(subcommand)]
+        action: CacheCommands,
+    },
+    /// Pin a command to the top of the suggestions for matching requests
+    Pin {
+        #[command(subcommand)]
+        action: PinCommands,
+    },
+    /// Export what commandy has learned as plain text
+    Learned {
+        /// Write to this file instead of stdout
+        #[arg(long)]
+        out: Option<PathBuf>,
+    },
+    /// Pack settings and learned context into a bundle for another machine
+    Export {
+        /// Bundle to write, e.g. commandy.tar.zst
+        #[arg(long)]
+        out: PathBuf,
+        /// Also include the suggestion cache
+        #[arg(long)]
+        include_cache: bool,
+    },
+    /// Merge a bundle written by `commandy export` into this machine
+    Import {
+        /// Bundle to read
+        bundle: PathBuf,
+        /// Replace config.toml instead of only adding settings missing here
+        #[arg(long)]
+        replace_settings: bool,
+    },
+    /// Score a model against a benchmark of queries and expected commands
+    Eval {
+        /// Model to evaluate instead of the configured one
+        #[arg(long)]
+        model: Option<String>,
+        /// TOML dataset to use instead of the bundled benchmark
+        #[arg(long, value_name = "PATH")]
+        dataset: Option<PathBuf>,
+        /// Only run the first N queries
+        #[arg(long)]
+        limit: Option<usize>,
+        /// Print the full report as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Suggest commands for every task in a file, one task per line
+    Batch {
+        /// File of tasks; `-` reads stdin. Blank lines and `#` comments are skipped
+        file: PathBuf,
+        /// Write a JSON report or an annotated shell script
+        #[arg(long, default_value = "json", value_parser = ["json", "script"])]
+        output: String,
+        /// Number of suggestions per task
+        #[arg(short = 'n', long, default_value = "3")]
+        suggestions: usize,
+        /// Include an explanation for each command
+        #[arg(long)]
+        explain: bool,
+    },
+    /// Ask for commands in a conversation; with --session it is saved and can be resumed
+    Chat {
+        /// Name to save the conversation under, or of one to resume
+        #[arg(long, value_name = "NAME")]
+        session: Option<String>,
+        /// Number of suggestions to show per request
+        #[arg(short = 'n', long, default_value = "3")]
+        suggestions: usize,
+    },
+    /// Work with the current shell session's history and named chat sessions
+    Session {
+        #[command(subcommand)]
+        action: SessionCommands,
+    },
+    /// Show or check the log of commands commandy has run
+    Audit {
+        #[command(subcommand)]
+        action: AuditCommands,
+    },
+    /// Re-run the exact generations of an earlier run from the logs
+    Replay {
+        /// Session id shown by --verbose and in `doctor --logs`
+        session_id: String,
+    },
+    /// Write a redacted zip of the last session's log, the config, and diagnostics
+    /// to attach to a bug report
+    Bugreport {
+        /// Session to report instead of the most recent one
+        #[arg(long)]
+        session: Option<String>,
+        /// Zip to write; defaults to commandy-bugreport-<time>.zip here
+        #[arg(long)]
+        out: Option<PathBuf>,
+    },
+    /// Fix the previous (or given) command line
+    Oops {
+        /// The command to fix; defaults to the last command in shell history
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        command: Vec<String>,
+    },
+    /// Explain what a command does (explanations are cached per command)
+    Explain {
+        /// The command to explain
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true, required = true)]
+        command: Vec<String>,
+    },
+    /// Check a command for risks and explain it before running or pasting it
+    Inspect {
+        /// Inspect the text on the clipboard
+        #[arg(long)]
+        clipboard: bool,
+        /// For `curl | sh` commands, download the script, show it, and offer to run the saved copy
+        #[arg(long)]
+        unpack: bool,
+        /// The command to inspect
+        #[arg(
+            trailing_var_arg = true,
+            allow_hyphen_values = true,
+            required_unless_present = "clipboard",
+            conflicts_with = "clipboard"
+        )]
+        command: Vec<String>,
+    },
+    /// Rewrite a command written for one shell in another shell's syntax
+    Translate {
+        /// Shell the command is written for
+        #[arg(long, default_value = "bash", value_parser = ["bash", "zsh", "sh", "fish", "powershell", "pwsh"])]
+        from: String,
+        /// Shell to translate to; defaults to the current shell
+        #[arg(long, value_parser = ["bash", "zsh", "sh", "fish", "powershell", "pwsh"])]
+        to: Option<String>,
+        /// The command to translate
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true, required = true)]
+        command: Vec<String>,
+    },
+    /// Generate commands from a `[templates]` prompt: commandy run deploy env=staging
+    Run {
+        /// Template name; lists the templates when omitted
+        template: Option<String>,
+        /// Variables as key=value
+        vars: Vec<String>,
+    },
+    /// Print shell integration code: eval "$(commandy hook zsh)"
+    Hook {
+        /// Shell to integrate with
+        #[arg(value_parser = ["bash", "zsh", "fish"])]
+        shell: String,
+    },
+    /// Serve a JSON API for editor integrations
+    Serve {
+        /// Port to listen on
+        #[arg(long, default_value = "7878")]
+        port: u16,
+        /// Address to bind; others than localhost need `[server] allow_remote`
+        #[arg(long, default_value = "127.0.0.1")]
+        host: String,
+        /// Token for `Authorization: Bearer <token>`; defaults to ~/.commandy/auth-token
+        #[arg(long, env = "COMMANDY_SERVE_TOKEN")]
+        token: Option<String>,
+    },
+    /// Keep llama-server running in the background, restarting it when it hangs or leaks
+    Daemon {
+        #[command(subcommand)]
+        action: DaemonCommands,
+    },
+    /// Show request and queue metrics from a running `commandy serve`
+    Stats {
+        #[command(subcommand)]
+        view: Option<StatsCommands>,
+        /// Port the server listens on
+        #[arg(long, default_value = "7878")]
+        port: u16,
+        /// Address the server listens on
+        #[arg(long, default_value = "127.0.0.1")]
+        host: String,
+        /// Bearer token; defaults to ~/.commandy/auth-token
+        #[arg(long, env = "COMMANDY_SERVE_TOKEN")]
+        token: Option<String>,
     },
     /// Show configuration
     Config,
@@ -54,17 +334,187 @@ pub enum Commands {
         context: bool,
     },
     /// Run diagnostics
-    Doctor,
+    Doctor {
+        /// Show recent warnings and errors from the log files
+        #[arg(long)]
+        logs: bool,
+    },
+    /// Update commandy itself to the latest release
+    SelfUpdate {
+        /// Only check whether an update is available
+        #[arg(long)]
+        check: bool,
+    },
     /// Show version information
     Version,
 }
 
+#[derive(Subcommand)]
+pub enum ModelCommands {
+    /// List installed models
+    List {
+        /// List recommended models from the registry instead
+        #[arg(long)]
+        available: bool,
+    },
+    /// Switch to a model by registry name or Hugging Face repo
+    Use {
+        /// Registry name (e.g. gemma-3-1b) or repo (e.g. ggml-org/gemma-3-1b-it-GGUF)
+        name: String,
+        /// Set the fast model, which categorizes requests, instead of the main model
+        #[arg(long)]
+        fast: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum DaemonCommands {
+    /// Start the daemon in the background
+    Start,
+    /// Stop the daemon and its llama-server
+    Stop,
+    /// Restart llama-server
+    Restart,
+    /// Show whether llama-server is healthy, its memory, and its restarts
+    Status,
+    /// Show llama-server's output and the daemon's restarts
+    Logs {
+        /// Lines to show
+        #[arg(short = 'n', long, default_value = "40")]
+        lines: usize,
+    },
+    /// Supervise llama-server in the foreground (what `start` runs)
+    Run,
+}
+
+#[derive(Subcommand)]
+pub enum SessionCommands {
+    /// Write the commands run in this session, with explanations and exit codes, as a runbook
+    Export {
+        /// Markdown document or shell script
+        #[arg(long, default_value = "markdown", value_parser = ["markdown", "script"])]
+        format: String,
+        /// Session to export instead of the current one ($COMMANDY_SESSION): a shell
+        /// session id or the name of a chat session
+        #[arg(long)]
+        id: Option<String>,
+        /// Write to this file instead of stdout
+        #[arg(long)]
+        out: Option<PathBuf>,
+    },
+    /// List named chat sessions
+    List,
+    /// Delete a named chat session
+    Delete {
+        /// Name given to `commandy chat --session`
+        name: String,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum AuditCommands {
+    /// Print the most recent records
+    Show {
+        /// Number of records to show
+        #[arg(long, default_value = "20")]
+        limit: usize,
+        /// Print records as JSON lines, as stored
+        #[arg(long)]
+        json: bool,
+    },
+    /// Check that no record was changed, removed, or reordered
+    Verify,
+}
+
+#[derive(Subcommand)]
+pub enum PackCommands {
+    /// List installed packs
+    List,
+    /// Install or update a pack from a file path or URL
+    Add {
+        /// Path or http(s) URL of the pack's .toml file
+        source: String,
+    },
+    /// Uninstall a pack
+    Remove {
+        /// Pack name as shown by `commandy packs list`
+        name: String,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum StatsCommands {
+    /// Compare how often each arm of an [experiment] prompt experiment was used
+    Experiments,
+}
+
+#[derive(Subcommand)]
+pub enum CacheCommands {
+    /// Generate and cache suggestions for frequent requests, so they are answered instantly
+    Warm {
+        /// Read the requests from this file, one per line, instead (- for stdin)
+        #[arg(long, value_name = "PATH")]
+        file: Option<PathBuf>,
+        /// How many of the most frequent requests to generate [default: [cache] warm_top]
+        #[arg(long)]
+        top: Option<usize>,
+    },
+    /// Write the cache, without secrets or personal details, as a seed for other machines
+    Export {
+        /// Seed file to write, e.g. team-seed.json
+        #[arg(long)]
+        out: PathBuf,
+        /// Leave out suggestions used fewer times than this
+        #[arg(long, default_value_t = 0)]
+        min_uses: u32,
+    },
+    /// Merge a seed cache written by `commandy cache export`
+    Import {
+        /// Seed file to read
+        seed: PathBuf,
+        /// Import even if the seed was made with a different model
+        #[arg(long)]
+        any_model: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum PinCommands {
+    /// List pinned commands
+    List,
+    /// Show a command first whenever a request matches a pattern
+    Add {
+        /// Regex matched case-insensitively against the request, e.g. "deploy to staging"
+        pattern: String,
+        /// The command to pin
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true, required = true)]
+        command: Vec<String>,
+    },
+    /// Remove a pin
+    Remove {
+        /// The pin's pattern, or its number in `commandy pin list`
+        pin: String,
+    },
+}
+
 #[derive(Debug, Clone)]
 pub struct PromptOptions {
     pub no_cache: bool,
     pub explain: bool,
     pub max_suggestions: usize,
     pub verbose: bool,
+    /// Command received from an upstream commandy to refine
+    pub base_command: Option<String>,
+    /// A query still being typed in watch mode: no spinner, nothing cached or recorded
+    pub draft: bool,
+    /// Files given as context with `-f`, and piped or pasted text
+    pub attachments: Vec<Attachment>,
+    /// Generate only this many now and the rest as the picker pages to them
+    pub page_size: Option<usize>,
+    /// Commands already shown, which another page must not repeat
+    pub exclude: Vec<String>,
+    /// A shown command to give variations of, from `m` in the picker
+    pub vary: Option<String>,
 }
 
 impl From<&Cli> for PromptOptions {
@@ -72,8 +522,15 @@ impl From<&Cli> for PromptOptions {
         Self {
             no_cache: cli.no_cache,
             explain: cli.explain,
-            max_suggestions: cli.suggestions,
+            // Settled by CommandHandler::suggestion_count, which knows the configured bounds
+            max_suggestions: cli.suggestions.unwrap_or_default(),
             verbose: cli.verbose,
+            base_command: None,
+            draft: false,
+            attachments: Vec::new(),
+            page_size: None,
+            exclude: Vec::new(),
+            vary: None,
         }
     }
 }
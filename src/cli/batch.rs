@@ -0,0 +1,94 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::io::Read;
+use std::path::Path;
+
+use crate::cli::Suggestion;
+
+/// A task read from a batch file, with its line number for reference
+#[derive(Debug, Clone)]
+pub struct BatchTask {
+    pub line: usize,
+    pub task: String,
+}
+
+/// What was suggested for one task
+#[derive(Debug, Serialize)]
+pub struct BatchItem {
+    pub line: usize,
+    pub task: String,
+    pub suggestions: Vec<Suggestion>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Everything `commandy batch` produced, as JSON or as a shell script
+#[derive(Debug, Serialize)]
+pub struct BatchReport {
+    pub source: String,
+    pub generated_at: String,
+    pub items: Vec<BatchItem>,
+}
+
+impl BatchTask {
+    /// One task per line; blank lines and `#` comments are skipped, `-` reads stdin
+    pub fn read_all(path: &Path) -> Result<Vec<BatchTask>> {
+        let content = if path == Path::new("-") {
+            let mut content = String::new();
+            std::io::stdin()
+                .read_to_string(&mut content)
+                .context("Failed to read tasks from stdin")?;
+            content
+        } else {
+            std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read {}", path.display()))?
+        };
+
+        Ok(content
+            .lines()
+            .enumerate()
+            .map(|(i, line)| BatchTask {
+                line: i + 1,
+                task: line.trim().to_string(),
+            })
+            .filter(|task| !task.task.is_empty() && !task.task.starts_with('#'))
+            .collect())
+    }
+}
+
+impl BatchReport {
+    /// A script that runs each task's top command, with the task, explanation,
+    /// and alternatives as comments
+    pub fn to_script(&self) -> String {
+        let mut script = format!(
+            "#!/bin/sh\n# Generated by commandy batch from {} on {}\n\
+             # Review every command before running this script.\nset -e\n",
+            self.source, self.generated_at
+        );
+
+        for (i, item) in self.items.iter().enumerate() {
+            script.push_str(&format!("\n# {}. {}\n", i + 1, item.task));
+            let Some((first, rest)) = item.suggestions.split_first() else {
+                let reason = item.error.as_deref().unwrap_or("no suggestion");
+                script.push_str(&format!("# commandy: skipped ({reason})\n"));
+                continue;
+            };
+            if let Some(explanation) = &first.explanation {
+                for line in explanation.lines().filter(|line| !line.trim().is_empty()) {
+                    script.push_str(&format!("#    {}\n", line.trim()));
+                }
+            }
+            script.push_str(&first.command);
+            script.push('\n');
+            if !rest.is_empty() {
+                script.push_str("#    alternatives:\n");
+                for alternative in rest {
+                    for line in alternative.command.lines() {
+                        script.push_str(&format!("#    {line}\n"));
+                    }
+                }
+            }
+        }
+        script
+    }
+}
@@ -0,0 +1,201 @@
+use std::collections::VecDeque;
+use std::io::{self, Read, Write};
+use std::process::{Command, ExitStatus, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// Programs that draw on the terminal, so their output can't go through a pipe
+const NEEDS_TERMINAL: &[&str] = &[
+    "sudo", "su", "top", "htop", "btop", "vim", "vi", "nvim", "nano", "emacs", "less", "more",
+    "man", "watch", "tmux", "screen", "passwd", "ssh", "fzf",
+];
+
+/// Longer lines, such as minified JSON, are cut to this many characters
+const MAX_LINE_CHARS: usize = 500;
+
+/// Last lines of standard error kept for finding a fix; the cause is usually near the end
+const MAX_ERROR_LINES: usize = 20;
+
+/// What a command printed, kept within a size limit for the model to
+/// summarize, suggest next steps from, or fix a failure with
+///
+/// The start of the output usually says what the command is doing and the end
+/// how it went, so a long output keeps both and drops the middle.
+#[derive(Debug, Clone)]
+pub struct CapturedOutput {
+    pub command: String,
+    pub exit_code: Option<i32>,
+    /// The last lines written to standard error
+    errors: VecDeque<String>,
+    head: String,
+    tail: VecDeque<String>,
+    tail_bytes: usize,
+    omitted_lines: usize,
+    limit: usize,
+}
+
+impl CapturedOutput {
+    fn new(command: &str, limit: usize) -> Self {
+        Self {
+            command: command.to_string(),
+            exit_code: None,
+            errors: VecDeque::new(),
+            head: String::new(),
+            tail: VecDeque::new(),
+            tail_bytes: 0,
+            omitted_lines: 0,
+            limit,
+        }
+    }
+
+    /// A command that ran on the terminal, so only how it exited is known
+    pub fn uncaptured(command: &str, exit_code: Option<i32>) -> Self {
+        Self {
+            exit_code,
+            ..Self::new(command, 0)
+        }
+    }
+
+    /// Whether `command` can run with its output piped through commandy: no
+    /// stage of it is a pager, editor, or full-screen program
+    pub fn capturable(command: &str) -> bool {
+        command.split(['|', ';', '&']).all(|stage| {
+            let program = stage
+                .split_whitespace()
+                .find(|word| !word.contains('='))
+                .unwrap_or_default();
+            let program = program.rsplit('/').next().unwrap_or(program);
+            !NEEDS_TERMINAL.contains(&program)
+        })
+    }
+
+    /// Runs `cmd`, showing its output as it comes and keeping up to `limit` bytes of it
+    ///
+    /// Without `stdout`, only standard error goes through commandy, and standard
+    /// output stays on the terminal.
+    pub fn run(
+        cmd: &mut Command,
+        command: &str,
+        limit: usize,
+        stdout: bool,
+    ) -> io::Result<(ExitStatus, Self)> {
+        if stdout {
+            cmd.stdout(Stdio::piped());
+        }
+        let mut child = cmd.stderr(Stdio::piped()).spawn()?;
+        let captured = Arc::new(Mutex::new(Self::new(command, limit)));
+
+        let stderr = child.stderr.take().map(|stderr| {
+            let captured = Arc::clone(&captured);
+            thread::spawn(move || Self::tee(stderr, &mut io::stderr(), &captured, true))
+        });
+        if let Some(stdout) = child.stdout.take() {
+            Self::tee(stdout, &mut io::stdout(), &captured, false);
+        }
+        if let Some(handle) = stderr {
+            let _ = handle.join();
+        }
+        let status = child.wait()?;
+
+        let mut captured = captured.lock().unwrap_or_else(|e| e.into_inner()).clone();
+        captured.exit_code = status.code();
+        Ok((status, captured))
+    }
+
+    /// Passes output on as it arrives, so prompts without a newline still show
+    fn tee(mut stream: impl Read, out: &mut impl Write, captured: &Mutex<Self>, errors: bool) {
+        let mut buffer = [0u8; 8192];
+        let mut line = Vec::new();
+        loop {
+            let read = match stream.read(&mut buffer) {
+                Ok(0) => break,
+                Ok(read) => read,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(_) => break,
+            };
+            let _ = out.write_all(&buffer[..read]);
+            let _ = out.flush();
+            for &byte in &buffer[..read] {
+                line.push(byte);
+                if byte == b'\n' {
+                    Self::push_line(captured, &line, errors);
+                    line.clear();
+                }
+            }
+        }
+        if !line.is_empty() {
+            Self::push_line(captured, &line, errors);
+        }
+    }
+
+    fn push_line(captured: &Mutex<Self>, line: &[u8], errors: bool) {
+        captured
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(&String::from_utf8_lossy(line), errors);
+    }
+
+    fn push(&mut self, line: &str, errors: bool) {
+        // Progress bars redraw a line with carriage returns; the last state is enough
+        let line = line.trim_end_matches(['\n', '\r']);
+        let line = line.rsplit('\r').next().unwrap_or(line);
+        let line = match line.char_indices().nth(MAX_LINE_CHARS) {
+            Some((end, _)) => format!("{}...", &line[..end]),
+            None => line.to_string(),
+        };
+        if errors {
+            if self.errors.len() == MAX_ERROR_LINES {
+                self.errors.pop_front();
+            }
+            self.errors.push_back(line.clone());
+        }
+        // Once a line has gone to the tail, the head is full
+        if self.tail.is_empty()
+            && self.omitted_lines == 0
+            && self.head.len() + line.len() < self.limit / 2
+        {
+            self.head.push_str(&line);
+            self.head.push('\n');
+            return;
+        }
+        self.tail_bytes += line.len() + 1;
+        self.tail.push_back(line);
+        while self.tail_bytes > self.limit / 2 {
+            let Some(dropped) = self.tail.pop_front() else {
+                break;
+            };
+            self.tail_bytes -= dropped.len() + 1;
+            self.omitted_lines += 1;
+        }
+    }
+
+    /// Exited with an error, rather than being interrupted or killed
+    pub fn failed(&self) -> bool {
+        // 130 is the shell's code for Ctrl-C
+        !matches!(self.exit_code, Some(0 | 130) | None)
+    }
+
+    /// The end of what the command wrote to standard error, if anything
+    pub fn errors(&self) -> Option<String> {
+        let errors: Vec<&str> = self.errors.iter().map(String::as_str).collect();
+        let errors = errors.join("\n");
+        (!errors.trim().is_empty()).then_some(errors)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.head.trim().is_empty() && self.tail.is_empty()
+    }
+
+    /// The kept output, with a marker where lines were dropped
+    pub fn text(&self) -> String {
+        let mut text = self.head.clone();
+        if self.omitted_lines > 0 {
+            text.push_str(&format!("[... {} lines omitted ...]\n", self.omitted_lines));
+        }
+        for line in &self.tail {
+            text.push_str(line);
+            text.push('\n');
+        }
+        text
+    }
+}
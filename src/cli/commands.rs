@@ -1,38 +1,208 @@
 use anyhow::Result;
-use log::{debug, info, warn};
-use std::io;
+use arboard::Clipboard;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
 
-use crate::ai::LlamaCppClient;
-use crate::cli::{Commands, FormatResult, OutputFormatter, PromptOptions, Spinner};
-use crate::config::Settings;
-use crate::context::ContextManager;
+use crate::ai::{
+    Arm, BackendMode, Device, DeviceProbe, Experiment, LlamaCppClient, MemoryGuard, ModelIdentity,
+    ModelRegistry, ModelTooLarge, PromptCache, RecordedGeneration,
+};
+use crate::bundle::{BugReport, Bundle, SeedCache, SettingsOutcome};
+use crate::cli::{
+    AuditCommands, BackendUnavailable, BatchItem, BatchReport, BatchTask, Blocked, CacheCommands,
+    CapturedOutput, Commands, DaemonCommands, FlagMenu, FormatResult, Interrupt, KeyOutcome,
+    KeyStream, KeyWatcher, Keymap, ModelCommands, OutputFormatter, PackCommands, Paging,
+    PinCommands, PipedInput, PromptOptions, QueryTemplate, Runbook, RunbookStep, SessionCommands,
+    ShellHook, Spinner, StatsCommands, Theme, WatchExit, WatchScreen, WatchStatus,
+};
+use crate::config::{GenerationMode, Settings, SudoPolicy};
+use crate::context::{
+    Attachment, CacheManager, Category, ContextData, ContextManager, EntityExtractor,
+    HistorySearch, Language, LanguageDetector, SessionStore, ShellSubstitution, TaxonomyClassifier,
+    TimeContext,
+};
+use crate::daemon::{Control, DaemonFiles, Supervisor};
+use crate::eval::{EvalDataset, Evaluator};
+use crate::fallback::FallbackEngine;
+use crate::fix::CommandFixer;
+use crate::flags::FlagCatalog;
+use crate::plugins::PluginHost;
+use crate::postprocess::{
+    Diversifier, ExpansionGuard, PostProcessor, QuotingCheck, Ranker, RankingFactors,
+};
+use crate::remote::RemoteTarget;
+use crate::safety::{
+    AuditLog, AuditVerification, AutoRunPolicy, PayloadScanner, RiskClassifier, RiskPolicy,
+    ScriptUnpacker,
+};
+use crate::server::{AuthToken, QueueStats};
+use crate::translate::{Dialect, ShellTranslator};
+use crate::update::{Flavor, LlamaInstaller, SelfUpdater, UpdateCheck};
+use crate::utils::{
+    HttpClient, Logging, MemoryInfo, PathDisplay, Platform, PrivilegeAnalyzer, Runtimes, Timeline,
+    ToolDocs,
+};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct Suggestion {
     pub command: String,
     pub explanation: Option<String>,
     pub confidence: f32,
+    pub source: SuggestionSource,
+    /// Why the suggestion has its confidence; set by [`Ranker`]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ranking: Option<RankingFactors>,
 }
 
+/// Where a suggestion came from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SuggestionSource {
+    #[default]
+    Model,
+    Cache,
+    /// Rule-based offline fallback used when no model is available
+    Heuristic,
+    /// Deterministic correction rule
+    Rule,
+    /// Pinned with `commandy pin add` for requests like this one
+    Pinned,
+}
+
+impl SuggestionSource {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SuggestionSource::Model => "model",
+            SuggestionSource::Cache => "cache",
+            SuggestionSource::Heuristic => "heuristic",
+            SuggestionSource::Rule => "rule",
+            SuggestionSource::Pinned => "pinned",
+        }
+    }
+}
+
+/// History entries consulted when choosing between duplicate suggestions
+const DIVERSITY_HISTORY_LIMIT: usize = 200;
+
+/// Queries each experiment arm needs before its acceptance rate means much
+const MIN_EXPERIMENT_RUNS: u32 = 30;
+
+/// Log lines shown by `doctor --logs`
+const RECENT_LOG_PROBLEMS: usize = 20;
+
+/// 100ms waits for the daemon to start, stop, or restart llama-server
+const DAEMON_WAIT_TICKS: usize = 100;
+
+/// Pause in typing before watch mode generates for the query
+const WATCH_DEBOUNCE: Duration = Duration::from_millis(350);
+
+/// Shorter watch-mode queries are too vague to generate for
+const WATCH_MIN_QUERY_CHARS: usize = 3;
+
+/// Result of asking for a follow-up modification
+enum FollowupOutcome {
+    Suggestions(Vec<Suggestion>),
+    /// Return to the previous suggestions
+    Back,
+    Quit,
+    Failed(anyhow::Error),
+}
+
+/// Minimum normalized relevance for a history match to be shown by `recall`
+const RECALL_MIN_SCORE: f64 = 0.5;
+
 pub struct CommandHandler {
     context: ContextManager,
-    ai_client: LlamaCppClient,
+    ai_client: Option<LlamaCppClient>,
+    fallback: FallbackEngine,
+    postprocessor: PostProcessor,
+    /// Discovered on first use so subcommands don't pay for spawning plugins
+    plugins: OnceLock<PluginHost>,
+    /// Read on first use, like the plugins
+    shell: OnceLock<ShellSubstitution>,
     settings: Settings,
     formatter: OutputFormatter,
+    /// Set by --log-file so `doctor --logs` reads the same file
+    log_file: Option<PathBuf>,
+    /// Host from --target that suggestions are generated and run for
+    remote: Option<RemoteTarget>,
+    /// What the remote host's probe found, fetched on the first request
+    remote_environment: Option<HashMap<String, String>>,
+    /// Set by --read-only: nothing is run, copied, saved, or learned
+    read_only: bool,
+    /// Name of the `[profiles]` entry whose limits apply
+    profile: Option<String>,
+    /// Experiment, arm, and prompt of the last generation, recorded once the
+    /// picker shows its suggestions
+    experiment_trial: Option<(String, Arm, String)>,
+    /// The request behind the picker, while it has pages left to generate
+    pending_page: Option<PendingPage>,
+    /// Language of the last request when it isn't English
+    request_language: Option<Language>,
+    /// Where the time of the last request went, shown by --verbose
+    timeline: Timeline,
+}
+
+/// A request whose later pages are generated when the picker reaches them
+struct PendingPage {
+    prompt: String,
+    options: PromptOptions,
+    /// Suggestions asked for across all pages
+    total: usize,
+    page_size: usize,
 }
 
 impl CommandHandler {
     pub fn new() -> Result<Self> {
         let settings = Settings::load()?;
         let context = ContextManager::new(&settings)?;
-        let ai_client = LlamaCppClient::new(&settings)?;
-        let formatter = OutputFormatter::new(settings.output.use_colors);
+        // A mistyped backend must not quietly fall back to heuristics
+        BackendMode::from_env()?;
+        let ai_client = match LlamaCppClient::new(&settings) {
+            Ok(client) => Some(client),
+            Err(e) => {
+                warn!("Model backend unavailable: {e}");
+                None
+            }
+        };
+        let mut formatter = OutputFormatter::new(
+            settings.output.use_colors && !OutputFormatter::no_color_requested(),
+        );
+        formatter.set_theme(Theme::from_config(&settings.theme));
+        formatter.set_keymap(Keymap::from_config(&settings.keymap));
+        formatter.set_auto_run(AutoRunPolicy::new(&settings.auto_execute));
+        formatter.set_rerun_cooldown(settings.output.rerun_cooldown_secs);
+        // Summaries and next steps read all the output; fixes only need standard error
+        let read_output = settings.output.summarize_output || settings.output.suggest_next;
+        formatter.set_capture(
+            (read_output || settings.output.triage_failures)
+                .then_some(settings.output.summary_capture_kb * 1024),
+            read_output,
+        );
 
         Ok(Self {
             context,
             ai_client,
+            fallback: FallbackEngine::new(),
+            postprocessor: PostProcessor::from_config(&settings.postprocess),
+            plugins: OnceLock::new(),
+            shell: OnceLock::new(),
             settings,
             formatter,
+            log_file: None,
+            remote: None,
+            remote_environment: None,
+            read_only: false,
+            profile: None,
+            experiment_trial: None,
+            request_language: None,
+            pending_page: None,
+            timeline: Timeline::new(),
         })
     }
 
@@ -40,39 +210,583 @@ impl CommandHandler {
         &mut self,
         prompt: &str,
         options: PromptOptions,
+    ) -> Result<Vec<Suggestion>> {
+        self.experiment_trial = None;
+        self.pending_page = None;
+        self.request_language = LanguageDetector::detect(prompt);
+        self.timeline = Timeline::new();
+        let verbose = options.verbose;
+        // With paging, only the first page is generated now
+        let options = match options
+            .page_size
+            .filter(|&size| size > 0 && size < options.max_suggestions)
+        {
+            Some(page_size) => {
+                self.pending_page = Some(PendingPage {
+                    prompt: prompt.to_string(),
+                    options: options.clone(),
+                    total: options.max_suggestions,
+                    page_size,
+                });
+                PromptOptions {
+                    max_suggestions: page_size,
+                    ..options
+                }
+            }
+            None => options,
+        };
+        // Pins are exact, so they need no model and skip every rewrite below
+        let pinned = self.pinned_suggestions(prompt);
+        let wanted = options.max_suggestions.saturating_sub(pinned.len());
+        let suggestions = if wanted == 0 {
+            if !options.draft {
+                self.context
+                    .record_session_query(prompt, Some(&pinned[0].command));
+            }
+            Vec::new()
+        } else {
+            let options = PromptOptions {
+                max_suggestions: wanted,
+                ..options
+            };
+            match self.generate_suggestions(prompt, options).await {
+                Ok(suggestions) => suggestions,
+                Err(e) if !pinned.is_empty() => {
+                    warn!(error = %e, "Generation failed, showing only pinned commands");
+                    Vec::new()
+                }
+                Err(e) => {
+                    if verbose {
+                        eprintln!("{}", self.timeline.render());
+                    }
+                    return Err(e);
+                }
+            }
+        };
+        let generated = suggestions.len();
+        let started = Instant::now();
+        let suggestions = self.adapt_suggestions(prompt, suggestions);
+        self.timeline.push(
+            "post-processing",
+            started.elapsed(),
+            format!("{generated} in, {} out", suggestions.len()),
+        );
+        let started = Instant::now();
+        let suggestions = self.rank_suggestions(suggestions);
+        self.timeline.push("ranking", started.elapsed(), "");
+        let suggestions = Self::pinned_first(pinned, suggestions);
+        let suggestions = self.drop_denied(suggestions);
+        let suggestions = self.apply_sudo_policy(suggestions);
+        if verbose {
+            eprintln!("{}", self.timeline.render());
+        }
+        suggestions
+    }
+
+    /// Generates the next page of the picker's request, leaving out the commands shown
+    async fn next_page(&mut self, shown: &[Suggestion]) -> Result<Vec<Suggestion>> {
+        let Some(pending) = &self.pending_page else {
+            return Ok(Vec::new());
+        };
+        let prompt = pending.prompt.clone();
+        let options = PromptOptions {
+            max_suggestions: pending
+                .total
+                .saturating_sub(shown.len())
+                .min(pending.page_size),
+            no_cache: true,
+            page_size: None,
+            exclude: shown.iter().map(|s| s.command.clone()).collect(),
+            ..pending.options.clone()
+        };
+        self.added_suggestions(&prompt, options, shown).await
+    }
+
+    /// Other versions of `command`, one of the suggestions `shown` for `prompt`
+    async fn more_like(
+        &mut self,
+        prompt: &str,
+        command: &str,
+        shown: &[Suggestion],
+    ) -> Result<Vec<Suggestion>> {
+        let output = &self.settings.output;
+        let options = PromptOptions {
+            no_cache: true,
+            explain: false,
+            max_suggestions: match output.page_size {
+                0 => output.max_suggestions.max(1),
+                size => size,
+            },
+            verbose: false,
+            base_command: None,
+            draft: false,
+            attachments: self
+                .pending_page
+                .as_ref()
+                .map(|pending| pending.options.attachments.clone())
+                .unwrap_or_default(),
+            page_size: None,
+            exclude: shown.iter().map(|s| s.command.clone()).collect(),
+            vary: Some(command.to_string()),
+        };
+        self.added_suggestions(prompt, options, shown).await
+    }
+
+    /// Suggestions to add to those already in the picker, without repeats
+    async fn added_suggestions(
+        &mut self,
+        prompt: &str,
+        options: PromptOptions,
+        shown: &[Suggestion],
+    ) -> Result<Vec<Suggestion>> {
+        let suggestions = self.generate_suggestions(prompt, options).await?;
+        let suggestions = self.adapt_suggestions(prompt, suggestions);
+        let suggestions: Vec<Suggestion> = self
+            .rank_suggestions(suggestions)
+            .into_iter()
+            .filter(|s| !shown.iter().any(|seen| seen.command == s.command))
+            .collect();
+        let suggestions = self.drop_denied(suggestions);
+        self.apply_sudo_policy(suggestions)
+    }
+
+    /// Whether the picker can generate another page after `shown` suggestions
+    fn has_next_page(&self, shown: usize) -> bool {
+        self.pending_page
+            .as_ref()
+            .is_some_and(|pending| shown < pending.total)
+    }
+
+    /// Rewrites and checks generated suggestions for this machine and user
+    fn adapt_suggestions(&self, prompt: &str, suggestions: Vec<Suggestion>) -> Vec<Suggestion> {
+        // Transforms adapt commands to this machine, so they'd be wrong on a remote one;
+        // so would this shell's history and aliases
+        let suggestions = match &self.remote {
+            Some(_) => suggestions,
+            None => {
+                let suggestions = suggestions
+                    .into_iter()
+                    .map(|s| Suggestion {
+                        command: self.shell().resolve(&s.command),
+                        ..s
+                    })
+                    .collect();
+                let plugin_transforms = self.plugins().transforms(prompt);
+                self.postprocessor
+                    .process_with(suggestions, &plugin_transforms)
+            }
+        };
+        let suggestions = if self.settings.postprocess.fix_quoting {
+            QuotingCheck::apply(suggestions, prompt)
+        } else {
+            suggestions
+        };
+        if self.settings.postprocess.guard_expansions {
+            ExpansionGuard::apply(suggestions)
+        } else {
+            suggestions
+        }
+    }
+
+    /// Orders suggestions by how this user's past runs went, then spreads out near-repeats
+    fn rank_suggestions(&self, suggestions: Vec<Suggestion>) -> Vec<Suggestion> {
+        let suggestions =
+            Ranker::apply(suggestions, |command| self.context.execution_stats(command));
+        Diversifier::apply(
+            suggestions,
+            &self.context.command_history(DIVERSITY_HISTORY_LIMIT),
+        )
+    }
+
+    /// Commands pinned for requests matching `prompt`
+    fn pinned_suggestions(&self, prompt: &str) -> Vec<Suggestion> {
+        self.context
+            .pins()
+            .matching(prompt)
+            .into_iter()
+            .map(|pin| Suggestion {
+                command: pin.command.clone(),
+                explanation: None,
+                confidence: 1.0,
+                source: SuggestionSource::Pinned,
+                ranking: None,
+            })
+            .collect()
+    }
+
+    /// Pinned commands, then the rest without repeating them
+    fn pinned_first(pinned: Vec<Suggestion>, suggestions: Vec<Suggestion>) -> Vec<Suggestion> {
+        let rest: Vec<Suggestion> = suggestions
+            .into_iter()
+            .filter(|s| !pinned.iter().any(|p| p.command == s.command))
+            .collect();
+        pinned.into_iter().chain(rest).collect()
+    }
+
+    /// Adds sudo to, or drops, suggestions that need root, per the `[sudo]` policy
+    ///
+    /// Pinned commands are kept exactly as the user wrote them.
+    fn apply_sudo_policy(&self, suggestions: Vec<Suggestion>) -> Result<Vec<Suggestion>> {
+        let policy = self.settings.sudo.policy;
+        if policy == SudoPolicy::Warn || suggestions.is_empty() {
+            return Ok(suggestions);
+        }
+
+        let mut kept = Vec::with_capacity(suggestions.len());
+        for mut suggestion in suggestions {
+            if suggestion.source == SuggestionSource::Pinned {
+                kept.push(suggestion);
+                continue;
+            }
+            let Some(reason) = PrivilegeAnalyzer::requires_root(&suggestion.command) else {
+                kept.push(suggestion);
+                continue;
+            };
+            match policy {
+                SudoPolicy::Refuse => {
+                    info!(
+                        command = suggestion.command,
+                        reason, "Suggestion needs root, refused by sudo policy"
+                    );
+                }
+                _ => {
+                    // Commands too complex to rewrite are kept, marked as needing root
+                    if let Some(elevated) = PrivilegeAnalyzer::with_sudo(&suggestion.command) {
+                        suggestion.command = elevated;
+                    }
+                    kept.push(suggestion);
+                }
+            }
+        }
+
+        if kept.is_empty() {
+            return Err(Blocked(
+                "Every suggestion needs root, and [sudo] policy is \"refuse\" in config.toml"
+                    .to_string(),
+            )
+            .into());
+        }
+        Ok(kept)
+    }
+
+    /// Characters of `-f` files that fit in a suggestion prompt for the current model
+    fn attachment_budget(&self) -> usize {
+        let model = &self.settings.model;
+        Attachment::budget(
+            model.runtime_for(&model.model_path).context_size,
+            model.token_budget(GenerationMode::Script),
+        )
+    }
+
+    /// Swaps this machine's details in the context for the --target host's
+    fn use_remote_context(&mut self, context_data: &mut ContextData) -> Result<()> {
+        let Some(remote) = &self.remote else {
+            return Ok(());
+        };
+        if self.remote_environment.is_none() {
+            let spinner = Spinner::new(&format!("Probing {}...", remote.destination()));
+            let probed = remote.probe();
+            spinner.stop();
+            self.remote_environment = Some(probed?);
+        }
+
+        context_data.environment = self.remote_environment.clone().unwrap_or_default();
+        // Names, projects, and variables found here don't exist over there
+        context_data.recent_commands.clear();
+        context_data.entities.clear();
+        context_data.ci_jobs.clear();
+        context_data.tasks.clear();
+        context_data.toolchain = None;
+        context_data.runtimes = Runtimes::default();
+        context_data.env_var_names.clear();
+        context_data.shell_definitions.clear();
+        context_data.working_directory = None;
+        context_data.file_types.clear();
+        Ok(())
+    }
+
+    /// Opens the flag menu for a suggestion; `None` when the command is unchanged
+    fn edit_flags(&self, command: &str) -> Result<Option<String>> {
+        let catalog = FlagCatalog::load()?;
+        let menu = FlagMenu::new(&catalog, self.formatter.theme());
+        let edited = match self.formatter.is_plain() {
+            true => Some(menu.edit_plain(command)?),
+            false => menu.edit(command)?,
+        };
+        let Some(edited) = edited.filter(|edited| edited != command) else {
+            return Ok(None);
+        };
+        if let Some(denial) = self.context.packs().denied(&edited) {
+            eprintln!(
+                "{}",
+                self.formatter.format_warning(&format!(
+                    "Not changed: the {} pack denies {edited} ({})",
+                    denial.pack, denial.reason
+                ))
+            );
+            return Ok(None);
+        }
+        Ok(Some(edited))
+    }
+
+    /// Removes suggestions that an installed pattern pack denies
+    fn drop_denied(&self, suggestions: Vec<Suggestion>) -> Vec<Suggestion> {
+        suggestions
+            .into_iter()
+            .filter(|s| match self.context.packs().denied(&s.command) {
+                Some(denial) => {
+                    info!(
+                        command = s.command,
+                        pack = denial.pack,
+                        reason = denial.reason,
+                        "Suggestion denied by pattern pack"
+                    );
+                    false
+                }
+                None => true,
+            })
+            .collect()
+    }
+
+    fn plugins(&self) -> &PluginHost {
+        self.plugins
+            .get_or_init(|| PluginHost::discover(&self.settings.plugins))
+    }
+
+    fn shell(&self) -> &ShellSubstitution {
+        self.shell
+            .get_or_init(|| ShellSubstitution::load(self.context.get_commandy_dir()))
+    }
+
+    /// The request with `!!` and the like replaced by the commands they stand for
+    pub fn resolve_query(&self, query: &str) -> String {
+        self.shell().resolve_query(query)
+    }
+
+    async fn generate_suggestions(
+        &mut self,
+        prompt: &str,
+        options: PromptOptions,
     ) -> Result<Vec<Suggestion>> {
         debug!("Processing prompt: {prompt}");
 
+        // Follow-ups like "now do it recursively" depend on the previous turn
+        let session_turns = self.context.session_turns_for(prompt);
+        let previous_command = session_turns.iter().rev().find_map(|t| t.command.clone());
+
+        // Refinements and follow-ups are cached under the composed request
+        let cache_key = match (&options.base_command, &previous_command) {
+            (Some(base), _) => format!("{base} | {prompt}"),
+            (None, Some(previous)) => format!("{previous} ; {prompt}"),
+            (None, None) => prompt.to_string(),
+        };
+        // Another host may need different commands for the same request
+        let cache_key = match &self.remote {
+            Some(remote) => format!("{}: {cache_key}", remote.destination()),
+            None => cache_key,
+        };
+        // The same request about other file contents needs other commands
+        let mut attachments = options.attachments.clone();
+        Attachment::fit(&mut attachments, self.attachment_budget());
+        let cache_key = match attachments.is_empty() {
+            true => cache_key,
+            false => format!("{cache_key} [{}]", Attachment::fingerprint(&attachments)),
+        };
+
+        // "since Monday" means a different day next week
+        let anchored = TimeContext::anchored(prompt);
+
         // Check cache first unless explicitly disabled
-        if !options.no_cache {
-            if let Ok(Some(cached)) = self.context.get_cached_suggestion(prompt) {
+        if !options.no_cache && !anchored {
+            let started = Instant::now();
+            let cached = self.context.get_cached_suggestion(&cache_key);
+            let found = match &cached {
+                Ok(Some(_)) => "hit",
+                _ => "miss",
+            };
+            self.timeline.push("cache check", started.elapsed(), found);
+            if let Ok(Some(cached)) = cached {
                 info!("Found cached suggestion for prompt");
+                if !options.draft {
+                    self.context
+                        .record_session_query(prompt, Some(&cached.command));
+                }
                 return Ok(vec![cached]);
             }
         }
 
+        // The model and the keyword rules below work best from English
+        let request = self.english_request(prompt, &options).await;
+        let anchored = anchored || TimeContext::anchored(&request);
+
         // Load context for prompt enhancement
-        let context_data = self.context.get_relevant_context(prompt)?;
+        let started = Instant::now();
+        let mut context_data = self.context.get_relevant_context(&request)?;
+        self.timeline.push(
+            "context",
+            started.elapsed(),
+            format!("category {}", context_data.prompt_category.label()),
+        );
+        self.timeline.nest(&context_data.timeline);
+        context_data.base_command = options.base_command.clone();
+        if !session_turns.is_empty() {
+            context_data.session_summary = self.context.session_summary();
+        }
+        context_data.session_turns = session_turns;
+        let started = Instant::now();
+        context_data.plugin_context = self.plugins().collect_context(&request);
+        if !context_data.plugin_context.is_empty() {
+            let found = format!("{} lines", context_data.plugin_context.len());
+            self.timeline
+                .push("plugin context", started.elapsed(), found);
+        }
+        context_data.attachments = attachments;
+        context_data.exclude_commands = options.exclude.clone();
+        context_data.vary_command = options.vary.clone();
+        if self.remote.is_some() {
+            let started = Instant::now();
+            self.use_remote_context(&mut context_data)?;
+            self.timeline.push("remote context", started.elapsed(), "");
+        }
         debug!(
             "Loaded context data with {} recent commands",
             context_data.recent_commands.len()
         );
 
-        // Show spinner while generating suggestions
-        let spinner = Spinner::new("Generating suggestions...");
+        // Show spinner while generating suggestions, unless watch mode owns the screen
+        // A later page of the picker only adds to the first, which was recorded and cached
+        let later_page = !options.exclude.is_empty();
+        let spinner = (!options.draft).then(|| match (&options.vary, later_page) {
+            (Some(_), _) => Spinner::new("Generating variations..."),
+            (None, true) => Spinner::new("Generating more suggestions..."),
+            (None, false) => Spinner::new("Generating suggestions..."),
+        });
+
+        let Some(ai_client) = &self.ai_client else {
+            if let Some(spinner) = spinner {
+                spinner.stop();
+            }
+            return self.fallback_suggestions(
+                prompt,
+                &options,
+                anyhow::anyhow!("llama.cpp binary not found (run 'commandy init' to install it)"),
+            );
+        };
+
+        // Ask the fast model only when the keyword rules can't tell
+        if ai_client.has_fast_model() && !TaxonomyClassifier::classify(&request).is_confident() {
+            let labels = Category::ALL.map(Category::label);
+            let started = Instant::now();
+            let result = ai_client.classify_prompt(&request, &labels).await;
+            let found = match &result {
+                Ok(Some(label)) => label.clone(),
+                Ok(None) => "no category".to_string(),
+                Err(_) => "failed".to_string(),
+            };
+            self.timeline
+                .push("classification", started.elapsed(), found);
+            match result {
+                Ok(Some(label)) => {
+                    if let Some(category) = Category::from_name(&label) {
+                        if category != context_data.prompt_category {
+                            context_data.entities = EntityExtractor::gather(category);
+                        }
+                        context_data.prompt_category = category;
+                    }
+                }
+                Ok(None) => debug!("Fast model returned no usable category"),
+                Err(e) => warn!(error = %e, "Fast model categorization failed"),
+            }
+        }
+
+        // A share of queries tries the [experiment] variant's instructions
+        let trial = Experiment::from_config(&self.settings.experiment)
+            .filter(|_| !options.draft && !later_page && options.base_command.is_none())
+            .map(|experiment| {
+                let arm = experiment.assign();
+                context_data.instructions = experiment.instructions(arm).map(str::to_string);
+                (experiment.name, arm)
+            });
 
         // Generate suggestions via AI
-        let suggestions = self
-            .ai_client
-            .generate_suggestions(prompt, &context_data, options.max_suggestions)
-            .await?;
+        let started = Instant::now();
+        let result = ai_client
+            .generate_suggestions(&request, &context_data, options.max_suggestions)
+            .await;
+        match ai_client.last_generation() {
+            Some(stats) => {
+                let tokens = match ai_client.last_prompt_eval() {
+                    Some(eval) => format!(
+                        "{} tokens, {} reused from cache",
+                        eval.cached_tokens + eval.evaluated_tokens,
+                        eval.cached_tokens
+                    ),
+                    None => format!("~{} tokens", stats.prompt_chars / 4),
+                };
+                let prompt_size = format!("{} chars, {tokens}", stats.prompt_chars);
+                self.timeline
+                    .push("prompt", stats.prompt_build, prompt_size);
+                let response = match ai_client.last_prompt_eval() {
+                    Some(eval) => format!(
+                        "{} chars out, prompt evaluated in {:.0} ms (~{:.0} ms saved)",
+                        stats.response_chars,
+                        eval.eval_ms,
+                        eval.saved_ms()
+                    ),
+                    None => format!("{} chars out", stats.response_chars),
+                };
+                self.timeline.push("backend", stats.backend, response);
+                let parsed = format!("{} lines, {} commands", stats.lines, stats.commands);
+                self.timeline.push("parse", stats.parse, parsed);
+            }
+            None => self
+                .timeline
+                .push("generation", started.elapsed(), "failed"),
+        }
 
-        spinner.stop();
+        if let Some(spinner) = spinner {
+            spinner.stop();
+        }
+        if options.verbose {
+            eprintln!(
+                "Session {0} (re-run with: commandy replay {0})",
+                ai_client.session_id()
+            );
+            if let Some((name, arm)) = &trial {
+                eprintln!("Experiment {name}: {} prompt", arm.as_str());
+            }
+        }
+        let suggestions = match result {
+            Ok(suggestions) => suggestions,
+            // Heuristics would hide why; the user can switch models or free memory
+            Err(e) if e.is::<ModelTooLarge>() => {
+                if !options.draft && self.switch_to_smaller_model(&e)? {
+                    return Box::pin(self.generate_suggestions(prompt, options)).await;
+                }
+                return Err(BackendUnavailable(e).into());
+            }
+            Err(e) => return self.fallback_suggestions(&request, &options, e),
+        };
         info!("Generated {} suggestions", suggestions.len());
 
+        // Partial queries would only clutter the session and cache
+        if options.draft || later_page {
+            return Ok(suggestions);
+        }
+
+        self.context
+            .record_session_query(prompt, suggestions.first().map(|s| s.command.as_str()));
+
+        // Variant answers stay out of the cache until the variant becomes the prompt
+        let variant = trial.as_ref().is_some_and(|(_, arm)| *arm == Arm::Variant);
+        self.experiment_trial = trial.map(|(name, arm)| (name, arm, prompt.to_string()));
+        if variant || anchored {
+            return Ok(suggestions);
+        }
+
         // Cache successful results
         for suggestion in &suggestions {
-            if let Err(e) = self.context.cache_suggestion(prompt, suggestion) {
+            if let Err(e) = self.context.cache_suggestion(&cache_key, suggestion) {
                 warn!("Failed to cache suggestion: {e}");
             }
         }
@@ -80,13 +794,205 @@ impl CommandHandler {
         Ok(suggestions)
     }
 
+    /// Offers the largest downloaded model that fits when the configured one
+    /// doesn't; true once switched to it
+    fn switch_to_smaller_model(&mut self, error: &anyhow::Error) -> Result<bool> {
+        let Some(too_large) = error.downcast_ref::<ModelTooLarge>() else {
+            return Ok(false);
+        };
+        let Some(smaller) = too_large.smaller.first().cloned() else {
+            return Ok(false);
+        };
+        let question = format!(
+            "{} needs about {} MB, but only {} MB of memory and swap are free.\n\
+             Switch to {}, which is downloaded and needs about {} MB?",
+            too_large.model,
+            too_large.needed_mb,
+            too_large.available_mb + too_large.swap_free_mb,
+            smaller.name,
+            smaller.needed_mb
+        );
+        if !self.formatter.confirm(&question) {
+            return Ok(false);
+        }
+
+        eprintln!("{}", self.use_model(&smaller.repo, false)?);
+        let mut ai_client = LlamaCppClient::new(&self.settings)?;
+        ai_client.set_check_path(self.remote.is_none());
+        self.ai_client = Some(ai_client);
+        self.context.set_model(&ModelIdentity::of(&self.settings))?;
+        Ok(true)
+    }
+
+    /// The request in English, put there by the model when it was written in
+    /// another language and `[language] translate` is on
+    async fn english_request(&mut self, prompt: &str, options: &PromptOptions) -> String {
+        let (Some(language), Some(ai_client)) = (self.request_language, &self.ai_client) else {
+            return prompt.to_string();
+        };
+        if !self.settings.language.translate {
+            return prompt.to_string();
+        }
+
+        let spinner = (!options.draft).then(|| Spinner::new("Translating request..."));
+        let started = Instant::now();
+        let result = ai_client.translate_request(prompt, language.name).await;
+        if let Some(spinner) = spinner {
+            spinner.stop();
+        }
+        self.timeline.push(
+            "translation",
+            started.elapsed(),
+            format!("from {}", language.name),
+        );
+        match result {
+            Ok(english) if !english.is_empty() => {
+                debug!(language = language.code, %english, "Translated request");
+                if options.verbose {
+                    eprintln!("Request in {}, as English: {english}", language.name);
+                }
+                english
+            }
+            Ok(_) => prompt.to_string(),
+            Err(e) => {
+                warn!(error = %e, "Failed to translate the request; using it as written");
+                prompt.to_string()
+            }
+        }
+    }
+
+    /// Falls back to offline heuristics, surfacing the backend error if none match
+    fn fallback_suggestions(
+        &mut self,
+        prompt: &str,
+        options: &PromptOptions,
+        backend_error: anyhow::Error,
+    ) -> Result<Vec<Suggestion>> {
+        warn!(error = %backend_error, "Model backend failed, trying offline heuristics");
+
+        let started = Instant::now();
+        let suggestions = self.fallback.suggest(prompt, options.max_suggestions);
+        let found = format!("{} suggestions", suggestions.len());
+        self.timeline
+            .push("offline heuristics", started.elapsed(), found);
+        if suggestions.is_empty() {
+            return Err(BackendUnavailable(backend_error).into());
+        }
+        if options.draft {
+            return Ok(suggestions);
+        }
+
+        if !self.formatter.is_quiet() {
+            eprintln!(
+                "{}",
+                self.formatter
+                    .format_warning("Model unavailable, showing offline heuristic suggestions")
+            );
+        }
+
+        if options.exclude.is_empty() {
+            self.context
+                .record_session_query(prompt, suggestions.first().map(|s| s.command.as_str()));
+        }
+
+        // Heuristic results are never cached so they can't displace model output
+        Ok(suggestions)
+    }
+
     pub async fn handle_command(&mut self, command: Commands) -> Result<String> {
+        if self.read_only {
+            if let Some(name) = Self::writes_files(&command) {
+                return Err(anyhow::anyhow!(
+                    "`commandy {name}` changes files, which --read-only doesn't allow"
+                ));
+            }
+        }
         match command {
             Commands::Init => self.handle_init().await,
-            Commands::Update { model, binary } => self.handle_update(model, binary),
+            Commands::Update {
+                model,
+                binary,
+                flavor,
+                build,
+            } => {
+                let output = self.handle_update(model, binary, flavor.as_deref(), build)?;
+                self.warm_after_update(binary, output).await
+            }
+            Commands::Model { action } => {
+                let switched = matches!(action, ModelCommands::Use { .. });
+                let output = self.handle_model(action)?;
+                self.warm_after_update(switched, output).await
+            }
+            Commands::Recall { query, limit } => self.handle_recall(&query, limit).await,
+            Commands::Oops { command } => self.handle_oops(command).await,
+            Commands::Explain { command } => self.explain_command(&command.join(" ")).await,
+            Commands::Inspect {
+                command,
+                clipboard,
+                unpack,
+            } => {
+                self.handle_inspect(&command.join(" "), clipboard, unpack)
+                    .await
+            }
+            Commands::Translate { from, to, command } => {
+                self.handle_translate(&command.join(" "), &from, to.as_deref())
+                    .await
+            }
+            Commands::Run { template, vars } => self.handle_run(template.as_deref(), &vars),
+            Commands::Packs { action } => self.handle_packs(action),
+            Commands::Cache { action } => self.handle_cache(action).await,
+            Commands::Pin { action } => self.handle_pin(action),
+            Commands::Learned { out } => self.handle_learned(out.as_deref()),
+            Commands::Export { out, include_cache } => self.handle_export(&out, include_cache),
+            Commands::Import {
+                bundle,
+                replace_settings,
+            } => self.handle_import(&bundle, replace_settings),
+            Commands::Eval {
+                model,
+                dataset,
+                limit,
+                json,
+            } => self.handle_eval(model, dataset, limit, json).await,
+            Commands::Batch {
+                file,
+                output,
+                suggestions,
+                explain,
+            } => {
+                self.handle_batch(&file, &output, suggestions, explain)
+                    .await
+            }
+            Commands::Audit { action } => self.handle_audit(action),
+            Commands::Chat {
+                session,
+                suggestions,
+            } => self.handle_chat(session.as_deref(), suggestions).await,
+            Commands::Session { action } => self.handle_session(action).await,
+            Commands::Replay { session_id } => self.handle_replay(&session_id).await,
+            Commands::Bugreport { session, out } => {
+                self.handle_bugreport(session.as_deref(), out.as_deref())
+                    .await
+            }
+            Commands::Hook { shell } => ShellHook::script(&shell),
+            // The server takes ownership of the handler, so main starts it
+            Commands::Serve { .. } => Err(anyhow::anyhow!("serve is started by main")),
+            Commands::Daemon { action } => self.handle_daemon(action).await,
+            Commands::Stats {
+                view: Some(StatsCommands::Experiments),
+                ..
+            } => self.handle_experiment_stats(),
+            Commands::Stats {
+                view: None,
+                port,
+                host,
+                token,
+            } => self.handle_stats(&host, port, token),
             Commands::Config => self.handle_config(),
             Commands::Clear { cache, context } => self.handle_clear(cache, context),
-            Commands::Doctor => self.handle_doctor().await,
+            Commands::Doctor { logs: true } => self.handle_doctor_logs(),
+            Commands::Doctor { logs: false } => self.handle_doctor().await,
+            Commands::SelfUpdate { check } => self.handle_self_update(check),
             Commands::Version => self.handle_version(),
         }
     }
@@ -98,9 +1004,36 @@ impl CommandHandler {
 
         // Initialize ~/.commandy directory
         self.context.initialize_directory()?;
+        spinner.stop();
+
+        // Let the user pick a model when running interactively
+        if console::user_attended() {
+            self.run_model_wizard()?;
+        }
+
+        let spinner = Spinner::new("Checking llama.cpp...");
+
+        // With several builds installed, the fastest one that works here wins
+        let fastest = LlamaInstaller::new()?.fastest();
+        if let Some(flavor) = fastest {
+            let chosen = Some(flavor.as_str().to_string());
+            if self.settings.model.flavor != chosen {
+                self.settings.model.flavor = chosen;
+                self.settings.save()?;
+            }
+            spinner.stop();
+            return Ok(self.formatter.format_success(&format!(
+                "Commandy initialized successfully (llama.cpp {} build)",
+                flavor.as_str()
+            )));
+        }
 
         // Check llama.cpp binary
-        if let Err(e) = self.ai_client.verify_connection().await {
+        let verified = match &self.ai_client {
+            Some(client) => client.verify_connection().await,
+            None => Err(anyhow::anyhow!("binary not found")),
+        };
+        if let Err(e) = verified {
             spinner.stop();
             return Ok(self.formatter.format_warning(&format!(
                 "llama.cpp binary not available: {e}. Make sure llama.cpp is installed."
@@ -113,99 +1046,1584 @@ impl CommandHandler {
             .format_success("Commandy initialized successfully"))
     }
 
-    fn handle_update(&mut self, model: bool, binary: bool) -> Result<String> {
-        if !model && !binary {
-            return Ok(self
-                .formatter
-                .format_info("Specify --model or --binary to update"));
-        }
+    fn run_model_wizard(&mut self) -> Result<()> {
+        let registry = ModelRegistry::load()?;
+        let total_ram_mb = MemoryInfo::detect().map(|m| m.total_mb);
 
-        let mut messages = Vec::new();
+        let items: Vec<String> = registry
+            .models()
+            .iter()
+            .map(|m| {
+                let fits = match total_ram_mb {
+                    Some(ram) => m.min_ram_mb <= ram,
+                    None => true,
+                };
+                format!(
+                    "{:<20} {:>5} MB  {}{}",
+                    m.name,
+                    m.size_mb,
+                    m.description,
+                    if fits { "" } else { " (needs more RAM)" }
+                )
+            })
+            .collect();
 
-        if model {
-            messages.push("Model update not yet implemented");
-        }
+        // Default to the configured model, else the best fit for this machine
+        let default = registry
+            .models()
+            .iter()
+            .position(|m| m.repo == self.settings.model.model_path)
+            .or_else(|| {
+                let best = registry.recommended_for(total_ram_mb?)?;
+                registry.models().iter().position(|m| m.name == best.name)
+            })
+            .unwrap_or(0);
 
-        if binary {
-            messages.push("Binary update not yet implemented");
+        let selection = dialoguer::Select::new()
+            .with_prompt("Choose a model (downloaded on first use)")
+            .items(&items)
+            .default(default)
+            .interact_opt()?;
+
+        if let Some(index) = selection {
+            self.settings.model.model_path = registry.models()[index].repo.clone();
+            self.settings.save()?;
         }
 
-        Ok(messages.join("\n"))
+        Ok(())
     }
 
-    fn handle_config(&self) -> Result<String> {
-        let mut config_info = format!(
-            "Commandy Configuration:\n\
-            - Config file: {:?}\n\
-            - Context file: {:?}\n\
-            - Cache database: {:?}\n\
-            - Model path: {:?}\n\
-            - Max suggestions: {}\n\
-            - Use colors: {}\n\n",
-            self.settings.get_config_path(),
-            self.context.get_context_file_path(),
-            self.context.get_cache_path(),
-            self.settings.model.model_path,
-            self.settings.output.max_suggestions,
-            self.settings.output.use_colors
-        );
+    fn handle_model(&mut self, action: ModelCommands) -> Result<String> {
+        match action {
+            ModelCommands::List { available: true } => self.list_available_models(),
+            ModelCommands::List { available: false } => self.list_installed_models(),
+            ModelCommands::Use { name, fast } => self.use_model(&name, fast),
+        }
+    }
 
-        // Add cache statistics
-        if let Some(cache) = &self.context.cache {
-            if let Ok(stats) = cache.get_cache_stats() {
-                config_info.push_str(&stats);
-            }
+    fn list_available_models(&self) -> Result<String> {
+        let registry = ModelRegistry::load()?;
+        let total_ram_mb = MemoryInfo::detect().map(|m| m.total_mb);
+
+        let mut output = String::from("Available models:\n");
+        for m in registry.models() {
+            let current = if m.repo == self.settings.model.model_path {
+                "*"
+            } else {
+                " "
+            };
+            output.push_str(&format!(
+                "{current} {:<20} {:>5} MB  {:<6} ctx {:<6} min RAM {:>5} MB  {:<10} {}\n",
+                m.name,
+                m.size_mb,
+                m.quantization,
+                m.context_length,
+                m.min_ram_mb,
+                m.license,
+                m.description
+            ));
         }
 
-        Ok(config_info)
+        if let Some(best) = total_ram_mb.and_then(|ram| registry.recommended_for(ram)) {
+            output.push_str(&format!(
+                "\nRecommended for this machine: {} (commandy model use {})",
+                best.name, best.name
+            ));
+        }
+
+        Ok(output)
     }
 
-    fn handle_clear(&mut self, cache: bool, context: bool) -> Result<String> {
-        let mut messages = Vec::new();
+    fn list_installed_models(&self) -> Result<String> {
+        let mut output = format!(
+            "Configured model: {}\n\nDownloaded models:\n",
+            self.settings.model.model_path
+        );
 
-        if cache {
-            self.context.clear_cache()?;
-            messages.push(self.formatter.format_success("Cache cleared"));
-        }
+        // llama.cpp stores -hf downloads in its own cache directory
+        let cache_dir = ModelIdentity::download_dir();
 
-        if context {
-            self.context.clear_context()?;
-            messages.push(self.formatter.format_success("Context cleared"));
+        let mut found = Vec::new();
+        if let Some(dir) = cache_dir {
+            if let Ok(entries) = std::fs::read_dir(&dir) {
+                for entry in entries.flatten() {
+                    let name = entry.file_name().to_string_lossy().to_string();
+                    if name.ends_with(".gguf") {
+                        let size_mb = entry.metadata().map(|m| m.len() / 1024 / 1024).unwrap_or(0);
+                        found.push(format!("  {name} ({size_mb} MB)"));
+                    }
+                }
+            }
         }
 
-        if !cache && !context {
-            messages.push(
-                self.formatter
-                    .format_info("Specify --cache or --context to clear"),
-            );
+        if found.is_empty() {
+            output.push_str("  (none yet; models download on first use)");
+        } else {
+            found.sort();
+            output.push_str(&found.join("\n"));
         }
 
-        Ok(messages.join("\n"))
+        Ok(output)
     }
 
-    async fn handle_doctor(&self) -> Result<String> {
-        let spinner = Spinner::new("Running diagnostics...");
-        let mut diagnostics = Vec::new();
+    fn use_model(&mut self, name: &str, fast: bool) -> Result<String> {
+        let registry = ModelRegistry::load()?;
 
-        // Check directories
-        let commandy_dir = dirs::home_dir()
-            .ok_or_else(|| anyhow::anyhow!("Could not find home directory"))?
-            .join(".commandy");
+        let repo = match registry.find(name) {
+            Some(entry) => entry.repo.clone(),
+            None if name.contains('/') => name.to_string(),
+            None => {
+                return Err(anyhow::anyhow!(
+                    "Unknown model '{name}'. See: commandy model list --available"
+                ))
+            }
+        };
 
-        if commandy_dir.exists() {
-            diagnostics.push("✓ ~/.commandy directory exists".to_string());
+        if fast {
+            self.settings.model.fast_model_path = Some(repo.clone());
         } else {
-            diagnostics.push("✗ ~/.commandy directory missing (run: commandy init)".to_string());
+            self.settings.model.model_path = repo.clone();
         }
+        self.settings.save()?;
 
-        // Check llama.cpp binary
-        match self.ai_client.verify_connection().await {
-            Ok(_) => diagnostics.push("✓ llama.cpp binary working".to_string()),
-            Err(e) => diagnostics.push(format!("✗ llama.cpp binary: {e}")),
+        let role = if fast { "fast model" } else { "model" };
+        Ok(self
+            .formatter
+            .format_success(&format!("Now using {role}: {repo}")))
+    }
+
+    fn handle_update(
+        &mut self,
+        model: bool,
+        binary: bool,
+        flavor: Option<&str>,
+        build: bool,
+    ) -> Result<String> {
+        if !model && !binary {
+            return Ok(self
+                .formatter
+                .format_info("Specify --model or --binary to update"));
         }
 
-        // Check database
-        if self.context.get_cache_path().exists() {
+        let mut messages = Vec::new();
+
+        if model {
+            messages.push("Model update not yet implemented".to_string());
+        }
+
+        if binary {
+            messages.push(self.update_binary(flavor, build)?);
+        }
+
+        Ok(messages.join("\n"))
+    }
+
+    /// Installs a llama.cpp flavor into ~/.commandy/bin/<flavor> and switches to it
+    ///
+    /// Flavors llama.cpp doesn't publish for this platform can still be switched
+    /// to once a build is in place, or compiled with `build`.
+    fn update_binary(&mut self, flavor: Option<&str>, build: bool) -> Result<String> {
+        let flavor = match flavor.or(self.settings.model.flavor.as_deref()) {
+            Some(name) => Flavor::parse(name)?,
+            None => Flavor::default_for_platform(),
+        };
+        let installer = LlamaInstaller::new()?;
+        let binary = installer.binary(flavor);
+
+        let spinner = Spinner::new(&format!(
+            "Installing the llama.cpp {} build...",
+            flavor.as_str()
+        ));
+        let installed = match build {
+            true => installer.build(flavor, &mut |step| spinner.set_message(step)),
+            false => installer.install(flavor),
+        };
+        spinner.stop();
+        let (warning, message) = match installed {
+            Ok(version) => (
+                None,
+                format!(
+                    "{} the llama.cpp {} build ({version}) in {}",
+                    if build { "Built" } else { "Installed" },
+                    flavor.as_str(),
+                    PathDisplay::shorten(&installer.dir(flavor))
+                ),
+            ),
+            Err(e) if binary.exists() => {
+                let Ok(version) = LlamaInstaller::verify(&binary) else {
+                    return Err(e);
+                };
+                (
+                    Some(
+                        self.formatter
+                            .format_warning(&format!("Not updated: {e:#}")),
+                    ),
+                    format!(
+                        "Using the llama.cpp {} build already in {} ({version})",
+                        flavor.as_str(),
+                        PathDisplay::shorten(&installer.dir(flavor))
+                    ),
+                )
+            }
+            Err(e) => return Err(e),
+        };
+
+        self.settings.model.flavor = Some(flavor.as_str().to_string());
+        self.settings.save()?;
+        let done = self.formatter.format_success(&message);
+        Ok(match warning {
+            Some(warning) => format!("{warning}\n{done}"),
+            None => done,
+        })
+    }
+
+    async fn handle_recall(&mut self, query: &str, limit: usize) -> Result<String> {
+        let history = self.context.get_searchable_history();
+        let matches: Vec<_> = HistorySearch::search(history, query, limit)
+            .into_iter()
+            .filter(|m| m.score >= RECALL_MIN_SCORE)
+            .collect();
+
+        if !matches.is_empty() {
+            return Ok(self.formatter.format_recall(&matches));
+        }
+
+        // Nothing close enough in history, so generate instead
+        let options = PromptOptions {
+            no_cache: false,
+            explain: false,
+            max_suggestions: self.settings.output.max_suggestions,
+            verbose: false,
+            base_command: None,
+            draft: false,
+            attachments: Vec::new(),
+            page_size: None,
+            exclude: Vec::new(),
+            vary: None,
+        };
+        let suggestions = self.handle_prompt(query, options).await?;
+
+        Ok(format!(
+            "{}\n{}",
+            self.formatter
+                .format_info("No close match in your history; generated suggestions instead:"),
+            self.formatter
+                .format_suggestions_static(&suggestions, false)
+        ))
+    }
+
+    async fn handle_oops(&mut self, command: Vec<String>) -> Result<String> {
+        let failed = if command.is_empty() {
+            Self::last_shell_command()?
+        } else {
+            command.join(" ")
+        };
+        debug!("Fixing command: {failed}");
+
+        let suggestions = self.suggest_fixes(&failed, None).await?;
+        if suggestions.is_empty() {
+            return Ok(self
+                .formatter
+                .format_info(&format!("No fix found for: {failed}")));
+        }
+
+        let prompt = format!("fix: {failed}");
+        self.format_suggestions(suggestions, true, &prompt).await
+    }
+
+    /// Risk checks and an explanation for a command, typically one about to be pasted
+    async fn handle_inspect(
+        &mut self,
+        command: &str,
+        clipboard: bool,
+        unpack: bool,
+    ) -> Result<String> {
+        let text = match clipboard {
+            true => Clipboard::new()
+                .and_then(|mut clipboard| clipboard.get_text())
+                .map_err(|e| anyhow::anyhow!("Couldn't read the clipboard: {e}"))?,
+            false => command.to_string(),
+        };
+        let command = text.trim();
+        if command.is_empty() {
+            anyhow::bail!("Nothing to inspect; the clipboard has no text");
+        }
+
+        let risk = RiskClassifier::classify(command);
+        let mut notes = Vec::new();
+        // Without bracketed paste, terminals run each pasted line as it arrives
+        if clipboard && text.trim_start().contains('\n') {
+            let lines = command.lines().count();
+            if lines > 1 {
+                notes.push(format!(
+                    "Has {lines} lines; pasting may run each one before you can look at it"
+                ));
+            } else {
+                notes.push("Ends with a newline, so pasting may run it right away".to_string());
+            }
+        }
+
+        let explanation = match self.has_model() {
+            true => match self.explain_command(command).await {
+                Ok(explanation) => Some(explanation).filter(|e| !e.is_empty()),
+                Err(e) => {
+                    warn!("Failed to explain {command}: {e}");
+                    None
+                }
+            },
+            false => None,
+        };
+
+        let inspection =
+            self.formatter
+                .format_inspection(&text, &risk, &notes, explanation.as_deref());
+        if !unpack {
+            return Ok(inspection);
+        }
+        if PayloadScanner::remote_script(command).is_none() {
+            return Ok(format!(
+                "{inspection}\n\n{}",
+                self.formatter.format_info(
+                    "Nothing to unpack; the command doesn't pipe a download into a shell"
+                )
+            ));
+        }
+        println!("{inspection}\n");
+        self.run_unpacked(command, None).await
+    }
+
+    /// Downloads the script behind a `curl | sh` command, shows it with a
+    /// summary, and runs the saved copy if the user agrees
+    async fn run_unpacked(&mut self, command: &str, prompt: Option<&str>) -> Result<String> {
+        let spinner = Spinner::new("Downloading script...");
+        let unpacked = ScriptUnpacker::unpack(command);
+        spinner.stop();
+        let Some(script) = unpacked? else {
+            anyhow::bail!("Couldn't find a script URL in: {command}");
+        };
+
+        let summary = match &self.ai_client {
+            Some(ai_client) => {
+                let spinner = Spinner::new("Summarizing script...");
+                let result = ai_client.summarize_script(&script.content).await;
+                spinner.stop();
+                match result {
+                    Ok(summary) => Some(summary).filter(|s| !s.is_empty()),
+                    Err(e) => {
+                        warn!("Failed to summarize {}: {e}", script.url);
+                        None
+                    }
+                }
+            }
+            None => None,
+        };
+
+        let preview_lines = self.settings.output.unpack_preview_lines;
+        if !self
+            .formatter
+            .confirm_unpacked_script(&script, summary.as_deref(), preview_lines)
+        {
+            return Ok(self.formatter.format_info(&format!(
+                "Not run; the script is saved at {}",
+                script.path.display()
+            )));
+        }
+
+        let local = script.local_command.as_deref().unwrap_or(command);
+        let output = self.formatter.execute(
+            local,
+            prompt.map(|prompt| (prompt, command)),
+            &mut self.context,
+        );
+        script.remove();
+        Ok(output)
+    }
+
+    /// Rewrites a command for another shell: dialect rules first, then the model
+    async fn handle_translate(
+        &mut self,
+        command: &str,
+        from: &str,
+        to: Option<&str>,
+    ) -> Result<String> {
+        let source =
+            Dialect::from_name(from).ok_or_else(|| anyhow::anyhow!("Unknown shell: {from}"))?;
+        let (to, target) = match to {
+            Some(name) => (
+                name.to_string(),
+                Dialect::from_name(name).ok_or_else(|| anyhow::anyhow!("Unknown shell: {name}"))?,
+            ),
+            None => Dialect::detect(),
+        };
+        if source == target {
+            anyhow::bail!(
+                "The command is already in {} syntax; pick another shell with --to",
+                target.name()
+            );
+        }
+
+        if let Some(translated) = ShellTranslator::translate(command, source, target) {
+            debug!("Translated {command} with {} rules", target.name());
+            let suggestion = Suggestion {
+                command: translated,
+                explanation: None,
+                confidence: 0.9,
+                source: SuggestionSource::Rule,
+                ranking: None,
+            };
+            return Ok(self.formatter.format_translations(&[suggestion]));
+        }
+
+        let ai_client = self.ai_client.as_ref().ok_or_else(|| {
+            anyhow::anyhow!(
+                "The {} rules don't cover this command, and translating it needs a model (run 'commandy init')",
+                target.name()
+            )
+        })?;
+        let spinner = Spinner::new("Translating...");
+        let result = ai_client.translate_command(command, from, &to).await;
+        spinner.stop();
+
+        let translated = result?;
+        if translated.is_empty() {
+            return Ok(self
+                .formatter
+                .format_info(&format!("No {to} translation found for: {command}")));
+        }
+        let suggestion = Suggestion {
+            command: translated,
+            explanation: None,
+            confidence: 0.8,
+            source: SuggestionSource::Model,
+            ranking: None,
+        };
+        Ok(self.formatter.format_translations(&[suggestion]))
+    }
+
+    /// Lists the `[templates]`; main runs a named one as a prompt
+    fn handle_run(&self, template: Option<&str>, vars: &[String]) -> Result<String> {
+        if let Some(name) = template {
+            return self.expand_template(name, vars);
+        }
+        if self.settings.templates.is_empty() {
+            return Ok(self.formatter.format_info(
+                "No templates defined. Add them under [templates] in ~/.commandy/config.toml",
+            ));
+        }
+
+        let mut names: Vec<&String> = self.settings.templates.keys().collect();
+        names.sort();
+        let lines: Vec<String> = names
+            .into_iter()
+            .map(|name| {
+                let template = &self.settings.templates[name];
+                let vars = QueryTemplate::variables(template);
+                let usage = vars.iter().map(|v| format!(" {v}=...")).collect::<String>();
+                format!("{name}{usage}\n    {template}")
+            })
+            .collect();
+        Ok(lines.join("\n"))
+    }
+
+    /// The prompt for a `[templates]` entry with `key=value` arguments filled in
+    pub fn expand_template(&self, name: &str, vars: &[String]) -> Result<String> {
+        let template = self.settings.templates.get(name).ok_or_else(|| {
+            anyhow::anyhow!("No template named '{name}'; `commandy run` lists them")
+        })?;
+        QueryTemplate::expand(template, &QueryTemplate::parse_vars(vars)?)
+    }
+
+    /// Corrections for a broken command: rule-based first, then the model
+    pub async fn suggest_fixes(
+        &mut self,
+        failed: &str,
+        error_output: Option<&str>,
+    ) -> Result<Vec<Suggestion>> {
+        let mut suggestions: Vec<Suggestion> = CommandFixer::new()
+            .fix(failed, error_output)
+            .into_iter()
+            .map(|fix| Suggestion {
+                command: fix.command,
+                explanation: Some(format!("Fixed {}", fix.rule)),
+                confidence: 0.9,
+                source: SuggestionSource::Rule,
+                ranking: None,
+            })
+            .collect();
+
+        // Fall back to the model when no rule applies
+        if suggestions.is_empty() {
+            let Some(ai_client) = &self.ai_client else {
+                return Ok(Vec::new());
+            };
+
+            let context_data = self.context.get_relevant_context(failed)?;
+            let spinner = Spinner::new("Looking for a fix...");
+            let result = ai_client
+                .generate_fixes(
+                    failed,
+                    error_output,
+                    &context_data,
+                    self.settings.output.max_suggestions,
+                )
+                .await;
+            spinner.stop();
+
+            suggestions = result?
+                .into_iter()
+                .filter(|s| s.command.trim() != failed.trim())
+                .collect();
+        }
+
+        // Fixes like `sudo !!` refer to the failed command
+        let suggestions = suggestions
+            .into_iter()
+            .map(|s| Suggestion {
+                command: self.shell().resolve_fix(&s.command, failed),
+                ..s
+            })
+            .collect();
+        Ok(self.postprocessor.process(suggestions))
+    }
+
+    /// Screen-reader-friendly output: no colors, spinners, or full-screen picker
+    pub fn set_plain(&mut self, plain: bool) {
+        self.formatter.set_plain(plain);
+    }
+
+    pub fn is_plain(&self) -> bool {
+        self.formatter.is_plain()
+    }
+
+    /// Nothing on stderr, for wrappers that go by the exit code
+    pub fn set_quiet(&mut self, quiet: bool) {
+        self.formatter.set_quiet(quiet);
+    }
+
+    pub fn is_quiet(&self) -> bool {
+        self.formatter.is_quiet()
+    }
+
+    /// Whether the chosen command was refused for safety
+    pub fn was_blocked(&self) -> bool {
+        self.formatter.was_blocked()
+    }
+
+    /// Runs the `n`th suggestion (from 1) instead of showing the picker
+    pub fn set_pick(&mut self, n: Option<usize>) {
+        self.formatter.set_pick(n.map(|n| n.saturating_sub(1)));
+    }
+
+    /// The `n`th suggestion (from 1) for `--pick`, or why there isn't one
+    pub fn picked<'a>(&self, suggestions: &'a [Suggestion], n: usize) -> Result<&'a Suggestion> {
+        match n.checked_sub(1).and_then(|i| suggestions.get(i)) {
+            Some(suggestion) => Ok(suggestion),
+            None if suggestions.is_empty() => Err(anyhow::anyhow!("No suggestions found")),
+            None => Err(anyhow::anyhow!(
+                "No suggestion {n}: there {} {}",
+                if suggestions.len() == 1 { "is" } else { "are" },
+                suggestions.len()
+            )),
+        }
+    }
+
+    /// Routes selected commands through terminal escape sequences
+    pub fn set_emit_osc(&mut self, emit_osc: bool) {
+        self.formatter.set_emit_osc(emit_osc);
+    }
+
+    /// Enables edit previews from the flag or the `[output] confirm_edits` setting
+    pub fn set_confirm_edits(&mut self, confirm_edits: bool) {
+        self.formatter
+            .set_confirm_edits(confirm_edits || self.settings.output.confirm_edits);
+    }
+
+    /// Generates for, and runs on, `user@host` over SSH instead of this machine
+    pub fn set_target(&mut self, target: Option<&str>) -> Result<()> {
+        let Some(target) = target else {
+            return Ok(());
+        };
+        let remote = RemoteTarget::new(target)?;
+        if let Some(ai_client) = &mut self.ai_client {
+            ai_client.set_check_path(false);
+        }
+        self.formatter.set_remote(Some(remote.clone()));
+        self.remote = Some(remote);
+        Ok(())
+    }
+
+    /// Whether llama.cpp waits for other commandy processes; `commandy serve`
+    /// turns this off, since its queue runs `[server] concurrency` at once
+    pub fn set_model_lock(&mut self, model_lock: bool) {
+        if let Some(ai_client) = &mut self.ai_client {
+            ai_client.set_model_lock(model_lock);
+        }
+    }
+
+    pub fn set_use_daemon(&mut self, use_daemon: bool) {
+        if let Some(ai_client) = &mut self.ai_client {
+            ai_client.set_use_daemon(use_daemon);
+        }
+    }
+
+    /// Starts `commandy daemon` for `commandy serve`, unless it is running,
+    /// models run elsewhere (`server_url`, fixtures), or this run is read-only
+    pub async fn ensure_daemon(&self) -> Result<()> {
+        let local = self
+            .ai_client
+            .as_ref()
+            .is_some_and(|ai_client| ai_client.runs_llama_cpp());
+        let files = DaemonFiles::new()?;
+        if !local || self.read_only || files.running().is_some() {
+            return Ok(());
+        }
+        let pid = self.start_daemon(&files).await?;
+        eprintln!(
+            "{}",
+            self.formatter.format_info(&format!(
+                "Started the daemon (pid {pid}) to keep the model loaded; requests run llama.cpp until its llama-server is ready"
+            ))
+        );
+        Ok(())
+    }
+
+    /// Spawns the daemon and waits for it to report in; returns its process id
+    async fn start_daemon(&self, files: &DaemonFiles) -> Result<u32> {
+        // Fails here, in the foreground, if llama-server can't be found
+        Supervisor::new(&self.settings)?;
+        let pid = Supervisor::spawn_detached(files)?;
+        for _ in 0..DAEMON_WAIT_TICKS {
+            if files.running().is_some() {
+                return Ok(pid);
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        Err(anyhow::anyhow!(
+            "The daemon exited right away; see 'commandy daemon logs'"
+        ))
+    }
+
+    /// Turns off running, copying, and saving anything, for demos and untrusted automation
+    pub fn set_read_only(&mut self, read_only: bool) {
+        self.read_only = read_only;
+        self.formatter.set_read_only(read_only);
+        self.context.set_read_only(read_only);
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// The profile set with [`set_profile`](Self::set_profile), if any
+    pub fn profile(&self) -> Option<&str> {
+        self.profile.as_deref()
+    }
+
+    /// Name of a subcommand that writes files or settings, for --read-only to refuse
+    fn writes_files(command: &Commands) -> Option<&'static str> {
+        match command {
+            Commands::Init => Some("init"),
+            Commands::Update { .. } => Some("update"),
+            Commands::Model {
+                action: ModelCommands::Use { .. },
+            } => Some("model use"),
+            Commands::Packs {
+                action: PackCommands::Add { .. } | PackCommands::Remove { .. },
+            } => Some("packs"),
+            Commands::Pin {
+                action: PinCommands::Add { .. } | PinCommands::Remove { .. },
+            } => Some("pin"),
+            Commands::Cache {
+                action: CacheCommands::Warm { .. },
+            } => Some("cache warm"),
+            Commands::Cache {
+                action: CacheCommands::Export { .. },
+            } => Some("cache export"),
+            Commands::Cache {
+                action: CacheCommands::Import { .. },
+            } => Some("cache import"),
+            Commands::Learned { out: Some(_) } => Some("learned --out"),
+            Commands::Bugreport { .. } => Some("bugreport"),
+            Commands::Export { .. } => Some("export"),
+            Commands::Import { .. } => Some("import"),
+            Commands::Session {
+                action: SessionCommands::Export { out: Some(_), .. },
+            } => Some("session export --out"),
+            Commands::Session {
+                action: SessionCommands::Delete { .. },
+            } => Some("session delete"),
+            Commands::Clear { .. } => Some("clear"),
+            Commands::SelfUpdate { check: false } => Some("self-update"),
+            Commands::Daemon {
+                action: DaemonCommands::Start | DaemonCommands::Run,
+            } => Some("daemon start"),
+            Commands::Daemon {
+                action: DaemonCommands::Stop,
+            } => Some("daemon stop"),
+            Commands::Daemon {
+                action: DaemonCommands::Restart,
+            } => Some("daemon restart"),
+            _ => None,
+        }
+    }
+
+    /// Enforces the execution limits of the profile from the flag or `[general] profile`
+    pub fn set_profile(&mut self, profile: Option<&str>) -> Result<()> {
+        let Some(name) = profile.or(self.settings.general.profile.as_deref()) else {
+            return Ok(());
+        };
+        let config = self.settings.profiles.get(name).cloned().ok_or_else(|| {
+            anyhow::anyhow!("Unknown profile {name:?}: add [profiles.{name}] to the config file")
+        })?;
+        self.formatter
+            .set_policy(Some(RiskPolicy::new(name, config)));
+        self.profile = Some(name.to_string());
+        Ok(())
+    }
+
+    /// Enables script unpacking from the flag or the `[output] unpack_scripts` setting
+    pub fn set_unpack_scripts(&mut self, unpack_scripts: bool) {
+        self.formatter
+            .set_unpack_scripts(unpack_scripts || self.settings.output.unpack_scripts);
+    }
+
+    /// Enables sandbox runs from the flag or the `[sandbox] enabled` setting
+    pub fn set_sandbox(&mut self, sandbox: bool) {
+        let config =
+            (sandbox || self.settings.sandbox.enabled).then(|| self.settings.sandbox.clone());
+        self.formatter.set_sandbox(config);
+    }
+
+    pub fn set_log_file(&mut self, log_file: Option<PathBuf>) {
+        self.log_file = log_file;
+    }
+
+    pub fn settings(&self) -> &Settings {
+        &self.settings
+    }
+
+    /// Default number of suggestions from config
+    pub fn max_suggestions(&self) -> usize {
+        self.settings.output.max_suggestions
+    }
+
+    /// Suggestions for `-n`, or `[output] max_suggestions` without it, at most
+    /// `[output] suggestion_limit`
+    pub fn suggestion_count(&self, requested: Option<usize>) -> usize {
+        let limit = self.settings.output.suggestion_limit.max(1);
+        let count = requested.unwrap_or(self.settings.output.max_suggestions);
+        if count > limit && !self.is_quiet() {
+            eprintln!(
+                "{}",
+                self.formatter.format_warning(&format!(
+                    "Showing at most {limit} suggestions ([output] suggestion_limit)"
+                ))
+            );
+        }
+        count.clamp(1, limit)
+    }
+
+    /// Suggestions per page of the picker
+    pub fn page_size(&self) -> usize {
+        self.settings.output.page_size
+    }
+
+    /// True when a llama.cpp backend is available
+    pub fn has_model(&self) -> bool {
+        self.ai_client.is_some()
+    }
+
+    /// Plain-language explanation of what a command does, cached per command
+    // Takes &mut so futures holding the handler stay Send for `commandy serve`
+    pub async fn explain_command(&mut self, command: &str) -> Result<String> {
+        self.explain_in(command, None).await
+    }
+
+    /// Explains `command` in `language`, or in English
+    async fn explain_in(&mut self, command: &str, language: Option<Language>) -> Result<String> {
+        // Each language's explanation is cached apart, as if after a comment
+        let key = match language {
+            Some(language) => format!("{command} # {}", language.code),
+            None => command.to_string(),
+        };
+        let ttl_days = self.settings.cache.explanation_ttl_days;
+        if let Some(explanation) = self.context.get_cached_explanation(&key, ttl_days) {
+            debug!("Using cached explanation for: {command}");
+            return Ok(explanation);
+        }
+
+        let ai_client = self.ai_client.as_ref().ok_or_else(|| {
+            anyhow::anyhow!("llama.cpp binary not found (run 'commandy init' to install it)")
+        })?;
+
+        let spinner = Spinner::new("Explaining...");
+        let result = ai_client
+            .explain_command(command, language.map(|language| language.name))
+            .await;
+        spinner.stop();
+
+        let explanation = result?;
+        if !explanation.is_empty() {
+            self.context.cache_explanation(&key, &explanation);
+        }
+        Ok(explanation)
+    }
+
+    /// A tldr-style summary of a program from its man page, for the picker's info pane
+    async fn tool_summary(&mut self, tool: &str) -> Result<String> {
+        let ttl_days = self.settings.cache.explanation_ttl_days;
+        if let Some(summary) = self.context.get_cached_tool_summary(tool, ttl_days) {
+            debug!("Using cached summary of {tool}");
+            return Ok(summary);
+        }
+
+        let ai_client = self.ai_client.as_ref().ok_or_else(|| {
+            anyhow::anyhow!("llama.cpp binary not found (run 'commandy init' to install it)")
+        })?;
+        let docs =
+            ToolDocs::read(tool).ok_or_else(|| anyhow::anyhow!("it has no man page or --help"))?;
+
+        let spinner = Spinner::new(&format!("Summarizing {tool}..."));
+        let result = ai_client.summarize_tool(tool, &docs).await;
+        spinner.stop();
+
+        let summary = result?;
+        if !summary.is_empty() {
+            self.context.cache_tool_summary(tool, &summary);
+        }
+        Ok(summary)
+    }
+
+    /// Offers to sum up what the command just run printed, and adds the summary
+    /// after `message`
+    async fn summarize_output(&mut self, message: String, captured: &CapturedOutput) -> String {
+        if !self.settings.output.summarize_output || captured.is_empty() {
+            return message;
+        }
+        let Some(ai_client) = self.ai_client.as_ref() else {
+            return message;
+        };
+        if !self.formatter.offer_summary() {
+            return message;
+        }
+
+        let spinner = Spinner::new("Summarizing output...");
+        let result = ai_client
+            .summarize_output(&captured.command, captured.exit_code, &captured.text())
+            .await;
+        spinner.stop();
+        let summary = match result {
+            Ok(summary) if !summary.is_empty() => self.formatter.format_output_summary(&summary),
+            Ok(_) => self.formatter.format_info("The model gave no summary"),
+            Err(e) => self
+                .formatter
+                .format_warning(&format!("Could not summarize the output: {e}")),
+        };
+        match message.is_empty() {
+            true => summary,
+            false => format!("{message}\n{summary}"),
+        }
+    }
+
+    /// After a chosen command runs: a summary of its output, then fixes when it
+    /// failed or likely next commands when it didn't, picked from a short list
+    /// and followed up the same way; returns the last run's message
+    async fn follow_up(
+        &mut self,
+        original_prompt: &str,
+        mut captured: CapturedOutput,
+        mut message: String,
+    ) -> String {
+        loop {
+            message = self.summarize_output(message, &captured).await;
+            if !io::stdin().is_terminal() {
+                return message;
+            }
+            let (command, feedback) = if captured.failed() && self.settings.output.triage_failures {
+                // A fix that works is what the request should have got
+                let fix = self.offer_fixes(&captured, &mut message).await;
+                (fix, Some(original_prompt))
+            } else if self.settings.output.suggest_next {
+                let next = self
+                    .offer_next_steps(original_prompt, &captured, &mut message)
+                    .await;
+                (next, None)
+            } else {
+                (None, None)
+            };
+            let Some(command) = command else {
+                return message;
+            };
+            message = self
+                .formatter
+                .run_follow_up(&command, feedback, &mut self.context);
+            match self.formatter.take_captured() {
+                Some(next) => captured = next,
+                None => return message,
+            }
+        }
+    }
+
+    /// Looks for fixes for a failed command from what it wrote to standard
+    /// error, and asks which to run; `message` is shown above the list
+    async fn offer_fixes(
+        &mut self,
+        captured: &CapturedOutput,
+        message: &mut String,
+    ) -> Option<String> {
+        let errors = captured.errors();
+        let fixes = match self
+            .suggest_fixes(&captured.command, errors.as_deref())
+            .await
+        {
+            Ok(fixes) => fixes,
+            Err(e) => {
+                warn!(error = %e, "Failed to look for a fix");
+                return None;
+            }
+        };
+        let fixes: Vec<Suggestion> = fixes
+            .into_iter()
+            .filter(|fix| fix.command.trim() != captured.command.trim())
+            .take(self.settings.output.max_suggestions)
+            .collect();
+        if fixes.is_empty() {
+            return None;
+        }
+        Self::flush_message(message);
+        let index = self.formatter.pick_fix(&captured.command, &fixes)?;
+        Some(fixes[index].command.clone())
+    }
+
+    /// Asks the model for likely next commands and which to run; `message` is
+    /// shown above the list
+    async fn offer_next_steps(
+        &mut self,
+        original_prompt: &str,
+        captured: &CapturedOutput,
+        message: &mut String,
+    ) -> Option<String> {
+        let suggestions = match self.next_steps(original_prompt, captured).await {
+            Ok(suggestions) if !suggestions.is_empty() => suggestions,
+            Ok(_) => return None,
+            Err(e) => {
+                warn!(error = %e, "Failed to suggest next steps");
+                return None;
+            }
+        };
+        Self::flush_message(message);
+        let index = self.formatter.pick_next_step(&suggestions)?;
+        Some(suggestions[index].command.clone())
+    }
+
+    /// Prints a run's message before a follow-up list, so it isn't shown again at the end
+    fn flush_message(message: &mut String) {
+        if !message.is_empty() {
+            println!("{message}");
+            message.clear();
+        }
+    }
+
+    /// Commands likely to follow `captured`, checked and cleaned up like suggestions
+    async fn next_steps(
+        &mut self,
+        original_prompt: &str,
+        captured: &CapturedOutput,
+    ) -> Result<Vec<Suggestion>> {
+        let Some(ai_client) = &self.ai_client else {
+            return Ok(Vec::new());
+        };
+        let context_data = self.context.get_relevant_context(original_prompt)?;
+        let spinner = Spinner::new("Looking for next steps...");
+        let result = ai_client
+            .generate_next_steps(
+                original_prompt,
+                &captured.command,
+                captured.exit_code,
+                &captured.text(),
+                &context_data,
+                self.settings.output.max_suggestions,
+            )
+            .await;
+        spinner.stop();
+
+        let suggestions = result?
+            .into_iter()
+            .filter(|s| s.command.trim() != captured.command.trim())
+            .map(|s| Suggestion {
+                command: self.shell().resolve(&s.command),
+                ..s
+            })
+            .collect();
+        Ok(self.postprocessor.process(suggestions))
+    }
+
+    /// Fills in explanations the model didn't give, from the cache where possible
+    async fn add_explanations(&mut self, suggestions: &mut [Suggestion]) {
+        // Explanations come in the language the request was written in
+        let language = self
+            .request_language
+            .filter(|_| self.settings.language.explain_in_request_language);
+        for suggestion in suggestions.iter_mut() {
+            if suggestion.explanation.is_some() || !self.has_model() {
+                continue;
+            }
+            match self.explain_in(&suggestion.command, language).await {
+                Ok(explanation) if !explanation.is_empty() => {
+                    suggestion.explanation = Some(explanation)
+                }
+                Ok(_) => {}
+                Err(e) => warn!("Failed to explain {}: {e}", suggestion.command),
+            }
+        }
+    }
+
+    /// Finds the most recent shell command that wasn't a commandy invocation
+    fn last_shell_command() -> Result<String> {
+        CacheManager::read_shell_history()?
+            .into_iter()
+            .rev()
+            .map(|entry| entry.command)
+            .find(|cmd| {
+                let first = cmd.split_whitespace().next().unwrap_or("");
+                !cmd.trim().is_empty() && first != "commandy" && first != "oops"
+            })
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "No previous command found; pass it explicitly: commandy oops -- <command>"
+                )
+            })
+    }
+
+    fn handle_experiment_stats(&self) -> Result<String> {
+        let learning = self.context.learning().ok_or_else(|| {
+            anyhow::anyhow!("Learning store not available. Run 'commandy init' first.")
+        })?;
+        let stats = learning.experiment_stats()?;
+        if stats.is_empty() {
+            return Ok(self.formatter.format_info(
+                "No experiment results yet (set name and variant under [experiment])",
+            ));
+        }
+
+        let mut output = String::new();
+        let mut experiments: Vec<&str> = stats.iter().map(|s| s.experiment.as_str()).collect();
+        experiments.dedup();
+        for experiment in experiments {
+            let current = match self.settings.experiment.name.as_deref() == Some(experiment) {
+                true => " (running)",
+                false => "",
+            };
+            output.push_str(&format!("Experiment {experiment}{current}:\n"));
+            let arms: Vec<_> = stats
+                .iter()
+                .filter(|s| s.experiment == experiment)
+                .collect();
+            for arm in &arms {
+                output.push_str(&format!(
+                    "  {:<8} {:>5} shown  {:>5} used ({:>3.0}%)  {:>5} ran successfully\n",
+                    arm.arm,
+                    arm.runs,
+                    arm.accepted,
+                    arm.acceptance_rate() * 100.0,
+                    arm.succeeded
+                ));
+            }
+            if arms.len() < 2 || arms.iter().any(|a| a.runs < MIN_EXPERIMENT_RUNS) {
+                output.push_str(&format!(
+                    "  Too few queries to compare yet; wait for {MIN_EXPERIMENT_RUNS} in each arm\n"
+                ));
+            }
+        }
+        Ok(output.trim_end().to_string())
+    }
+
+    fn handle_stats(&self, host: &str, port: u16, token: Option<String>) -> Result<String> {
+        let url = format!("http://{host}:{port}/stats");
+        let headers: Vec<String> = token
+            .or_else(AuthToken::load)
+            .map(|t| format!("Authorization: Bearer {t}"))
+            .into_iter()
+            .collect();
+
+        let body = HttpClient::fetch_text_with_headers(&url, &headers)
+            .map_err(|e| anyhow::anyhow!("Could not reach commandy serve at {url}: {e}"))?;
+        let stats: QueueStats = serde_json::from_str(&body)?;
+
+        let mut output = format!(
+            "Server stats ({host}:{port}):\n\
+            - Concurrency: {}\n\
+            - Active: {}\n\
+            - Queued: {}\n\
+            - Completed: {}\n\
+            - Rejected as busy: {}\n\
+            - Avg generation time: {} ms\n\
+            - Avg queue wait: {} ms (max {} ms)",
+            stats.concurrency,
+            stats.active,
+            stats.queued,
+            stats.completed,
+            stats.rejected,
+            stats.avg_service_ms,
+            stats.avg_wait_ms,
+            stats.max_wait_ms
+        );
+
+        if !stats.requests_by_client.is_empty() {
+            let mut clients: Vec<_> = stats.requests_by_client.into_iter().collect();
+            clients.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+            output.push_str("\n\nRequests by client:");
+            for (client, count) in clients {
+                output.push_str(&format!("\n  {client:<24} {count}"));
+            }
+        }
+
+        if !stats.requests_by_category.is_empty() {
+            let mut categories: Vec<_> = stats.requests_by_category.into_iter().collect();
+            categories.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+            output.push_str("\n\nSuggestions by category:");
+            for (category, count) in categories {
+                output.push_str(&format!("\n  {category:<24} {count}"));
+            }
+        }
+
+        Ok(output)
+    }
+
+    fn handle_config(&self) -> Result<String> {
+        let mut config_info = format!(
+            "Commandy Configuration:\n\
+            - Config file: {:?}\n\
+            - Learning store: {:?}\n\
+            - Cache database: {:?}\n\
+            - Model path: {:?}\n\
+            - Fast model: {}\n\
+            - Max suggestions: {}\n\
+            - Use colors: {}\n\
+            - Theme: {}\n\n",
+            self.settings.get_config_path(),
+            self.context.get_learning_db_path(),
+            self.context.get_cache_path(),
+            self.settings.model.model_path,
+            self.settings
+                .model
+                .fast_model_path
+                .as_deref()
+                .unwrap_or("(same as main model)"),
+            self.settings.output.max_suggestions,
+            self.settings.output.use_colors,
+            match self.formatter.theme() {
+                Some(theme) => format!("{} ({})", theme.name(), theme.depth().label()),
+                None => "off".to_string(),
+            }
+        );
+
+        // Add cache statistics
+        if let Some(cache) = self.context.cache() {
+            if let Ok(stats) = cache.get_cache_stats() {
+                config_info.push_str(&stats);
+            }
+        }
+
+        if let Some(learning) = self.context.learning() {
+            if let Ok(count) = learning.pattern_count() {
+                config_info.push_str(&format!("\nLearned patterns: {count}\n"));
+            }
+        }
+
+        Ok(config_info)
+    }
+
+    fn handle_packs(&self, action: PackCommands) -> Result<String> {
+        let packs = self.context.packs();
+        match action {
+            PackCommands::List => {
+                if packs.packs().is_empty() {
+                    return Ok(self.formatter.format_info(
+                        "No pattern packs installed (commandy packs add <path|url>)",
+                    ));
+                }
+                let mut output = String::from("Installed pattern packs:\n");
+                for pack in packs.packs() {
+                    output.push_str(&format!(
+                        "  {:<20} {:<8} {:>3} examples {:>3} deny rules  {}\n",
+                        pack.name,
+                        pack.version.as_deref().unwrap_or("-"),
+                        pack.examples.len(),
+                        pack.deny.len(),
+                        pack.description.as_deref().unwrap_or("")
+                    ));
+                }
+                Ok(output)
+            }
+            PackCommands::Add { source } => {
+                let pack = packs.install(&source)?;
+                Ok(self.formatter.format_success(&format!(
+                    "Installed pack '{}' ({} examples, {} deny rules)",
+                    pack.name,
+                    pack.examples.len(),
+                    pack.deny.len()
+                )))
+            }
+            PackCommands::Remove { name } => {
+                packs.remove(&name)?;
+                Ok(self
+                    .formatter
+                    .format_success(&format!("Removed pack '{name}'")))
+            }
+        }
+    }
+
+    async fn handle_cache(&mut self, action: CacheCommands) -> Result<String> {
+        match action {
+            CacheCommands::Warm { file, top } => {
+                let queries = match file {
+                    Some(file) => BatchTask::read_all(&file)?
+                        .into_iter()
+                        .map(|task| task.task)
+                        .collect(),
+                    None => self.warm_queries(top.unwrap_or(self.settings.cache.warm_top)),
+                };
+                self.warm_cache(queries).await
+            }
+            CacheCommands::Export { out, min_uses } => {
+                let cache = self.context.cache().ok_or_else(|| {
+                    anyhow::anyhow!("Cache not available. Run 'commandy init' first.")
+                })?;
+                let export =
+                    SeedCache::collect(cache, ModelIdentity::of(&self.settings), min_uses)?;
+                export.seed.write(&out)?;
+
+                let mut report = format!(
+                    "Exported {} cached suggestions for {} to {}",
+                    export.seed.suggestions.len(),
+                    export.seed.model,
+                    out.display()
+                );
+                if export.withheld > 0 {
+                    report.push_str(&format!(
+                        "\n  Left out {}: failing, used too few times, or holding secrets or personal details",
+                        export.withheld
+                    ));
+                }
+                Ok(self.formatter.format_success(&report))
+            }
+            CacheCommands::Import { seed, any_model } => {
+                let seed = SeedCache::read(&seed)?;
+                if !any_model {
+                    seed.check_model(&ModelIdentity::of(&self.settings))?;
+                }
+                if self.context.cache().is_none() {
+                    self.context.initialize_directory()?;
+                }
+                let cache = self.context.cache_mut().ok_or_else(|| {
+                    anyhow::anyhow!("Cache not available. Run 'commandy init' first.")
+                })?;
+                let count = cache.import_seed(&seed.suggestions)?;
+                Ok(self.formatter.format_success(&format!(
+                    "Imported {count} cached suggestions made with {} on {}",
+                    seed.model.model,
+                    seed.created_at.format("%Y-%m-%d")
+                )))
+            }
+        }
+    }
+
+    /// `[cache] warm_queries`, then the `top` most frequent requests, without repeats
+    fn warm_queries(&self, top: usize) -> Vec<String> {
+        let mut queries: Vec<String> = Vec::new();
+        let frequent = self.context.frequent_prompts(top);
+        for query in self.settings.cache.warm_queries.iter().chain(&frequent) {
+            let query = query.trim();
+            if !query.is_empty() && !queries.iter().any(|q| q.eq_ignore_ascii_case(query)) {
+                queries.push(query.to_string());
+            }
+        }
+        queries
+    }
+
+    /// Generates suggestions for each request and caches the top one, to be
+    /// served the next time it is asked without waiting for the model
+    async fn warm_cache(&mut self, queries: Vec<String>) -> Result<String> {
+        if queries.is_empty() {
+            return Ok(self.formatter.format_info(
+                "Nothing to warm: no requests in the history yet, and [cache] warm_queries is empty",
+            ));
+        }
+        // Heuristic answers are never cached, so there is nothing to warm without a model
+        if self.ai_client.is_none() {
+            return Err(anyhow::anyhow!(
+                "llama.cpp binary not found (run 'commandy init' to install it)"
+            ));
+        }
+
+        let total = queries.len();
+        let (mut warmed, mut skipped, mut failed) = (0, 0, 0);
+        for (i, query) in queries.iter().enumerate() {
+            eprintln!("[{}/{total}] {query}", i + 1);
+            // Answers about "yesterday" are never cached
+            if TimeContext::anchored(query) {
+                skipped += 1;
+                continue;
+            }
+            let options = PromptOptions {
+                no_cache: true,
+                explain: false,
+                max_suggestions: self.settings.output.max_suggestions,
+                verbose: false,
+                base_command: None,
+                draft: true,
+                attachments: Vec::new(),
+                page_size: None,
+                exclude: Vec::new(),
+                vary: None,
+            };
+            let top = match self.handle_prompt(query, options).await {
+                Ok(suggestions) => suggestions
+                    .into_iter()
+                    .find(|s| s.source == SuggestionSource::Model),
+                Err(e) => {
+                    warn!("Failed to warm \"{query}\": {e}");
+                    None
+                }
+            };
+            match top {
+                Some(top) => {
+                    self.context.warm_suggestion(query, &top)?;
+                    warmed += 1;
+                }
+                None => failed += 1,
+            }
+        }
+
+        let mut report = format!("Warmed {warmed} of {total} requests");
+        if skipped > 0 {
+            report.push_str(&format!(", skipped {skipped} about dates"));
+        }
+        if failed > 0 {
+            report.push_str(&format!(", {failed} had no model suggestion"));
+        }
+        Ok(self.formatter.format_success(&report))
+    }
+
+    /// Warms the cache after the model or llama.cpp changed, when `[cache] warm_after_update` asks
+    async fn warm_after_update(&mut self, updated: bool, output: String) -> Result<String> {
+        if !updated || !self.settings.cache.warm_after_update {
+            return Ok(output);
+        }
+        // The new model or binary only takes effect in a new client
+        self.ai_client = LlamaCppClient::new(&self.settings).ok();
+        self.context.set_model(&ModelIdentity::of(&self.settings))?;
+        let queries = self.warm_queries(self.settings.cache.warm_top);
+        let report = match self.warm_cache(queries).await {
+            Ok(report) => report,
+            Err(e) => self
+                .formatter
+                .format_warning(&format!("Cache not warmed: {e}")),
+        };
+        Ok(format!("{output}\n{report}"))
+    }
+
+    fn handle_pin(&mut self, action: PinCommands) -> Result<String> {
+        match action {
+            PinCommands::List => {
+                let pins = self.context.pins().pins();
+                if pins.is_empty() {
+                    return Ok(self
+                        .formatter
+                        .format_info("No pinned commands (commandy pin add <pattern> <command>)"));
+                }
+                let mut output = String::from("Pinned commands:\n");
+                for (i, pin) in pins.iter().enumerate() {
+                    output.push_str(&format!(
+                        "  {:>2}. {:<30} {}\n",
+                        i + 1,
+                        pin.pattern,
+                        pin.command
+                    ));
+                }
+                Ok(output)
+            }
+            PinCommands::Add { pattern, command } => {
+                let command = command.join(" ");
+                self.context.pins_mut().add(&pattern, &command)?;
+                Ok(self.formatter.format_success(&format!(
+                    "Pinned '{command}' for requests matching '{pattern}'"
+                )))
+            }
+            PinCommands::Remove { pin } => {
+                let removed = self.context.pins_mut().remove(&pin)?;
+                Ok(self
+                    .formatter
+                    .format_success(&format!("Removed the pin for '{}'", removed.pattern)))
+            }
+        }
+    }
+
+    fn handle_learned(&self, out: Option<&Path>) -> Result<String> {
+        let learning = self.context.learning().ok_or_else(|| {
+            anyhow::anyhow!("Learning store not available. Run 'commandy init' first.")
+        })?;
+        let text = learning.export_text()?;
+
+        match out {
+            Some(path) => {
+                std::fs::write(path, text)?;
+                Ok(self.formatter.format_success(&format!(
+                    "Exported {} learned patterns to {}",
+                    learning.pattern_count()?,
+                    path.display()
+                )))
+            }
+            None => Ok(text),
+        }
+    }
+
+    fn handle_export(&self, out: &Path, include_cache: bool) -> Result<String> {
+        let cache = if include_cache {
+            self.context.cache()
+        } else {
+            None
+        };
+        let bundle = Bundle::collect(
+            &self.settings.get_config_path()?,
+            self.context.learning(),
+            cache,
+        )?;
+        bundle.write(out)?;
+
+        Ok(self.formatter.format_success(&format!(
+            "Exported {} to {}",
+            bundle.manifest.entries.join(", "),
+            out.display()
+        )))
+    }
+
+    fn handle_import(&mut self, path: &Path, replace_settings: bool) -> Result<String> {
+        let bundle = Bundle::read(path)?;
+        let mut messages = Vec::new();
+
+        // Settings go first so `initialize_directory` doesn't write defaults over them
+        let config_path = self.settings.get_config_path()?;
+        let backup_dir = self.context.get_commandy_dir().join("backups");
+        let outcome = bundle.apply_settings(&config_path, &backup_dir, replace_settings)?;
+        messages.push(match outcome {
+            SettingsOutcome::Installed => "Settings: installed from bundle".to_string(),
+            SettingsOutcome::Merged => {
+                "Settings: added missing keys, kept local values (--replace-settings to overwrite)"
+                    .to_string()
+            }
+            SettingsOutcome::Replaced => format!(
+                "Settings: replaced (previous config saved in {})",
+                backup_dir.display()
+            ),
+            SettingsOutcome::Absent => "Settings: none in bundle".to_string(),
+        });
+
+        if self.context.learning().is_none() || self.context.cache().is_none() {
+            self.context.initialize_directory()?;
+        }
+
+        if let Some(learning) = self.context.learning() {
+            let count = bundle.import_learning(learning)?;
+            messages.push(format!("Learned patterns: merged {count}"));
+        }
+        if bundle.has_cache() {
+            if let Some(cache) = self.context.cache_mut() {
+                let count = bundle.import_cache(cache)?;
+                messages.push(format!("Cached suggestions: merged {count}"));
+            }
+        }
+
+        Ok(format!(
+            "{}\n{}",
+            self.formatter.format_success(&format!(
+                "Imported bundle from commandy {} ({})",
+                bundle.manifest.commandy_version,
+                bundle.manifest.created_at.format("%Y-%m-%d %H:%M UTC")
+            )),
+            messages.join("\n")
+        ))
+    }
+
+    fn handle_clear(&mut self, cache: bool, context: bool) -> Result<String> {
+        let mut messages = Vec::new();
+
+        if cache {
+            self.context.clear_cache()?;
+            PromptCache::from_home()?.clear()?;
+            messages.push(self.formatter.format_success("Cache cleared"));
+        }
+
+        if context {
+            self.context.clear_context()?;
+            messages.push(self.formatter.format_success("Context cleared"));
+        }
+
+        if !cache && !context {
+            messages.push(
+                self.formatter
+                    .format_info("Specify --cache or --context to clear"),
+            );
+        }
+
+        Ok(messages.join("\n"))
+    }
+
+    async fn handle_doctor(&self) -> Result<String> {
+        let spinner = Spinner::new("Running diagnostics...");
+        let mut diagnostics = Vec::new();
+
+        // Check directories
+        let commandy_dir = dirs::home_dir()
+            .ok_or_else(|| anyhow::anyhow!("Could not find home directory"))?
+            .join(".commandy");
+
+        if commandy_dir.exists() {
+            diagnostics.push("✓ ~/.commandy directory exists".to_string());
+        } else {
+            diagnostics.push("✗ ~/.commandy directory missing (run: commandy init)".to_string());
+        }
+
+        let platform = Platform::detect();
+        diagnostics.push(format!("✓ Platform: {}", platform.describe()));
+        // Windows drives are slow through WSL and can't always mark files executable
+        if platform.wsl.is_some() && commandy_dir.starts_with("/mnt") {
+            diagnostics.push(format!(
+                "⚠ {} is on a Windows drive; set HOME to your WSL home (/home/<user>) \
+                so the llama.cpp binary and models live on the Linux filesystem",
+                commandy_dir.display()
+            ));
+        }
+
+        // Check llama.cpp binary
+        match &self.ai_client {
+            Some(client) => match client.verify_connection().await {
+                Ok(_) => diagnostics.push("✓ llama.cpp binary working".to_string()),
+                Err(e) => diagnostics.push(format!("✗ llama.cpp binary: {e}")),
+            },
+            None => diagnostics.push(
+                "✗ llama.cpp binary not found (offline heuristics only; run: commandy init)"
+                    .to_string(),
+            ),
+        }
+        let installed = LlamaInstaller::new()?.installed();
+        if !installed.is_empty() {
+            let names: Vec<&str> = installed.iter().map(|f| f.as_str()).collect();
+            diagnostics.push(format!(
+                "✓ llama.cpp builds installed: {} (using {})",
+                names.join(", "),
+                self.settings
+                    .model
+                    .flavor
+                    .as_deref()
+                    .unwrap_or("the default binary")
+            ));
+        }
+        diagnostics.extend(self.device_diagnostics());
+
+        // Check database
+        if self.context.get_cache_path().exists() {
             diagnostics.push("✓ Cache database exists".to_string());
         } else {
             diagnostics.push("✗ Cache database missing".to_string());
@@ -214,6 +2632,22 @@ impl CommandHandler {
         // Check model configuration
         diagnostics.push(format!("✓ Using model: {}", self.settings.model.model_path));
         diagnostics.push("✓ Model downloads automatically on first use".to_string());
+        diagnostics.push(self.check_model_memory());
+
+        // Check plugins
+        for plugin in self.plugins().plugins() {
+            let capabilities: Vec<String> = plugin
+                .capabilities
+                .iter()
+                .map(|c| format!("{c:?}").to_lowercase())
+                .collect();
+            diagnostics.push(format!(
+                "✓ Plugin {} ({}) at {}",
+                plugin.name,
+                capabilities.join(", "),
+                plugin.path.display()
+            ));
+        }
 
         spinner.stop();
         Ok(format!(
@@ -222,12 +2656,828 @@ impl CommandHandler {
         ))
     }
 
+    async fn handle_eval(
+        &self,
+        model: Option<String>,
+        dataset: Option<PathBuf>,
+        limit: Option<usize>,
+        json: bool,
+    ) -> Result<String> {
+        let mut cases = match &dataset {
+            Some(path) => EvalDataset::load(path)?,
+            None => EvalDataset::builtin()?,
+        };
+        if let Some(limit) = limit {
+            cases.truncate(limit);
+        }
+        let idioms = EvalDataset::expansion_idioms()?;
+
+        let mut settings = self.settings.clone();
+        if let Some(model) = model {
+            settings.model.model_path = model;
+        }
+        let client = LlamaCppClient::new(&settings)?;
+
+        let evaluator = Evaluator::new(
+            &client,
+            &self.context,
+            &self.postprocessor,
+            self.max_suggestions(),
+        );
+        let spinner = Spinner::new(&format!("Evaluating {} queries...", cases.len()));
+        let report = evaluator
+            .run(&settings.model.model_path, &cases, &idioms)
+            .await;
+        spinner.stop();
+
+        if json {
+            return Ok(serde_json::to_string_pretty(&report)?);
+        }
+
+        let mut output = String::new();
+        for failure in &report.idiom_failures {
+            output.push_str(&format!(
+                "⚠ expansion guard: {}\n    got:      {}\n    expected: {}\n",
+                failure.command, failure.got, failure.expected
+            ));
+        }
+        for case in report.cases.iter().filter(|c| !c.exact_match) {
+            let got = match (&case.error, case.suggestions.first()) {
+                (Some(error), _) => format!("error: {error}"),
+                (None, Some(command)) => command.clone(),
+                (None, None) => "no suggestion".to_string(),
+            };
+            let mark = if case.safety_violation { "⚠" } else { "✗" };
+            output.push_str(&format!(
+                "{mark} {}\n    got:      {got}\n    expected: {}\n",
+                case.query,
+                case.expected
+                    .first()
+                    .map(String::as_str)
+                    .unwrap_or_default()
+            ));
+        }
+
+        let line = |label: &str, count: usize| {
+            format!(
+                "  {label:<18} {count:>3}/{}  {:5.1}%\n",
+                report.total,
+                report.rate(count)
+            )
+        };
+        output.push_str(&format!(
+            "\nEvaluated {} queries with {} in {:.1}s\n",
+            report.total,
+            report.model,
+            report.duration_ms as f64 / 1000.0
+        ));
+        output.push_str(&line("Exact match", report.exact_match));
+        output.push_str(&line(
+            &format!("Top-{} match", self.max_suggestions()),
+            report.top_k_match,
+        ));
+        output.push_str(&line("Executable valid", report.executable_valid));
+        output.push_str(&line("Safety violations", report.safety_violations));
+        output.push_str(&line("Category correct", report.category_correct));
+        if report.errors > 0 {
+            output.push_str(&line("Errors", report.errors));
+        }
+        output.push_str(&format!(
+            "  {:<18} {:>3}/{}\n",
+            "Expansion guard",
+            report.idioms - report.idiom_failures.len(),
+            report.idioms
+        ));
+
+        Ok(output.trim().to_string())
+    }
+
+    /// Suggests commands for each task in a file, reusing the loaded model for all of them
+    async fn handle_batch(
+        &mut self,
+        file: &Path,
+        output: &str,
+        suggestions: usize,
+        explain: bool,
+    ) -> Result<String> {
+        let tasks = BatchTask::read_all(file)?;
+        if tasks.is_empty() {
+            return Err(anyhow::anyhow!("No tasks in {}", file.display()));
+        }
+
+        let total = tasks.len();
+        let mut items = Vec::with_capacity(total);
+        for (i, task) in tasks.into_iter().enumerate() {
+            eprintln!("[{}/{total}] {}", i + 1, task.task);
+            let options = PromptOptions {
+                no_cache: false,
+                explain,
+                max_suggestions: suggestions,
+                verbose: false,
+                base_command: None,
+                draft: false,
+                attachments: Vec::new(),
+                page_size: None,
+                exclude: Vec::new(),
+                vary: None,
+            };
+            let (suggestions, error) = match self.handle_prompt(&task.task, options).await {
+                Ok(mut suggestions) => {
+                    if explain {
+                        self.add_explanations(&mut suggestions).await;
+                    }
+                    (suggestions, None)
+                }
+                Err(e) => {
+                    warn!("Batch task on line {} failed: {e}", task.line);
+                    (Vec::new(), Some(e.to_string()))
+                }
+            };
+            items.push(BatchItem {
+                line: task.line,
+                task: task.task,
+                suggestions,
+                error,
+            });
+        }
+
+        let report = BatchReport {
+            source: file.display().to_string(),
+            generated_at: chrono::Utc::now().format("%Y-%m-%d %H:%M UTC").to_string(),
+            items,
+        };
+        match output {
+            "script" => Ok(report.to_script().trim_end().to_string()),
+            _ => Ok(serde_json::to_string_pretty(&report)?),
+        }
+    }
+
+    fn handle_audit(&self, action: AuditCommands) -> Result<String> {
+        let audit = AuditLog::new(self.context.get_commandy_dir());
+        match action {
+            AuditCommands::Show { limit, json } => {
+                let records = audit.records()?;
+                if records.is_empty() {
+                    return Ok(self.formatter.format_info(&format!(
+                        "No commands recorded in {}",
+                        audit.path().display()
+                    )));
+                }
+
+                let recent = &records[records.len().saturating_sub(limit)..];
+                if !json {
+                    return Ok(self.formatter.format_audit(recent));
+                }
+                let lines = recent
+                    .iter()
+                    .map(serde_json::to_string)
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(lines.join("\n"))
+            }
+            AuditCommands::Verify => match audit.verify()? {
+                AuditVerification::Intact { records, last_hash } => {
+                    Ok(self.formatter.format_success(&format!(
+                        "Audit log intact: {records} records, last hash {}",
+                        &last_hash[..16]
+                    )))
+                }
+                AuditVerification::Broken { line, reason } => Err(anyhow::anyhow!(
+                    "Audit log {} is broken at line {line}: {reason}",
+                    audit.path().display()
+                )),
+            },
+        }
+    }
+
+    async fn handle_session(&mut self, action: SessionCommands) -> Result<String> {
+        let commandy_dir = self.context.get_commandy_dir().clone();
+        match action {
+            SessionCommands::Export { format, id, out } => {
+                self.handle_session_export(&format, id.as_deref(), out.as_deref())
+                    .await
+            }
+            SessionCommands::List => {
+                let sessions = SessionStore::list_named(&commandy_dir);
+                if sessions.is_empty() {
+                    return Ok(self.formatter.format_info(
+                        "No named sessions yet; start one with commandy chat --session <name>",
+                    ));
+                }
+                let lines: Vec<String> = sessions
+                    .iter()
+                    .map(|session| {
+                        let updated = session
+                            .updated
+                            .map(|t| t.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M"))
+                            .map_or("never used".to_string(), |t| t.to_string());
+                        format!("{:<24} {:>3} turns  {updated}", session.name, session.turns)
+                    })
+                    .collect();
+                Ok(lines.join("\n"))
+            }
+            SessionCommands::Delete { name } => {
+                SessionStore::delete_named(&commandy_dir, &name)?;
+                Ok(self
+                    .formatter
+                    .format_success(&format!("Deleted session {name}")))
+            }
+        }
+    }
+
+    /// Asks for commands one request after another, each able to refer to the
+    /// ones before; a named session is saved after every turn
+    async fn handle_chat(&mut self, name: Option<&str>, suggestions: usize) -> Result<String> {
+        let name = match name {
+            Some(name) => name.to_string(),
+            None => chrono::Local::now()
+                .format("chat-%Y%m%d-%H%M%S")
+                .to_string(),
+        };
+        let session = SessionStore::named(self.context.get_commandy_dir(), &name)?;
+
+        let earlier = session.summary().len() + session.turns().len();
+        if earlier == 0 {
+            eprintln!(
+                "{}",
+                self.formatter.format_info(&format!(
+                    "Session {name} (resume with: commandy chat --session {name})"
+                ))
+            );
+        } else {
+            eprintln!(
+                "{}",
+                self.formatter.format_info(&format!(
+                    "Resuming session {name} ({earlier} earlier turns)"
+                ))
+            );
+            for turn in session.turns() {
+                match &turn.command {
+                    Some(command) => eprintln!("  > {}  ->  {command}", turn.prompt),
+                    None => eprintln!("  > {}", turn.prompt),
+                }
+            }
+        }
+        eprintln!("Type a request, or press Enter on an empty line to quit.");
+        self.context.use_session(session);
+
+        let stdin = io::stdin();
+        loop {
+            eprint!("> ");
+            io::stderr().flush()?;
+            let mut line = String::new();
+            if stdin.read_line(&mut line)? == 0 {
+                eprintln!();
+                break;
+            }
+            let prompt = line.trim();
+            if prompt.is_empty() || matches!(prompt, "exit" | "quit") {
+                break;
+            }
+
+            let options = PromptOptions {
+                no_cache: false,
+                explain: false,
+                max_suggestions: suggestions,
+                verbose: false,
+                base_command: None,
+                draft: false,
+                attachments: Vec::new(),
+                page_size: None,
+                exclude: Vec::new(),
+                vary: None,
+            };
+            let output = match self.handle_prompt(prompt, options).await {
+                Ok(suggestions) if suggestions.is_empty() => self
+                    .formatter
+                    .format_error("No suggestions found. Try rephrasing your prompt."),
+                Ok(suggestions) => {
+                    match self.format_suggestions(suggestions, false, prompt).await {
+                        Ok(output) => output,
+                        Err(e) => self.format_error(&format!("Failed to format suggestions: {e}")),
+                    }
+                }
+                Err(e) => self.format_error(&format!("Failed to generate suggestions: {e}")),
+            };
+            if !output.is_empty() {
+                println!("{output}");
+            }
+        }
+
+        Ok(self
+            .formatter
+            .format_info(&format!("Resume with: commandy chat --session {name}")))
+    }
+
+    /// Writes the commands run in a session as a markdown or shell runbook
+    async fn handle_session_export(
+        &mut self,
+        format: &str,
+        id: Option<&str>,
+        out: Option<&Path>,
+    ) -> Result<String> {
+        let commandy_dir = self.context.get_commandy_dir().clone();
+        let session = match id {
+            Some(id) => SessionStore::find_named(&commandy_dir, id)
+                .or_else(|| SessionStore::open(&commandy_dir, id)),
+            None => SessionStore::current(&commandy_dir),
+        }
+        .ok_or_else(|| {
+            anyhow::anyhow!(
+                "No session to export: pass --id or load the shell hook (eval \"$(commandy hook zsh)\")"
+            )
+        })?;
+        if session.steps().is_empty() {
+            return Err(anyhow::anyhow!(
+                "Session {} has no commands run through commandy yet",
+                session.id()
+            ));
+        }
+
+        let mut steps = Vec::with_capacity(session.steps().len());
+        for step in session.steps() {
+            // Explanations come from the cache when the model isn't available
+            let explanation = self
+                .explain_command(&step.command)
+                .await
+                .ok()
+                .filter(|explanation| !explanation.is_empty());
+            steps.push(RunbookStep {
+                step: step.clone(),
+                explanation,
+            });
+        }
+
+        let runbook = Runbook {
+            session: session.id().to_string(),
+            generated_at: chrono::Utc::now().format("%Y-%m-%d %H:%M UTC").to_string(),
+            steps,
+        };
+        let text = match format {
+            "script" => runbook.to_script(),
+            _ => runbook.to_markdown(),
+        };
+
+        match out {
+            Some(path) => {
+                std::fs::write(path, &text)?;
+                Ok(self.formatter.format_success(&format!(
+                    "Wrote {} steps to {}",
+                    runbook.steps.len(),
+                    path.display()
+                )))
+            }
+            None => Ok(text.trim_end().to_string()),
+        }
+    }
+
+    /// Re-runs a logged session's generations and compares them with what was recorded
+    async fn handle_replay(&self, session_id: &str) -> Result<String> {
+        let client = self.ai_client.as_ref().ok_or_else(|| {
+            anyhow::anyhow!("llama.cpp binary not found. Please run 'commandy init' first.")
+        })?;
+
+        let entries = Logging::entries(&self.settings.log, self.log_file.as_deref())?;
+        let generations = RecordedGeneration::find(&entries, session_id);
+        if generations.is_empty() {
+            return Err(anyhow::anyhow!(
+                "No generations logged for session {session_id} (prompts are only logged with -v or [log] level = \"debug\")"
+            ));
+        }
+
+        let mut output = String::new();
+        let total = generations.len();
+        for (i, generation) in generations.iter().enumerate() {
+            let record = &generation.record;
+            output.push_str(&format!(
+                "Generation {}/{total} ({} model {}, seed {}, temperature {})\n",
+                i + 1,
+                record.role,
+                record.model,
+                record.seed,
+                record.temperature
+            ));
+
+            let spinner = Spinner::new("Replaying generation...");
+            let result = client.replay(record, self.max_suggestions()).await;
+            spinner.stop();
+            let (response, suggestions) = result?;
+
+            output.push_str(&format!(
+                "Prompt:\n{}\n\nResponse:\n{response}\n",
+                record.prompt
+            ));
+            match &generation.response {
+                Some(recorded) if *recorded == response => {
+                    output.push_str("✓ Identical to the recorded response\n")
+                }
+                Some(recorded) => output.push_str(&format!(
+                    "✗ Differs from the recorded response:\n{recorded}\n"
+                )),
+                None => output.push_str("⚠ The original run did not record a response\n"),
+            }
+            if record.role == "main" {
+                let commands: Vec<&str> = suggestions.iter().map(|s| s.command.as_str()).collect();
+                output.push_str(&format!("Parsed commands: {commands:?}\n"));
+            }
+            output.push('\n');
+        }
+
+        Ok(output.trim_end().to_string())
+    }
+
+    /// Writes a redacted zip of a session's log, generations, the config, and
+    /// diagnostics, for attaching to a GitHub issue
+    async fn handle_bugreport(&self, session: Option<&str>, out: Option<&Path>) -> Result<String> {
+        let entries = Logging::entries(&self.settings.log, self.log_file.as_deref())?;
+        let session = session.or_else(|| RecordedGeneration::latest_session(&entries));
+        let now = chrono::Local::now();
+        let mut report = BugReport::new();
+
+        let mut summary = format!(
+            "{}\nBackend: {}\nModel: {}\nSession: {}\nCreated: {}\n",
+            self.handle_version()?,
+            match BackendMode::from_env() {
+                Ok(BackendMode::LlamaCpp) | Err(_) => match &self.settings.model.server_url {
+                    Some(url) => format!("llama.cpp server {url}"),
+                    None => "llama.cpp".to_string(),
+                },
+                Ok(BackendMode::Mock) => "mock".to_string(),
+                Ok(BackendMode::Record) => "record".to_string(),
+            },
+            self.settings.model.model_path,
+            session.unwrap_or("none logged"),
+            now.format("%Y-%m-%d %H:%M:%S %z"),
+        );
+
+        let doctor = match self.handle_doctor().await {
+            Ok(doctor) => doctor,
+            Err(e) => format!("Diagnostics failed: {e:#}"),
+        };
+        report.add("doctor.txt", &doctor);
+
+        let config_path = self.settings.get_config_path()?;
+        if let Ok(config) = std::fs::read_to_string(&config_path) {
+            report.add_config(&config);
+        }
+
+        if let Some(session) = session {
+            report.add_log(RecordedGeneration::session_log(&entries, session));
+
+            let generations = RecordedGeneration::find(&entries, session);
+            let mut text = String::new();
+            for (i, generation) in generations.iter().enumerate() {
+                let record = &generation.record;
+                text.push_str(&format!(
+                    "Generation {}/{} ({} model {}, seed {}, temperature {})\n\nPrompt:\n{}\n\n",
+                    i + 1,
+                    generations.len(),
+                    record.role,
+                    record.model,
+                    record.seed,
+                    record.temperature,
+                    record.prompt
+                ));
+                match &generation.response {
+                    Some(response) => {
+                        text.push_str(&format!("Response:\n{response}\n"));
+                        if let (Some(client), "main") = (&self.ai_client, record.role.as_str()) {
+                            let commands: Vec<String> = client
+                                .parse_recorded(response, self.max_suggestions())
+                                .into_iter()
+                                .map(|s| s.command)
+                                .collect();
+                            text.push_str(&format!("Parsed commands: {commands:?}\n"));
+                        }
+                    }
+                    None => text.push_str("No response was recorded\n"),
+                }
+                text.push('\n');
+            }
+            if !generations.is_empty() {
+                report.add("generations.txt", &text);
+                report.add_fixtures(&generations)?;
+            }
+        } else {
+            summary.push_str(
+                "No generations were found in the logs (they are logged at level info)\n",
+            );
+        }
+        report.add("report.txt", &summary);
+
+        let out = match out {
+            Some(path) => path.to_path_buf(),
+            None => PathBuf::from(format!(
+                "commandy-bugreport-{}.zip",
+                now.format("%Y%m%d-%H%M%S")
+            )),
+        };
+        report.write(&out, now.naive_local())?;
+
+        Ok(format!(
+            "{}\n  Contains: {}\n  Secrets, your home directory, and user and host names are redacted;\n  unzip and read it before attaching it to an issue.",
+            self.formatter
+                .format_success(&format!("Wrote bug report to {}", out.display())),
+            report.names().collect::<Vec<_>>().join(", ")
+        ))
+    }
+
+    /// GPU support of the llama.cpp build, and whether `[model] device` can be used
+    fn device_diagnostics(&self) -> Vec<String> {
+        let mut diagnostics = Vec::new();
+        let device = match self.settings.model.device.as_deref().map(Device::parse) {
+            Some(Ok(device)) => Some(device),
+            Some(Err(e)) => {
+                diagnostics.push(format!("✗ [model] device: {e}"));
+                None
+            }
+            None => None,
+        };
+        let Some(client) = &self.ai_client else {
+            return diagnostics;
+        };
+        if client.uses_server() {
+            if device.is_some() {
+                diagnostics.push(
+                    "⚠ [model] device is ignored with server_url; start llama-server with --device"
+                        .to_string(),
+                );
+            }
+            return diagnostics;
+        }
+        let probe = match client.probe_devices() {
+            Some(Ok(probe)) => probe,
+            Some(Err(e)) => {
+                diagnostics.push(format!("⚠ Could not list llama.cpp devices: {e}"));
+                return diagnostics;
+            }
+            None => return diagnostics,
+        };
+
+        if probe.is_cpu_only() {
+            diagnostics.push("✓ llama.cpp build runs on the CPU only".to_string());
+            if let Some((backend, gpu)) = DeviceProbe::host_gpu() {
+                diagnostics.push(format!(
+                    "⚠ This machine has {gpu}, but llama.cpp can't use it; \
+                    install a llama.cpp build with {} support to run models on it",
+                    backend.label()
+                ));
+            }
+        } else if probe.devices.is_empty() {
+            let backends: Vec<&str> = probe.backends.iter().map(|b| b.label()).collect();
+            diagnostics.push(format!("✓ llama.cpp built with {}", backends.join(", ")));
+        } else {
+            for listed in &probe.devices {
+                diagnostics.push(format!(
+                    "✓ llama.cpp device {}: {}",
+                    listed.name, listed.description
+                ));
+            }
+        }
+
+        match device {
+            Some(device) if probe.supports(device) => {
+                diagnostics.push(format!("✓ Running models on {device}"))
+            }
+            Some(device) => {
+                let mut found: Vec<&str> = probe.devices.iter().map(|d| d.name.as_str()).collect();
+                if found.is_empty() {
+                    found = probe.backends.iter().map(|b| b.label()).collect();
+                }
+                if found.is_empty() {
+                    found.push("CPU only");
+                }
+                diagnostics.push(format!(
+                    "✗ [model] device = \"{device}\", but llama.cpp can't run on it (found: {})",
+                    found.join(", ")
+                ));
+            }
+            None => {}
+        }
+        diagnostics
+    }
+
+    async fn handle_daemon(&self, action: DaemonCommands) -> Result<String> {
+        let files = DaemonFiles::new()?;
+        match action {
+            DaemonCommands::Run => {
+                Supervisor::new(&self.settings)?.run().await?;
+                Ok(String::new())
+            }
+            DaemonCommands::Start => {
+                if let Some(state) = files.running() {
+                    return Ok(self.formatter.format_info(&format!(
+                        "The daemon is already running (pid {})",
+                        state.pid
+                    )));
+                }
+                let url = Supervisor::new(&self.settings)?.url().to_string();
+                let pid = self.start_daemon(&files).await?;
+
+                let mut output = self.formatter.format_success(&format!(
+                    "Started the daemon (pid {pid}); llama-server will listen on {url}"
+                ));
+                if self.settings.model.server_url.as_deref() != Some(url.as_str()) {
+                    output.push_str(&format!(
+                        "\n  Set server_url = \"{url}\" under [model] to send requests to it"
+                    ));
+                }
+                Ok(output)
+            }
+            DaemonCommands::Stop => {
+                let Some(state) = files.running() else {
+                    return Ok(self.formatter.format_info("The daemon is not running"));
+                };
+                files.request(Control::Stop)?;
+                for _ in 0..DAEMON_WAIT_TICKS {
+                    if !MemoryInfo::process_running(state.pid) {
+                        return Ok(self.formatter.format_success("Stopped the daemon"));
+                    }
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                }
+                Err(anyhow::anyhow!(
+                    "The daemon (pid {}) did not stop; stop it with: kill {}",
+                    state.pid,
+                    state.pid
+                ))
+            }
+            DaemonCommands::Restart => {
+                let Some(state) = files.running() else {
+                    return Err(anyhow::anyhow!(
+                        "The daemon is not running (start it with 'commandy daemon start')"
+                    ));
+                };
+                files.request(Control::Restart)?;
+                for _ in 0..DAEMON_WAIT_TICKS {
+                    let restarted = files.running().is_some_and(|now| {
+                        now.restarts > state.restarts && now.server_pid.is_some()
+                    });
+                    if restarted {
+                        return Ok(self.formatter.format_success("Restarted llama-server"));
+                    }
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                }
+                Ok(self.formatter.format_info(
+                    "Asked the daemon to restart llama-server; see 'commandy daemon status'",
+                ))
+            }
+            DaemonCommands::Status => {
+                let Some(state) = files.running() else {
+                    return Ok(self.formatter.format_info(
+                        "The daemon is not running (start it with 'commandy daemon start')",
+                    ));
+                };
+                let now = chrono::Utc::now();
+                let since = |at: chrono::DateTime<chrono::Utc>| {
+                    let secs = (now - at).num_seconds().max(0);
+                    match secs {
+                        0..=59 => format!("{secs}s"),
+                        60..=3599 => format!("{}m", secs / 60),
+                        3600..=86_399 => format!("{}h {}m", secs / 3600, secs % 3600 / 60),
+                        _ => format!("{}d", secs / 86_400),
+                    }
+                };
+
+                let mut lines = vec![format!(
+                    "Daemon running (pid {}, up {})",
+                    state.pid,
+                    since(state.started_at)
+                )];
+                let health = match (state.server_pid, state.healthy, state.loading) {
+                    (None, _, _) => "restarting".to_string(),
+                    (Some(_), true, _) => "healthy".to_string(),
+                    (Some(_), false, true) => "loading the model".to_string(),
+                    (Some(_), false, false) if state.last_check.is_none() => "starting".to_string(),
+                    (Some(_), false, false) => "not responding".to_string(),
+                };
+                let mut details = Vec::new();
+                if let Some(pid) = state.server_pid {
+                    details.push(format!("pid {pid}"));
+                }
+                if let Some(rss) = state.rss_mb {
+                    details.push(match self.settings.daemon.max_memory_mb {
+                        0 => format!("{rss} MB"),
+                        limit => format!("{rss} of {limit} MB"),
+                    });
+                }
+                if let Some(checked) = state.last_check {
+                    details.push(format!("checked {} ago", since(checked)));
+                }
+                lines.push(format!(
+                    "llama-server at {}: {health} ({})",
+                    state.url,
+                    details.join(", ")
+                ));
+                lines.push(match &state.last_restart {
+                    Some(last) => format!(
+                        "Restarts: {}, last {} ago: {}",
+                        state.restarts,
+                        since(last.at),
+                        last.reason
+                    ),
+                    None => "Restarts: 0".to_string(),
+                });
+                Ok(lines.join("\n"))
+            }
+            DaemonCommands::Logs { lines } => files.tail_log(lines),
+        }
+    }
+
+    /// Recent warnings and errors, for attaching to bug reports
+    fn handle_doctor_logs(&self) -> Result<String> {
+        let problems = Logging::recent_problems(
+            &self.settings.log,
+            self.log_file.as_deref(),
+            RECENT_LOG_PROBLEMS,
+        )?;
+        let source = match &self.log_file {
+            Some(path) => path.display().to_string(),
+            None => Logging::log_dir(&self.settings.log)?.display().to_string(),
+        };
+
+        if problems.is_empty() {
+            return Ok(self
+                .formatter
+                .format_success(&format!("No recent warnings or errors in {source}")));
+        }
+
+        let mut output = format!("Recent warnings and errors ({source}):\n");
+        for entry in &problems {
+            output.push_str(&format!(
+                "{} {:5} {}: {}",
+                entry.timestamp,
+                entry.level,
+                entry.target,
+                entry.message()
+            ));
+            let extra = entry.extra_fields();
+            if !extra.is_empty() {
+                output.push_str(&format!(" {extra}"));
+            }
+            output.push('\n');
+        }
+        Ok(output.trim_end().to_string())
+    }
+
+    fn handle_self_update(&self, check_only: bool) -> Result<String> {
+        let updater = SelfUpdater::new();
+
+        if check_only {
+            let spinner = Spinner::new("Checking for updates...");
+            let result = updater.check();
+            spinner.stop();
+
+            return Ok(match result? {
+                UpdateCheck::UpToDate { current } => self
+                    .formatter
+                    .format_success(&format!("commandy {current} is up to date")),
+                UpdateCheck::Available { current, latest } => self.formatter.format_info(&format!(
+                    "Update available: {current} → {latest} (run: commandy self-update)"
+                )),
+            });
+        }
+
+        let spinner = Spinner::new("Updating commandy...");
+        let result = updater.update();
+        spinner.stop();
+
+        Ok(match result? {
+            UpdateCheck::UpToDate { current } => self
+                .formatter
+                .format_success(&format!("commandy {current} is already up to date")),
+            UpdateCheck::Available { current, latest } => self
+                .formatter
+                .format_success(&format!("Updated commandy {current} → {latest}")),
+        })
+    }
+
+    /// Estimates whether the configured model and runtime options fit in memory
+    fn check_model_memory(&self) -> String {
+        let model = &self.settings.model.model_path;
+        let Some(estimate_mb) = MemoryGuard::estimate_mb(&self.settings.model, model) else {
+            return "⚠ Memory estimate unavailable (model neither downloaded nor in the registry)"
+                .to_string();
+        };
+
+        match MemoryInfo::detect() {
+            Some(memory) if estimate_mb > memory.available_mb => format!(
+                "⚠ Model needs ~{estimate_mb} MB but only {} MB is available; \
+                lower model.context_size or set model.cache_type_k/v = \"q8_0\"",
+                memory.available_mb
+            ),
+            Some(memory) => format!(
+                "✓ Estimated model memory: ~{estimate_mb} MB ({} MB available)",
+                memory.available_mb
+            ),
+            None => format!("✓ Estimated model memory: ~{estimate_mb} MB"),
+        }
+    }
+
     fn handle_version(&self) -> Result<String> {
         Ok(format!(
             "commandy {}\nRust version: {}\nPlatform: {}",
             env!("CARGO_PKG_VERSION"),
             env!("CARGO_PKG_RUST_VERSION"),
-            std::env::consts::OS
+            Platform::detect().describe()
         ))
     }
 
@@ -237,46 +3487,122 @@ impl CommandHandler {
         show_explanations: bool,
         original_prompt: &str,
     ) -> Result<String> {
+        if show_explanations {
+            self.add_explanations(&mut suggestions).await;
+        }
+        let (mut selected, mut new_from) = (0, 0);
         loop {
+            // Only runs the user saw count toward an experiment's results
+            if let Some((experiment, arm, prompt)) = self.experiment_trial.take() {
+                self.context
+                    .start_experiment_run(&experiment, arm.as_str(), &prompt);
+            }
+            let paging = Paging {
+                page_size: self.settings.output.page_size,
+                more: self.has_next_page(suggestions.len()),
+                selected,
+                new_from,
+            };
+            // Until more are added, a reopened picker lists them all
+            new_from = 0;
             match self.formatter.format_suggestions(
                 &suggestions,
                 show_explanations,
                 original_prompt,
                 &mut self.context,
+                paging,
             ) {
-                FormatResult::Executed(output) => return Ok(output),
+                FormatResult::Executed(output) => {
+                    let output = match self.formatter.take_captured() {
+                        Some(captured) => self.follow_up(original_prompt, captured, output).await,
+                        None => output,
+                    };
+                    self.summarize_learned_context().await;
+                    return Ok(output);
+                }
                 FormatResult::Output(output) => return Ok(output),
                 FormatResult::Static(output) => return Ok(output),
-                FormatResult::FollowupRequested => {
-                    // Ask user for modification request
-                    println!("What would you like to modify about the command?");
-                    let mut input = String::new();
-                    io::stdin().read_line(&mut input)?;
-                    let modification_request = input.trim();
-
-                    if modification_request.is_empty() {
-                        continue;
+                FormatResult::UnpackRequested(command) => {
+                    return self.run_unpacked(&command, Some(original_prompt)).await;
+                }
+                FormatResult::MoreRequested => {
+                    selected = suggestions.len() - 1;
+                    new_from = suggestions.len();
+                    match self.next_page(&suggestions).await {
+                        // Nothing new means the model has run out of ideas
+                        Ok(more) if more.is_empty() => self.pending_page = None,
+                        Ok(mut more) => {
+                            if show_explanations {
+                                self.add_explanations(&mut more).await;
+                            }
+                            selected = suggestions.len();
+                            suggestions.extend(more);
+                        }
+                        Err(e) => {
+                            warn!(error = %e, "Failed to generate another page");
+                            self.pending_page = None;
+                        }
                     }
-
-                    // Create follow-up prompt (much cleaner)
-                    let followup_prompt =
-                        format!("{original_prompt} ({})", modification_request.trim());
-
-                    // Get new suggestions
-                    let options = PromptOptions {
-                        max_suggestions: 3,
-                        no_cache: true,
-                        explain: false,
-                        verbose: false,
-                    };
-
-                    match self.handle_prompt(&followup_prompt, options).await {
-                        Ok(new_suggestions) => {
-                            // Replace suggestions and continue the loop
+                }
+                FormatResult::MoreLikeRequested(index) => {
+                    let command = suggestions[index].command.clone();
+                    selected = index;
+                    new_from = suggestions.len();
+                    match self
+                        .more_like(original_prompt, &command, &suggestions)
+                        .await
+                    {
+                        Ok(more) if more.is_empty() => {}
+                        Ok(mut more) => {
+                            if show_explanations {
+                                self.add_explanations(&mut more).await;
+                            }
+                            selected = suggestions.len();
+                            suggestions.extend(more);
+                        }
+                        Err(e) => warn!(error = %e, "Failed to generate variations of {command}"),
+                    }
+                }
+                FormatResult::InfoRequested(index) => {
+                    selected = index;
+                    // --plain prints the summary where the user asked for it
+                    new_from = suggestions.len();
+                    if let Some(tool) = ToolDocs::tool(&suggestions[index].command) {
+                        let summary = match self.tool_summary(&tool).await {
+                            Ok(summary) if !summary.is_empty() => summary,
+                            Ok(_) => format!("The model gave no summary of {tool}."),
+                            Err(e) => format!("No summary of {tool}: {e}"),
+                        };
+                        if self.formatter.is_plain() {
+                            println!("{}", self.formatter.format_tool_summary(&tool, &summary));
+                        }
+                        self.formatter.set_tool_summary(&tool, summary);
+                    }
+                }
+                FormatResult::FlagsRequested(index) => {
+                    selected = index;
+                    match self.edit_flags(&suggestions[index].command) {
+                        Ok(Some(command)) => {
+                            suggestions[index].command = command;
+                            // The old explanation described the old flags
+                            suggestions[index].explanation = None;
+                            if show_explanations {
+                                self.add_explanations(&mut suggestions[index..=index]).await;
+                            }
+                        }
+                        Ok(None) => {}
+                        Err(e) => warn!(error = %e, "Failed to edit flags"),
+                    }
+                }
+                FormatResult::FollowupRequested => {
+                    match self.run_followup(original_prompt).await? {
+                        FollowupOutcome::Suggestions(new_suggestions) => {
                             suggestions = new_suggestions;
-                            continue;
+                            selected = 0;
                         }
-                        Err(e) => {
+                        FollowupOutcome::Back => {}
+                        FollowupOutcome::Quit => return Ok(String::new()),
+                        FollowupOutcome::Failed(e) => {
                             return Ok(self.format_error(&format!(
                                 "Failed to get follow-up suggestions: {e}"
                             )));
@@ -287,7 +3613,230 @@ impl CommandHandler {
         }
     }
 
+    /// Summarizes older learned patterns into rules once there are enough of them
+    ///
+    /// Runs after a command was executed, when an accepted pattern may have
+    /// just been added, so the wait doesn't come before suggestions.
+    async fn summarize_learned_context(&mut self) {
+        let Some((category, patterns)) = self.context.learned_summary_batch() else {
+            return;
+        };
+        let Some(ai_client) = &self.ai_client else {
+            return;
+        };
+
+        let spinner = Spinner::new("Summarizing older learned patterns...");
+        let result = ai_client
+            .summarize_patterns(category.label(), &patterns)
+            .await;
+        spinner.stop();
+        match result {
+            // Without rules, the patterns are kept rather than lost
+            Ok(rules) if rules.is_empty() => warn!("Model wrote no rules for learned patterns"),
+            Ok(rules) => {
+                if let Err(e) = self
+                    .context
+                    .store_learned_rules(category, &rules, &patterns)
+                {
+                    warn!("Failed to store learned rules: {e}");
+                }
+            }
+            Err(e) => warn!("Failed to summarize learned patterns: {e}"),
+        }
+    }
+
+    /// Reads a modification request and generates for it; typing during
+    /// generation cancels it and starts a new request with what was typed
+    async fn run_followup(&mut self, original_prompt: &str) -> Result<FollowupOutcome> {
+        let mut typed_ahead = String::new();
+
+        loop {
+            // Prompting: ask for the modification, keeping any typed-ahead text
+            println!("What would you like to modify about the command?");
+            print!("{typed_ahead}");
+            io::stdout().flush()?;
+            let mut input = String::new();
+            io::stdin().read_line(&mut input)?;
+            let modification_request = format!("{typed_ahead}{}", input.trim_end());
+            let modification_request = modification_request.trim();
+            typed_ahead.clear();
+
+            if modification_request.is_empty() {
+                return Ok(FollowupOutcome::Back);
+            }
+
+            let followup_prompt = format!("{original_prompt} ({modification_request})");
+            let options = PromptOptions {
+                max_suggestions: 3,
+                no_cache: true,
+                explain: false,
+                verbose: false,
+                base_command: None,
+                draft: false,
+                attachments: Vec::new(),
+                page_size: None,
+                exclude: Vec::new(),
+                vary: None,
+            };
+
+            // Generating: race the model against the keyboard
+            let mut watcher = KeyWatcher::start()?;
+            let interrupt = tokio::select! {
+                result = self.handle_prompt(&followup_prompt, options) => {
+                    drop(watcher);
+                    return Ok(match result {
+                        Ok(suggestions) => FollowupOutcome::Suggestions(suggestions),
+                        Err(e) => FollowupOutcome::Failed(e),
+                    });
+                }
+                interrupt = watcher.wait() => interrupt,
+            };
+            // Dropping the generation future above killed llama.cpp
+            drop(watcher);
+            eprintln!("\r\x1b[KCancelled.");
+
+            match interrupt {
+                Interrupt::Text(c) => typed_ahead.push(c),
+                Interrupt::Cancel => return Ok(FollowupOutcome::Back),
+                Interrupt::Quit => return Ok(FollowupOutcome::Quit),
+            }
+        }
+    }
+
+    /// Regenerates suggestions as the query is typed; Enter hands them to the usual selector
+    pub async fn handle_watch(
+        &mut self,
+        initial_query: &str,
+        show_explanations: bool,
+    ) -> Result<String> {
+        let WatchExit::Chosen { query, suggestions } = self.watch_loop(initial_query).await? else {
+            return Ok(String::new());
+        };
+        if query.is_empty() {
+            return Ok(String::new());
+        }
+
+        let suggestions = if suggestions.is_empty() {
+            // Enter was pressed before suggestions for the final query arrived
+            let options = PromptOptions {
+                no_cache: false,
+                explain: show_explanations,
+                max_suggestions: self.settings.output.max_suggestions,
+                verbose: false,
+                base_command: None,
+                draft: false,
+                attachments: Vec::new(),
+                page_size: None,
+                exclude: Vec::new(),
+                vary: None,
+            };
+            self.handle_prompt(&query, options).await?
+        } else {
+            self.context
+                .record_session_query(&query, suggestions.first().map(|s| s.command.as_str()));
+            suggestions
+        };
+        if suggestions.is_empty() {
+            return Ok(self.format_error("No suggestions found. Try rephrasing your prompt."));
+        }
+        self.format_suggestions(suggestions, show_explanations, &query)
+            .await
+    }
+
+    async fn watch_loop(&mut self, initial_query: &str) -> Result<WatchExit> {
+        let mut screen = WatchScreen::enter(initial_query, self.formatter.theme().cloned())?;
+        let mut keys = KeyStream::start();
+        let mut dirty = initial_query.trim().chars().count() >= WATCH_MIN_QUERY_CHARS;
+
+        loop {
+            if !dirty {
+                let key = keys.next().await;
+                match screen.handle_key(key)? {
+                    KeyOutcome::Exit(exit) => return Ok(exit),
+                    KeyOutcome::QueryChanged => dirty = Self::watch_query_changed(&mut screen)?,
+                    KeyOutcome::Redraw | KeyOutcome::Ignored => {}
+                }
+                continue;
+            }
+
+            // Wait for a pause in typing before generating
+            screen.set_status(WatchStatus::Waiting)?;
+            let key = tokio::select! {
+                key = keys.next() => Some(key),
+                _ = tokio::time::sleep(WATCH_DEBOUNCE) => None,
+            };
+            if let Some(key) = key {
+                match screen.handle_key(key)? {
+                    KeyOutcome::Exit(exit) => return Ok(exit),
+                    KeyOutcome::QueryChanged => dirty = Self::watch_query_changed(&mut screen)?,
+                    KeyOutcome::Redraw | KeyOutcome::Ignored => {}
+                }
+                continue;
+            }
+
+            dirty = false;
+            let query = screen.query().trim().to_string();
+            screen.set_status(WatchStatus::Generating)?;
+            let options = PromptOptions {
+                no_cache: false,
+                explain: false,
+                max_suggestions: self.settings.output.max_suggestions,
+                verbose: false,
+                base_command: None,
+                draft: true,
+                attachments: Vec::new(),
+                page_size: None,
+                exclude: Vec::new(),
+                vary: None,
+            };
+            let generation = self.handle_prompt(&query, options);
+            tokio::pin!(generation);
+
+            loop {
+                tokio::select! {
+                    result = &mut generation => {
+                        match result {
+                            Ok(suggestions) => screen.show_suggestions(&query, suggestions)?,
+                            Err(e) => screen.set_status(WatchStatus::Failed(e.to_string()))?,
+                        }
+                        break;
+                    }
+                    key = keys.next() => match screen.handle_key(key)? {
+                        KeyOutcome::Exit(exit) => return Ok(exit),
+                        // Dropping the generation stops llama.cpp
+                        KeyOutcome::QueryChanged => {
+                            dirty = Self::watch_query_changed(&mut screen)?;
+                            break;
+                        }
+                        KeyOutcome::Redraw | KeyOutcome::Ignored => {}
+                    },
+                }
+            }
+        }
+    }
+
+    /// Whether the edited query should be generated for; short ones go back to idle
+    fn watch_query_changed(screen: &mut WatchScreen) -> Result<bool> {
+        let long_enough = screen.query().trim().chars().count() >= WATCH_MIN_QUERY_CHARS;
+        if !long_enough {
+            screen.set_status(WatchStatus::Idle)?;
+        }
+        Ok(long_enough)
+    }
+
+    /// Formats the top suggestion for consumption by a downstream commandy
+    pub fn format_piped(&self, suggestions: &[Suggestion], prompt: &str) -> String {
+        suggestions
+            .first()
+            .map(|s| PipedInput::format(prompt, &s.command))
+            .unwrap_or_default()
+    }
+
     pub fn format_error(&self, message: &str) -> String {
         self.formatter.format_error(message)
     }
+
+    pub fn format_info(&self, message: &str) -> String {
+        self.formatter.format_info(message)
+    }
 }
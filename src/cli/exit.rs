@@ -0,0 +1,88 @@
+use std::fmt;
+
+/// How a prompt run ended, as the exit code scripts can branch on
+///
+/// The codes are stable across releases. 2 is left to clap, which uses it
+/// for command-line usage errors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Success,
+    /// Anything not covered by a more specific code
+    Error,
+    /// No suggestions, or fewer than `--pick` asked for
+    NoSuggestions,
+    /// The model backend failed and the offline heuristics had nothing either
+    BackendUnavailable,
+    /// The chosen command was refused by a profile limit or a declined confirmation
+    Blocked,
+    /// Generation took longer than `--timeout`
+    TimedOut,
+}
+
+impl Outcome {
+    pub fn code(self) -> i32 {
+        match self {
+            Self::Success => 0,
+            Self::Error => 1,
+            Self::NoSuggestions => 3,
+            Self::BackendUnavailable => 4,
+            Self::Blocked => 5,
+            Self::TimedOut => 6,
+        }
+    }
+
+    /// The outcome a failed prompt run stands for
+    pub fn of(error: &anyhow::Error) -> Self {
+        if error.is::<BackendUnavailable>() {
+            Self::BackendUnavailable
+        } else if error.is::<Blocked>() {
+            Self::Blocked
+        } else {
+            Self::Error
+        }
+    }
+
+    pub fn exit(self) -> ! {
+        std::process::exit(self.code())
+    }
+}
+
+/// A backend error with no offline suggestions to fall back on, kept
+/// distinct so it exits with [`Outcome::BackendUnavailable`]
+#[derive(Debug)]
+pub struct BackendUnavailable(pub anyhow::Error);
+
+impl fmt::Display for BackendUnavailable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl std::error::Error for BackendUnavailable {}
+
+/// A refusal for safety, such as `[sudo] policy = "refuse"` dropping every
+/// suggestion, kept distinct so it exits with [`Outcome::Blocked`]
+#[derive(Debug)]
+pub struct Blocked(pub String);
+
+impl fmt::Display for Blocked {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl std::error::Error for Blocked {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn typed_errors_keep_their_exit_codes() {
+        let blocked: anyhow::Error = Blocked("refused".to_string()).into();
+        assert_eq!(Outcome::of(&blocked), Outcome::Blocked);
+        let unavailable: anyhow::Error = BackendUnavailable(anyhow::anyhow!("down")).into();
+        assert_eq!(Outcome::of(&unavailable), Outcome::BackendUnavailable);
+        assert_eq!(Outcome::of(&anyhow::anyhow!("other")), Outcome::Error);
+    }
+}
@@ -0,0 +1,248 @@
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use crossterm::terminal::{self, disable_raw_mode, enable_raw_mode};
+use crossterm::terminal::{EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{cursor, execute, queue};
+use std::io::{self, Write};
+
+use crate::cli::{Role, Theme};
+use crate::flags::{CommandFlags, FlagCatalog, FlagChoice};
+
+/// Small menu for turning a suggestion's flags on and off and changing their values
+///
+/// It offers the flags the [`FlagCatalog`] lists for the command's tools, so
+/// a command can be adjusted without retyping it.
+pub struct FlagMenu<'a> {
+    catalog: &'a FlagCatalog,
+    /// `None` when colors are off
+    theme: Option<&'a Theme>,
+}
+
+impl<'a> FlagMenu<'a> {
+    pub fn new(catalog: &'a FlagCatalog, theme: Option<&'a Theme>) -> Self {
+        Self { catalog, theme }
+    }
+
+    /// The command with the user's changes, or `None` when they cancel
+    pub fn edit(&self, command: &str) -> io::Result<Option<String>> {
+        enable_raw_mode()?;
+        let mut stdout = io::stdout();
+        execute!(stdout, EnterAlternateScreen)?;
+
+        let result = self.edit_loop(&mut stdout, command);
+
+        disable_raw_mode()?;
+        execute!(stdout, LeaveAlternateScreen)?;
+        result
+    }
+
+    /// Numbered flags and typed changes, for `--plain`; finishing keeps the changes
+    pub fn edit_plain(&self, command: &str) -> io::Result<String> {
+        let mut command = command.to_string();
+        loop {
+            let choices = CommandFlags::choices(&command, self.catalog);
+            if choices.is_empty() {
+                println!("{}", Self::unknown_tools_note());
+                return Ok(command);
+            }
+            println!("Flags of {command}:");
+            for (i, choice) in choices.iter().enumerate() {
+                println!("{}. {}", i + 1, self.describe(choice, &choices));
+            }
+            print!(
+                "Enter a number to turn that flag on or off, a number and a value to set it, \
+                 or nothing to finish: "
+            );
+            io::stdout().flush()?;
+            let mut answer = String::new();
+            if io::stdin().read_line(&mut answer)? == 0 {
+                return Ok(command);
+            }
+
+            let answer = answer.trim();
+            if answer.is_empty() {
+                return Ok(command);
+            }
+            let (number, value) = match answer.split_once(char::is_whitespace) {
+                Some((number, value)) => (number, Some(value.trim())),
+                None => (answer, None),
+            };
+            let choice = match number.parse::<usize>() {
+                Ok(n) if (1..=choices.len()).contains(&n) => &choices[n - 1],
+                _ => {
+                    println!("Flags are numbered 1 to {}.", choices.len());
+                    continue;
+                }
+            };
+            command = match value {
+                Some(value) if choice.spec.takes_value() => {
+                    CommandFlags::set_value(&command, choice, value, self.catalog)
+                }
+                Some(_) => {
+                    println!("{} takes no value.", choice.spec.flag);
+                    continue;
+                }
+                None if choice.spec.takes_value() && !choice.present => {
+                    println!(
+                        "{} needs a value: type {number} and the value.",
+                        choice.spec.flag
+                    );
+                    continue;
+                }
+                None => CommandFlags::toggle(&command, choice, self.catalog),
+            };
+        }
+    }
+
+    fn edit_loop(&self, stdout: &mut io::Stdout, original: &str) -> io::Result<Option<String>> {
+        let mut command = original.to_string();
+        let mut selected = 0;
+        // The value being typed for the selected flag
+        let mut editing: Option<String> = None;
+        loop {
+            let choices = CommandFlags::choices(&command, self.catalog);
+            if choices.is_empty() {
+                self.render_unknown(stdout, &command)?;
+                Self::read_key()?;
+                return Ok(None);
+            }
+            selected = selected.min(choices.len() - 1);
+            self.render(stdout, &command, &choices, selected, editing.as_deref())?;
+
+            let key = Self::read_key()?;
+            if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+                return Ok(None);
+            }
+            let choice = &choices[selected];
+            if let Some(value) = editing.as_mut() {
+                match key.code {
+                    KeyCode::Enter => {
+                        if !value.trim().is_empty() {
+                            command = CommandFlags::set_value(
+                                &command,
+                                choice,
+                                value.trim(),
+                                self.catalog,
+                            );
+                        }
+                        editing = None;
+                    }
+                    KeyCode::Esc => editing = None,
+                    KeyCode::Backspace => {
+                        value.pop();
+                    }
+                    KeyCode::Char(c) => value.push(c),
+                    _ => {}
+                }
+                continue;
+            }
+            match key.code {
+                KeyCode::Up | KeyCode::Char('k') => selected = selected.saturating_sub(1),
+                KeyCode::Down | KeyCode::Char('j') => selected += 1,
+                KeyCode::Char(' ') if choice.spec.takes_value() && !choice.present => {
+                    editing = Some(String::new());
+                }
+                KeyCode::Char(' ') => {
+                    command = CommandFlags::toggle(&command, choice, self.catalog);
+                }
+                KeyCode::Char('e') | KeyCode::Right if choice.spec.takes_value() => {
+                    editing = Some(choice.value.clone().unwrap_or_default());
+                }
+                KeyCode::Enter => return Ok(Some(command)),
+                KeyCode::Esc => return Ok(None),
+                _ => {}
+            }
+        }
+    }
+
+    fn read_key() -> io::Result<event::KeyEvent> {
+        loop {
+            if let Event::Key(key) = event::read()? {
+                if key.kind != KeyEventKind::Release {
+                    return Ok(key);
+                }
+            }
+        }
+    }
+
+    fn render(
+        &self,
+        stdout: &mut io::Stdout,
+        command: &str,
+        choices: &[FlagChoice],
+        selected: usize,
+        editing: Option<&str>,
+    ) -> io::Result<()> {
+        queue!(
+            stdout,
+            terminal::Clear(terminal::ClearType::All),
+            cursor::MoveTo(0, 0)
+        )?;
+        match editing {
+            Some(_) => write!(stdout, "Type the value (Enter=set, Esc=back):\r\n\r\n")?,
+            None => write!(
+                stdout,
+                "Flags (Space=on/off, e=edit value, Enter=apply, Esc=cancel):\r\n\r\n"
+            )?,
+        }
+        write!(stdout, "  {}\r\n\r\n", self.paint(command, Role::Command))?;
+
+        for (i, choice) in choices.iter().enumerate() {
+            let line = match editing {
+                Some(value) if i == selected => format!(
+                    "[{}] {} {value}_",
+                    if choice.present { "x" } else { " " },
+                    choice.spec.flag
+                ),
+                _ => self.describe(choice, choices),
+            };
+            match i == selected {
+                true => write!(stdout, "▶ {}\r\n", self.paint(&line, Role::Command))?,
+                false => write!(stdout, "  {line}\r\n")?,
+            }
+        }
+        stdout.flush()
+    }
+
+    fn render_unknown(&self, stdout: &mut io::Stdout, command: &str) -> io::Result<()> {
+        queue!(
+            stdout,
+            terminal::Clear(terminal::ClearType::All),
+            cursor::MoveTo(0, 0)
+        )?;
+        write!(
+            stdout,
+            "  {}\r\n\r\n{}\r\n\r\nPress any key to go back.",
+            self.paint(command, Role::Command),
+            Self::unknown_tools_note()
+        )?;
+        stdout.flush()
+    }
+
+    /// One flag as a menu line, such as `[x] -n 10  lines to show`
+    fn describe(&self, choice: &FlagChoice, choices: &[FlagChoice]) -> String {
+        let mark = if choice.present { "x" } else { " " };
+        let value = match (&choice.value, &choice.spec.value) {
+            (Some(value), _) if choice.present => format!(" {value}"),
+            (_, Some(name)) => format!(" <{name}>"),
+            _ => String::new(),
+        };
+        // Flags of a pipeline's later commands say which tool they belong to
+        let tool = match choices.iter().any(|c| c.command != choice.command) {
+            true => format!("{} ", choice.tool),
+            false => String::new(),
+        };
+        let about = self.paint(&choice.spec.about, Role::Meta);
+        format!("[{mark}] {tool}{}{value}  {about}", choice.spec.flag)
+    }
+
+    fn unknown_tools_note() -> &'static str {
+        "No flags are known for this command. Add its tool to ~/.commandy/flags.toml to edit them here."
+    }
+
+    fn paint(&self, text: &str, role: Role) -> String {
+        match self.theme {
+            Some(theme) => theme.paint(role, text),
+            None => text.to_string(),
+        }
+    }
+}
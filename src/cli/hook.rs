@@ -0,0 +1,97 @@
+use anyhow::Result;
+
+use crate::context::{ALIASES_FILE, FUNCTIONS_FILE, SESSION_ENV, SHELL_LOG_FILE};
+
+/// Shells `commandy hook` can generate integration code for
+pub const SUPPORTED_SHELLS: [&str; 3] = ["bash", "zsh", "fish"];
+
+/// Shell integration installed with `eval "$(commandy hook <shell>)"`
+///
+/// Besides naming the session, each script appends every command the shell runs
+/// to ~/.commandy/history.tsv as `epoch<TAB>exit<TAB>cwd<TAB>command`, with tabs
+/// and newlines in the command turned into spaces. The shell's aliases and the
+/// names of its functions are written to ~/.commandy/aliases and
+/// ~/.commandy/functions at the first prompt and again after any command that
+/// may change them, so suggestions can use them.
+pub struct ShellHook;
+
+impl ShellHook {
+    /// Returns the integration script for the given shell
+    pub fn script(shell: &str) -> Result<String> {
+        let script = match shell {
+            // $$ is the shell's pid; the timestamp keeps ids unique across pid reuse.
+            // history numbers only advance for new commands, so an empty prompt
+            // doesn't log the previous one again
+            "bash" => format!(
+                r#"# commandy shell integration
+export {SESSION_ENV}="$$-$(date +%s)"
+__commandy_last=$(HISTTIMEFORMAT= builtin history 1 | awk '{{print $1}}')
+__commandy_log() {{
+    local exit_status=$? entry command re='^ *([0-9]+) +(.*)$'
+    entry=$(HISTTIMEFORMAT= builtin history 1)
+    if [[ $entry =~ $re && ${{BASH_REMATCH[1]}} != "$__commandy_last" ]]; then
+        __commandy_last=${{BASH_REMATCH[1]}}
+        command=${{BASH_REMATCH[2]//$'\n'/ }}
+        printf '%s\t%s\t%s\t%s\n' "${{EPOCHSECONDS:-$(date +%s)}}" "$exit_status" "$PWD" \
+            "${{command//$'\t'/ }}" >> "$HOME/.commandy/{SHELL_LOG_FILE}" 2>/dev/null
+    fi
+    if [[ -z $__commandy_aliased || $command =~ ^(un)?alias|^(unset|function|source|\.)\  ]]; then
+        __commandy_aliased=1
+        builtin alias -p > "$HOME/.commandy/{ALIASES_FILE}" 2>/dev/null
+        compgen -A function > "$HOME/.commandy/{FUNCTIONS_FILE}" 2>/dev/null
+    fi
+    return $exit_status
+}}
+PROMPT_COMMAND="__commandy_log${{PROMPT_COMMAND:+;$PROMPT_COMMAND}}"
+"#
+            ),
+            "zsh" => format!(
+                r#"# commandy shell integration
+export {SESSION_ENV}="$$-$(date +%s)"
+zmodload zsh/datetime 2>/dev/null
+autoload -Uz add-zsh-hook
+__commandy_preexec() {{
+    __commandy_command=$1
+}}
+__commandy_precmd() {{
+    local exit_status=$?
+    if [[ -z $__commandy_aliased || $__commandy_command =~ '^(un)?alias|^(unfunction|unset|function|source|\.) ' ]]; then
+        __commandy_aliased=1
+        alias -L > "$HOME/.commandy/{ALIASES_FILE}" 2>/dev/null
+        print -rl -- ${{(k)functions}} > "$HOME/.commandy/{FUNCTIONS_FILE}" 2>/dev/null
+    fi
+    [[ -n $__commandy_command ]] || return
+    local command=${{__commandy_command//$'\n'/ }}
+    printf '%s\t%s\t%s\t%s\n' "$EPOCHSECONDS" "$exit_status" "$PWD" \
+        "${{command//$'\t'/ }}" >> "$HOME/.commandy/{SHELL_LOG_FILE}" 2>/dev/null
+    __commandy_command=
+}}
+add-zsh-hook preexec __commandy_preexec
+add-zsh-hook precmd __commandy_precmd
+"#
+            ),
+            "fish" => format!(
+                r#"# commandy shell integration
+set -gx {SESSION_ENV} "$fish_pid-"(date +%s)
+function __commandy_log --on-event fish_postexec
+    set -l exit_status $status
+    test -n "$argv[1]"; or return
+    set -l command (string join ' ' -- (string split \n -- $argv[1]) | string replace -a \t ' ')
+    printf '%s\t%s\t%s\t%s\n' (date +%s) $exit_status $PWD "$command" >> "$HOME/.commandy/{SHELL_LOG_FILE}" 2>/dev/null
+    if not set -q __commandy_aliased; or string match -qr '^(un)?alias|^(function|funcsave|source|\.) |^functions -e' -- $command
+        set -g __commandy_aliased 1
+        alias > "$HOME/.commandy/{ALIASES_FILE}" 2>/dev/null
+        functions -n | string split ', ' | string match -rv '^fish_' > "$HOME/.commandy/{FUNCTIONS_FILE}" 2>/dev/null
+    end
+end
+"#
+            ),
+            other => anyhow::bail!(
+                "Unsupported shell '{other}' (supported: {})",
+                SUPPORTED_SHELLS.join(", ")
+            ),
+        };
+
+        Ok(script)
+    }
+}
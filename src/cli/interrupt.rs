@@ -0,0 +1,108 @@
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use crossterm::terminal::{disable_raw_mode, enable_raw_mode};
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// A keypress that interrupts an in-flight generation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interrupt {
+    /// The user started typing a new request
+    Text(char),
+    /// Esc: drop the request and go back to the previous suggestions
+    Cancel,
+    /// Ctrl-C: stop altogether
+    Quit,
+}
+
+/// Watches the keyboard in raw mode while a generation runs
+///
+/// Output processing stays on, so the spinner and anything printed under it
+/// still start their lines at the left margin. Dropping the watcher stops the
+/// polling thread and restores the terminal.
+pub struct KeyWatcher {
+    stop: Arc<AtomicBool>,
+    handle: Option<tokio::task::JoinHandle<Option<Interrupt>>>,
+}
+
+impl KeyWatcher {
+    pub fn start() -> io::Result<Self> {
+        enable_raw_mode()?;
+        Self::keep_output_processing();
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = stop.clone();
+        let handle = tokio::task::spawn_blocking(move || {
+            while !thread_stop.load(Ordering::Relaxed) {
+                if !event::poll(Duration::from_millis(50)).ok()? {
+                    continue;
+                }
+                let Event::Key(key) = event::read().ok()? else {
+                    continue;
+                };
+                if key.kind != KeyEventKind::Press {
+                    continue;
+                }
+
+                match key.code {
+                    KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        return Some(Interrupt::Quit)
+                    }
+                    KeyCode::Char(c) => return Some(Interrupt::Text(c)),
+                    KeyCode::Esc => return Some(Interrupt::Cancel),
+                    _ => {}
+                }
+            }
+            None
+        });
+
+        Ok(Self {
+            stop,
+            handle: Some(handle),
+        })
+    }
+
+    /// Turns newline translation back on after raw mode turned it off
+    #[cfg(unix)]
+    fn keep_output_processing() {
+        use std::os::fd::AsRawFd;
+
+        let Ok(tty) = std::fs::File::open("/dev/tty") else {
+            return;
+        };
+        let fd = tty.as_raw_fd();
+        // SAFETY: termios is plain data filled in by tcgetattr, and fd stays open throughout
+        unsafe {
+            let mut termios: libc::termios = std::mem::zeroed();
+            if libc::tcgetattr(fd, &mut termios) == 0 {
+                termios.c_oflag |= libc::OPOST | libc::ONLCR;
+                libc::tcsetattr(fd, libc::TCSANOW, &termios);
+            }
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn keep_output_processing() {}
+
+    /// Resolves on the first interrupting key; never resolves if watching fails
+    pub async fn wait(&mut self) -> Interrupt {
+        if let Some(handle) = self.handle.as_mut() {
+            if let Ok(Some(interrupt)) = handle.await {
+                self.handle = None;
+                return interrupt;
+            }
+            self.handle = None;
+        }
+        std::future::pending().await
+    }
+}
+
+impl Drop for KeyWatcher {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        // Let the poller notice before handing the terminal back
+        std::thread::sleep(Duration::from_millis(60));
+        let _ = disable_raw_mode();
+    }
+}
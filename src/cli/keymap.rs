@@ -0,0 +1,264 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::collections::HashMap;
+use tracing::warn;
+
+/// Something a key does in the picker
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PickerAction {
+    Up,
+    Down,
+    PageUp,
+    PageDown,
+    Top,
+    Bottom,
+    Run,
+    Output,
+    FollowUp,
+    MoreLike,
+    Flags,
+    Info,
+    Why,
+    Filter,
+    Quit,
+}
+
+impl PickerAction {
+    pub const ALL: [Self; 15] = [
+        Self::Up,
+        Self::Down,
+        Self::PageUp,
+        Self::PageDown,
+        Self::Top,
+        Self::Bottom,
+        Self::Run,
+        Self::Output,
+        Self::FollowUp,
+        Self::MoreLike,
+        Self::Flags,
+        Self::Info,
+        Self::Why,
+        Self::Filter,
+        Self::Quit,
+    ];
+
+    /// The action's name under `[keymap]`
+    pub fn key(self) -> &'static str {
+        match self {
+            Self::Up => "up",
+            Self::Down => "down",
+            Self::PageUp => "page_up",
+            Self::PageDown => "page_down",
+            Self::Top => "top",
+            Self::Bottom => "bottom",
+            Self::Run => "run",
+            Self::Output => "output",
+            Self::FollowUp => "follow_up",
+            Self::MoreLike => "more_like",
+            Self::Flags => "flags",
+            Self::Info => "info",
+            Self::Why => "why",
+            Self::Filter => "filter",
+            Self::Quit => "quit",
+        }
+    }
+
+    fn default_keys(self) -> &'static [&'static str] {
+        match self {
+            Self::Up => &["Up", "k", "Ctrl-p"],
+            Self::Down => &["Down", "j", "Ctrl-n"],
+            Self::PageUp => &["PageUp", "Ctrl-b"],
+            Self::PageDown => &["PageDown", "Ctrl-f"],
+            Self::Top => &["Home", "g g"],
+            Self::Bottom => &["End", "G"],
+            Self::Run => &["Enter"],
+            Self::Output => &["Tab"],
+            Self::FollowUp => &["Esc", "f", "F"],
+            Self::MoreLike => &["m", "M"],
+            Self::Flags => &["t", "T"],
+            Self::Info => &["i", "I"],
+            Self::Why => &["?"],
+            Self::Filter => &["/"],
+            Self::Quit => &["Esc Esc", "q", "Ctrl-c"],
+        }
+    }
+}
+
+/// A key press; Shift shows in the character itself, so only Ctrl is kept
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Key {
+    pub code: KeyCode,
+    pub ctrl: bool,
+}
+
+impl Key {
+    pub fn from_event(event: &KeyEvent) -> Self {
+        let ctrl = event.modifiers.contains(KeyModifiers::CONTROL);
+        let code = match event.code {
+            KeyCode::Char(c) if ctrl => KeyCode::Char(c.to_ascii_lowercase()),
+            code => code,
+        };
+        Self { code, ctrl }
+    }
+
+    /// A key name such as "Enter", "PageDown", "Ctrl-n", or a single character
+    fn parse(name: &str) -> Option<Self> {
+        let lower = name.to_lowercase();
+        if let Some(rest) = ["ctrl-", "ctrl+", "c-"]
+            .iter()
+            .find_map(|prefix| lower.strip_prefix(prefix))
+        {
+            let mut chars = rest.chars();
+            return match (chars.next(), chars.next()) {
+                (Some(c), None) => Some(Self {
+                    code: KeyCode::Char(c),
+                    ctrl: true,
+                }),
+                _ => None,
+            };
+        }
+        let code = match lower.as_str() {
+            "enter" | "return" => KeyCode::Enter,
+            "tab" => KeyCode::Tab,
+            "esc" | "escape" => KeyCode::Esc,
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "pageup" | "pgup" => KeyCode::PageUp,
+            "pagedown" | "pgdn" => KeyCode::PageDown,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "space" => KeyCode::Char(' '),
+            "backspace" => KeyCode::Backspace,
+            _ => {
+                let mut chars = name.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => KeyCode::Char(c),
+                    _ => return None,
+                }
+            }
+        };
+        Some(Self { code, ctrl: false })
+    }
+
+    /// Keys pressed one after another, separated by spaces ("g g", "Esc Esc");
+    /// a word that isn't a key name is its characters in turn ("gg")
+    fn parse_sequence(spec: &str) -> Option<Vec<Self>> {
+        let mut keys = Vec::new();
+        for word in spec.split_whitespace() {
+            match Self::parse(word) {
+                Some(key) => keys.push(key),
+                None if !word.contains('-') => keys.extend(word.chars().map(|c| Self {
+                    code: KeyCode::Char(c),
+                    ctrl: false,
+                })),
+                None => return None,
+            }
+        }
+        (!keys.is_empty()).then_some(keys)
+    }
+
+    fn label(&self) -> String {
+        let name = match self.code {
+            KeyCode::Enter => "Enter".to_string(),
+            KeyCode::Tab => "Tab".to_string(),
+            KeyCode::Esc => "Esc".to_string(),
+            KeyCode::Up => "↑".to_string(),
+            KeyCode::Down => "↓".to_string(),
+            KeyCode::Left => "←".to_string(),
+            KeyCode::Right => "→".to_string(),
+            KeyCode::PageUp => "PgUp".to_string(),
+            KeyCode::PageDown => "PgDn".to_string(),
+            KeyCode::Home => "Home".to_string(),
+            KeyCode::End => "End".to_string(),
+            KeyCode::Backspace => "Backspace".to_string(),
+            KeyCode::Char(' ') => "Space".to_string(),
+            KeyCode::Char(c) => c.to_string(),
+            _ => "?".to_string(),
+        };
+        match self.ctrl {
+            true => format!("Ctrl-{name}"),
+            false => name,
+        }
+    }
+
+    /// The suggestion number a digit key picks, 1 to 9
+    pub fn digit(&self) -> Option<usize> {
+        match self.code {
+            KeyCode::Char(c @ '1'..='9') if !self.ctrl => c.to_digit(10).map(|d| d as usize),
+            _ => None,
+        }
+    }
+}
+
+/// What the keys pressed so far amount to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyMatch {
+    Action(PickerAction),
+    /// The start of a longer binding, and what the keys do if nothing follows
+    Pending(Option<PickerAction>),
+    None,
+}
+
+/// Picker keys: the built-in bindings, with any action listed under
+/// `[keymap]` taking the keys given there instead
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    bindings: Vec<(Vec<Key>, PickerAction)>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self::from_config(&HashMap::new())
+    }
+}
+
+impl Keymap {
+    pub fn from_config(config: &HashMap<String, Vec<String>>) -> Self {
+        for action in config.keys() {
+            if !PickerAction::ALL.iter().any(|a| a.key() == action) {
+                warn!(action, "Unknown [keymap] entry");
+            }
+        }
+
+        let mut bindings = Vec::new();
+        for action in PickerAction::ALL {
+            let specs: Vec<&str> = match config.get(action.key()) {
+                Some(specs) => specs.iter().map(String::as_str).collect(),
+                None => action.default_keys().to_vec(),
+            };
+            for spec in specs {
+                match Key::parse_sequence(spec) {
+                    Some(keys) => bindings.push((keys, action)),
+                    None => warn!(action = action.key(), key = spec, "Unknown key in [keymap]"),
+                }
+            }
+        }
+        Self { bindings }
+    }
+
+    pub fn lookup(&self, keys: &[Key]) -> KeyMatch {
+        let exact = self
+            .bindings
+            .iter()
+            .find(|(bound, _)| bound == keys)
+            .map(|(_, action)| *action);
+        let longer = self
+            .bindings
+            .iter()
+            .any(|(bound, _)| bound.len() > keys.len() && bound.starts_with(keys));
+        match (exact, longer) {
+            (_, true) => KeyMatch::Pending(exact),
+            (Some(action), false) => KeyMatch::Action(action),
+            (None, false) => KeyMatch::None,
+        }
+    }
+
+    /// The first keys bound to `action`, as the picker's help line shows them
+    pub fn label(&self, action: PickerAction) -> Option<String> {
+        self.bindings
+            .iter()
+            .find(|(_, bound)| *bound == action)
+            .map(|(keys, _)| keys.iter().map(Key::label).collect::<Vec<_>>().join(" "))
+    }
+}
@@ -1,7 +1,36 @@
 pub mod args;
+pub mod batch;
+pub mod capture;
 pub mod commands;
+pub mod exit;
+pub mod flag_menu;
+pub mod hook;
+pub mod interrupt;
+pub mod keymap;
+pub mod osc;
 pub mod output;
+pub mod pipe;
+pub mod runbook;
+pub mod template;
+pub mod theme;
+pub mod watch;
 
-pub use args::{Cli, Commands, PromptOptions};
-pub use commands::{CommandHandler, Suggestion};
-pub use output::{FormatResult, OutputFormatter, Spinner};
+pub use args::{
+    AuditCommands, CacheCommands, Cli, Commands, DaemonCommands, ModelCommands, PackCommands,
+    PinCommands, PromptOptions, SessionCommands, StatsCommands,
+};
+pub use batch::{BatchItem, BatchReport, BatchTask};
+pub use capture::CapturedOutput;
+pub use commands::{CommandHandler, Suggestion, SuggestionSource};
+pub use exit::{BackendUnavailable, Blocked, Outcome};
+pub use flag_menu::FlagMenu;
+pub use hook::ShellHook;
+pub use interrupt::{Interrupt, KeyWatcher};
+pub use keymap::{Key, KeyMatch, Keymap, PickerAction};
+pub use osc::OscEmitter;
+pub use output::{FormatResult, OutputFormatter, Paging, Spinner};
+pub use pipe::PipedInput;
+pub use runbook::{Runbook, RunbookStep};
+pub use template::QueryTemplate;
+pub use theme::{ColorDepth, Role, Theme, ThemeColor};
+pub use watch::{KeyOutcome, KeyStream, WatchExit, WatchScreen, WatchStatus};
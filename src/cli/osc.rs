@@ -0,0 +1,98 @@
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+
+/// Hands a command to the terminal emulator through escape sequences
+///
+/// OSC 52 asks the terminal to put text on the system clipboard, which works
+/// over SSH and inside tmux/screen where native clipboard access does not.
+/// Inside tmux the command is also bracketed-pasted into the pane so it
+/// is waiting on the prompt, unexecuted, when the shell takes over again.
+pub struct OscEmitter;
+
+impl OscEmitter {
+    /// Copies the text via OSC 52; returns true if it was also placed on the prompt
+    pub fn emit(text: &str) -> io::Result<bool> {
+        Self::write_to_terminal(&Self::sequence(text))?;
+        Ok(std::env::var_os("TMUX").is_some() && Self::tmux_paste(text))
+    }
+
+    fn write_to_terminal(sequence: &str) -> io::Result<()> {
+        // Prefer the tty so the sequence survives stdout/stderr redirection
+        match OpenOptions::new().write(true).open("/dev/tty") {
+            Ok(mut tty) => {
+                tty.write_all(sequence.as_bytes())?;
+                tty.flush()
+            }
+            Err(_) => {
+                let mut stderr = io::stderr();
+                stderr.write_all(sequence.as_bytes())?;
+                stderr.flush()
+            }
+        }
+    }
+
+    /// Loads the text into a tmux buffer and pastes it with bracketed-paste markers
+    fn tmux_paste(text: &str) -> bool {
+        let loaded = Command::new("tmux")
+            .args(["load-buffer", "-b", "commandy", "-"])
+            .stdin(Stdio::piped())
+            .spawn()
+            .and_then(|mut child| {
+                if let Some(mut stdin) = child.stdin.take() {
+                    stdin.write_all(text.as_bytes())?;
+                }
+                child.wait()
+            })
+            .is_ok_and(|status| status.success());
+
+        loaded
+            && Command::new("tmux")
+                .args(["paste-buffer", "-p", "-d", "-b", "commandy"])
+                .status()
+                .is_ok_and(|status| status.success())
+    }
+
+    /// OSC 52 clipboard sequence, wrapped for multiplexers when needed
+    pub fn sequence(text: &str) -> String {
+        let osc = format!("\x1b]52;c;{}\x07", Self::base64(text.as_bytes()));
+
+        if std::env::var_os("TMUX").is_some() {
+            // tmux passthrough: double every ESC inside a DCS envelope
+            format!("\x1bPtmux;{}\x1b\\", osc.replace('\x1b', "\x1b\x1b"))
+        } else if std::env::var("TERM").is_ok_and(|t| t.starts_with("screen")) {
+            format!("\x1bP{osc}\x1b\\")
+        } else {
+            osc
+        }
+    }
+
+    fn base64(bytes: &[u8]) -> String {
+        const ALPHABET: &[u8; 64] =
+            b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+        let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+        for chunk in bytes.chunks(3) {
+            let b = [
+                chunk[0],
+                *chunk.get(1).unwrap_or(&0),
+                *chunk.get(2).unwrap_or(&0),
+            ];
+            let n = (u32::from(b[0]) << 16) | (u32::from(b[1]) << 8) | u32::from(b[2]);
+
+            encoded.push(ALPHABET[(n >> 18) as usize & 63] as char);
+            encoded.push(ALPHABET[(n >> 12) as usize & 63] as char);
+            encoded.push(if chunk.len() > 1 {
+                ALPHABET[(n >> 6) as usize & 63] as char
+            } else {
+                '='
+            });
+            encoded.push(if chunk.len() > 2 {
+                ALPHABET[n as usize & 63] as char
+            } else {
+                '='
+            });
+        }
+        encoded
+    }
+}
@@ -1,24 +1,80 @@
-use crate::cli::Suggestion;
-use crate::context::ContextManager;
+use crate::cli::{
+    CapturedOutput, Key, KeyMatch, Keymap, OscEmitter, PickerAction, Role, Suggestion,
+    SuggestionSource, Theme,
+};
+use crate::config::{RiskAction, SandboxConfig, SandboxMount};
+use crate::context::{
+    ContextManager, EntityExtractor, EntityKind, HistorySource, RecallMatch, ShellSubstitution,
+};
+use crate::postprocess::{ExpansionGuard, QuotingCheck};
+use crate::preview::{EditPreview, Sandbox, SandboxOutcome};
+use crate::remote::RemoteTarget;
+use crate::safety::{
+    AuditRecord, AutoRunPolicy, PayloadScanner, Risk, RiskClassifier, RiskPolicy, RiskTier,
+    RunContext, UnpackedScript,
+};
+use crate::utils::{Platform, PrivilegeAnalyzer, ShellDetector, ToolDocs, WordChange, WordDiff};
 use arboard::Clipboard;
-use console::{style, Color};
 use crossterm::{
-    event::{self, Event, KeyCode},
+    event::{self, Event, KeyCode, KeyEventKind},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
-use std::io::{self, Write};
-use std::process::Command;
+use std::collections::HashMap;
+use std::io::{self, IsTerminal, Write};
+use std::process::{Command, Stdio};
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::Duration;
 
+/// Cells in the confidence bar shown next to each suggestion
+const CONFIDENCE_BAR_CELLS: usize = 5;
+
+/// Lines of a program's summary the info pane shows
+const INFO_PANE_LINES: usize = 12;
+
+/// How long the picker waits for the rest of a key sequence such as Esc Esc
+/// when the first key does something on its own
+const SEQUENCE_TIMEOUT: Duration = Duration::from_millis(300);
+
+/// A suggestion as shown in the picker
+struct MenuItem {
+    label: String,
+    confidence: f32,
+    /// Ranking factors, shown with `?`
+    why: Vec<String>,
+    /// The program summarized in the info pane
+    tool: Option<String>,
+}
+
+/// How the picker pages through suggestions
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Paging {
+    /// Suggestions per page; 0 shows them all at once
+    pub page_size: usize,
+    /// Another page can be generated after the last suggestion
+    pub more: bool,
+    /// The suggestion selected when the picker opens
+    pub selected: usize,
+    /// The first suggestion added since the picker was last open; `--plain`
+    /// lists only these, below the others
+    pub new_from: usize,
+}
+
 #[derive(Debug)]
 pub enum SelectAction {
     Execute(usize),
     Output(usize),
     Followup(usize),
+    /// Scrolled past the last suggestion while another page can be generated
+    More,
+    /// Other versions of this suggestion, with different flags or tools
+    MoreLike(usize),
+    /// The info pane needs a summary of this suggestion's program
+    Info(usize),
+    /// Turn this suggestion's flags on and off or change their values
+    Flags(usize),
     Cancel,
 }
 
@@ -27,47 +83,162 @@ pub enum FormatResult {
     Executed(String),
     Output(String),
     FollowupRequested,
+    /// Generate the next page and open the picker again
+    MoreRequested,
+    /// Add variations of this suggestion and open the picker again
+    MoreLikeRequested(usize),
+    /// Open the flag menu for this suggestion, then the picker again
+    FlagsRequested(usize),
+    /// Summarize this suggestion's program for the info pane and open the picker again
+    InfoRequested(usize),
+    /// The chosen command runs a downloaded script; the handler unpacks it first
+    UnpackRequested(String),
     Static(String),
 }
 
 pub struct OutputFormatter {
     use_colors: bool,
+    theme: Theme,
+    /// Keys the picker responds to
+    keymap: Keymap,
+    /// Hand selected commands to the terminal via OSC 52 instead of the clipboard API
+    emit_osc: bool,
+    /// Preview in-place file edits as a diff and ask before running them
+    confirm_edits: bool,
+    /// Try executed commands in a throwaway container before the host
+    sandbox: Option<SandboxConfig>,
+    /// Download `curl | sh` scripts and show them before running
+    unpack_scripts: bool,
+    /// Host from --target that chosen commands run on
+    remote: Option<RemoteTarget>,
+    /// Limits from the active profile, enforced just before a command runs
+    policy: Option<RiskPolicy>,
+    /// --read-only: chosen commands are printed, never run or copied
+    read_only: bool,
+    /// Runs a confident read-only top suggestion without showing the picker
+    auto_run: Option<AutoRunPolicy>,
+    /// --pick: the suggestion to run, by index, instead of showing the picker
+    pick: Option<usize>,
+    /// Seconds during which a destructive command that just ran needs a second yes
+    rerun_cooldown_secs: u64,
+    /// Line-oriented output for screen readers and dumb terminals: no TUI or symbols
+    plain: bool,
+    /// Summaries of programs for the info pane, by name
+    tool_summaries: HashMap<String, String>,
+    /// The info pane is open; it stays open when the picker is reopened
+    info_pane: AtomicBool,
+    /// Bytes of a chosen command's output kept for summaries, next steps, and
+    /// fixes; `None` leaves output uncaptured
+    capture_limit: Option<usize>,
+    /// Standard output is captured too, not only standard error
+    capture_stdout: bool,
+    /// The last command run and its output, until it is summarized or dropped
+    captured: Mutex<Option<CapturedOutput>>,
+    /// A chosen command was refused by the profile or a declined confirmation
+    blocked: AtomicBool,
+    /// --quiet: nothing on stderr, and no spinners
+    quiet: bool,
 }
 
+/// Cleared by `--plain`, which turns spinners into a single status line
+static SPINNERS_ANIMATED: AtomicBool = AtomicBool::new(true);
+
+/// Cleared by `--quiet`, which leaves spinners out entirely
+static SPINNERS_SHOWN: AtomicBool = AtomicBool::new(true);
+
+/// Text of the spinner being shown, so code running under it can say what it waits for
+static ACTIVE_SPINNER: Mutex<Option<Arc<Mutex<String>>>> = Mutex::new(None);
+
 pub struct Spinner {
     running: Arc<AtomicBool>,
+    message: Arc<Mutex<String>>,
     handle: Option<thread::JoinHandle<()>>,
 }
 
 impl Spinner {
     pub fn new(message: &str) -> Self {
         let running = Arc::new(AtomicBool::new(true));
+        let shared = Arc::new(Mutex::new(message.to_string()));
+        if !SPINNERS_SHOWN.load(Ordering::Relaxed) {
+            return Self {
+                running,
+                message: shared,
+                handle: None,
+            };
+        }
+        *ACTIVE_SPINNER.lock().unwrap_or_else(|e| e.into_inner()) = Some(shared.clone());
+        if !SPINNERS_ANIMATED.load(Ordering::Relaxed) {
+            eprintln!("{message}");
+            return Self {
+                running,
+                message: shared,
+                handle: None,
+            };
+        }
+
         let running_clone = running.clone();
-        let message = message.to_string();
+        let message = shared.clone();
 
         let handle = thread::spawn(move || {
             let frames = ["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
             let mut frame_index = 0;
+            let mut width = 0usize;
 
             while running_clone.load(Ordering::Relaxed) {
-                eprint!("\r{} {}", frames[frame_index], message);
+                let text = message.lock().unwrap_or_else(|e| e.into_inner()).clone();
+                // Pad over the end of a longer earlier message
+                let padding = width.saturating_sub(text.len());
+                width = width.max(text.len());
+                eprint!("\r{} {text}{}", frames[frame_index], " ".repeat(padding));
                 io::stderr().flush().unwrap();
                 frame_index = (frame_index + 1) % frames.len();
                 thread::sleep(Duration::from_millis(100));
             }
 
             // Clear the spinner line
-            eprint!("\r{}\r", " ".repeat(message.len() + 3));
+            eprint!("\r{}\r", " ".repeat(width + 3));
             io::stderr().flush().unwrap();
         });
 
         Self {
             running,
+            message: shared,
             handle: Some(handle),
         }
     }
 
+    /// Replaces the text next to the spinner; with `--plain` it is printed as a new line
+    pub fn set_message(&self, message: &str) {
+        let mut current = self.message.lock().unwrap_or_else(|e| e.into_inner());
+        if *current != message {
+            if self.handle.is_none() && SPINNERS_SHOWN.load(Ordering::Relaxed) {
+                eprintln!("{message}");
+            }
+            *current = message.to_string();
+        }
+    }
+
+    /// Replaces the text of whichever spinner is showing, for code that runs
+    /// under a spinner it didn't start; returns the text it had, or `None`
+    /// when no spinner is showing
+    pub fn retitle(message: &str) -> Option<String> {
+        let active = ACTIVE_SPINNER.lock().unwrap_or_else(|e| e.into_inner());
+        let mut current = active.as_ref()?.lock().unwrap_or_else(|e| e.into_inner());
+        if !SPINNERS_ANIMATED.load(Ordering::Relaxed) && *current != message {
+            eprintln!("{message}");
+        }
+        Some(std::mem::replace(&mut current, message.to_string()))
+    }
+
     pub fn stop(mut self) {
+        let mut active = ACTIVE_SPINNER.lock().unwrap_or_else(|e| e.into_inner());
+        if active
+            .as_ref()
+            .is_some_and(|shown| Arc::ptr_eq(shown, &self.message))
+        {
+            *active = None;
+        }
+        drop(active);
         self.running.store(false, Ordering::Relaxed);
         if let Some(handle) = self.handle.take() {
             handle.join().unwrap();
@@ -77,7 +248,273 @@ impl Spinner {
 
 impl OutputFormatter {
     pub fn new(use_colors: bool) -> Self {
-        Self { use_colors }
+        Self {
+            use_colors,
+            theme: Theme::default(),
+            keymap: Keymap::default(),
+            emit_osc: false,
+            confirm_edits: false,
+            sandbox: None,
+            unpack_scripts: false,
+            remote: None,
+            policy: None,
+            read_only: false,
+            auto_run: None,
+            pick: None,
+            rerun_cooldown_secs: 0,
+            plain: false,
+            tool_summaries: HashMap::new(),
+            info_pane: AtomicBool::new(false),
+            capture_limit: None,
+            capture_stdout: false,
+            captured: Mutex::new(None),
+            blocked: AtomicBool::new(false),
+            quiet: false,
+        }
+    }
+
+    /// `NO_COLOR` set to anything but an empty string turns colors off (no-color.org)
+    pub fn no_color_requested() -> bool {
+        std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty())
+    }
+
+    pub fn set_plain(&mut self, plain: bool) {
+        self.plain = plain;
+        if plain {
+            self.use_colors = false;
+            SPINNERS_ANIMATED.store(false, Ordering::Relaxed);
+        }
+    }
+
+    pub fn is_plain(&self) -> bool {
+        self.plain
+    }
+
+    pub fn set_quiet(&mut self, quiet: bool) {
+        self.quiet = quiet;
+        SPINNERS_SHOWN.store(!quiet, Ordering::Relaxed);
+    }
+
+    pub fn is_quiet(&self) -> bool {
+        self.quiet
+    }
+
+    /// Whether a chosen command was refused for safety, for the exit code
+    pub fn was_blocked(&self) -> bool {
+        self.blocked.load(Ordering::Relaxed)
+    }
+
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+    }
+
+    pub fn set_pick(&mut self, pick: Option<usize>) {
+        self.pick = pick;
+    }
+
+    pub fn set_keymap(&mut self, keymap: Keymap) {
+        self.keymap = keymap;
+    }
+
+    /// The theme in use, or `None` when colors are off
+    pub fn theme(&self) -> Option<&Theme> {
+        self.use_colors.then_some(&self.theme)
+    }
+
+    pub fn set_emit_osc(&mut self, emit_osc: bool) {
+        self.emit_osc = emit_osc;
+    }
+
+    pub fn set_confirm_edits(&mut self, confirm_edits: bool) {
+        self.confirm_edits = confirm_edits;
+    }
+
+    pub fn set_sandbox(&mut self, sandbox: Option<SandboxConfig>) {
+        self.sandbox = sandbox;
+    }
+
+    pub fn set_unpack_scripts(&mut self, unpack_scripts: bool) {
+        self.unpack_scripts = unpack_scripts;
+    }
+
+    pub fn set_remote(&mut self, remote: Option<RemoteTarget>) {
+        self.remote = remote;
+    }
+
+    pub fn set_policy(&mut self, policy: Option<RiskPolicy>) {
+        self.policy = policy;
+    }
+
+    pub fn set_read_only(&mut self, read_only: bool) {
+        self.read_only = read_only;
+    }
+
+    pub fn set_auto_run(&mut self, auto_run: Option<AutoRunPolicy>) {
+        self.auto_run = auto_run;
+    }
+
+    /// Shows `summary` in the info pane for suggestions that run `tool`
+    pub fn set_tool_summary(&mut self, tool: &str, summary: String) {
+        self.tool_summaries.insert(tool.to_string(), summary);
+    }
+
+    pub fn tool_summary(&self, tool: &str) -> Option<&str> {
+        self.tool_summaries.get(tool).map(String::as_str)
+    }
+
+    pub fn set_rerun_cooldown(&mut self, secs: u64) {
+        self.rerun_cooldown_secs = secs;
+    }
+
+    /// Keeps up to `limit` bytes of what chosen commands print, for summaries,
+    /// next steps, and fixes; without `stdout`, only standard error
+    pub fn set_capture(&mut self, limit: Option<usize>, stdout: bool) {
+        self.capture_limit = limit;
+        self.capture_stdout = stdout;
+    }
+
+    /// The last command run, with its output when that could be captured
+    pub fn take_captured(&self) -> Option<CapturedOutput> {
+        self.captured
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take()
+    }
+
+    /// Asks whether to summarize a command's output: `s` does, any other key doesn't
+    pub fn offer_summary(&self) -> bool {
+        let answer = self.ask_key(
+            "Press s to summarize the output, any other key to finish",
+            "Enter s to summarize the output, or nothing to finish:",
+        );
+        answer.is_some_and(|answer| answer.eq_ignore_ascii_case("s"))
+    }
+
+    /// Lists likely next commands and asks which to run, by number
+    pub fn pick_next_step(&self, suggestions: &[Suggestion]) -> Option<usize> {
+        eprintln!("{}", self.style_text("Next steps:", Role::Muted));
+        for (i, suggestion) in suggestions.iter().enumerate() {
+            eprintln!(
+                "  {} {}",
+                self.style_text(&format!("{}.", i + 1), Role::Number),
+                self.style_text(&suggestion.command, Role::Command)
+            );
+        }
+        let answer = self.ask_key(
+            "Press a number to run one, any other key to finish",
+            "Enter a number to run one, or nothing to finish:",
+        )?;
+        match answer.parse::<usize>() {
+            Ok(n) if (1..=suggestions.len()).contains(&n) => Some(n - 1),
+            _ => None,
+        }
+    }
+
+    /// Shows fixes for a failed command with what each changes, and asks which to run
+    pub fn pick_fix(&self, failed: &str, fixes: &[Suggestion]) -> Option<usize> {
+        eprintln!("{}", self.style_text("Possible fixes:", Role::Muted));
+        for (i, fix) in fixes.iter().enumerate() {
+            let number = format!("{}.", i + 1);
+            let indent = " ".repeat(number.len());
+            let changes = WordDiff::diff(failed, &fix.command);
+            if self.use_colors {
+                let old = self.diff_words(&changes, false);
+                let new = self.diff_words(&changes, true);
+                eprintln!(
+                    "  {} {} {old}",
+                    self.style_text(&number, Role::Number),
+                    self.style_text("-", Role::DiffRemoved)
+                );
+                eprintln!("  {indent} {} {new}", self.style_text("+", Role::DiffAdded));
+            } else {
+                // git's --word-diff markers, where colors can't show the change
+                let words: Vec<String> = changes
+                    .iter()
+                    .map(|change| match change {
+                        WordChange::Same(word) => word.clone(),
+                        WordChange::Removed(word) => format!("[-{word}-]"),
+                        WordChange::Added(word) => format!("{{+{word}+}}"),
+                    })
+                    .collect();
+                eprintln!("  {number} {}", words.join(" "));
+            }
+        }
+        let answer = self.ask_key(
+            "Press Enter to run the first fix, a number for another, any other key to finish",
+            "Enter a number to run that fix, or nothing to finish:",
+        )?;
+        match answer.as_str() {
+            "\n" => Some(0),
+            answer => match answer.parse::<usize>() {
+                Ok(n) if (1..=fixes.len()).contains(&n) => Some(n - 1),
+                _ => None,
+            },
+        }
+    }
+
+    /// One side of a word diff, with the words only on that side highlighted
+    fn diff_words(&self, changes: &[WordChange], new: bool) -> String {
+        let words: Vec<String> = changes
+            .iter()
+            .filter_map(|change| match (change, new) {
+                (WordChange::Same(word), _) => Some(word.clone()),
+                (WordChange::Removed(word), false) => {
+                    Some(self.style_text(word, Role::DiffRemoved))
+                }
+                (WordChange::Added(word), true) => Some(self.style_text(word, Role::DiffAdded)),
+                _ => None,
+            })
+            .collect();
+        words.join(" ")
+    }
+
+    /// Runs a fix or next step the way a chosen suggestion runs, after filling
+    /// in its placeholders; a fix counts as an answer to `prompt`
+    pub fn run_follow_up(
+        &self,
+        command: &str,
+        prompt: Option<&str>,
+        context: &mut ContextManager,
+    ) -> String {
+        eprintln!("{command}");
+        let Some(filled) = self.fill_placeholders(command) else {
+            return self.format_info("Cancelled");
+        };
+        self.execute(&filled, prompt.map(|prompt| (prompt, command)), context)
+    }
+
+    /// A single key from the terminal, with Enter as `\n`, or a line under
+    /// `--plain`; `None` without a terminal
+    fn ask_key(&self, question: &str, plain_question: &str) -> Option<String> {
+        if !io::stdin().is_terminal() {
+            return None;
+        }
+        let question = if self.plain { plain_question } else { question };
+        eprint!("{} ", self.style_text(question, Role::Muted));
+        let _ = io::stderr().flush();
+        if self.plain {
+            let mut answer = String::new();
+            io::stdin().read_line(&mut answer).ok()?;
+            return Some(answer.trim().to_string());
+        }
+
+        enable_raw_mode().ok()?;
+        let answer = loop {
+            match event::read() {
+                Ok(Event::Key(key)) if key.kind != event::KeyEventKind::Release => {
+                    break match key.code {
+                        KeyCode::Char(c) => Some(c.to_string()),
+                        KeyCode::Enter => Some("\n".to_string()),
+                        _ => Some(String::new()),
+                    };
+                }
+                Ok(_) => continue,
+                Err(_) => break None,
+            }
+        };
+        let _ = disable_raw_mode();
+        eprintln!();
+        answer
     }
 
     pub fn format_suggestions(
@@ -86,12 +523,55 @@ impl OutputFormatter {
         show_explanations: bool,
         original_prompt: &str,
         context: &mut ContextManager,
+        paging: Paging,
     ) -> FormatResult {
         if suggestions.is_empty() {
-            return FormatResult::Static(self.style_text("No suggestions found.", Color::Yellow));
+            return FormatResult::Static(self.style_text("No suggestions found.", Role::Warning));
+        }
+        // Reopening with more suggestions means the user already passed the top
+        // one, and --pick has chosen already
+        if paging.new_from == 0 && self.pick.is_none() {
+            if let Some(output) = self.auto_run(&suggestions[0], original_prompt, context) {
+                return FormatResult::Executed(output);
+            }
         }
 
-        self.interactive_select(suggestions, show_explanations, original_prompt, context)
+        self.interactive_select(
+            suggestions,
+            show_explanations,
+            original_prompt,
+            context,
+            paging,
+        )
+    }
+
+    /// Runs the top suggestion without the picker when `[auto_execute]` allows it
+    ///
+    /// Never with --read-only, --target, or --sandbox, which ask for a closer look.
+    fn auto_run(
+        &self,
+        top: &Suggestion,
+        original_prompt: &str,
+        context: &mut ContextManager,
+    ) -> Option<String> {
+        let policy = self.auto_run.as_ref()?;
+        if self.read_only || self.remote.is_some() || self.sandbox.is_some() {
+            return None;
+        }
+        if let Some(reason) = policy.refusal(&top.command, top.confidence) {
+            tracing::debug!("Not auto-running {}: {reason}", top.command);
+            return None;
+        }
+
+        eprintln!(
+            "{}",
+            self.format_info(&format!(
+                "Running read-only command ({:.0}% confident): {}",
+                top.confidence * 100.0,
+                top.command
+            ))
+        );
+        Some(self.execute(&top.command, Some((original_prompt, &top.command)), context))
     }
 
     fn interactive_select(
@@ -100,94 +580,150 @@ impl OutputFormatter {
         show_explanations: bool,
         original_prompt: &str,
         context: &mut ContextManager,
+        paging: Paging,
     ) -> FormatResult {
-        let items: Vec<String> = suggestions
+        let generated = RunContext::capture();
+        let items: Vec<MenuItem> = suggestions
             .iter()
             .map(|s| {
-                if show_explanations && s.explanation.is_some() {
-                    format!("{} - {}", s.command, s.explanation.as_ref().unwrap())
-                } else {
-                    s.command.clone()
+                let label = match &s.explanation {
+                    Some(explanation) if show_explanations => {
+                        let summary = explanation.lines().next().unwrap_or_default();
+                        format!("{} - {}", s.command, summary)
+                    }
+                    _ => s.command.clone(),
+                };
+                let label = match Self::root_marker(&s.command) {
+                    Some(marker) => format!("{label} {marker}"),
+                    None => label,
+                };
+                let label = match QuotingCheck::unbalanced_quote(&s.command) {
+                    Some(_) => format!("{label} [unbalanced quotes]"),
+                    None => label,
+                };
+                let label = match ExpansionGuard::unguarded(&s.command) {
+                    Some(name) => format!("{label} [unguarded ${name}]"),
+                    None => label,
+                };
+                let label = match s.source {
+                    SuggestionSource::Heuristic => format!("{label} [offline heuristic]"),
+                    SuggestionSource::Pinned => format!("{label} [pinned]"),
+                    _ => label,
+                };
+                MenuItem {
+                    label,
+                    confidence: s.confidence,
+                    why: s.ranking.as_ref().map(|r| r.describe()).unwrap_or_default(),
+                    tool: ToolDocs::tool(&s.command),
                 }
             })
             .collect();
 
-        match self.custom_select(&items) {
+        let selection = if let Some(index) = self.pick.filter(|&i| i < items.len()) {
+            Ok(SelectAction::Execute(index))
+        } else if self.plain {
+            self.plain_select(&items, paging)
+        } else {
+            self.custom_select(&items, paging)
+        };
+        match selection {
             Ok(SelectAction::Execute(index)) => {
-                let selected_command = &suggestions[index].command;
-
                 // Ensure we're back to normal terminal mode before printing
                 io::stdout().flush().unwrap();
-                eprintln!("{selected_command}");
+                eprintln!("{}", suggestions[index].command);
 
-                let mut cmd = if cfg!(target_os = "windows") {
-                    let mut cmd = Command::new("cmd");
-                    cmd.args(["/C", selected_command]);
-                    cmd
-                } else {
-                    let mut cmd = Command::new("sh");
-                    cmd.args(["-c", selected_command]);
-                    cmd
+                let Some(filled) = self.fill_placeholders(&suggestions[index].command) else {
+                    return FormatResult::Executed(self.format_info("Cancelled"));
                 };
+                let selected_command = &filled;
 
-                match cmd.status() {
-                    Ok(status) => {
-                        let success = status.success();
-
-                        // Record feedback for learning
-                        if let Err(e) = context.record_suggestion_feedback(
-                            original_prompt,
-                            selected_command,
-                            success,
-                        ) {
-                            log::warn!("Failed to record suggestion feedback: {e}");
-                        }
+                // Before sandboxes and downloads, which run things too
+                if self.read_only {
+                    return FormatResult::Executed(self.format_info("Read-only mode: not run"));
+                }
 
-                        if success {
-                            FormatResult::Executed(String::new())
-                        } else {
-                            FormatResult::Executed(self.format_error(&format!(
-                                "Command exited with code: {:?}",
-                                status.code()
-                            )))
-                        }
-                    }
-                    Err(e) => {
-                        // Record execution failure
-                        if let Err(err) = context.record_suggestion_feedback(
-                            original_prompt,
-                            selected_command,
-                            false,
-                        ) {
-                            log::warn!("Failed to record suggestion feedback: {err}");
-                        }
-                        FormatResult::Executed(
-                            self.format_error(&format!("Failed to execute command: {e}")),
-                        )
+                // Previews, sandboxes, and downloads all act on this machine
+                if let Some(remote) = &self.remote {
+                    return FormatResult::Executed(self.execute_remote(
+                        remote,
+                        selected_command,
+                        original_prompt,
+                        context,
+                    ));
+                }
+
+                if !self.confirm_repeat(selected_command, &generated, context) {
+                    self.blocked.store(true, Ordering::Relaxed);
+                    return FormatResult::Executed(self.format_info("Not run"));
+                }
+
+                if self.unpack_scripts && PayloadScanner::remote_script(selected_command).is_some()
+                {
+                    return FormatResult::UnpackRequested(filled);
+                }
+
+                // Previews and sandboxes run the command too, so the profile gets its say first
+                let host = Platform::hostname().unwrap_or_else(|| "localhost".to_string());
+                if let Err(message) = self.enforce_policy(selected_command, &host) {
+                    return FormatResult::Executed(message);
+                }
+
+                if self.confirm_edits && !self.confirm_file_edits(selected_command) {
+                    return FormatResult::Executed(self.format_info("Not applied"));
+                }
+
+                if let Some(sandbox) = &self.sandbox {
+                    if !self.confirm_after_sandbox(selected_command, sandbox) {
+                        return FormatResult::Executed(self.format_info("Not run on the host"));
                     }
                 }
+
+                FormatResult::Executed(self.run_command(
+                    selected_command,
+                    Some((original_prompt, selected_command)),
+                    context,
+                ))
             }
             Ok(SelectAction::Output(index)) => {
                 let selected_command = &suggestions[index].command;
+                context.record_session_accepted(original_prompt, selected_command);
 
-                // Copy to clipboard and show instructions
-                match Clipboard::new() {
-                    Ok(mut clipboard) => {
-                        if clipboard.set_text(selected_command).is_ok() {
-                            eprintln!("Command copied to clipboard: {selected_command}");
-                            eprintln!("Press Cmd+V (Mac) or Ctrl+V to paste at your prompt");
-                        } else {
+                if self.read_only {
+                    println!("{selected_command}");
+                    return FormatResult::Output(String::new());
+                }
+
+                if self.emit_osc {
+                    match OscEmitter::emit(selected_command) {
+                        Ok(true) => {}
+                        Ok(false) => {
+                            eprintln!("Command sent to terminal clipboard: {selected_command}")
+                        }
+                        Err(e) => {
+                            tracing::warn!("Failed to emit OSC sequence: {e}");
                             eprintln!("{selected_command}");
                         }
                     }
-                    Err(_) => {
-                        eprintln!("{selected_command}");
-                    }
+                    return FormatResult::Output(String::new());
+                }
+
+                // Copy to clipboard and show instructions
+                if Self::copy_to_clipboard(selected_command) {
+                    eprintln!("Command copied to clipboard: {selected_command}");
+                    eprintln!("Press Cmd+V (Mac) or Ctrl+V to paste at your prompt");
+                } else {
+                    eprintln!("{selected_command}");
                 }
 
                 FormatResult::Output(String::new())
             }
             Ok(SelectAction::Followup(_index)) => FormatResult::FollowupRequested,
+            Ok(SelectAction::More) => FormatResult::MoreRequested,
+            Ok(SelectAction::MoreLike(index)) => FormatResult::MoreLikeRequested(index),
+            Ok(SelectAction::Flags(index)) => FormatResult::FlagsRequested(index),
+            Ok(SelectAction::Info(index)) => FormatResult::InfoRequested(index),
+            // The numbered list is already on screen
+            Ok(SelectAction::Cancel) if self.plain => FormatResult::Static(String::new()),
             Ok(SelectAction::Cancel) => {
                 FormatResult::Static(self.format_suggestions_static(suggestions, show_explanations))
             }
@@ -197,19 +733,600 @@ impl OutputFormatter {
         }
     }
 
+    /// Copies text to the system clipboard; under WSL that is the Windows one, via `clip.exe`
+    fn copy_to_clipboard(text: &str) -> bool {
+        if Platform::detect().wsl.is_some() {
+            let copied = Command::new("clip.exe")
+                .stdin(Stdio::piped())
+                .spawn()
+                .and_then(|mut child| {
+                    if let Some(mut stdin) = child.stdin.take() {
+                        stdin.write_all(text.as_bytes())?;
+                    }
+                    child.wait()
+                });
+            if matches!(copied, Ok(status) if status.success()) {
+                return true;
+            }
+        }
+        Clipboard::new()
+            .and_then(|mut clipboard| clipboard.set_text(text))
+            .is_ok()
+    }
+
+    /// Asks for a value for each `<placeholder>` in a command, offering real names
+    /// where the placeholder's kind is known; `None` if the user leaves one empty
+    fn fill_placeholders(&self, command: &str) -> Option<String> {
+        let placeholder = regex::Regex::new(r"<([A-Za-z][A-Za-z0-9_-]*)>").expect("valid regex");
+        let names: Vec<String> = placeholder
+            .captures_iter(command)
+            .map(|c| c[1].to_string())
+            .collect();
+        if names.is_empty() {
+            return Some(command.to_string());
+        }
+
+        let mut filled = command.to_string();
+        for name in names {
+            let token = format!("<{name}>");
+            // Repeated placeholders are filled on first sight
+            if !filled.contains(&token) {
+                continue;
+            }
+            let candidates = EntityKind::for_placeholder(&name)
+                .and_then(EntityExtractor::extract)
+                .map(|set| set.values)
+                .unwrap_or_default();
+            for (i, candidate) in candidates.iter().enumerate() {
+                eprintln!("  {}) {candidate}", i + 1);
+            }
+            eprint!("{} ", self.style_text(&format!("{token}:"), Role::Number));
+            let _ = io::stderr().flush();
+
+            let mut answer = String::new();
+            io::stdin().read_line(&mut answer).ok()?;
+            let answer = answer.trim();
+            let value = match answer.parse::<usize>() {
+                Ok(n) if (1..=candidates.len()).contains(&n) => candidates[n - 1].clone(),
+                _ if answer.is_empty() => return None,
+                _ => answer.to_string(),
+            };
+            filled = filled.replace(&token, &value);
+        }
+
+        eprintln!("{filled}");
+        Some(filled)
+    }
+
+    /// Asks first when the directory or git branch changed while the picker was
+    /// open, or when a destructive command already ran moments ago, which is
+    /// usually one Enter too many
+    fn confirm_repeat(
+        &self,
+        command: &str,
+        generated: &RunContext,
+        context: &ContextManager,
+    ) -> bool {
+        let changes = RunContext::capture().changes_since(generated);
+        if !changes.is_empty() {
+            eprintln!(
+                "{}",
+                self.format_warning("Changed since these suggestions were made:")
+            );
+            for change in &changes {
+                eprintln!("  {}", self.style_text(change, Role::Risk));
+            }
+            if !self.ask_yes_no("Run it anyway?") {
+                return false;
+            }
+        }
+
+        if self.rerun_cooldown_secs == 0
+            || RiskClassifier::classify(command).tier != RiskTier::Destructive
+        {
+            return true;
+        }
+        let Some(last_run) = context.last_run(command) else {
+            return true;
+        };
+        let seconds = (chrono::Utc::now() - last_run).num_seconds().max(0);
+        if seconds as u64 >= self.rerun_cooldown_secs {
+            return true;
+        }
+        eprintln!(
+            "{}",
+            self.format_warning(&format!("This destructive command ran {seconds}s ago"))
+        );
+        self.ask_yes_no("Run it again?")
+    }
+
+    /// Shows what an in-place edit would change and asks whether to run it
+    fn confirm_file_edits(&self, command: &str) -> bool {
+        let preview = match EditPreview::run(command) {
+            Ok(Some(preview)) => preview,
+            Ok(None) => return true,
+            Err(e) => {
+                eprintln!(
+                    "{}",
+                    self.format_warning(&format!("Could not preview edits: {e}"))
+                );
+                return self.ask_yes_no("Run it anyway?");
+            }
+        };
+
+        if !preview.has_changes() {
+            eprintln!(
+                "{}",
+                self.format_info("The command would not change any files")
+            );
+            return self.ask_yes_no("Run it anyway?");
+        }
+        for (_, diff) in &preview.diffs {
+            for line in diff.lines() {
+                eprintln!("{}", self.style_diff_line(line));
+            }
+        }
+        self.ask_yes_no("Apply these changes?")
+    }
+
+    /// Runs the command in a container, then asks whether to run it on the host
+    fn confirm_after_sandbox(&self, command: &str, config: &SandboxConfig) -> bool {
+        let cwd = std::env::current_dir().unwrap_or_else(|_| std::path::PathBuf::from("."));
+        let mount = match config.mount {
+            SandboxMount::ReadOnly => "current directory read-only at /work",
+            SandboxMount::ReadWrite => "current directory writable at /work",
+            SandboxMount::None => "no files from the host",
+        };
+        eprintln!(
+            "{}",
+            self.format_info(&format!("Running in a {} sandbox ({mount})", config.image))
+        );
+
+        match Sandbox::new(config).run(command, &cwd) {
+            Ok(SandboxOutcome::Exited(status)) if status.success() => {
+                eprintln!("{}", self.format_info("Sandbox run succeeded"));
+            }
+            Ok(SandboxOutcome::Exited(status)) => {
+                let code = status
+                    .code()
+                    .map_or_else(|| "a signal".to_string(), |c| format!("code {c}"));
+                eprintln!(
+                    "{}",
+                    self.format_warning(&format!("Sandbox run exited with {code}"))
+                );
+            }
+            Ok(SandboxOutcome::TimedOut) => {
+                eprintln!(
+                    "{}",
+                    self.format_warning(&format!(
+                        "Sandbox run stopped after {}s",
+                        config.timeout_secs
+                    ))
+                );
+            }
+            Err(e) => {
+                eprintln!(
+                    "{}",
+                    self.format_warning(&format!("Could not run the sandbox: {e}"))
+                );
+            }
+        }
+        self.ask_yes_no("Run it on the host?")
+    }
+
+    /// Shows a downloaded script, what the model made of it, and what will run,
+    /// then asks whether to run the saved copy
+    pub fn confirm_unpacked_script(
+        &self,
+        script: &UnpackedScript,
+        summary: Option<&str>,
+        preview_lines: usize,
+    ) -> bool {
+        let total = script.line_count();
+        eprintln!(
+            "{}",
+            self.format_info(&format!(
+                "Downloaded {} to {} ({total} lines, sha256 {})",
+                script.url,
+                script.path.display(),
+                &script.sha256[..16]
+            ))
+        );
+        if let Some(summary) = summary {
+            eprintln!("\n{}", self.style_text("Summary:", Role::Muted));
+            eprintln!(
+                "  {}",
+                self.style_lines(summary, Some(Role::Explanation), "  ", "\n")
+            );
+        }
+
+        if preview_lines > 0 {
+            eprintln!("\n{}", self.style_text("Script:", Role::Muted));
+            let width = total.min(preview_lines).to_string().len();
+            for (i, line) in script.head(preview_lines).iter().enumerate() {
+                let number = format!("{:>width$} ", i + 1);
+                eprintln!("  {}{line}", self.style_text(&number, Role::Number));
+            }
+            if total > preview_lines {
+                eprintln!(
+                    "  {}",
+                    self.style_text(
+                        &format!("... {} more lines", total - preview_lines),
+                        Role::Meta
+                    )
+                );
+            }
+        }
+
+        // Installers often fetch a second stage the same way
+        for finding in PayloadScanner::scan(&script.content) {
+            eprintln!(
+                "{}",
+                self.format_warning(&format!("In the script: {}", finding.describe()))
+            );
+        }
+
+        let Some(local) = &script.local_command else {
+            eprintln!(
+                "{}",
+                self.format_warning("Couldn't rewrite the command to run the saved file")
+            );
+            return false;
+        };
+        eprintln!(
+            "\n{} {}",
+            self.style_text("Runs:", Role::Muted),
+            self.style_text(local, Role::Command)
+        );
+        self.ask_yes_no("Run the downloaded script?")
+    }
+
+    /// Runs a command on the --target host, keeping its output in
+    /// `~/.commandy/remote/` and recording how it went
+    fn execute_remote(
+        &self,
+        remote: &RemoteTarget,
+        command: &str,
+        original_prompt: &str,
+        context: &mut ContextManager,
+    ) -> String {
+        let host = remote.destination().rsplit('@').next().unwrap_or_default();
+        if let Err(message) = self.enforce_policy(command, host) {
+            return message;
+        }
+        eprintln!(
+            "{}",
+            self.format_info(&format!("Running on {}", remote.destination()))
+        );
+        let run = match remote.run(command) {
+            Ok(run) => run,
+            Err(e) => return self.format_error(&format!("Failed to run over ssh: {e}")),
+        };
+
+        let success = run.status.success();
+        context.record_audit(command, run.status.code(), Some(remote.destination()));
+        if let Err(e) =
+            context.record_suggestion_feedback(original_prompt, command, success, run.status.code())
+        {
+            tracing::warn!("Failed to record suggestion feedback: {e}");
+        }
+
+        let mut message = match success {
+            true => String::new(),
+            false => self.format_error(&format!(
+                "Command exited on {} with code: {:?}",
+                remote.destination(),
+                run.status.code()
+            )),
+        };
+        if !run.output.is_empty() {
+            let dir = context.get_commandy_dir().join("remote");
+            let log = dir.join(format!(
+                "{}-{}.log",
+                remote.destination().replace(['@', ':', '/'], "_"),
+                chrono::Local::now().format("%Y%m%d-%H%M%S")
+            ));
+            let saved = std::fs::create_dir_all(&dir)
+                .and_then(|_| std::fs::write(&log, format!("$ {command}\n{}", run.output)));
+            match saved {
+                Ok(()) => {
+                    let note = self.format_info(&format!("Output saved to {}", log.display()));
+                    message = match message.is_empty() {
+                        true => note,
+                        false => format!("{message}\n{note}"),
+                    };
+                }
+                Err(e) => tracing::warn!("Failed to save remote output: {e}"),
+            }
+        }
+        message
+    }
+
+    /// Runs a command through the shell, recording how it went against a
+    /// `(prompt, suggestion)` pair when one is given
+    pub fn execute(
+        &self,
+        command: &str,
+        feedback: Option<(&str, &str)>,
+        context: &mut ContextManager,
+    ) -> String {
+        let host = Platform::hostname().unwrap_or_else(|| "localhost".to_string());
+        if let Err(message) = self.enforce_policy(command, &host) {
+            return message;
+        }
+        self.run_command(command, feedback, context)
+    }
+
+    /// [`Self::execute`] for a command the profile's policy has already allowed
+    fn run_command(
+        &self,
+        command: &str,
+        feedback: Option<(&str, &str)>,
+        context: &mut ContextManager,
+    ) -> String {
+        let mut cmd = if cfg!(target_os = "windows") {
+            let mut cmd = Command::new("cmd");
+            cmd.args(["/C", command]);
+            cmd
+        } else {
+            // The user's functions only exist in their own interactive shell
+            let shell = ShellDetector::detect_shell();
+            let interactive = matches!(shell.as_str(), "bash" | "zsh" | "fish")
+                && ShellSubstitution::load(context.get_commandy_dir()).calls_function(command);
+            let mut cmd = match interactive {
+                true => {
+                    let mut cmd = Command::new(shell);
+                    cmd.arg("-i");
+                    cmd
+                }
+                false => Command::new("sh"),
+            };
+            cmd.args(["-c", command]);
+            cmd
+        };
+
+        let status = match self.capture_limit {
+            Some(limit) if CapturedOutput::capturable(command) => {
+                CapturedOutput::run(&mut cmd, command, limit, self.capture_stdout).map(
+                    |(status, captured)| {
+                        *self.captured.lock().unwrap_or_else(|e| e.into_inner()) = Some(captured);
+                        status
+                    },
+                )
+            }
+            Some(_) => cmd.status().inspect(|status| {
+                *self.captured.lock().unwrap_or_else(|e| e.into_inner()) =
+                    Some(CapturedOutput::uncaptured(command, status.code()));
+            }),
+            None => cmd.status(),
+        };
+        let (success, code, message) = match status {
+            Ok(status) if status.success() => (true, status.code(), String::new()),
+            Ok(status) => (
+                false,
+                status.code(),
+                self.format_error(&format!("Command exited with code: {:?}", status.code())),
+            ),
+            Err(e) => (
+                false,
+                None,
+                self.format_error(&format!("Failed to execute command: {e}")),
+            ),
+        };
+
+        context.record_audit(command, code, None);
+
+        // Record feedback for learning
+        if let Some((prompt, suggestion)) = feedback {
+            if let Err(e) = context.record_suggestion_feedback(prompt, suggestion, success, code) {
+                tracing::warn!("Failed to record suggestion feedback: {e}");
+            }
+        }
+        message
+    }
+
+    /// Applies --read-only, then the profile's limit for the command's risk tier on `host`
+    ///
+    /// Returns the message to show instead when the command must not run.
+    fn enforce_policy(&self, command: &str, host: &str) -> Result<(), String> {
+        if self.read_only {
+            return Err(self.format_info("Read-only mode: not run"));
+        }
+        let Some(policy) = &self.policy else {
+            return Ok(());
+        };
+        let (action, risk) = policy.check(command);
+        if action == RiskAction::Run {
+            return Ok(());
+        }
+
+        let tier = risk.tier.as_str();
+        eprintln!(
+            "{}",
+            self.format_warning(&format!(
+                "{tier} command on the {} profile",
+                policy.profile()
+            ))
+        );
+        for (_, reason) in risk.reasons.iter().filter(|(t, _)| *t == risk.tier) {
+            eprintln!("  {}", self.style_text(reason, Role::Risk));
+        }
+
+        let allowed = match action {
+            RiskAction::Run => true,
+            RiskAction::Confirm => self.ask_yes_no("Run it anyway?"),
+            RiskAction::ConfirmHost => {
+                eprint!("Type the hostname ({host}) to run it: ");
+                let _ = io::stderr().flush();
+                let mut answer = String::new();
+                io::stdin().read_line(&mut answer).is_ok() && answer.trim() == host
+            }
+            RiskAction::DisplayOnly => {
+                tracing::warn!(
+                    "Refused {tier} command on profile {}: {command}",
+                    policy.profile()
+                );
+                self.blocked.store(true, Ordering::Relaxed);
+                return Err(self.format_info(&format!(
+                    "Not run: {tier} commands are display-only on the {} profile",
+                    policy.profile()
+                )));
+            }
+        };
+        self.blocked.store(!allowed, Ordering::Relaxed);
+        match allowed {
+            true => Ok(()),
+            false => Err(self.format_info("Not run")),
+        }
+    }
+
+    fn style_diff_line(&self, line: &str) -> String {
+        if line.starts_with("+++") || line.starts_with("---") {
+            self.style_text(line, Role::Muted)
+        } else if line.starts_with('+') {
+            self.style_text(line, Role::DiffAdded)
+        } else if line.starts_with('-') {
+            self.style_text(line, Role::DiffRemoved)
+        } else if line.starts_with("@@") {
+            self.style_text(line, Role::DiffHunk)
+        } else {
+            line.to_string()
+        }
+    }
+
+    /// Asks a yes-or-no question on the terminal; no when there is none to ask on, or with --quiet
+    pub fn confirm(&self, question: &str) -> bool {
+        if self.quiet || !io::stdin().is_terminal() || !io::stderr().is_terminal() {
+            return false;
+        }
+        self.ask_yes_no(question)
+    }
+
+    fn ask_yes_no(&self, question: &str) -> bool {
+        eprint!("{question} [y/N] ");
+        let _ = io::stderr().flush();
+        let mut answer = String::new();
+        if io::stdin().read_line(&mut answer).is_err() {
+            return false;
+        }
+        matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+    }
+
     // ========================================================================
     // Interactive Selection
     // ========================================================================
 
+    /// Numbered list and a typed choice, for `--plain`
+    ///
+    /// Reopened for another page, it lists only the new suggestions below the others.
+    fn plain_select(&self, items: &[MenuItem], paging: Paging) -> Result<SelectAction, io::Error> {
+        for (i, item) in items.iter().enumerate().skip(paging.new_from) {
+            let number = format!("{}. ", i + 1);
+            println!(
+                "{number}{} (confidence {:.0}%)",
+                self.style_lines(&item.label, None, &" ".repeat(number.len()), "\n"),
+                item.confidence * 100.0
+            );
+        }
+
+        loop {
+            print!(
+                "Enter a number to run that command, p and a number to print it, \
+                 ? and a number for why it ranked there, l and a number for more like it, \
+                 t and a number to change its flags, i and a number to summarize its program, \
+                 m to modify the request, {}\
+                 or nothing to quit: ",
+                match paging.more {
+                    true => "+ for more, ",
+                    false => "",
+                }
+            );
+            io::stdout().flush()?;
+            let mut answer = String::new();
+            if io::stdin().read_line(&mut answer)? == 0 {
+                return Ok(SelectAction::Cancel);
+            }
+
+            let answer = answer.trim().to_lowercase();
+            if answer.is_empty() {
+                return Ok(SelectAction::Cancel);
+            }
+            if answer == "m" {
+                return Ok(SelectAction::Followup(0));
+            }
+            if answer == "+" && paging.more {
+                return Ok(SelectAction::More);
+            }
+            if let Some(number) = answer.strip_prefix('?') {
+                match number.trim().parse::<usize>() {
+                    Ok(n) if (1..=items.len()).contains(&n) => {
+                        for line in &items[n - 1].why {
+                            println!("  {line}");
+                        }
+                    }
+                    _ => println!("Commands are numbered 1 to {}.", items.len()),
+                }
+                continue;
+            }
+            if let Some(number) = answer.strip_prefix('l') {
+                match number.trim().parse::<usize>() {
+                    Ok(n) if (1..=items.len()).contains(&n) => {
+                        return Ok(SelectAction::MoreLike(n - 1))
+                    }
+                    _ => println!("Commands are numbered 1 to {}.", items.len()),
+                }
+                continue;
+            }
+            if let Some(number) = answer.strip_prefix('i') {
+                match number.trim().parse::<usize>() {
+                    Ok(n) if (1..=items.len()).contains(&n) => match &items[n - 1].tool {
+                        Some(tool) => match self.tool_summary(tool) {
+                            Some(summary) => {
+                                println!("{}", self.format_tool_summary(tool, summary))
+                            }
+                            None => return Ok(SelectAction::Info(n - 1)),
+                        },
+                        None => println!("{}", Self::NO_TOOL),
+                    },
+                    _ => println!("Commands are numbered 1 to {}.", items.len()),
+                }
+                continue;
+            }
+            if let Some(number) = answer.strip_prefix('t') {
+                match number.trim().parse::<usize>() {
+                    Ok(n) if (1..=items.len()).contains(&n) => {
+                        return Ok(SelectAction::Flags(n - 1))
+                    }
+                    _ => println!("Commands are numbered 1 to {}.", items.len()),
+                }
+                continue;
+            }
+            let (print, number) = match answer.strip_prefix('p') {
+                Some(rest) => (true, rest.trim()),
+                None => (false, answer.as_str()),
+            };
+            match number.parse::<usize>() {
+                Ok(n) if (1..=items.len()).contains(&n) && print => {
+                    return Ok(SelectAction::Output(n - 1))
+                }
+                Ok(n) if (1..=items.len()).contains(&n) => return Ok(SelectAction::Execute(n - 1)),
+                _ => println!(
+                    "Not a choice: {answer}. Commands are numbered 1 to {}.",
+                    items.len()
+                ),
+            }
+        }
+    }
+
     /// Custom selection interface with keyboard navigation
-    fn custom_select(&self, items: &[String]) -> Result<SelectAction, io::Error> {
+    fn custom_select(&self, items: &[MenuItem], paging: Paging) -> Result<SelectAction, io::Error> {
         enable_raw_mode()?;
         let mut stdout = io::stdout();
         execute!(stdout, EnterAlternateScreen)?;
 
-        let mut selected = 0;
+        let mut selected = paging.selected.min(items.len() - 1);
 
-        let result = self.selection_loop(&mut stdout, items, &mut selected);
+        let result = self.selection_loop(&mut stdout, items, &mut selected, paging);
 
         disable_raw_mode()?;
         execute!(stdout, LeaveAlternateScreen)?;
@@ -220,27 +1337,159 @@ impl OutputFormatter {
     fn selection_loop(
         &self,
         stdout: &mut io::Stdout,
-        items: &[String],
+        items: &[MenuItem],
         selected: &mut usize,
+        paging: Paging,
     ) -> Result<SelectAction, io::Error> {
+        let mut show_why = false;
+        // Text typed after `/`; only suggestions containing it are listed
+        let mut filter = String::new();
+        let mut typing = false;
+        // Keys of a sequence such as `g g` pressed so far
+        let mut pending: Vec<Key> = Vec::new();
+        // A key that broke off a sequence, handled on its own next
+        let mut queued: Option<Key> = None;
         loop {
-            self.render_menu(stdout, items, *selected)?;
+            let visible = Self::matching(items, &filter);
+            if !visible.contains(selected) {
+                *selected = visible.first().copied().unwrap_or(*selected);
+            }
+            if queued.is_none() {
+                self.render_menu(
+                    stdout, items, &visible, *selected, show_why, &filter, typing, paging,
+                )?;
+            }
 
-            if let Event::Key(key_event) = event::read()? {
-                match self.handle_key_input(key_event.code, selected, items.len()) {
-                    Some(action) => return Ok(action),
-                    None => continue,
+            let key = match queued.take() {
+                Some(key) => key,
+                None => match event::read()? {
+                    Event::Key(event) if event.kind != KeyEventKind::Release => {
+                        Key::from_event(&event)
+                    }
+                    _ => continue,
+                },
+            };
+            if typing {
+                match key.code {
+                    KeyCode::Enter => typing = false,
+                    KeyCode::Esc => {
+                        typing = false;
+                        filter.clear();
+                    }
+                    KeyCode::Backspace => {
+                        filter.pop();
+                    }
+                    KeyCode::Char('c') if key.ctrl => return Ok(SelectAction::Cancel),
+                    KeyCode::Char(c) if !key.ctrl => filter.push(c),
+                    _ => {}
                 }
+                continue;
+            }
+
+            pending.push(key);
+            let action = match self.keymap.lookup(&pending) {
+                KeyMatch::Action(action) => action,
+                KeyMatch::Pending(Some(action)) if !event::poll(SEQUENCE_TIMEOUT)? => action,
+                KeyMatch::Pending(_) => continue,
+                KeyMatch::None if pending.len() > 1 => {
+                    // This key doesn't finish the sequence: the keys before it
+                    // do what they do alone, then it is handled by itself
+                    queued = pending.pop();
+                    let earlier = self.keymap.lookup(&pending);
+                    pending.clear();
+                    match earlier {
+                        KeyMatch::Pending(Some(action)) => action,
+                        _ => continue,
+                    }
+                }
+                KeyMatch::None => {
+                    pending.clear();
+                    match key.digit() {
+                        Some(n) if visible.contains(&(n - 1)) => {
+                            return Ok(SelectAction::Execute(n - 1))
+                        }
+                        _ => continue,
+                    }
+                }
+            };
+            pending.clear();
+
+            match action {
+                PickerAction::Why => {
+                    show_why = !show_why;
+                    continue;
+                }
+                PickerAction::Filter => {
+                    typing = true;
+                    continue;
+                }
+                PickerAction::Info => {
+                    self.info_pane.fetch_xor(true, Ordering::Relaxed);
+                }
+                action => {
+                    // Further pages are only generated while the whole list is shown
+                    let more = paging.more && visible.len() == items.len();
+                    if let Some(result) =
+                        self.handle_key_input(action, selected, &visible, paging.page_size, more)
+                    {
+                        return Ok(result);
+                    }
+                }
+            }
+            // The pane shows the selected suggestion's program, summarized on first view
+            let tool = items[*selected].tool.as_deref();
+            if self.info_pane.load(Ordering::Relaxed)
+                && !visible.is_empty()
+                && tool.is_some_and(|tool| self.tool_summary(tool).is_none())
+            {
+                return Ok(SelectAction::Info(*selected));
             }
         }
     }
 
-    /// Renders the selection menu
+    /// Indices of the suggestions containing `filter`, ignoring case
+    fn matching(items: &[MenuItem], filter: &str) -> Vec<usize> {
+        let filter = filter.to_lowercase();
+        items
+            .iter()
+            .enumerate()
+            .filter(|(_, item)| item.label.to_lowercase().contains(&filter))
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// The picker's help line, naming the keys bound to each action
+    fn picker_help(&self) -> String {
+        let mut help: Vec<String> = [
+            (PickerAction::Run, "run"),
+            (PickerAction::Output, "output"),
+            (PickerAction::MoreLike, "more like this"),
+            (PickerAction::Flags, "flags"),
+            (PickerAction::Info, "info"),
+            (PickerAction::Filter, "filter"),
+            (PickerAction::FollowUp, "follow-up"),
+            (PickerAction::Quit, "exit"),
+            (PickerAction::Why, "why"),
+        ]
+        .into_iter()
+        .filter_map(|(action, name)| Some(format!("{}={name}", self.keymap.label(action)?)))
+        .collect();
+        help.push("1-9=run that one".to_string());
+        format!("Select command ({}):", help.join(", "))
+    }
+
+    /// Renders the selection menu, with the ranking factors of the selected item if asked
+    #[allow(clippy::too_many_arguments)]
     fn render_menu(
         &self,
         stdout: &mut io::Stdout,
-        items: &[String],
+        items: &[MenuItem],
+        visible: &[usize],
         selected: usize,
+        show_why: bool,
+        filter: &str,
+        typing: bool,
+        paging: Paging,
     ) -> Result<(), io::Error> {
         execute!(
             stdout,
@@ -248,65 +1497,178 @@ impl OutputFormatter {
         )?;
         execute!(stdout, crossterm::cursor::MoveTo(0, 0))?;
 
-        println!("Select command (Enter=run, Tab=output, Esc=follow-up, Esc Esc=exit):\r");
-        println!("\r");
+        match typing {
+            true => println!("Type to filter (Enter=keep, Esc=clear):\r"),
+            false => println!("{}\r", self.picker_help()),
+        }
+        if typing || !filter.is_empty() {
+            println!("{}\r", self.style_text(&format!("/{filter}"), Role::Info));
+        } else {
+            println!("\r");
+        }
+        if visible.is_empty() {
+            println!(
+                "{}\r",
+                self.style_text("No suggestion contains that.", Role::Muted)
+            );
+            return stdout.flush();
+        }
 
-        for (i, item) in items.iter().enumerate() {
+        // Only the selected suggestion's page is drawn
+        let position = visible.iter().position(|&i| i == selected).unwrap_or(0);
+        let (start, end) = match paging.page_size {
+            0 => (0, visible.len()),
+            size => {
+                let start = position / size * size;
+                (start, (start + size).min(visible.len()))
+            }
+        };
+        let width = items.len().to_string().len();
+        for &i in &visible[start..end] {
+            let item = &items[i];
+            let bar = self.confidence_bar(item.confidence);
+            let number = self.style_text(&format!("{:>width$}", i + 1), Role::Number);
+            // Continuation lines line up under the command, past the marker, number, and bar
+            let indent = " ".repeat(CONFIDENCE_BAR_CELLS + width + 4);
             if i == selected {
-                println!("▶ {}\r", self.style_text(item, Color::Green));
+                let label = self.style_lines(&item.label, Some(Role::Command), &indent, "\r\n");
+                println!("▶ {number} {bar} {label}\r");
+                if show_why {
+                    for line in &item.why {
+                        println!("        {}\r", self.style_text(line, Role::Meta));
+                    }
+                }
             } else {
-                println!("  {item}\r");
+                let label = self.style_lines(&item.label, None, &indent, "\r\n");
+                println!("  {number} {bar} {label}\r");
+            }
+        }
+        let more = paging.more && visible.len() == items.len();
+        if end - start < visible.len() || more {
+            let hint = match more {
+                true => ", ↓ past the end for more",
+                false => "",
+            };
+            let position = format!(
+                "{}-{} of {}{}{} (PgUp/PgDn{hint})",
+                start + 1,
+                end,
+                visible.len(),
+                if more { "+" } else { "" },
+                if visible.len() < items.len() {
+                    " matching"
+                } else {
+                    ""
+                }
+            );
+            println!("\r");
+            println!("{}\r", self.style_text(&position, Role::Meta));
+        }
+        if self.info_pane.load(Ordering::Relaxed) {
+            println!("\r");
+            let text = match &items[selected].tool {
+                Some(tool) => {
+                    let summary = self.tool_summary(tool).unwrap_or_default();
+                    self.format_tool_summary(tool, summary)
+                }
+                None => Self::NO_TOOL.to_string(),
+            };
+            for line in text.lines().take(INFO_PANE_LINES) {
+                println!("{line}\r");
             }
         }
 
         stdout.flush()
     }
 
-    /// Handles keyboard input and returns action if any
+    /// Five cells filled in proportion to the confidence, colored by level
+    fn confidence_bar(&self, confidence: f32) -> String {
+        let filled = (confidence.clamp(0.0, 1.0) * CONFIDENCE_BAR_CELLS as f32).round() as usize;
+        let bar = format!(
+            "{}{}",
+            "█".repeat(filled),
+            "░".repeat(CONFIDENCE_BAR_CELLS - filled)
+        );
+        let role = if confidence >= 0.75 {
+            Role::ConfidenceHigh
+        } else if confidence >= 0.5 {
+            Role::ConfidenceMedium
+        } else {
+            Role::ConfidenceLow
+        };
+        self.style_text(&bar, role)
+    }
+
+    /// Moves the selection within the listed suggestions, or returns what the picker should do
     fn handle_key_input(
         &self,
-        key_code: KeyCode,
+        action: PickerAction,
         selected: &mut usize,
-        items_len: usize,
+        visible: &[usize],
+        page_size: usize,
+        more: bool,
     ) -> Option<SelectAction> {
-        match key_code {
-            KeyCode::Up => {
-                *selected = selected.saturating_sub(1);
-                None
-            }
-            KeyCode::Down => {
-                if *selected < items_len - 1 {
-                    *selected += 1;
-                }
-                None
+        // With nothing matching the filter, there is nothing to run
+        if visible.is_empty() && !matches!(action, PickerAction::FollowUp | PickerAction::Quit) {
+            return None;
+        }
+        let page = page_size.max(1);
+        let last = visible.len().saturating_sub(1);
+        let position = visible.iter().position(|i| i == selected).unwrap_or(0);
+        let moved = match action {
+            PickerAction::Up => position.saturating_sub(1),
+            PickerAction::Down if position == last && more => return Some(SelectAction::More),
+            PickerAction::Down => (position + 1).min(last),
+            PickerAction::PageUp => position.saturating_sub(page) / page * page,
+            PickerAction::PageDown if position / page == last / page && more => {
+                return Some(SelectAction::More)
             }
-            KeyCode::Enter => Some(SelectAction::Execute(*selected)),
-            KeyCode::Tab => Some(SelectAction::Output(*selected)),
-            KeyCode::Char('f') | KeyCode::Char('F') => Some(SelectAction::Followup(*selected)),
-            KeyCode::Esc => self.handle_escape_key(*selected),
-            _ => None,
+            PickerAction::PageDown => ((position / page + 1) * page).min(last),
+            PickerAction::Top => 0,
+            PickerAction::Bottom => last,
+            PickerAction::Run => return Some(SelectAction::Execute(*selected)),
+            PickerAction::Output => return Some(SelectAction::Output(*selected)),
+            PickerAction::FollowUp => return Some(SelectAction::Followup(*selected)),
+            PickerAction::MoreLike => return Some(SelectAction::MoreLike(*selected)),
+            PickerAction::Flags => return Some(SelectAction::Flags(*selected)),
+            PickerAction::Quit => return Some(SelectAction::Cancel),
+            PickerAction::Info | PickerAction::Why | PickerAction::Filter => return None,
+        };
+        if let Some(&index) = visible.get(moved) {
+            *selected = index;
         }
+        None
     }
 
-    /// Handles escape key with double-escape detection
-    fn handle_escape_key(&self, selected: usize) -> Option<SelectAction> {
-        let timeout = Duration::from_millis(300);
+    const NO_TOOL: &'static str =
+        "No program to summarize: it runs only builtins or a local script.";
 
-        if let Ok(true) = event::poll(timeout) {
-            if let Ok(Event::Key(second_key)) = event::read() {
-                if matches!(second_key.code, KeyCode::Esc) {
-                    return Some(SelectAction::Cancel);
-                }
-                // Different key after escape - treat as single escape
-                return Some(SelectAction::Followup(selected));
-            }
-        }
+    /// A program's summary under its name, as the info pane and `--plain` show it
+    pub fn format_tool_summary(&self, tool: &str, summary: &str) -> String {
+        format!(
+            "{}\n{}",
+            self.style_text(&format!("── {tool} ──"), Role::Muted),
+            summary
+        )
+    }
 
-        // Single escape (no second key within timeout)
-        Some(SelectAction::Followup(selected))
+    pub fn format_output_summary(&self, summary: &str) -> String {
+        format!(
+            "{}\n{}",
+            self.style_text("── summary ──", Role::Muted),
+            summary
+        )
     }
 
-    fn format_suggestions_static(
+    /// Notes commands that need root but don't say so with sudo
+    fn root_marker(command: &str) -> Option<String> {
+        if PrivilegeAnalyzer::is_elevated(command) {
+            return None;
+        }
+        PrivilegeAnalyzer::requires_root(command).map(|reason| format!("[needs root: {reason}]"))
+    }
+
+    pub fn format_suggestions_static(
         &self,
         suggestions: &[Suggestion],
         show_explanations: bool,
@@ -316,26 +1678,213 @@ impl OutputFormatter {
         for (i, suggestion) in suggestions.iter().enumerate() {
             // Command number and text
             let number = format!("{}. ", i + 1);
-            output.push_str(&self.style_text(&number, Color::Cyan));
-            output.push_str(&self.style_text(&suggestion.command, Color::Green));
+            output.push_str(&self.style_text(&number, Role::Number));
+            output.push_str(&self.style_lines(
+                &suggestion.command,
+                Some(Role::Command),
+                &" ".repeat(number.len()),
+                "\n",
+            ));
+            if let Some(marker) = Self::root_marker(&suggestion.command) {
+                output.push_str(&self.style_text(&format!(" {marker}"), Role::Risk));
+            }
+            if QuotingCheck::unbalanced_quote(&suggestion.command).is_some() {
+                output.push_str(&self.style_text(" [unbalanced quotes]", Role::Risk));
+            }
+            if let Some(name) = ExpansionGuard::unguarded(&suggestion.command) {
+                let marker = format!(" [unguarded ${name}]");
+                output.push_str(&self.style_text(&marker, Role::Risk));
+            }
+            if suggestion.source == SuggestionSource::Heuristic {
+                output.push_str(&self.style_text(" [offline heuristic]", Role::Warning));
+            }
+            if suggestion.source == SuggestionSource::Pinned {
+                output.push_str(&self.style_text(" [pinned]", Role::Meta));
+            }
             output.push('\n');
 
             // Explanation if available and requested
             if show_explanations {
                 if let Some(explanation) = &suggestion.explanation {
-                    let indented = format!("   {explanation}");
-                    output.push_str(&self.style_text(&indented, Color::White));
+                    output.push_str("   ");
+                    output.push_str(&self.style_lines(
+                        explanation,
+                        Some(Role::Explanation),
+                        "   ",
+                        "\n",
+                    ));
                     output.push('\n');
                 }
             }
 
             // Confidence (only in verbose mode)
             if suggestion.confidence > 0.0 {
-                let confidence = format!("   (confidence: {:.1}%)", suggestion.confidence * 100.0);
-                output.push_str(&self.style_text(&confidence, Color::Blue));
+                let confidence = format!("(confidence: {:.1}%)", suggestion.confidence * 100.0);
+                output.push_str("   ");
+                if !self.plain {
+                    output.push_str(&self.confidence_bar(suggestion.confidence));
+                    output.push(' ');
+                }
+                output.push_str(&self.style_text(&confidence, Role::Meta));
+                output.push('\n');
+            }
+
+            if i < suggestions.len() - 1 {
+                output.push('\n');
+            }
+        }
+
+        output
+    }
+
+    pub fn format_recall(&self, matches: &[RecallMatch]) -> String {
+        let mut output = String::new();
+
+        for (i, m) in matches.iter().enumerate() {
+            let number = format!("{}. ", i + 1);
+            output.push_str(&self.style_text(&number, Role::Number));
+            output.push_str(&self.style_text(&m.entry.command, Role::Command));
+            output.push('\n');
+
+            let when = m
+                .entry
+                .timestamp
+                .map(|t| {
+                    t.with_timezone(&chrono::Local)
+                        .format("%Y-%m-%d %H:%M")
+                        .to_string()
+                })
+                .unwrap_or_else(|| "unknown time".to_string());
+            let source = match m.entry.source {
+                HistorySource::Shell => "shell history",
+                HistorySource::Commandy => "commandy",
+            };
+            let mut meta = format!("   {when} · {source}");
+            if let Some(prompt) = &m.entry.prompt {
+                meta.push_str(&format!(" · \"{prompt}\""));
+            }
+            output.push_str(&self.style_text(&meta, Role::Meta));
+
+            if i < matches.len() - 1 {
                 output.push('\n');
             }
+        }
+
+        output
+    }
+
+    /// Audit records with their sequence numbers, oldest first
+    pub fn format_audit(&self, records: &[AuditRecord]) -> String {
+        let mut output = String::new();
+
+        for (i, record) in records.iter().enumerate() {
+            let number = format!("{:>5}  ", record.seq);
+            output.push_str(&self.style_text(&number, Role::Number));
+            output.push_str(&self.style_text(&record.command, Role::Command));
+            output.push('\n');
+
+            let when = record
+                .timestamp
+                .with_timezone(&chrono::Local)
+                .format("%Y-%m-%d %H:%M:%S");
+            let exit = match record.exit_code {
+                Some(code) => format!("exit {code}"),
+                None => "no exit code".to_string(),
+            };
+            let mut meta = format!("       {when} · {} · {exit} · {}", record.user, record.cwd);
+            if let Some(target) = &record.target {
+                meta.push_str(&format!(" · on {target}"));
+            }
+            output.push_str(&self.style_text(&meta, Role::Meta));
+
+            if i < records.len() - 1 {
+                output.push('\n');
+            }
+        }
+
+        output
+    }
 
+    /// What `commandy inspect` found: the text as the shell sees it, risks, and an explanation
+    pub fn format_inspection(
+        &self,
+        text: &str,
+        risk: &Risk,
+        notes: &[String],
+        explanation: Option<&str>,
+    ) -> String {
+        let mut output = String::new();
+
+        // Invisible and control characters are spelled out so nothing is hidden
+        let visible: String = text
+            .trim()
+            .chars()
+            .map(|c| match PayloadScanner::is_invisible(c) {
+                true => format!("<U+{:04X}>", c as u32),
+                false => c.to_string(),
+            })
+            .collect();
+        output.push_str(&self.style_text("Command:", Role::Muted));
+        output.push_str("\n  ");
+        output.push_str(&self.style_lines(&visible, Some(Role::Command), "  ", "\n"));
+        output.push_str("\n\n");
+
+        let (tier_role, mark) = match risk.tier {
+            RiskTier::Safe => (Role::Success, "    "),
+            _ => (Role::Risk, "  ! "),
+        };
+        output.push_str(&self.style_text("Risk: ", Role::Muted));
+        output.push_str(&self.style_text(risk.tier.as_str(), tier_role));
+        if risk.reasons.is_empty() {
+            output.push_str(&self.style_text(" (nothing risky found)", Role::Meta));
+        }
+        for (tier, reason) in &risk.reasons {
+            let role = match tier {
+                RiskTier::Safe => Role::Meta,
+                _ => Role::Risk,
+            };
+            output.push_str(&format!(
+                "\n{}",
+                self.style_text(&format!("{mark}{reason}"), role)
+            ));
+        }
+        for note in notes {
+            output.push_str(&format!(
+                "\n{}",
+                self.style_text(&format!("  {note}"), Role::Warning)
+            ));
+        }
+
+        if let Some(explanation) = explanation {
+            output.push_str("\n\n");
+            output.push_str(&self.style_text("Explanation:", Role::Muted));
+            output.push_str("\n  ");
+            output.push_str(&self.style_lines(explanation, Some(Role::Explanation), "  ", "\n"));
+        }
+
+        output
+    }
+
+    /// Commands for another shell, printed without the checks that assume `sh`
+    pub fn format_translations(&self, suggestions: &[Suggestion]) -> String {
+        let mut output = String::new();
+
+        for (i, suggestion) in suggestions.iter().enumerate() {
+            let number = format!("{}. ", i + 1);
+            output.push_str(&self.style_text(&number, Role::Number));
+            output.push_str(&self.style_lines(
+                &suggestion.command,
+                Some(Role::Command),
+                &" ".repeat(number.len()),
+                "\n",
+            ));
+            output.push('\n');
+
+            let source = match suggestion.source {
+                SuggestionSource::Rule => "dialect rules",
+                _ => "model; check it before running",
+            };
+            output.push_str(&self.style_text(&format!("   {source}"), Role::Meta));
             if i < suggestions.len() - 1 {
                 output.push('\n');
             }
@@ -345,28 +1894,42 @@ impl OutputFormatter {
     }
 
     pub fn format_error(&self, message: &str) -> String {
-        format!("{} {}", self.style_text("Error:", Color::Red), message)
+        format!("{} {}", self.style_text("Error:", Role::Error), message)
     }
 
     pub fn format_success(&self, message: &str) -> String {
-        format!("{} {}", self.style_text("✓", Color::Green), message)
+        let label = if self.plain { "Done:" } else { "✓" };
+        format!("{} {}", self.style_text(label, Role::Success), message)
     }
 
     pub fn format_warning(&self, message: &str) -> String {
-        format!("{} {}", self.style_text("⚠", Color::Yellow), message)
+        let label = if self.plain { "Warning:" } else { "⚠" };
+        format!("{} {}", self.style_text(label, Role::Warning), message)
     }
 
     pub fn format_info(&self, message: &str) -> String {
-        format!("{} {}", self.style_text("ℹ", Color::Blue), message)
+        let label = if self.plain { "Note:" } else { "ℹ" };
+        format!("{} {}", self.style_text(label, Role::Info), message)
     }
 
-    fn style_text(&self, text: &str, color: Color) -> String {
+    fn style_text(&self, text: &str, role: Role) -> String {
         if self.use_colors {
-            style(text).fg(color).to_string()
+            self.theme.paint(role, text)
         } else {
             text.to_string()
         }
     }
+
+    /// Styles each line on its own and indents continuation lines of a multi-line command
+    fn style_lines(&self, text: &str, role: Option<Role>, indent: &str, line_end: &str) -> String {
+        text.lines()
+            .map(|line| match role {
+                Some(role) => self.style_text(line, role),
+                None => line.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join(&format!("{line_end}{indent}"))
+    }
 }
 
 impl Default for OutputFormatter {
@@ -0,0 +1,70 @@
+use std::io::{self, IsTerminal, Read};
+
+/// Header marking commandy output so a downstream commandy can recognize it
+pub const PIPE_HEADER: &str = "# commandy:";
+
+/// Input received on stdin when commandy is used in a pipeline
+#[derive(Debug, Clone)]
+pub enum PipedInput {
+    /// A command previously emitted by commandy, with the prompt that produced it
+    Command { prompt: String, command: String },
+    /// Arbitrary text
+    Text(String),
+}
+
+impl PipedInput {
+    /// Reads stdin when it is not a terminal
+    pub fn read_stdin() -> Option<Self> {
+        let mut stdin = io::stdin();
+        if stdin.is_terminal() {
+            return None;
+        }
+
+        let mut content = String::new();
+        stdin.read_to_string(&mut content).ok()?;
+        Self::parse(&content)
+    }
+
+    /// Reads a block of text pasted at the terminal, up to Ctrl-D
+    ///
+    /// Nothing to quote or save first, so an error trace or config snippet
+    /// goes in exactly as copied.
+    pub fn read_paste() -> io::Result<String> {
+        eprintln!("Paste the text, then press Ctrl-D on an empty line:");
+        let mut content = String::new();
+        io::stdin().read_to_string(&mut content)?;
+        Ok(content)
+    }
+
+    pub fn parse(content: &str) -> Option<Self> {
+        if content.trim().is_empty() {
+            return None;
+        }
+
+        let mut lines = content.lines();
+        let first = lines.next().unwrap_or("");
+
+        if let Some(prompt) = first.strip_prefix(PIPE_HEADER) {
+            // Everything after leading comments is the command, which may span lines
+            let command = lines
+                .skip_while(|line| line.trim().is_empty() || line.trim().starts_with('#'))
+                .collect::<Vec<_>>()
+                .join("\n");
+            let command = command.trim();
+            if command.is_empty() {
+                return None;
+            }
+            return Some(Self::Command {
+                prompt: prompt.trim().to_string(),
+                command: command.to_string(),
+            });
+        }
+
+        Some(Self::Text(content.to_string()))
+    }
+
+    /// Formats a command so a downstream commandy can refine it; also valid shell
+    pub fn format(prompt: &str, command: &str) -> String {
+        format!("{PIPE_HEADER} {prompt}\n{command}")
+    }
+}
@@ -0,0 +1,79 @@
+use crate::context::SessionStep;
+
+/// A step of a runbook: what was asked, what ran, and how it went
+pub struct RunbookStep {
+    pub step: SessionStep,
+    pub explanation: Option<String>,
+}
+
+/// Ops documentation written from the commands run in a shell session
+pub struct Runbook {
+    pub session: String,
+    pub generated_at: String,
+    pub steps: Vec<RunbookStep>,
+}
+
+impl Runbook {
+    /// A markdown document with a section per step
+    pub fn to_markdown(&self) -> String {
+        let mut doc = format!(
+            "# Runbook\n\nSession `{}`, exported {}.\n",
+            self.session, self.generated_at
+        );
+
+        for (i, entry) in self.steps.iter().enumerate() {
+            let step = &entry.step;
+            doc.push_str(&format!("\n## {}. {}\n\n", i + 1, step.prompt));
+            if let Some(explanation) = &entry.explanation {
+                doc.push_str(explanation.trim());
+                doc.push_str("\n\n");
+            }
+            doc.push_str(&format!("```sh\n{}\n```\n\n", step.command.trim_end()));
+            doc.push_str(&format!(
+                "Ran at {}: {}\n",
+                step.timestamp.format("%Y-%m-%d %H:%M:%S UTC"),
+                Self::outcome(step)
+            ));
+        }
+        doc
+    }
+
+    /// A shell script that repeats the steps that succeeded
+    ///
+    /// Failed steps are kept as comments, since `set -e` would stop the script there.
+    pub fn to_script(&self) -> String {
+        let mut script = format!(
+            "#!/bin/sh\n# Runbook from commandy session {}, exported {}\n\
+             # Review every command before running this script.\nset -e\n",
+            self.session, self.generated_at
+        );
+
+        for (i, entry) in self.steps.iter().enumerate() {
+            let step = &entry.step;
+            script.push_str(&format!("\n# {}. {}\n", i + 1, step.prompt));
+            if let Some(explanation) = &entry.explanation {
+                for line in explanation.lines().filter(|line| !line.trim().is_empty()) {
+                    script.push_str(&format!("#    {}\n", line.trim()));
+                }
+            }
+            if step.success {
+                script.push_str(step.command.trim_end());
+                script.push('\n');
+            } else {
+                script.push_str(&format!("#    {}:\n", Self::outcome(step)));
+                for line in step.command.lines() {
+                    script.push_str(&format!("# {line}\n"));
+                }
+            }
+        }
+        script
+    }
+
+    fn outcome(step: &SessionStep) -> String {
+        match (step.success, step.exit_code) {
+            (true, _) => "exit code 0".to_string(),
+            (false, Some(code)) => format!("failed with exit code {code}"),
+            (false, None) => "failed without an exit code".to_string(),
+        }
+    }
+}
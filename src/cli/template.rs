@@ -0,0 +1,86 @@
+use anyhow::{anyhow, bail, Result};
+use regex::{Captures, Regex};
+use std::collections::{BTreeSet, HashMap};
+
+/// Expands `[templates]` entries like "build and push the ${env} image" into prompts
+pub struct QueryTemplate;
+
+impl QueryTemplate {
+    /// Parses `key=value` arguments from the command line
+    pub fn parse_vars(args: &[String]) -> Result<HashMap<String, String>> {
+        args.iter()
+            .map(|arg| {
+                let (key, value) = arg
+                    .split_once('=')
+                    .ok_or_else(|| anyhow!("Expected key=value, got '{arg}'"))?;
+                if key.is_empty() {
+                    bail!("Missing variable name in '{arg}'");
+                }
+                Ok((key.to_string(), value.to_string()))
+            })
+            .collect()
+    }
+
+    /// Substitutes `${name}` and `${name:-default}` in `template`
+    ///
+    /// Fails listing every variable that has no value, and any value given for a
+    /// variable the template doesn't use, since that is usually a typo.
+    pub fn expand(template: &str, vars: &HashMap<String, String>) -> Result<String> {
+        let placeholder =
+            Regex::new(r"\$\{([A-Za-z_][A-Za-z0-9_]*)(?::-([^}]*))?\}").expect("valid regex");
+
+        let used: BTreeSet<&str> = placeholder
+            .captures_iter(template)
+            .map(|c| c.get(1).map_or("", |m| m.as_str()))
+            .collect();
+        let mut unknown: Vec<&str> = vars
+            .keys()
+            .map(String::as_str)
+            .filter(|k| !used.contains(k))
+            .collect();
+        if !unknown.is_empty() {
+            unknown.sort_unstable();
+            bail!(
+                "Unknown variable(s): {} (the template uses: {})",
+                unknown.join(", "),
+                Self::names(&used)
+            );
+        }
+
+        let mut missing = BTreeSet::new();
+        let expanded = placeholder.replace_all(template, |c: &Captures| {
+            let name = &c[1];
+            match (vars.get(name), c.get(2)) {
+                (Some(value), _) => value.clone(),
+                (None, Some(default)) => default.as_str().to_string(),
+                (None, None) => {
+                    missing.insert(name.to_string());
+                    String::new()
+                }
+            }
+        });
+        if !missing.is_empty() {
+            let args: Vec<String> = missing.iter().map(|name| format!("{name}=...")).collect();
+            bail!("Missing value(s), pass {}", args.join(" "));
+        }
+        Ok(expanded.into_owned())
+    }
+
+    /// Variables in a template, for listing
+    pub fn variables(template: &str) -> Vec<String> {
+        let placeholder = Regex::new(r"\$\{([A-Za-z_][A-Za-z0-9_]*)").expect("valid regex");
+        let names: BTreeSet<&str> = placeholder
+            .captures_iter(template)
+            .filter_map(|c| c.get(1).map(|m| m.as_str()))
+            .collect();
+        names.into_iter().map(str::to_string).collect()
+    }
+
+    fn names(used: &BTreeSet<&str>) -> String {
+        if used.is_empty() {
+            "none".to_string()
+        } else {
+            used.iter().copied().collect::<Vec<_>>().join(", ")
+        }
+    }
+}
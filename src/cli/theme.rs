@@ -0,0 +1,388 @@
+use anyhow::{anyhow, bail, Result};
+use std::collections::HashMap;
+use tracing::warn;
+
+use crate::config::ThemeConfig;
+
+/// What a piece of output is, so themes can color it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Role {
+    Command,
+    /// List numbers and input labels
+    Number,
+    Explanation,
+    /// Status lines and diff headers
+    Muted,
+    /// Confidence, run counts, and other details
+    Meta,
+    /// Notes like "[offline heuristic]"
+    Warning,
+    /// Markers on commands that need root or could do damage
+    Risk,
+    Error,
+    Success,
+    Info,
+    DiffAdded,
+    DiffRemoved,
+    DiffHunk,
+    ConfidenceHigh,
+    ConfidenceMedium,
+    ConfidenceLow,
+}
+
+impl Role {
+    pub const ALL: [Role; 16] = [
+        Role::Command,
+        Role::Number,
+        Role::Explanation,
+        Role::Muted,
+        Role::Meta,
+        Role::Warning,
+        Role::Risk,
+        Role::Error,
+        Role::Success,
+        Role::Info,
+        Role::DiffAdded,
+        Role::DiffRemoved,
+        Role::DiffHunk,
+        Role::ConfidenceHigh,
+        Role::ConfidenceMedium,
+        Role::ConfidenceLow,
+    ];
+
+    /// Key under `[theme.colors]`
+    pub fn key(self) -> &'static str {
+        match self {
+            Role::Command => "command",
+            Role::Number => "number",
+            Role::Explanation => "explanation",
+            Role::Muted => "muted",
+            Role::Meta => "meta",
+            Role::Warning => "warning",
+            Role::Risk => "risk",
+            Role::Error => "error",
+            Role::Success => "success",
+            Role::Info => "info",
+            Role::DiffAdded => "diff_added",
+            Role::DiffRemoved => "diff_removed",
+            Role::DiffHunk => "diff_hunk",
+            Role::ConfidenceHigh => "confidence_high",
+            Role::ConfidenceMedium => "confidence_medium",
+            Role::ConfidenceLow => "confidence_low",
+        }
+    }
+}
+
+/// How many colors the terminal can show
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ColorDepth {
+    Ansi16,
+    Ansi256,
+    TrueColor,
+}
+
+impl ColorDepth {
+    /// Reads `COLORTERM` and `TERM` the way most terminal programs do
+    pub fn detect() -> Self {
+        let colorterm = std::env::var("COLORTERM").unwrap_or_default();
+        let term = std::env::var("TERM").unwrap_or_default();
+        if matches!(colorterm.as_str(), "truecolor" | "24bit") {
+            Self::TrueColor
+        } else if term.contains("256color") || !colorterm.is_empty() {
+            Self::Ansi256
+        } else {
+            Self::Ansi16
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Ansi16 => "16 colors",
+            Self::Ansi256 => "256 colors",
+            Self::TrueColor => "truecolor",
+        }
+    }
+}
+
+/// A color as written in a theme
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThemeColor {
+    /// One of the 16 terminal colors; 8-15 are the bright variants
+    Ansi(u8),
+    /// An index into the 256-color palette
+    Fixed(u8),
+    Rgb(u8, u8, u8),
+}
+
+const ANSI_NAMES: [&str; 8] = [
+    "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+];
+
+/// The xterm defaults, used to find the nearest of the 16 colors
+const ANSI_RGB: [(u8, u8, u8); 16] = [
+    (0, 0, 0),
+    (205, 0, 0),
+    (0, 205, 0),
+    (205, 205, 0),
+    (0, 0, 238),
+    (205, 0, 205),
+    (0, 205, 205),
+    (229, 229, 229),
+    (127, 127, 127),
+    (255, 0, 0),
+    (0, 255, 0),
+    (255, 255, 0),
+    (92, 92, 255),
+    (255, 0, 255),
+    (0, 255, 255),
+    (255, 255, 255),
+];
+
+impl ThemeColor {
+    /// Parses `green`, `bright-green`, `#50fa7b`, or a palette index like `208`
+    pub fn parse(value: &str) -> Result<Self> {
+        let value = value.trim().to_lowercase();
+        if let Some(hex) = value.strip_prefix('#') {
+            if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+                bail!("'#{hex}' is not a #rrggbb color");
+            }
+            let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).unwrap_or(0);
+            return Ok(Self::Rgb(channel(0), channel(2), channel(4)));
+        }
+        if let Ok(index) = value.parse::<u8>() {
+            return Ok(Self::Fixed(index));
+        }
+        let (bright, name) = match value.strip_prefix("bright-") {
+            Some(name) => (8, name),
+            None => (0, value.as_str()),
+        };
+        ANSI_NAMES
+            .iter()
+            .position(|n| *n == name)
+            .map(|i| Self::Ansi(i as u8 + bright))
+            .ok_or_else(|| anyhow!("unknown color '{value}'"))
+    }
+
+    /// Closest color the terminal can show
+    fn fit(self, depth: ColorDepth) -> Self {
+        match (self, depth) {
+            (Self::Ansi(_), _) | (_, ColorDepth::TrueColor) => self,
+            (Self::Fixed(_), ColorDepth::Ansi256) => self,
+            (Self::Rgb(r, g, b), ColorDepth::Ansi256) => Self::Fixed(Self::to_256(r, g, b)),
+            (Self::Fixed(index), ColorDepth::Ansi16) => {
+                let (r, g, b) = Self::fixed_rgb(index);
+                Self::Ansi(Self::nearest_ansi(r, g, b))
+            }
+            (Self::Rgb(r, g, b), ColorDepth::Ansi16) => Self::Ansi(Self::nearest_ansi(r, g, b)),
+        }
+    }
+
+    fn escape(self) -> String {
+        match self {
+            Self::Ansi(n) if n < 8 => format!("\x1b[{}m", 30 + n),
+            Self::Ansi(n) => format!("\x1b[{}m", 90 + (n - 8)),
+            Self::Fixed(n) => format!("\x1b[38;5;{n}m"),
+            Self::Rgb(r, g, b) => format!("\x1b[38;2;{r};{g};{b}m"),
+        }
+    }
+
+    /// Nearest entry of the 6x6x6 cube or the grayscale ramp
+    fn to_256(r: u8, g: u8, b: u8) -> u8 {
+        let level = |c: u8| -> u8 {
+            match c {
+                0..=47 => 0,
+                48..=114 => 1,
+                _ => ((c - 35) / 40).min(5),
+            }
+        };
+        let cube = 16 + 36 * level(r) + 6 * level(g) + level(b);
+        let average = (r as u32 + g as u32 + b as u32) / 3;
+        let gray = if average > 238 {
+            255
+        } else {
+            232 + (average.saturating_sub(3) / 10).min(23) as u8
+        };
+        let distance = |index: u8| Self::distance(Self::fixed_rgb(index), (r, g, b));
+        if distance(gray) < distance(cube) {
+            gray
+        } else {
+            cube
+        }
+    }
+
+    fn fixed_rgb(index: u8) -> (u8, u8, u8) {
+        match index {
+            0..=15 => ANSI_RGB[index as usize],
+            16..=231 => {
+                let i = index - 16;
+                let step = |v: u8| if v == 0 { 0 } else { 55 + v * 40 };
+                (step(i / 36), step((i / 6) % 6), step(i % 6))
+            }
+            _ => {
+                let v = 8 + (index - 232) * 10;
+                (v, v, v)
+            }
+        }
+    }
+
+    fn nearest_ansi(r: u8, g: u8, b: u8) -> u8 {
+        (0..16u8)
+            .min_by_key(|&i| Self::distance(ANSI_RGB[i as usize], (r, g, b)))
+            .unwrap_or(7)
+    }
+
+    fn distance(a: (u8, u8, u8), b: (u8, u8, u8)) -> u32 {
+        let d = |x: u8, y: u8| (x as i32 - y as i32).pow(2) as u32;
+        d(a.0, b.0) + d(a.1, b.1) + d(a.2, b.2)
+    }
+}
+
+/// Colors for each [`Role`], fitted to the terminal
+#[derive(Debug, Clone)]
+pub struct Theme {
+    name: String,
+    colors: HashMap<Role, ThemeColor>,
+    depth: ColorDepth,
+}
+
+impl Theme {
+    pub const BUILT_IN: [&'static str; 4] = ["default", "light", "high-contrast", "dracula"];
+
+    /// The configured theme with `[theme.colors]` overrides; mistakes are logged and skipped
+    pub fn from_config(config: &ThemeConfig) -> Self {
+        let mut theme = Self::built_in(&config.name).unwrap_or_else(|| {
+            warn!(theme = config.name, "Unknown theme, using the default");
+            Self::built_in("default").expect("default theme exists")
+        });
+
+        for (key, value) in &config.colors {
+            let Some(role) = Role::ALL.into_iter().find(|r| r.key() == key) else {
+                warn!(key, "Unknown [theme.colors] entry");
+                continue;
+            };
+            match ThemeColor::parse(value) {
+                Ok(color) => {
+                    theme.colors.insert(role, color);
+                }
+                Err(e) => warn!(key, "Ignoring [theme.colors] entry: {e}"),
+            }
+        }
+        theme
+    }
+
+    pub fn built_in(name: &str) -> Option<Self> {
+        let spec: [(&str, &str); 16] = match name {
+            "default" => [
+                ("command", "green"),
+                ("number", "cyan"),
+                ("explanation", "white"),
+                ("muted", "white"),
+                ("meta", "blue"),
+                ("warning", "yellow"),
+                ("risk", "yellow"),
+                ("error", "red"),
+                ("success", "green"),
+                ("info", "blue"),
+                ("diff_added", "green"),
+                ("diff_removed", "red"),
+                ("diff_hunk", "cyan"),
+                ("confidence_high", "green"),
+                ("confidence_medium", "yellow"),
+                ("confidence_low", "red"),
+            ],
+            // Nothing light enough to vanish on a white background
+            "light" => [
+                ("command", "#1a7f37"),
+                ("number", "#0550ae"),
+                ("explanation", "#57606a"),
+                ("muted", "#6e7781"),
+                ("meta", "#0969da"),
+                ("warning", "#9a6700"),
+                ("risk", "#bc4c00"),
+                ("error", "#cf222e"),
+                ("success", "#1a7f37"),
+                ("info", "#0969da"),
+                ("diff_added", "#1a7f37"),
+                ("diff_removed", "#cf222e"),
+                ("diff_hunk", "#8250df"),
+                ("confidence_high", "#1a7f37"),
+                ("confidence_medium", "#9a6700"),
+                ("confidence_low", "#cf222e"),
+            ],
+            "high-contrast" => [
+                ("command", "bright-green"),
+                ("number", "bright-cyan"),
+                ("explanation", "bright-white"),
+                ("muted", "bright-white"),
+                ("meta", "bright-cyan"),
+                ("warning", "bright-yellow"),
+                ("risk", "bright-magenta"),
+                ("error", "bright-red"),
+                ("success", "bright-green"),
+                ("info", "bright-cyan"),
+                ("diff_added", "bright-green"),
+                ("diff_removed", "bright-red"),
+                ("diff_hunk", "bright-cyan"),
+                ("confidence_high", "bright-green"),
+                ("confidence_medium", "bright-yellow"),
+                ("confidence_low", "bright-red"),
+            ],
+            "dracula" => [
+                ("command", "#50fa7b"),
+                ("number", "#8be9fd"),
+                ("explanation", "#f8f8f2"),
+                ("muted", "#6272a4"),
+                ("meta", "#bd93f9"),
+                ("warning", "#f1fa8c"),
+                ("risk", "#ffb86c"),
+                ("error", "#ff5555"),
+                ("success", "#50fa7b"),
+                ("info", "#8be9fd"),
+                ("diff_added", "#50fa7b"),
+                ("diff_removed", "#ff5555"),
+                ("diff_hunk", "#bd93f9"),
+                ("confidence_high", "#50fa7b"),
+                ("confidence_medium", "#f1fa8c"),
+                ("confidence_low", "#ff5555"),
+            ],
+            _ => return None,
+        };
+
+        let colors = spec
+            .into_iter()
+            .filter_map(|(key, value)| {
+                let role = Role::ALL.into_iter().find(|r| r.key() == key)?;
+                Some((role, ThemeColor::parse(value).ok()?))
+            })
+            .collect();
+        Some(Self {
+            name: name.to_string(),
+            colors,
+            depth: ColorDepth::detect(),
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn depth(&self) -> ColorDepth {
+        self.depth
+    }
+
+    /// Wraps `text` in the role's color; plain text when stdout shouldn't get colors
+    pub fn paint(&self, role: Role, text: &str) -> String {
+        match self.colors.get(&role) {
+            Some(color) if console::colors_enabled() => {
+                format!("{}{text}\x1b[0m", color.fit(self.depth).escape())
+            }
+            _ => text.to_string(),
+        }
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::built_in("default").expect("default theme exists")
+    }
+}
@@ -0,0 +1,296 @@
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::terminal::{
+    self, disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
+};
+use crossterm::{cursor, execute, queue};
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+use crate::cli::{Role, Suggestion, Theme};
+
+const QUERY_ROW: u16 = 2;
+const STATUS_ROW: u16 = 4;
+const RESULTS_ROW: u16 = 5;
+
+/// What the suggestion area is showing
+#[derive(Debug, Clone, PartialEq)]
+pub enum WatchStatus {
+    /// Too short to generate for
+    Idle,
+    /// Typed, but still inside the debounce window
+    Waiting,
+    Generating,
+    /// Suggestions for `query`, which may be older than what is typed now
+    Ready {
+        query: String,
+    },
+    Failed(String),
+}
+
+/// How the user left watch mode
+pub enum WatchExit {
+    /// Enter: the query and its suggestions, with the chosen one first
+    Chosen {
+        query: String,
+        suggestions: Vec<Suggestion>,
+    },
+    Quit,
+}
+
+/// Edit to the query or selection from one keypress
+pub enum KeyOutcome {
+    QueryChanged,
+    Redraw,
+    Exit(WatchExit),
+    Ignored,
+}
+
+/// Full-screen query editor whose suggestions refresh as the user types
+///
+/// Only the query line or the suggestion area is redrawn, whichever changed.
+/// Dropping the screen restores the terminal.
+pub struct WatchScreen {
+    query: String,
+    suggestions: Vec<Suggestion>,
+    selected: usize,
+    status: WatchStatus,
+    /// `None` when colors are off
+    theme: Option<Theme>,
+    stdout: io::Stdout,
+}
+
+impl WatchScreen {
+    pub fn enter(initial_query: &str, theme: Option<Theme>) -> io::Result<Self> {
+        enable_raw_mode()?;
+        let mut stdout = io::stdout();
+        execute!(
+            stdout,
+            EnterAlternateScreen,
+            terminal::Clear(terminal::ClearType::All)
+        )?;
+
+        let mut screen = Self {
+            query: initial_query.to_string(),
+            suggestions: Vec::new(),
+            selected: 0,
+            status: WatchStatus::Idle,
+            theme,
+            stdout,
+        };
+        screen.draw_header()?;
+        screen.draw_results()?;
+        screen.draw_query()?;
+        Ok(screen)
+    }
+
+    pub fn query(&self) -> &str {
+        &self.query
+    }
+
+    pub fn set_status(&mut self, status: WatchStatus) -> io::Result<()> {
+        if self.status != status {
+            self.status = status;
+            self.draw_results()?;
+            self.draw_query()?;
+        }
+        Ok(())
+    }
+
+    pub fn show_suggestions(
+        &mut self,
+        query: &str,
+        suggestions: Vec<Suggestion>,
+    ) -> io::Result<()> {
+        self.suggestions = suggestions;
+        self.selected = 0;
+        self.status = WatchStatus::Ready {
+            query: query.to_string(),
+        };
+        self.draw_results()?;
+        self.draw_query()
+    }
+
+    /// Applies a keypress, redrawing whatever it changed
+    pub fn handle_key(&mut self, key: KeyEvent) -> io::Result<KeyOutcome> {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let outcome = match key.code {
+            KeyCode::Char('c') if ctrl => KeyOutcome::Exit(WatchExit::Quit),
+            KeyCode::Char('u') if ctrl => {
+                self.query.clear();
+                KeyOutcome::QueryChanged
+            }
+            KeyCode::Char('w') if ctrl => {
+                let trimmed = self.query.trim_end().len();
+                let word_start = self.query[..trimmed].rfind(' ').map_or(0, |i| i + 1);
+                self.query.truncate(word_start);
+                KeyOutcome::QueryChanged
+            }
+            KeyCode::Char(c) => {
+                self.query.push(c);
+                KeyOutcome::QueryChanged
+            }
+            KeyCode::Backspace => match self.query.pop() {
+                Some(_) => KeyOutcome::QueryChanged,
+                None => KeyOutcome::Ignored,
+            },
+            KeyCode::Up if self.selected > 0 => {
+                self.selected -= 1;
+                KeyOutcome::Redraw
+            }
+            KeyCode::Down if self.selected + 1 < self.suggestions.len() => {
+                self.selected += 1;
+                KeyOutcome::Redraw
+            }
+            KeyCode::Enter => KeyOutcome::Exit(self.chosen()),
+            KeyCode::Esc => KeyOutcome::Exit(WatchExit::Quit),
+            _ => KeyOutcome::Ignored,
+        };
+
+        match outcome {
+            KeyOutcome::QueryChanged => self.draw_query()?,
+            KeyOutcome::Redraw => {
+                self.draw_results()?;
+                self.draw_query()?;
+            }
+            _ => {}
+        }
+        Ok(outcome)
+    }
+
+    /// Suggestions only count when they were generated for the query as typed now
+    fn chosen(&mut self) -> WatchExit {
+        let query = self.query.trim().to_string();
+        let current = matches!(&self.status, WatchStatus::Ready { query: q } if q.trim() == query);
+        let mut suggestions = if current {
+            std::mem::take(&mut self.suggestions)
+        } else {
+            Vec::new()
+        };
+        if self.selected < suggestions.len() {
+            let chosen = suggestions.remove(self.selected);
+            suggestions.insert(0, chosen);
+        }
+        WatchExit::Chosen { query, suggestions }
+    }
+
+    fn draw_header(&mut self) -> io::Result<()> {
+        queue!(self.stdout, cursor::MoveTo(0, 0))?;
+        write!(
+            self.stdout,
+            "Type to refine (↑/↓ select, Enter=choose, Esc=quit)"
+        )?;
+        self.stdout.flush()
+    }
+
+    fn draw_query(&mut self) -> io::Result<()> {
+        let prompt = self.paint("> ", Role::Number);
+        queue!(
+            self.stdout,
+            cursor::MoveTo(0, QUERY_ROW),
+            terminal::Clear(terminal::ClearType::CurrentLine)
+        )?;
+        write!(self.stdout, "{prompt}{}", self.query)?;
+        self.stdout.flush()
+    }
+
+    fn draw_results(&mut self) -> io::Result<()> {
+        let status = match &self.status {
+            WatchStatus::Idle => self.paint("Keep typing…", Role::Muted),
+            WatchStatus::Waiting => self.paint("…", Role::Muted),
+            WatchStatus::Generating => self.paint("Generating…", Role::Warning),
+            WatchStatus::Ready { query } if query.trim() != self.query.trim() => {
+                self.paint(&format!("Suggestions for \"{query}\""), Role::Muted)
+            }
+            WatchStatus::Ready { .. } if self.suggestions.is_empty() => {
+                self.paint("No suggestions", Role::Muted)
+            }
+            WatchStatus::Ready { .. } => String::new(),
+            WatchStatus::Failed(error) => self.paint(error, Role::Error),
+        };
+
+        queue!(
+            self.stdout,
+            cursor::MoveTo(0, STATUS_ROW),
+            terminal::Clear(terminal::ClearType::FromCursorDown)
+        )?;
+        write!(self.stdout, "{status}")?;
+        for (i, suggestion) in self.suggestions.iter().enumerate() {
+            queue!(self.stdout, cursor::MoveTo(0, RESULTS_ROW + i as u16))?;
+            // One row per suggestion, so multi-line commands show their first line
+            let mut lines = suggestion.command.lines();
+            let first = lines.next().unwrap_or("");
+            let more = match lines.count() {
+                0 => String::new(),
+                n => self.paint(&format!(" (+{n} lines)"), Role::Muted),
+            };
+            if i == self.selected {
+                let line = self.paint(first, Role::Command);
+                write!(self.stdout, "▶ {line}{more}")?;
+            } else {
+                write!(self.stdout, "  {first}{more}")?;
+            }
+        }
+        self.stdout.flush()
+    }
+
+    fn paint(&self, text: &str, role: Role) -> String {
+        match &self.theme {
+            Some(theme) => theme.paint(role, text),
+            None => text.to_string(),
+        }
+    }
+}
+
+impl Drop for WatchScreen {
+    fn drop(&mut self) {
+        let _ = disable_raw_mode();
+        let _ = execute!(self.stdout, LeaveAlternateScreen);
+    }
+}
+
+/// Reads keypresses on a blocking thread so they can be awaited next to a generation
+pub struct KeyStream {
+    stop: Arc<AtomicBool>,
+    receiver: mpsc::UnboundedReceiver<KeyEvent>,
+}
+
+impl KeyStream {
+    pub fn start() -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let thread_stop = stop.clone();
+        std::thread::spawn(move || {
+            while !thread_stop.load(Ordering::Relaxed) {
+                if !event::poll(Duration::from_millis(50)).unwrap_or(false) {
+                    continue;
+                }
+                let Ok(Event::Key(key)) = event::read() else {
+                    continue;
+                };
+                if key.kind == KeyEventKind::Press && sender.send(key).is_err() {
+                    break;
+                }
+            }
+        });
+        Self { stop, receiver }
+    }
+
+    /// Next keypress; pending forever once the reader has stopped
+    pub async fn next(&mut self) -> KeyEvent {
+        match self.receiver.recv().await {
+            Some(key) => key,
+            None => std::future::pending().await,
+        }
+    }
+}
+
+impl Drop for KeyStream {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        // Let the reader notice before the terminal is handed back
+        std::thread::sleep(Duration::from_millis(60));
+    }
+}
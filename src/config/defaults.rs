@@ -4,29 +4,268 @@ pub struct DefaultConfig;
 
 impl DefaultConfig {
     pub fn create_default_config_file() -> String {
-        r#"[general]
+        r##"[general]
 max_context_size_kb = 50
 recent_commands_limit = 100
 learning_enabled = true
+# Learned patterns beyond the newest 200 are summarized by the model into short
+# rules about your habits, kept in the prompt instead; 0 keeps every pattern
+summarize_learned_after = 200
+# profile = "prod"
 
 [model]
 model_path = "ggml-org/gemma-3-270m-GGUF"
+# Small model that categorizes requests the keyword rules are unsure of
+# fast_model_path = "ggml-org/gemma-3-270m-GGUF"
 max_tokens = 200
 temperature = 0.1
+# Fix the sampling seed for reproducible suggestions (see `commandy replay`)
+# seed = 42
+# Lower these on low-RAM machines (cache types: f16, q8_0, q4_0)
+# context_size = 4096
+# cache_type_k = "q8_0"
+# cache_type_v = "q8_0"
+# Reuse the evaluated start of suggestion prompts between runs
+# (~/.commandy/prompt-cache, cleared by `commandy clear --cache`)
+prompt_cache = true
+# Send requests to a running llama-server instead of starting llama.cpp
+# each time; its prefix cache keeps the system prompt evaluated
+# server_url = "http://127.0.0.1:8080"
+# Run the model on "cpu", "metal", or a GPU such as "cuda:0", "vulkan:0", or
+# "rocm:0"; unset lets llama.cpp choose (`commandy doctor` lists the devices)
+# device = "cuda:0"
+# llama.cpp build in ~/.commandy/bin/<flavor> (cpu, avx2, cuda, metal, vulkan);
+# set by `commandy update --binary --flavor cuda` and by `commandy init`
+# flavor = "cuda"
+# Don't start llama.cpp when the model (weights and KV cache, read from its
+# GGUF header) won't fit in free memory and swap; ignored on CUDA, Vulkan, and ROCm
+memory_guard = true
+#
+# Token limits per kind of request; by default suggestions and fixes use
+# max_tokens, explanations and translations twice that, and scripts four times
+# [model.token_budgets]
+# suggest = 120
+# explain = 400
+# script = 800
+# fix = 200
+# translate = 400
+#
+# Text that ends generation, per prompt template (suggest, refine, fix,
+# explain, translate, summarize, classify); a template listed here replaces its built-in stops
+# [model.stop_sequences]
+# suggest = ["\nRequest:", "Explanation:", "```"]
+#
+# [model.per_model."ggml-org/gemma-3-4b-it-GGUF"]
+# context_size = 2048
+# cache_type_k = "q4_0"
 
 [cache]
+# false never opens the cache database, as if every run had --no-cache
+enabled = true
 max_cache_entries = 1000
 cache_ttl_hours = 24
+explanation_ttl_days = 90
+# `commandy cache warm` generates the warm_top most frequent requests from your
+# history plus warm_queries, so they are answered from the cache straight away
+warm_queries = []
+warm_top = 20
+# Warm the cache after `commandy update --binary` or `commandy model use`
+warm_after_update = false
 
 [output]
 show_explanations = true
 use_colors = true
+# Suggestions shown without -n; -n asks for up to suggestion_limit
 max_suggestions = 3
+suggestion_limit = 20
+# The picker shows page_size suggestions at a time and generates the next page
+# only when you scroll past the last one; 0 generates them all up front
+page_size = 3
+# Preview in-place edits (sed -i, >, tee) as a diff before running them
+confirm_edits = false
+# Download the script of a `curl ... | sh` command, summarize it, and show its
+# first lines before offering to run the saved copy
+unpack_scripts = false
+unpack_preview_lines = 20
+# Ask again before rerunning a destructive command within this many seconds of
+# its last run; 0 turns this off
+rerun_cooldown_secs = 60
+# After a chosen command finishes, press s to have the model summarize what it
+# printed ("3 containers restarted, 1 failed: web-2"). Commands then write to a
+# pipe rather than the terminal, so some leave out colors; pagers, editors, and
+# full-screen programs still get the terminal. The first and last
+# summary_capture_kb / 2 kilobytes of output are kept for the summary
+summarize_output = false
+summary_capture_kb = 8
+# After a chosen command finishes, ask the model what you are likely to run
+# next, from its exit code and output (`git rebase origin/main` after
+# `git fetch`), and pick one by number. Costs a generation per command run
+suggest_next = false
+# When a chosen command fails, look for fixes from what it wrote to standard
+# error and show each as a word diff against the command; Enter runs the first.
+# Standard error goes through a pipe, so some commands drop progress bars
+triage_failures = true
 
 [privacy]
 collect_usage_stats = false
 share_anonymous_data = false
-"#
+# Tell the model the names (never the values) of set variables that bear on
+# the query, such as AWS_PROFILE or KUBECONFIG
+env_var_names = true
+# Tell the model how many files of each type are under the current directory,
+# such as "312 .py files, 4 Dockerfiles"; file names are never sent
+file_type_stats = true
+# Tell the model your shell aliases (secrets masked) and function names, as
+# saved by `commandy hook`, so it can use them in suggestions
+shell_definitions = true
+
+[postprocess]
+# Built-ins: normalize_whitespace, expand_tilde (outside quotes), python3
+# (python and pip become python3 and pip3 when only those exist), runtimes
+# (pip into the active or project virtualenv, sudo with a version manager's
+# node), color_auto, progress (progress output for rsync, dd, cp, and tar), wsl
+# (Windows paths, clip.exe, and wslview under WSL; does nothing elsewhere).
+# Only the first two are on by default; the others change what a command does.
+transforms = ["normalize_whitespace", "expand_tilde"]
+# Quote names from the query that a suggestion left bare, quote find -name
+# globs, and close a quote left open at the end of a command
+fix_quoting = true
+# Turn $DIR into "${DIR:?}" in rm, mv, find -delete and other destructive
+# commands, so an unset variable stops the command instead of reaching /
+guard_expansions = true
+#
+# [[postprocess.replace]]
+# pattern = "^docker-compose "
+# replacement = "docker compose "
+#
+# [[postprocess.env_prefix]]
+# path = "~/work/infra"
+# prefix = "AWS_PROFILE=staging"
+# commands = ["aws", "terraform"]
+
+[plugins]
+# Load executables named commandy-<name> on PATH as plugins; each one found
+# is run on every request, so only turn this on for plugins you trust
+enabled = false
+disabled = []
+timeout_ms = 2000
+# Sandboxed ~/.commandy/plugins/*.wasm (wasm-plugins builds) get no filesystem
+# access unless listed here, which grants read-only access to the current directory
+# wasm_allow_cwd = ["terraform"]
+
+[server]
+# Parallel generations for `commandy serve`; each runs its own llama.cpp
+concurrency = 1
+max_queue = 16
+max_queued_per_client = 4
+# Requests need the token in ~/.commandy/auth-token; set this to serve on an
+# address other machines can reach with --host
+allow_remote = false
+# Start `commandy daemon` with the server so its llama-server keeps the model
+# loaded; false runs llama.cpp for each request
+use_daemon = true
+
+[daemon]
+# llama-server kept running by `commandy daemon start`; point [model] server_url
+# at http://127.0.0.1:<port> to use it. Requests need the token in
+# ~/.commandy/auth-token; allow_remote lets host be reachable from other machines
+host = "127.0.0.1"
+port = 8080
+allow_remote = false
+# Restart llama-server after max_failures health checks in a row fail
+health_interval_secs = 10
+health_timeout_secs = 5
+max_failures = 3
+# Restart it when it holds more memory than this (MB); 0 turns the limit off
+max_memory_mb = 0
+# server_binary = "/usr/local/bin/llama-server"
+
+[log]
+# JSON lines rotated daily in ~/.commandy/logs; see `commandy doctor --logs`
+enabled = true
+# path = "~/.commandy/logs"
+# "debug" also logs full prompts and responses, for `commandy replay`; prompts
+# include your history and attached files
+level = "info"
+max_files = 7
+
+[sudo]
+# Commands that need root (package installs, systemctl, writes under /etc):
+# "prepend" adds sudo, "warn" marks them, "refuse" drops them
+policy = "warn"
+
+[sandbox]
+# Try executed suggestions in a throwaway container first (same as --sandbox)
+enabled = false
+runtime = "docker"
+image = "debian:stable-slim"
+# The current directory is mounted at /work: "read_only", "read_write", or "none"
+mount = "read_only"
+network = false
+timeout_secs = 60
+
+[theme]
+# default, light, high-contrast, or dracula; hex colors are reduced to 256 or 16
+# colors when COLORTERM doesn't advertise truecolor
+name = "default"
+#
+# [theme.colors]
+# command = "#50fa7b"
+# risk = "bright-magenta"
+
+[audit]
+# Append every command commandy runs to ~/.commandy/audit.log, hash-chained so
+# edits show up in `commandy audit verify`
+enabled = true
+
+[auto_execute]
+# Run the top suggestion straight away, without the picker, when it is at least
+# min_confidence sure, classified safe, installed, has no redirections or
+# command substitution, and every part of its pipeline starts with an entry in allow
+enabled = false
+min_confidence = 0.9
+allow = ["ls", "cat", "head", "tail", "grep", "rg", "wc", "du", "df", "free", "ps",
+  "pwd", "whoami", "uptime", "find", "git status", "git log", "git diff", "git show",
+  "kubectl get", "kubectl describe", "kubectl logs", "docker ps", "docker images",
+  "docker logs", "systemctl status"]
+
+[experiment]
+# Try other suggestion instructions on a share of real queries before making
+# them the default; `commandy stats experiments` shows how often each arm's
+# suggestions are used. {os}, {shell}, and {tools} are filled in
+# name = "terse-rules"
+share = 0.5
+# variant = """Reply with shell commands only, one per line, for {shell} on {os}.
+# Use only these programs: {tools}
+# """
+
+[language]
+# Requests in another language, such as "busca archivos de más de 1 GB", are
+# put in English by the model first, since small models write better commands
+# from English; the explanations from --explain come back in the request's language
+translate = true
+explain_in_request_language = true
+
+[profiles]
+# Execution limits per profile, chosen with --profile, $COMMANDY_PROFILE, or
+# `profile = "prod"` under [general]. Each tier is "run", "confirm",
+# "confirm_host" (type the hostname to run), or "display_only"
+# [profiles.prod]
+# privileged = "confirm_host"
+# destructive = "display_only"
+
+[templates]
+# Prompts for `commandy run <name> key=value ...`; ${var:-default} sets a default
+# deploy = "build and push the ${env} image then restart the ${app:-web} deployment"
+
+[keymap]
+# Picker keys; listing an action replaces its built-in keys. Actions: up, down,
+# page_up, page_down, top, bottom, run, output, follow_up, more_like, flags,
+# info, why, filter, quit. Keys are names like "Enter", "PageDown", "Ctrl-n",
+# or characters, with spaces between keys pressed in turn ("g g")
+# down = ["Down", "j", "Ctrl-n"]
+# quit = ["Esc Esc", "q"]
+"##
         .to_string()
     }
 
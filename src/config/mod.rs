@@ -2,4 +2,9 @@ pub mod defaults;
 pub mod settings;
 
 pub use defaults::DefaultConfig;
-pub use settings::Settings;
+pub use settings::{
+    AuditConfig, AutoExecuteConfig, DaemonConfig, EnvPrefixRule, ExperimentConfig, GenerationMode,
+    LanguageConfig, LogConfig, ModelConfig, ModelRuntimeOptions, PluginConfig, PostProcessConfig,
+    ProfileConfig, ReplaceRule, RiskAction, SandboxConfig, SandboxMount, ServerConfig, Settings,
+    SudoConfig, SudoPolicy, ThemeConfig, TokenBudgets,
+};
@@ -1,5 +1,6 @@
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
 use std::path::PathBuf;
 
@@ -10,6 +11,39 @@ pub struct Settings {
     pub cache: CacheConfig,
     pub output: OutputConfig,
     pub privacy: PrivacyConfig,
+    #[serde(default)]
+    pub postprocess: PostProcessConfig,
+    #[serde(default)]
+    pub plugins: PluginConfig,
+    #[serde(default)]
+    pub server: ServerConfig,
+    #[serde(default)]
+    pub daemon: DaemonConfig,
+    #[serde(default)]
+    pub log: LogConfig,
+    #[serde(default)]
+    pub sudo: SudoConfig,
+    #[serde(default)]
+    pub sandbox: SandboxConfig,
+    #[serde(default)]
+    pub theme: ThemeConfig,
+    #[serde(default)]
+    pub audit: AuditConfig,
+    #[serde(default)]
+    pub auto_execute: AutoExecuteConfig,
+    #[serde(default)]
+    pub experiment: ExperimentConfig,
+    #[serde(default)]
+    pub language: LanguageConfig,
+    /// Named prompts with ${variables}, expanded by `commandy run`
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub templates: HashMap<String, String>,
+    /// Limits on running risky commands, selected with `--profile` or `[general] profile`
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub profiles: HashMap<String, ProfileConfig>,
+    /// Picker action to the keys that trigger it, replacing the built-in keys for that action
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub keymap: HashMap<String, Vec<String>>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -17,32 +51,706 @@ pub struct GeneralConfig {
     pub max_context_size_kb: usize,
     pub recent_commands_limit: usize,
     pub learning_enabled: bool,
+    /// Accepted patterns kept word for word; the model summarizes older ones into rules (0 never does)
+    #[serde(default = "GeneralConfig::default_summarize_learned_after")]
+    pub summarize_learned_after: usize,
+    /// Entry of `[profiles]` in effect when `--profile` isn't given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+}
+
+impl GeneralConfig {
+    fn default_summarize_learned_after() -> usize {
+        200
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ModelConfig {
     pub model_path: String,
+    /// Optional small model that categorizes requests the keyword rules are unsure of
+    #[serde(default)]
+    pub fast_model_path: Option<String>,
     pub max_tokens: u32,
     pub temperature: f32,
+    /// Fixed sampling seed; unset picks a new one per run (every seed is logged for replay)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u32>,
+    /// Context size in tokens; unset uses the model's full context
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context_size: Option<u32>,
+    /// KV-cache quantization for keys (e.g. "q8_0", "q4_0")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_type_k: Option<String>,
+    /// KV-cache quantization for values (e.g. "q8_0", "q4_0")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_type_v: Option<String>,
+    /// Runtime overrides keyed by model repo
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub per_model: HashMap<String, ModelRuntimeOptions>,
+    /// Save the evaluated start of suggestion prompts with llama.cpp's --prompt-cache
+    #[serde(default = "ModelConfig::default_prompt_cache")]
+    pub prompt_cache: bool,
+    /// Base URL of a running llama-server to use instead of the llama.cpp binary
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub server_url: Option<String>,
+    /// Where llama.cpp runs the model: "cpu", "metal", or "cuda:0", "vulkan:0", "rocm:0";
+    /// unset leaves the choice to llama.cpp
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device: Option<String>,
+    /// llama.cpp build to run from ~/.commandy/bin/<flavor>: "cpu", "avx2", "cuda", "metal",
+    /// or "vulkan"; unset uses ~/.commandy/bin/llama-cpp or one on the PATH
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub flavor: Option<String>,
+    /// Token limits per kind of generation, overriding those derived from `max_tokens`
+    #[serde(default, skip_serializing_if = "TokenBudgets::is_unset")]
+    pub token_budgets: TokenBudgets,
+    /// Stop sequences per prompt template (suggest, refine, fix, explain, translate, summarize, classify)
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub stop_sequences: HashMap<String, Vec<String>>,
+    /// Refuse to start llama.cpp when the model is estimated not to fit in free memory and swap
+    #[serde(default = "ModelConfig::default_memory_guard")]
+    pub memory_guard: bool,
+}
+
+/// Kind of generation, each with its own token budget
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GenerationMode {
+    /// A few one-line commands
+    Suggest,
+    /// Plain-language explanation of a command
+    Explain,
+    /// Suggestions for a request that calls for a loop or a small script
+    Script,
+    /// Corrections of a failed command
+    Fix,
+    /// A command rewritten for another shell
+    Translate,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct TokenBudgets {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub suggest: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub explain: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub script: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fix: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub translate: Option<u32>,
+}
+
+impl TokenBudgets {
+    fn is_unset(&self) -> bool {
+        self.suggest.is_none()
+            && self.explain.is_none()
+            && self.script.is_none()
+            && self.fix.is_none()
+            && self.translate.is_none()
+    }
+}
+
+/// llama.cpp runtime options that trade accuracy for memory
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ModelRuntimeOptions {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context_size: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_type_k: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_type_v: Option<String>,
+}
+
+impl ModelConfig {
+    fn default_prompt_cache() -> bool {
+        true
+    }
+
+    fn default_memory_guard() -> bool {
+        true
+    }
+
+    /// Token limit for a kind of generation
+    ///
+    /// Explanations and translations get twice `max_tokens` and scripts four
+    /// times, unless `[model.token_budgets]` sets them.
+    pub fn token_budget(&self, mode: GenerationMode) -> u32 {
+        let budgets = &self.token_budgets;
+        match mode {
+            GenerationMode::Suggest => budgets.suggest.unwrap_or(self.max_tokens),
+            GenerationMode::Explain => budgets.explain.unwrap_or(self.max_tokens * 2),
+            GenerationMode::Script => budgets.script.unwrap_or(self.max_tokens * 4),
+            GenerationMode::Fix => budgets.fix.unwrap_or(self.max_tokens),
+            GenerationMode::Translate => budgets.translate.unwrap_or(self.max_tokens * 2),
+        }
+    }
+
+    /// Resolves runtime options for a model, preferring its per-model overrides
+    pub fn runtime_for(&self, model: &str) -> ModelRuntimeOptions {
+        let overrides = self.per_model.get(model).cloned().unwrap_or_default();
+
+        ModelRuntimeOptions {
+            context_size: overrides.context_size.or(self.context_size),
+            cache_type_k: overrides.cache_type_k.or_else(|| self.cache_type_k.clone()),
+            cache_type_v: overrides.cache_type_v.or_else(|| self.cache_type_v.clone()),
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct CacheConfig {
+    /// Serve and store suggestions in ~/.commandy/cache/suggestions.db
+    #[serde(default = "CacheConfig::default_enabled")]
+    pub enabled: bool,
     pub max_cache_entries: usize,
     pub cache_ttl_hours: u32,
+    /// How long an explanation is reused; they rarely change, so far longer than suggestions
+    #[serde(default = "CacheConfig::default_explanation_ttl_days")]
+    pub explanation_ttl_days: u32,
+    /// Requests `commandy cache warm` always generates, besides the most frequent ones
+    #[serde(default)]
+    pub warm_queries: Vec<String>,
+    /// How many of the most frequently asked requests `commandy cache warm` generates
+    #[serde(default = "CacheConfig::default_warm_top")]
+    pub warm_top: usize,
+    /// Warm the cache after `commandy update --binary` or `commandy model use`
+    #[serde(default)]
+    pub warm_after_update: bool,
+}
+
+impl CacheConfig {
+    fn default_enabled() -> bool {
+        true
+    }
+
+    fn default_explanation_ttl_days() -> u32 {
+        90
+    }
+
+    fn default_warm_top() -> usize {
+        20
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct OutputConfig {
     pub show_explanations: bool,
     pub use_colors: bool,
+    /// Suggestions shown when `-n` isn't given
     pub max_suggestions: usize,
+    /// Most suggestions `-n` may ask for
+    #[serde(default = "OutputConfig::default_suggestion_limit")]
+    pub suggestion_limit: usize,
+    /// Suggestions per page of the picker; later pages are generated when reached
+    #[serde(default = "OutputConfig::default_page_size")]
+    pub page_size: usize,
+    /// Show a diff and ask before running commands that edit files in place
+    #[serde(default)]
+    pub confirm_edits: bool,
+    /// Download the script of a `curl | sh` command and show it before running it
+    #[serde(default)]
+    pub unpack_scripts: bool,
+    /// Lines of a downloaded script to show before asking to run it
+    #[serde(default = "OutputConfig::default_unpack_preview_lines")]
+    pub unpack_preview_lines: usize,
+    /// Seconds after running a destructive command during which running it
+    /// again asks first; 0 turns this off
+    #[serde(default = "OutputConfig::default_rerun_cooldown_secs")]
+    pub rerun_cooldown_secs: u64,
+    /// Capture what a chosen command prints and offer to have the model summarize it
+    #[serde(default)]
+    pub summarize_output: bool,
+    /// After a chosen command runs, ask the model for likely next commands to pick from
+    #[serde(default)]
+    pub suggest_next: bool,
+    /// When a chosen command fails, look for fixes from its error output and offer to run one
+    #[serde(default = "OutputConfig::default_triage_failures")]
+    pub triage_failures: bool,
+    /// Kilobytes of output kept for a summary: the start and the end
+    #[serde(default = "OutputConfig::default_summary_capture_kb")]
+    pub summary_capture_kb: usize,
+}
+
+impl OutputConfig {
+    fn default_suggestion_limit() -> usize {
+        20
+    }
+
+    fn default_page_size() -> usize {
+        3
+    }
+
+    fn default_unpack_preview_lines() -> usize {
+        20
+    }
+
+    fn default_rerun_cooldown_secs() -> u64 {
+        60
+    }
+
+    fn default_triage_failures() -> bool {
+        true
+    }
+
+    fn default_summary_capture_kb() -> usize {
+        8
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct PrivacyConfig {
     pub collect_usage_stats: bool,
     pub share_anonymous_data: bool,
+    /// Tell the model which relevant environment variables are set (names only)
+    #[serde(default = "PrivacyConfig::default_env_var_names")]
+    pub env_var_names: bool,
+    /// Tell the model how many files of each type are in the current directory (no names)
+    #[serde(default = "PrivacyConfig::default_file_type_stats")]
+    pub file_type_stats: bool,
+    /// Tell the model the shell's aliases and function names saved by the hook
+    #[serde(default = "PrivacyConfig::default_shell_definitions")]
+    pub shell_definitions: bool,
+}
+
+impl PrivacyConfig {
+    fn default_env_var_names() -> bool {
+        true
+    }
+
+    fn default_file_type_stats() -> bool {
+        true
+    }
+
+    fn default_shell_definitions() -> bool {
+        true
+    }
+}
+
+/// Rewrites applied to suggestions before they are shown
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PostProcessConfig {
+    /// Built-in transforms to run, in order
+    #[serde(default = "PostProcessConfig::default_transforms")]
+    pub transforms: Vec<String>,
+    /// Regex replacements applied after the built-ins
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub replace: Vec<ReplaceRule>,
+    /// Environment prefixes for commands run inside a directory
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub env_prefix: Vec<EnvPrefixRule>,
+    /// Repair quoting of names from the query, find globs, and unclosed quotes
+    #[serde(default = "PostProcessConfig::default_fix_quoting")]
+    pub fix_quoting: bool,
+    /// Rewrite `$VAR` in destructive commands to `"${VAR:?}"`
+    #[serde(default = "PostProcessConfig::default_guard_expansions")]
+    pub guard_expansions: bool,
+}
+
+impl PostProcessConfig {
+    fn default_transforms() -> Vec<String> {
+        ["normalize_whitespace", "expand_tilde"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect()
+    }
+
+    fn default_fix_quoting() -> bool {
+        true
+    }
+
+    fn default_guard_expansions() -> bool {
+        true
+    }
+}
+
+impl Default for PostProcessConfig {
+    fn default() -> Self {
+        Self {
+            transforms: Self::default_transforms(),
+            replace: Vec::new(),
+            env_prefix: Vec::new(),
+            fix_quoting: true,
+            guard_expansions: true,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ReplaceRule {
+    pub pattern: String,
+    pub replacement: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EnvPrefixRule {
+    /// Directory the rule applies under; `~/` is expanded
+    pub path: String,
+    /// Text prepended to the command, e.g. "AWS_PROFILE=staging"
+    pub prefix: String,
+    /// Executables the prefix applies to; empty means all
+    #[serde(default)]
+    pub commands: Vec<String>,
+}
+
+/// External `commandy-<name>` plugins found on PATH
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PluginConfig {
+    /// Off unless asked for, since loading a plugin runs it
+    #[serde(default = "PluginConfig::default_enabled")]
+    pub enabled: bool,
+    /// Plugin names to ignore even if found on PATH
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub disabled: Vec<String>,
+    /// How long a plugin may take to answer before it is killed
+    #[serde(default = "PluginConfig::default_timeout_ms")]
+    pub timeout_ms: u64,
+    /// Wasm plugins allowed to read the current directory (requires the wasm-plugins feature)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub wasm_allow_cwd: Vec<String>,
+}
+
+impl PluginConfig {
+    fn default_enabled() -> bool {
+        false
+    }
+
+    fn default_timeout_ms() -> u64 {
+        2000
+    }
+}
+
+impl Default for PluginConfig {
+    fn default() -> Self {
+        Self {
+            enabled: Self::default_enabled(),
+            disabled: Vec::new(),
+            timeout_ms: Self::default_timeout_ms(),
+            wasm_allow_cwd: Vec::new(),
+        }
+    }
+}
+
+/// Request handling for `commandy serve`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct ServerConfig {
+    /// Requests generated in parallel; each slot runs its own llama.cpp process
+    pub concurrency: usize,
+    /// Requests allowed to wait before new ones are turned away as busy
+    pub max_queue: usize,
+    /// Waiting requests allowed per client, so one shell can't fill the queue
+    pub max_queued_per_client: usize,
+    /// Allow `--host` addresses other machines can reach
+    pub allow_remote: bool,
+    /// Start `commandy daemon` and send requests to its llama-server once it is
+    /// healthy, instead of running llama.cpp for each request
+    pub use_daemon: bool,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            concurrency: 1,
+            max_queue: 16,
+            max_queued_per_client: 4,
+            allow_remote: false,
+            use_daemon: true,
+        }
+    }
+}
+
+/// llama-server kept running by `commandy daemon`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct DaemonConfig {
+    /// Address llama-server listens on
+    pub host: String,
+    pub port: u16,
+    /// Allow a `host` other machines can reach
+    pub allow_remote: bool,
+    /// Seconds between health checks
+    pub health_interval_secs: u64,
+    /// Seconds a health check may take before it counts as failed
+    pub health_timeout_secs: u64,
+    /// Failed health checks in a row before llama-server is restarted
+    pub max_failures: u32,
+    /// Resident memory in MB above which llama-server is restarted; 0 turns the limit off
+    pub max_memory_mb: u64,
+    /// llama-server to run; defaults to the one next to the current llama.cpp flavor, then PATH
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub server_binary: Option<PathBuf>,
+}
+
+impl Default for DaemonConfig {
+    fn default() -> Self {
+        Self {
+            host: "127.0.0.1".to_string(),
+            port: 8080,
+            allow_remote: false,
+            health_interval_secs: 10,
+            health_timeout_secs: 5,
+            max_failures: 3,
+            max_memory_mb: 0,
+            server_binary: None,
+        }
+    }
+}
+
+/// Structured log files used to debug parsing and backend failures
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct LogConfig {
+    pub enabled: bool,
+    /// Directory for rotated logs; defaults to ~/.commandy/logs
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<PathBuf>,
+    /// Level written to the log files: error, warn, info, debug, or trace
+    pub level: String,
+    /// Daily log files kept before the oldest is deleted
+    pub max_files: usize,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            path: None,
+            level: "info".to_string(),
+            max_files: 7,
+        }
+    }
+}
+
+/// What to do with suggestions that need root
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SudoPolicy {
+    /// Rewrite the command to run with sudo
+    Prepend,
+    /// Show the command unchanged, marked as needing root
+    #[default]
+    Warn,
+    /// Never show the command
+    Refuse,
+}
+
+/// Handling of commands that write system paths, install packages, or manage services
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct SudoConfig {
+    pub policy: SudoPolicy,
+}
+
+/// How much of the current directory a sandbox container sees
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SandboxMount {
+    #[default]
+    ReadOnly,
+    ReadWrite,
+    None,
+}
+
+/// Trying suggestions in a throwaway container before running them on the host
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct SandboxConfig {
+    /// Run every executed suggestion in the sandbox first, like `--sandbox`
+    pub enabled: bool,
+    /// Container CLI: docker or podman
+    pub runtime: String,
+    pub image: String,
+    /// Mounted at /work, which is also the working directory
+    pub mount: SandboxMount,
+    pub network: bool,
+    /// Sandbox runs are stopped after this long
+    pub timeout_secs: u64,
+}
+
+impl Default for SandboxConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            runtime: "docker".to_string(),
+            image: "debian:stable-slim".to_string(),
+            mount: SandboxMount::ReadOnly,
+            network: false,
+            timeout_secs: 60,
+        }
+    }
+}
+
+/// What a profile lets happen when a command of some risk tier is chosen, least strict first
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum RiskAction {
+    /// Run it like any other command
+    #[default]
+    Run,
+    /// Ask before running
+    Confirm,
+    /// Run only after the hostname is typed back
+    ConfirmHost,
+    /// Show the command but never run it
+    DisplayOnly,
+}
+
+/// Execution limits for one named profile, such as `prod`
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct ProfileConfig {
+    /// Commands that need root
+    pub privileged: RiskAction,
+    /// Commands that delete data or run unread code; never looser than `privileged`
+    pub destructive: RiskAction,
+}
+
+/// The hash-chained record of executed commands in ~/.commandy/audit.log
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct AuditConfig {
+    pub enabled: bool,
+}
+
+impl Default for AuditConfig {
+    fn default() -> Self {
+        Self { enabled: true }
+    }
+}
+
+/// Requests written in languages other than English
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct LanguageConfig {
+    /// Have the model put the request in English before generating commands
+    pub translate: bool,
+    /// Write `--explain` explanations in the request's language
+    pub explain_in_request_language: bool,
+}
+
+impl Default for LanguageConfig {
+    fn default() -> Self {
+        Self {
+            translate: true,
+            explain_in_request_language: true,
+        }
+    }
+}
+
+/// A prompt experiment: a share of queries get other suggestion instructions,
+/// and `commandy stats experiments` compares how often each kind is used
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ExperimentConfig {
+    /// Name the results are kept under; nothing runs without a name and a variant
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Share of queries (0.0 to 1.0) that get the variant
+    #[serde(default = "ExperimentConfig::default_share")]
+    pub share: f32,
+    /// Instructions replacing the start of the suggestion prompt, with `{os}`,
+    /// `{shell}`, and `{tools}` filled in
+    #[serde(default)]
+    pub variant: Option<String>,
+}
+
+impl ExperimentConfig {
+    fn default_share() -> f32 {
+        0.5
+    }
+}
+
+impl Default for ExperimentConfig {
+    fn default() -> Self {
+        Self {
+            name: None,
+            share: Self::default_share(),
+            variant: None,
+        }
+    }
+}
+
+/// Running the top suggestion without the picker when it is read-only and confident
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AutoExecuteConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Lowest confidence (0.0 to 1.0) that runs without asking
+    #[serde(default = "AutoExecuteConfig::default_min_confidence")]
+    pub min_confidence: f32,
+    /// Programs, or programs with subcommands like "kubectl get", that only read
+    #[serde(default = "AutoExecuteConfig::default_allow")]
+    pub allow: Vec<String>,
+}
+
+impl AutoExecuteConfig {
+    fn default_min_confidence() -> f32 {
+        0.9
+    }
+
+    fn default_allow() -> Vec<String> {
+        [
+            "ls",
+            "cat",
+            "head",
+            "tail",
+            "grep",
+            "rg",
+            "wc",
+            "du",
+            "df",
+            "free",
+            "ps",
+            "pwd",
+            "whoami",
+            "uptime",
+            "find",
+            "git status",
+            "git log",
+            "git diff",
+            "git show",
+            "kubectl get",
+            "kubectl describe",
+            "kubectl logs",
+            "docker ps",
+            "docker images",
+            "docker logs",
+            "systemctl status",
+        ]
+        .map(String::from)
+        .to_vec()
+    }
+}
+
+impl Default for AutoExecuteConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            min_confidence: Self::default_min_confidence(),
+            allow: Self::default_allow(),
+        }
+    }
+}
+
+/// Output colors: a built-in theme plus per-role overrides
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct ThemeConfig {
+    /// default, light, high-contrast, or dracula
+    pub name: String,
+    /// Role name to color: a name like "bright-red", "#rrggbb", or a 0-255 palette index
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub colors: HashMap<String, String>,
+}
+
+impl Default for ThemeConfig {
+    fn default() -> Self {
+        Self {
+            name: "default".to_string(),
+            colors: HashMap::new(),
+        }
+    }
 }
 
 impl Settings {
@@ -94,25 +802,73 @@ impl Default for Settings {
                 max_context_size_kb: 50,
                 recent_commands_limit: 100,
                 learning_enabled: true,
+                summarize_learned_after: GeneralConfig::default_summarize_learned_after(),
+                profile: None,
             },
             model: ModelConfig {
                 model_path: model_name,
+                fast_model_path: None,
                 max_tokens: 200,
                 temperature: 0.1,
+                seed: None,
+                context_size: None,
+                cache_type_k: None,
+                cache_type_v: None,
+                per_model: HashMap::new(),
+                prompt_cache: true,
+                server_url: None,
+                device: None,
+                flavor: None,
+                token_budgets: TokenBudgets::default(),
+                stop_sequences: HashMap::new(),
+                memory_guard: true,
             },
             cache: CacheConfig {
+                enabled: true,
                 max_cache_entries: 1000,
                 cache_ttl_hours: 24,
+                explanation_ttl_days: 90,
+                warm_queries: Vec::new(),
+                warm_top: CacheConfig::default_warm_top(),
+                warm_after_update: false,
             },
             output: OutputConfig {
                 show_explanations: true,
                 use_colors: true,
                 max_suggestions: 3,
+                suggestion_limit: OutputConfig::default_suggestion_limit(),
+                page_size: OutputConfig::default_page_size(),
+                confirm_edits: false,
+                unpack_scripts: false,
+                unpack_preview_lines: OutputConfig::default_unpack_preview_lines(),
+                rerun_cooldown_secs: OutputConfig::default_rerun_cooldown_secs(),
+                summarize_output: false,
+                suggest_next: false,
+                triage_failures: OutputConfig::default_triage_failures(),
+                summary_capture_kb: OutputConfig::default_summary_capture_kb(),
             },
             privacy: PrivacyConfig {
                 collect_usage_stats: false,
                 share_anonymous_data: false,
+                env_var_names: true,
+                file_type_stats: true,
+                shell_definitions: true,
             },
+            postprocess: PostProcessConfig::default(),
+            plugins: PluginConfig::default(),
+            server: ServerConfig::default(),
+            daemon: DaemonConfig::default(),
+            log: LogConfig::default(),
+            sudo: SudoConfig::default(),
+            sandbox: SandboxConfig::default(),
+            theme: ThemeConfig::default(),
+            audit: AuditConfig::default(),
+            auto_execute: AutoExecuteConfig::default(),
+            experiment: ExperimentConfig::default(),
+            language: LanguageConfig::default(),
+            templates: HashMap::new(),
+            profiles: HashMap::new(),
+            keymap: HashMap::new(),
         }
     }
 }
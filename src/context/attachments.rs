@@ -0,0 +1,142 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+/// Characters of attached text in one prompt when the context size is unknown
+const MAX_ATTACHED_CHARS: usize = 8000;
+
+/// Tokens kept free for the rest of the prompt: rules, context, and the request
+const PROMPT_RESERVE_TOKENS: usize = 1024;
+
+/// A conservative guess; code and configs tokenize worse than prose
+const CHARS_PER_TOKEN: usize = 3;
+
+/// Bytes read from one file; the prompt only ever holds the start of it
+const MAX_READ_BYTES: u64 = 256 * 1024;
+
+/// Text the user gave as context for a request: a file passed with `-f`, or
+/// text piped to commandy or pasted with `--paste`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Attachment {
+    /// The path as given, or a label for text that isn't a file
+    pub name: String,
+    pub content: String,
+    /// Whether the end was cut off to fit the prompt
+    #[serde(default)]
+    pub truncated: bool,
+}
+
+impl Attachment {
+    /// Reads a text file, refusing directories and binary files
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let name = path.display().to_string();
+        let file = File::open(path).map_err(|e| anyhow::anyhow!("Cannot read {name}: {e}"))?;
+        let metadata = file.metadata()?;
+        if metadata.is_dir() {
+            anyhow::bail!("{name} is a directory; pass files with -f");
+        }
+
+        let mut bytes = Vec::new();
+        file.take(MAX_READ_BYTES)
+            .read_to_end(&mut bytes)
+            .map_err(|e| anyhow::anyhow!("Cannot read {name}: {e}"))?;
+        let truncated = metadata.len() > MAX_READ_BYTES;
+
+        if bytes.contains(&0) {
+            anyhow::bail!("{name} looks like a binary file; only text files can be attached");
+        }
+        // A cut may land inside a multi-byte character; anything else is binary
+        let content = match String::from_utf8(bytes) {
+            Ok(content) => content,
+            Err(e) if truncated && e.utf8_error().error_len().is_none() => {
+                let valid = e.utf8_error().valid_up_to();
+                String::from_utf8_lossy(&e.into_bytes()[..valid]).into_owned()
+            }
+            Err(_) => anyhow::bail!("{name} is not UTF-8 text; only text files can be attached"),
+        };
+
+        Ok(Self {
+            name,
+            content,
+            truncated,
+        })
+    }
+
+    /// Text that didn't come from a file, such as piped or pasted input
+    pub fn from_text(name: &str, content: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            content: content.trim_matches('\n').to_string(),
+            truncated: false,
+        }
+    }
+
+    /// Reads every file given with `-f`, stopping at the first that can't be attached
+    pub fn from_files(paths: &[impl AsRef<Path>]) -> Result<Vec<Self>> {
+        paths
+            .iter()
+            .map(|path| Self::from_file(path.as_ref()))
+            .collect()
+    }
+
+    /// Characters of attachments that fit in the prompt, from the model's context size
+    ///
+    /// `None` or 0 means llama.cpp uses the model's full context, which is far
+    /// more than a suggestion prompt should spend on files.
+    pub fn budget(context_size: Option<u32>, output_tokens: u32) -> usize {
+        match context_size {
+            Some(tokens) if tokens > 0 => (tokens as usize)
+                .saturating_sub(output_tokens as usize + PROMPT_RESERVE_TOKENS)
+                .saturating_mul(CHARS_PER_TOKEN)
+                .min(MAX_ATTACHED_CHARS),
+            _ => MAX_ATTACHED_CHARS,
+        }
+    }
+
+    /// Cuts attachments down to `budget` characters in total
+    ///
+    /// Each gets an equal share, and what a short one leaves over goes to the
+    /// longer ones. Files are cut at a line break where possible.
+    pub fn fit(attachments: &mut [Self], budget: usize) {
+        let mut order: Vec<usize> = (0..attachments.len()).collect();
+        order.sort_by_key(|&i| attachments[i].content.chars().count());
+
+        let mut remaining = budget;
+        for (position, &i) in order.iter().enumerate() {
+            let share = remaining / (order.len() - position);
+            let attachment = &mut attachments[i];
+            attachment.truncate(share);
+            remaining -= attachment.content.chars().count().min(remaining);
+        }
+    }
+
+    /// Names and a hash of the contents, so cached answers follow the files
+    pub fn fingerprint(attachments: &[Self]) -> String {
+        let mut hasher = Sha256::new();
+        for attachment in attachments {
+            hasher.update(attachment.name.as_bytes());
+            hasher.update([0]);
+            hasher.update(attachment.content.as_bytes());
+            hasher.update([0]);
+        }
+        let names: Vec<&str> = attachments.iter().map(|a| a.name.as_str()).collect();
+        format!("{} {:.12x}", names.join(", "), hasher.finalize())
+    }
+
+    fn truncate(&mut self, max_chars: usize) {
+        let Some((end, _)) = self.content.char_indices().nth(max_chars) else {
+            return;
+        };
+        let cut = &self.content[..end];
+        // Whole lines read better than half of one, unless that loses most of it
+        let cut = match cut.rfind('\n') {
+            Some(newline) if newline >= end / 2 => &cut[..newline + 1],
+            _ => cut,
+        };
+        self.content = cut.to_string();
+        self.truncated = true;
+    }
+}
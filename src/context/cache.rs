@@ -1,14 +1,49 @@
+use anyhow::Result;
+use chrono::{DateTime, NaiveDateTime, Utc};
 use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
 use std::collections::hash_map::DefaultHasher;
 use std::hash::{Hash, Hasher};
 use std::path::Path;
-// use chrono::Utc; // Will be used when we add timestamp functionality
-use anyhow::Result;
 
-use crate::cli::Suggestion;
+use crate::cli::{Suggestion, SuggestionSource};
+use crate::normalize::CommandNormalizer;
+
+/// Where a history entry was recorded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HistorySource {
+    Shell,
+    Commandy,
+}
+
+/// A previously run command, from shell history or commandy's execution log
+#[derive(Debug, Clone)]
+pub struct HistoryEntry {
+    pub command: String,
+    /// The natural-language prompt that produced the command, if any
+    pub prompt: Option<String>,
+    pub timestamp: Option<DateTime<Utc>>,
+    pub source: HistorySource,
+}
+
+/// A cached suggestion with its usage counts, as moved between machines
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedSuggestion {
+    pub prompt: String,
+    pub command: String,
+    pub explanation: Option<String>,
+    pub confidence: f32,
+    pub use_count: u32,
+    pub success_count: u32,
+    /// Fingerprint of the model that suggested it; empty in older bundles
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub model: String,
+}
 
 pub struct CacheManager {
     connection: Connection,
+    /// Fingerprint of the configured model; only its suggestions are served or updated
+    model: String,
 }
 
 impl CacheManager {
@@ -21,7 +56,22 @@ impl CacheManager {
         // Run migrations for existing databases
         Self::migrate_database(&connection)?;
 
-        Ok(Self { connection })
+        Ok(Self {
+            connection,
+            model: String::new(),
+        })
+    }
+
+    /// Serves and stores suggestions for the model with this fingerprint
+    ///
+    /// Suggestions cached before models were recorded are taken to be this model's.
+    pub fn set_model(&mut self, fingerprint: &str) -> Result<()> {
+        self.model = fingerprint.to_string();
+        self.connection.execute(
+            "UPDATE OR IGNORE suggestions SET model = ?1 WHERE model = ''",
+            [fingerprint],
+        )?;
+        Ok(())
     }
 
     fn migrate_database(connection: &Connection) -> Result<()> {
@@ -33,11 +83,15 @@ impl CacheManager {
 
         let mut has_success_count = false;
         let mut has_success_rate = false;
+        let mut has_warmed_at = false;
+        let mut has_model = false;
 
         for row in rows {
             match row? {
                 name if name == "success_count" => has_success_count = true,
                 name if name == "success_rate" => has_success_rate = true,
+                name if name == "warmed_at" => has_warmed_at = true,
+                name if name == "model" => has_model = true,
                 _ => {}
             }
         }
@@ -55,6 +109,52 @@ impl CacheManager {
                 [],
             )?;
         }
+        if !has_warmed_at {
+            connection.execute("ALTER TABLE suggestions ADD COLUMN warmed_at TIMESTAMP", [])?;
+        }
+        if !has_model {
+            connection.execute(
+                "ALTER TABLE suggestions ADD COLUMN model TEXT NOT NULL DEFAULT ''",
+                [],
+            )?;
+        }
+        // Each model keeps its own answers, so the same command can be cached once per model
+        connection.execute_batch(
+            "DROP INDEX IF EXISTS idx_suggestions_unique;
+             CREATE UNIQUE INDEX IF NOT EXISTS idx_suggestions_model_unique
+                 ON suggestions(model, prompt_hash, suggestion);",
+        )?;
+
+        // History rows are keyed by canonical command and shape, filled in for older rows
+        let mut stmt = connection.prepare("PRAGMA table_info(history)")?;
+        let history_columns = stmt
+            .query_map([], |row| row.get::<_, String>(1))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        if !history_columns.iter().any(|name| name == "command_key") {
+            connection.execute("ALTER TABLE history ADD COLUMN command_key TEXT", [])?;
+            connection.execute("ALTER TABLE history ADD COLUMN command_shape TEXT", [])?;
+
+            let mut select = connection.prepare("SELECT id, command FROM history")?;
+            let rows = select
+                .query_map([], |row| {
+                    Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+                })?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            for (id, command) in rows {
+                connection.execute(
+                    "UPDATE history SET command_key = ?1, command_shape = ?2 WHERE id = ?3",
+                    params![
+                        CommandNormalizer::canonical(&command),
+                        CommandNormalizer::shape(&command),
+                        id
+                    ],
+                )?;
+            }
+        }
+        connection.execute(
+            "CREATE INDEX IF NOT EXISTS idx_history_command_key ON history(command_key)",
+            [],
+        )?;
 
         Ok(())
     }
@@ -62,21 +162,23 @@ impl CacheManager {
     pub fn get_suggestion(&self, prompt: &str) -> Result<Option<Suggestion>> {
         let prompt_hash = self.hash_prompt(prompt);
 
+        // A warmed suggestion is served before it has been used, until it fails
         let mut stmt = self.connection.prepare(
             "SELECT suggestion, explanation, confidence, use_count, success_rate FROM suggestions 
-             WHERE prompt_hash = ?1 
-             AND created_at > datetime('now', '-7 days')
-             AND use_count >= 5
-             AND success_rate > 0.7
+             WHERE prompt_hash = ?1 AND model = ?2
+             AND ((created_at > datetime('now', '-7 days') AND use_count >= 5 AND success_rate > 0.7)
+                  OR (warmed_at > datetime('now', '-7 days') AND success_rate >= 0.5))
              ORDER BY (success_rate * 0.6 + confidence * 0.4) DESC 
              LIMIT 1",
         )?;
 
-        let result = stmt.query_row([prompt_hash.clone()], |row| {
+        let result = stmt.query_row(params![prompt_hash, self.model], |row| {
             Ok(Suggestion {
                 command: row.get(0)?,
                 explanation: row.get(1)?,
                 confidence: row.get(2)?,
+                source: SuggestionSource::Cache,
+                ranking: None,
             })
         });
 
@@ -93,11 +195,17 @@ impl CacheManager {
 
     pub fn cache_suggestion(&mut self, prompt: &str, suggestion: &Suggestion) -> Result<()> {
         let prompt_hash = self.hash_prompt(prompt);
+        let command = Self::stored_spelling(
+            &self.connection,
+            &self.model,
+            &prompt_hash,
+            &suggestion.command,
+        )?;
 
         // Check if this suggestion already exists
         let existing = self.connection.query_row(
-            "SELECT id, use_count, success_count FROM suggestions WHERE prompt_hash = ?1 AND suggestion = ?2",
-            params![prompt_hash, suggestion.command],
+            "SELECT id, use_count, success_count FROM suggestions WHERE prompt_hash = ?1 AND suggestion = ?2 AND model = ?3",
+            params![prompt_hash, command, self.model],
             |row| Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?, row.get::<_, i64>(2)?))
         );
 
@@ -119,14 +227,15 @@ impl CacheManager {
                 // Insert new suggestion with conservative defaults
                 self.connection.execute(
                     "INSERT INTO suggestions 
-                     (prompt_hash, prompt, suggestion, explanation, confidence, created_at, last_used, use_count, success_count, success_rate) 
-                     VALUES (?, ?, ?, ?, ?, datetime('now'), datetime('now'), 0, 0, 0.5)",
+                     (prompt_hash, prompt, suggestion, explanation, confidence, created_at, last_used, use_count, success_count, success_rate, model) 
+                     VALUES (?, ?, ?, ?, ?, datetime('now'), datetime('now'), 0, 0, 0.5, ?)",
                     params![
                         prompt_hash,
                         prompt,
-                        suggestion.command,
+                        command,
                         suggestion.explanation,
                         suggestion.confidence,
+                        self.model,
                     ],
                 )?;
             }
@@ -145,9 +254,18 @@ impl CacheManager {
         let context_snapshot = self.get_current_environment_snapshot()?;
 
         self.connection.execute(
-            "INSERT INTO history (command, prompt, success, exit_code, context_snapshot) 
-             VALUES (?, ?, ?, ?, ?)",
-            params![command, prompt, success, exit_code, context_snapshot,],
+            "INSERT INTO history
+             (command, prompt, success, exit_code, context_snapshot, command_key, command_shape)
+             VALUES (?, ?, ?, ?, ?, ?, ?)",
+            params![
+                command,
+                prompt,
+                success,
+                exit_code,
+                context_snapshot,
+                CommandNormalizer::canonical(command),
+                CommandNormalizer::shape(command),
+            ],
         )?;
 
         Ok(())
@@ -171,6 +289,30 @@ impl CacheManager {
         Ok(commands)
     }
 
+    /// How often a command was run through commandy, and how often it succeeded
+    pub fn execution_stats(&self, command: &str) -> Result<(u32, u32)> {
+        let stats = self.connection.query_row(
+            "SELECT COUNT(*), COALESCE(SUM(success), 0) FROM history WHERE command_key = ?1",
+            [CommandNormalizer::canonical(command)],
+            |row| Ok((row.get::<_, u32>(0)?, row.get::<_, u32>(1)?)),
+        )?;
+        Ok(stats)
+    }
+
+    /// When a command last finished running through commandy
+    pub fn last_run(&self, command: &str) -> Result<Option<DateTime<Utc>>> {
+        let executed_at: Option<String> = self.connection.query_row(
+            "SELECT MAX(executed_at) FROM history WHERE command = ?1",
+            [command],
+            |row| row.get(0),
+        )?;
+        Ok(executed_at.and_then(|t| {
+            NaiveDateTime::parse_from_str(&t, "%Y-%m-%d %H:%M:%S")
+                .ok()
+                .map(|t| t.and_utc())
+        }))
+    }
+
     pub fn update_environment(&mut self, key: &str, value: &str) -> Result<()> {
         self.connection.execute(
             "INSERT OR REPLACE INTO environment (key, value, updated_at) 
@@ -199,8 +341,110 @@ impl CacheManager {
         Ok(env)
     }
 
+    /// Cached explanation of a command, if one was saved in the last `ttl_days`
+    pub fn get_explanation(&self, command: &str, ttl_days: u32) -> Result<Option<String>> {
+        let key = Self::explanation_key(command);
+        let result = self.connection.query_row(
+            "SELECT explanation FROM explanations
+             WHERE command = ?1 AND created_at > datetime('now', '-' || ?2 || ' days')",
+            params![key, ttl_days],
+            |row| row.get::<_, String>(0),
+        );
+
+        match result {
+            Ok(explanation) => {
+                self.connection.execute(
+                    "UPDATE explanations SET last_used = datetime('now'), use_count = use_count + 1
+                     WHERE command = ?1",
+                    [&key],
+                )?;
+                Ok(Some(explanation))
+            }
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn cache_explanation(&mut self, command: &str, explanation: &str) -> Result<()> {
+        self.connection.execute(
+            "INSERT OR REPLACE INTO explanations (command, explanation) VALUES (?1, ?2)",
+            params![Self::explanation_key(command), explanation],
+        )?;
+        Ok(())
+    }
+
+    /// Explanations don't depend on spacing, so `tar  -xzvf a.tgz` shares one with `tar -xzvf a.tgz`
+    fn explanation_key(command: &str) -> String {
+        CommandNormalizer::canonical(command)
+    }
+
+    /// Cached summary of a tool, if one was saved in the last `ttl_days`
+    pub fn get_tool_summary(&self, tool: &str, ttl_days: u32) -> Result<Option<String>> {
+        let result = self.connection.query_row(
+            "SELECT summary FROM tool_summaries
+             WHERE tool = ?1 AND created_at > datetime('now', '-' || ?2 || ' days')",
+            params![tool, ttl_days],
+            |row| row.get::<_, String>(0),
+        );
+
+        match result {
+            Ok(summary) => Ok(Some(summary)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn cache_tool_summary(&mut self, tool: &str, summary: &str) -> Result<()> {
+        self.connection.execute(
+            "INSERT OR REPLACE INTO tool_summaries (tool, summary) VALUES (?1, ?2)",
+            params![tool, summary],
+        )?;
+        Ok(())
+    }
+
+    /// Caches `suggestion` as the answer to serve for `prompt` without waiting
+    /// for it to prove itself by being used
+    pub fn warm_suggestion(&mut self, prompt: &str, suggestion: &Suggestion) -> Result<()> {
+        self.cache_suggestion(prompt, suggestion)?;
+        let prompt_hash = self.hash_prompt(prompt);
+        let command = Self::stored_spelling(
+            &self.connection,
+            &self.model,
+            &prompt_hash,
+            &suggestion.command,
+        )?;
+
+        // One warmed answer per request; an earlier warm may have picked another
+        let tx = self.connection.transaction()?;
+        tx.execute(
+            "UPDATE suggestions SET warmed_at = NULL WHERE prompt_hash = ?1 AND model = ?2",
+            params![prompt_hash, self.model],
+        )?;
+        tx.execute(
+            "UPDATE suggestions SET warmed_at = datetime('now'), explanation = COALESCE(?3, explanation)
+             WHERE prompt_hash = ?1 AND suggestion = ?2 AND model = ?4",
+            params![prompt_hash, command, suggestion.explanation, self.model],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// The requests whose commands were run most often, most frequent first
+    pub fn frequent_prompts(&self, limit: usize) -> Result<Vec<String>> {
+        let mut stmt = self.connection.prepare(
+            "SELECT prompt FROM history WHERE trim(prompt) != ''
+             GROUP BY lower(trim(prompt))
+             ORDER BY COUNT(*) DESC, MAX(executed_at) DESC
+             LIMIT ?1",
+        )?;
+        let rows = stmt.query_map([limit as i64], |row| row.get::<_, String>(0))?;
+        Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?)
+    }
+
     pub fn clear_cache(&mut self) -> Result<()> {
         self.connection.execute("DELETE FROM suggestions", [])?;
+        self.connection.execute("DELETE FROM explanations", [])?;
+        self.connection.execute("DELETE FROM tool_summaries", [])?;
         self.connection.execute("DELETE FROM history", [])?;
         Ok(())
     }
@@ -215,14 +459,19 @@ impl CacheManager {
 
         // Cached suggestions (ready for reuse)
         let cached: i64 = self.connection.query_row(
-            "SELECT COUNT(*) FROM suggestions WHERE use_count >= 5 AND success_rate > 0.7",
-            [],
+            "SELECT COUNT(*) FROM suggestions WHERE use_count >= 5 AND success_rate > 0.7 AND model = ?1",
+            [&self.model],
+            |row| row.get(0),
+        )?;
+        let other_models: i64 = self.connection.query_row(
+            "SELECT COUNT(*) FROM suggestions WHERE model != ?1",
+            [&self.model],
             |row| row.get(0),
         )?;
 
         // Success rate stats
         let (avg_success_rate, high_success): (f64, i64) = self.connection.query_row(
-            "SELECT AVG(success_rate), COUNT(*) FROM suggestions WHERE success_rate > 0.8",
+            "SELECT COALESCE(AVG(success_rate), 0), COUNT(*) FROM suggestions WHERE success_rate > 0.8",
             [],
             |row| Ok((row.get(0)?, row.get(1)?)),
         )?;
@@ -243,6 +492,39 @@ impl CacheManager {
             avg_success_rate * 100.0
         ));
         stats.push_str(&format!("- High success (>80%): {high_success}\n"));
+        if other_models > 0 {
+            stats.push_str(&format!(
+                "- From other models (kept, not served): {other_models}\n"
+            ));
+        }
+
+        let explanations: i64 =
+            self.connection
+                .query_row("SELECT COUNT(*) FROM explanations", [], |row| row.get(0))?;
+        stats.push_str(&format!("- Cached explanations: {explanations}\n"));
+
+        let tool_summaries: i64 =
+            self.connection
+                .query_row("SELECT COUNT(*) FROM tool_summaries", [], |row| row.get(0))?;
+        stats.push_str(&format!("- Cached tool summaries: {tool_summaries}\n"));
+
+        // Grouped by shape, so `du -sh /var` and `du -sh ~/src` count together
+        let mut stmt = self.connection.prepare(
+            "SELECT command_shape, COUNT(*) AS runs FROM history
+             WHERE command_shape IS NOT NULL
+             GROUP BY command_shape ORDER BY runs DESC LIMIT 3",
+        )?;
+        let most_run = stmt
+            .query_map([], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        if !most_run.is_empty() {
+            stats.push_str("- Most run:\n");
+            for (shape, runs) in most_run {
+                stats.push_str(&format!("    {shape} ({runs})\n"));
+            }
+        }
 
         Ok(stats)
     }
@@ -273,13 +555,109 @@ impl CacheManager {
         self.connection.execute(
             "UPDATE suggestions 
              SET last_used = datetime('now'), use_count = use_count + 1 
-             WHERE prompt_hash = ?1",
-            [prompt_hash],
+             WHERE prompt_hash = ?1 AND model = ?2",
+            params![prompt_hash, self.model],
         )?;
 
         Ok(())
     }
 
+    pub fn export_suggestions(&self) -> Result<Vec<CachedSuggestion>> {
+        let mut stmt = self.connection.prepare(
+            "SELECT prompt, suggestion, explanation, confidence, use_count, success_count, model
+             FROM suggestions ORDER BY id",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok(CachedSuggestion {
+                prompt: row.get(0)?,
+                command: row.get(1)?,
+                explanation: row.get(2)?,
+                confidence: row.get(3)?,
+                use_count: row.get(4)?,
+                success_count: row.get(5)?,
+                model: row.get(6)?,
+            })
+        })?;
+        Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?)
+    }
+
+    /// Merges suggestions from another machine, adding their usage counts to any local copy
+    ///
+    /// Suggestions keep the model they came from; those without one are taken
+    /// to be the configured model's.
+    pub fn import_suggestions(&mut self, suggestions: &[CachedSuggestion]) -> Result<usize> {
+        // Prompt hashes aren't stable across builds, so they are recomputed here
+        let hashes: Vec<String> = suggestions
+            .iter()
+            .map(|s| self.hash_prompt(&s.prompt))
+            .collect();
+
+        let tx = self.connection.transaction()?;
+        for (suggestion, prompt_hash) in suggestions.iter().zip(hashes) {
+            let model = match suggestion.model.is_empty() {
+                true => &self.model,
+                false => &suggestion.model,
+            };
+            tx.execute(
+                "INSERT INTO suggestions
+                 (prompt_hash, prompt, suggestion, explanation, confidence, use_count, success_count, success_rate, model)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7,
+                         CASE WHEN ?6 > 0 THEN CAST(?7 AS FLOAT) / ?6 ELSE 0.5 END, ?8)
+                 ON CONFLICT(model, prompt_hash, suggestion) DO UPDATE SET
+                     use_count = use_count + excluded.use_count,
+                     success_count = success_count + excluded.success_count,
+                     success_rate = CASE WHEN use_count + excluded.use_count > 0
+                         THEN CAST(success_count + excluded.success_count AS FLOAT)
+                              / (use_count + excluded.use_count)
+                         ELSE success_rate END",
+                params![
+                    prompt_hash,
+                    suggestion.prompt,
+                    Self::stored_spelling(&tx, model, &prompt_hash, &suggestion.command)?,
+                    suggestion.explanation,
+                    suggestion.confidence,
+                    suggestion.use_count,
+                    suggestion.success_count,
+                    model,
+                ],
+            )?;
+        }
+        tx.commit()?;
+        Ok(suggestions.len())
+    }
+
+    /// Merges a shared seed cache, whose answers are served straight away like warmed ones
+    ///
+    /// The seed's model was checked against the configured one, so its
+    /// suggestions are stored as the configured model's.
+    pub fn import_seed(&mut self, suggestions: &[CachedSuggestion]) -> Result<usize> {
+        let suggestions: Vec<CachedSuggestion> = suggestions
+            .iter()
+            .map(|s| CachedSuggestion {
+                model: self.model.clone(),
+                ..s.clone()
+            })
+            .collect();
+        let count = self.import_suggestions(&suggestions)?;
+        let hashes: Vec<String> = suggestions
+            .iter()
+            .map(|s| self.hash_prompt(&s.prompt))
+            .collect();
+
+        let tx = self.connection.transaction()?;
+        for (suggestion, prompt_hash) in suggestions.iter().zip(hashes) {
+            let command =
+                Self::stored_spelling(&tx, &self.model, &prompt_hash, &suggestion.command)?;
+            tx.execute(
+                "UPDATE suggestions SET warmed_at = datetime('now')
+                 WHERE prompt_hash = ?1 AND suggestion = ?2 AND model = ?3",
+                params![prompt_hash, command, self.model],
+            )?;
+        }
+        tx.commit()?;
+        Ok(count)
+    }
+
     pub fn record_suggestion_usage(
         &mut self,
         prompt: &str,
@@ -287,6 +665,7 @@ impl CacheManager {
         success: bool,
     ) -> Result<()> {
         let prompt_hash = self.hash_prompt(prompt);
+        let command = Self::stored_spelling(&self.connection, &self.model, &prompt_hash, command)?;
 
         // Update the suggestion's usage statistics
         let mut stmt = self.connection.prepare(
@@ -295,14 +674,48 @@ impl CacheManager {
                  success_count = success_count + CASE WHEN ?3 THEN 1 ELSE 0 END,
                  success_rate = CAST(success_count + CASE WHEN ?3 THEN 1 ELSE 0 END AS FLOAT) / (use_count + 1),
                  last_used = datetime('now')
-             WHERE prompt_hash = ?1 AND suggestion = ?2"
+             WHERE prompt_hash = ?1 AND suggestion = ?2 AND model = ?4"
         )?;
 
-        stmt.execute(params![prompt_hash, command, success])?;
+        stmt.execute(params![prompt_hash, command, success, self.model])?;
         Ok(())
     }
 
+    /// The spelling `model` already cached for a prompt that is the same command, else `command`
+    ///
+    /// Spelling variants (`ls -la`, `ls  -al;`) then share one entry and its usage counts.
+    fn stored_spelling(
+        connection: &Connection,
+        model: &str,
+        prompt_hash: &str,
+        command: &str,
+    ) -> Result<String> {
+        let mut stmt = connection
+            .prepare("SELECT suggestion FROM suggestions WHERE prompt_hash = ?1 AND model = ?2")?;
+        let cached = stmt
+            .query_map([prompt_hash, model], |row| row.get::<_, String>(0))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(cached
+            .into_iter()
+            .find(|spelling| CommandNormalizer::same(spelling, command))
+            .unwrap_or_else(|| command.to_string()))
+    }
+
     pub fn get_shell_history(&self) -> Result<Vec<String>> {
+        let mut commands: Vec<String> = Self::read_shell_history()?
+            .into_iter()
+            .map(|entry| entry.command)
+            .collect();
+
+        // Get last 100 commands and reverse to get most recent first
+        commands.reverse();
+        commands.truncate(100);
+
+        Ok(commands)
+    }
+
+    /// Parses the user's shell history file, oldest first, keeping timestamps when recorded
+    pub fn read_shell_history() -> Result<Vec<HistoryEntry>> {
         let home = std::env::var("HOME")?;
         let shell = std::env::var("SHELL").unwrap_or_default();
 
@@ -319,32 +732,87 @@ impl CacheManager {
             return Ok(Vec::new());
         }
 
-        let content = std::fs::read_to_string(history_path)?;
-        let mut commands: Vec<String> = content
-            .lines()
-            .filter_map(|line| {
-                let line = line.trim();
-                // Skip empty lines and comments
-                if line.is_empty() || line.starts_with('#') {
-                    return None;
-                }
+        // zsh history may contain non-UTF-8 metafied bytes
+        let content = String::from_utf8_lossy(&std::fs::read(history_path)?).to_string();
+        let mut entries = Vec::new();
+        let mut pending_timestamp = None;
+
+        for line in content.lines() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
 
-                // Handle zsh history format (: timestamp:duration;command)
-                if line.starts_with(':') {
-                    if let Some(semicolon_pos) = line.find(';') {
-                        return Some(line[semicolon_pos + 1..].to_string());
-                    }
+            // bash with HISTTIMEFORMAT writes "#<epoch>" before each command
+            if let Some(epoch) = line.strip_prefix('#') {
+                pending_timestamp = epoch
+                    .parse::<i64>()
+                    .ok()
+                    .and_then(|secs| DateTime::from_timestamp(secs, 0));
+                continue;
+            }
+
+            // Handle zsh history format (: timestamp:duration;command)
+            if line.starts_with(':') {
+                if let Some(semicolon_pos) = line.find(';') {
+                    let timestamp = line[1..semicolon_pos]
+                        .trim()
+                        .split(':')
+                        .next()
+                        .and_then(|epoch| epoch.parse::<i64>().ok())
+                        .and_then(|secs| DateTime::from_timestamp(secs, 0));
+                    entries.push(HistoryEntry {
+                        command: line[semicolon_pos + 1..].to_string(),
+                        prompt: None,
+                        timestamp,
+                        source: HistorySource::Shell,
+                    });
+                    continue;
                 }
+            }
 
-                Some(line.to_string())
-            })
-            .collect();
+            entries.push(HistoryEntry {
+                command: line.to_string(),
+                prompt: None,
+                timestamp: pending_timestamp.take(),
+                source: HistorySource::Shell,
+            });
+        }
 
-        // Get last 100 commands and reverse to get most recent first
-        commands.reverse();
-        commands.truncate(100);
+        Ok(entries)
+    }
 
-        Ok(commands)
+    /// Returns commands executed through commandy, most recent first
+    pub fn get_execution_history(&self, limit: usize) -> Result<Vec<HistoryEntry>> {
+        let mut stmt = self.connection.prepare(
+            "SELECT command, prompt, executed_at FROM history 
+             WHERE success = TRUE 
+             ORDER BY executed_at DESC 
+             LIMIT ?1",
+        )?;
+
+        let rows = stmt.query_map([limit], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+            ))
+        })?;
+
+        let mut entries = Vec::new();
+        for row in rows {
+            let (command, prompt, executed_at) = row?;
+            entries.push(HistoryEntry {
+                command,
+                prompt: Some(prompt),
+                timestamp: NaiveDateTime::parse_from_str(&executed_at, "%Y-%m-%d %H:%M:%S")
+                    .ok()
+                    .map(|t| t.and_utc()),
+                source: HistorySource::Commandy,
+            });
+        }
+
+        Ok(entries)
     }
 
     fn get_current_environment_snapshot(&self) -> Result<String> {
@@ -0,0 +1,167 @@
+use chrono::{Duration, Local};
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fs;
+
+/// Words that make a request depend on the date or time
+const TIME_WORDS: &[&str] = &[
+    "recent",
+    "recently",
+    "ago",
+    "since",
+    "until",
+    "older",
+    "newer",
+    "minute",
+    "minutes",
+    "mins",
+    "hour",
+    "hours",
+    "hrs",
+    "day",
+    "days",
+    "daily",
+    "week",
+    "weeks",
+    "weekly",
+    "fortnight",
+    "month",
+    "months",
+    "monthly",
+    "year",
+    "years",
+    "morning",
+    "afternoon",
+    "evening",
+    "night",
+    "date",
+    "dated",
+    "modified",
+    "changed",
+    "created",
+];
+
+/// Words that name a particular day rather than a span back from now, so the
+/// answer changes from one day to the next
+const ANCHORED_WORDS: &[&str] = &[
+    "today",
+    "yesterday",
+    "tonight",
+    "tomorrow",
+    "midnight",
+    "noon",
+    "monday",
+    "tuesday",
+    "wednesday",
+    "thursday",
+    "friday",
+    "saturday",
+    "sunday",
+    "weekend",
+    "january",
+    "february",
+    "march",
+    "april",
+    "june",
+    "july",
+    "august",
+    "september",
+    "october",
+    "november",
+    "december",
+];
+
+/// The date, time zone, and locale, for requests like "logs since Monday"
+///
+/// Small models otherwise guess today's date, so `--since` and `-mtime`
+/// arguments come out wrong.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimeContext {
+    /// `Friday 2026-10-16 14:35`
+    pub now: String,
+    /// `+02:00`
+    pub utc_offset: String,
+    /// `Europe/Berlin`, when it can be found
+    pub timezone: Option<String>,
+    /// `de_DE.UTF-8`; decides how `date` and `ls` print dates
+    pub locale: Option<String>,
+    /// The six days before today, oldest first, as `Saturday 2026-10-10`
+    pub past_days: Vec<String>,
+}
+
+impl TimeContext {
+    pub fn now() -> Self {
+        let now = Local::now();
+        let today = now.date_naive();
+        let past_days = (1..=6)
+            .rev()
+            .map(|back| {
+                (today - Duration::days(back))
+                    .format("%A %Y-%m-%d")
+                    .to_string()
+            })
+            .collect();
+        Self {
+            now: now.format("%A %Y-%m-%d %H:%M").to_string(),
+            utc_offset: now.format("%:z").to_string(),
+            timezone: Self::timezone(),
+            locale: ["LC_ALL", "LC_TIME", "LANG"]
+                .iter()
+                .filter_map(|name| env::var(name).ok())
+                .find(|value| !value.is_empty()),
+            past_days,
+        }
+    }
+
+    /// Whether the request mentions dates or times at all
+    pub fn mentions_time(prompt: &str) -> bool {
+        Self::words(prompt).any(|word| {
+            TIME_WORDS.contains(&word.as_str()) || ANCHORED_WORDS.contains(&word.as_str())
+        })
+    }
+
+    /// Whether the request names a particular day, so yesterday's answer is wrong today
+    pub fn anchored(prompt: &str) -> bool {
+        Self::words(prompt).any(|word| ANCHORED_WORDS.contains(&word.as_str()))
+    }
+
+    /// Lines for the prompt
+    pub fn describe(&self) -> Vec<String> {
+        let zone = match &self.timezone {
+            Some(timezone) => format!("{timezone}, UTC{}", self.utc_offset),
+            None => format!("UTC{}", self.utc_offset),
+        };
+        let mut lines = vec![
+            format!("Now: {} ({zone})", self.now),
+            format!("Past days: {}", self.past_days.join(", ")),
+        ];
+        if let Some(locale) = &self.locale {
+            lines.push(format!("Locale: {locale}"));
+        }
+        lines
+    }
+
+    fn words(prompt: &str) -> impl Iterator<Item = String> + '_ {
+        prompt
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .map(str::to_lowercase)
+    }
+
+    /// `TZ`, else the zone `/etc/localtime` links to, else `/etc/timezone`
+    fn timezone() -> Option<String> {
+        if let Some(tz) = env::var("TZ").ok().filter(|tz| !tz.is_empty()) {
+            return Some(tz.trim_start_matches(':').to_string());
+        }
+        if let Ok(target) = fs::read_link("/etc/localtime") {
+            let target = target.to_string_lossy();
+            if let Some((_, zone)) = target.split_once("zoneinfo/") {
+                return Some(zone.to_string());
+            }
+        }
+        fs::read_to_string("/etc/timezone")
+            .ok()
+            .map(|zone| zone.trim().to_string())
+            .filter(|zone| !zone.is_empty())
+    }
+}
@@ -0,0 +1,68 @@
+use sha2::{Digest, Sha256};
+
+/// Dimensions of each embedding vector
+pub const EMBEDDING_DIMENSIONS: usize = 256;
+
+/// Words that say nothing about which command is wanted
+const STOP_WORDS: &[&str] = &[
+    "a", "an", "the", "to", "of", "in", "on", "for", "with", "and", "or", "all", "my", "me", "i",
+    "is", "it", "this", "that", "from", "by", "how", "do", "show", "get", "please",
+];
+
+/// Embeds short queries as dense vectors so similar ones can be found by cosine similarity
+///
+/// Words and character trigrams are hashed into a fixed number of buckets. This needs no
+/// model, is deterministic across runs, and still matches rephrasings and typos such as
+/// "list containers" / "show running docker containers".
+pub struct Embedder;
+
+impl Embedder {
+    pub fn embed(text: &str) -> Vec<f32> {
+        let mut vector = vec![0.0f32; EMBEDDING_DIMENSIONS];
+        let text = text.to_lowercase();
+        let words = text
+            .split(|c: char| !(c.is_alphanumeric() || c == '-' || c == '_'))
+            .filter(|w| !w.is_empty() && !STOP_WORDS.contains(w));
+
+        for word in words {
+            // Whole words carry more signal than fragments of them
+            Self::add_feature(&mut vector, word, 2.0);
+
+            let padded: Vec<char> = format!("<{word}>").chars().collect();
+            for trigram in padded.windows(3) {
+                let trigram: String = trigram.iter().collect();
+                Self::add_feature(&mut vector, &trigram, 1.0);
+            }
+        }
+
+        let norm = vector.iter().map(|v| v * v).sum::<f32>().sqrt();
+        if norm > 0.0 {
+            vector.iter_mut().for_each(|v| *v /= norm);
+        }
+        vector
+    }
+
+    /// Cosine similarity of two vectors from [`Embedder::embed`], which are already normalized
+    pub fn similarity(a: &[f32], b: &[f32]) -> f32 {
+        a.iter().zip(b).map(|(x, y)| x * y).sum()
+    }
+
+    pub fn to_bytes(vector: &[f32]) -> Vec<u8> {
+        vector.iter().flat_map(|v| v.to_le_bytes()).collect()
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Vec<f32> {
+        bytes
+            .chunks_exact(4)
+            .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+            .collect()
+    }
+
+    fn add_feature(vector: &mut [f32], feature: &str, weight: f32) {
+        let hash = Sha256::digest(feature.as_bytes());
+        let bucket = u16::from_le_bytes([hash[0], hash[1]]) as usize % EMBEDDING_DIMENSIONS;
+        // A second hash bit spreads collisions around zero instead of piling them up
+        let sign = if hash[2] & 1 == 0 { 1.0 } else { -1.0 };
+        vector[bucket] += sign * weight;
+    }
+}
@@ -0,0 +1,188 @@
+use serde::{Deserialize, Serialize};
+use std::io::Read;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+use crate::context::Category;
+
+/// Longest any one extractor may run before it is killed
+const EXTRACTOR_TIMEOUT: Duration = Duration::from_millis(1500);
+
+/// Names kept per entity kind
+const MAX_VALUES: usize = 15;
+
+/// A kind of concrete name a command might need
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EntityKind {
+    GitBranch,
+    DockerImage,
+    DockerContainer,
+    KubeContext,
+    KubeNamespace,
+    SystemdUnit,
+}
+
+impl EntityKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            EntityKind::GitBranch => "git branches",
+            EntityKind::DockerImage => "docker images",
+            EntityKind::DockerContainer => "docker containers",
+            EntityKind::KubeContext => "kubectl contexts",
+            EntityKind::KubeNamespace => "kubectl namespaces",
+            EntityKind::SystemdUnit => "systemd services",
+        }
+    }
+
+    /// Kinds worth gathering for a task category
+    pub fn for_category(category: Category) -> &'static [EntityKind] {
+        match category {
+            Category::Git => &[EntityKind::GitBranch],
+            Category::Containers => &[
+                EntityKind::DockerContainer,
+                EntityKind::DockerImage,
+                EntityKind::KubeContext,
+                EntityKind::KubeNamespace,
+            ],
+            Category::SystemAdmin => &[EntityKind::SystemdUnit],
+            _ => &[],
+        }
+    }
+
+    /// Guesses the kind a placeholder such as `<container_name>` stands for
+    pub fn for_placeholder(name: &str) -> Option<Self> {
+        let name = name.to_lowercase();
+        let has = |words: &[&str]| words.iter().any(|w| name.contains(w));
+        if has(&["branch"]) {
+            Some(EntityKind::GitBranch)
+        } else if has(&["container"]) {
+            Some(EntityKind::DockerContainer)
+        } else if has(&["image"]) {
+            Some(EntityKind::DockerImage)
+        } else if has(&["namespace"]) || name == "ns" {
+            Some(EntityKind::KubeNamespace)
+        } else if has(&["context", "cluster"]) {
+            Some(EntityKind::KubeContext)
+        } else if has(&["service", "unit"]) {
+            Some(EntityKind::SystemdUnit)
+        } else {
+            None
+        }
+    }
+
+    fn command(self) -> (&'static str, &'static [&'static str]) {
+        match self {
+            EntityKind::GitBranch => (
+                "git",
+                &["for-each-ref", "--format=%(refname:short)", "refs/heads"],
+            ),
+            EntityKind::DockerImage => (
+                "docker",
+                &["images", "--format", "{{.Repository}}:{{.Tag}}"],
+            ),
+            EntityKind::DockerContainer => ("docker", &["ps", "-a", "--format", "{{.Names}}"]),
+            EntityKind::KubeContext => ("kubectl", &["config", "get-contexts", "-o", "name"]),
+            EntityKind::KubeNamespace => (
+                "kubectl",
+                &["get", "namespaces", "-o", "name", "--request-timeout=1s"],
+            ),
+            EntityKind::SystemdUnit => (
+                "systemctl",
+                &["list-units", "--type=service", "--no-legend", "--plain"],
+            ),
+        }
+    }
+
+    fn parse(self, output: &str) -> Vec<String> {
+        output
+            .lines()
+            .filter_map(|line| match self {
+                EntityKind::KubeNamespace => line.strip_prefix("namespace/"),
+                // First column is the unit name
+                EntityKind::SystemdUnit => line.split_whitespace().next(),
+                _ => Some(line),
+            })
+            .map(str::trim)
+            .filter(|value| !value.is_empty() && !value.contains("<none>"))
+            .take(MAX_VALUES)
+            .map(String::from)
+            .collect()
+    }
+}
+
+/// Names of one kind found on this machine
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EntitySet {
+    pub kind: EntityKind,
+    pub values: Vec<String>,
+}
+
+/// Gathers the branches, images, namespaces, and units a suggestion might refer to
+pub struct EntityExtractor;
+
+impl EntityExtractor {
+    /// Entities relevant to a category, gathered in parallel
+    pub fn gather(category: Category) -> Vec<EntitySet> {
+        let handles: Vec<_> = EntityKind::for_category(category)
+            .iter()
+            .map(|&kind| thread::spawn(move || Self::extract(kind)))
+            .collect();
+
+        handles
+            .into_iter()
+            .filter_map(|handle| handle.join().ok().flatten())
+            .collect()
+    }
+
+    /// Runs one extractor; `None` when the tool is missing, fails, or finds nothing
+    pub fn extract(kind: EntityKind) -> Option<EntitySet> {
+        let (program, args) = kind.command();
+        which::which(program).ok()?;
+
+        let output = run_with_timeout(program, args, EXTRACTOR_TIMEOUT)?;
+        let values = kind.parse(&output);
+        debug!(
+            kind = kind.label(),
+            count = values.len(),
+            "Extracted entities"
+        );
+        (!values.is_empty()).then_some(EntitySet { kind, values })
+    }
+}
+
+/// Runs a command and returns its stdout, killing it if it outlives `timeout`
+fn run_with_timeout(program: &str, args: &[&str], timeout: Duration) -> Option<String> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .ok()?;
+
+    let mut stdout = child.stdout.take()?;
+    let reader = thread::spawn(move || {
+        let mut output = String::new();
+        stdout.read_to_string(&mut output).ok().map(|_| output)
+    });
+
+    let started = Instant::now();
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) if status.success() => break,
+            Ok(Some(_)) | Err(_) => return None,
+            Ok(None) if started.elapsed() > timeout => {
+                debug!(program, "Entity extractor timed out");
+                let _ = child.kill();
+                let _ = child.wait();
+                return None;
+            }
+            Ok(None) => thread::sleep(Duration::from_millis(20)),
+        }
+    }
+
+    reader.join().ok().flatten()
+}
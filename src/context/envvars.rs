@@ -0,0 +1,162 @@
+use regex::Regex;
+
+/// Names kept in one prompt
+const MAX_NAMES: usize = 20;
+
+/// Variables that say a tool is configured, and the words in a query that make them relevant
+///
+/// A name ending in `*` matches every variable with that prefix.
+const KNOWN: &[(&str, &[&str])] = &[
+    (
+        "AWS_*",
+        &[
+            "aws",
+            "s3",
+            "ec2",
+            "lambda",
+            "ecr",
+            "ecs",
+            "eks",
+            "iam",
+            "cloudwatch",
+        ],
+    ),
+    (
+        "KUBECONFIG",
+        &[
+            "kubectl",
+            "kubernetes",
+            "k8s",
+            "pod",
+            "pods",
+            "helm",
+            "cluster",
+            "namespace",
+        ],
+    ),
+    ("HELM_*", &["helm", "chart", "release"]),
+    (
+        "GOOGLE_APPLICATION_CREDENTIALS",
+        &["gcloud", "gcp", "gcs", "gke", "bigquery", "google"],
+    ),
+    ("CLOUDSDK_*", &["gcloud", "gcp", "gke", "google"]),
+    ("AZURE_*", &["az", "azure", "aks", "blob"]),
+    ("ARM_*", &["azure", "terraform"]),
+    ("DIGITALOCEAN_*", &["doctl", "digitalocean", "droplet"]),
+    (
+        "DATABASE_URL",
+        &[
+            "database",
+            "db",
+            "sql",
+            "migrate",
+            "migration",
+            "psql",
+            "mysql",
+        ],
+    ),
+    (
+        "PG*",
+        &[
+            "postgres",
+            "postgresql",
+            "psql",
+            "pg_dump",
+            "database",
+            "db",
+        ],
+    ),
+    ("MYSQL_*", &["mysql", "mariadb", "database", "db"]),
+    ("REDIS_URL", &["redis", "cache"]),
+    ("MONGODB_URI", &["mongo", "mongodb", "mongosh"]),
+    (
+        "DOCKER_*",
+        &["docker", "container", "containers", "image", "compose"],
+    ),
+    ("CONTAINER_HOST", &["podman", "container", "containers"]),
+    ("GITHUB_TOKEN", &["gh", "github", "pr", "release", "issue"]),
+    ("GH_*", &["gh", "github", "pr", "release", "issue"]),
+    ("GITLAB_TOKEN", &["glab", "gitlab", "mr", "pipeline"]),
+    ("NPM_TOKEN", &["npm", "publish", "package"]),
+    ("CARGO_REGISTRY_TOKEN", &["cargo", "publish", "crate"]),
+    ("TWINE_*", &["twine", "pypi", "publish", "upload"]),
+    ("TF_*", &["terraform", "tf", "plan", "apply"]),
+    ("VAULT_*", &["vault", "secret", "secrets"]),
+    ("CONSUL_*", &["consul"]),
+    ("NOMAD_*", &["nomad"]),
+    ("ANSIBLE_*", &["ansible", "playbook"]),
+    (
+        "SSH_AUTH_SOCK",
+        &["ssh", "scp", "rsync", "git", "agent", "key"],
+    ),
+    ("GPG_TTY", &["gpg", "sign", "signing"]),
+    (
+        "HTTP_PROXY",
+        &["proxy", "curl", "wget", "download", "pip", "npm", "apt"],
+    ),
+    (
+        "HTTPS_PROXY",
+        &["proxy", "curl", "wget", "download", "pip", "npm", "apt"],
+    ),
+    ("NO_PROXY", &["proxy"]),
+    (
+        "VIRTUAL_ENV",
+        &["python", "pip", "venv", "virtualenv", "pytest"],
+    ),
+    (
+        "CONDA_DEFAULT_ENV",
+        &["conda", "python", "pip", "environment"],
+    ),
+    ("JAVA_HOME", &["java", "maven", "mvn", "gradle", "jar"]),
+    ("GOPATH", &["go", "golang"]),
+    ("CUDA_VISIBLE_DEVICES", &["gpu", "cuda", "nvidia", "torch"]),
+];
+
+/// Names of configured environment variables that bear on a query
+///
+/// Only names leave this type: values are dropped as the environment is listed,
+/// and a name that doesn't look like a plain variable name is skipped rather
+/// than risk it holding something secret.
+pub struct EnvInventory;
+
+impl EnvInventory {
+    /// Exported variable names relevant to the prompt, sorted
+    pub fn names_for(prompt: &str) -> Vec<String> {
+        let words: Vec<String> = prompt
+            .to_lowercase()
+            .split(|c: char| !c.is_alphanumeric() && c != '_' && c != '-')
+            .filter(|word| !word.is_empty())
+            .map(String::from)
+            .collect();
+        let relevant: Vec<&str> = KNOWN
+            .iter()
+            .filter(|(_, keywords)| keywords.iter().any(|k| words.iter().any(|w| w == k)))
+            .map(|(name, _)| *name)
+            .collect();
+        if relevant.is_empty() {
+            return Vec::new();
+        }
+
+        let mut names: Vec<String> = Self::exported_names()
+            .into_iter()
+            .filter(|name| {
+                relevant.iter().any(|known| match known.strip_suffix('*') {
+                    Some(prefix) => name.starts_with(prefix),
+                    None => name == known,
+                })
+            })
+            .collect();
+        names.sort();
+        names.truncate(MAX_NAMES);
+        names
+    }
+
+    /// Names of every exported variable that looks like an ordinary name
+    fn exported_names() -> Vec<String> {
+        let valid = Regex::new(r"^[A-Z][A-Z0-9_]{0,63}$").expect("valid regex");
+        std::env::vars_os()
+            .filter_map(|(name, _)| name.into_string().ok())
+            .filter(|name| valid.is_match(name))
+            .collect()
+    }
+}
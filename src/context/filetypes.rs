@@ -0,0 +1,157 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Directory levels below the current one that are counted
+const MAX_DEPTH: usize = 4;
+
+/// Entries looked at before counting stops, so a huge tree stays quick
+const MAX_ENTRIES: usize = 10_000;
+
+/// Extensions kept in one prompt, most common first; known names are always kept
+const MAX_EXTENSIONS: usize = 8;
+
+/// Dependencies, build output, and environments that say nothing about the project
+const SKIPPED_DIRS: &[&str] = &[
+    "node_modules",
+    "target",
+    "vendor",
+    "dist",
+    "build",
+    "venv",
+    "__pycache__",
+];
+
+/// File names that pick a tool by themselves, counted by name instead of extension
+const KNOWN_NAMES: &[&str] = &[
+    "Dockerfile",
+    "Containerfile",
+    "docker-compose.yml",
+    "docker-compose.yaml",
+    "compose.yml",
+    "compose.yaml",
+    "Makefile",
+    "justfile",
+    "Taskfile.yml",
+    "CMakeLists.txt",
+    "Cargo.toml",
+    "package.json",
+    "go.mod",
+    "pyproject.toml",
+    "requirements.txt",
+    "Gemfile",
+    "pom.xml",
+    "build.gradle",
+    "Jenkinsfile",
+    "Vagrantfile",
+    "Chart.yaml",
+];
+
+/// How many files of one kind are under the current directory
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileTypeCount {
+    /// An extension such as `.py`, or a name from `KNOWN_NAMES`
+    pub kind: String,
+    pub count: usize,
+}
+
+impl FileTypeCount {
+    /// `312 .py files`, `4 Dockerfiles`, `1 docker-compose.yml`
+    pub fn describe(&self) -> String {
+        let plural = self.count != 1;
+        if self.kind.starts_with('.') {
+            let noun = if plural { "files" } else { "file" };
+            format!("{} {} {noun}", self.count, self.kind)
+        } else if !self.kind.contains('.') && plural {
+            format!("{} {}s", self.count, self.kind)
+        } else if plural {
+            format!("{} {} files", self.count, self.kind)
+        } else {
+            format!("1 {}", self.kind)
+        }
+    }
+}
+
+/// Counts files under the current directory by extension, so the model can tell
+/// a Python project from a Rust one without seeing any file names
+pub struct FileTypeScanner;
+
+impl FileTypeScanner {
+    /// The most common kinds of file under `dir`, most common first
+    pub fn scan(dir: &Path) -> Vec<FileTypeCount> {
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        let mut pending: Vec<(PathBuf, usize)> = vec![(dir.to_path_buf(), 0)];
+        let mut seen = 0;
+
+        while let Some((dir, depth)) = pending.pop() {
+            let Ok(entries) = fs::read_dir(&dir) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                seen += 1;
+                if seen > MAX_ENTRIES {
+                    return Self::top(counts);
+                }
+                let name = entry.file_name().to_string_lossy().to_string();
+                if name.starts_with('.') {
+                    continue;
+                }
+                let Ok(file_type) = entry.file_type() else {
+                    continue;
+                };
+                if file_type.is_dir() {
+                    if depth < MAX_DEPTH && !SKIPPED_DIRS.contains(&name.as_str()) {
+                        pending.push((entry.path(), depth + 1));
+                    }
+                } else if file_type.is_file() {
+                    if let Some(kind) = Self::kind(&name) {
+                        *counts.entry(kind).or_default() += 1;
+                    }
+                }
+            }
+        }
+
+        Self::top(counts)
+    }
+
+    /// The current directory's counts, as a prompt line: `312 .py files, 4 Dockerfiles`
+    pub fn describe(counts: &[FileTypeCount]) -> String {
+        counts
+            .iter()
+            .map(FileTypeCount::describe)
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    fn kind(name: &str) -> Option<String> {
+        if let Some(known) = KNOWN_NAMES.iter().find(|known| **known == name) {
+            return Some(known.to_string());
+        }
+        // Dockerfile.dev, api.Dockerfile
+        if name.starts_with("Dockerfile.") || name.ends_with(".Dockerfile") {
+            return Some("Dockerfile".to_string());
+        }
+
+        let (stem, extension) = name.rsplit_once('.')?;
+        let plausible = !stem.is_empty()
+            && (1..=10).contains(&extension.len())
+            && extension.chars().all(|c| c.is_ascii_alphanumeric());
+        plausible.then(|| format!(".{}", extension.to_ascii_lowercase()))
+    }
+
+    fn top(counts: HashMap<String, usize>) -> Vec<FileTypeCount> {
+        let (names, mut extensions): (Vec<FileTypeCount>, Vec<FileTypeCount>) = counts
+            .into_iter()
+            .map(|(kind, count)| FileTypeCount { kind, count })
+            .partition(|c| !c.kind.starts_with('.'));
+        extensions.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.kind.cmp(&b.kind)));
+        extensions.truncate(MAX_EXTENSIONS);
+
+        // A lone Dockerfile decides as much as hundreds of source files
+        let mut counts = names;
+        counts.extend(extensions);
+        counts.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.kind.cmp(&b.kind)));
+        counts
+    }
+}
@@ -0,0 +1,186 @@
+/// A language a request can be written in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Language {
+    /// ISO 639-1 code, e.g. "es"
+    pub code: &'static str,
+    /// English name, as prompts name it
+    pub name: &'static str,
+}
+
+/// Words common in requests in each language written in the Latin alphabet,
+/// separated by spaces, English included so an English request isn't taken for another
+const STOPWORDS: &[(Language, &str)] = &[
+    (
+        ENGLISH,
+        "the a an of in on for with all and to from by my that this is than how files \
+            show find list larger bigger every which into are",
+    ),
+    (
+        Language {
+            code: "es",
+            name: "Spanish",
+        },
+        "el la los las de del en un una que con por para cómo todos todas archivos \
+            carpeta mostrar muestra buscar busca encontrar encuentra y mis este esta más \
+            mayores sin listar lista directorio",
+    ),
+    (
+        Language {
+            code: "pt",
+            name: "Portuguese",
+        },
+        "o os as de do da dos das em no na um uma que com para por como todos arquivos \
+            pasta mostrar mostre encontrar encontre e meus não mais maiores diretório",
+    ),
+    (
+        Language {
+            code: "fr",
+            name: "French",
+        },
+        "le la les de des du un une et dans pour avec sur tous toutes fichiers dossier \
+            afficher affiche trouver trouve qui est mes ce cette plus sans lister \
+            répertoire",
+    ),
+    (
+        Language {
+            code: "de",
+            name: "German",
+        },
+        "der die das den dem des ein eine und mit für von im alle dateien ordner \
+            verzeichnis zeige zeigen finde finden auf ist nicht meine größer als wie",
+    ),
+    (
+        Language {
+            code: "it",
+            name: "Italian",
+        },
+        "il lo la gli le di del della un una e con per nella nel tutti tutte cartella \
+            mostra mostrami trova che miei più elenca maggiori come",
+    ),
+    (
+        Language {
+            code: "nl",
+            name: "Dutch",
+        },
+        "de het een en van met voor op alle bestanden map toon zoek vind die dat niet \
+            mijn groter dan hoe",
+    ),
+];
+
+const ENGLISH: Language = Language {
+    code: "en",
+    name: "English",
+};
+
+/// Stopwords a request needs before it is taken to be in another language
+const MIN_STOPWORDS: usize = 2;
+
+/// Tells which language a request is written in, so one in another language
+/// can be put in English for the model and answered in its own
+pub struct LanguageDetector;
+
+impl LanguageDetector {
+    /// The request's language, or `None` for English or when it can't tell
+    ///
+    /// Other alphabets are told by their letters; languages written in the
+    /// Latin alphabet by their common words. Paths, flags, and quoted text
+    /// are left out, since they read the same in any language.
+    pub fn detect(request: &str) -> Option<Language> {
+        let words: Vec<String> = Self::words(request);
+        if let Some(language) = Self::by_script(&words) {
+            return Some(language);
+        }
+
+        let count = |list: &str| {
+            words
+                .iter()
+                .filter(|w| list.split_whitespace().any(|s| s == w.as_str()))
+                .count()
+        };
+        let english = count(STOPWORDS[0].1);
+        let (language, hits) = STOPWORDS[1..]
+            .iter()
+            .map(|(language, list)| (*language, count(list)))
+            .max_by_key(|(_, hits)| *hits)?;
+        (hits >= MIN_STOPWORDS && hits > english).then_some(language)
+    }
+
+    /// The request's words in lowercase, without paths, flags, and quoted text
+    fn words(request: &str) -> Vec<String> {
+        let mut words = Vec::new();
+        let mut quoted = None;
+        for token in request.split_whitespace() {
+            if let Some(quote) = quoted {
+                if token.ends_with(quote) {
+                    quoted = None;
+                }
+                continue;
+            }
+            if let Some(quote) = token
+                .chars()
+                .next()
+                .filter(|c| matches!(c, '"' | '\'' | '`'))
+            {
+                if token.len() == 1 || !token[1..].ends_with(quote) {
+                    quoted = Some(quote);
+                }
+                continue;
+            }
+            if token.starts_with('-') || token.contains(['/', '=', '~', '$', '*']) {
+                continue;
+            }
+            let word: String = token
+                .trim_matches(|c: char| !c.is_alphanumeric())
+                .to_lowercase();
+            if !word.is_empty() {
+                words.push(word);
+            }
+        }
+        words
+    }
+
+    /// The language of a request mostly written in another alphabet
+    fn by_script(words: &[String]) -> Option<Language> {
+        let letters: Vec<char> = words
+            .iter()
+            .flat_map(|w| w.chars())
+            .filter(|c| c.is_alphabetic())
+            .collect();
+        let latin = letters.iter().filter(|c| c.is_ascii_alphabetic()).count();
+        let other = letters.len() - latin;
+        if other == 0 || other * 2 < letters.len() {
+            return None;
+        }
+
+        let any = |range: std::ops::RangeInclusive<char>| letters.iter().any(|c| range.contains(c));
+        let has = |chars: &str| letters.iter().any(|c| chars.contains(*c));
+        let (code, name) = if any('\u{3040}'..='\u{30ff}') {
+            ("ja", "Japanese")
+        } else if any('\u{ac00}'..='\u{d7af}') || any('\u{1100}'..='\u{11ff}') {
+            ("ko", "Korean")
+        } else if any('\u{4e00}'..='\u{9fff}') {
+            ("zh", "Chinese")
+        } else if any('\u{0400}'..='\u{04ff}') {
+            match has("іїєґ") {
+                true => ("uk", "Ukrainian"),
+                false => ("ru", "Russian"),
+            }
+        } else if any('\u{0370}'..='\u{03ff}') {
+            ("el", "Greek")
+        } else if any('\u{0600}'..='\u{06ff}') {
+            match has("پچژگ") {
+                true => ("fa", "Persian"),
+                false => ("ar", "Arabic"),
+            }
+        } else if any('\u{0590}'..='\u{05ff}') {
+            ("he", "Hebrew")
+        } else if any('\u{0900}'..='\u{097f}') {
+            ("hi", "Hindi")
+        } else if any('\u{0e00}'..='\u{0e7f}') {
+            ("th", "Thai")
+        } else {
+            return None;
+        };
+        Some(Language { code, name })
+    }
+}
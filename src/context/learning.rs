@@ -0,0 +1,564 @@
+use anyhow::{Context, Result};
+use chrono::Utc;
+use regex::Regex;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::{debug, info};
+
+use crate::context::{Category, Embedder, TaxonomyClassifier};
+use crate::normalize::CommandNormalizer;
+
+/// Schema migrations, applied in order; `PRAGMA user_version` records how many ran
+const MIGRATIONS: &[&str] = &[
+    include_str!("../../sql/learning/001_initial.sql"),
+    include_str!("../../sql/learning/002_pattern_embeddings.sql"),
+    include_str!("../../sql/learning/003_rules.sql"),
+    include_str!("../../sql/learning/004_experiments.sql"),
+];
+
+/// Least cosine similarity for a past query to count as related
+const MIN_SIMILARITY: f32 = 0.3;
+
+/// Patterns kept before the least useful ones are dropped
+const MAX_PATTERNS: usize = 5000;
+
+/// Queries, suggestions, and feedback older than this are pruned
+const HISTORY_RETENTION_DAYS: i64 = 180;
+
+/// Older patterns of one category summarized into rules at a time
+const SUMMARY_BATCH: usize = 40;
+
+/// Fewest older patterns of a category worth a summary
+const MIN_SUMMARY_BATCH: usize = 10;
+
+/// Executables too common to be worth learning as patterns
+const TRIVIAL_EXECUTABLES: &[&str] = &["ls", "cd", "pwd", "echo", "cat", "grep"];
+
+/// A prompt and the command learned for it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LearnedPattern {
+    pub prompt: String,
+    pub command: String,
+    pub category: Category,
+    /// The user ran the command successfully, rather than only being shown it
+    pub accepted: bool,
+    pub hits: u32,
+}
+
+/// How one arm of a prompt experiment has done
+#[derive(Debug, Clone)]
+pub struct ArmStats {
+    pub experiment: String,
+    pub arm: String,
+    /// Queries this arm generated suggestions for
+    pub runs: u32,
+    /// Queries where the user ran or copied one of them
+    pub accepted: u32,
+    /// Queries where a command the user ran exited successfully
+    pub succeeded: u32,
+}
+
+impl ArmStats {
+    pub fn acceptance_rate(&self) -> f32 {
+        match self.runs {
+            0 => 0.0,
+            runs => self.accepted as f32 / runs as f32,
+        }
+    }
+}
+
+/// SQLite store for learned context, replacing the old PHLOEM.md text file
+pub struct LearningStore {
+    connection: Connection,
+    path: PathBuf,
+}
+
+impl LearningStore {
+    /// Opens (creating if needed) the store and brings its schema up to date
+    pub fn open(path: &Path) -> Result<Self> {
+        let connection = Connection::open(path)
+            .with_context(|| format!("Failed to open learning store {}", path.display()))?;
+        // `commandy serve` may hold several handles at once
+        connection.busy_timeout(Duration::from_secs(5))?;
+        connection.execute_batch("PRAGMA foreign_keys = ON;")?;
+
+        let store = Self {
+            connection,
+            path: path.to_path_buf(),
+        };
+        store.migrate()?;
+        store.backfill_embeddings()?;
+        store.prune_history()?;
+        Ok(store)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn schema_version(&self) -> Result<usize> {
+        let version: i64 = self
+            .connection
+            .query_row("PRAGMA user_version", [], |row| row.get(0))?;
+        Ok(version as usize)
+    }
+
+    fn migrate(&self) -> Result<()> {
+        let current = self.schema_version()?;
+        for (index, migration) in MIGRATIONS.iter().enumerate().skip(current) {
+            let version = index + 1;
+            debug!("Applying learning store migration {version}");
+            // user_version can't be bound as a parameter
+            self.connection.execute_batch(&format!(
+                "BEGIN;\n{migration}\nPRAGMA user_version = {version};\nCOMMIT;"
+            ))?;
+        }
+        Ok(())
+    }
+
+    /// Records a suggestion shown for a prompt
+    pub fn record_suggestion(&self, prompt: &str, command: &str, source: &str) -> Result<()> {
+        let query_id = self.upsert_query(prompt)?;
+        self.connection.execute(
+            "INSERT OR IGNORE INTO suggestions (query_id, command, source) VALUES (?1, ?2, ?3)",
+            params![query_id, command, source],
+        )?;
+        self.upsert_pattern(prompt, command, false)
+    }
+
+    /// Records whether a suggested command worked; successes become accepted patterns
+    pub fn record_feedback(
+        &self,
+        prompt: &str,
+        command: &str,
+        success: bool,
+        exit_code: Option<i32>,
+    ) -> Result<()> {
+        let query_id = self.upsert_query(prompt)?;
+        self.connection.execute(
+            "INSERT INTO feedback (query_id, command, success, exit_code) VALUES (?1, ?2, ?3, ?4)",
+            params![query_id, command, success, exit_code],
+        )?;
+
+        let executable = command.split_whitespace().next().unwrap_or_default();
+        if success && !TRIVIAL_EXECUTABLES.contains(&executable) {
+            self.upsert_pattern(prompt, command, true)?;
+        }
+        Ok(())
+    }
+
+    /// Accepted patterns whose prompts are most similar to `prompt`, best first
+    pub fn similar(&self, prompt: &str, limit: usize) -> Result<Vec<LearnedPattern>> {
+        let query = Embedder::embed(prompt);
+        let mut stmt = self.connection.prepare(
+            "SELECT prompt, command, category, accepted, hits, embedding FROM patterns
+             WHERE accepted AND embedding IS NOT NULL",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            let embedding: Vec<u8> = row.get(5)?;
+            Ok((Self::pattern_from_row(row)?, embedding))
+        })?;
+
+        let mut scored = Vec::new();
+        for row in rows {
+            let (pattern, embedding) = row?;
+            let similarity = Embedder::similarity(&query, &Embedder::from_bytes(&embedding));
+            if similarity >= MIN_SIMILARITY {
+                scored.push((similarity, pattern));
+            }
+        }
+        // Equal scores go to the pattern used more often
+        scored.sort_by(|(a, pa), (b, pb)| b.total_cmp(a).then(pb.hits.cmp(&pa.hits)));
+        debug!(
+            candidates = scored.len(),
+            best = scored.first().map(|(s, _)| *s),
+            "Retrieved learned patterns"
+        );
+
+        Ok(scored
+            .into_iter()
+            .take(limit)
+            .map(|(_, pattern)| pattern)
+            .collect())
+    }
+
+    /// The oldest accepted patterns of one category beyond the newest `keep`,
+    /// when there are enough of them to summarize
+    pub fn summary_batch(&self, keep: usize) -> Result<Option<(Category, Vec<LearnedPattern>)>> {
+        const OLDER: &str = "SELECT id FROM patterns WHERE accepted
+             ORDER BY last_seen DESC, id DESC LIMIT -1 OFFSET ?1";
+        let busiest: Option<(String, i64)> = self
+            .connection
+            .query_row(
+                &format!(
+                    "SELECT category, COUNT(*) AS count FROM patterns WHERE id IN ({OLDER})
+                     GROUP BY category ORDER BY count DESC LIMIT 1"
+                ),
+                params![keep as i64],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+        let Some((category, count)) = busiest else {
+            return Ok(None);
+        };
+        if (count as usize) < MIN_SUMMARY_BATCH {
+            return Ok(None);
+        }
+
+        let patterns = self.query_patterns(
+            &format!(
+                "SELECT prompt, command, category, accepted, hits FROM patterns
+                 WHERE category = ?2 AND id IN ({OLDER})
+                 ORDER BY last_seen, id LIMIT ?3"
+            ),
+            params![keep as i64, category, SUMMARY_BATCH as i64],
+        )?;
+        Ok(Some((
+            Category::from_name(&category).unwrap_or_default(),
+            patterns,
+        )))
+    }
+
+    /// Stores rules summarizing `patterns` and drops the patterns they replace
+    pub fn replace_with_rules(
+        &self,
+        category: Category,
+        rules: &[String],
+        patterns: &[LearnedPattern],
+    ) -> Result<()> {
+        let transaction = self.connection.unchecked_transaction()?;
+        for rule in rules {
+            transaction.execute(
+                "INSERT INTO rules (category, rule, patterns) VALUES (?1, ?2, ?3)",
+                params![category.slug(), rule, patterns.len() as i64],
+            )?;
+        }
+        for pattern in patterns {
+            transaction.execute(
+                "DELETE FROM patterns WHERE prompt = ?1 AND command = ?2",
+                params![pattern.prompt, pattern.command],
+            )?;
+        }
+        transaction.commit()?;
+        info!(
+            "Summarized {} {} patterns into {} rules",
+            patterns.len(),
+            category.label(),
+            rules.len()
+        );
+        Ok(())
+    }
+
+    /// Rules learned for a category, newest first
+    pub fn rules_for(&self, category: Category, limit: usize) -> Result<Vec<String>> {
+        let mut stmt = self.connection.prepare(
+            "SELECT rule FROM rules WHERE category = ?1 ORDER BY created_at DESC, id DESC LIMIT ?2",
+        )?;
+        let rules = stmt
+            .query_map(params![category.slug(), limit as i64], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<String>>>()?;
+        Ok(rules)
+    }
+
+    /// Every rule with its category, for export
+    pub fn rules(&self) -> Result<Vec<(Category, String)>> {
+        let mut stmt = self
+            .connection
+            .prepare("SELECT category, rule FROM rules ORDER BY category, id")?;
+        let rules = stmt
+            .query_map([], |row| {
+                let category: String = row.get(0)?;
+                Ok((
+                    Category::from_name(&category).unwrap_or_default(),
+                    row.get(1)?,
+                ))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(rules)
+    }
+
+    /// Every pattern, grouped by category
+    pub fn patterns(&self) -> Result<Vec<LearnedPattern>> {
+        self.query_patterns(
+            "SELECT prompt, command, category, accepted, hits FROM patterns
+             ORDER BY category, accepted DESC, hits DESC, last_seen DESC",
+            [],
+        )
+    }
+
+    /// Merges patterns from another machine, adding their hit counts to any local copy
+    pub fn import_patterns(&self, patterns: &[LearnedPattern]) -> Result<usize> {
+        for pattern in patterns {
+            self.connection.execute(
+                "INSERT INTO patterns (prompt, command, category, accepted, hits, embedding)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+                 ON CONFLICT(prompt, command) DO UPDATE SET
+                    hits = hits + excluded.hits,
+                    accepted = accepted OR excluded.accepted",
+                params![
+                    pattern.prompt,
+                    self.stored_spelling(&pattern.prompt, &pattern.command)?,
+                    pattern.category.slug(),
+                    pattern.accepted,
+                    pattern.hits,
+                    Embedder::to_bytes(&Embedder::embed(&pattern.prompt))
+                ],
+            )?;
+        }
+        self.prune_patterns()?;
+        Ok(patterns.len())
+    }
+
+    /// Plain-text rendering of what has been learned, one section per category
+    pub fn export_text(&self) -> Result<String> {
+        let mut by_category: BTreeMap<&'static str, Vec<LearnedPattern>> = BTreeMap::new();
+        for pattern in self.patterns()? {
+            by_category
+                .entry(pattern.category.label())
+                .or_default()
+                .push(pattern);
+        }
+
+        let mut text = format!(
+            "# Commandy learned context\nExported: {}\n",
+            Utc::now().format("%Y-%m-%d %H:%M UTC")
+        );
+        let mut rules: BTreeMap<&'static str, Vec<String>> = BTreeMap::new();
+        for (category, rule) in self.rules()? {
+            rules.entry(category.label()).or_default().push(rule);
+        }
+        for (label, rules) in rules {
+            text.push_str(&format!("\n## {label} (summarized)\n"));
+            for rule in rules {
+                text.push_str(&format!("- {rule}\n"));
+            }
+        }
+        for (label, patterns) in by_category {
+            text.push_str(&format!("\n## {label}\n"));
+            for pattern in patterns {
+                let status = if pattern.accepted { "✓" } else { "→" };
+                text.push_str(&format!(
+                    "{status} \"{}\" → `{}` (seen {}×)\n",
+                    pattern.prompt, pattern.command, pattern.hits
+                ));
+            }
+        }
+        Ok(text)
+    }
+
+    /// Imports patterns from a PHLOEM.md written by older versions
+    pub fn import_legacy_text(&self, text: &str) -> Result<usize> {
+        let suggested = Regex::new(r#"User prompt: "(.+)"\n→ Suggested: `(.+)`"#)?;
+        let executed = Regex::new(r#"(?m)^"(.+)" → `(.+)`$"#)?;
+        let validated = Regex::new(r#"Context: "(.+)"\nFull command: `(.+)`"#)?;
+
+        let mut imported = 0;
+        for (regex, accepted) in [(&suggested, false), (&executed, true), (&validated, true)] {
+            for captures in regex.captures_iter(text) {
+                self.upsert_pattern(&captures[1], &captures[2], accepted)?;
+                imported += 1;
+            }
+        }
+        Ok(imported)
+    }
+
+    /// One-time move of a legacy PHLOEM.md into the store; the file is kept in `backup_dir`
+    pub fn migrate_legacy_file(&self, legacy: &Path, backup_dir: &Path) -> Result<()> {
+        if !legacy.exists() {
+            return Ok(());
+        }
+
+        let text = std::fs::read_to_string(legacy)?;
+        let imported = self.import_legacy_text(&text)?;
+        std::fs::create_dir_all(backup_dir)?;
+        std::fs::rename(legacy, backup_dir.join("PHLOEM_pre_sqlite.md"))?;
+        info!("Imported {imported} learned patterns from {legacy:?}");
+        Ok(())
+    }
+
+    pub fn clear(&self) -> Result<()> {
+        self.connection.execute_batch(
+            "DELETE FROM feedback; DELETE FROM suggestions; DELETE FROM queries; DELETE FROM patterns;
+             DELETE FROM rules; DELETE FROM experiment_runs;",
+        )?;
+        Ok(())
+    }
+
+    /// Records that `arm` of `experiment` answered `prompt`; returns the run's id
+    pub fn record_experiment_run(&self, experiment: &str, arm: &str, prompt: &str) -> Result<i64> {
+        self.connection.execute(
+            "INSERT INTO experiment_runs (experiment, arm, prompt) VALUES (?1, ?2, ?3)",
+            params![experiment, arm, prompt],
+        )?;
+        Ok(self.connection.last_insert_rowid())
+    }
+
+    /// Marks a run's suggestions as used, and as working when `success` is true
+    pub fn record_experiment_outcome(&self, run: i64, success: bool) -> Result<()> {
+        self.connection.execute(
+            "UPDATE experiment_runs SET accepted = TRUE, succeeded = succeeded OR ?2
+             WHERE id = ?1",
+            params![run, success],
+        )?;
+        Ok(())
+    }
+
+    /// Runs, acceptances, and successes for every arm, by experiment and arm
+    pub fn experiment_stats(&self) -> Result<Vec<ArmStats>> {
+        let mut stmt = self.connection.prepare(
+            "SELECT experiment, arm, COUNT(*), SUM(accepted), SUM(succeeded)
+             FROM experiment_runs
+             GROUP BY experiment, arm
+             ORDER BY experiment, arm",
+        )?;
+        let stats = stmt
+            .query_map([], |row| {
+                Ok(ArmStats {
+                    experiment: row.get(0)?,
+                    arm: row.get(1)?,
+                    runs: row.get(2)?,
+                    accepted: row.get(3)?,
+                    succeeded: row.get(4)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(stats)
+    }
+
+    fn upsert_query(&self, prompt: &str) -> Result<i64> {
+        let category = TaxonomyClassifier::classify(prompt).category;
+        self.connection.execute(
+            "INSERT INTO queries (prompt, category) VALUES (?1, ?2)
+             ON CONFLICT(prompt) DO UPDATE SET
+                ask_count = ask_count + 1,
+                last_asked = CURRENT_TIMESTAMP",
+            params![prompt, category.slug()],
+        )?;
+        let id = self.connection.query_row(
+            "SELECT id FROM queries WHERE prompt = ?1",
+            params![prompt],
+            |row| row.get(0),
+        )?;
+        Ok(id)
+    }
+
+    fn upsert_pattern(&self, prompt: &str, command: &str, accepted: bool) -> Result<()> {
+        let command = &self.stored_spelling(prompt, command)?;
+        let category = TaxonomyClassifier::classify(prompt).category;
+        self.connection.execute(
+            "INSERT INTO patterns (prompt, command, category, accepted, embedding)
+             VALUES (?1, ?2, ?3, ?4, ?5)
+             ON CONFLICT(prompt, command) DO UPDATE SET
+                hits = hits + 1,
+                accepted = accepted OR excluded.accepted,
+                last_seen = CURRENT_TIMESTAMP",
+            params![
+                prompt,
+                command,
+                category.slug(),
+                accepted,
+                Embedder::to_bytes(&Embedder::embed(prompt))
+            ],
+        )?;
+        self.prune_patterns()
+    }
+
+    /// The spelling of a pattern already learned for `prompt` that is the same command
+    ///
+    /// Spelling variants (`ls -la`, `ls  -al;`) then count as hits on one pattern.
+    fn stored_spelling(&self, prompt: &str, command: &str) -> Result<String> {
+        let mut stmt = self
+            .connection
+            .prepare("SELECT command FROM patterns WHERE prompt = ?1")?;
+        let learned = stmt
+            .query_map([prompt], |row| row.get::<_, String>(0))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(learned
+            .into_iter()
+            .find(|spelling| CommandNormalizer::same(spelling, command))
+            .unwrap_or_else(|| command.to_string()))
+    }
+
+    /// Keeps the table bounded by dropping the least useful patterns
+    fn prune_patterns(&self) -> Result<()> {
+        let count: i64 = self
+            .connection
+            .query_row("SELECT COUNT(*) FROM patterns", [], |row| row.get(0))?;
+        let excess = count - MAX_PATTERNS as i64;
+        if excess > 0 {
+            self.connection.execute(
+                "DELETE FROM patterns WHERE id IN (
+                    SELECT id FROM patterns ORDER BY accepted, hits, last_seen LIMIT ?1
+                 )",
+                params![excess],
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Embeds patterns stored before embeddings existed
+    fn backfill_embeddings(&self) -> Result<()> {
+        let missing: Vec<(i64, String)> = self
+            .connection
+            .prepare("SELECT id, prompt FROM patterns WHERE embedding IS NULL")?
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<rusqlite::Result<_>>()?;
+
+        for (id, prompt) in &missing {
+            self.connection.execute(
+                "UPDATE patterns SET embedding = ?1 WHERE id = ?2",
+                params![Embedder::to_bytes(&Embedder::embed(prompt)), id],
+            )?;
+        }
+        if !missing.is_empty() {
+            debug!("Embedded {} learned patterns", missing.len());
+        }
+        Ok(())
+    }
+
+    fn prune_history(&self) -> Result<()> {
+        let cutoff = format!("-{HISTORY_RETENTION_DAYS} days");
+        self.connection.execute(
+            "DELETE FROM queries WHERE last_asked < datetime('now', ?1)",
+            params![cutoff],
+        )?;
+        self.connection.execute(
+            "DELETE FROM experiment_runs WHERE created_at < datetime('now', ?1)",
+            params![cutoff],
+        )?;
+        Ok(())
+    }
+
+    fn query_patterns(
+        &self,
+        sql: &str,
+        params: impl rusqlite::Params,
+    ) -> Result<Vec<LearnedPattern>> {
+        let mut stmt = self.connection.prepare(sql)?;
+        let rows = stmt.query_map(params, Self::pattern_from_row)?;
+        Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?)
+    }
+
+    fn pattern_from_row(row: &rusqlite::Row) -> rusqlite::Result<LearnedPattern> {
+        let category: String = row.get(2)?;
+        Ok(LearnedPattern {
+            prompt: row.get(0)?,
+            command: row.get(1)?,
+            category: Category::from_name(&category).unwrap_or_default(),
+            accepted: row.get(3)?,
+            hits: row.get(4)?,
+        })
+    }
+
+    /// Number of learned patterns, for `commandy config`
+    pub fn pattern_count(&self) -> Result<usize> {
+        let count: Option<i64> = self
+            .connection
+            .query_row("SELECT COUNT(*) FROM patterns", [], |row| row.get(0))
+            .optional()?;
+        Ok(count.unwrap_or(0) as usize)
+    }
+}
@@ -1,37 +1,189 @@
 use anyhow::Result;
-use log::{debug, info, warn};
 use serde::{Deserialize, Serialize};
+use std::cell::OnceCell;
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use tracing::{debug, info, warn};
 
+use crate::ai::ModelIdentity;
 use crate::cli::Suggestion;
 use crate::config::Settings;
-use crate::context::{CacheManager, StorageManager};
+use crate::context::cache::HistoryEntry;
+use crate::context::{
+    Attachment, CacheManager, Category, CiJob, EntityExtractor, EntitySet, EnvInventory,
+    FileTypeCount, FileTypeScanner, LearnedPattern, LearningStore, PatternPacks, Pins,
+    ProjectScanner, ProjectToolchain, Quantity, QuantityParser, RecentCommand, SessionStore,
+    SessionTurn, ShellLog, ShellSubstitution, StorageManager, TaskTarget, TaxonomyClassifier,
+    TimeContext, WorkingDirectory,
+};
+use crate::safety::AuditLog;
 use crate::utils::environment::EnvironmentDetector;
+use crate::utils::{Platform, PrivilegeAnalyzer, Runtimes, Timeline, VersionShims};
+
+/// Most similar learned patterns included in the model prompt
+const LEARNED_PATTERNS_IN_PROMPT: usize = 5;
+
+/// Executables the prompt names; only these are checked for unselected shims
+const AVAILABLE_TOOLS_IN_PROMPT: usize = 20;
+
+/// Most rules summarized from older patterns included in the model prompt
+const LEARNED_RULES_IN_PROMPT: usize = 5;
+
+/// Characters of learned patterns and rules kept in the prompt, so a long
+/// history can't crowd out the request
+const LEARNED_CONTEXT_MAX_CHARS: usize = 1500;
+
+/// Most recent relevant commands included in the model prompt
+const RECENT_COMMANDS_IN_PROMPT: usize = 5;
+
+/// Shell commands looked through for relevant ones
+const RECENT_COMMANDS_SCANNED: usize = 50;
+
+/// Commands run in the current directory this recently are relevant whatever they say
+const SAME_DIRECTORY_MINUTES: i64 = 15;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ContextData {
-    pub content: String,
+    /// Accepted commands for the most similar past prompts, then pattern pack examples
+    #[serde(default)]
+    pub learned_patterns: Vec<LearnedPattern>,
+    /// Rules the model summarized from older patterns in the prompt's category
+    #[serde(default)]
+    pub learned_rules: Vec<String>,
     pub environment: HashMap<String, String>,
-    pub recent_commands: Vec<String>,
-    pub prompt_category: String,
+    /// Full command lines from shell history and commandy runs that bear on the prompt, oldest first
+    pub recent_commands: Vec<RecentCommand>,
+    pub prompt_category: Category,
+    /// Existing command the prompt refines, when piped from another commandy
+    #[serde(default)]
+    pub base_command: Option<String>,
+    /// Earlier turns from this shell session that a follow-up prompt may refer to
+    #[serde(default)]
+    pub session_turns: Vec<SessionTurn>,
+    /// Older turns of a named session, condensed to one line each
+    #[serde(default)]
+    pub session_summary: Vec<String>,
+    /// Extra context lines supplied by plugins
+    #[serde(default)]
+    pub plugin_context: Vec<String>,
+    /// Branches, images, namespaces, and so on that exist on this machine
+    #[serde(default)]
+    pub entities: Vec<EntitySet>,
+    /// CI jobs in the current project, when the prompt is about CI
+    #[serde(default)]
+    pub ci_jobs: Vec<CiJob>,
+    /// Makefile, justfile, and Taskfile targets in the current directory
+    #[serde(default)]
+    pub tasks: Vec<TaskTarget>,
+    /// Tool versions and PATH entries direnv, asdf, or mise activate here
+    #[serde(default)]
+    pub toolchain: Option<ProjectToolchain>,
+    /// The virtualenv or conda env and the node version in use
+    #[serde(default)]
+    pub runtimes: Runtimes,
+    /// Date, time zone, and locale, when the prompt mentions dates or times
+    #[serde(default)]
+    pub time: Option<TimeContext>,
+    /// Sizes, durations, and counts in the prompt, converted for command flags
+    #[serde(default)]
+    pub quantities: Vec<Quantity>,
+    /// The current directory and the project root above it
+    #[serde(default)]
+    pub working_directory: Option<WorkingDirectory>,
+    /// Counts of files under the current directory by extension; never their names
+    #[serde(default)]
+    pub file_types: Vec<FileTypeCount>,
+    /// Files passed with `-f`, cut to fit the prompt
+    #[serde(default)]
+    pub attachments: Vec<Attachment>,
+    /// Names of set environment variables relevant to the prompt; never their values
+    #[serde(default)]
+    pub env_var_names: Vec<String>,
+    /// The user's aliases and shell functions, as `name: value` or `name (function)`
+    #[serde(default)]
+    pub shell_definitions: Vec<String>,
+    /// Instructions replacing the start of the suggestion prompt, from an `[experiment]` variant
+    #[serde(default)]
+    pub instructions: Option<String>,
+    /// Commands on earlier pages of the picker, for a page of different ones
+    #[serde(default)]
+    pub exclude_commands: Vec<String>,
+    /// A suggestion to give other flags or tools for, keeping its approach
+    #[serde(default)]
+    pub vary_command: Option<String>,
+    /// How long each part of the context took to gather, for `--verbose`
+    #[serde(skip)]
+    pub timeline: Timeline,
 }
 
 pub struct ContextManager {
-    pub cache: Option<CacheManager>,
+    /// Opened on first use; holds `None` when caching is off or the database won't open
+    cache: OnceCell<Option<CacheManager>>,
+    cache_path: PathBuf,
+    cache_enabled: bool,
+    learning: Option<LearningStore>,
+    packs: PatternPacks,
+    pins: Pins,
     storage: StorageManager,
     env_detector: EnvironmentDetector,
+    session: Option<SessionStore>,
+    env_var_names: bool,
+    shell_definitions: bool,
+    file_type_stats: bool,
+    audit: Option<AuditLog>,
+    /// Read the cache, learning store, and session, but never write them
+    read_only: bool,
+    /// Accepted patterns kept before older ones are summarized; 0 turns summaries off
+    summarize_learned_after: usize,
+    /// The experiment run whose suggestions are on screen, to record what the user did
+    experiment_run: Option<i64>,
+    /// Fingerprint of the configured model, whose cached suggestions are served
+    model_fingerprint: String,
 }
 
 impl ContextManager {
-    pub fn new(_settings: &Settings) -> Result<Self> {
+    pub fn new(settings: &Settings) -> Result<Self> {
         let storage = StorageManager::new()?;
         let env_detector = EnvironmentDetector::new();
 
+        let cache_path = storage
+            .get_commandy_dir()
+            .join("cache")
+            .join("suggestions.db");
+        let model_fingerprint = ModelIdentity::of(settings).fingerprint;
+
+        let learning = if storage.get_commandy_dir().exists() {
+            Self::open_learning_store(&storage)
+        } else {
+            None
+        };
+
+        let packs = PatternPacks::load(&storage.get_packs_dir());
+        let pins = Pins::load(storage.get_commandy_dir());
+        let session = SessionStore::current(storage.get_commandy_dir());
+        let audit = settings
+            .audit
+            .enabled
+            .then(|| AuditLog::new(storage.get_commandy_dir()));
+
         Ok(Self {
-            cache: None,
+            cache: OnceCell::new(),
+            cache_path,
+            cache_enabled: settings.cache.enabled,
+            learning,
+            packs,
+            pins,
             storage,
             env_detector,
+            session,
+            env_var_names: settings.privacy.env_var_names,
+            shell_definitions: settings.privacy.shell_definitions,
+            file_type_stats: settings.privacy.file_type_stats,
+            audit,
+            read_only: false,
+            summarize_learned_after: settings.general.summarize_learned_after,
+            experiment_run: None,
+            model_fingerprint,
         })
     }
 
@@ -40,12 +192,11 @@ impl ContextManager {
         self.storage.initialize_directory()?;
 
         // Initialize cache after directories are created
-        let cache_path = self
-            .storage
-            .get_commandy_dir()
-            .join("cache")
-            .join("suggestions.db");
-        self.cache = Some(CacheManager::new(&cache_path)?);
+        if self.cache_enabled {
+            let cache = Self::open_cache(&self.cache_path, &self.model_fingerprint)?;
+            self.cache = OnceCell::from(Some(cache));
+        }
+        self.learning = Self::open_learning_store(&self.storage);
 
         // Detect and store initial environment
         let env_info = self.env_detector.detect_environment()?;
@@ -54,17 +205,121 @@ impl ContextManager {
         Ok(())
     }
 
+    /// The suggestion cache, opened on first use if caching is on and a
+    /// previous `commandy init` created it
+    pub fn cache(&self) -> Option<&CacheManager> {
+        self.cache
+            .get_or_init(|| {
+                if !self.cache_enabled || !self.cache_path.exists() {
+                    return None;
+                }
+                Self::open_cache(&self.cache_path, &self.model_fingerprint)
+                    .inspect_err(|e| warn!("Failed to open cache database: {e}"))
+                    .ok()
+            })
+            .as_ref()
+    }
+
+    pub fn cache_mut(&mut self) -> Option<&mut CacheManager> {
+        self.cache();
+        self.cache.get_mut()?.as_mut()
+    }
+
+    fn open_cache(path: &Path, model_fingerprint: &str) -> Result<CacheManager> {
+        let mut cache = CacheManager::new(path)?;
+        cache.set_model(model_fingerprint)?;
+        Ok(cache)
+    }
+
+    /// Serves cached suggestions for `model` from now on, after the model changed
+    pub fn set_model(&mut self, model: &ModelIdentity) -> Result<()> {
+        self.model_fingerprint = model.fingerprint.clone();
+        match self.cache.get_mut() {
+            Some(Some(cache)) => cache.set_model(&model.fingerprint),
+            _ => Ok(()),
+        }
+    }
+
+    /// Opens the learning store, importing a PHLOEM.md left by older versions
+    fn open_learning_store(storage: &StorageManager) -> Option<LearningStore> {
+        let store = match LearningStore::open(&storage.get_learning_db_path()) {
+            Ok(store) => store,
+            Err(e) => {
+                warn!("Failed to open learning store: {e}");
+                return None;
+            }
+        };
+
+        let backups = storage.get_commandy_dir().join("backups");
+        if let Err(e) = store.migrate_legacy_file(&storage.get_legacy_context_path(), &backups) {
+            warn!("Failed to import legacy context file: {e}");
+        }
+        Some(store)
+    }
+
     pub fn get_cached_suggestion(&self, prompt: &str) -> Result<Option<Suggestion>> {
         debug!("Checking cache for prompt: {prompt}");
-        match &self.cache {
+        match self.cache() {
             Some(cache) => cache.get_suggestion(prompt),
             None => Ok(None), // Cache not initialized yet
         }
     }
 
+    /// Explanation saved for a command in the last `ttl_days`
+    pub fn get_cached_explanation(&self, command: &str, ttl_days: u32) -> Option<String> {
+        let cache = self.cache()?;
+        match cache.get_explanation(command, ttl_days) {
+            Ok(explanation) => explanation,
+            Err(e) => {
+                warn!("Failed to read cached explanation: {e}");
+                None
+            }
+        }
+    }
+
+    pub fn get_cached_tool_summary(&self, tool: &str, ttl_days: u32) -> Option<String> {
+        let cache = self.cache()?;
+        match cache.get_tool_summary(tool, ttl_days) {
+            Ok(summary) => summary,
+            Err(e) => {
+                warn!("Failed to read cached tool summary: {e}");
+                None
+            }
+        }
+    }
+
+    pub fn cache_tool_summary(&mut self, tool: &str, summary: &str) {
+        if self.read_only {
+            return;
+        }
+        if let Some(cache) = self.cache_mut() {
+            if let Err(e) = cache.cache_tool_summary(tool, summary) {
+                warn!("Failed to cache tool summary: {e}");
+            }
+        }
+    }
+
+    pub fn set_read_only(&mut self, read_only: bool) {
+        self.read_only = read_only;
+    }
+
+    pub fn cache_explanation(&mut self, command: &str, explanation: &str) {
+        if self.read_only {
+            return;
+        }
+        if let Some(cache) = self.cache_mut() {
+            if let Err(e) = cache.cache_explanation(command, explanation) {
+                warn!("Failed to cache explanation: {e}");
+            }
+        }
+    }
+
     pub fn cache_suggestion(&mut self, prompt: &str, suggestion: &Suggestion) -> Result<()> {
+        if self.read_only {
+            return Ok(());
+        }
         debug!("Caching suggestion for prompt: {prompt}");
-        if let Some(cache) = &mut self.cache {
+        if let Some(cache) = self.cache_mut() {
             cache.cache_suggestion(prompt, suggestion)?;
         }
 
@@ -77,49 +332,243 @@ impl ContextManager {
     pub fn get_relevant_context(&self, prompt: &str) -> Result<ContextData> {
         debug!("Loading relevant context for prompt: {prompt}");
 
-        // Read context file
-        let context_content = self.storage.read_context_file()?;
+        let mut timeline = Timeline::new();
 
         // Get environment information
-        let environment = match &self.cache {
-            Some(cache) => cache.get_environment()?,
-            None => std::collections::HashMap::new(), // Return empty if cache not initialized
-        };
+        let mut environment = timeline.time("environment", || -> Result<_> {
+            let mut environment = match self.cache() {
+                Some(cache) => cache.get_environment()?,
+                None => std::collections::HashMap::new(), // Return empty if cache not initialized
+            };
+            // Stored at init, so refresh the platform after OS upgrades and for older caches
+            environment.extend(Platform::detect().environment());
+            Ok(environment)
+        })?;
+        // Shims are on PATH everywhere, but only run where a version is selected
+        if let Some(tools) = environment.get_mut("available_tools") {
+            timeline.time("version shims", || {
+                *tools = tools
+                    .split(',')
+                    .filter(|tool| VersionShims::runnable(tool))
+                    .take(AVAILABLE_TOOLS_IN_PROMPT)
+                    .collect::<Vec<_>>()
+                    .join(",");
+            });
+        }
 
-        // Get recent successful commands from commandy history
-        let mut recent_commands = match &self.cache {
-            Some(cache) => cache.get_recent_commands(10)?,
-            None => Vec::new(), // Return empty if cache not initialized
-        };
+        let recent_commands = timeline.time("recent commands", || self.recent_commands(prompt));
 
-        // Integrate shell history for richer context
-        if let Some(cache) = &self.cache {
-            if let Ok(shell_history) = cache.get_shell_history() {
-                // Add relevant shell commands to context
-                let relevant_shell_commands: Vec<String> = shell_history
-                    .into_iter()
-                    .take(20) // Get more shell history
-                    .filter(|cmd| self.is_command_relevant(cmd, prompt))
-                    .collect();
-
-                // Merge and deduplicate
-                recent_commands.extend(relevant_shell_commands);
-                recent_commands.sort();
-                recent_commands.dedup();
+        // Categorize the prompt
+        let prompt_category = timeline.time("category", || self.categorize_prompt(prompt));
+
+        let (learned_patterns, learned_rules) = timeline.time("learned patterns", || {
+            let mut learned_patterns = match &self.learning {
+                Some(learning) => learning
+                    .similar(prompt, LEARNED_PATTERNS_IN_PROMPT)
+                    .unwrap_or_else(|e| {
+                        warn!("Failed to read learned patterns: {e}");
+                        Vec::new()
+                    }),
+                None => Vec::new(),
+            };
+
+            // Team packs fill whatever room personal learning leaves
+            for example in self.packs.similar(prompt, LEARNED_PATTERNS_IN_PROMPT) {
+                if learned_patterns.len() >= LEARNED_PATTERNS_IN_PROMPT {
+                    break;
+                }
+                if !learned_patterns
+                    .iter()
+                    .any(|p| p.command == example.command)
+                {
+                    learned_patterns.push(example);
+                }
             }
-        }
 
-        // Categorize the prompt
-        let prompt_category = self.categorize_prompt(prompt);
+            let mut learned_rules = match &self.learning {
+                Some(learning) => learning
+                    .rules_for(prompt_category, LEARNED_RULES_IN_PROMPT)
+                    .unwrap_or_else(|e| {
+                        warn!("Failed to read learned rules: {e}");
+                        Vec::new()
+                    }),
+                None => Vec::new(),
+            };
+            Self::fit_learned_context(&mut learned_patterns, &mut learned_rules);
+            (learned_patterns, learned_rules)
+        });
+
+        let entities = timeline.time("entities", || EntityExtractor::gather(prompt_category));
+        let ci_jobs = timeline.time("ci jobs", || ProjectScanner::ci_jobs_for(prompt));
+        let tasks = timeline.time("project tasks", ProjectScanner::tasks);
+        let toolchain = timeline.time("toolchain", ProjectToolchain::detect);
+        let runtimes = timeline.time("runtimes", Runtimes::detect);
+        let working_directory =
+            timeline.time("working directory", ProjectScanner::working_directory);
+        let file_types = match (self.file_type_stats, std::env::current_dir()) {
+            (true, Ok(cwd)) => timeline.time("file types", || FileTypeScanner::scan(&cwd)),
+            _ => Vec::new(),
+        };
+        let env_var_names = match self.env_var_names {
+            true => timeline.time("environment variables", || EnvInventory::names_for(prompt)),
+            false => Vec::new(),
+        };
+        let shell_definitions = match self.shell_definitions {
+            true => timeline.time("aliases and functions", || {
+                ShellSubstitution::load(self.get_commandy_dir()).definitions_for(prompt)
+            }),
+            false => Vec::new(),
+        };
 
         Ok(ContextData {
-            content: context_content,
+            learned_patterns,
+            learned_rules,
             environment,
             recent_commands,
             prompt_category,
+            base_command: None,
+            session_turns: Vec::new(),
+            session_summary: Vec::new(),
+            plugin_context: Vec::new(),
+            entities,
+            ci_jobs,
+            tasks,
+            toolchain,
+            runtimes,
+            time: TimeContext::mentions_time(prompt).then(TimeContext::now),
+            quantities: QuantityParser::parse(prompt),
+            working_directory,
+            attachments: Vec::new(),
+            file_types,
+            env_var_names,
+            shell_definitions,
+            instructions: None,
+            exclude_commands: Vec::new(),
+            vary_command: None,
+            timeline,
         })
     }
 
+    /// Earlier session turns to include when the prompt looks like a follow-up,
+    /// or always in a named session, where every prompt continues the conversation
+    pub fn session_turns_for(&self, prompt: &str) -> Vec<SessionTurn> {
+        match &self.session {
+            Some(session) if session.is_named() || SessionStore::is_follow_up(prompt) => {
+                session.turns().to_vec()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// Condensed older turns of a named session
+    pub fn session_summary(&self) -> Vec<String> {
+        match &self.session {
+            Some(session) => session.summary().to_vec(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Records prompts and runs in this session instead of the shell's, for `commandy chat`
+    pub fn use_session(&mut self, session: SessionStore) {
+        self.session = Some(session);
+    }
+
+    /// Drops the least similar patterns, then the oldest rules, until both fit the budget
+    fn fit_learned_context(patterns: &mut Vec<LearnedPattern>, rules: &mut Vec<String>) {
+        let size = |patterns: &[LearnedPattern], rules: &[String]| {
+            patterns
+                .iter()
+                .map(|p| p.prompt.len() + p.command.len())
+                .chain(rules.iter().map(String::len))
+                .sum::<usize>()
+        };
+        while size(patterns, rules) > LEARNED_CONTEXT_MAX_CHARS && patterns.len() > 1 {
+            patterns.pop();
+        }
+        while size(patterns, rules) > LEARNED_CONTEXT_MAX_CHARS && !rules.is_empty() {
+            rules.pop();
+        }
+    }
+
+    /// Older accepted patterns due to be summarized into rules, if any
+    pub fn learned_summary_batch(&self) -> Option<(Category, Vec<LearnedPattern>)> {
+        if self.read_only || self.summarize_learned_after == 0 {
+            return None;
+        }
+        let learning = self.learning.as_ref()?;
+        learning
+            .summary_batch(self.summarize_learned_after)
+            .unwrap_or_else(|e| {
+                warn!("Failed to check learned patterns for summarizing: {e}");
+                None
+            })
+    }
+
+    /// Replaces summarized patterns with the rules written for them
+    pub fn store_learned_rules(
+        &self,
+        category: Category,
+        rules: &[String],
+        patterns: &[LearnedPattern],
+    ) -> Result<()> {
+        match &self.learning {
+            Some(learning) if !self.read_only => {
+                learning.replace_with_rules(category, rules, patterns)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Remembers a prompt and its top suggestion for follow-ups in this shell
+    pub fn record_session_query(&mut self, prompt: &str, command: Option<&str>) {
+        if self.read_only {
+            return;
+        }
+        if let Some(session) = &mut self.session {
+            if let Err(e) = session.record_query(prompt, command) {
+                warn!("Failed to save session state: {e}");
+            }
+        }
+    }
+
+    /// Notes that the suggestions about to be shown came from `arm` of `experiment`
+    pub fn start_experiment_run(&mut self, experiment: &str, arm: &str, prompt: &str) {
+        self.experiment_run = None;
+        if self.read_only {
+            return;
+        }
+        let Some(learning) = &self.learning else {
+            return;
+        };
+        match learning.record_experiment_run(experiment, arm, prompt) {
+            Ok(run) => self.experiment_run = Some(run),
+            Err(e) => warn!("Failed to record experiment run: {e}"),
+        }
+    }
+
+    /// Credits the experiment arm whose suggestions the user just ran or copied
+    fn record_experiment_outcome(&self, success: bool) {
+        let (Some(run), Some(learning)) = (self.experiment_run, &self.learning) else {
+            return;
+        };
+        if let Err(e) = learning.record_experiment_outcome(run, success) {
+            warn!("Failed to record experiment outcome: {e}");
+        }
+    }
+
+    /// Remembers the command the user picked for a prompt in this shell
+    pub fn record_session_accepted(&mut self, prompt: &str, command: &str) {
+        if self.read_only {
+            return;
+        }
+        self.record_experiment_outcome(false);
+        if let Some(session) = &mut self.session {
+            if let Err(e) = session.record_accepted(prompt, command) {
+                warn!("Failed to save session state: {e}");
+            }
+        }
+    }
+
     pub fn record_command_execution(
         &mut self,
         command: &str,
@@ -127,10 +576,13 @@ impl ContextManager {
         success: bool,
         exit_code: Option<i32>,
     ) -> Result<()> {
+        if self.read_only {
+            return Ok(());
+        }
         debug!("Recording command execution: {command} (success: {success})");
 
         // Record in history table
-        if let Some(cache) = &mut self.cache {
+        if let Some(cache) = self.cache_mut() {
             cache.record_command_execution(command, prompt, success, exit_code)?;
 
             // Update suggestion success metrics
@@ -139,8 +591,8 @@ impl ContextManager {
             }
         }
 
-        if success {
-            self.update_successful_command_pattern(prompt, command)?;
+        if let Some(learning) = &self.learning {
+            learning.record_feedback(prompt, command, success, exit_code)?;
         }
 
         Ok(())
@@ -151,49 +603,138 @@ impl ContextManager {
         prompt: &str,
         command: &str,
         success: bool,
+        exit_code: Option<i32>,
     ) -> Result<()> {
+        if self.read_only {
+            return Ok(());
+        }
         debug!("Recording suggestion feedback: {prompt} -> {command} (success: {success})");
 
-        // If successful, learn about the command pattern
+        self.record_session_accepted(prompt, command);
         if success {
-            self.learn_successful_command(prompt, command)?;
+            self.record_experiment_outcome(true);
+        }
+        if let Some(session) = &mut self.session {
+            if let Err(e) = session.record_step(prompt, command, success, exit_code) {
+                warn!("Failed to save session state: {e}");
+            }
+        }
+
+        // A run as root says little about whether the command works for the user,
+        // so it is kept for recall but not learned or cached as a success
+        let elevated = PrivilegeAnalyzer::is_elevated(command)
+            || PrivilegeAnalyzer::requires_root(command).is_some();
+
+        if let Some(learning) = self.learning.as_ref().filter(|_| !elevated) {
+            learning.record_feedback(prompt, command, success, exit_code)?;
         }
 
-        if let Some(cache) = &mut self.cache {
+        if let Some(cache) = self.cache_mut() {
+            // Keep an execution log for `commandy recall`
+            if let Err(e) = cache.record_command_execution(command, prompt, success, exit_code) {
+                warn!("Failed to record command execution: {e}");
+            }
+            if elevated {
+                debug!("Not caching elevated run of {command}");
+                return Ok(());
+            }
             cache.record_suggestion_usage(prompt, command, success)
         } else {
             Ok(()) // Cache not initialized yet
         }
     }
 
-    fn learn_successful_command(&self, prompt: &str, command: &str) -> Result<()> {
-        // Extract the executable name
-        let executable = command.split_whitespace().next().unwrap_or("").trim();
+    /// Adds an executed command to the audit log, when auditing is on
+    pub fn record_audit(&self, command: &str, exit_code: Option<i32>, target: Option<&str>) {
+        if let Some(audit) = &self.audit {
+            if let Err(e) = audit.append(command, exit_code, target) {
+                warn!("Failed to write audit record: {e}");
+            }
+        }
+    }
 
-        // Skip common commands that don't need learning
-        let skip_learning = ["ls", "cd", "pwd", "echo", "cat", "grep"];
-        if skip_learning.contains(&executable) {
-            return Ok(());
+    /// Runs and successes of a command executed through commandy; `None` if never run
+    pub fn execution_stats(&self, command: &str) -> Option<(u32, u32)> {
+        let cache = self.cache()?;
+        match cache.execution_stats(command) {
+            Ok((0, _)) => None,
+            Ok(stats) => Some(stats),
+            Err(e) => {
+                warn!("Failed to read execution stats: {e}");
+                None
+            }
         }
+    }
 
-        let category = self.categorize_prompt(prompt);
+    /// When a command last ran through commandy; `None` if never, or unknown
+    pub fn last_run(&self, command: &str) -> Option<chrono::DateTime<chrono::Utc>> {
+        let cache = self.cache()?;
+        match cache.last_run(command) {
+            Ok(last_run) => last_run,
+            Err(e) => {
+                warn!("Failed to read when {command} last ran: {e}");
+                None
+            }
+        }
+    }
 
-        // Update COMMANDY.md with learned command pattern
-        let learning_content = format!(
-            "✓ Validated executable: `{executable}`\n\
-            Context: \"{prompt}\"\n\
-            Full command: `{command}`"
-        );
+    /// Commands the user has run: commandy's successful executions, then shell history
+    pub fn command_history(&self, limit: usize) -> Vec<String> {
+        let Some(cache) = self.cache() else {
+            return Vec::new();
+        };
+        let mut commands = cache.get_recent_commands(limit).unwrap_or_default();
+        if let Ok(shell) = cache.get_shell_history() {
+            commands.extend(shell.into_iter().take(limit));
+        }
+        commands
+    }
 
-        self.storage
-            .append_to_context(&category, &learning_content)?;
+    /// Gathers shell history and commandy's execution log for searching
+    pub fn get_searchable_history(&self) -> Vec<HistoryEntry> {
+        let mut entries = match CacheManager::read_shell_history() {
+            Ok(entries) => entries,
+            Err(e) => {
+                warn!("Failed to read shell history: {e}");
+                Vec::new()
+            }
+        };
 
-        Ok(())
+        if let Some(cache) = self.cache() {
+            match cache.get_execution_history(1000) {
+                Ok(history) => entries.extend(history),
+                Err(e) => warn!("Failed to read execution history: {e}"),
+            }
+        }
+
+        entries
+    }
+
+    /// Caches the answer `commandy cache warm` generated for a request
+    pub fn warm_suggestion(&mut self, prompt: &str, suggestion: &Suggestion) -> Result<()> {
+        if self.read_only {
+            return Ok(());
+        }
+        match self.cache_mut() {
+            Some(cache) => cache.warm_suggestion(prompt, suggestion),
+            None => Ok(()),
+        }
+    }
+
+    /// The requests asked most often, from the execution history
+    pub fn frequent_prompts(&self, limit: usize) -> Vec<String> {
+        let Some(cache) = self.cache() else {
+            return Vec::new();
+        };
+        cache.frequent_prompts(limit).unwrap_or_else(|e| {
+            warn!("Failed to read frequent requests: {e}");
+            Vec::new()
+        })
     }
 
     pub fn clear_cache(&mut self) -> Result<()> {
         info!("Clearing command cache");
-        if let Some(cache) = &mut self.cache {
+        if let Some(cache) = self.cache_mut() {
             cache.clear_cache()
         } else {
             Ok(()) // Cache not initialized yet
@@ -202,11 +743,34 @@ impl ContextManager {
 
     pub fn clear_context(&self) -> Result<()> {
         info!("Clearing learning context");
-        self.storage.clear_context()
+        match &self.learning {
+            Some(learning) => learning.clear(),
+            None => Ok(()),
+        }
+    }
+
+    pub fn learning(&self) -> Option<&LearningStore> {
+        self.learning.as_ref()
+    }
+
+    pub fn packs(&self) -> &PatternPacks {
+        &self.packs
+    }
+
+    pub fn pins(&self) -> &Pins {
+        &self.pins
+    }
+
+    pub fn pins_mut(&mut self) -> &mut Pins {
+        &mut self.pins
+    }
+
+    pub fn get_commandy_dir(&self) -> &PathBuf {
+        self.storage.get_commandy_dir()
     }
 
-    pub fn get_context_file_path(&self) -> &PathBuf {
-        self.storage.get_context_file_path()
+    pub fn get_learning_db_path(&self) -> PathBuf {
+        self.storage.get_learning_db_path()
     }
 
     pub fn get_cache_path(&self) -> PathBuf {
@@ -217,7 +781,7 @@ impl ContextManager {
     }
 
     fn update_environment_info(&mut self, env_info: &HashMap<String, String>) -> Result<()> {
-        if let Some(cache) = &mut self.cache {
+        if let Some(cache) = self.cache_mut() {
             for (key, value) in env_info {
                 if let Err(e) = cache.update_environment(key, value) {
                     warn!("Failed to update environment info for {key}: {e}");
@@ -227,66 +791,62 @@ impl ContextManager {
         Ok(())
     }
 
-    fn categorize_prompt(&self, prompt: &str) -> String {
-        let prompt_lower = prompt.to_lowercase();
-
-        // Simple categorization based on keywords
-        if prompt_lower.contains("docker") || prompt_lower.contains("container") {
-            "Docker".to_string()
-        } else if prompt_lower.contains("kubectl")
-            || prompt_lower.contains("pod")
-            || prompt_lower.contains("kubernetes")
-        {
-            "Kubernetes".to_string()
-        } else if prompt_lower.contains("git")
-            || prompt_lower.contains("commit")
-            || prompt_lower.contains("branch")
-        {
-            "Git".to_string()
-        } else if prompt_lower.contains("file")
-            || prompt_lower.contains("find")
-            || prompt_lower.contains("ls")
-        {
-            "File Management".to_string()
-        } else if prompt_lower.contains("process")
-            || prompt_lower.contains("kill")
-            || prompt_lower.contains("ps")
-        {
-            "Process Management".to_string()
-        } else {
-            "General".to_string()
-        }
+    fn categorize_prompt(&self, prompt: &str) -> Category {
+        TaxonomyClassifier::classify(prompt).category
     }
 
     fn update_context_learning(&self, prompt: &str, suggestion: &Suggestion) -> Result<()> {
-        let category = self.categorize_prompt(prompt);
-
-        let learning_content = format!(
-            "User prompt: \"{}\"\n→ Suggested: `{}`\n{}",
-            prompt,
-            suggestion.command,
-            suggestion
-                .explanation
-                .as_ref()
-                .map(|e| format!("Explanation: {e}"))
-                .unwrap_or_default()
-        );
-
-        self.storage
-            .append_to_context(&category, &learning_content)?;
-
-        Ok(())
+        match &self.learning {
+            Some(learning) => {
+                learning.record_suggestion(prompt, &suggestion.command, suggestion.source.as_str())
+            }
+            None => Ok(()),
+        }
     }
 
-    fn update_successful_command_pattern(&self, prompt: &str, command: &str) -> Result<()> {
-        let category = self.categorize_prompt(prompt);
-
-        let success_content = format!("✓ Successful execution:\n\"{prompt}\" → `{command}`");
-
-        self.storage
-            .append_to_context(&category, &success_content)?;
-
-        Ok(())
+    /// The last few commands that share words or a category with the prompt, or
+    /// that just ran in this directory
+    fn recent_commands(&self, prompt: &str) -> Vec<RecentCommand> {
+        let commandy_dir = self.storage.get_commandy_dir();
+        if !self.read_only {
+            ShellLog::compact(commandy_dir);
+        }
+        let mut commands = ShellLog::recent(commandy_dir, RECENT_COMMANDS_SCANNED);
+
+        // Commands commandy ran itself never reach the shell's history
+        if let Some(cache) = self.cache() {
+            match cache.get_execution_history(10) {
+                Ok(history) => commands.extend(history.into_iter().map(|entry| RecentCommand {
+                    command: ShellLog::sanitize(&entry.command),
+                    timestamp: entry.timestamp,
+                    cwd: None,
+                    exit_code: Some(0),
+                })),
+                Err(e) => warn!("Failed to read execution history: {e}"),
+            }
+        }
+        commands.sort_by_key(|c| c.timestamp);
+
+        let cwd = std::env::current_dir()
+            .ok()
+            .map(|dir| dir.to_string_lossy().to_string());
+        let recent_since = chrono::Utc::now() - chrono::Duration::minutes(SAME_DIRECTORY_MINUTES);
+
+        let mut seen = std::collections::HashSet::new();
+        let mut relevant: Vec<RecentCommand> = commands
+            .into_iter()
+            .rev()
+            .filter(|c| {
+                let here_just_now = c.cwd.is_some()
+                    && c.cwd == cwd
+                    && c.timestamp.is_some_and(|t| t >= recent_since);
+                here_just_now || self.is_command_relevant(&c.command, prompt)
+            })
+            .filter(|c| seen.insert(c.command.clone()))
+            .take(RECENT_COMMANDS_IN_PROMPT)
+            .collect();
+        relevant.reverse();
+        relevant
     }
 
     fn is_command_relevant(&self, command: &str, prompt: &str) -> bool {
@@ -322,7 +882,7 @@ impl ContextManager {
         let prompt_category = self.categorize_prompt(prompt);
         let command_category = self.categorize_prompt(command);
 
-        if prompt_category != "General" && prompt_category == command_category {
+        if prompt_category != Category::General && prompt_category == command_category {
             return true;
         }
 
@@ -1,7 +1,43 @@
+pub mod attachments;
 pub mod cache;
+pub mod clock;
+pub mod embedding;
+pub mod entities;
+pub mod envvars;
+pub mod filetypes;
+pub mod language;
+pub mod learning;
 pub mod manager;
+pub mod packs;
+pub mod pins;
+pub mod project;
+pub mod quantities;
+pub mod recall;
+pub mod session;
+pub mod shell_log;
 pub mod storage;
+pub mod substitution;
+pub mod taxonomy;
+pub mod toolchain;
 
-pub use cache::CacheManager;
+pub use attachments::Attachment;
+pub use cache::{CacheManager, CachedSuggestion, HistoryEntry, HistorySource};
+pub use clock::TimeContext;
+pub use embedding::Embedder;
+pub use entities::{EntityExtractor, EntityKind, EntitySet};
+pub use envvars::EnvInventory;
+pub use filetypes::{FileTypeCount, FileTypeScanner};
+pub use language::{Language, LanguageDetector};
+pub use learning::{ArmStats, LearnedPattern, LearningStore};
 pub use manager::{ContextData, ContextManager};
+pub use packs::{Denial, DenyRule, PackExample, PatternPack, PatternPacks};
+pub use pins::{Pin, Pins, PINS_FILE};
+pub use project::{CiJob, CiSystem, ProjectScanner, TaskRunner, TaskTarget, WorkingDirectory};
+pub use quantities::{Quantity, QuantityParser};
+pub use recall::{HistorySearch, RecallMatch};
+pub use session::{NamedSession, SessionStep, SessionStore, SessionTurn, SESSION_ENV};
+pub use shell_log::{RecentCommand, ShellLog, SHELL_LOG_FILE};
 pub use storage::StorageManager;
+pub use substitution::{ShellSubstitution, ALIASES_FILE, FUNCTIONS_FILE};
+pub use taxonomy::{Category, Classification, TaxonomyClassifier};
+pub use toolchain::{LocalBin, PinnedTool, ProjectToolchain};
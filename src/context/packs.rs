@@ -0,0 +1,203 @@
+use anyhow::{bail, Context, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+use crate::context::{Embedder, LearnedPattern, TaxonomyClassifier};
+use crate::utils::HttpClient;
+
+/// Least cosine similarity for a pack example to count as related
+const MIN_SIMILARITY: f32 = 0.3;
+
+/// A team-published set of example commands and denylist rules
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PatternPack {
+    pub name: String,
+    #[serde(default)]
+    pub version: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub examples: Vec<PackExample>,
+    #[serde(default)]
+    pub deny: Vec<DenyRule>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PackExample {
+    pub query: String,
+    pub command: String,
+}
+
+/// Suggestions matching `pattern` (a regex) are never shown
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DenyRule {
+    pub pattern: String,
+    pub reason: String,
+}
+
+impl PatternPack {
+    pub fn parse(text: &str) -> Result<Self> {
+        let pack: PatternPack = toml::from_str(text).context("Invalid pattern pack")?;
+        let valid_name = !pack.name.is_empty()
+            && pack
+                .name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if !valid_name {
+            bail!(
+                "Invalid pack name '{}': use letters, digits, '-' and '_'",
+                pack.name
+            );
+        }
+        for rule in &pack.deny {
+            Regex::new(&rule.pattern)
+                .with_context(|| format!("Invalid deny pattern '{}'", rule.pattern))?;
+        }
+        Ok(pack)
+    }
+}
+
+/// Why a suggestion was blocked
+#[derive(Debug, Clone)]
+pub struct Denial {
+    pub pack: String,
+    pub reason: String,
+}
+
+struct CompiledDeny {
+    pack: String,
+    regex: Regex,
+    reason: String,
+}
+
+struct EmbeddedExample {
+    pattern: LearnedPattern,
+    embedding: Vec<f32>,
+}
+
+/// Installed packs in `~/.commandy/packs`, layered under personal learning
+pub struct PatternPacks {
+    dir: PathBuf,
+    packs: Vec<PatternPack>,
+    examples: Vec<EmbeddedExample>,
+    deny: Vec<CompiledDeny>,
+}
+
+impl PatternPacks {
+    /// Loads every pack in `dir`, skipping ones that fail to parse
+    pub fn load(dir: &Path) -> Self {
+        let mut packs = Vec::new();
+        if let Ok(entries) = fs::read_dir(dir) {
+            let mut paths: Vec<PathBuf> = entries
+                .filter_map(|entry| entry.ok().map(|e| e.path()))
+                .filter(|path| path.extension().is_some_and(|ext| ext == "toml"))
+                .collect();
+            paths.sort();
+
+            for path in paths {
+                match fs::read_to_string(&path)
+                    .map_err(anyhow::Error::from)
+                    .and_then(|text| PatternPack::parse(&text))
+                {
+                    Ok(pack) => packs.push(pack),
+                    Err(e) => warn!("Skipping pattern pack {path:?}: {e}"),
+                }
+            }
+        }
+
+        let examples = packs
+            .iter()
+            .flat_map(|pack| &pack.examples)
+            .map(|example| EmbeddedExample {
+                pattern: LearnedPattern {
+                    prompt: example.query.clone(),
+                    command: example.command.clone(),
+                    category: TaxonomyClassifier::classify(&example.query).category,
+                    accepted: true,
+                    hits: 0,
+                },
+                embedding: Embedder::embed(&example.query),
+            })
+            .collect();
+
+        let deny = packs
+            .iter()
+            .flat_map(|pack| {
+                pack.deny.iter().filter_map(|rule| {
+                    Regex::new(&rule.pattern).ok().map(|regex| CompiledDeny {
+                        pack: pack.name.clone(),
+                        regex,
+                        reason: rule.reason.clone(),
+                    })
+                })
+            })
+            .collect();
+
+        Self {
+            dir: dir.to_path_buf(),
+            packs,
+            examples,
+            deny,
+        }
+    }
+
+    pub fn packs(&self) -> &[PatternPack] {
+        &self.packs
+    }
+
+    /// Installs (or updates) a pack from a file path or http(s) URL
+    pub fn install(&self, source: &str) -> Result<PatternPack> {
+        let text = if source.starts_with("http://") || source.starts_with("https://") {
+            HttpClient::fetch_text(source)?
+        } else {
+            fs::read_to_string(source).with_context(|| format!("Failed to read {source}"))?
+        };
+        let pack = PatternPack::parse(&text)?;
+
+        fs::create_dir_all(&self.dir)?;
+        fs::write(self.dir.join(format!("{}.toml", pack.name)), text)?;
+        info!(pack = pack.name, source, "Installed pattern pack");
+        Ok(pack)
+    }
+
+    pub fn remove(&self, name: &str) -> Result<()> {
+        let path = self.dir.join(format!("{name}.toml"));
+        if !self.packs.iter().any(|p| p.name == name) || !path.exists() {
+            bail!("No pattern pack named '{name}' (see 'commandy packs list')");
+        }
+        fs::remove_file(path)?;
+        Ok(())
+    }
+
+    /// Pack examples whose queries are most similar to `prompt`, best first
+    pub fn similar(&self, prompt: &str, limit: usize) -> Vec<LearnedPattern> {
+        let query = Embedder::embed(prompt);
+        let mut scored: Vec<(f32, &LearnedPattern)> = self
+            .examples
+            .iter()
+            .map(|e| (Embedder::similarity(&query, &e.embedding), &e.pattern))
+            .filter(|(similarity, _)| *similarity >= MIN_SIMILARITY)
+            .collect();
+        scored.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+
+        scored
+            .into_iter()
+            .take(limit)
+            .map(|(_, pattern)| pattern.clone())
+            .collect()
+    }
+
+    /// The first deny rule a command matches, if any
+    pub fn denied(&self, command: &str) -> Option<Denial> {
+        self.deny
+            .iter()
+            .find(|rule| rule.regex.is_match(command))
+            .map(|rule| Denial {
+                pack: rule.pack.clone(),
+                reason: rule.reason.clone(),
+            })
+    }
+}
@@ -0,0 +1,131 @@
+use anyhow::{bail, Context, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+/// File in `~/.commandy` holding the pins
+pub const PINS_FILE: &str = "pins.toml";
+
+/// A command shown first for every request matching `pattern`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Pin {
+    /// A regex, matched case-insensitively anywhere in the request
+    pub pattern: String,
+    pub command: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct PinFile {
+    #[serde(default, rename = "pin")]
+    pins: Vec<Pin>,
+}
+
+/// Commands the user pinned to the top for requests like "deploy to staging",
+/// kept in `~/.commandy/pins.toml`
+pub struct Pins {
+    path: PathBuf,
+    pins: Vec<Pin>,
+    compiled: Vec<Regex>,
+}
+
+impl Pins {
+    /// Loads the pins in `dir`, skipping patterns that no longer compile
+    pub fn load(dir: &Path) -> Self {
+        let path = dir.join(PINS_FILE);
+        let file: PinFile = match fs::read_to_string(&path) {
+            Ok(text) => toml::from_str(&text).unwrap_or_else(|e| {
+                warn!("Ignoring {path:?}: {e}");
+                PinFile::default()
+            }),
+            Err(_) => PinFile::default(),
+        };
+
+        let (pins, compiled) = file
+            .pins
+            .into_iter()
+            .filter_map(|pin| match Self::compile(&pin.pattern) {
+                Ok(regex) => Some((pin, regex)),
+                Err(e) => {
+                    warn!("Skipping pin: {e}");
+                    None
+                }
+            })
+            .unzip();
+        Self {
+            path,
+            pins,
+            compiled,
+        }
+    }
+
+    pub fn pins(&self) -> &[Pin] {
+        &self.pins
+    }
+
+    /// Pinned commands for `prompt`, in the order they were added
+    pub fn matching(&self, prompt: &str) -> Vec<&Pin> {
+        self.pins
+            .iter()
+            .zip(&self.compiled)
+            .filter(|(_, regex)| regex.is_match(prompt))
+            .map(|(pin, _)| pin)
+            .collect()
+    }
+
+    /// Pins `command` for `pattern`, replacing the command of an existing pin
+    /// with the same pattern
+    pub fn add(&mut self, pattern: &str, command: &str) -> Result<()> {
+        if pattern.trim().is_empty() {
+            bail!("The pin pattern is empty");
+        }
+        let regex = Self::compile(pattern)?;
+        if command.trim().is_empty() {
+            bail!("The pinned command is empty");
+        }
+        match self.pins.iter_mut().find(|pin| pin.pattern == pattern) {
+            Some(pin) => pin.command = command.to_string(),
+            None => {
+                self.pins.push(Pin {
+                    pattern: pattern.to_string(),
+                    command: command.to_string(),
+                });
+                self.compiled.push(regex);
+            }
+        }
+        self.save()
+    }
+
+    /// Removes the pin with this pattern, or at this position in `commandy pin list`
+    pub fn remove(&mut self, pin: &str) -> Result<Pin> {
+        let index = match pin.parse::<usize>() {
+            Ok(n) if (1..=self.pins.len()).contains(&n) => n - 1,
+            _ => self
+                .pins
+                .iter()
+                .position(|p| p.pattern == pin)
+                .with_context(|| format!("No pin for '{pin}' (see 'commandy pin list')"))?,
+        };
+        self.compiled.remove(index);
+        let removed = self.pins.remove(index);
+        self.save()?;
+        Ok(removed)
+    }
+
+    fn compile(pattern: &str) -> Result<Regex> {
+        Regex::new(&format!("(?i){pattern}"))
+            .with_context(|| format!("Invalid pin pattern '{pattern}'"))
+    }
+
+    fn save(&self) -> Result<()> {
+        let file = PinFile {
+            pins: self.pins.clone(),
+        };
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&self.path, toml::to_string_pretty(&file)?)
+            .with_context(|| format!("Failed to write {}", self.path.display()))
+    }
+}
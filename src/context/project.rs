@@ -0,0 +1,459 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::debug;
+
+use crate::utils::PathDisplay;
+
+/// Jobs kept per project
+const MAX_CI_JOBS: usize = 30;
+
+/// Task runner targets kept per directory
+const MAX_TASKS: usize = 30;
+
+/// Top-level `.gitlab-ci.yml` keys that configure the pipeline instead of naming a job
+const GITLAB_KEYWORDS: &[&str] = &[
+    "after_script",
+    "before_script",
+    "cache",
+    "default",
+    "image",
+    "include",
+    "services",
+    "stages",
+    "variables",
+    "workflow",
+];
+
+/// Words that make a prompt about the project's CI
+const CI_WORDS: &[&str] = &[
+    "ci",
+    "job",
+    "jobs",
+    "workflow",
+    "workflows",
+    "pipeline",
+    "action",
+    "actions",
+    "act",
+    "gitlab",
+    "github",
+];
+
+/// A CI system whose jobs can be run on this machine
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CiSystem {
+    GitHubActions,
+    GitLab,
+}
+
+impl CiSystem {
+    pub fn label(self) -> &'static str {
+        match self {
+            CiSystem::GitHubActions => "GitHub Actions",
+            CiSystem::GitLab => "GitLab CI",
+        }
+    }
+
+    /// How to run one of its jobs locally
+    pub fn local_runner(self) -> &'static str {
+        match self {
+            CiSystem::GitHubActions => "act -j <job> -W <workflow file>",
+            CiSystem::GitLab => "gitlab-runner exec docker <job>",
+        }
+    }
+}
+
+/// A job defined in the project's CI configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CiJob {
+    pub system: CiSystem,
+    /// Workflow file relative to the project root
+    pub file: String,
+    pub name: String,
+}
+
+/// A tool that runs named tasks from a file in the project
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskRunner {
+    Make,
+    Just,
+    Task,
+}
+
+impl TaskRunner {
+    /// The files each runner reads, in the order it looks for them
+    const FILES: &'static [(TaskRunner, &'static str)] = &[
+        (TaskRunner::Make, "GNUmakefile"),
+        (TaskRunner::Make, "makefile"),
+        (TaskRunner::Make, "Makefile"),
+        (TaskRunner::Just, "justfile"),
+        (TaskRunner::Just, "Justfile"),
+        (TaskRunner::Just, ".justfile"),
+        (TaskRunner::Task, "Taskfile.yml"),
+        (TaskRunner::Task, "Taskfile.yaml"),
+        (TaskRunner::Task, "taskfile.yml"),
+        (TaskRunner::Task, "taskfile.yaml"),
+    ];
+
+    /// The command that runs a target, such as `make docs`
+    pub fn program(self) -> &'static str {
+        match self {
+            TaskRunner::Make => "make",
+            TaskRunner::Just => "just",
+            TaskRunner::Task => "task",
+        }
+    }
+}
+
+/// A target defined in a Makefile, justfile, or Taskfile
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskTarget {
+    pub runner: TaskRunner,
+    pub name: String,
+    /// The comment or `desc:` written for it
+    pub description: Option<String>,
+}
+
+impl TaskTarget {
+    pub fn command(&self) -> String {
+        format!("{} {}", self.runner.program(), self.name)
+    }
+}
+
+/// Where the user is: the current directory and the checkout around it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkingDirectory {
+    /// The current directory, with `$HOME` as `~` and long paths shortened
+    pub cwd: String,
+    /// The enclosing git checkout, written the same way; `None` outside one
+    pub project_root: Option<String>,
+}
+
+impl WorkingDirectory {
+    /// `~/src/app/crates/core (project root: ~/src/app)`
+    pub fn describe(&self) -> String {
+        match &self.project_root {
+            Some(root) if *root == self.cwd => format!("{} (the project root)", self.cwd),
+            Some(root) => format!("{} (project root: {root})", self.cwd),
+            None => self.cwd.clone(),
+        }
+    }
+}
+
+/// Reads the project around the current directory for things a command may name
+pub struct ProjectScanner;
+
+impl ProjectScanner {
+    /// The enclosing git checkout, or the current directory outside one
+    pub fn root() -> Option<PathBuf> {
+        let cwd = std::env::current_dir().ok()?;
+        Some(Self::checkout_root(&cwd).unwrap_or(cwd))
+    }
+
+    /// The nearest directory at or above `dir` holding a `.git` (a directory,
+    /// or a file in worktrees and submodules)
+    pub fn checkout_root(dir: &Path) -> Option<PathBuf> {
+        dir.ancestors()
+            .find(|dir| dir.join(".git").exists())
+            .map(Path::to_path_buf)
+    }
+
+    /// The branch checked out in the checkout around `dir`, or the commit when detached
+    pub fn git_branch(dir: &Path) -> Option<String> {
+        let root = Self::checkout_root(dir)?;
+        let dot_git = root.join(".git");
+        // Worktrees and submodules point at their git directory from a file
+        let git_dir = match fs::read_to_string(&dot_git) {
+            Ok(pointer) => root.join(pointer.strip_prefix("gitdir:")?.trim()),
+            Err(_) => dot_git,
+        };
+        let head = fs::read_to_string(git_dir.join("HEAD")).ok()?;
+        let head = head.trim();
+        Some(match head.strip_prefix("ref: refs/heads/") {
+            Some(branch) => branch.to_string(),
+            None => head.chars().take(12).collect(),
+        })
+    }
+
+    /// The current directory and its project root, as the prompt shows them
+    pub fn working_directory() -> Option<WorkingDirectory> {
+        let cwd = std::env::current_dir().ok()?;
+        Some(WorkingDirectory {
+            cwd: PathDisplay::shorten(&cwd),
+            project_root: Self::checkout_root(&cwd).map(|root| PathDisplay::shorten(&root)),
+        })
+    }
+
+    /// CI jobs, when the prompt is about CI or names one of them
+    pub fn ci_jobs_for(prompt: &str) -> Vec<CiJob> {
+        let Some(root) = Self::root() else {
+            return Vec::new();
+        };
+        let jobs = Self::ci_jobs(&root);
+        let prompt = prompt.to_lowercase();
+        let mentioned = prompt
+            .split(|c: char| !c.is_alphanumeric() && c != '-' && c != '_')
+            .any(|word| CI_WORDS.contains(&word))
+            || jobs
+                .iter()
+                .any(|job| prompt.contains(&job.name.to_lowercase()));
+        match mentioned {
+            true => jobs,
+            false => Vec::new(),
+        }
+    }
+
+    /// Jobs from `.github/workflows/*.yml` and `.gitlab-ci.yml` under `root`
+    pub fn ci_jobs(root: &Path) -> Vec<CiJob> {
+        let mut jobs = Vec::new();
+
+        let workflows = root.join(".github").join("workflows");
+        if let Ok(entries) = fs::read_dir(&workflows) {
+            let mut files: Vec<PathBuf> = entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| {
+                    matches!(
+                        path.extension().and_then(|ext| ext.to_str()),
+                        Some("yml" | "yaml")
+                    )
+                })
+                .collect();
+            files.sort();
+            for path in files {
+                let Ok(content) = fs::read_to_string(&path) else {
+                    continue;
+                };
+                let file = path
+                    .strip_prefix(root)
+                    .unwrap_or(&path)
+                    .to_string_lossy()
+                    .into_owned();
+                jobs.extend(Self::github_jobs(&content).into_iter().map(|name| CiJob {
+                    system: CiSystem::GitHubActions,
+                    file: file.clone(),
+                    name,
+                }));
+            }
+        }
+
+        if let Ok(content) = fs::read_to_string(root.join(".gitlab-ci.yml")) {
+            jobs.extend(Self::gitlab_jobs(&content).into_iter().map(|name| CiJob {
+                system: CiSystem::GitLab,
+                file: ".gitlab-ci.yml".to_string(),
+                name,
+            }));
+        }
+
+        debug!(count = jobs.len(), "Found CI jobs");
+        jobs.truncate(MAX_CI_JOBS);
+        jobs
+    }
+
+    /// Targets from the Makefile, justfile, and Taskfile in the current directory
+    pub fn tasks() -> Vec<TaskTarget> {
+        match std::env::current_dir() {
+            Ok(cwd) => Self::tasks_in(&cwd),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    /// Targets from the first file each runner would read in `dir`
+    pub fn tasks_in(dir: &Path) -> Vec<TaskTarget> {
+        let mut tasks = Vec::new();
+        let mut seen = Vec::new();
+
+        for &(runner, file) in TaskRunner::FILES {
+            if seen.contains(&runner) {
+                continue;
+            }
+            let Ok(content) = fs::read_to_string(dir.join(file)) else {
+                continue;
+            };
+            seen.push(runner);
+            let targets = match runner {
+                TaskRunner::Make => Self::make_targets(&content),
+                TaskRunner::Just => Self::just_recipes(&content),
+                TaskRunner::Task => Self::taskfile_tasks(&content),
+            };
+            tasks.extend(targets.into_iter().map(|(name, description)| TaskTarget {
+                runner,
+                name,
+                description,
+            }));
+        }
+
+        debug!(count = tasks.len(), "Found task runner targets");
+        tasks.truncate(MAX_TASKS);
+        tasks
+    }
+
+    /// Explicit targets with a `## help` comment after them or `#` comments above them
+    fn make_targets(content: &str) -> Vec<(String, Option<String>)> {
+        let rule =
+            Regex::new(r"^([A-Za-z0-9][\w.\-/]*(?:\s+[A-Za-z0-9][\w.\-/]*)*)\s*::?(?:[^=]|$)")
+                .expect("valid regex");
+        let mut targets: Vec<(String, Option<String>)> = Vec::new();
+        let mut comments: Vec<&str> = Vec::new();
+
+        for line in content.lines() {
+            if let Some(comment) = line.strip_prefix('#') {
+                comments.push(comment.trim_start_matches('#').trim());
+                continue;
+            }
+            let above = Self::join_comments(&comments);
+            comments.clear();
+            let Some(caps) = rule.captures(line) else {
+                continue;
+            };
+            let inline = line
+                .split_once("##")
+                .map(|(_, help)| help.trim().to_string())
+                .filter(|help| !help.is_empty());
+            for name in caps[1].split_whitespace() {
+                // Pattern rules and files such as `build/app.o` aren't things to ask for
+                if name.contains(['%', '/', '.']) {
+                    continue;
+                }
+                if !targets.iter().any(|(seen, _)| seen == name) {
+                    targets.push((name.to_string(), inline.clone().or(above.clone())));
+                }
+            }
+        }
+        targets
+    }
+
+    /// Public recipes with the `#` comments above them
+    fn just_recipes(content: &str) -> Vec<(String, Option<String>)> {
+        let recipe =
+            Regex::new(r"^@?([A-Za-z][\w-]*)(?:\s+[^:]*)?:(?:[^=]|$)").expect("valid regex");
+        let mut recipes = Vec::new();
+        let mut comments: Vec<&str> = Vec::new();
+        let mut private = false;
+
+        for line in content.lines() {
+            if let Some(comment) = line.strip_prefix('#') {
+                comments.push(comment.trim());
+                continue;
+            }
+            // Attributes such as `[private]` or `[group('ci')]` sit between comment and recipe
+            if line.starts_with('[') {
+                private |= line.contains("private");
+                continue;
+            }
+            let description = Self::join_comments(&comments);
+            comments.clear();
+            let is_private = std::mem::take(&mut private);
+            let Some(caps) = recipe.captures(line) else {
+                continue;
+            };
+            let name = &caps[1];
+            if is_private || matches!(name, "set" | "alias" | "export" | "import" | "mod") {
+                continue;
+            }
+            recipes.push((name.to_string(), description));
+        }
+        recipes
+    }
+
+    /// Tasks under `tasks:` with their `desc:`, skipping `internal: true` ones
+    fn taskfile_tasks(content: &str) -> Vec<(String, Option<String>)> {
+        let mut tasks: Vec<(String, Option<String>)> = Vec::new();
+        let mut internal = Vec::new();
+        let mut in_tasks = false;
+        let mut task_indent = None;
+
+        for line in content.lines() {
+            let trimmed = line.trim_start();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+            let indent = line.len() - trimmed.len();
+            if indent == 0 {
+                in_tasks = Self::yaml_key(line).as_deref() == Some("tasks");
+                continue;
+            }
+            if !in_tasks {
+                continue;
+            }
+            if indent == *task_indent.get_or_insert(indent) {
+                tasks.extend(Self::yaml_key(trimmed).map(|name| (name, None)));
+                continue;
+            }
+            let Some((_, description)) = tasks.last_mut() else {
+                continue;
+            };
+            if let Some(desc) = trimmed.strip_prefix("desc:") {
+                *description = Some(desc.trim().trim_matches(['"', '\'']).to_string())
+                    .filter(|desc| !desc.is_empty());
+            } else if trimmed.starts_with("internal: true") {
+                internal.push(tasks.len() - 1);
+            }
+        }
+
+        tasks
+            .into_iter()
+            .enumerate()
+            .filter(|(i, _)| !internal.contains(i))
+            .map(|(_, task)| task)
+            .collect()
+    }
+
+    /// A block of comment lines as one description, `None` when there are none
+    fn join_comments(comments: &[&str]) -> Option<String> {
+        let text = comments
+            .iter()
+            .filter(|line| !line.is_empty())
+            .copied()
+            .collect::<Vec<_>>()
+            .join(" ");
+        (!text.is_empty()).then_some(text)
+    }
+
+    /// Job ids: the keys one level under the top-level `jobs:`
+    fn github_jobs(content: &str) -> Vec<String> {
+        let mut jobs = Vec::new();
+        let mut in_jobs = false;
+        let mut job_indent = None;
+
+        for line in content.lines() {
+            let trimmed = line.trim_start();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+            let indent = line.len() - trimmed.len();
+            if indent == 0 {
+                in_jobs = Self::yaml_key(line).as_deref() == Some("jobs");
+                continue;
+            }
+            if !in_jobs {
+                continue;
+            }
+            if indent == *job_indent.get_or_insert(indent) {
+                jobs.extend(Self::yaml_key(trimmed));
+            }
+        }
+        jobs
+    }
+
+    /// Top-level keys that aren't pipeline keywords or hidden `.template` jobs
+    fn gitlab_jobs(content: &str) -> Vec<String> {
+        content
+            .lines()
+            .filter(|line| !line.starts_with([' ', '\t', '#', '-']))
+            .filter_map(Self::yaml_key)
+            .filter(|key| !key.starts_with('.') && !GITLAB_KEYWORDS.contains(&key.as_str()))
+            .collect()
+    }
+
+    /// The key of a `key:` line that opens a mapping, unquoted
+    fn yaml_key(line: &str) -> Option<String> {
+        let key = Regex::new(r#"^\s*("[^"]+"|'[^']+'|[^\s#"'][^:#]*?)\s*:\s*(?:#.*)?$"#)
+            .expect("valid regex");
+        let caps = key.captures(line)?;
+        Some(caps[1].trim_matches(['"', '\'']).to_string())
+    }
+}
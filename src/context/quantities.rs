@@ -0,0 +1,419 @@
+use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+
+/// Spelled-out numbers, as in "the ten largest files" or "two weeks"
+const NUMBER_WORDS: &[(&str, f64)] = &[
+    ("two", 2.0),
+    ("three", 3.0),
+    ("four", 4.0),
+    ("five", 5.0),
+    ("six", 6.0),
+    ("seven", 7.0),
+    ("eight", 8.0),
+    ("nine", 9.0),
+    ("ten", 10.0),
+    ("eleven", 11.0),
+    ("twelve", 12.0),
+    ("fifteen", 15.0),
+    ("twenty", 20.0),
+    ("thirty", 30.0),
+    ("forty", 40.0),
+    ("fifty", 50.0),
+    ("sixty", 60.0),
+    ("ninety", 90.0),
+    ("hundred", 100.0),
+    ("a hundred", 100.0),
+    ("a thousand", 1000.0),
+    ("dozen", 12.0),
+    ("a dozen", 12.0),
+    ("half a dozen", 6.0),
+    ("a couple of", 2.0),
+    ("a couple", 2.0),
+    ("couple of", 2.0),
+    ("half an", 0.5),
+    ("half a", 0.5),
+    ("an", 1.0),
+    ("a", 1.0),
+];
+
+/// Size units in bytes; single letters only count right after digits, in capitals
+const SIZE_UNITS: &[(&str, f64)] = &[
+    ("bytes", 1.0),
+    ("byte", 1.0),
+    ("kilobytes", KIB),
+    ("kilobyte", KIB),
+    ("kibibytes", KIB),
+    ("kib", KIB),
+    ("kb", KIB),
+    ("K", KIB),
+    ("megabytes", MIB),
+    ("megabyte", MIB),
+    ("mebibytes", MIB),
+    ("megs", MIB),
+    ("meg", MIB),
+    ("mib", MIB),
+    ("mb", MIB),
+    ("M", MIB),
+    ("gigabytes", GIB),
+    ("gigabyte", GIB),
+    ("gibibytes", GIB),
+    ("gigs", GIB),
+    ("gig", GIB),
+    ("gib", GIB),
+    ("gb", GIB),
+    ("G", GIB),
+    ("terabytes", TIB),
+    ("terabyte", TIB),
+    ("tib", TIB),
+    ("tb", TIB),
+    ("T", TIB),
+];
+
+/// Duration units in seconds; a lowercase `m` after digits is minutes
+const DURATION_UNITS: &[(&str, f64)] = &[
+    ("seconds", 1.0),
+    ("second", 1.0),
+    ("secs", 1.0),
+    ("sec", 1.0),
+    ("s", 1.0),
+    ("minutes", MINUTE),
+    ("minute", MINUTE),
+    ("mins", MINUTE),
+    ("min", MINUTE),
+    ("m", MINUTE),
+    ("hours", HOUR),
+    ("hour", HOUR),
+    ("hrs", HOUR),
+    ("hr", HOUR),
+    ("h", HOUR),
+    ("days", DAY),
+    ("day", DAY),
+    ("d", DAY),
+    ("weeks", WEEK),
+    ("week", WEEK),
+    ("wks", WEEK),
+    ("wk", WEEK),
+    ("w", WEEK),
+    ("fortnights", 2.0 * WEEK),
+    ("fortnight", 2.0 * WEEK),
+    ("months", 30.0 * DAY),
+    ("month", 30.0 * DAY),
+    ("years", 365.0 * DAY),
+    ("year", 365.0 * DAY),
+    ("yrs", 365.0 * DAY),
+    ("yr", 365.0 * DAY),
+];
+
+const KIB: f64 = 1024.0;
+const MIB: f64 = 1024.0 * KIB;
+const GIB: f64 = 1024.0 * MIB;
+const TIB: f64 = 1024.0 * GIB;
+const MINUTE: f64 = 60.0;
+const HOUR: f64 = 60.0 * MINUTE;
+const DAY: f64 = 24.0 * HOUR;
+const WEEK: f64 = 7.0 * DAY;
+
+/// Words before a size or age that make it a bound rather than an amount
+const MORE_THAN: &[&str] = &[
+    "over",
+    "above",
+    "more than",
+    "larger than",
+    "bigger than",
+    "greater than",
+    "at least",
+    "older than",
+];
+const LESS_THAN: &[&str] = &[
+    "under",
+    "below",
+    "less than",
+    "smaller than",
+    "at most",
+    "within",
+    "in the last",
+    "in the past",
+    "the last",
+    "the past",
+    "last",
+    "past",
+    "newer than",
+    "younger than",
+];
+
+/// Bounds that only make sense for ages: "the last 500 MB" isn't an upper bound
+const AGE_BOUNDS: &[&str] = &[
+    "older than",
+    "within",
+    "in the last",
+    "in the past",
+    "the last",
+    "the past",
+    "last",
+    "past",
+    "newer than",
+    "younger than",
+];
+
+/// A size, duration, or count from the request, with a form commands take
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Quantity {
+    /// As the user wrote it, such as `over 500 megs`
+    pub phrase: String,
+    /// Such as `more than 500 MiB (524288000 bytes; find -size +500M)`
+    pub canonical: String,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Bound {
+    More,
+    Less,
+    Exact,
+}
+
+/// Reads "500 megs", "the last fortnight", or "the ten largest" in a request
+///
+/// Small models get unit conversions and the rounding of `find -size` and
+/// `-mtime` wrong, so the arithmetic is done here and appended to the request.
+pub struct QuantityParser;
+
+impl QuantityParser {
+    pub fn parse(prompt: &str) -> Vec<Quantity> {
+        let mut quantities = Vec::new();
+        let mut taken: Vec<Range<usize>> = Vec::new();
+
+        for caps in Self::measure_pattern().captures_iter(prompt) {
+            let Some(quantity) = Self::measure(&caps) else {
+                continue;
+            };
+            let whole = caps.get(0).expect("whole match");
+            taken.push(whole.range());
+            quantities.push(quantity);
+        }
+
+        // Spelled-out counts, once sizes and durations have their numbers
+        for caps in Self::count_pattern().captures_iter(prompt) {
+            let whole = caps.get(0).expect("whole match");
+            if taken.iter().any(|range| range.contains(&whole.start())) {
+                continue;
+            }
+            let Some(value) = Self::count(&caps) else {
+                continue;
+            };
+            quantities.push(Quantity {
+                phrase: whole.as_str().to_string(),
+                canonical: Self::number(value),
+            });
+        }
+        quantities
+    }
+
+    /// Lines for the prompt
+    pub fn describe(quantities: &[Quantity]) -> Vec<String> {
+        quantities
+            .iter()
+            .map(|quantity| format!("\"{}\" = {}", quantity.phrase, quantity.canonical))
+            .collect()
+    }
+
+    fn measure_pattern() -> Regex {
+        let bounds: Vec<&str> = MORE_THAN.iter().chain(LESS_THAN).copied().collect();
+        let numbers: Vec<&str> = NUMBER_WORDS.iter().map(|(word, _)| *word).collect();
+        let units: Vec<&str> = SIZE_UNITS
+            .iter()
+            .chain(DURATION_UNITS)
+            .map(|(unit, _)| *unit)
+            .collect();
+        Regex::new(&format!(
+            r"(?i)(?:\b(?P<bound>{})\s+)?\b(?P<num>\d{{1,3}}(?:,\d{{3}})+|\d+(?:\.\d+)?|(?:{})\b)?(?P<space>[\s-]*)(?P<unit>{})\b(?P<ago>\s+ago\b)?",
+            bounds.join("|"),
+            numbers.join("|"),
+            units.join("|"),
+        ))
+        .expect("valid regex")
+    }
+
+    fn count_pattern() -> Regex {
+        let numbers: Vec<&str> = NUMBER_WORDS
+            .iter()
+            .map(|(word, _)| *word)
+            .filter(|word| !matches!(*word, "a" | "an" | "half a" | "half an"))
+            .collect();
+        Regex::new(&format!(
+            r"(?i)\b(?:(?P<scaled>{})\s+(?P<word_scale>thousand|million)|(?P<num>\d+(?:\.\d+)?)\s*(?P<scale>k|thousand|million|mil)|(?P<word>{}))\b",
+            numbers.join("|"),
+            numbers.join("|"),
+        ))
+        .expect("valid regex")
+    }
+
+    fn measure(caps: &Captures) -> Option<Quantity> {
+        let phrase = caps.get(0)?.as_str().trim().to_string();
+        let unit = caps.name("unit")?.as_str();
+        let bound = caps.name("bound").map(|b| b.as_str().to_lowercase());
+        let num = caps.name("num").map(|n| n.as_str());
+        let glued = caps.name("space").is_none_or(|s| s.as_str().is_empty());
+
+        let value = match num {
+            Some(num) if num.starts_with(|c: char| c.is_ascii_digit()) => {
+                num.replace(',', "").parse::<f64>().ok()?
+            }
+            Some(num) => {
+                // "a b" or "an m" aren't amounts
+                if unit.len() < 3 {
+                    return None;
+                }
+                Self::word_value(num)?
+            }
+            // "the last fortnight", "past hour"
+            None if bound
+                .as_deref()
+                .is_some_and(|b| b.ends_with("last") || b.ends_with("past"))
+                && unit.len() > 2 =>
+            {
+                1.0
+            }
+            None => return None,
+        };
+
+        // Single letters are too ambiguous apart from digits: `5m` is minutes, `5M` is MiB
+        let single = unit.len() == 1;
+        if single && (!glued || !num.is_some_and(|n| n.starts_with(|c: char| c.is_ascii_digit()))) {
+            return None;
+        }
+        let bound = match bound.as_deref() {
+            Some(b) if MORE_THAN.contains(&b) => Bound::More,
+            Some(b) if LESS_THAN.contains(&b) => Bound::Less,
+            _ => Bound::Exact,
+        };
+
+        if let Some(multiplier) = Self::unit(SIZE_UNITS, unit, single) {
+            let ages = caps
+                .name("bound")
+                .is_some_and(|b| AGE_BOUNDS.contains(&b.as_str().to_lowercase().as_str()));
+            return Some(Quantity {
+                phrase,
+                canonical: Self::size(value * multiplier, if ages { Bound::Exact } else { bound }),
+            });
+        }
+        let multiplier = Self::unit(DURATION_UNITS, unit, single)?;
+        Some(Quantity {
+            phrase,
+            canonical: Self::duration(value * multiplier, bound, caps.name("ago").is_some()),
+        })
+    }
+
+    fn unit(units: &[(&str, f64)], unit: &str, single: bool) -> Option<f64> {
+        units
+            .iter()
+            .find(|(name, _)| match single {
+                true => *name == unit,
+                false => name.eq_ignore_ascii_case(unit),
+            })
+            .map(|(_, multiplier)| *multiplier)
+    }
+
+    fn word_value(word: &str) -> Option<f64> {
+        let word = word.to_lowercase();
+        let word = word.split_whitespace().collect::<Vec<_>>().join(" ");
+        NUMBER_WORDS
+            .iter()
+            .find(|(name, _)| *name == word)
+            .map(|(_, value)| *value)
+    }
+
+    fn count(caps: &Captures) -> Option<f64> {
+        if let Some(word) = caps.name("word") {
+            return Self::word_value(word.as_str());
+        }
+        let (value, scale) = match (caps.name("num"), caps.name("scaled")) {
+            (Some(num), _) => (num.as_str().parse::<f64>().ok()?, caps.name("scale")?),
+            (None, Some(word)) => (Self::word_value(word.as_str())?, caps.name("word_scale")?),
+            _ => return None,
+        };
+        let scale = match scale.as_str().to_lowercase().as_str() {
+            "k" | "thousand" => 1e3,
+            _ => 1e6,
+        };
+        Some(value * scale)
+    }
+
+    /// `more than 500 MiB (524288000 bytes; find -size +500M)`
+    fn size(bytes: f64, bound: Bound) -> String {
+        let bytes = bytes.round() as u64;
+        let human = [(TIB, "TiB"), (GIB, "GiB"), (MIB, "MiB"), (KIB, "KiB")]
+            .iter()
+            .find(|(unit, _)| bytes >= *unit as u64 && bytes.is_multiple_of(*unit as u64))
+            .map(|(unit, name)| format!("{} {name}", bytes / *unit as u64))
+            .unwrap_or_else(|| format!("{bytes} bytes"));
+        // find rounds sizes up to whole units, so `-size -1M` only finds empty files;
+        // an upper bound is given in smaller units
+        let find_units: &[(f64, &str)] = match bound {
+            Bound::Less => &[(KIB, "k")],
+            _ => &[(GIB, "G"), (MIB, "M"), (KIB, "k")],
+        };
+        let find = find_units
+            .iter()
+            .find(|(unit, _)| bytes >= *unit as u64 && bytes.is_multiple_of(*unit as u64))
+            .map(|(unit, suffix)| format!("{}{suffix}", bytes / *unit as u64))
+            .unwrap_or_else(|| format!("{bytes}c"));
+        match bound {
+            Bound::More => format!("more than {human} ({bytes} bytes; find -size +{find})"),
+            Bound::Less => format!("less than {human} ({bytes} bytes; find -size -{find})"),
+            Bound::Exact => format!("{human} ({bytes} bytes)"),
+        }
+    }
+
+    /// `the last 14 days (find -mtime -14; --since "14 days ago")`
+    fn duration(seconds: f64, bound: Bound, ago: bool) -> String {
+        let seconds = seconds.round() as u64;
+        let (amount, unit, short) = [
+            (DAY, "day", "d"),
+            (HOUR, "hour", "h"),
+            (MINUTE, "minute", "m"),
+        ]
+        .iter()
+        .find(|(unit, _, _)| seconds >= *unit as u64 && seconds.is_multiple_of(*unit as u64))
+        .map(|(unit, name, short)| (seconds / *unit as u64, *name, *short))
+        .unwrap_or((seconds, "second", "s"));
+        let plural = if amount == 1 { "" } else { "s" };
+        let span = format!("{amount} {unit}{plural}");
+
+        // -mtime counts whole days, dropping the fraction: +13 is 14 days or more
+        let find = match (bound, unit) {
+            (Bound::Less, "day") => Some(format!("find -mtime -{amount}")),
+            (Bound::More, "day") => Some(format!("find -mtime +{}", amount - 1)),
+            (Bound::Exact, "day") if ago => Some(format!("find -mtime {amount}")),
+            (Bound::Less, "hour" | "minute") => Some(format!("find -mmin -{}", seconds / 60)),
+            (Bound::More, "hour" | "minute") => Some(format!("find -mmin +{}", seconds / 60)),
+            _ => None,
+        };
+        let mut hints: Vec<String> = find.into_iter().collect();
+        match bound {
+            Bound::Less => hints.push(format!("--since \"{span} ago\"")),
+            Bound::More => hints.push(format!("--until \"{span} ago\"")),
+            Bound::Exact if ago => {}
+            Bound::Exact if unit == "second" => hints.push(format!("{amount}s")),
+            Bound::Exact => hints.push(format!("{seconds} seconds, {amount}{short}")),
+        }
+        let hints = match hints.is_empty() {
+            true => String::new(),
+            false => format!(" ({})", hints.join("; ")),
+        };
+        match (bound, ago) {
+            (Bound::Less, _) => format!("the last {span}{hints}"),
+            (Bound::More, _) => format!("more than {span} ago{hints}"),
+            (Bound::Exact, true) => format!("{span} ago{hints}"),
+            (Bound::Exact, false) => format!("{span}{hints}"),
+        }
+    }
+
+    /// Whole numbers without a fraction
+    fn number(value: f64) -> String {
+        match value.fract() == 0.0 {
+            true => format!("{}", value as u64),
+            false => format!("{value}"),
+        }
+    }
+}
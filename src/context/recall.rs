@@ -0,0 +1,140 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::context::cache::{HistoryEntry, HistorySource};
+
+/// Words that carry no meaning when searching for a half-remembered command
+const STOPWORDS: &[&str] = &[
+    "a", "an", "and", "the", "that", "this", "for", "to", "of", "in", "on", "with", "my", "i",
+    "me", "it", "command", "commands", "ran", "run", "used", "use", "how", "do", "did", "what",
+    "was", "which", "from", "into", "thing", "one",
+];
+
+/// A history entry with its relevance score
+#[derive(Debug, Clone)]
+pub struct RecallMatch {
+    pub entry: HistoryEntry,
+    pub score: f64,
+}
+
+/// Ranks history entries against a natural-language description
+pub struct HistorySearch;
+
+impl HistorySearch {
+    /// Returns up to `limit` entries ranked by relevance, deduplicated by command
+    pub fn search(entries: Vec<HistoryEntry>, query: &str, limit: usize) -> Vec<RecallMatch> {
+        let query_terms: Vec<String> = Self::tokenize(query)
+            .into_iter()
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        if query_terms.is_empty() {
+            return Vec::new();
+        }
+
+        // Keep the most recent occurrence of each command
+        let mut unique: HashMap<String, HistoryEntry> = HashMap::new();
+        for entry in entries {
+            let key = entry.command.trim().to_string();
+            match unique.get(&key) {
+                Some(existing) if existing.timestamp >= entry.timestamp => {
+                    // Prefer the commandy record since it carries the prompt
+                    if entry.source == HistorySource::Commandy {
+                        unique.insert(key, entry);
+                    }
+                }
+                _ => {
+                    unique.insert(key, entry);
+                }
+            }
+        }
+
+        let documents: Vec<(HistoryEntry, HashSet<String>)> = unique
+            .into_values()
+            .map(|entry| {
+                let text = format!(
+                    "{} {}",
+                    entry.command,
+                    entry.prompt.as_deref().unwrap_or_default()
+                );
+                let terms = Self::tokenize(&text).into_iter().collect();
+                (entry, terms)
+            })
+            .collect();
+
+        // Inverse document frequency so rare terms like "ffmpeg" dominate
+        let total = documents.len() as f64;
+        let idf: HashMap<&str, f64> = query_terms
+            .iter()
+            .map(|term| {
+                let df = documents
+                    .iter()
+                    .filter(|(_, terms)| Self::matches(term, terms) > 0.0)
+                    .count() as f64;
+                (term.as_str(), ((total + 1.0) / (df + 0.5)).ln().max(0.0))
+            })
+            .collect();
+        let max_score: f64 = idf.values().sum();
+
+        let mut matches: Vec<RecallMatch> = documents
+            .into_iter()
+            .filter_map(|(entry, terms)| {
+                let mut score: f64 = query_terms
+                    .iter()
+                    .map(|term| Self::matches(term, &terms) * idf[term.as_str()])
+                    .sum();
+                if score <= 0.0 || max_score <= 0.0 {
+                    return None;
+                }
+
+                score /= max_score;
+                if entry.source == HistorySource::Commandy {
+                    // Accepted suggestions are known-good
+                    score *= 1.2;
+                }
+
+                Some(RecallMatch { entry, score })
+            })
+            .collect();
+
+        matches.sort_by(|a, b| {
+            b.score
+                .partial_cmp(&a.score)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then(b.entry.timestamp.cmp(&a.entry.timestamp))
+        });
+        matches.truncate(limit);
+        matches
+    }
+
+    /// 1.0 for an exact term match, 0.5 for a prefix match, 0.0 otherwise
+    fn matches(term: &str, terms: &HashSet<String>) -> f64 {
+        if terms.contains(term) {
+            1.0
+        } else if term.len() >= 3
+            && terms
+                .iter()
+                .any(|t| t.len() >= 3 && (t.starts_with(term) || term.starts_with(t.as_str())))
+        {
+            0.5
+        } else {
+            0.0
+        }
+    }
+
+    fn tokenize(text: &str) -> Vec<String> {
+        text.to_lowercase()
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty() && !STOPWORDS.contains(word))
+            .map(Self::stem)
+            .collect()
+    }
+
+    /// Crude plural stripping so "gifs" matches ".gif"
+    fn stem(word: &str) -> String {
+        if word.len() > 3 && word.ends_with('s') && !word.ends_with("ss") {
+            word[..word.len() - 1].to_string()
+        } else {
+            word.to_string()
+        }
+    }
+}
@@ -0,0 +1,359 @@
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::{debug, warn};
+
+/// Environment variable exported by `commandy hook` to identify a shell session
+pub const SESSION_ENV: &str = "COMMANDY_SESSION";
+
+/// Number of recent turns kept per session
+const MAX_TURNS: usize = 5;
+
+/// Number of executed commands kept per session for `commandy session export`
+const MAX_STEPS: usize = 200;
+
+/// Sessions idle for longer than this are considered stale
+const SESSION_TTL_HOURS: i64 = 12;
+
+/// Older turns of a named session kept in its summary, oldest dropped first
+const MAX_SUMMARY_LINES: usize = 20;
+
+/// Directory under `sessions` for named sessions, which never go stale
+const NAMED_DIR: &str = "named";
+
+/// Words that make a prompt depend on what came before it
+const FOLLOW_UP_MARKERS: &[&str] = &[
+    "it", "that", "those", "them", "this", "these", "same", "again", "now", "also", "instead",
+    "too", "previous", "last",
+];
+
+/// A prompt asked earlier in the session and the command that came of it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionTurn {
+    pub prompt: String,
+    pub command: Option<String>,
+    /// Whether the user ran or copied the command rather than just seeing it
+    #[serde(default)]
+    pub accepted: bool,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// A command run through commandy during the session, for runbooks
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionStep {
+    pub prompt: String,
+    pub command: String,
+    /// `None` when the command couldn't be started or was killed by a signal
+    pub exit_code: Option<i32>,
+    pub success: bool,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// A named session as listed by `commandy session list`
+#[derive(Debug, Clone)]
+pub struct NamedSession {
+    pub name: String,
+    /// Recent turns plus those condensed into the summary
+    pub turns: usize,
+    pub updated: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct SessionFile {
+    turns: Vec<SessionTurn>,
+    #[serde(default)]
+    steps: Vec<SessionStep>,
+    /// Older turns of a named session, one line each
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    summary: Vec<String>,
+}
+
+/// Per-shell memory so follow-up prompts can refer back
+///
+/// Shell sessions are short-lived and keyed by $COMMANDY_SESSION. Named
+/// sessions, started with `commandy chat --session <name>`, are kept until
+/// deleted, and condense turns that fall out of the window into a summary.
+pub struct SessionStore {
+    id: String,
+    path: PathBuf,
+    turns: Vec<SessionTurn>,
+    steps: Vec<SessionStep>,
+    summary: Vec<String>,
+    named: bool,
+}
+
+impl SessionStore {
+    /// Opens the session named by $COMMANDY_SESSION, if the shell hook set one
+    pub fn current(commandy_dir: &Path) -> Option<Self> {
+        let id = std::env::var(SESSION_ENV).ok()?;
+        Self::open(commandy_dir, &id)
+    }
+
+    /// Opens a session by id, such as one exported from another shell
+    pub fn open(commandy_dir: &Path, id: &str) -> Option<Self> {
+        // Session ids become file names, so keep them to a safe alphabet
+        let id: String = id
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '_')
+            .collect();
+        if id.is_empty() {
+            return None;
+        }
+
+        let sessions_dir = commandy_dir.join("sessions");
+        Self::prune_stale(&sessions_dir);
+
+        let path = sessions_dir.join(format!("{id}.json"));
+        let file = match Self::read_file(&path, true) {
+            Ok(file) => file,
+            Err(e) => {
+                warn!("Ignoring unreadable session state {path:?}: {e}");
+                SessionFile::default()
+            }
+        };
+
+        Some(Self {
+            id,
+            path,
+            turns: file.turns,
+            steps: file.steps,
+            summary: file.summary,
+            named: false,
+        })
+    }
+
+    /// Opens a named session, creating it on first use
+    pub fn named(commandy_dir: &Path, name: &str) -> Result<Self> {
+        let path = Self::named_path(commandy_dir, name)?;
+        let file = Self::read_file(&path, false)?;
+        Ok(Self {
+            id: name.to_string(),
+            path,
+            turns: file.turns,
+            steps: file.steps,
+            summary: file.summary,
+            named: true,
+        })
+    }
+
+    /// Opens a named session only if it already exists
+    pub fn find_named(commandy_dir: &Path, name: &str) -> Option<Self> {
+        let path = Self::named_path(commandy_dir, name).ok()?;
+        path.exists()
+            .then(|| Self::named(commandy_dir, name).ok())
+            .flatten()
+    }
+
+    /// Named sessions, most recently used first
+    pub fn list_named(commandy_dir: &Path) -> Vec<NamedSession> {
+        let Ok(entries) = fs::read_dir(commandy_dir.join("sessions").join(NAMED_DIR)) else {
+            return Vec::new();
+        };
+        let mut sessions: Vec<NamedSession> = entries
+            .flatten()
+            .filter_map(|entry| {
+                let path = entry.path();
+                let name = path
+                    .file_name()?
+                    .to_str()?
+                    .strip_suffix(".json")?
+                    .to_string();
+                let file = Self::read_file(&path, false).ok()?;
+                Some(NamedSession {
+                    name,
+                    turns: file.turns.len() + file.summary.len(),
+                    updated: file
+                        .turns
+                        .iter()
+                        .map(|t| t.timestamp)
+                        .chain(file.steps.iter().map(|s| s.timestamp))
+                        .max(),
+                })
+            })
+            .collect();
+        sessions.sort_by(|a, b| b.updated.cmp(&a.updated).then_with(|| a.name.cmp(&b.name)));
+        sessions
+    }
+
+    /// Deletes a named session
+    pub fn delete_named(commandy_dir: &Path, name: &str) -> Result<()> {
+        let path = Self::named_path(commandy_dir, name)?;
+        if !path.exists() {
+            anyhow::bail!("No session named '{name}' (see `commandy session list`)");
+        }
+        fs::remove_file(&path)?;
+        Ok(())
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Whether this is a named session rather than a shell's
+    pub fn is_named(&self) -> bool {
+        self.named
+    }
+
+    /// Older turns of a named session, one line each, oldest first
+    pub fn summary(&self) -> &[String] {
+        &self.summary
+    }
+
+    /// Turns from this session, oldest first
+    pub fn turns(&self) -> &[SessionTurn] {
+        &self.turns
+    }
+
+    /// Commands run in this session, oldest first
+    pub fn steps(&self) -> &[SessionStep] {
+        &self.steps
+    }
+
+    /// Records a command that was run and how it exited
+    pub fn record_step(
+        &mut self,
+        prompt: &str,
+        command: &str,
+        success: bool,
+        exit_code: Option<i32>,
+    ) -> Result<()> {
+        self.steps.push(SessionStep {
+            prompt: prompt.to_string(),
+            command: command.to_string(),
+            exit_code,
+            success,
+            timestamp: Utc::now(),
+        });
+        if self.steps.len() > MAX_STEPS {
+            let excess = self.steps.len() - MAX_STEPS;
+            self.steps.drain(..excess);
+        }
+        self.save()
+    }
+
+    /// Records a prompt and the top suggestion shown for it
+    pub fn record_query(&mut self, prompt: &str, command: Option<&str>) -> Result<()> {
+        self.turns.push(SessionTurn {
+            prompt: prompt.to_string(),
+            command: command.map(str::to_string),
+            accepted: false,
+            timestamp: Utc::now(),
+        });
+        if self.turns.len() > MAX_TURNS {
+            let excess = self.turns.len() - MAX_TURNS;
+            let dropped: Vec<SessionTurn> = self.turns.drain(..excess).collect();
+            if self.named {
+                self.summary.extend(dropped.iter().map(Self::condense));
+                if self.summary.len() > MAX_SUMMARY_LINES {
+                    let excess = self.summary.len() - MAX_SUMMARY_LINES;
+                    self.summary.drain(..excess);
+                }
+            }
+        }
+        self.save()
+    }
+
+    /// Marks the command the user actually picked for the most recent matching prompt
+    pub fn record_accepted(&mut self, prompt: &str, command: &str) -> Result<()> {
+        match self.turns.iter_mut().rev().find(|t| t.prompt == prompt) {
+            Some(turn) => {
+                turn.command = Some(command.to_string());
+                turn.accepted = true;
+                turn.timestamp = Utc::now();
+            }
+            None => {
+                self.record_query(prompt, Some(command))?;
+                if let Some(turn) = self.turns.last_mut() {
+                    turn.accepted = true;
+                }
+            }
+        }
+        self.save()
+    }
+
+    /// True when the prompt reads like a continuation of an earlier one
+    pub fn is_follow_up(prompt: &str) -> bool {
+        prompt
+            .to_lowercase()
+            .split(|c: char| !c.is_alphanumeric())
+            .any(|word| FOLLOW_UP_MARKERS.contains(&word))
+    }
+
+    /// One summary line for a turn: the command only if the user took it
+    fn condense(turn: &SessionTurn) -> String {
+        match (&turn.command, turn.accepted) {
+            (Some(command), true) => format!("\"{}\" -> {command}", turn.prompt),
+            _ => format!("\"{}\"", turn.prompt),
+        }
+    }
+
+    /// Where a named session is stored; names become file names, so only
+    /// letters, digits, `-`, and `_` are allowed
+    fn named_path(commandy_dir: &Path, name: &str) -> Result<PathBuf> {
+        let valid = !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if !valid {
+            anyhow::bail!("Invalid session name '{name}': use letters, digits, '-', and '_'");
+        }
+        Ok(commandy_dir
+            .join("sessions")
+            .join(NAMED_DIR)
+            .join(format!("{name}.json")))
+    }
+
+    /// Removes state left behind by shells that have long since exited
+    fn prune_stale(sessions_dir: &Path) {
+        let Ok(entries) = fs::read_dir(sessions_dir) else {
+            return;
+        };
+        let ttl = std::time::Duration::from_secs(SESSION_TTL_HOURS as u64 * 3600);
+
+        for entry in entries.flatten() {
+            let stale = entry
+                .metadata()
+                .and_then(|m| m.modified())
+                .ok()
+                .and_then(|modified| modified.elapsed().ok())
+                .is_some_and(|age| age > ttl);
+            if stale {
+                let _ = fs::remove_file(entry.path());
+            }
+        }
+    }
+
+    /// Reads a session file; `expire` drops turns older than the shell session TTL
+    fn read_file(path: &Path, expire: bool) -> Result<SessionFile> {
+        if !path.exists() {
+            return Ok(SessionFile::default());
+        }
+
+        let mut file: SessionFile = serde_json::from_str(&fs::read_to_string(path)?)?;
+        if expire {
+            let cutoff = Utc::now() - Duration::hours(SESSION_TTL_HOURS);
+            file.turns.retain(|t| t.timestamp > cutoff);
+        }
+        debug!(
+            "Loaded {} session turns and {} steps from {path:?}",
+            file.turns.len(),
+            file.steps.len()
+        );
+        Ok(file)
+    }
+
+    fn save(&self) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let file = SessionFile {
+            turns: self.turns.clone(),
+            steps: self.steps.clone(),
+            summary: self.summary.clone(),
+        };
+        fs::write(&self.path, serde_json::to_string_pretty(&file)?)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,187 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use tracing::{debug, warn};
+
+use crate::context::CacheManager;
+use crate::utils::{PathDisplay, Redactor};
+
+/// File in ~/.commandy the shell hook appends each command to
+pub const SHELL_LOG_FILE: &str = "history.tsv";
+
+/// Bytes read from the end of the log; far more than the commands ever needed
+const TAIL_BYTES: u64 = 64 * 1024;
+
+/// Once the log grows past this it is cut back to its tail
+const MAX_LOG_BYTES: u64 = 1024 * 1024;
+
+/// Longer commands are cut off; the start says what they do
+const MAX_COMMAND_CHARS: usize = 300;
+
+/// A command line the user ran in their shell
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecentCommand {
+    /// The full command line, with secrets redacted unless read unredacted
+    pub command: String,
+    /// When it ran, if the shell recorded it
+    #[serde(default)]
+    pub timestamp: Option<DateTime<Utc>>,
+    /// Directory it ran in; only known from the shell hook
+    #[serde(default)]
+    pub cwd: Option<String>,
+    /// Exit status; only known from the shell hook
+    #[serde(default)]
+    pub exit_code: Option<i32>,
+}
+
+impl RecentCommand {
+    /// `cargo build  (5m ago in ~/src/app, exit 101)`
+    pub fn describe(&self, now: DateTime<Utc>) -> String {
+        let mut details = Vec::new();
+        if let Some(timestamp) = self.timestamp {
+            let secs = (now - timestamp).num_seconds().max(0);
+            details.push(match secs {
+                0..=59 => "just now".to_string(),
+                60..=3599 => format!("{}m ago", secs / 60),
+                3600..=86_399 => format!("{}h ago", secs / 3600),
+                _ => format!("{}d ago", secs / 86_400),
+            });
+        }
+        if let Some(cwd) = &self.cwd {
+            details.push(format!("in {}", PathDisplay::shorten(Path::new(cwd))));
+        }
+        match self.exit_code {
+            Some(0) | None => {}
+            Some(code) => details.push(format!("exit {code}")),
+        }
+
+        if details.is_empty() {
+            self.command.clone()
+        } else {
+            format!("{}  ({})", self.command, details.join(", "))
+        }
+    }
+}
+
+/// Full recent command lines, from the shell hook's log or the shell's history file
+///
+/// The hook (`commandy hook <shell>`) appends `epoch<TAB>exit<TAB>cwd<TAB>command`
+/// after every command, which is the only place cwd and exit status come from.
+/// Without it, the history file still gives full commands and, where the shell
+/// records them, timestamps.
+pub struct ShellLog;
+
+impl ShellLog {
+    pub fn path(commandy_dir: &Path) -> PathBuf {
+        commandy_dir.join(SHELL_LOG_FILE)
+    }
+
+    /// The last `limit` commands, oldest first, with secrets redacted
+    pub fn recent(commandy_dir: &Path, limit: usize) -> Vec<RecentCommand> {
+        let mut commands = Self::recent_unredacted(commandy_dir, limit);
+        for command in &mut commands {
+            command.command = Self::sanitize(&command.command);
+        }
+        commands
+    }
+
+    /// The last `limit` commands as they were typed, for running them again;
+    /// never for prompts
+    pub fn recent_unredacted(commandy_dir: &Path, limit: usize) -> Vec<RecentCommand> {
+        let mut commands = match Self::read_tail(&Self::path(commandy_dir)) {
+            Some(content) => Self::parse(&content),
+            None => Self::from_history_file(),
+        };
+        commands.retain(|c| !c.command.is_empty());
+        let skip = commands.len().saturating_sub(limit);
+        commands.drain(..skip);
+        commands
+    }
+
+    /// Cuts the hook's log back to its tail once it passes `MAX_LOG_BYTES`
+    pub fn compact(commandy_dir: &Path) {
+        let path = Self::path(commandy_dir);
+        let Ok(metadata) = fs::metadata(&path) else {
+            return;
+        };
+        if metadata.len() <= MAX_LOG_BYTES {
+            return;
+        }
+        let Some(tail) = Self::read_tail(&path) else {
+            return;
+        };
+        // Written beside the log and renamed over it, so a concurrent append
+        // loses at most one line instead of truncating the file
+        let temp = path.with_extension("tsv.tmp");
+        match fs::write(&temp, tail).and_then(|_| fs::rename(&temp, &path)) {
+            Ok(()) => debug!("Compacted shell log to {TAIL_BYTES} bytes"),
+            Err(e) => warn!("Failed to compact shell log: {e}"),
+        }
+    }
+
+    /// Masks passwords, tokens, and credentials so they never reach the model
+    pub fn sanitize(command: &str) -> String {
+        let command = Redactor::secrets(command);
+        let command = command.trim();
+        match command.char_indices().nth(MAX_COMMAND_CHARS) {
+            Some((end, _)) => format!("{}...", &command[..end]),
+            None => command.to_string(),
+        }
+    }
+
+    /// The end of the log, starting at a line boundary
+    fn read_tail(path: &Path) -> Option<String> {
+        let mut file = File::open(path).ok()?;
+        let len = file.metadata().ok()?.len();
+        let start = len.saturating_sub(TAIL_BYTES);
+        file.seek(SeekFrom::Start(start)).ok()?;
+        let mut bytes = Vec::new();
+        file.read_to_end(&mut bytes).ok()?;
+
+        let content = String::from_utf8_lossy(&bytes).into_owned();
+        if start == 0 {
+            return Some(content);
+        }
+        // The first line is probably cut in half
+        content.split_once('\n').map(|(_, rest)| rest.to_string())
+    }
+
+    fn parse(content: &str) -> Vec<RecentCommand> {
+        content
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.splitn(4, '\t');
+                let epoch = fields.next()?.trim().parse::<i64>().ok();
+                let exit_code = fields.next()?.trim().parse::<i32>().ok();
+                let cwd = fields.next()?.trim();
+                let command = fields.next()?.trim();
+                Some(RecentCommand {
+                    command: command.to_string(),
+                    timestamp: epoch.and_then(|secs| DateTime::from_timestamp(secs, 0)),
+                    cwd: (!cwd.is_empty()).then(|| cwd.to_string()),
+                    exit_code,
+                })
+            })
+            .collect()
+    }
+
+    fn from_history_file() -> Vec<RecentCommand> {
+        match CacheManager::read_shell_history() {
+            Ok(entries) => entries
+                .into_iter()
+                .map(|entry| RecentCommand {
+                    command: entry.command.trim().to_string(),
+                    timestamp: entry.timestamp,
+                    cwd: None,
+                    exit_code: None,
+                })
+                .collect(),
+            Err(e) => {
+                debug!("No shell history: {e}");
+                Vec::new()
+            }
+        }
+    }
+}
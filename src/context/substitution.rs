@@ -0,0 +1,365 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+use crate::context::ShellLog;
+use crate::postprocess::QuotingCheck;
+use crate::utils::validation::SHELL_BUILTINS;
+
+/// File under ~/.commandy where the shell hook writes the shell's aliases
+pub const ALIASES_FILE: &str = "aliases";
+
+/// File under ~/.commandy where the shell hook lists the shell's functions
+pub const FUNCTIONS_FILE: &str = "functions";
+
+/// Aliases and functions described in the prompt
+const MAX_DEFINITIONS_IN_PROMPT: usize = 15;
+
+/// Commands of the shell log that `!!` and friends can refer to
+const HISTORY_LIMIT: usize = 50;
+
+/// Words that end one simple command and start the next
+const SEPARATORS: &[&str] = &["|", "||", "&&", ";", "&", "|&"];
+
+/// How deep aliases may expand into other aliases
+const MAX_ALIAS_DEPTH: usize = 10;
+
+/// A word of a command line and the bytes it covers
+struct Word<'a> {
+    text: &'a str,
+    start: usize,
+    end: usize,
+}
+
+/// Resolves what the interactive shell would have: history designators such as
+/// `!!` and `!$`, and the user's aliases
+///
+/// Both come from the shell hook. Commands are run with `sh -c`, which knows
+/// neither, so `sudo !!` or an alias like `ll` would otherwise fail or be
+/// rejected as unknown. Functions can't be expanded; commands calling one are
+/// run by the user's shell instead.
+#[derive(Default)]
+pub struct ShellSubstitution {
+    aliases: HashMap<String, String>,
+    /// Names of the shell's functions, without helpers starting with `_`
+    functions: HashSet<String>,
+    /// Previous commands, oldest first, as they were typed
+    previous: Vec<String>,
+}
+
+impl ShellSubstitution {
+    pub fn load(commandy_dir: &Path) -> Self {
+        let aliases = fs::read_to_string(commandy_dir.join(ALIASES_FILE))
+            .map(|content| Self::parse_aliases(&content))
+            .unwrap_or_default();
+        let functions = fs::read_to_string(commandy_dir.join(FUNCTIONS_FILE))
+            .map(|content| {
+                content
+                    .lines()
+                    .map(str::trim)
+                    .filter(|name| !name.is_empty() && !name.starts_with('_'))
+                    .map(String::from)
+                    .collect()
+            })
+            .unwrap_or_default();
+        let previous = ShellLog::recent_unredacted(commandy_dir, HISTORY_LIMIT)
+            .into_iter()
+            .map(|recent| recent.command)
+            .filter(|command| {
+                let first = command.split_whitespace().next().unwrap_or("");
+                first != "commandy" && first != "oops"
+            })
+            .collect();
+        Self {
+            aliases,
+            functions,
+            previous,
+        }
+    }
+
+    /// Whether the user's shell defines `name` as an alias or function
+    pub fn defines(&self, name: &str) -> bool {
+        self.aliases.contains_key(name) || self.functions.contains(name)
+    }
+
+    /// Whether the command calls one of the user's functions, so only their
+    /// shell can run it
+    pub fn calls_function(&self, command: &str) -> bool {
+        !self.functions.is_empty()
+            && Self::command_words(command)
+                .into_iter()
+                .any(|word| self.functions.contains(word.text) && self.should_expand(word.text))
+    }
+
+    /// Aliases and functions for the prompt, those sharing a word with it first
+    ///
+    /// Aliases that only add options to a program of the same name are left out,
+    /// and values have their secrets redacted.
+    pub fn definitions_for(&self, prompt: &str) -> Vec<String> {
+        let prompt_words: HashSet<String> = prompt
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| word.len() >= 3)
+            .map(str::to_lowercase)
+            .collect();
+        let mentioned = |text: &str| {
+            text.split(|c: char| !c.is_alphanumeric())
+                .any(|word| prompt_words.contains(&word.to_lowercase()))
+        };
+
+        let mut definitions: Vec<(bool, String)> = self
+            .aliases
+            .iter()
+            .filter(|(name, _)| self.should_expand(name))
+            .map(|(name, value)| {
+                let value = ShellLog::sanitize(value);
+                (
+                    mentioned(name) || mentioned(&value),
+                    format!("{name}: {value}"),
+                )
+            })
+            .chain(
+                self.functions
+                    .iter()
+                    .filter(|name| self.should_expand(name))
+                    .map(|name| (mentioned(name), format!("{name} (function)"))),
+            )
+            .collect();
+        definitions.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+        definitions
+            .into_iter()
+            .take(MAX_DEFINITIONS_IN_PROMPT)
+            .map(|(_, line)| line)
+            .collect()
+    }
+
+    /// The command with history designators and aliases expanded
+    pub fn resolve(&self, command: &str) -> String {
+        let command = Self::expand_designators(command, &self.previous);
+        self.expand_aliases(&command, &HashSet::new(), 0)
+    }
+
+    /// A fix for `failed` resolved like [`Self::resolve`], with `!!` meaning `failed`
+    ///
+    /// The command being fixed needn't be the last one the shell ran, as with
+    /// `commandy oops -- <command>`.
+    pub fn resolve_fix(&self, command: &str, failed: &str) -> String {
+        let mut previous = self.previous.clone();
+        if previous.last().map(String::as_str) != Some(failed.trim()) {
+            previous.push(failed.trim().to_string());
+        }
+        let command = Self::expand_designators(command, &previous);
+        self.expand_aliases(&command, &HashSet::new(), 0)
+    }
+
+    /// The user's request with history designators expanded, for the prompt
+    ///
+    /// Previous commands have their secrets redacted here, since the request
+    /// is sent to the model.
+    pub fn resolve_query(&self, query: &str) -> String {
+        if !query.contains('!') {
+            return query.to_string();
+        }
+        let previous: Vec<String> = self
+            .previous
+            .iter()
+            .map(|command| ShellLog::sanitize(command))
+            .collect();
+        Self::expand_designators(query, &previous)
+    }
+
+    /// Aliases as printed by bash's `alias -p`, zsh's `alias -L`, or fish's `alias`
+    fn parse_aliases(content: &str) -> HashMap<String, String> {
+        let mut aliases = HashMap::new();
+        for line in content.lines() {
+            let Some(rest) = line.trim().strip_prefix("alias ") else {
+                continue;
+            };
+            let rest = rest.trim_start();
+            let rest = rest.strip_prefix("-- ").unwrap_or(rest);
+            // zsh's global and suffix aliases don't apply at the start of a command
+            if rest.starts_with('-') {
+                continue;
+            }
+            // bash and zsh print `name='value'`, fish prints `name 'value'`
+            let Some(split) = rest.find(['=', ' ']) else {
+                continue;
+            };
+            let name = &rest[..split];
+            let value: Vec<char> = rest[split + 1..].trim().chars().collect();
+            let value = QuotingCheck::dequote(&value);
+            if !name.is_empty() && !value.trim().is_empty() {
+                aliases.insert(name.to_string(), value);
+            }
+        }
+        aliases
+    }
+
+    /// Replaces `!!`, `!$`, `!^`, `!*`, and `!-n` with parts of previous commands
+    ///
+    /// Like the shell, designators are expanded outside quotes and inside double
+    /// quotes, but not in single quotes or after a backslash. One whose command
+    /// isn't in the log is left as it is.
+    fn expand_designators(line: &str, previous: &[String]) -> String {
+        let chars: Vec<char> = line.chars().collect();
+        let (states, _) = QuotingCheck::quote_states(&chars);
+        let mut expanded = String::with_capacity(line.len());
+        let mut i = 0;
+        while i < chars.len() {
+            let expands = chars[i] == '!'
+                && matches!(states[i], None | Some('"'))
+                && (i == 0 || chars[i - 1] != '$');
+            if expands {
+                if let Some((text, len)) = Self::designator(&chars[i + 1..], previous) {
+                    expanded.push_str(&text);
+                    i += 1 + len;
+                    continue;
+                }
+            }
+            expanded.push(chars[i]);
+            i += 1;
+        }
+        expanded
+    }
+
+    /// What the designator after a `!` stands for, and how many characters it has
+    fn designator(rest: &[char], previous: &[String]) -> Option<(String, usize)> {
+        let (back, len, part) = match rest.first()? {
+            '!' => (1, 1, None),
+            c @ ('$' | '^' | '*') => (1, 1, Some(*c)),
+            '-' => {
+                let digits = rest[1..].iter().take_while(|c| c.is_ascii_digit()).count();
+                let back: usize = rest[1..1 + digits]
+                    .iter()
+                    .collect::<String>()
+                    .parse()
+                    .ok()?;
+                (back, 1 + digits, None)
+            }
+            _ => return None,
+        };
+        let command = previous
+            .len()
+            .checked_sub(back)
+            .and_then(|i| previous.get(i))?;
+        let text = match part {
+            None => command.clone(),
+            Some(part) => {
+                let words: Vec<&str> = Self::words(command).iter().map(|w| w.text).collect();
+                match part {
+                    '$' => words.last()?.to_string(),
+                    '^' => words.get(1)?.to_string(),
+                    _ => words.get(1..).unwrap_or_default().join(" "),
+                }
+            }
+        };
+        Some((text, len))
+    }
+
+    /// Replaces aliases where a command starts, the way the shell does
+    ///
+    /// Names that are also installed programs or builtins are kept, since the
+    /// alias only adds options the suggestion didn't ask for.
+    fn expand_aliases(&self, line: &str, seen: &HashSet<&str>, depth: usize) -> String {
+        if self.aliases.is_empty() || depth > MAX_ALIAS_DEPTH {
+            return line.to_string();
+        }
+        let mut expanded = String::with_capacity(line.len());
+        let mut copied = 0;
+        let mut command_start = true;
+        for word in Self::words(line) {
+            if SEPARATORS.contains(&word.text) {
+                command_start = true;
+                continue;
+            }
+            if !command_start {
+                continue;
+            }
+            // Assignments such as `LANG=C` come before the command
+            if Self::is_assignment(word.text) {
+                continue;
+            }
+            command_start = false;
+            let Some((name, value)) = self.aliases.get_key_value(word.text) else {
+                continue;
+            };
+            if seen.contains(name.as_str()) || !self.should_expand(name) {
+                continue;
+            }
+            let mut inner = seen.clone();
+            inner.insert(name);
+            expanded.push_str(&line[copied..word.start]);
+            expanded.push_str(self.expand_aliases(value, &inner, depth + 1).trim_end());
+            copied = word.end;
+            // An alias ending in a space makes the next word a command too, as with sudo
+            if value.ends_with(' ') {
+                command_start = true;
+            }
+        }
+        expanded.push_str(&line[copied..]);
+        expanded
+    }
+
+    /// The words of a line that name the command to run
+    fn command_words(line: &str) -> Vec<Word<'_>> {
+        let mut command_start = true;
+        Self::words(line)
+            .into_iter()
+            .filter(|word| {
+                if SEPARATORS.contains(&word.text) {
+                    command_start = true;
+                    return false;
+                }
+                let is_command = command_start && !Self::is_assignment(word.text);
+                if is_command {
+                    command_start = false;
+                }
+                is_command
+            })
+            .collect()
+    }
+
+    /// Whether the name would reach the user's definition instead of a program
+    fn should_expand(&self, name: &str) -> bool {
+        let quoted = name.contains(['\'', '"', '\\', '/', '$']);
+        !quoted && !SHELL_BUILTINS.contains(&name) && which::which(name).is_err()
+    }
+
+    fn is_assignment(word: &str) -> bool {
+        word.split_once('=').is_some_and(|(name, _)| {
+            !name.is_empty()
+                && !name.starts_with(|c: char| c.is_ascii_digit())
+                && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        })
+    }
+
+    /// Words of a command line, split on whitespace outside quotes
+    fn words(line: &str) -> Vec<Word<'_>> {
+        let chars: Vec<(usize, char)> = line.char_indices().collect();
+        let plain: Vec<char> = chars.iter().map(|(_, c)| *c).collect();
+        let (states, _) = QuotingCheck::quote_states(&plain);
+
+        let mut words = Vec::new();
+        let mut start = None;
+        for ((offset, c), state) in chars.iter().zip(&states) {
+            if c.is_whitespace() && state.is_none() {
+                if let Some(s) = start.take() {
+                    words.push(Word {
+                        text: &line[s..*offset],
+                        start: s,
+                        end: *offset,
+                    });
+                }
+            } else if start.is_none() {
+                start = Some(*offset);
+            }
+        }
+        if let Some(s) = start {
+            words.push(Word {
+                text: &line[s..],
+                start: s,
+                end: line.len(),
+            });
+        }
+        words
+    }
+}
@@ -0,0 +1,245 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// What kind of task a query (or command) is about
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Category {
+    FileOps,
+    Git,
+    Containers,
+    Networking,
+    TextProcessing,
+    SystemAdmin,
+    #[default]
+    General,
+}
+
+impl Category {
+    pub const ALL: [Category; 7] = [
+        Category::FileOps,
+        Category::Git,
+        Category::Containers,
+        Category::Networking,
+        Category::TextProcessing,
+        Category::SystemAdmin,
+        Category::General,
+    ];
+
+    /// Human-readable name, also used for `commandy learned` sections
+    pub fn label(self) -> &'static str {
+        match self {
+            Category::FileOps => "File Operations",
+            Category::Git => "Git",
+            Category::Containers => "Containers",
+            Category::Networking => "Networking",
+            Category::TextProcessing => "Text Processing",
+            Category::SystemAdmin => "System Administration",
+            Category::General => "General",
+        }
+    }
+
+    /// Short machine name used in stats and APIs
+    pub fn slug(self) -> &'static str {
+        match self {
+            Category::FileOps => "file_ops",
+            Category::Git => "git",
+            Category::Containers => "containers",
+            Category::Networking => "networking",
+            Category::TextProcessing => "text_processing",
+            Category::SystemAdmin => "system_admin",
+            Category::General => "general",
+        }
+    }
+
+    /// Matches a label or slug, as returned by the fast model
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.trim().to_lowercase();
+        Self::ALL
+            .into_iter()
+            .find(|c| c.label().to_lowercase() == name || c.slug() == name)
+    }
+
+    /// Few-shot examples shown to the model for this kind of task
+    pub fn examples(self) -> &'static [&'static str] {
+        match self {
+            Category::FileOps => &[
+                "find . -type f -size +100M",
+                "du -sh * | sort -rh | head",
+                "tar -czf backup.tar.gz src/",
+            ],
+            Category::Git => &[
+                "git log --oneline -10",
+                "git switch -c feature/login",
+                "git diff --staged",
+            ],
+            Category::Containers => &[
+                "docker ps -a",
+                "docker logs -f web",
+                "kubectl get pods -n default",
+            ],
+            Category::Networking => &[
+                "curl -I https://example.com",
+                "ss -tlnp",
+                "dig +short example.com",
+            ],
+            Category::TextProcessing => &[
+                "grep -rn \"error\" /var/log/",
+                "sed -i 's/old/new/g' config.txt",
+                "jq '.items[].name' data.json",
+            ],
+            Category::SystemAdmin => &[
+                "systemctl status nginx",
+                "ps aux --sort=-%mem | head",
+                "df -h",
+            ],
+            Category::General => &[
+                "docker ps -a",
+                "ls -la /var/log",
+                "grep -r \"error\" /var/log/",
+            ],
+        }
+    }
+
+    /// Extra instruction added to the prompt for this kind of task
+    pub fn prompt_hint(self) -> Option<&'static str> {
+        match self {
+            Category::FileOps => {
+                Some("Quote paths that may contain spaces; prefer non-destructive flags.")
+            }
+            Category::Git => Some(
+                "Prefer current porcelain commands (git switch, git restore); \
+                never force-push unless asked.",
+            ),
+            Category::Containers => Some(
+                "Use `docker compose` (v2) rather than `docker-compose`; \
+                name the namespace for kubectl when relevant.",
+            ),
+            Category::Networking => {
+                Some("Prefer ss and ip over netstat and ifconfig; limit ping with -c.")
+            }
+            Category::TextProcessing => {
+                Some("Quote patterns with single quotes; keep pipelines short.")
+            }
+            Category::SystemAdmin => {
+                Some("Use systemctl for services; add sudo only where root is required.")
+            }
+            Category::General => None,
+        }
+    }
+
+    /// Executables that point at this category, separated by spaces
+    fn executables(self) -> &'static str {
+        match self {
+            Category::FileOps => "ls cp mv rm mkdir touch chmod chown ln du tar zip unzip rsync",
+            Category::Git => "git gh",
+            Category::Containers => "docker podman kubectl helm minikube docker-compose",
+            Category::Networking => {
+                "curl wget ping ssh scp netstat ss dig nslookup nc traceroute nmap ifconfig ip"
+            }
+            Category::TextProcessing => "grep rg sed awk sort uniq wc cut head tail jq yq",
+            Category::SystemAdmin => {
+                "systemctl service journalctl ps htop free kill pkill pgrep df uptime apt apt-get \
+                 dnf yum pacman brew useradd crontab lsblk dmesg"
+            }
+            Category::General => "",
+        }
+    }
+
+    /// Words in a query that point at this category, separated by spaces
+    fn keywords(self) -> &'static str {
+        match self {
+            Category::FileOps => {
+                "find tree file files directory directories folder folders copy move rename delete \
+                 permission permissions executable archive compress extract symlink hidden largest \
+                 biggest"
+            }
+            Category::Git => {
+                "commit commits branch branches merge rebase stash repo repository push pull \
+                 checkout staged clone remote cherry-pick blame tag discard uncommitted"
+            }
+            Category::Containers => {
+                "container containers image images pod pods kubernetes k8s deployment namespace \
+                 namespaces compose dockerfile cluster"
+            }
+            Category::Networking => {
+                "port ports network address addresses dns http https url download upload listening \
+                 reachable connection connections interface firewall proxy"
+            }
+            Category::TextProcessing => {
+                "text line lines word words replace search pattern regex count json yaml csv column \
+                 columns occurrences duplicates recursively follow log logs"
+            }
+            Category::SystemAdmin => {
+                "top mount process processes services memory cpu disk install package packages user \
+                 users cron kernel reboot running daemon"
+            }
+            Category::General => "",
+        }
+    }
+}
+
+impl fmt::Display for Category {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.label())
+    }
+}
+
+/// A category and how sure the rules were about it
+#[derive(Debug, Clone, Copy)]
+pub struct Classification {
+    pub category: Category,
+    /// Share of the keyword score that went to the winning category
+    pub confidence: f32,
+}
+
+impl Classification {
+    /// Clear enough that asking a model wouldn't help
+    pub fn is_confident(&self) -> bool {
+        self.category != Category::General && self.confidence >= 0.6
+    }
+}
+
+/// Keyword rules that tag queries and commands with a [`Category`]
+pub struct TaxonomyClassifier;
+
+impl TaxonomyClassifier {
+    pub fn classify(text: &str) -> Classification {
+        let text = text.to_lowercase();
+        let words: Vec<&str> = text
+            .split(|c: char| !(c.is_alphanumeric() || c == '-' || c == '_'))
+            .filter(|w| !w.is_empty())
+            .collect();
+
+        let mut scores: Vec<(Category, u32)> = Category::ALL
+            .into_iter()
+            .map(|category| {
+                let count = |list: &str| {
+                    list.split_whitespace()
+                        .filter(|w| words.contains(w))
+                        .count()
+                };
+                // Naming a tool is a much stronger signal than a topic word
+                let mut score = count(category.executables()) * 3 + count(category.keywords());
+                if category == Category::Networking && text.contains("://") {
+                    score += 3;
+                }
+                (category, score as u32)
+            })
+            .collect();
+        // Stable, so ties go to the earlier category
+        scores.sort_by_key(|(_, score)| std::cmp::Reverse(*score));
+
+        let total: u32 = scores.iter().map(|(_, score)| score).sum();
+        match scores.first() {
+            Some(&(category, best)) if best > 0 => Classification {
+                category,
+                confidence: best as f32 / total as f32,
+            },
+            _ => Classification {
+                category: Category::General,
+                confidence: 0.0,
+            },
+        }
+    }
+}
@@ -0,0 +1,312 @@
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::utils::PathDisplay;
+
+/// Files that select tool versions for their directory and the ones below it,
+/// nearest first within a directory
+const VERSION_FILES: &[&str] = &[
+    "mise.local.toml",
+    "mise.toml",
+    ".mise.toml",
+    ".config/mise.toml",
+    ".tool-versions",
+];
+
+/// Executables named per directory a project puts on PATH
+const MAX_EXECUTABLES_PER_DIR: usize = 12;
+
+/// Tools with a version selected for this directory
+const MAX_PINNED_TOOLS: usize = 20;
+
+/// A tool version selected by `.tool-versions` or `mise.toml`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PinnedTool {
+    pub name: String,
+    pub version: String,
+    /// The file that selects it, such as `.tool-versions` or `~/.tool-versions`
+    pub file: String,
+}
+
+/// A directory the project puts on PATH, and what can be run from it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LocalBin {
+    /// Relative to the current directory when below it
+    pub dir: String,
+    pub executables: Vec<String>,
+}
+
+/// Tools this directory activates through direnv, asdf, or mise
+///
+/// The PATH and the tool list gathered at `commandy init` are global. A
+/// project's `.envrc` may add its own `bin/` or `node_modules/.bin`, and its
+/// `.tool-versions` decides which version a shim runs, or whether it runs at all.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ProjectToolchain {
+    /// Nearest selection of each tool first
+    pub pinned: Vec<PinnedTool>,
+    pub local_bins: Vec<LocalBin>,
+    /// The `.envrc` that applies here, when direnv hasn't loaded it
+    pub unloaded_envrc: Option<String>,
+}
+
+impl ProjectToolchain {
+    /// What the current directory and the ones above it activate; `None` when nothing
+    pub fn detect() -> Option<Self> {
+        let cwd = env::current_dir().ok()?;
+        let toolchain = Self::scan(&cwd);
+        let empty = toolchain.pinned.is_empty()
+            && toolchain.local_bins.is_empty()
+            && toolchain.unloaded_envrc.is_none();
+        (!empty).then_some(toolchain)
+    }
+
+    /// Lines for the prompt
+    pub fn describe(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        if !self.pinned.is_empty() {
+            // Group the tools by the file that selects them
+            let mut groups: Vec<(&str, Vec<String>)> = Vec::new();
+            for tool in &self.pinned {
+                let entry = format!("{} {}", tool.name, tool.version);
+                match groups.iter_mut().find(|(file, _)| *file == tool.file) {
+                    Some((_, tools)) => tools.push(entry),
+                    None => groups.push((&tool.file, vec![entry])),
+                }
+            }
+            let groups: Vec<String> = groups
+                .into_iter()
+                .map(|(file, tools)| format!("{} ({file})", tools.join(", ")))
+                .collect();
+            lines.push(format!("Versions selected here: {}", groups.join("; ")));
+        }
+        if !self.local_bins.is_empty() {
+            let bins: Vec<String> = self
+                .local_bins
+                .iter()
+                .map(|bin| match bin.executables.is_empty() {
+                    true => bin.dir.clone(),
+                    false => format!("{} ({})", bin.dir, bin.executables.join(", ")),
+                })
+                .collect();
+            lines.push(format!(
+                "Directories the project adds to PATH: {}",
+                bins.join("; ")
+            ));
+        }
+        if let Some(envrc) = &self.unloaded_envrc {
+            lines.push(format!(
+                "direnv hasn't loaded {envrc}: run project tools with `direnv exec . <command>`"
+            ));
+        }
+        lines
+    }
+
+    fn scan(cwd: &Path) -> Self {
+        let home = dirs::home_dir();
+        let mut toolchain = Self::default();
+        let mut envrc_seen = false;
+        for dir in cwd.ancestors() {
+            for file in VERSION_FILES {
+                let path = dir.join(file);
+                let Ok(text) = fs::read_to_string(&path) else {
+                    continue;
+                };
+                let label = Self::label(cwd, &path);
+                let tools = match *file {
+                    ".tool-versions" => Self::parse_tool_versions(&text),
+                    _ => {
+                        let (tools, paths) = Self::parse_mise(&text);
+                        toolchain.add_bins(cwd, paths.iter().map(|p| dir.join(p)));
+                        tools
+                    }
+                };
+                for (name, version) in tools {
+                    // A nearer file's version wins
+                    if !toolchain.pinned.iter().any(|tool| tool.name == name) {
+                        toolchain.pinned.push(PinnedTool {
+                            name,
+                            version,
+                            file: label.clone(),
+                        });
+                    }
+                }
+            }
+
+            // direnv only loads the nearest .envrc
+            let envrc = dir.join(".envrc");
+            if !envrc_seen && envrc.is_file() {
+                envrc_seen = true;
+                if let Ok(text) = fs::read_to_string(&envrc) {
+                    toolchain.add_bins(cwd, Self::parse_envrc(&text, dir));
+                }
+                if !Self::direnv_loaded(dir) {
+                    toolchain.unloaded_envrc = Some(Self::label(cwd, &envrc));
+                }
+            }
+
+            if home.as_deref() == Some(dir) {
+                break;
+            }
+        }
+        toolchain.pinned.truncate(MAX_PINNED_TOOLS);
+        toolchain
+    }
+
+    fn add_bins(&mut self, cwd: &Path, dirs: impl IntoIterator<Item = PathBuf>) {
+        for dir in dirs {
+            if !dir.is_dir() {
+                continue;
+            }
+            let label = Self::label(cwd, &dir);
+            if self.local_bins.iter().any(|bin| bin.dir == label) {
+                continue;
+            }
+            self.local_bins.push(LocalBin {
+                executables: Self::executables(&dir),
+                dir: label,
+            });
+        }
+    }
+
+    /// `nodejs 20.11.0` lines; a tool may list fallback versions after the first
+    fn parse_tool_versions(text: &str) -> Vec<(String, String)> {
+        text.lines()
+            .map(|line| line.split('#').next().unwrap_or("").trim())
+            .filter_map(|line| {
+                let mut words = line.split_whitespace();
+                Some((words.next()?.to_string(), words.next()?.to_string()))
+            })
+            .collect()
+    }
+
+    /// The `[tools]` table, and directories `[env] _.path` adds to PATH
+    fn parse_mise(text: &str) -> (Vec<(String, String)>, Vec<String>) {
+        let Ok(config) = text.parse::<toml::Table>() else {
+            return (Vec::new(), Vec::new());
+        };
+        let tools = config
+            .get("tools")
+            .and_then(toml::Value::as_table)
+            .map(|tools| {
+                tools
+                    .iter()
+                    .filter_map(|(name, value)| {
+                        let version = match value {
+                            toml::Value::String(version) => version.clone(),
+                            toml::Value::Array(versions) => versions.first()?.as_str()?.to_string(),
+                            toml::Value::Table(options) => {
+                                options.get("version")?.as_str()?.to_string()
+                            }
+                            _ => return None,
+                        };
+                        // Backends such as `npm:prettier` or `aqua:cli/cli` install the last part
+                        let name = name.rsplit([':', '/']).next().unwrap_or(name);
+                        Some((name.to_string(), version))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        let paths = match config
+            .get("env")
+            .and_then(|env| env.get("_"))
+            .and_then(|special| special.get("path"))
+        {
+            Some(toml::Value::String(path)) => vec![path.clone()],
+            Some(toml::Value::Array(paths)) => paths
+                .iter()
+                .filter_map(|path| path.as_str().map(String::from))
+                .collect(),
+            _ => Vec::new(),
+        };
+        let paths = paths
+            .into_iter()
+            .map(|path| {
+                path.replace("{{config_root}}", ".")
+                    .replace("{{ config_root }}", ".")
+            })
+            .collect();
+        (tools, paths)
+    }
+
+    /// Directories `PATH_add` and `layout node` or `layout python` put on PATH
+    fn parse_envrc(text: &str, dir: &Path) -> Vec<PathBuf> {
+        let mut paths = Vec::new();
+        for line in text.lines() {
+            let words: Vec<&str> = line
+                .split('#')
+                .next()
+                .unwrap_or("")
+                .split_whitespace()
+                .collect();
+            match words.as_slice() {
+                ["PATH_add", added @ ..] => paths.extend(
+                    added
+                        .iter()
+                        .map(|path| dir.join(path.trim_matches(['"', '\'']))),
+                ),
+                ["layout", "node", ..] => paths.push(dir.join("node_modules/.bin")),
+                ["layout", python, ..] if python.starts_with("python") => {
+                    // The virtualenv is named after the Python version
+                    let venvs = fs::read_dir(dir.join(".direnv")).into_iter().flatten();
+                    paths.extend(
+                        venvs
+                            .flatten()
+                            .filter(|entry| {
+                                entry.file_name().to_string_lossy().starts_with("python")
+                            })
+                            .map(|entry| entry.path().join("bin")),
+                    );
+                }
+                _ => {}
+            }
+        }
+        paths
+    }
+
+    /// Whether the shell commandy runs in has direnv's environment for `dir`
+    fn direnv_loaded(dir: &Path) -> bool {
+        env::var("DIRENV_DIR").is_ok_and(|loaded| Path::new(loaded.trim_start_matches('-')) == dir)
+    }
+
+    /// Names of the executable files in a directory, sorted
+    fn executables(dir: &Path) -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(dir)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter(|entry| Self::is_executable(&entry.path()))
+            .filter_map(|entry| entry.file_name().to_str().map(String::from))
+            .filter(|name| !name.starts_with('.'))
+            .collect();
+        names.sort();
+        names.truncate(MAX_EXECUTABLES_PER_DIR);
+        names
+    }
+
+    fn is_executable(path: &Path) -> bool {
+        // Follows symlinks, as node_modules/.bin is made of them
+        let Ok(metadata) = fs::metadata(path) else {
+            return false;
+        };
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            metadata.is_file() && metadata.permissions().mode() & 0o111 != 0
+        }
+        #[cfg(not(unix))]
+        {
+            metadata.is_file()
+        }
+    }
+
+    /// A path relative to the current directory when below it, else shortened
+    fn label(cwd: &Path, path: &Path) -> String {
+        match path.strip_prefix(cwd) {
+            Ok(relative) => relative.display().to_string(),
+            Err(_) => PathDisplay::shorten(path),
+        }
+    }
+}
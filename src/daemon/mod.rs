@@ -0,0 +1,5 @@
+pub mod state;
+pub mod supervisor;
+
+pub use state::{Control, DaemonFiles, DaemonState, Restart};
+pub use supervisor::Supervisor;
@@ -0,0 +1,159 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+
+use crate::utils::MemoryInfo;
+
+/// Size at which the log is moved to llama-server.log.1 when llama-server starts
+const MAX_LOG_BYTES: u64 = 10 * 1024 * 1024;
+
+/// What the supervisor last saw, rewritten after every check
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DaemonState {
+    /// The supervisor's own process
+    pub pid: u32,
+    pub url: String,
+    pub started_at: DateTime<Utc>,
+    pub server_pid: Option<u32>,
+    pub server_started_at: Option<DateTime<Utc>>,
+    /// The last health check passed
+    pub healthy: bool,
+    /// llama-server answers but is still loading the model
+    #[serde(default)]
+    pub loading: bool,
+    pub last_check: Option<DateTime<Utc>>,
+    pub rss_mb: Option<u64>,
+    pub restarts: u32,
+    pub last_restart: Option<Restart>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Restart {
+    pub at: DateTime<Utc>,
+    pub reason: String,
+}
+
+/// A request from `commandy daemon` to the running supervisor
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Control {
+    Restart,
+    Stop,
+}
+
+impl Control {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Restart => "restart",
+            Self::Stop => "stop",
+        }
+    }
+}
+
+/// Files in ~/.commandy/daemon through which the supervisor and the
+/// `commandy daemon` commands talk: its state, its log, and a control file
+pub struct DaemonFiles {
+    dir: PathBuf,
+}
+
+impl DaemonFiles {
+    pub fn new() -> Result<Self> {
+        let home = dirs::home_dir().context("Could not find home directory")?;
+        Ok(Self {
+            dir: home.join(".commandy").join("daemon"),
+        })
+    }
+
+    /// llama-server's output, with the supervisor's restarts in between
+    pub fn log_path(&self) -> PathBuf {
+        self.dir.join("llama-server.log")
+    }
+
+    fn state_path(&self) -> PathBuf {
+        self.dir.join("state.json")
+    }
+
+    fn control_path(&self) -> PathBuf {
+        self.dir.join("control")
+    }
+
+    /// Creates the directory, private since the control file steers the daemon
+    pub fn create_dir(&self) -> Result<()> {
+        fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Failed to create {}", self.dir.display()))?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&self.dir, fs::Permissions::from_mode(0o700))?;
+        }
+        Ok(())
+    }
+
+    /// The state of a supervisor that is still running
+    pub fn running(&self) -> Option<DaemonState> {
+        let text = fs::read_to_string(self.state_path()).ok()?;
+        let state: DaemonState = serde_json::from_str(&text).ok()?;
+        MemoryInfo::process_running(state.pid).then_some(state)
+    }
+
+    pub fn write_state(&self, state: &DaemonState) -> Result<()> {
+        // Written whole and renamed, so readers never see half a file
+        let staging = self.dir.join("state.json.tmp");
+        fs::write(&staging, serde_json::to_string_pretty(state)?)?;
+        fs::rename(&staging, self.state_path())?;
+        Ok(())
+    }
+
+    pub fn clear_state(&self) {
+        let _ = fs::remove_file(self.state_path());
+    }
+
+    pub fn request(&self, control: Control) -> Result<()> {
+        fs::write(self.control_path(), control.as_str()).context("Failed to signal the daemon")
+    }
+
+    /// A pending request, removed as it is read
+    pub fn take_request(&self) -> Option<Control> {
+        let text = fs::read_to_string(self.control_path()).ok()?;
+        let _ = fs::remove_file(self.control_path());
+        match text.trim() {
+            "restart" => Some(Control::Restart),
+            "stop" => Some(Control::Stop),
+            _ => None,
+        }
+    }
+
+    /// Keeps one older log, so a server that is restarted often can't fill the disk
+    pub fn rotate_log(&self) {
+        let path = self.log_path();
+        if fs::metadata(&path).is_ok_and(|m| m.len() > MAX_LOG_BYTES) {
+            let _ = fs::rename(&path, path.with_extension("log.1"));
+        }
+    }
+
+    /// Adds a supervisor event to the log, between llama-server's own lines
+    pub fn log_event(&self, message: &str) {
+        let line = format!(
+            "[commandy {}] {message}\n",
+            Utc::now().format("%Y-%m-%d %H:%M:%S UTC")
+        );
+        if let Ok(mut file) = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.log_path())
+        {
+            let _ = file.write_all(line.as_bytes());
+        }
+    }
+
+    /// The last `lines` lines of the log
+    pub fn tail_log(&self, lines: usize) -> Result<String> {
+        let text = fs::read(self.log_path())
+            .with_context(|| format!("No daemon log at {}", self.log_path().display()))?;
+        let text = String::from_utf8_lossy(&text);
+        let all: Vec<&str> = text.lines().collect();
+        Ok(all[all.len().saturating_sub(lines)..].join("\n"))
+    }
+}
@@ -0,0 +1,323 @@
+use anyhow::{bail, Context, Result};
+use chrono::Utc;
+use std::fs::OpenOptions;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::process::{Child, Command};
+use tracing::{info, warn};
+
+use crate::ai::Device;
+use crate::config::{DaemonConfig, Settings};
+use crate::daemon::{Control, DaemonFiles, DaemonState, Restart};
+use crate::server::{AuthToken, Exposure};
+use crate::update::{Flavor, LlamaInstaller};
+use crate::utils::MemoryInfo;
+
+/// How often control requests and llama-server exiting are noticed
+const TICK: Duration = Duration::from_secs(1);
+
+/// Longest wait before starting a llama-server that keeps crashing
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// A server that ran this long before failing was working, so its restart isn't delayed
+const STABLE_AFTER: Duration = Duration::from_secs(30);
+
+/// What a health check found
+enum Health {
+    Ok,
+    /// Answering 503 while the model loads, which can take minutes on first download
+    Loading,
+    Failed(String),
+}
+
+/// Keeps llama-server running: restarts it when it exits, stops answering
+/// health checks, or grows past `[daemon] max_memory_mb`
+pub struct Supervisor {
+    config: DaemonConfig,
+    files: DaemonFiles,
+    binary: PathBuf,
+    args: Vec<String>,
+    /// Where health checks connect
+    address: SocketAddr,
+    url: String,
+}
+
+impl Supervisor {
+    pub fn new(settings: &Settings) -> Result<Self> {
+        let config = settings.daemon.clone();
+        let host: IpAddr = match config.host.as_str() {
+            "localhost" => Ipv4Addr::LOCALHOST.into(),
+            host => host
+                .parse()
+                .with_context(|| format!("Invalid [daemon] host '{host}'"))?,
+        };
+        Exposure::check(host, config.allow_remote, "[daemon]")?;
+        // Listening on every address includes loopback, which is what checks use
+        let address = match host.is_unspecified() {
+            true => SocketAddr::new(Ipv4Addr::LOCALHOST.into(), config.port),
+            false => SocketAddr::new(host, config.port),
+        };
+
+        // The key file rather than --api-key, which would show in the process list
+        AuthToken::load_or_create()?;
+        let mut args = vec![
+            "-hf".to_string(),
+            settings.model.model_path.clone(),
+            "--host".to_string(),
+            host.to_string(),
+            "--port".to_string(),
+            config.port.to_string(),
+            "--api-key-file".to_string(),
+            AuthToken::path()?.display().to_string(),
+        ];
+        let runtime = settings.model.runtime_for(&settings.model.model_path);
+        if let Some(context_size) = runtime.context_size {
+            args.extend(["-c".to_string(), context_size.to_string()]);
+        }
+        if let Some(device) = &settings.model.device {
+            args.extend(Device::parse(device)?.llama_args());
+        }
+
+        Ok(Self {
+            binary: Self::server_binary(settings)?,
+            address,
+            url: format!("http://{address}"),
+            files: DaemonFiles::new()?,
+            config,
+            args,
+        })
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// `[daemon] server_binary`, else llama-server next to the current flavor, else on PATH
+    fn server_binary(settings: &Settings) -> Result<PathBuf> {
+        if let Some(binary) = &settings.daemon.server_binary {
+            return Ok(binary.clone());
+        }
+        if let Some(flavor) = &settings.model.flavor {
+            let binary = LlamaInstaller::new()?
+                .dir(Flavor::parse(flavor)?)
+                .join("llama-server");
+            if binary.exists() {
+                return Ok(binary);
+            }
+        }
+        which::which("llama-server")
+            .context("llama-server not found; install it or set [daemon] server_binary")
+    }
+
+    /// Starts `commandy daemon run` in the background and returns its process id
+    pub fn spawn_detached(files: &DaemonFiles) -> Result<u32> {
+        files.create_dir()?;
+        let log = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(files.log_path())?;
+        let mut command = std::process::Command::new(std::env::current_exe()?);
+        command
+            .args(["daemon", "run"])
+            .stdin(Stdio::null())
+            .stdout(log.try_clone()?)
+            .stderr(log);
+        // Its own process group, so Ctrl-C in the terminal that started it doesn't stop it
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::CommandExt;
+            command.process_group(0);
+        }
+        Ok(command.spawn().context("Failed to start the daemon")?.id())
+    }
+
+    /// Supervises llama-server until `commandy daemon stop` or Ctrl-C
+    pub async fn run(self) -> Result<()> {
+        self.files.create_dir()?;
+        if let Some(other) = self.files.running() {
+            if other.pid != std::process::id() {
+                bail!("The daemon is already running (pid {})", other.pid);
+            }
+        }
+
+        let mut state = DaemonState {
+            pid: std::process::id(),
+            url: self.url.clone(),
+            started_at: Utc::now(),
+            server_pid: None,
+            server_started_at: None,
+            healthy: false,
+            loading: false,
+            last_check: None,
+            rss_mb: None,
+            restarts: 0,
+            last_restart: None,
+        };
+        let mut child = self.start(&mut state)?;
+        let mut started = Instant::now();
+        let mut backoff = Duration::from_secs(1);
+        let mut failures = 0;
+        let interval = Duration::from_secs(self.config.health_interval_secs.max(1));
+        let mut next_check = Instant::now() + interval;
+
+        loop {
+            self.files.write_state(&state)?;
+            tokio::select! {
+                _ = tokio::time::sleep(TICK) => {}
+                _ = tokio::signal::ctrl_c() => break,
+            }
+
+            let requested = match self.files.take_request() {
+                Some(Control::Stop) => break,
+                Some(Control::Restart) => true,
+                None => false,
+            };
+            let mut restart = requested.then(|| "restart requested".to_string());
+            if restart.is_none() {
+                if let Some(status) = child.try_wait()? {
+                    restart = Some(format!("llama-server exited ({status})"));
+                }
+            }
+            if restart.is_none() && Instant::now() >= next_check {
+                next_check = Instant::now() + interval;
+                restart = self.check(&mut state, &mut failures).await;
+            }
+
+            if let Some(reason) = restart {
+                warn!("Restarting llama-server: {reason}");
+                self.files
+                    .log_event(&format!("Restarting llama-server: {reason}"));
+                let _ = child.kill().await;
+
+                // A server that keeps dying right away waits longer each time
+                backoff = match !requested && started.elapsed() < STABLE_AFTER {
+                    true => (backoff * 2).min(MAX_BACKOFF),
+                    false => Duration::from_secs(1),
+                };
+                state.restarts += 1;
+                state.last_restart = Some(Restart {
+                    at: Utc::now(),
+                    reason,
+                });
+                state.healthy = false;
+                state.server_pid = None;
+                self.files.write_state(&state)?;
+                tokio::time::sleep(backoff).await;
+
+                child = self.start(&mut state)?;
+                started = Instant::now();
+                failures = 0;
+                next_check = Instant::now() + interval;
+            }
+        }
+
+        let _ = child.kill().await;
+        self.files.log_event("Daemon stopped");
+        self.files.clear_state();
+        info!("Daemon stopped");
+        Ok(())
+    }
+
+    fn start(&self, state: &mut DaemonState) -> Result<Child> {
+        self.files.rotate_log();
+        let log = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.files.log_path())?;
+        let child = Command::new(&self.binary)
+            .args(&self.args)
+            .stdin(Stdio::null())
+            .stdout(log.try_clone()?)
+            .stderr(log)
+            .kill_on_drop(true)
+            .spawn()
+            .with_context(|| format!("Failed to start {}", self.binary.display()))?;
+
+        state.server_pid = child.id();
+        state.server_started_at = Some(Utc::now());
+        let message = format!(
+            "Started {} (pid {}) on {}",
+            self.binary.display(),
+            state.server_pid.unwrap_or_default(),
+            self.url
+        );
+        info!("{message}");
+        self.files.log_event(&message);
+        Ok(child)
+    }
+
+    /// Runs a health check and the memory check; a reason to restart if either says so
+    async fn check(&self, state: &mut DaemonState, failures: &mut u32) -> Option<String> {
+        state.last_check = Some(Utc::now());
+        match self.health().await {
+            Health::Ok => {
+                *failures = 0;
+                state.healthy = true;
+                state.loading = false;
+            }
+            Health::Loading => {
+                *failures = 0;
+                state.healthy = false;
+                state.loading = true;
+            }
+            Health::Failed(error) => {
+                *failures += 1;
+                state.healthy = false;
+                state.loading = false;
+                warn!("llama-server health check failed ({failures}): {error}");
+                if *failures >= self.config.max_failures.max(1) {
+                    return Some(format!(
+                        "not responding ({failures} failed health checks, last: {error})"
+                    ));
+                }
+            }
+        }
+
+        state.rss_mb = state.server_pid.and_then(MemoryInfo::process_rss_mb);
+        match state.rss_mb {
+            Some(rss) if self.config.max_memory_mb > 0 && rss > self.config.max_memory_mb => {
+                Some(format!(
+                    "using {rss} MB, over the {} MB limit",
+                    self.config.max_memory_mb
+                ))
+            }
+            _ => None,
+        }
+    }
+
+    /// GET /health, which llama-server answers 200 when ready and 503 while loading
+    async fn health(&self) -> Health {
+        let request = async {
+            let mut stream = TcpStream::connect(self.address).await?;
+            let request = format!(
+                "GET /health HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n\r\n",
+                self.address
+            );
+            stream.write_all(request.as_bytes()).await?;
+            let mut response = Vec::new();
+            stream.read_to_end(&mut response).await?;
+            anyhow::Ok(response)
+        };
+        let timeout = Duration::from_secs(self.config.health_timeout_secs.max(1));
+        let response = match tokio::time::timeout(timeout, request).await {
+            Ok(Ok(response)) => response,
+            Ok(Err(e)) => return Health::Failed(e.to_string()),
+            Err(_) => return Health::Failed(format!("no answer within {}s", timeout.as_secs())),
+        };
+
+        let status_line = String::from_utf8_lossy(&response)
+            .lines()
+            .next()
+            .unwrap_or_default()
+            .to_string();
+        match status_line.split_whitespace().nth(1) {
+            Some("200") => Health::Ok,
+            Some("503") => Health::Loading,
+            _ => Health::Failed(format!("unexpected answer '{status_line}'")),
+        }
+    }
+}
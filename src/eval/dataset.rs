@@ -0,0 +1,63 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+
+/// A natural-language query and the commands accepted as answers
+#[derive(Debug, Clone, Deserialize)]
+pub struct EvalCase {
+    pub query: String,
+    #[serde(default)]
+    pub category: String,
+    pub expected: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DatasetFile {
+    cases: Vec<EvalCase>,
+}
+
+/// A destructive command and what the expansion guard should make of it
+#[derive(Debug, Clone, Deserialize)]
+pub struct ExpansionIdiom {
+    pub command: String,
+    /// The rewrite; the command itself when it should be left alone
+    #[serde(default)]
+    pub guarded: Option<String>,
+    /// Still marked as unguarded after the rewrite
+    #[serde(default)]
+    pub flagged: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct IdiomFile {
+    idioms: Vec<ExpansionIdiom>,
+}
+
+/// Benchmark queries for `commandy eval`
+pub struct EvalDataset;
+
+impl EvalDataset {
+    /// The bundled benchmark
+    pub fn builtin() -> Result<Vec<EvalCase>> {
+        let file: DatasetFile = toml::from_str(include_str!("../../data/eval.toml"))
+            .context("Built-in eval dataset is invalid")?;
+        Ok(file.cases)
+    }
+
+    /// The bundled corpus of variable expansions in destructive commands
+    pub fn expansion_idioms() -> Result<Vec<ExpansionIdiom>> {
+        let file: IdiomFile = toml::from_str(include_str!("../../data/expansion_idioms.toml"))
+            .context("Built-in expansion idiom corpus is invalid")?;
+        Ok(file.idioms)
+    }
+
+    /// A dataset in the same TOML format as the bundled one
+    pub fn load(path: &Path) -> Result<Vec<EvalCase>> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read eval dataset {}", path.display()))?;
+        let file: DatasetFile = toml::from_str(&content)
+            .with_context(|| format!("Invalid eval dataset {}", path.display()))?;
+        Ok(file.cases)
+    }
+}
@@ -0,0 +1,5 @@
+pub mod dataset;
+pub mod runner;
+
+pub use dataset::{EvalCase, EvalDataset, ExpansionIdiom};
+pub use runner::{CaseResult, EvalReport, Evaluator, IdiomFailure};
@@ -0,0 +1,237 @@
+use serde::Serialize;
+use std::time::Instant;
+use tracing::debug;
+
+use crate::ai::LlamaCppClient;
+use crate::context::{ContextManager, TaxonomyClassifier};
+use crate::eval::{EvalCase, ExpansionIdiom};
+use crate::normalize::CommandNormalizer;
+use crate::postprocess::{Diversifier, ExpansionGuard, PostProcessor, QuotingCheck, Ranker};
+use crate::utils::{CommandValidator, Runtimes};
+
+/// How one query fared
+#[derive(Debug, Clone, Serialize)]
+pub struct CaseResult {
+    pub query: String,
+    pub category: String,
+    pub expected: Vec<String>,
+    pub suggestions: Vec<String>,
+    /// The top suggestion is one of the expected commands
+    pub exact_match: bool,
+    /// Any suggestion is one of the expected commands
+    pub top_k_match: bool,
+    /// Every executable in the top suggestion exists on this machine
+    pub executable_valid: bool,
+    /// A suggestion matched a dangerous pattern
+    pub safety_violation: bool,
+    /// Category the taxonomy rules assigned to the query
+    pub predicted_category: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub duration_ms: u64,
+}
+
+/// A corpus idiom the expansion guard handled differently than expected
+#[derive(Debug, Clone, Serialize)]
+pub struct IdiomFailure {
+    pub command: String,
+    pub expected: String,
+    pub got: String,
+}
+
+/// Aggregate results of an eval run
+#[derive(Debug, Clone, Serialize)]
+pub struct EvalReport {
+    pub model: String,
+    pub total: usize,
+    pub exact_match: usize,
+    pub top_k_match: usize,
+    pub executable_valid: usize,
+    pub safety_violations: usize,
+    /// Cases whose labelled category the taxonomy rules got right
+    pub category_correct: usize,
+    pub errors: usize,
+    pub duration_ms: u64,
+    pub cases: Vec<CaseResult>,
+    /// Expansion idioms checked, and those guarded wrongly
+    pub idioms: usize,
+    pub idiom_failures: Vec<IdiomFailure>,
+}
+
+impl EvalReport {
+    pub fn rate(&self, count: usize) -> f64 {
+        if self.total == 0 {
+            0.0
+        } else {
+            count as f64 / self.total as f64 * 100.0
+        }
+    }
+}
+
+/// Runs benchmark queries through generation, parsing, and post-processing
+pub struct Evaluator<'a> {
+    client: &'a LlamaCppClient,
+    context: &'a ContextManager,
+    postprocessor: &'a PostProcessor,
+    validator: CommandValidator,
+    max_suggestions: usize,
+}
+
+impl<'a> Evaluator<'a> {
+    pub fn new(
+        client: &'a LlamaCppClient,
+        context: &'a ContextManager,
+        postprocessor: &'a PostProcessor,
+        max_suggestions: usize,
+    ) -> Self {
+        Self {
+            client,
+            context,
+            postprocessor,
+            validator: CommandValidator::new(),
+            max_suggestions,
+        }
+    }
+
+    pub async fn run(
+        &self,
+        model: &str,
+        cases: &[EvalCase],
+        idioms: &[ExpansionIdiom],
+    ) -> EvalReport {
+        let started = Instant::now();
+        let mut results = Vec::with_capacity(cases.len());
+
+        for case in cases {
+            results.push(self.run_case(case).await);
+        }
+
+        let count = |f: fn(&CaseResult) -> bool| results.iter().filter(|r| f(r)).count();
+        EvalReport {
+            model: model.to_string(),
+            total: results.len(),
+            exact_match: count(|r| r.exact_match),
+            top_k_match: count(|r| r.top_k_match),
+            executable_valid: count(|r| r.executable_valid),
+            safety_violations: count(|r| r.safety_violation),
+            category_correct: count(|r| r.category == r.predicted_category),
+            errors: count(|r| r.error.is_some()),
+            duration_ms: started.elapsed().as_millis() as u64,
+            cases: results,
+            idioms: idioms.len(),
+            idiom_failures: Self::check_idioms(idioms),
+        }
+    }
+
+    /// Runs the expansion guard over the idiom corpus; no model involved
+    fn check_idioms(idioms: &[ExpansionIdiom]) -> Vec<IdiomFailure> {
+        let describe = |command: &str, flagged: bool| {
+            if flagged {
+                format!("{command} (flagged)")
+            } else {
+                command.to_string()
+            }
+        };
+
+        idioms
+            .iter()
+            .filter_map(|idiom| {
+                let expected = idiom.guarded.as_deref().unwrap_or(&idiom.command);
+                let guarded = ExpansionGuard::guard(&idiom.command);
+                let flagged = ExpansionGuard::unguarded(&guarded).is_some();
+                (guarded != expected || flagged != idiom.flagged).then(|| IdiomFailure {
+                    command: idiom.command.clone(),
+                    expected: describe(expected, idiom.flagged),
+                    got: describe(&guarded, flagged),
+                })
+            })
+            .collect()
+    }
+
+    async fn run_case(&self, case: &EvalCase) -> CaseResult {
+        let started = Instant::now();
+        debug!("Evaluating: {}", case.query);
+
+        let (suggestions, error) = match self.generate(&case.query).await {
+            Ok(suggestions) => (suggestions, None),
+            Err(e) => (Vec::new(), Some(e.to_string())),
+        };
+
+        let expected: Vec<String> = case
+            .expected
+            .iter()
+            .map(|e| CommandNormalizer::canonical(e))
+            .collect();
+        let matches = |command: &String| expected.contains(&CommandNormalizer::canonical(command));
+
+        CaseResult {
+            query: case.query.clone(),
+            category: case.category.clone(),
+            expected: case.expected.clone(),
+            exact_match: suggestions.first().is_some_and(matches),
+            top_k_match: suggestions.iter().any(matches),
+            executable_valid: suggestions
+                .first()
+                .is_some_and(|command| CommandValidator::new().executables_exist(command)),
+            safety_violation: suggestions
+                .iter()
+                .any(|command| !self.validator.is_safe_command(command)),
+            predicted_category: TaxonomyClassifier::classify(&case.query)
+                .category
+                .slug()
+                .to_string(),
+            suggestions,
+            error,
+            duration_ms: started.elapsed().as_millis() as u64,
+        }
+    }
+
+    async fn generate(&self, query: &str) -> anyhow::Result<Vec<String>> {
+        // History, learned patterns, and local names would make results depend on who runs the eval
+        let mut context = self.context.get_relevant_context(query)?;
+        context.learned_patterns.clear();
+        context.learned_rules.clear();
+        context.recent_commands.clear();
+        context.shell_definitions.clear();
+        context.toolchain = None;
+        context.runtimes = Runtimes::default();
+        context.time = None;
+        context.entities.clear();
+        context.working_directory = None;
+        context.file_types.clear();
+
+        let suggestions = self
+            .client
+            .generate_suggestions(query, &context, self.max_suggestions)
+            .await?;
+        let suggestions = self.postprocessor.process(suggestions);
+        let suggestions = QuotingCheck::apply(suggestions, query);
+        let suggestions = ExpansionGuard::apply(suggestions);
+        let suggestions = Ranker::apply(suggestions, |_| None);
+        Ok(Diversifier::apply(suggestions, &[])
+            .into_iter()
+            .map(|s| s.command)
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Evaluator;
+    use crate::eval::EvalDataset;
+
+    #[test]
+    fn expansion_idioms_pass() {
+        let idioms = EvalDataset::expansion_idioms().expect("idiom corpus parses");
+        assert!(!idioms.is_empty());
+        let failures = Evaluator::check_idioms(&idioms);
+        assert!(
+            failures.is_empty(),
+            "expansion guard disagrees with the corpus: {:#?}",
+            failures
+                .iter()
+                .map(|f| format!("{}: expected {}, got {}", f.command, f.expected, f.got))
+                .collect::<Vec<_>>()
+        );
+    }
+}
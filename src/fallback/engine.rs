@@ -0,0 +1,122 @@
+use regex::{Regex, RegexBuilder};
+use tracing::debug;
+use which::which;
+
+use crate::cli::{Suggestion, SuggestionSource};
+use crate::fallback::intents::{Intent, INTENTS};
+
+/// Rule-based suggestion engine used when no model backend is reachable
+pub struct FallbackEngine {
+    intents: Vec<(Regex, &'static Intent)>,
+    placeholder: Regex,
+}
+
+impl Default for FallbackEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FallbackEngine {
+    pub fn new() -> Self {
+        let intents = INTENTS
+            .iter()
+            .filter_map(|intent| {
+                RegexBuilder::new(intent.pattern)
+                    .case_insensitive(true)
+                    .build()
+                    .ok()
+                    .map(|re| (re, intent))
+            })
+            .collect();
+
+        Self {
+            intents,
+            placeholder: Regex::new(r"\{(\w+)\}").expect("valid placeholder regex"),
+        }
+    }
+
+    /// Returns heuristic suggestions for the first intent matching the prompt
+    pub fn suggest(&self, prompt: &str, max_suggestions: usize) -> Vec<Suggestion> {
+        for (pattern, intent) in &self.intents {
+            let Some(captures) = pattern.captures(prompt) else {
+                continue;
+            };
+            debug!("Fallback matched intent: {}", intent.name);
+
+            let suggestions: Vec<Suggestion> = intent
+                .templates
+                .iter()
+                .filter_map(|template| self.render(template, intent, &captures))
+                .filter(|command| Self::is_available(command))
+                .take(max_suggestions)
+                .map(|command| Suggestion {
+                    command,
+                    explanation: Some(intent.description.to_string()),
+                    confidence: 0.4,
+                    source: SuggestionSource::Heuristic,
+                    ranking: None,
+                })
+                .collect();
+
+            if !suggestions.is_empty() {
+                return suggestions;
+            }
+        }
+
+        Vec::new()
+    }
+
+    /// Fills template placeholders, returning None if any value is missing
+    fn render(
+        &self,
+        template: &str,
+        intent: &Intent,
+        captures: &regex::Captures,
+    ) -> Option<String> {
+        let mut missing = false;
+
+        let rendered = self
+            .placeholder
+            .replace_all(template, |caps: &regex::Captures| {
+                let key = &caps[1];
+                let value = captures
+                    .name(key)
+                    .map(|m| Self::normalize_value(key, m.as_str()))
+                    .or_else(|| {
+                        intent
+                            .defaults
+                            .iter()
+                            .find(|(name, _)| *name == key)
+                            .map(|(_, value)| value.to_string())
+                    });
+
+                value.unwrap_or_else(|| {
+                    missing = true;
+                    String::new()
+                })
+            });
+
+        (!missing).then(|| rendered.to_string())
+    }
+
+    fn normalize_value(key: &str, value: &str) -> String {
+        match key {
+            // "500 m" -> "500M" as understood by find -size
+            "size" => value
+                .chars()
+                .filter(|c| !c.is_whitespace())
+                .collect::<String>()
+                .to_uppercase(),
+            _ => value.to_string(),
+        }
+    }
+
+    /// Skips templates whose executable is not installed (e.g. `free` on macOS)
+    fn is_available(command: &str) -> bool {
+        command
+            .split_whitespace()
+            .next()
+            .is_some_and(|exe| which(exe).is_ok())
+    }
+}
@@ -0,0 +1,144 @@
+/// A common request shape and the command templates that satisfy it
+///
+/// Patterns are case-insensitive regexes matched against the prompt. Named
+/// capture groups fill `{name}` placeholders in templates; `defaults` supplies
+/// values for groups that did not participate in the match.
+pub struct Intent {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub pattern: &'static str,
+    pub templates: &'static [&'static str],
+    pub defaults: &'static [(&'static str, &'static str)],
+}
+
+pub const INTENTS: &[Intent] = &[
+    Intent {
+        name: "kill_port",
+        description: "Stop the process listening on a port",
+        pattern: r"(kill|stop|free)\b.*\bport\s*(?P<port>\d+)",
+        templates: &["lsof -ti :{port} | xargs kill", "fuser -k {port}/tcp"],
+        defaults: &[],
+    },
+    Intent {
+        name: "port_owner",
+        description: "Show which process is using a port",
+        pattern: r"(what|which|who|process|using|on)\b.*\bport\s*(?P<port>\d+)",
+        templates: &["lsof -i :{port}", "ss -ltnp 'sport = :{port}'"],
+        defaults: &[],
+    },
+    Intent {
+        name: "large_files",
+        description: "Find the largest files below the current directory",
+        pattern: r"(large|big|huge|largest|biggest)\s+files?(.*?(over|than|above)\s*(?P<size>\d+\s*[kmg]))?",
+        templates: &[
+            "find . -type f -size +{size} -exec ls -lh {} +",
+            "du -ah . | sort -rh | head -20",
+        ],
+        defaults: &[("size", "100M")],
+    },
+    Intent {
+        name: "disk_usage",
+        description: "Show disk space usage",
+        pattern: r"disk\s+(usage|space)|free\s+space|storage\s+left",
+        templates: &["df -h", "du -sh * | sort -rh | head -10"],
+        defaults: &[],
+    },
+    Intent {
+        name: "directory_size",
+        description: "Show the size of a directory",
+        pattern: r"size\s+of\s+(this|the|current)?\s*(directory|folder|dir)",
+        templates: &["du -sh .", "du -h --max-depth=1 . | sort -rh"],
+        defaults: &[],
+    },
+    Intent {
+        name: "memory_usage",
+        description: "Show memory usage",
+        pattern: r"(memory|ram)\s+(usage|used|free|left)|how much (memory|ram)",
+        templates: &["free -h", "vm_stat", "ps aux --sort=-%mem | head -10"],
+        defaults: &[],
+    },
+    Intent {
+        name: "list_processes",
+        description: "List running processes",
+        pattern: r"(list|show|running)\s+(all\s+)?process(es)?",
+        templates: &["ps aux", "top"],
+        defaults: &[],
+    },
+    Intent {
+        name: "find_by_name",
+        description: "Find files by name",
+        pattern: r#"find\s+(files?\s+)?(named|called)\s+["']?(?P<name>[^"'\s]+)"#,
+        templates: &["find . -name \"{name}\"", "find . -iname \"*{name}*\""],
+        defaults: &[],
+    },
+    Intent {
+        name: "search_text",
+        description: "Search file contents for text",
+        pattern: r#"(search|grep|look)\s+(for\s+)?["'](?P<text>[^"']+)["']"#,
+        templates: &["grep -rn \"{text}\" .", "rg \"{text}\""],
+        defaults: &[],
+    },
+    Intent {
+        name: "recent_files",
+        description: "List recently modified files",
+        pattern: r"(recent(ly)?|latest|last)\s+(modified|changed|edited)\s+files?|files?\s+(modified|changed)\s+(recently|today)",
+        templates: &["find . -type f -mtime -1", "ls -lt | head -20"],
+        defaults: &[],
+    },
+    Intent {
+        name: "running_containers",
+        description: "List containers",
+        pattern: r"(list|show|running)\s+(all\s+)?(docker\s+)?containers?",
+        templates: &["docker ps", "docker ps -a"],
+        defaults: &[],
+    },
+    Intent {
+        name: "git_undo_commit",
+        description: "Undo the last commit but keep the changes",
+        pattern: r"undo\s+(the\s+)?last\s+commit",
+        templates: &["git reset --soft HEAD~1"],
+        defaults: &[],
+    },
+    Intent {
+        name: "git_current_branch",
+        description: "Show the current git branch",
+        pattern: r"(current|which|what)\s+(git\s+)?branch",
+        templates: &["git branch --show-current", "git status -sb"],
+        defaults: &[],
+    },
+    Intent {
+        name: "git_changes",
+        description: "Show uncommitted changes",
+        pattern: r"(uncommitted|pending|my)\s+changes|git\s+(status|diff)",
+        templates: &["git status", "git diff --stat"],
+        defaults: &[],
+    },
+    Intent {
+        name: "extract_archive",
+        description: "Extract an archive",
+        pattern: r"(extract|unpack|untar|unzip)\s+(?P<file>\S+\.(tar\.gz|tgz|tar|zip))",
+        templates: &["tar -xf {file}", "unzip {file}"],
+        defaults: &[],
+    },
+    Intent {
+        name: "compress_directory",
+        description: "Compress a file or directory into a tarball",
+        pattern: r"(compress|archive|tar\s+up|zip\s+up)\s+(?P<target>[\w./-]+)",
+        templates: &["tar -czf {target}.tar.gz {target}"],
+        defaults: &[],
+    },
+    Intent {
+        name: "ip_address",
+        description: "Show network addresses",
+        pattern: r"(my|local|public)?\s*ip\s+address|what('s| is) my ip",
+        templates: &["ip addr", "ifconfig", "curl -s ifconfig.me"],
+        defaults: &[],
+    },
+    Intent {
+        name: "count_lines",
+        description: "Count lines in files",
+        pattern: r"count\s+(the\s+)?lines",
+        templates: &["find . -type f | xargs wc -l", "wc -l *"],
+        defaults: &[],
+    },
+];
@@ -0,0 +1,5 @@
+pub mod engine;
+pub mod intents;
+
+pub use engine::FallbackEngine;
+pub use intents::Intent;
@@ -0,0 +1,346 @@
+use std::collections::HashSet;
+use std::env;
+use std::process::Command;
+use tracing::debug;
+
+/// A corrected command and the rule that produced it
+#[derive(Debug, Clone)]
+pub struct Fix {
+    pub command: String,
+    pub rule: &'static str,
+}
+
+type Rule = fn(&CommandFixer, &str, Option<&str>) -> Option<String>;
+
+/// Rules are tried in order; each sees the original command
+const RULES: &[(&str, Rule)] = &[
+    ("unicode punctuation", CommandFixer::fix_unicode_punctuation),
+    ("missing space", CommandFixer::fix_missing_space),
+    ("unknown executable", CommandFixer::fix_unknown_executable),
+    ("git subcommand", CommandFixer::fix_git_subcommand),
+    (
+        "single-dash long flag",
+        CommandFixer::fix_single_dash_long_flag,
+    ),
+    ("missing sudo", CommandFixer::fix_missing_sudo),
+];
+
+const GIT_SUBCOMMANDS: &[&str] = &[
+    "add",
+    "am",
+    "bisect",
+    "blame",
+    "branch",
+    "checkout",
+    "cherry-pick",
+    "clean",
+    "clone",
+    "commit",
+    "config",
+    "describe",
+    "diff",
+    "fetch",
+    "grep",
+    "init",
+    "log",
+    "merge",
+    "mv",
+    "pull",
+    "push",
+    "rebase",
+    "reflog",
+    "remote",
+    "reset",
+    "restore",
+    "revert",
+    "rm",
+    "show",
+    "stash",
+    "status",
+    "submodule",
+    "switch",
+    "tag",
+    "worktree",
+];
+
+/// Tools whose own options are words after a single dash, like `java -version`
+const SINGLE_DASH_LONG_OPTION_TOOLS: &[&str] = &[
+    "java", "find", "gcc", "g++", "clang", "ffmpeg", "ffprobe", "xdotool",
+];
+
+/// Fast rule-based corrections for mistyped command lines
+pub struct CommandFixer {
+    executables: HashSet<String>,
+}
+
+impl Default for CommandFixer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CommandFixer {
+    pub fn new() -> Self {
+        Self {
+            executables: Self::scan_path(),
+        }
+    }
+
+    /// Applies every rule, returning distinct fixes in rule order
+    pub fn fix(&self, command: &str, error_output: Option<&str>) -> Vec<Fix> {
+        let command = command.trim();
+        let mut fixes: Vec<Fix> = Vec::new();
+
+        for (rule, apply) in RULES {
+            if let Some(fixed) = apply(self, command, error_output) {
+                if fixed != command && !fixes.iter().any(|f| f.command == fixed) {
+                    debug!("Rule '{rule}' fixed command: {fixed}");
+                    fixes.push(Fix {
+                        command: fixed,
+                        rule,
+                    });
+                }
+            }
+        }
+
+        fixes
+    }
+
+    /// Em dashes and smart quotes copied from web pages
+    fn fix_unicode_punctuation(&self, command: &str, _error: Option<&str>) -> Option<String> {
+        let fixed = command
+            .replace('—', "--")
+            .replace('–', "-")
+            .replace(['“', '”'], "\"")
+            .replace(['‘', '’'], "'");
+        (fixed != command).then_some(fixed)
+    }
+
+    /// `cd..` and similar
+    fn fix_missing_space(&self, command: &str, _error: Option<&str>) -> Option<String> {
+        match command {
+            "cd.." => Some("cd ..".to_string()),
+            "cd~" => Some("cd ~".to_string()),
+            "cd-" => Some("cd -".to_string()),
+            _ => None,
+        }
+    }
+
+    fn fix_unknown_executable(&self, command: &str, _error: Option<&str>) -> Option<String> {
+        let (prefix, rest) = Self::split_sudo(command);
+        let mut words = rest.splitn(2, ' ');
+        let executable = words.next()?;
+        let args = words.next();
+
+        if executable.contains('/') || self.executables.contains(executable) {
+            return None;
+        }
+
+        let candidate = Self::closest(executable, self.executables.iter().map(String::as_str))?;
+        Some(match args {
+            Some(args) => format!("{prefix}{candidate} {args}"),
+            None => format!("{prefix}{candidate}"),
+        })
+    }
+
+    fn fix_git_subcommand(&self, command: &str, _error: Option<&str>) -> Option<String> {
+        let mut words: Vec<&str> = command.split(' ').collect();
+        let git_index = words.iter().position(|w| *w == "git")?;
+        let subcommand = *words.get(git_index + 1)?;
+
+        if subcommand.starts_with('-') || GIT_SUBCOMMANDS.contains(&subcommand) {
+            return None;
+        }
+
+        let candidate = Self::closest(subcommand, GIT_SUBCOMMANDS.iter().copied())?;
+        words[git_index + 1] = candidate;
+        Some(words.join(" "))
+    }
+
+    /// `-help` and `-version` meant as long flags, when the tool said it didn't
+    /// recognize the option
+    fn fix_single_dash_long_flag(&self, command: &str, error: Option<&str>) -> Option<String> {
+        let unrecognized = error.is_some_and(|e| {
+            let e = e.to_lowercase();
+            [
+                "unrecognized option",
+                "unknown option",
+                "invalid option",
+                "illegal option",
+            ]
+            .iter()
+            .any(|message| e.contains(message))
+        });
+        let (_, rest) = Self::split_sudo(command);
+        let executable = rest.split(' ').next()?;
+        let executable = executable.rsplit('/').next().unwrap_or(executable);
+        if !unrecognized || SINGLE_DASH_LONG_OPTION_TOOLS.contains(&executable) {
+            return None;
+        }
+
+        let fixed: Vec<String> = command
+            .split(' ')
+            .map(|word| match word {
+                "-help" => "--help".to_string(),
+                "-version" => "--version".to_string(),
+                _ => word.to_string(),
+            })
+            .collect();
+        let fixed = fixed.join(" ");
+        (fixed != command).then_some(fixed)
+    }
+
+    fn fix_missing_sudo(&self, command: &str, error: Option<&str>) -> Option<String> {
+        if command.starts_with("sudo ") || Self::is_root() {
+            return None;
+        }
+
+        let permission_error = error.is_some_and(|e| {
+            let e = e.to_lowercase();
+            e.contains("permission denied")
+                || e.contains("operation not permitted")
+                || e.contains("are you root")
+                || e.contains("must be run as root")
+                || e.contains("eacces")
+        });
+
+        if permission_error || Self::needs_privileges(command) {
+            Some(format!("sudo {command}"))
+        } else {
+            None
+        }
+    }
+
+    /// Commands that fail without root on most systems
+    fn needs_privileges(command: &str) -> bool {
+        let words: Vec<&str> = command.split_whitespace().collect();
+        match words.as_slice() {
+            ["apt" | "apt-get" | "dnf" | "yum" | "zypper", verb, ..] => {
+                matches!(*verb, "install" | "remove" | "purge" | "update" | "upgrade")
+            }
+            ["pacman", flag, ..] => flag.starts_with("-S") || flag.starts_with("-R"),
+            ["systemctl", verb, ..] => matches!(
+                *verb,
+                "start" | "stop" | "restart" | "reload" | "enable" | "disable"
+            ),
+            _ => false,
+        }
+    }
+
+    fn split_sudo(command: &str) -> (&str, &str) {
+        match command.strip_prefix("sudo ") {
+            Some(rest) => ("sudo ", rest),
+            None => ("", command),
+        }
+    }
+
+    /// Closest candidate within a small edit distance
+    fn closest<'a>(word: &str, candidates: impl Iterator<Item = &'a str>) -> Option<&'a str> {
+        let max_distance = (word.len() / 3).clamp(1, 2);
+
+        candidates
+            .map(|c| (Self::edit_distance(word, c), c))
+            .filter(|(distance, _)| *distance <= max_distance)
+            .min_by(|(da, a), (db, b)| da.cmp(db).then(a.len().cmp(&b.len())).then(a.cmp(b)))
+            .map(|(_, c)| c)
+    }
+
+    /// Damerau-Levenshtein distance (optimal string alignment)
+    fn edit_distance(a: &str, b: &str) -> usize {
+        let a: Vec<char> = a.chars().collect();
+        let b: Vec<char> = b.chars().collect();
+        let mut d = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+
+        for (i, row) in d.iter_mut().enumerate() {
+            row[0] = i;
+        }
+        for (j, cell) in d[0].iter_mut().enumerate() {
+            *cell = j;
+        }
+
+        for i in 1..=a.len() {
+            for j in 1..=b.len() {
+                let cost = usize::from(a[i - 1] != b[j - 1]);
+                d[i][j] = (d[i - 1][j] + 1)
+                    .min(d[i][j - 1] + 1)
+                    .min(d[i - 1][j - 1] + cost);
+                if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                    d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+                }
+            }
+        }
+
+        d[a.len()][b.len()]
+    }
+
+    fn is_root() -> bool {
+        Command::new("id")
+            .arg("-u")
+            .output()
+            .map(|out| String::from_utf8_lossy(&out.stdout).trim() == "0")
+            .unwrap_or(false)
+    }
+
+    fn scan_path() -> HashSet<String> {
+        let mut executables = HashSet::new();
+        let Some(path) = env::var_os("PATH") else {
+            return executables;
+        };
+
+        for dir in env::split_paths(&path) {
+            if let Ok(entries) = std::fs::read_dir(dir) {
+                for entry in entries.flatten() {
+                    if let Some(name) = entry.file_name().to_str() {
+                        executables.insert(name.to_string());
+                    }
+                }
+            }
+        }
+
+        // Shell builtins are valid first words too
+        for builtin in ["cd", "echo", "export", "alias", "source", "pwd", "exit"] {
+            executables.insert(builtin.to_string());
+        }
+
+        executables
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixer() -> CommandFixer {
+        CommandFixer {
+            executables: HashSet::new(),
+        }
+    }
+
+    #[test]
+    fn single_dash_long_flag_needs_an_unrecognized_option() {
+        let fixer = fixer();
+        assert_eq!(
+            fixer.fix_single_dash_long_flag("ls -help", Some("ls: invalid option -- 'e'")),
+            Some("ls --help".to_string())
+        );
+        assert_eq!(fixer.fix_single_dash_long_flag("ls -help", None), None);
+        assert_eq!(
+            fixer.fix_single_dash_long_flag("ls -help", Some("ls: cannot access 'x'")),
+            None
+        );
+    }
+
+    #[test]
+    fn single_dash_long_flag_leaves_single_dash_tools_alone() {
+        let fixer = fixer();
+        let error = Some("Unrecognized option: -version");
+        assert_eq!(
+            fixer.fix_single_dash_long_flag("java -version", error),
+            None
+        );
+        assert_eq!(
+            fixer.fix_single_dash_long_flag("sudo /usr/bin/ffmpeg -version", error),
+            None
+        );
+    }
+}
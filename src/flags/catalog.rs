@@ -0,0 +1,100 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::fs;
+use std::path::PathBuf;
+use tracing::{debug, warn};
+
+/// One flag the flag menu offers
+#[derive(Debug, Clone, Deserialize)]
+pub struct FlagSpec {
+    /// The spelling added to commands, such as `-n` or `--since`
+    pub flag: String,
+    /// Another spelling recognized in commands, such as `--lines`
+    #[serde(default)]
+    pub alias: Option<String>,
+    /// Name of the flag's argument; `None` for flags that are toggled
+    #[serde(default)]
+    pub value: Option<String>,
+    #[serde(default)]
+    pub about: String,
+}
+
+impl FlagSpec {
+    pub fn takes_value(&self) -> bool {
+        self.value.is_some()
+    }
+
+    /// Whether `word` spells this flag
+    pub fn is(&self, word: &str) -> bool {
+        self.flag == word || self.alias.as_deref() == Some(word)
+    }
+}
+
+/// The flags of a tool, or of one of its subcommands such as `git log`
+#[derive(Debug, Clone, Deserialize)]
+pub struct ToolFlags {
+    pub tool: String,
+    #[serde(default)]
+    pub flags: Vec<FlagSpec>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CatalogFile {
+    #[serde(default)]
+    tools: Vec<ToolFlags>,
+}
+
+/// Common flags of common tools, built in and optionally extended by the user
+pub struct FlagCatalog {
+    tools: Vec<ToolFlags>,
+}
+
+impl FlagCatalog {
+    /// Loads the built-in flags merged with ~/.commandy/flags.toml if present
+    pub fn load() -> Result<Self> {
+        let mut catalog = Self::builtin()?;
+
+        let override_path = Self::get_override_path()?;
+        if override_path.exists() {
+            debug!("Loading flag overrides from {override_path:?}");
+            let content = fs::read_to_string(&override_path)?;
+            match toml::from_str::<CatalogFile>(&content) {
+                Ok(file) => catalog.merge(file.tools),
+                Err(e) => warn!("Ignoring invalid flags file {override_path:?}: {e}"),
+            }
+        }
+
+        Ok(catalog)
+    }
+
+    pub fn builtin() -> Result<Self> {
+        let file: CatalogFile = toml::from_str(include_str!("../../data/flags.toml"))
+            .context("Built-in flags are invalid")?;
+        Ok(Self { tools: file.tools })
+    }
+
+    /// The entry for a tool and its subcommand, falling back to the tool alone
+    pub fn lookup(&self, tool: &str, subcommand: Option<&str>) -> Option<&ToolFlags> {
+        let find = |name: &str| self.tools.iter().find(|t| t.tool == name);
+        subcommand
+            .and_then(|sub| find(&format!("{tool} {sub}")))
+            .or_else(|| find(tool))
+    }
+
+    /// An entry replaces the built-in one for the same tool
+    fn merge(&mut self, overrides: Vec<ToolFlags>) {
+        for entry in overrides {
+            if let Some(existing) = self.tools.iter_mut().find(|t| t.tool == entry.tool) {
+                *existing = entry;
+            } else {
+                self.tools.push(entry);
+            }
+        }
+    }
+
+    fn get_override_path() -> Result<PathBuf> {
+        let home_dir =
+            dirs::home_dir().ok_or_else(|| anyhow::anyhow!("Could not find home directory"))?;
+        Ok(home_dir.join(".commandy").join("flags.toml"))
+    }
+}
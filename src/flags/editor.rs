@@ -0,0 +1,587 @@
+use std::collections::BTreeMap;
+use std::ops::Range;
+
+use crate::flags::{FlagCatalog, FlagSpec, ToolFlags};
+use crate::normalize::command::SINGLE_DASH_TOOLS;
+use crate::postprocess::QuotingCheck;
+
+/// Words that end one simple command and start the next
+const SEPARATORS: &[&str] = &["|", "||", "&&", ";", "&", "|&"];
+
+/// Words that run the command after them
+const COMMAND_PREFIXES: &[&str] = &["sudo", "doas", "env", "nohup", "nice", "time", "command"];
+
+/// find's actions; tests added to a find command go before the first of them
+const FIND_ACTIONS: &[&str] = &[
+    "-exec", "-execdir", "-ok", "-okdir", "-delete", "-print", "-print0", "-printf", "-ls",
+    "-fprint", "-quit",
+];
+
+/// Characters that make the shell expand a value unless it is quoted
+const GLOB: &[char] = &['*', '?', '[', '~'];
+
+/// A word of a command line and the bytes it covers
+struct Word {
+    text: String,
+    span: Range<usize>,
+}
+
+/// Where a flag's value is
+enum ValueAt {
+    /// Inside the flag's word, as in `-n10` or `--lines=10`
+    Inline(Range<usize>),
+    /// The word after the flag
+    Word(usize),
+}
+
+/// One place a command sets a flag
+struct Occurrence {
+    spec: usize,
+    word: usize,
+    /// The flag's letter in a bundle such as `-la`; `None` when it is the whole word
+    letter: Option<Range<usize>>,
+    value: Option<ValueAt>,
+}
+
+/// A simple command whose tool has known flags
+struct ParsedCommand<'a> {
+    /// Position of the command in the line, counting from 0
+    index: usize,
+    tool: &'a ToolFlags,
+    /// Where a new flag is inserted, after a word
+    insert_at: usize,
+    occurrences: Vec<Occurrence>,
+}
+
+/// A known flag of one command in a line, and whether the line sets it
+#[derive(Debug, Clone)]
+pub struct FlagChoice {
+    /// Which simple command in the line, counting from 0
+    pub command: usize,
+    /// The catalog entry, such as `ls` or `git log`
+    pub tool: String,
+    pub spec: FlagSpec,
+    pub present: bool,
+    /// The value the line gives it, with quoting removed
+    pub value: Option<String>,
+}
+
+/// Finds and edits the flags of the commands in a line
+///
+/// Only flags listed in the [`FlagCatalog`] are recognized. Edits touch just
+/// the words of that flag, so the rest of the line keeps its spacing and
+/// quoting.
+pub struct CommandFlags;
+
+impl CommandFlags {
+    /// Every catalog flag of every command in the line, set or not
+    pub fn choices(line: &str, catalog: &FlagCatalog) -> Vec<FlagChoice> {
+        let words = Self::words(line);
+        let mut choices = Vec::new();
+        for command in Self::parse(&words, catalog) {
+            for (i, spec) in command.tool.flags.iter().enumerate() {
+                let found: Vec<&Occurrence> =
+                    command.occurrences.iter().filter(|o| o.spec == i).collect();
+                let value = found.last().and_then(|o| match &o.value {
+                    Some(ValueAt::Inline(range)) => Some(Self::dequote(&line[range.clone()])),
+                    Some(ValueAt::Word(w)) => Some(Self::dequote(&words[*w].text)),
+                    None => None,
+                });
+                choices.push(FlagChoice {
+                    command: command.index,
+                    tool: command.tool.tool.clone(),
+                    spec: spec.clone(),
+                    present: !found.is_empty(),
+                    value,
+                });
+            }
+        }
+        choices
+    }
+
+    /// Removes a flag the line sets, or adds one that takes no value
+    ///
+    /// A flag that takes a value is added with [`set_value`](Self::set_value).
+    pub fn toggle(line: &str, choice: &FlagChoice, catalog: &FlagCatalog) -> String {
+        let words = Self::words(line);
+        let commands = Self::parse(&words, catalog);
+        let Some((command, spec)) = Self::find(&commands, choice) else {
+            return line.to_string();
+        };
+
+        let found: Vec<&Occurrence> = command
+            .occurrences
+            .iter()
+            .filter(|o| o.spec == spec)
+            .collect();
+        if found.is_empty() {
+            if choice.spec.takes_value() {
+                return line.to_string();
+            }
+            let addition =
+                match Self::is_letter(&choice.spec.flag) && Self::follows_bundle(&words, command) {
+                    true => choice.spec.flag[1..].to_string(),
+                    false => format!(" {}", choice.spec.flag),
+                };
+            return Self::apply(line, vec![(command.insert_at..command.insert_at, addition)]);
+        }
+
+        // Letters to take out of bundles, by word
+        let mut letters: BTreeMap<usize, Vec<Range<usize>>> = BTreeMap::new();
+        let mut removed_words = Vec::new();
+        for occurrence in found {
+            match (&occurrence.letter, &occurrence.value) {
+                (Some(letter), Some(ValueAt::Inline(value))) => letters
+                    .entry(occurrence.word)
+                    .or_default()
+                    .push(letter.start..value.end),
+                (Some(letter), _) => letters
+                    .entry(occurrence.word)
+                    .or_default()
+                    .push(letter.clone()),
+                (None, _) => removed_words.push(occurrence.word),
+            }
+            if let Some(ValueAt::Word(w)) = occurrence.value {
+                removed_words.push(w);
+            }
+        }
+
+        let mut edits = Vec::new();
+        for (w, ranges) in letters {
+            let span = &words[w].span;
+            let kept: String = words[w]
+                .text
+                .char_indices()
+                .filter(|(i, _)| !ranges.iter().any(|r| r.contains(&(span.start + i))))
+                .map(|(_, c)| c)
+                .collect();
+            if kept == "-" {
+                removed_words.push(w);
+            } else {
+                edits.push((span.clone(), kept));
+            }
+        }
+        removed_words.sort_unstable();
+        removed_words.dedup();
+        for w in removed_words {
+            // The whitespace before the word goes with it
+            let start = match w {
+                0 => words[w].span.start,
+                _ => words[w - 1].span.end,
+            };
+            edits.push((start..words[w].span.end, String::new()));
+        }
+        Self::apply(line, edits)
+    }
+
+    /// Gives a flag a new value, adding the flag if the line doesn't set it
+    pub fn set_value(
+        line: &str,
+        choice: &FlagChoice,
+        value: &str,
+        catalog: &FlagCatalog,
+    ) -> String {
+        let words = Self::words(line);
+        let commands = Self::parse(&words, catalog);
+        let Some((command, spec)) = Self::find(&commands, choice) else {
+            return line.to_string();
+        };
+
+        let value = Self::quote(value);
+        let edit = match command.occurrences.iter().rev().find(|o| o.spec == spec) {
+            Some(occurrence) => match &occurrence.value {
+                Some(ValueAt::Inline(range)) => (range.clone(), value),
+                Some(ValueAt::Word(w)) => (words[*w].span.clone(), value),
+                None => {
+                    let end = words[occurrence.word].span.end;
+                    (end..end, format!(" {value}"))
+                }
+            },
+            None => (
+                command.insert_at..command.insert_at,
+                format!(" {} {value}", choice.spec.flag),
+            ),
+        };
+        Self::apply(line, vec![edit])
+    }
+
+    fn find<'c, 'a>(
+        commands: &'c [ParsedCommand<'a>],
+        choice: &FlagChoice,
+    ) -> Option<(&'c ParsedCommand<'a>, usize)> {
+        let command = commands
+            .iter()
+            .find(|c| c.index == choice.command && c.tool.tool == choice.tool)?;
+        let spec = command
+            .tool
+            .flags
+            .iter()
+            .position(|s| s.flag == choice.spec.flag)?;
+        Some((command, spec))
+    }
+
+    /// Whether new flags go after a bundle of flags that take no value, like `-la`
+    fn follows_bundle(words: &[Word], command: &ParsedCommand) -> bool {
+        let Some(word) = words.iter().find(|w| w.span.end == command.insert_at) else {
+            return false;
+        };
+        let Some(letters) = word.text.strip_prefix('-') else {
+            return false;
+        };
+        let known = |c: char| {
+            command
+                .tool
+                .flags
+                .iter()
+                .any(|s| s.is(&format!("-{c}")) && !s.takes_value())
+        };
+        !letters.is_empty() && letters.chars().all(known)
+    }
+
+    /// Whether a flag is a dash and one letter, so it can join a bundle
+    fn is_letter(flag: &str) -> bool {
+        let mut chars = flag.chars();
+        chars.next() == Some('-')
+            && chars.next().is_some_and(|c| c.is_ascii_alphanumeric())
+            && chars.next().is_none()
+    }
+
+    /// Words split on whitespace outside quotes
+    fn words(line: &str) -> Vec<Word> {
+        let chars: Vec<(usize, char)> = line.char_indices().collect();
+        let plain: Vec<char> = chars.iter().map(|(_, c)| *c).collect();
+        let (states, _) = QuotingCheck::quote_states(&plain);
+
+        let mut words = Vec::new();
+        let mut start = None;
+        for ((offset, c), state) in chars.iter().zip(&states) {
+            if c.is_whitespace() && state.is_none() {
+                if let Some(s) = start.take() {
+                    words.push(Word {
+                        text: line[s..*offset].to_string(),
+                        span: s..*offset,
+                    });
+                }
+            } else if start.is_none() {
+                start = Some(*offset);
+            }
+        }
+        if let Some(s) = start {
+            words.push(Word {
+                text: line[s..].to_string(),
+                span: s..line.len(),
+            });
+        }
+        words
+    }
+
+    /// The commands in the line that the catalog knows, with the flags they set
+    fn parse<'a>(words: &[Word], catalog: &'a FlagCatalog) -> Vec<ParsedCommand<'a>> {
+        let mut commands = Vec::new();
+        let mut start = 0;
+        let mut index = 0;
+        while start < words.len() {
+            let end = words[start..]
+                .iter()
+                .position(|w| SEPARATORS.contains(&w.text.as_str()))
+                .map_or(words.len(), |p| start + p);
+            if let Some(command) = Self::parse_command(words, start..end, index, catalog) {
+                commands.push(command);
+            }
+            start = end + 1;
+            index += 1;
+        }
+        commands
+    }
+
+    fn parse_command<'a>(
+        words: &[Word],
+        range: Range<usize>,
+        index: usize,
+        catalog: &'a FlagCatalog,
+    ) -> Option<ParsedCommand<'a>> {
+        // Skip assignments and prefixes such as `sudo -E`
+        let mut i = range.start;
+        while i < range.end {
+            let text = words[i].text.as_str();
+            if COMMAND_PREFIXES.contains(&text) {
+                i += 1;
+                while i < range.end && words[i].text.starts_with('-') {
+                    i += 1;
+                }
+            } else if text.contains('=') && !text.starts_with('-') && !text.starts_with('=') {
+                i += 1;
+            } else {
+                break;
+            }
+        }
+        let executable = words.get(i).filter(|_| i < range.end)?.text.as_str();
+        let executable = executable.rsplit('/').next().unwrap_or(executable);
+        let subcommand = words
+            .get(i + 1)
+            .filter(|w| i + 1 < range.end && !w.text.starts_with('-'))
+            .map(|w| w.text.as_str());
+        let tool = catalog.lookup(executable, subcommand)?;
+        let first_argument = i + tool.tool.split_whitespace().count();
+        let single_dash = SINGLE_DASH_TOOLS.contains(&executable);
+
+        let mut occurrences = Vec::new();
+        // The last word of the options right after the tool, where new flags go
+        let mut last_option = first_argument - 1;
+        let mut in_options = true;
+        let mut w = first_argument;
+        while w < range.end {
+            let word = &words[w];
+            let text = word.text.as_str();
+            let next_word = (w + 1 < range.end).then_some(w + 1);
+            if text == "--" {
+                break;
+            }
+            let consumed = if !text.starts_with('-') || text.len() == 1 {
+                in_options = false;
+                1
+            } else {
+                Self::parse_option(tool, single_dash, words, w, next_word, &mut occurrences)
+            };
+            if in_options {
+                last_option = w + consumed - 1;
+            }
+            w += consumed;
+        }
+
+        let mut insert_at = words[last_option].span.end;
+        if executable == "find" {
+            // find takes its paths first, so tests go before any action or at the end
+            let action = (first_argument..range.end)
+                .find(|&w| FIND_ACTIONS.contains(&words[w].text.as_str()));
+            insert_at = words[action.unwrap_or(range.end) - 1].span.end;
+        }
+
+        Some(ParsedCommand {
+            index,
+            tool,
+            insert_at,
+            occurrences,
+        })
+    }
+
+    /// Records the known flags in an option word and returns how many words it
+    /// uses, two when its value is the next word
+    fn parse_option(
+        tool: &ToolFlags,
+        single_dash: bool,
+        words: &[Word],
+        w: usize,
+        next_word: Option<usize>,
+        occurrences: &mut Vec<Occurrence>,
+    ) -> usize {
+        let word = &words[w];
+        let text = word.text.as_str();
+
+        // A whole word such as `--all`, `--lines=10`, `-name`, or `-1`
+        let (name, inline) = match text.split_once('=') {
+            Some((name, _)) if text.starts_with("--") => {
+                (name, Some(word.span.start + name.len() + 1..word.span.end))
+            }
+            _ => (text, None),
+        };
+        if let Some(spec) = tool.flags.iter().position(|s| s.is(name)) {
+            let value = match (tool.flags[spec].takes_value(), inline, next_word) {
+                (false, _, _) => None,
+                (true, Some(inline), _) => Some(ValueAt::Inline(inline)),
+                (true, None, Some(next)) => Some(ValueAt::Word(next)),
+                (true, None, None) => None,
+            };
+            let consumed = 1 + usize::from(matches!(value, Some(ValueAt::Word(_))));
+            occurrences.push(Occurrence {
+                spec,
+                word: w,
+                letter: None,
+                value,
+            });
+            return consumed;
+        }
+        if single_dash || text.starts_with("--") {
+            return 1;
+        }
+
+        // A bundle of letters such as `-la` or `-rn10`
+        for (offset, c) in text.char_indices().skip(1) {
+            let Some(spec) = tool.flags.iter().position(|s| s.is(&format!("-{c}"))) else {
+                continue;
+            };
+            let letter = word.span.start + offset..word.span.start + offset + c.len_utf8();
+            if !tool.flags[spec].takes_value() {
+                occurrences.push(Occurrence {
+                    spec,
+                    word: w,
+                    letter: Some(letter),
+                    value: None,
+                });
+                continue;
+            }
+            // The rest of the word, or else the next word, is the value
+            let value = match (letter.end < word.span.end, next_word) {
+                (true, _) => Some(ValueAt::Inline(letter.end..word.span.end)),
+                (false, Some(next)) => Some(ValueAt::Word(next)),
+                (false, None) => None,
+            };
+            let consumed = 1 + usize::from(matches!(value, Some(ValueAt::Word(_))));
+            occurrences.push(Occurrence {
+                spec,
+                word: w,
+                letter: Some(letter),
+                value,
+            });
+            return consumed;
+        }
+        1
+    }
+
+    /// Replaces byte ranges, which must not overlap
+    fn apply(line: &str, mut edits: Vec<(Range<usize>, String)>) -> String {
+        edits.sort_by_key(|(range, _)| std::cmp::Reverse(range.start));
+        let mut line = line.to_string();
+        for (range, text) in edits {
+            line.replace_range(range, &text);
+        }
+        line
+    }
+
+    fn dequote(text: &str) -> String {
+        QuotingCheck::dequote(&text.chars().collect::<Vec<_>>())
+    }
+
+    /// A typed value as a single shell word, quoted when the shell would change it
+    fn quote(value: &str) -> String {
+        if value.is_empty() || value.contains(GLOB) {
+            QuotingCheck::single_quote(value)
+        } else {
+            QuotingCheck::quote_if_needed(value)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn catalog() -> FlagCatalog {
+        FlagCatalog::builtin().unwrap()
+    }
+
+    /// The choice for `flag` of the first command in `line` that is `tool`
+    fn choice(line: &str, tool: &str, flag: &str) -> FlagChoice {
+        CommandFlags::choices(line, &catalog())
+            .into_iter()
+            .find(|c| c.tool == tool && c.spec.flag == flag)
+            .unwrap_or_else(|| panic!("no {flag} for {tool} in {line}"))
+    }
+
+    fn toggled(line: &str, tool: &str, flag: &str) -> String {
+        CommandFlags::toggle(line, &choice(line, tool, flag), &catalog())
+    }
+
+    fn set(line: &str, tool: &str, flag: &str, value: &str) -> String {
+        CommandFlags::set_value(line, &choice(line, tool, flag), value, &catalog())
+    }
+
+    #[test]
+    fn bundled_short_flags() {
+        let line = "ls -la /tmp";
+        assert!(choice(line, "ls", "-l").present);
+        assert!(choice(line, "ls", "-a").present);
+        assert!(!choice(line, "ls", "-h").present);
+
+        assert_eq!(toggled(line, "ls", "-a"), "ls -l /tmp");
+        assert_eq!(toggled(line, "ls", "-h"), "ls -lah /tmp");
+        assert_eq!(toggled("ls -a /tmp", "ls", "-a"), "ls /tmp");
+    }
+
+    #[test]
+    fn bundled_flag_with_value() {
+        let line = "grep -rC3 TODO src";
+        assert!(choice(line, "grep", "-r").present);
+        assert_eq!(choice(line, "grep", "-C").value.as_deref(), Some("3"));
+        assert_eq!(set(line, "grep", "-C", "5"), "grep -rC5 TODO src");
+        assert_eq!(toggled(line, "grep", "-C"), "grep -r TODO src");
+
+        // The value can also be the next word
+        let line = "grep -rC 3 TODO src";
+        assert_eq!(choice(line, "grep", "-C").value.as_deref(), Some("3"));
+        assert_eq!(toggled(line, "grep", "-C"), "grep -r TODO src");
+    }
+
+    #[test]
+    fn long_flag_value_with_equals_or_next_word() {
+        let line = "git log --max-count=5 --oneline";
+        assert_eq!(choice(line, "git log", "-n").value.as_deref(), Some("5"));
+        assert_eq!(
+            set(line, "git log", "-n", "10"),
+            "git log --max-count=10 --oneline"
+        );
+        assert_eq!(toggled(line, "git log", "-n"), "git log --oneline");
+
+        let line = "git log --max-count 5 --oneline";
+        assert_eq!(choice(line, "git log", "-n").value.as_deref(), Some("5"));
+        assert_eq!(
+            set(line, "git log", "-n", "10"),
+            "git log --max-count 10 --oneline"
+        );
+        assert_eq!(toggled(line, "git log", "-n"), "git log --oneline");
+
+        assert_eq!(
+            set("git log --oneline", "git log", "-n", "3"),
+            "git log --oneline -n 3"
+        );
+    }
+
+    #[test]
+    fn nothing_after_double_dash_is_a_flag() {
+        let line = "grep -i -- -v notes.txt";
+        assert!(choice(line, "grep", "-i").present);
+        assert!(!choice(line, "grep", "-v").present);
+    }
+
+    #[test]
+    fn quoted_arguments() {
+        // A flag inside a quoted pattern isn't one
+        let line = r#"grep -i "a -v b" notes.txt"#;
+        assert!(!choice(line, "grep", "-v").present);
+        assert_eq!(toggled(line, "grep", "-i"), r#"grep "a -v b" notes.txt"#);
+
+        let line = r#"git log --author "Jane Doe""#;
+        assert_eq!(
+            choice(line, "git log", "--author").value.as_deref(),
+            Some("Jane Doe")
+        );
+        let changed = set(line, "git log", "--author", "John Smith");
+        assert_eq!(
+            choice(&changed, "git log", "--author").value.as_deref(),
+            Some("John Smith")
+        );
+
+        // Globs stay quoted so the shell doesn't expand them
+        assert_eq!(
+            set("find . -name '*.rs'", "find", "-name", "*.md"),
+            "find . -name '*.md'"
+        );
+    }
+
+    #[test]
+    fn commands_in_a_pipeline() {
+        let line = "sudo ls -l /var/log | grep -i error";
+        let grep = choice(line, "grep", "-i");
+        assert_eq!(grep.command, 1);
+        assert!(grep.present);
+        assert_eq!(
+            toggled(line, "grep", "-n"),
+            "sudo ls -l /var/log | grep -in error"
+        );
+        // find's tests go before its actions
+        assert_eq!(
+            toggled("find . -name '*.log' -delete", "find", "-empty"),
+            "find . -name '*.log' -empty -delete"
+        );
+    }
+}
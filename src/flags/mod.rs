@@ -0,0 +1,5 @@
+pub mod catalog;
+pub mod editor;
+
+pub use catalog::{FlagCatalog, FlagSpec, ToolFlags};
+pub use editor::{CommandFlags, FlagChoice};
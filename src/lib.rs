@@ -1,7 +1,22 @@
 pub mod ai;
+pub mod bundle;
 pub mod cli;
 pub mod config;
 pub mod context;
+pub mod daemon;
+pub mod eval;
+pub mod fallback;
+pub mod fix;
+pub mod flags;
+pub mod normalize;
+pub mod plugins;
+pub mod postprocess;
+pub mod preview;
+pub mod remote;
+pub mod safety;
+pub mod server;
+pub mod translate;
+pub mod update;
 pub mod utils;
 
 pub use cli::{Cli, CommandHandler, Commands};
@@ -1,26 +1,34 @@
 use anyhow::Result;
 use clap::Parser;
-use log::error;
+use std::io::IsTerminal;
+use std::time::Duration;
+use tracing::{debug, error, warn};
 
+use commandy::ai::ModelTooLarge;
+use commandy::cli::{BackendUnavailable, Outcome, PipedInput, PromptOptions};
+use commandy::config::Settings;
+use commandy::context::Attachment;
+use commandy::server::{ApiServer, AuthToken};
+use commandy::utils::{Logging, Platform};
 use commandy::{Cli, CommandHandler, Commands};
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    // Initialize logging - only show errors
-    env_logger::Builder::from_default_env()
-        .filter_level(log::LevelFilter::Error)
-        .init();
+    let mut cli = Cli::parse();
 
-    let cli = Cli::parse();
+    // Errors on stderr, everything at log.level in the log files
+    let log_config = Settings::load().map(|s| s.log).unwrap_or_default();
+    if let Err(e) = Logging::init(&log_config, cli.log_file.as_deref(), cli.verbose, cli.quiet) {
+        eprintln!("Warning: {e}");
+    }
 
     // Handle version early
     if matches!(cli.command, Some(Commands::Version)) {
         let version_info = format!(
-            "commandy {}\nRust version: {}\nPlatform: {}-{}",
+            "commandy {}\nRust version: {}\nPlatform: {}",
             env!("CARGO_PKG_VERSION"),
             env!("CARGO_PKG_RUST_VERSION"),
-            std::env::consts::OS,
-            std::env::consts::ARCH
+            Platform::detect().describe()
         );
         println!("{version_info}");
         return Ok(());
@@ -37,7 +45,71 @@ async fn main() -> Result<()> {
         }
     };
 
+    handler.set_plain(cli.plain);
+    handler.set_quiet(cli.quiet);
+    handler.set_emit_osc(cli.emit_osc);
+    handler.set_confirm_edits(cli.confirm_edits);
+    handler.set_sandbox(cli.sandbox);
+    handler.set_unpack_scripts(cli.unpack_scripts);
+    handler.set_read_only(cli.read_only);
+    if let Err(e) = handler.set_profile(cli.profile.as_deref()) {
+        fail(&handler, &e.to_string(), Outcome::Error);
+    }
+    if let Err(e) = handler.set_target(cli.target.as_deref()) {
+        fail(&handler, &e.to_string(), Outcome::Error);
+    }
+    handler.set_log_file(cli.log_file.clone());
+
+    // A template expands into a prompt, then runs like one typed on the command line
+    let template_prompt = match cli.command.take() {
+        Some(Commands::Run {
+            template: Some(name),
+            vars,
+        }) => match handler.expand_template(&name, &vars) {
+            Ok(prompt) => {
+                if !cli.quiet {
+                    eprintln!("{}", handler.format_info(&prompt));
+                }
+                Some(prompt)
+            }
+            Err(e) => fail(&handler, &e.to_string(), Outcome::Error),
+        },
+        other => {
+            cli.command = other;
+            None
+        }
+    };
+
     match cli.command {
+        Some(Commands::Serve { port, host, token }) => {
+            let addr = format!("{host}:{port}").parse()?;
+            let config = handler.settings().server.clone();
+            if config.use_daemon {
+                if let Err(e) = handler.ensure_daemon().await {
+                    warn!("Failed to start the daemon: {e}");
+                    eprintln!(
+                        "{}",
+                        handler.format_info(&format!(
+                            "Running llama.cpp for each request; the daemon didn't start: {e}"
+                        ))
+                    );
+                }
+            }
+            let token = match token {
+                Some(token) => token,
+                // Read-only doesn't generate the token file
+                None if handler.is_read_only() => match AuthToken::load() {
+                    Some(token) => token,
+                    None => fail(
+                        &handler,
+                        "No token in ~/.commandy/auth-token to serve with under --read-only; pass --token",
+                        Outcome::Error,
+                    ),
+                },
+                None => AuthToken::load_or_create()?,
+            };
+            ApiServer::new(handler, token, &config)?.run(addr).await?;
+        }
         Some(command) => {
             // Handle subcommands
             match handler.handle_command(command).await {
@@ -51,50 +123,155 @@ async fn main() -> Result<()> {
             }
         }
         None => {
-            if let Some(ref prompt) = cli.prompt {
+            let prompt = template_prompt.or_else(|| cli.prompt.clone());
+            if cli.watch && handler.is_plain() {
+                eprintln!("--watch needs a full-screen terminal; ignoring it with --plain");
+            }
+            if cli.watch && !handler.is_plain() && std::io::stdout().is_terminal() {
+                let initial_query = prompt.unwrap_or_default();
+                match handler.handle_watch(&initial_query, cli.explain).await {
+                    Ok(output) => {
+                        if !output.is_empty() {
+                            println!("{output}");
+                        }
+                    }
+                    Err(e) => {
+                        error!("Watch mode failed: {e}");
+                        eprintln!("{}", handler.format_error(&e.to_string()));
+                        std::process::exit(1);
+                    }
+                }
+            } else if let Some(ref prompt) = prompt {
                 // Handle prompt for command generation
 
-                let options = (&cli).into();
-
-                match handler.handle_prompt(prompt, options).await {
-                    Ok(suggestions) => {
-                        if suggestions.is_empty() {
-                            println!(
-                                "{}",
-                                handler.format_error(
-                                    "No suggestions found. Try rephrasing your prompt."
-                                )
-                            );
-                        } else {
-                            match handler
-                                .format_suggestions(suggestions, cli.explain, prompt)
-                                .await
-                            {
-                                Ok(output) => {
-                                    if !output.is_empty() {
-                                        println!("{output}");
-                                    }
-                                }
-                                Err(e) => {
-                                    error!("Failed to format suggestions: {e}");
-                                    eprintln!(
-                                        "{}",
-                                        handler.format_error(&format!(
-                                            "Failed to format suggestions: {e}"
-                                        ))
-                                    );
-                                    std::process::exit(1);
-                                }
-                            }
+                let mut options: PromptOptions = (&cli).into();
+                options.max_suggestions = handler.suggestion_count(cli.suggestions);
+                // --pick N needs at least N suggestions
+                if let Some(pick) = cli.pick.filter(|&pick| pick > options.max_suggestions) {
+                    options.max_suggestions = handler.suggestion_count(Some(pick));
+                }
+                // Scripts taking a suggestion get them all at once, with no picker
+                let choosing = cli.pick.is_some() || cli.print_only || cli.quiet;
+                // The picker generates pages past the first when it reaches them
+                if std::io::stdout().is_terminal() && !choosing {
+                    options.page_size = Some(handler.page_size());
+                }
+                options.attachments = match Attachment::from_files(&cli.files) {
+                    Ok(attachments) => attachments,
+                    Err(e) => fail(&handler, &e.to_string(), Outcome::Error),
+                };
+
+                // A command piped from another commandy becomes the base to refine;
+                // other piped text is context, like a file
+                match PipedInput::read_stdin() {
+                    Some(PipedInput::Command { command, .. }) => {
+                        options.base_command = Some(command)
+                    }
+                    Some(PipedInput::Text(text)) => {
+                        debug!("Using {} bytes of stdin as context", text.len());
+                        options
+                            .attachments
+                            .push(Attachment::from_text("stdin", &text));
+                    }
+                    None => {}
+                }
+
+                if cli.paste && std::io::stdin().is_terminal() {
+                    match PipedInput::read_paste() {
+                        Ok(text) if !text.trim().is_empty() => options
+                            .attachments
+                            .push(Attachment::from_text("pasted text", &text)),
+                        Ok(_) if !handler.is_quiet() => {
+                            eprintln!("{}", handler.format_info("Nothing was pasted"))
                         }
+                        Ok(_) => {}
+                        Err(e) => fail(
+                            &handler,
+                            &format!("Failed to read paste: {e}"),
+                            Outcome::Error,
+                        ),
                     }
+                }
+
+                // `!!` in single quotes reaches us unexpanded
+                let prompt = handler.resolve_query(prompt);
+                let generation = handler.handle_prompt(&prompt, options);
+                let result = match cli.timeout {
+                    Some(secs) => {
+                        match tokio::time::timeout(Duration::from_secs(secs), generation).await {
+                            Ok(result) => result,
+                            Err(_) => fail(
+                                &handler,
+                                &format!("No suggestions within {secs}s (--timeout)"),
+                                Outcome::TimedOut,
+                            ),
+                        }
+                    }
+                    None => generation.await,
+                };
+                let suggestions = match result {
+                    Ok(suggestions) => suggestions,
                     Err(e) => {
                         error!("Failed to generate suggestions: {e}");
-                        let error_msg = handler.format_error(&format!(
-                            "Failed to generate suggestions: {e}. Check that the ML service is properly configured."
-                        ));
-                        eprintln!("{error_msg}");
-                        std::process::exit(1);
+                        let outcome = Outcome::of(&e);
+                        // The memory guard already says what to do instead
+                        let too_large = e
+                            .downcast_ref::<BackendUnavailable>()
+                            .is_some_and(|b| b.0.is::<ModelTooLarge>());
+                        let message = match outcome {
+                            Outcome::BackendUnavailable if !too_large => format!(
+                                "Failed to generate suggestions: {e}. Check that the ML service is properly configured."
+                            ),
+                            _ => format!("Failed to generate suggestions: {e}"),
+                        };
+                        fail(&handler, &message, outcome);
+                    }
+                };
+                if suggestions.is_empty() {
+                    fail(
+                        &handler,
+                        "No suggestions found. Try rephrasing your prompt.",
+                        Outcome::NoSuggestions,
+                    );
+                }
+
+                if choosing {
+                    // The exit code tells a script whether there was one to take
+                    let picked = match handler.picked(&suggestions, cli.pick.unwrap_or(1)) {
+                        Ok(suggestion) => suggestion.command.clone(),
+                        Err(e) => fail(&handler, &e.to_string(), Outcome::NoSuggestions),
+                    };
+                    // Model output only runs unseen when asked for with --execute
+                    if cli.print_only || cli.quiet || !cli.execute {
+                        println!("{picked}");
+                        return Ok(());
+                    }
+                } else if cli.pipe {
+                    // Feeds the next commandy, and is also valid shell
+                    println!("{}", handler.format_piped(&suggestions, &prompt));
+                    return Ok(());
+                }
+
+                handler.set_pick(cli.pick);
+                match handler
+                    .format_suggestions(suggestions, cli.explain, &prompt)
+                    .await
+                {
+                    Ok(output) => {
+                        if !output.is_empty() {
+                            println!("{output}");
+                        }
+                        if handler.was_blocked() {
+                            Outcome::Blocked.exit();
+                        }
+                    }
+                    Err(e) => {
+                        error!("Failed to format suggestions: {e}");
+                        fail(
+                            &handler,
+                            &format!("Failed to format suggestions: {e}"),
+                            Outcome::Error,
+                        );
                     }
                 }
             } else {
@@ -109,22 +286,81 @@ Examples:
   commandy "list running containers"
   commandy "find large files in current directory"
   commandy --explain "git commit with message"
+  commandy --watch                 (suggestions update as you type)
+  commandy --pipe "list pods" | commandy "only the crashlooping ones"
+  commandy --target admin@web-1 "restart nginx"
+  commandy -f docker-compose.yml "scale the web service to 3"
+  cmd=$(commandy --pick 1 "count lines in *.rs") || exit 1
+  commandy --quiet "disk usage of /var" || echo "exit code $?"
 
 Commands:
   init      Initialize commandy setup
-  update    Update model or binary  
+  update    Update model or binary (--binary --flavor cuda installs another llama.cpp build,
+            --build compiles it from source)
+  model     List and switch models
+  recall    Search history for a command you ran before
+  serve     Serve a JSON API for editors (POST /suggest, /explain, /fix; GET /metrics)
+  daemon    Keep llama-server running and restart it when it fails
+            (daemon start|stop|restart|status|logs)
+  stats     Show request metrics from a running server
+            (stats experiments compares the arms of a prompt experiment)
+  hook      Print shell integration (eval "$(commandy hook zsh)")
+  eval      Score a model against the bundled query benchmark
+  batch     Suggest commands for each line of a file (--output json|script)
+  chat      Ask for commands in a conversation (--session NAME saves it to resume later)
+  session   Export this shell's commands as a runbook (session export --format markdown|script),
+            or list and delete chat sessions (session list|delete)
+  audit     Show or verify the hash-chained log of executed commands (audit show|verify)
+  replay    Re-run a logged session's generations (session id from -v)
+  bugreport Write a redacted zip of the last session, config, and diagnostics for an issue
+  explain   Explain what a command does: commandy explain tar -xzvf a.tgz
+  inspect   Check a command for risks and explain it (--clipboard before you paste,
+            --unpack to read a curl | sh script before running it)
+  translate Rewrite a command for another shell: commandy translate --to fish 'export A=1'
+  oops      Fix the previous command (alias oops='commandy oops -- "$(fc -ln -1)"')
+  run       Expand a [templates] prompt: commandy run deploy env=staging
+  packs     Manage team pattern packs (list, add, remove)
+  pin       Put a command first for matching requests (pin add|list|remove)
+  learned   Export learned patterns as text (--out FILE)
+  export    Pack settings and learning into a bundle (--out FILE)
+  import    Merge a bundle from another machine
   config    Show configuration
+  cache     Pre-generate suggestions (cache warm), or share them (cache export/import)
   clear     Clear cache and context
-  doctor    Run diagnostics
+  doctor    Run diagnostics (--logs shows recent errors)
+  self-update  Update commandy to the latest release
   help      Show this help message
 
 Options:
   -e, --explain       Show detailed explanations
-  -n, --suggestions   Number of suggestions to show [default: 3]
+  -n, --suggestions   Number of suggestions to show; pages past the first are generated
+                      as you scroll to them [default: 3, at most 20]
       --no-cache      Skip cache and force fresh inference
-  -v, --verbose       Verbose output
+  -f, --file          Include a file's contents as context (repeatable)
+      --paste         Paste an error or snippet as context, ended with Ctrl-D
+  -v, --verbose       Verbose output, with a timed breakdown of each request
+  -w, --watch         Update suggestions live as you type the query
+      --plain         Screen-reader-friendly output: no colors, spinners, or menus
+      --emit-osc      Send the chosen command to the terminal via OSC 52
+      --confirm-edits Preview in-place file edits as a diff before running them
+      --sandbox       Try the chosen command in a throwaway container first
+      --unpack-scripts  Download curl | sh scripts and show them before running
+      --target        Suggest for an SSH host and run the chosen command there (user@host)
+      --read-only     Never run, copy, or save anything (demos, untrusted automation)
+      --profile       Apply a [profiles] entry's limits on running risky commands
+      --log-file      Write logs to this file instead of ~/.commandy/logs
+      --pick          Take the Nth suggestion (1 is the top) without the picker
+      --print-only    Print the chosen suggestion raw to stdout instead of running it
+      --execute       Run the suggestion taken with --pick instead of printing it
+  -q, --quiet         Print only the chosen command, or nothing; nothing on stderr
+      --pipe          Print the top suggestion for a downstream commandy to refine
+      --timeout       Give up on generating suggestions after this many seconds
   -h, --help          Print help
 
+Exit codes:
+  0 success, 1 other error, 2 usage error, 3 no suggestions (or fewer than --pick),
+  4 model backend unavailable, 5 refused for safety, 6 timed out
+
 For more information, visit: https://commandy.sh
 "#;
                 println!("{help}");
@@ -134,3 +370,11 @@ For more information, visit: https://commandy.sh
 
     Ok(())
 }
+
+/// Reports `message` on stderr, unless --quiet, and exits with `outcome`'s code
+fn fail(handler: &CommandHandler, message: &str, outcome: Outcome) -> ! {
+    if !handler.is_quiet() {
+        eprintln!("{}", handler.format_error(message));
+    }
+    outcome.exit()
+}
@@ -0,0 +1,318 @@
+use std::sync::OnceLock;
+
+use crate::ai::ResponseParser;
+use crate::flags::FlagCatalog;
+use crate::postprocess::QuotingCheck;
+
+/// Words after which the next word runs a command
+const SEPARATORS: &[&str] = &["|", "||", "&&", ";", "&", "|&"];
+
+/// Words that run the command after them
+const COMMAND_PREFIXES: &[&str] = &["sudo", "doas", "env", "nohup", "nice", "time", "command"];
+
+/// Tools whose options are words after a single dash, like `find -name`
+pub(crate) const SINGLE_DASH_TOOLS: &[&str] = &[
+    "find", "java", "ffmpeg", "ffprobe", "gcc", "g++", "clang", "cc", "go", "convert", "magick",
+    "openssl", "xrandr",
+];
+
+/// One word of a command line, with the quoting state of each character
+struct Word {
+    text: String,
+    /// Some part of the word is quoted or escaped
+    quoted: bool,
+    /// An option of a tool in [`SINGLE_DASH_TOOLS`], whose letters aren't separate flags
+    long_option: bool,
+    /// Every letter of a flag bundle is a flag of the tool that takes no value
+    value_free: bool,
+}
+
+/// Canonical forms of commands, shared by everything that compares them
+///
+/// [`canonical`](Self::canonical) removes differences in spelling such as
+/// spacing and flag order; the cache, dedup, the learning store and execution
+/// stats compare commands by it. It is a key, not a command to run: stores
+/// keep the spelling they were given. [`shape`](Self::shape) also replaces
+/// literals such as paths, numbers, and quoted strings with placeholders, so
+/// `du -sh /var/log` and `du -sh ~/src` count as the same kind of command.
+pub struct CommandNormalizer;
+
+impl CommandNormalizer {
+    /// The command with single spaces outside quotes, no trailing `;`, bundled
+    /// short flags sorted (`-la` and `-al` match; the last flag of a bundle
+    /// that may take a value stays last, so `tar -xzvf` keeps its `f` in
+    /// place), `'` for double-quoted words
+    /// that expand nothing, and tidied path arguments (`./src//` becomes `src`)
+    ///
+    /// Heredoc bodies are kept verbatim.
+    pub fn canonical(command: &str) -> String {
+        Self::normalize_lines(command, |words| {
+            words
+                .iter()
+                .map(|(word, command_position)| Self::canonical_word(word, *command_position))
+                .collect()
+        })
+    }
+
+    /// [`canonical`](Self::canonical) with literals replaced by placeholders
+    ///
+    /// Quoted strings become `<str>`, URLs `<url>`, IP addresses `<ip>`,
+    /// numbers (with an optional unit, as in `10M`) `<n>`, hashes `<hash>`,
+    /// and paths `<path>`; `--name=value` keeps its name.
+    pub fn shape(command: &str) -> String {
+        Self::normalize_lines(command, |words| {
+            words
+                .iter()
+                .map(|(word, command_position)| {
+                    let text = Self::canonical_word(word, *command_position);
+                    if *command_position {
+                        text
+                    } else if word.text.contains(['\'', '"']) {
+                        "<str>".to_string()
+                    } else {
+                        Self::placeholder(&text)
+                    }
+                })
+                .collect()
+        })
+    }
+
+    /// Whether two spellings are the same command
+    pub fn same(a: &str, b: &str) -> bool {
+        a == b || Self::canonical(a) == Self::canonical(b)
+    }
+
+    fn normalize_lines(command: &str, rewrite: impl Fn(&[(Word, bool)]) -> Vec<String>) -> String {
+        let (head, body) = ResponseParser::split_heredoc(command.trim());
+        let lines: Vec<String> = head
+            .lines()
+            .map(|line| {
+                let mut words = Self::words(line);
+                // A trailing `;` ends the command either way
+                if let Some((last, _)) = words.last_mut() {
+                    if !last.quoted {
+                        last.text = last.text.trim_end_matches(';').to_string();
+                    }
+                }
+                words.retain(|(word, _)| !word.text.is_empty());
+                rewrite(&words).join(" ")
+            })
+            .filter(|line| !line.is_empty())
+            .collect();
+
+        let mut normalized = lines.join("\n");
+        if !body.is_empty() {
+            normalized.push('\n');
+            normalized.push_str(body.trim_end());
+        }
+        normalized
+    }
+
+    /// Words split on whitespace outside quotes, each with whether it runs a command
+    fn words(line: &str) -> Vec<(Word, bool)> {
+        let chars: Vec<char> = line.chars().collect();
+        let (states, _) = QuotingCheck::quote_states(&chars);
+
+        let mut words = Vec::new();
+        let mut current = Word {
+            text: String::new(),
+            quoted: false,
+            long_option: false,
+            value_free: false,
+        };
+        for (c, state) in chars.iter().zip(&states) {
+            if c.is_whitespace() && state.is_none() {
+                if !current.text.is_empty() {
+                    words.push(std::mem::replace(
+                        &mut current,
+                        Word {
+                            text: String::new(),
+                            quoted: false,
+                            long_option: false,
+                            value_free: false,
+                        },
+                    ));
+                }
+                continue;
+            }
+            current.quoted |= state.is_some() || matches!(c, '\'' | '"' | '\\');
+            current.text.push(*c);
+        }
+        if !current.text.is_empty() {
+            words.push(current);
+        }
+
+        let mut command_position = true;
+        let mut single_dash = false;
+        let mut tool = String::new();
+        words
+            .into_iter()
+            .map(|mut word| {
+                let runs_command = command_position;
+                let text = word.text.as_str();
+                if runs_command {
+                    let executable = text.rsplit('/').next().unwrap_or(text);
+                    single_dash = SINGLE_DASH_TOOLS.contains(&executable);
+                    tool = executable.to_string();
+                } else {
+                    word.long_option = single_dash && text.starts_with('-');
+                    word.value_free = Self::value_free(&tool, text);
+                }
+                command_position = SEPARATORS.contains(&text)
+                    || (runs_command && COMMAND_PREFIXES.contains(&text));
+                (word, runs_command)
+            })
+            .collect()
+    }
+
+    fn canonical_word(word: &Word, command_position: bool) -> String {
+        let text = word.text.as_str();
+        if word.quoted {
+            return Self::single_quoted(text).unwrap_or_else(|| text.to_string());
+        }
+        if let Some(flags) = Self::sorted_flags(text, word.value_free).filter(|_| !word.long_option)
+        {
+            return flags;
+        }
+        if !command_position {
+            return Self::tidy_path(text);
+        }
+        text.to_string()
+    }
+
+    /// Whether every letter of `-xyz` is a flag of `tool` that takes no value,
+    /// going by the built-in flag catalog
+    fn value_free(tool: &str, word: &str) -> bool {
+        static CATALOG: OnceLock<Option<FlagCatalog>> = OnceLock::new();
+        let Some(letters) = word.strip_prefix('-').filter(|l| !l.starts_with('-')) else {
+            return false;
+        };
+        let Some(flags) = CATALOG
+            .get_or_init(|| FlagCatalog::builtin().ok())
+            .as_ref()
+            .and_then(|catalog| catalog.lookup(tool, None))
+        else {
+            return false;
+        };
+        letters.chars().all(|c| {
+            flags
+                .flags
+                .iter()
+                .any(|s| s.is(&format!("-{c}")) && !s.takes_value())
+        })
+    }
+
+    /// `-la` as `-al`; bundles that repeat a letter, like `-vv`, are left alone
+    ///
+    /// Unless `value_free`, the last letter may be a flag whose value is the
+    /// next word (`-xzvf a.tgz`), so only the letters before it are sorted.
+    fn sorted_flags(word: &str, value_free: bool) -> Option<String> {
+        let letters = word.strip_prefix('-')?;
+        if letters.len() < 2
+            || letters.starts_with('-')
+            || !letters.chars().all(|c| c.is_ascii_alphabetic())
+        {
+            return None;
+        }
+        let mut sorted: Vec<char> = letters.chars().collect();
+        let last = if value_free { None } else { sorted.pop() };
+        sorted.sort_unstable();
+        sorted.extend(last);
+        let mut unique = sorted.clone();
+        unique.sort_unstable();
+        if unique.windows(2).any(|pair| pair[0] == pair[1]) {
+            return None;
+        }
+        Some(format!("-{}", sorted.into_iter().collect::<String>()))
+    }
+
+    /// `"text"` as `'text'` when nothing inside would expand or need escaping
+    fn single_quoted(word: &str) -> Option<String> {
+        let inner = word.strip_prefix('"')?.strip_suffix('"')?;
+        if inner.contains(['"', '\'', '$', '`', '\\', '!']) {
+            return None;
+        }
+        Some(format!("'{inner}'"))
+    }
+
+    /// Collapses repeated `/`, and drops a leading `./` and a trailing `/`
+    fn tidy_path(word: &str) -> String {
+        if !word.contains('/') || word.contains("://") || word.contains('=') {
+            return word.to_string();
+        }
+        let mut path = String::with_capacity(word.len());
+        for c in word.chars() {
+            if !(c == '/' && path.ends_with('/')) {
+                path.push(c);
+            }
+        }
+        if path.len() > 1 && path.ends_with('/') {
+            path.pop();
+        }
+        match path.strip_prefix("./") {
+            Some(rest) if !rest.is_empty() && !rest.starts_with('-') => rest.to_string(),
+            _ => path,
+        }
+    }
+
+    fn placeholder(word: &str) -> String {
+        if let Some((name, value)) = word.split_once('=') {
+            if name.starts_with("--") && !value.is_empty() {
+                return format!("{name}={}", Self::placeholder(value));
+            }
+        }
+        if word.starts_with('-') {
+            return word.to_string();
+        }
+
+        let is_hex = |w: &str| w.chars().all(|c| c.is_ascii_hexdigit());
+        let number = word.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+        if word == "." || word == ".." {
+            "<path>".to_string()
+        } else if word.contains("://") {
+            "<url>".to_string()
+        } else if word.split('.').count() == 4
+            && word.split('.').all(|part| part.parse::<u8>().is_ok())
+        {
+            "<ip>".to_string()
+        } else if number.starts_with(|c: char| c.is_ascii_digit())
+            && number.chars().all(|c| c.is_ascii_digit() || c == '.')
+            && word.len() - number.len() <= 2
+        {
+            "<n>".to_string()
+        } else if word.len() >= 7 && is_hex(word) && word.chars().any(|c| c.is_ascii_digit()) {
+            "<hash>".to_string()
+        } else if word.contains('/') || word.starts_with('~') {
+            "<path>".to_string()
+        } else {
+            word.to_string()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sorts_bundles_of_toggles() {
+        assert_eq!(CommandNormalizer::canonical("ls -la /tmp"), "ls -al /tmp");
+        assert!(CommandNormalizer::same("ls -la /tmp", "ls -al /tmp"));
+    }
+
+    #[test]
+    fn keeps_a_possible_value_flag_last() {
+        assert_eq!(
+            CommandNormalizer::canonical("tar -xzvf a.tgz"),
+            "tar -vxzf a.tgz"
+        );
+        assert!(CommandNormalizer::same(
+            "tar -xzvf a.tgz",
+            "tar -zxvf a.tgz"
+        ));
+        assert!(!CommandNormalizer::same(
+            "tar -xzvf a.tgz",
+            "tar -fxzv a.tgz"
+        ));
+    }
+}
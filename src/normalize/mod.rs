@@ -0,0 +1,3 @@
+pub mod command;
+
+pub use command::CommandNormalizer;
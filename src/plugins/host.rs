@@ -0,0 +1,317 @@
+use anyhow::{Context, Result};
+use std::collections::HashSet;
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+use tracing::{debug, warn};
+
+use crate::config::PluginConfig;
+use crate::plugins::protocol::{
+    PluginCapability, PluginRequest, PluginResponse, PLUGIN_SCHEMA_VERSION,
+};
+use crate::postprocess::Transform;
+
+/// Executable name prefix that marks a plugin
+const PLUGIN_PREFIX: &str = "commandy-";
+
+/// How commandy runs a plugin
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PluginKind {
+    /// A `commandy-<name>` executable on PATH
+    Exec,
+    /// A sandboxed `.wasm` module in ~/.commandy/plugins
+    #[cfg(feature = "wasm-plugins")]
+    Wasm { allow_cwd: bool },
+}
+
+/// A plugin that answered `describe`
+#[derive(Debug, Clone)]
+pub struct Plugin {
+    pub name: String,
+    pub path: PathBuf,
+    pub kind: PluginKind,
+    pub capabilities: Vec<PluginCapability>,
+    timeout: Duration,
+}
+
+impl Plugin {
+    pub fn has(&self, capability: PluginCapability) -> bool {
+        self.capabilities.contains(&capability)
+    }
+
+    /// Sends one request and parses the response
+    fn call(&self, request: &PluginRequest) -> Result<PluginResponse> {
+        let payload = serde_json::to_vec(request)?;
+
+        let output = match self.kind {
+            PluginKind::Exec => self.run_exec(&payload)?,
+            #[cfg(feature = "wasm-plugins")]
+            PluginKind::Wasm { allow_cwd } => {
+                crate::plugins::wasm::run(&self.path, &payload, self.timeout, allow_cwd)
+                    .with_context(|| format!("Plugin {} failed", self.name))?
+            }
+        };
+
+        let response: PluginResponse = serde_json::from_slice(&output)
+            .with_context(|| format!("Plugin {} returned invalid JSON", self.name))?;
+        if response.schema_version != PLUGIN_SCHEMA_VERSION {
+            anyhow::bail!(
+                "Plugin {} speaks schema version {}, expected {PLUGIN_SCHEMA_VERSION}",
+                self.name,
+                response.schema_version
+            );
+        }
+
+        Ok(response)
+    }
+
+    /// Runs an executable plugin, killing it on timeout
+    fn run_exec(&self, payload: &[u8]) -> Result<Vec<u8>> {
+        let mut child = Command::new(&self.path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .with_context(|| format!("Failed to start plugin {}", self.name))?;
+
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(payload)?;
+        }
+
+        // Read stdout on a thread so a chatty plugin can't block on a full pipe
+        let mut stdout = child.stdout.take().context("Plugin stdout unavailable")?;
+        let reader = std::thread::spawn(move || {
+            let mut output = Vec::new();
+            stdout.read_to_end(&mut output).map(|_| output)
+        });
+
+        let started = Instant::now();
+        let status = loop {
+            if let Some(status) = child.try_wait()? {
+                break status;
+            }
+            if started.elapsed() > self.timeout {
+                let _ = child.kill();
+                let _ = child.wait();
+                anyhow::bail!("Plugin {} timed out after {:?}", self.name, self.timeout);
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        };
+
+        let output = reader
+            .join()
+            .map_err(|_| anyhow::anyhow!("Plugin reader thread panicked"))??;
+        if !status.success() {
+            anyhow::bail!("Plugin {} exited with {status}", self.name);
+        }
+
+        Ok(output)
+    }
+}
+
+/// Discovers plugins and talks to them
+#[derive(Default)]
+pub struct PluginHost {
+    plugins: Vec<Plugin>,
+}
+
+impl PluginHost {
+    /// Finds plugins, skipping disabled ones and those that fail `describe`
+    pub fn discover(config: &PluginConfig) -> Self {
+        if !config.enabled {
+            return Self::default();
+        }
+
+        let timeout = Duration::from_millis(config.timeout_ms);
+        let mut seen = HashSet::new();
+        let mut plugins = Vec::new();
+
+        #[cfg(feature = "wasm-plugins")]
+        Self::discover_wasm(config, timeout, &mut seen, &mut plugins);
+
+        let path = std::env::var_os("PATH").unwrap_or_default();
+        for dir in std::env::split_paths(&path) {
+            let Ok(entries) = std::fs::read_dir(&dir) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                let file_name = entry.file_name().to_string_lossy().to_string();
+                let Some(name) = file_name.strip_prefix(PLUGIN_PREFIX) else {
+                    continue;
+                };
+                // First match on PATH wins, like the shell
+                if !Self::is_executable(&entry.path())
+                    || config.disabled.iter().any(|d| d == name)
+                    || !seen.insert(name.to_string())
+                {
+                    continue;
+                }
+
+                let candidate = Plugin {
+                    name: name.to_string(),
+                    path: entry.path(),
+                    kind: PluginKind::Exec,
+                    capabilities: Vec::new(),
+                    timeout,
+                };
+                plugins.extend(Self::describe(candidate));
+            }
+        }
+
+        Self { plugins }
+    }
+
+    /// Loads `*.wasm` modules from ~/.commandy/plugins; these take precedence over PATH
+    #[cfg(feature = "wasm-plugins")]
+    fn discover_wasm(
+        config: &PluginConfig,
+        timeout: Duration,
+        seen: &mut HashSet<String>,
+        plugins: &mut Vec<Plugin>,
+    ) {
+        let Some(dir) = crate::plugins::wasm::plugin_dir() else {
+            return;
+        };
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            return;
+        };
+
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("wasm") {
+                continue;
+            }
+            let Some(name) = path.file_stem().and_then(|s| s.to_str()) else {
+                continue;
+            };
+            if config.disabled.iter().any(|d| d == name) || !seen.insert(name.to_string()) {
+                continue;
+            }
+
+            let candidate = Plugin {
+                name: name.to_string(),
+                path: path.clone(),
+                kind: PluginKind::Wasm {
+                    allow_cwd: config.wasm_allow_cwd.iter().any(|n| n == name),
+                },
+                capabilities: Vec::new(),
+                timeout,
+            };
+            plugins.extend(Self::describe(candidate));
+        }
+    }
+
+    /// Asks a candidate for its name and capabilities
+    fn describe(candidate: Plugin) -> Option<Plugin> {
+        let describe = PluginRequest::Describe {
+            schema_version: PLUGIN_SCHEMA_VERSION,
+        };
+        match candidate.call(&describe) {
+            Ok(response) => {
+                debug!(
+                    "Loaded plugin {} with capabilities {:?}",
+                    candidate.name, response.capabilities
+                );
+                Some(Plugin {
+                    name: response.name.unwrap_or_else(|| candidate.name.clone()),
+                    capabilities: response.capabilities,
+                    ..candidate
+                })
+            }
+            Err(e) => {
+                debug!("Skipping plugin {}: {e}", candidate.path.display());
+                None
+            }
+        }
+    }
+
+    pub fn plugins(&self) -> &[Plugin] {
+        &self.plugins
+    }
+
+    /// Collects context lines from every context-provider plugin
+    pub fn collect_context(&self, prompt: &str) -> Vec<String> {
+        let cwd = std::env::current_dir()
+            .map(|p| p.display().to_string())
+            .unwrap_or_default();
+        let mut lines = Vec::new();
+
+        for plugin in self
+            .plugins
+            .iter()
+            .filter(|p| p.has(PluginCapability::Context))
+        {
+            let request = PluginRequest::Context {
+                schema_version: PLUGIN_SCHEMA_VERSION,
+                prompt,
+                cwd: cwd.clone(),
+            };
+            match plugin.call(&request) {
+                Ok(response) => lines.extend(response.context),
+                Err(e) => warn!(plugin = %plugin.name, error = %e, "Context plugin failed"),
+            }
+        }
+
+        lines
+    }
+
+    /// Wraps each post-processor plugin as a pipeline transform
+    pub fn transforms(&self, prompt: &str) -> Vec<Box<dyn Transform>> {
+        self.plugins
+            .iter()
+            .filter(|p| p.has(PluginCapability::Postprocess))
+            .map(|plugin| {
+                Box::new(PluginTransform {
+                    plugin: plugin.clone(),
+                    prompt: prompt.to_string(),
+                }) as Box<dyn Transform>
+            })
+            .collect()
+    }
+
+    #[cfg(unix)]
+    fn is_executable(path: &std::path::Path) -> bool {
+        use std::os::unix::fs::PermissionsExt;
+        path.metadata()
+            .map(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+            .unwrap_or(false)
+    }
+
+    #[cfg(not(unix))]
+    fn is_executable(path: &std::path::Path) -> bool {
+        path.is_file()
+    }
+}
+
+/// Runs a post-processor plugin as one step of the pipeline
+pub struct PluginTransform {
+    plugin: Plugin,
+    prompt: String,
+}
+
+impl Transform for PluginTransform {
+    fn name(&self) -> &str {
+        &self.plugin.name
+    }
+
+    fn apply(&self, command: &str) -> String {
+        let commands = [command.to_string()];
+        let request = PluginRequest::Postprocess {
+            schema_version: PLUGIN_SCHEMA_VERSION,
+            prompt: &self.prompt,
+            commands: &commands,
+        };
+
+        match self.plugin.call(&request) {
+            Ok(response) => match response.commands.into_iter().next() {
+                Some(rewritten) if !rewritten.trim().is_empty() => rewritten,
+                _ => command.to_string(),
+            },
+            Err(e) => {
+                warn!(plugin = %self.plugin.name, error = %e, "Post-processor plugin failed");
+                command.to_string()
+            }
+        }
+    }
+}
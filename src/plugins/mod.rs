@@ -0,0 +1,7 @@
+pub mod host;
+pub mod protocol;
+#[cfg(feature = "wasm-plugins")]
+pub mod wasm;
+
+pub use host::{Plugin, PluginHost, PluginKind, PluginTransform};
+pub use protocol::{PluginCapability, PluginRequest, PluginResponse, PLUGIN_SCHEMA_VERSION};
@@ -0,0 +1,55 @@
+//! JSON messages exchanged with `commandy-<name>` plugin executables
+//!
+//! commandy writes one request object to the plugin's stdin and reads one
+//! response object from its stdout. Every message carries `schema_version`;
+//! plugins should reject versions they do not understand by exiting non-zero.
+
+use serde::{Deserialize, Serialize};
+
+/// Version of the request/response schema spoken by this build
+pub const PLUGIN_SCHEMA_VERSION: u32 = 1;
+
+/// What a plugin can do for commandy
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PluginCapability {
+    /// Adds lines of context to the model prompt
+    Context,
+    /// Rewrites suggested commands before display
+    Postprocess,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PluginRequest<'a> {
+    /// Asks the plugin for its name and capabilities
+    Describe { schema_version: u32 },
+    /// Asks for context relevant to a prompt
+    Context {
+        schema_version: u32,
+        prompt: &'a str,
+        cwd: String,
+    },
+    /// Asks the plugin to rewrite commands; the response must keep their order
+    Postprocess {
+        schema_version: u32,
+        prompt: &'a str,
+        commands: &'a [String],
+    },
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct PluginResponse {
+    pub schema_version: u32,
+    /// Display name, returned from `describe`
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub capabilities: Vec<PluginCapability>,
+    /// Context lines, returned from `context`
+    #[serde(default)]
+    pub context: Vec<String>,
+    /// Rewritten commands, returned from `postprocess`
+    #[serde(default)]
+    pub commands: Vec<String>,
+}
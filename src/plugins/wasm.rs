@@ -0,0 +1,81 @@
+//! Sandboxed plugins compiled to WebAssembly (WASI preview 1)
+//!
+//! A wasm plugin is a WASI command module that speaks the same JSON protocol
+//! as executable plugins: one request on stdin, one response on stdout. It
+//! gets no network, no environment, and no filesystem unless the config
+//! grants read-only access to the current directory.
+
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use wasmtime::{Config, Engine, Linker, Module, Store};
+use wasmtime_wasi::pipe::{MemoryInputPipe, MemoryOutputPipe};
+use wasmtime_wasi::preview1::{self, WasiP1Ctx};
+use wasmtime_wasi::{DirPerms, FilePerms, I32Exit, WasiCtxBuilder};
+
+/// Largest response a plugin may write to stdout
+const MAX_OUTPUT_BYTES: usize = 1 << 20;
+
+/// Directory scanned for `*.wasm` plugins
+pub fn plugin_dir() -> Option<PathBuf> {
+    dirs::home_dir().map(|home| home.join(".commandy").join("plugins"))
+}
+
+/// Runs a wasm plugin with the request on stdin and returns its stdout
+pub fn run(path: &Path, payload: &[u8], timeout: Duration, allow_cwd: bool) -> Result<Vec<u8>> {
+    // wasmtime-wasi's sync bindings start their own runtime, which can't
+    // nest inside the tokio runtime commandy runs on
+    std::thread::scope(|scope| {
+        scope
+            .spawn(|| run_guest(path, payload, timeout, allow_cwd))
+            .join()
+            .map_err(|_| anyhow::anyhow!("wasm plugin thread panicked"))?
+    })
+}
+
+fn run_guest(path: &Path, payload: &[u8], timeout: Duration, allow_cwd: bool) -> Result<Vec<u8>> {
+    // A fresh engine per run so one plugin's timeout can't interrupt another
+    let mut config = Config::new();
+    config.epoch_interruption(true);
+    let engine = &Engine::new(&config)?;
+
+    let module = Module::from_file(engine, path)
+        .with_context(|| format!("Failed to load wasm plugin {}", path.display()))?;
+
+    let mut linker: Linker<WasiP1Ctx> = Linker::new(engine);
+    preview1::add_to_linker_sync(&mut linker, |ctx| ctx)?;
+
+    let stdout = MemoryOutputPipe::new(MAX_OUTPUT_BYTES);
+    let mut builder = WasiCtxBuilder::new();
+    builder
+        .stdin(MemoryInputPipe::new(payload.to_vec()))
+        .stdout(stdout.clone());
+    if allow_cwd {
+        let cwd = std::env::current_dir()?;
+        builder.preopened_dir(cwd, ".", DirPerms::READ, FilePerms::READ)?;
+    }
+
+    let mut store = Store::new(engine, builder.build_p1());
+    store.set_epoch_deadline(1);
+
+    // Interrupt the guest once the timeout passes
+    let ticker_engine = engine.clone();
+    std::thread::spawn(move || {
+        std::thread::sleep(timeout);
+        ticker_engine.increment_epoch();
+    });
+
+    let instance = linker.instantiate(&mut store, &module)?;
+    let start = instance.get_typed_func::<(), ()>(&mut store, "_start")?;
+
+    match start.call(&mut store, ()) {
+        Ok(()) => {}
+        Err(e) => match e.downcast_ref::<I32Exit>() {
+            Some(I32Exit(0)) => {}
+            Some(I32Exit(code)) => anyhow::bail!("exited with status {code}"),
+            None => return Err(e.context("wasm plugin trapped or timed out")),
+        },
+    }
+
+    Ok(stdout.contents().to_vec())
+}
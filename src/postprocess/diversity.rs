@@ -0,0 +1,64 @@
+use std::collections::HashSet;
+use tracing::debug;
+
+use crate::cli::Suggestion;
+use crate::normalize::CommandNormalizer;
+
+/// Leading tokens two suggestions may share before the later one counts as the same approach
+const APPROACH_TOKENS: usize = 2;
+
+/// Removes near-duplicate suggestions and moves repeated approaches to the back
+///
+/// Small models often return three variations of one command. Duplicates are found by
+/// comparing canonical commands ([`CommandNormalizer::canonical`]); when one of a set of
+/// duplicates appears in the user's history, that spelling is the one kept.
+pub struct Diversifier;
+
+impl Diversifier {
+    pub fn apply(suggestions: Vec<Suggestion>, history: &[String]) -> Vec<Suggestion> {
+        let history: HashSet<String> = history
+            .iter()
+            .map(|c| CommandNormalizer::canonical(c))
+            .collect();
+
+        // Dedup, letting a spelling from history replace the first one seen
+        let mut unique: Vec<(String, Suggestion)> = Vec::with_capacity(suggestions.len());
+        for suggestion in suggestions {
+            let key = CommandNormalizer::canonical(&suggestion.command);
+            match unique.iter_mut().find(|(k, _)| *k == key) {
+                Some((_, kept)) => {
+                    debug!(
+                        kept = kept.command,
+                        dropped = suggestion.command,
+                        "Dropped duplicate suggestion"
+                    );
+                    let kept_known = history.contains(&CommandNormalizer::canonical(&kept.command));
+                    if !kept_known && history.contains(&key) {
+                        kept.command = suggestion.command;
+                    }
+                }
+                None => unique.push((key, suggestion)),
+            }
+        }
+
+        // Keep order, but push repeats of an approach behind every distinct one
+        let mut selected: Vec<Suggestion> = Vec::with_capacity(unique.len());
+        let mut repeated = Vec::new();
+        let mut approaches: Vec<Vec<String>> = Vec::new();
+        for (key, suggestion) in unique {
+            let approach: Vec<String> = key
+                .split_whitespace()
+                .take(APPROACH_TOKENS)
+                .map(String::from)
+                .collect();
+            if approaches.contains(&approach) {
+                repeated.push(suggestion);
+            } else {
+                approaches.push(approach);
+                selected.push(suggestion);
+            }
+        }
+        selected.extend(repeated);
+        selected
+    }
+}
@@ -0,0 +1,215 @@
+use tracing::debug;
+
+use crate::ai::ResponseParser;
+use crate::cli::Suggestion;
+use crate::postprocess::QuotingCheck;
+
+/// Words that run the command after them
+const COMMAND_PREFIXES: &[&str] = &[
+    "sudo", "doas", "env", "nohup", "nice", "time", "command", "xargs",
+];
+
+/// Shells whose `-c` script is checked as well
+const SHELLS: &[&str] = &["sh", "bash", "zsh", "dash"];
+
+/// A variable expanded in a destructive command
+struct Expansion {
+    start: usize,
+    end: usize,
+    name: String,
+    /// Inside double quotes already
+    quoted: bool,
+}
+
+/// Guards variables in destructive commands against being unset or empty
+///
+/// `rm -rf $DIR/` deletes `/` when `DIR` is unset, so `$DIR` and `${DIR}` in
+/// `rm`, `mv`, `shred`, recursive `chmod`/`chown`, `find -delete`, `rsync
+/// --delete` and similar become `"${DIR:?}"`, which stops the shell with an
+/// error instead. Variables that already have a default or a guard
+/// (`${DIR:-/tmp}`, `${DIR:?}`) are left alone.
+pub struct ExpansionGuard;
+
+impl ExpansionGuard {
+    pub fn apply(mut suggestions: Vec<Suggestion>) -> Vec<Suggestion> {
+        for suggestion in &mut suggestions {
+            let guarded = Self::guard(&suggestion.command);
+            if guarded != suggestion.command {
+                debug!(
+                    "Expansion guard rewrote: {} -> {guarded}",
+                    suggestion.command
+                );
+                suggestion.command = guarded;
+            }
+        }
+        suggestions
+    }
+
+    /// Rewrites each unguarded variable of a destructive command to `"${VAR:?}"`
+    pub fn guard(command: &str) -> String {
+        let (head, body) = ResponseParser::split_heredoc(command);
+        let chars: Vec<char> = head.chars().collect();
+
+        let mut guarded = String::with_capacity(command.len() + 16);
+        let mut copied = 0;
+        for expansion in Self::expansions(&chars) {
+            guarded.extend(&chars[copied..expansion.start]);
+            if expansion.quoted {
+                guarded.push_str(&format!("${{{}:?}}", expansion.name));
+            } else {
+                guarded.push_str(&format!("\"${{{}:?}}\"", expansion.name));
+            }
+            copied = expansion.end;
+        }
+        guarded.extend(&chars[copied..]);
+        guarded.push_str(body);
+        guarded
+    }
+
+    /// First variable a destructive command expands without a guard
+    ///
+    /// Also looks inside scripts given to `sh -c`, which [`guard`](Self::guard)
+    /// can't rewrite without re-quoting the whole script.
+    pub fn unguarded(command: &str) -> Option<String> {
+        let (head, _) = ResponseParser::split_heredoc(command);
+        let chars: Vec<char> = head.chars().collect();
+        if let Some(expansion) = Self::expansions(&chars).into_iter().next() {
+            return Some(expansion.name);
+        }
+
+        let (states, _) = QuotingCheck::quote_states(&chars);
+        for (start, end) in Self::segments(&chars, &states) {
+            let words = Self::words(&chars[start..end], &states[start..end]);
+            let mut words = words
+                .iter()
+                .skip_while(|word| COMMAND_PREFIXES.contains(&word.as_str()));
+            let shell = words
+                .next()
+                .is_some_and(|word| SHELLS.contains(&word.as_str()));
+            if shell && words.next().is_some_and(|word| word == "-c") {
+                let Some(script) = words.next() else {
+                    continue;
+                };
+                let script: Vec<char> = script.chars().collect();
+                if let Some(name) = Self::unguarded(&QuotingCheck::dequote(&script)) {
+                    return Some(name);
+                }
+            }
+        }
+        None
+    }
+
+    fn expansions(chars: &[char]) -> Vec<Expansion> {
+        let (states, _) = QuotingCheck::quote_states(chars);
+        let mut found = Vec::new();
+
+        for (start, end) in Self::segments(chars, &states) {
+            let words = Self::words(&chars[start..end], &states[start..end]);
+            if !Self::is_destructive(&words) {
+                continue;
+            }
+
+            let mut i = start;
+            while i < end {
+                let live = matches!(states[i], None | Some('"'));
+                if chars[i] == '$' && live {
+                    if let Some((name, len)) = Self::variable(&chars[i..end]) {
+                        found.push(Expansion {
+                            start: i,
+                            end: i + len,
+                            name,
+                            quoted: states[i] == Some('"'),
+                        });
+                        i += len;
+                        continue;
+                    }
+                }
+                i += 1;
+            }
+        }
+        found
+    }
+
+    /// `$NAME`, `${NAME}`, or `$1` at the start of `chars`, with its length
+    fn variable(chars: &[char]) -> Option<(String, usize)> {
+        let is_name = |c: &char| c.is_ascii_alphanumeric() || *c == '_';
+        match chars.get(1)? {
+            '{' => {
+                let close = chars.iter().position(|&c| c == '}')?;
+                let name: String = chars[2..close].iter().collect();
+                // Anything but a bare name already has an operator such as :- or :?
+                let bare = !name.is_empty() && name.chars().all(|c| is_name(&c));
+                bare.then_some((name, close + 1))
+            }
+            c if c.is_ascii_digit() => Some((c.to_string(), 2)),
+            c if c.is_ascii_alphabetic() || *c == '_' => {
+                let name: String = chars[1..].iter().take_while(|c| is_name(c)).collect();
+                let len = name.len() + 1;
+                Some((name, len))
+            }
+            _ => None,
+        }
+    }
+
+    fn is_destructive(words: &[String]) -> bool {
+        let mut words = words.iter().map(String::as_str).skip_while(|word| {
+            COMMAND_PREFIXES.contains(word) || (word.contains('=') && !word.starts_with('-'))
+        });
+        let Some(executable) = words.next() else {
+            return false;
+        };
+        let executable = executable.rsplit('/').next().unwrap_or(executable);
+        let args: Vec<&str> = words.collect();
+        let recursive = args.iter().any(|arg| {
+            *arg == "--recursive"
+                || (arg.starts_with('-') && !arg.starts_with("--") && arg.contains('R'))
+        });
+
+        match executable {
+            "rm" | "rmdir" | "shred" | "unlink" | "truncate" | "mv" | "dd" => true,
+            "chmod" | "chown" | "chgrp" => recursive,
+            "find" => {
+                args.contains(&"-delete")
+                    || args.windows(2).any(|pair| {
+                        matches!(pair[0], "-exec" | "-execdir" | "-ok")
+                            && matches!(pair[1], "rm" | "shred" | "mv")
+                    })
+            }
+            "rsync" => args.iter().any(|arg| arg.starts_with("--delete")),
+            executable => executable.starts_with("mkfs"),
+        }
+    }
+
+    /// Character ranges of the commands in a list or pipeline
+    fn segments(chars: &[char], states: &[Option<char>]) -> Vec<(usize, usize)> {
+        let mut segments = Vec::new();
+        let mut start = 0;
+        for (i, c) in chars.iter().enumerate() {
+            if states[i].is_none() && matches!(c, ';' | '|' | '&' | '\n') {
+                segments.push((start, i));
+                start = i + 1;
+            }
+        }
+        segments.push((start, chars.len()));
+        segments
+    }
+
+    /// Words split on whitespace outside quotes, with their quoting kept
+    fn words(chars: &[char], states: &[Option<char>]) -> Vec<String> {
+        let mut words = Vec::new();
+        let mut word = String::new();
+        for (c, state) in chars.iter().zip(states) {
+            if c.is_whitespace() && state.is_none() {
+                if !word.is_empty() {
+                    words.push(std::mem::take(&mut word));
+                }
+            } else {
+                word.push(*c);
+            }
+        }
+        if !word.is_empty() {
+            words.push(word);
+        }
+        words
+    }
+}
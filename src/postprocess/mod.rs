@@ -0,0 +1,13 @@
+pub mod diversity;
+pub mod expansion;
+pub mod pipeline;
+pub mod quoting;
+pub mod ranking;
+pub mod transforms;
+
+pub use diversity::Diversifier;
+pub use expansion::ExpansionGuard;
+pub use pipeline::PostProcessor;
+pub use quoting::QuotingCheck;
+pub use ranking::{Ranker, RankingFactors};
+pub use transforms::Transform;
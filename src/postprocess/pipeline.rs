@@ -0,0 +1,97 @@
+use tracing::{debug, warn};
+
+use crate::ai::ResponseParser;
+use crate::cli::Suggestion;
+use crate::config::PostProcessConfig;
+use crate::postprocess::transforms::{self, EnvPrefix, RegexReplace, Transform};
+
+/// Ordered list of transforms applied to suggestions before display
+pub struct PostProcessor {
+    transforms: Vec<Box<dyn Transform>>,
+}
+
+impl PostProcessor {
+    /// Builds the pipeline: built-ins in configured order, then regex rules, then env prefixes
+    pub fn from_config(config: &PostProcessConfig) -> Self {
+        let mut pipeline: Vec<Box<dyn Transform>> = Vec::new();
+
+        for name in &config.transforms {
+            match transforms::builtin(name) {
+                Some(transform) => pipeline.push(transform),
+                None => warn!(
+                    "Unknown post-processor '{name}' (available: {})",
+                    transforms::BUILTIN_TRANSFORMS.join(", ")
+                ),
+            }
+        }
+
+        for rule in &config.replace {
+            match RegexReplace::new(rule) {
+                Ok(transform) => pipeline.push(Box::new(transform)),
+                Err(e) => warn!("Ignoring invalid replace rule '{}': {e}", rule.pattern),
+            }
+        }
+
+        pipeline.extend(
+            config
+                .env_prefix
+                .iter()
+                .filter_map(EnvPrefix::for_current_dir)
+                .map(|t| Box::new(t) as Box<dyn Transform>),
+        );
+
+        Self {
+            transforms: pipeline,
+        }
+    }
+
+    /// Runs every transform over a single command
+    pub fn apply(&self, command: &str) -> String {
+        self.apply_with(command, &[])
+    }
+
+    /// Runs the pipeline followed by per-request transforms such as plugins
+    ///
+    /// Heredoc bodies are file content, so only the shell before them is rewritten.
+    pub fn apply_with(&self, command: &str, extra: &[Box<dyn Transform>]) -> String {
+        let (head, body) = ResponseParser::split_heredoc(command);
+        let head = self.transforms.iter().chain(extra).fold(
+            head.trim_end_matches('\n').to_string(),
+            |cmd, t| {
+                let rewritten = t.apply(&cmd);
+                if rewritten != cmd {
+                    debug!("Post-processor {} rewrote: {cmd} -> {rewritten}", t.name());
+                }
+                rewritten
+            },
+        );
+        if body.is_empty() {
+            head
+        } else {
+            format!("{head}\n{body}")
+        }
+    }
+
+    /// Rewrites suggestions in place, dropping any that become duplicates
+    pub fn process(&self, suggestions: Vec<Suggestion>) -> Vec<Suggestion> {
+        self.process_with(suggestions, &[])
+    }
+
+    /// Like `process`, appending per-request transforms to the pipeline
+    pub fn process_with(
+        &self,
+        suggestions: Vec<Suggestion>,
+        extra: &[Box<dyn Transform>],
+    ) -> Vec<Suggestion> {
+        let mut processed: Vec<Suggestion> = Vec::with_capacity(suggestions.len());
+
+        for mut suggestion in suggestions {
+            suggestion.command = self.apply_with(&suggestion.command, extra);
+            if !processed.iter().any(|s| s.command == suggestion.command) {
+                processed.push(suggestion);
+            }
+        }
+
+        processed
+    }
+}
@@ -0,0 +1,290 @@
+use tracing::debug;
+
+use crate::ai::ResponseParser;
+use crate::cli::Suggestion;
+
+/// Characters that stop an unquoted word from meaning itself
+const SHELL_SPECIAL: &[char] = &[
+    ' ', '\t', '$', '`', ';', '&', '|', '(', ')', '<', '>', '\'', '"', '\\', '!', '#', '{', '}',
+];
+
+/// Characters in a query literal that could run something if left unquoted
+const HAZARDS: &[char] = &['$', '`', ';', '&', '|', '(', ')', '<', '>', '!'];
+
+/// Characters the shell expands into file names
+const GLOB: &[char] = &['*', '?', '[', ']'];
+
+/// find tests whose argument is a pattern for find, not for the shell
+const FIND_PATTERN_TESTS: &[&str] = &[
+    "-name",
+    "-iname",
+    "-path",
+    "-ipath",
+    "-wholename",
+    "-iwholename",
+    "-lname",
+    "-ilname",
+    "-regex",
+    "-iregex",
+];
+
+/// Repairs quoting the model commonly gets wrong
+///
+/// Names the user quoted in the query (`"report (final).pdf"`) are re-quoted
+/// wherever a suggestion left them bare, or left `$` and backticks live inside
+/// double quotes. Globs given to `find -name` and friends are quoted so the
+/// shell doesn't expand them first, and a quote left open at the end of a
+/// one-line command is closed. Heredoc bodies are never touched.
+pub struct QuotingCheck;
+
+impl QuotingCheck {
+    pub fn apply(mut suggestions: Vec<Suggestion>, query: &str) -> Vec<Suggestion> {
+        let literals = Self::literals(query);
+        for suggestion in &mut suggestions {
+            let fixed = Self::fix(&suggestion.command, &literals);
+            if fixed != suggestion.command {
+                debug!("Quoting check rewrote: {} -> {fixed}", suggestion.command);
+                suggestion.command = fixed;
+            }
+        }
+        suggestions
+    }
+
+    /// Applies every repair to one command
+    pub fn fix(command: &str, literals: &[String]) -> String {
+        let (head, body) = ResponseParser::split_heredoc(command);
+        let mut head = head.trim_end_matches('\n').to_string();
+        for literal in literals {
+            head = Self::quote_literal(&head, literal);
+        }
+        head = Self::quote_find_patterns(&head);
+        if !head.contains('\n') {
+            if let Some(quote) = Self::unbalanced_quote(&head) {
+                head.push(quote);
+            }
+        }
+
+        if body.is_empty() {
+            head
+        } else {
+            format!("{head}\n{body}")
+        }
+    }
+
+    /// Quote still open at the end of the command, ignoring heredoc bodies
+    pub fn unbalanced_quote(command: &str) -> Option<char> {
+        let (head, _) = ResponseParser::split_heredoc(command);
+        let chars: Vec<char> = head.chars().collect();
+        Self::quote_states(&chars).1
+    }
+
+    /// Names from the query that need quoting in a command
+    ///
+    /// These are quoted strings with spaces or shell characters, and bare
+    /// file names that contain shell characters, such as `a&b.txt`.
+    fn literals(query: &str) -> Vec<String> {
+        let chars: Vec<char> = query.chars().collect();
+        let mut literals: Vec<String> = Vec::new();
+        let mut i = 0;
+
+        while i < chars.len() {
+            let c = chars[i];
+            if c.is_whitespace() {
+                i += 1;
+                continue;
+            }
+
+            let at_word_start = i == 0 || chars[i - 1].is_whitespace();
+            if matches!(c, '"' | '\'' | '`') && at_word_start {
+                let close = chars[i + 1..]
+                    .iter()
+                    .position(|&d| d == c)
+                    .map(|n| i + 1 + n);
+                // An apostrophe inside a word, as in "don't", doesn't close anything
+                if let Some(end) =
+                    close.filter(|&end| chars.get(end + 1).is_none_or(|d| !d.is_alphanumeric()))
+                {
+                    let literal: String = chars[i + 1..end].iter().collect();
+                    if literal.contains(SHELL_SPECIAL) {
+                        literals.push(literal);
+                    }
+                    i = end + 1;
+                    continue;
+                }
+            }
+
+            let start = i;
+            while i < chars.len() && !chars[i].is_whitespace() {
+                i += 1;
+            }
+            let word: String = chars[start..i].iter().collect();
+            let word = word.trim_end_matches([',', ':', '?']);
+            let looks_like_file = word.contains(['.', '/']) && !word.starts_with('$');
+            if looks_like_file && word.contains(HAZARDS) {
+                literals.push(word.to_string());
+            }
+        }
+
+        // Longest first, so a name isn't quoted piecemeal through a shorter one
+        literals.sort_by_key(|literal| std::cmp::Reverse(literal.len()));
+        literals.dedup();
+        literals
+    }
+
+    /// Quotes every occurrence of `literal` that the shell would split or expand
+    fn quote_literal(command: &str, literal: &str) -> String {
+        if literal.is_empty() {
+            return command.to_string();
+        }
+        let chars: Vec<char> = command.chars().collect();
+        let (states, _) = Self::quote_states(&chars);
+        let char_index = |byte: usize| command[..byte].chars().count();
+
+        let mut result = String::with_capacity(command.len() + 8);
+        let mut copied = 0;
+        for (byte, _) in command.match_indices(literal) {
+            if byte < copied {
+                continue;
+            }
+            let start = char_index(byte);
+            let end = start + literal.chars().count();
+            let replacement = match states[start] {
+                None => Self::quote_preserving_globs(literal),
+                // Still live inside double quotes
+                Some('"')
+                    if literal.contains(['$', '`'])
+                        && !literal.contains(['"', '\\'])
+                        && states[start..end].iter().all(|s| *s == Some('"')) =>
+                {
+                    literal.replace('$', "\\$").replace('`', "\\`")
+                }
+                _ => continue,
+            };
+            result.push_str(&command[copied..byte]);
+            result.push_str(&replacement);
+            copied = byte + literal.len();
+        }
+        result.push_str(&command[copied..]);
+        result
+    }
+
+    /// Quotes the arguments of `find -name` and similar tests that hold a bare glob
+    fn quote_find_patterns(command: &str) -> String {
+        let chars: Vec<char> = command.chars().collect();
+        let (states, _) = Self::quote_states(&chars);
+        let words = Self::word_spans(&chars, &states);
+        let text = |&(start, end): &(usize, usize)| chars[start..end].iter().collect::<String>();
+
+        if !words.iter().any(|span| text(span) == "find") {
+            return command.to_string();
+        }
+
+        let mut result: Vec<char> = Vec::with_capacity(chars.len() + 4);
+        let mut copied = 0;
+        for pair in words.windows(2) {
+            let (test, (start, end)) = (&pair[0], pair[1]);
+            let bare_glob = (start..end).any(|i| GLOB.contains(&chars[i]) && states[i].is_none());
+            if !FIND_PATTERN_TESTS.contains(&text(test).as_str()) || !bare_glob {
+                continue;
+            }
+            let pattern = Self::dequote(&chars[start..end]);
+            result.extend_from_slice(&chars[copied..start]);
+            result.extend(Self::single_quote(&pattern).chars());
+            copied = end;
+        }
+        result.extend_from_slice(&chars[copied..]);
+        result.into_iter().collect()
+    }
+
+    /// Quotes the parts of a name between glob characters, so the globs still expand
+    fn quote_preserving_globs(literal: &str) -> String {
+        let mut quoted = String::new();
+        let mut run = String::new();
+        for c in literal.chars() {
+            if GLOB.contains(&c) {
+                quoted.push_str(&Self::quote_if_needed(&run));
+                run.clear();
+                quoted.push(c);
+            } else {
+                run.push(c);
+            }
+        }
+        quoted.push_str(&Self::quote_if_needed(&run));
+        quoted
+    }
+
+    pub(crate) fn quote_if_needed(text: &str) -> String {
+        if text.contains(SHELL_SPECIAL) {
+            Self::single_quote(text)
+        } else {
+            text.to_string()
+        }
+    }
+
+    pub(crate) fn single_quote(text: &str) -> String {
+        format!("'{}'", text.replace('\'', r"'\''"))
+    }
+
+    /// The value the shell would pass for a word, with its quoting removed
+    pub(crate) fn dequote(word: &[char]) -> String {
+        let mut value = String::new();
+        let mut quote: Option<char> = None;
+        let mut chars = word.iter().copied();
+        while let Some(c) = chars.next() {
+            match (c, quote) {
+                ('\\', Some('\'')) => value.push(c),
+                ('\\', _) => value.extend(chars.next()),
+                ('\'' | '"', None) => quote = Some(c),
+                (c, Some(q)) if c == q => quote = None,
+                _ => value.push(c),
+            }
+        }
+        value
+    }
+
+    /// Start and end of each word, split on whitespace outside quotes
+    fn word_spans(chars: &[char], states: &[Option<char>]) -> Vec<(usize, usize)> {
+        let mut spans = Vec::new();
+        let mut start = None;
+        for (i, c) in chars.iter().enumerate() {
+            let separator = c.is_whitespace() && states[i].is_none();
+            match (separator, start) {
+                (true, Some(s)) => {
+                    spans.push((s, i));
+                    start = None;
+                }
+                (false, None) => start = Some(i),
+                _ => {}
+            }
+        }
+        if let Some(s) = start {
+            spans.push((s, chars.len()));
+        }
+        spans
+    }
+
+    /// Quoting in effect before each character, and the quote left open at the end
+    ///
+    /// A character escaped with a backslash counts as quoted by `\`.
+    pub(crate) fn quote_states(chars: &[char]) -> (Vec<Option<char>>, Option<char>) {
+        let mut states = Vec::with_capacity(chars.len());
+        let mut quote = None;
+        let mut escaped = false;
+        for &c in chars {
+            if escaped {
+                states.push(Some('\\'));
+                escaped = false;
+                continue;
+            }
+            states.push(quote);
+            match (c, quote) {
+                ('\\', Some('\'')) => {}
+                ('\\', _) => escaped = true,
+                ('\'' | '"', None) => quote = Some(c),
+                (c, Some(q)) if c == q => quote = None,
+                _ => {}
+            }
+        }
+        (states, quote)
+    }
+}
@@ -0,0 +1,130 @@
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::cli::Suggestion;
+use crate::normalize::CommandNormalizer;
+use crate::utils::CommandValidator;
+
+/// Penalty for a command whose program isn't installed
+const MISSING_EXECUTABLE_PENALTY: f32 = 0.3;
+
+/// Runs after which history outweighs the source's own confidence
+const TRUSTED_RUNS: u32 = 5;
+
+/// Bonus per extra candidate that agreed on a command, and how many count
+const CONSENSUS_BONUS: f32 = 0.05;
+const MAX_CONSENSUS_BONUSES: usize = 3;
+
+/// What went into a suggestion's confidence, shown by `?` in the picker
+#[derive(Debug, Clone, Serialize)]
+pub struct RankingFactors {
+    /// Confidence the source gave before ranking
+    pub prior: f32,
+    /// First executable that isn't installed, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub missing_executable: Option<String>,
+    /// Times the command was run through commandy
+    pub runs: u32,
+    pub successes: u32,
+    /// Candidates that normalized to this same command
+    pub consensus: usize,
+    pub score: f32,
+}
+
+impl RankingFactors {
+    fn compute(
+        prior: f32,
+        missing: Option<String>,
+        history: Option<(u32, u32)>,
+        consensus: usize,
+    ) -> Self {
+        let (runs, successes) = history.unwrap_or((0, 0));
+        let mut score = prior;
+        if runs > 0 {
+            let weight = runs.min(TRUSTED_RUNS) as f32 / TRUSTED_RUNS as f32;
+            let success_rate = successes as f32 / runs as f32;
+            score = score * (1.0 - weight) + success_rate * weight;
+        }
+        if missing.is_some() {
+            score -= MISSING_EXECUTABLE_PENALTY;
+        }
+        score += CONSENSUS_BONUS * consensus.saturating_sub(1).min(MAX_CONSENSUS_BONUSES) as f32;
+
+        Self {
+            prior,
+            missing_executable: missing,
+            runs,
+            successes,
+            consensus,
+            score: score.clamp(0.05, 1.0),
+        }
+    }
+
+    /// One line per factor, for people
+    pub fn describe(&self) -> Vec<String> {
+        let mut lines = vec![format!(
+            "Starting confidence {:.0}% from the source",
+            self.prior * 100.0
+        )];
+        lines.push(match &self.missing_executable {
+            None => "Every program it runs is installed".to_string(),
+            Some(missing) => format!(
+                "{missing} is not installed (-{:.0}%)",
+                MISSING_EXECUTABLE_PENALTY * 100.0
+            ),
+        });
+        lines.push(match self.runs {
+            0 => "Never run through commandy before".to_string(),
+            runs => format!(
+                "Run {runs} time{} before, {} succeeded",
+                if runs == 1 { "" } else { "s" },
+                self.successes
+            ),
+        });
+        lines.push(match self.consensus {
+            0 | 1 => "Suggested once".to_string(),
+            n => format!("{n} candidates agreed on it"),
+        });
+        lines
+    }
+}
+
+/// Scores suggestions from validation, history, and agreement, best first
+pub struct Ranker;
+
+impl Ranker {
+    /// `history` returns (runs, successes) for commands run through commandy before
+    ///
+    /// Duplicates are counted but kept; [`Diversifier`](crate::postprocess::Diversifier) removes them afterwards.
+    pub fn apply(
+        mut suggestions: Vec<Suggestion>,
+        history: impl Fn(&str) -> Option<(u32, u32)>,
+    ) -> Vec<Suggestion> {
+        let mut agreement: HashMap<String, usize> = HashMap::new();
+        for suggestion in &suggestions {
+            *agreement
+                .entry(CommandNormalizer::canonical(&suggestion.command))
+                .or_default() += 1;
+        }
+
+        let validator = CommandValidator::new();
+        for suggestion in &mut suggestions {
+            let consensus = agreement
+                .get(&CommandNormalizer::canonical(&suggestion.command))
+                .copied()
+                .unwrap_or(1);
+            let factors = RankingFactors::compute(
+                suggestion.confidence,
+                validator.missing_executable(&suggestion.command),
+                history(&suggestion.command),
+                consensus,
+            );
+            suggestion.confidence = factors.score;
+            suggestion.ranking = Some(factors);
+        }
+
+        // Stable, so ties keep the source's order
+        suggestions.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+        suggestions
+    }
+}
@@ -0,0 +1,552 @@
+use regex::Regex;
+use which::which;
+
+use crate::config::{EnvPrefixRule, ReplaceRule};
+use crate::postprocess::QuotingCheck;
+use crate::utils::runtimes::{NodeEnvironment, PythonEnvironment};
+use crate::utils::{Platform, Runtimes};
+
+/// A rewrite applied to every suggested command before it is shown
+pub trait Transform: Send + Sync {
+    fn name(&self) -> &str;
+    fn apply(&self, command: &str) -> String;
+}
+
+/// Names of the built-in transforms accepted in `[postprocess] transforms`
+pub const BUILTIN_TRANSFORMS: [&str; 7] = [
+    "normalize_whitespace",
+    "expand_tilde",
+    "python3",
+    "runtimes",
+    "color_auto",
+    "progress",
+    "wsl",
+];
+
+/// Looks up a built-in transform by name
+pub fn builtin(name: &str) -> Option<Box<dyn Transform>> {
+    match name {
+        "normalize_whitespace" => Some(Box::new(NormalizeWhitespace)),
+        "expand_tilde" => dirs::home_dir().map(|home| {
+            Box::new(ExpandTilde {
+                home: home.display().to_string(),
+            }) as Box<dyn Transform>
+        }),
+        "python3" => Some(Box::new(PreferPython3::detect())),
+        "runtimes" => Some(Box::new(ActiveRuntimes::detect())),
+        "color_auto" => Some(Box::new(ColorAuto)),
+        "progress" => Some(Box::new(ProgressFlags::detect())),
+        "wsl" => Some(Box::new(WslInterop::detect())),
+        _ => None,
+    }
+}
+
+/// Trims the command and collapses runs of blanks outside of quotes
+pub struct NormalizeWhitespace;
+
+impl Transform for NormalizeWhitespace {
+    fn name(&self) -> &str {
+        "normalize_whitespace"
+    }
+
+    fn apply(&self, command: &str) -> String {
+        let mut result = String::with_capacity(command.len());
+        let mut quote: Option<char> = None;
+        let mut escaped = false;
+        // Whether the last character was an unquoted blank, unlike an escaped space
+        let mut blank = false;
+
+        for c in command.trim().chars() {
+            if escaped {
+                escaped = false;
+                result.push(c);
+                continue;
+            }
+
+            match (c, quote) {
+                (' ' | '\t', None) if blank => continue,
+                (' ' | '\t', None) => {
+                    blank = true;
+                    result.push(' ');
+                    continue;
+                }
+                ('\\', q) if q != Some('\'') => escaped = true,
+                ('\'' | '"', None) => quote = Some(c),
+                (c, Some(q)) if c == q => quote = None,
+                _ => {}
+            }
+            blank = false;
+            result.push(c);
+        }
+
+        result
+    }
+}
+
+/// Replaces a bare `~` with the home directory, so it also works where the
+/// shell wouldn't expand it, as after `--option=`
+///
+/// A `~` inside quotes is left alone, since the shell keeps it literal there.
+pub struct ExpandTilde {
+    home: String,
+}
+
+impl Transform for ExpandTilde {
+    fn name(&self) -> &str {
+        "expand_tilde"
+    }
+
+    fn apply(&self, command: &str) -> String {
+        let mut result = String::with_capacity(command.len());
+        let chars: Vec<char> = command.chars().collect();
+        let mut quote: Option<char> = None;
+        let mut escaped = false;
+
+        for (i, &c) in chars.iter().enumerate() {
+            if escaped {
+                escaped = false;
+                result.push(c);
+                continue;
+            }
+            match (c, quote) {
+                ('\\', q) if q != Some('\'') => escaped = true,
+                ('\'' | '"', None) => quote = Some(c),
+                (c, Some(q)) if c == q => quote = None,
+                ('~', None) => {
+                    let at_word_start = i == 0 || matches!(chars[i - 1], ' ' | '=' | ':');
+                    let ends_word = matches!(chars.get(i + 1), None | Some('/' | ' ' | ':'));
+                    if at_word_start && ends_word {
+                        result.push_str(&self.home);
+                        continue;
+                    }
+                }
+                _ => {}
+            }
+            result.push(c);
+        }
+
+        result
+    }
+}
+
+/// Rewrites `python`/`pip` to `python3`/`pip3` where only the latter exist
+pub struct PreferPython3 {
+    pattern: Option<Regex>,
+}
+
+impl PreferPython3 {
+    pub fn detect() -> Self {
+        let needs_rewrite = which("python").is_err() && which("python3").is_ok();
+        let pattern = needs_rewrite.then(|| {
+            Regex::new(r"(^|[|;&(]\s*|\bsudo\s+|\benv\s+)(python|pip)(\s|$)")
+                .expect("valid python regex")
+        });
+        Self { pattern }
+    }
+}
+
+impl Transform for PreferPython3 {
+    fn name(&self) -> &str {
+        "python3"
+    }
+
+    fn apply(&self, command: &str) -> String {
+        match &self.pattern {
+            Some(pattern) => pattern.replace_all(command, "${1}${2}3${3}").to_string(),
+            None => command.to_string(),
+        }
+    }
+}
+
+/// Adds `--color=auto` to tools that support it
+pub struct ColorAuto;
+
+impl ColorAuto {
+    fn supports_color(executable: &str) -> bool {
+        match executable {
+            "grep" | "egrep" | "fgrep" => true,
+            // BSD ls and diff on macOS predate --color
+            "ls" | "diff" => cfg!(target_os = "linux"),
+            _ => false,
+        }
+    }
+}
+
+impl Transform for ColorAuto {
+    fn name(&self) -> &str {
+        "color_auto"
+    }
+
+    fn apply(&self, command: &str) -> String {
+        command
+            .split(" | ")
+            .map(|segment| {
+                let mut words = segment.splitn(2, ' ');
+                let executable = words.next().unwrap_or("");
+                let rest = words.next();
+                if !Self::supports_color(executable) || segment.contains("--color") {
+                    return segment.to_string();
+                }
+                match rest {
+                    Some(rest) => format!("{executable} --color=auto {rest}"),
+                    None => format!("{executable} --color=auto"),
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" | ")
+    }
+}
+
+/// Shows progress for long copies: rsync and dd flags, or `pv` where it can be spliced in
+pub struct ProgressFlags {
+    has_pv: bool,
+    has_rsync: bool,
+}
+
+impl ProgressFlags {
+    pub fn detect() -> Self {
+        Self {
+            has_pv: which("pv").is_ok(),
+            has_rsync: which("rsync").is_ok(),
+        }
+    }
+
+    fn rsync(words: &[&str], command: &str) -> Option<String> {
+        let has_progress = words
+            .iter()
+            .any(|w| w.starts_with("--info=progress") || *w == "--progress" || *w == "-P");
+        (!has_progress).then(|| command.replacen("rsync", "rsync --info=progress2", 1))
+    }
+
+    fn dd(words: &[&str], command: &str) -> Option<String> {
+        let has_status = words.iter().any(|w| w.starts_with("status="));
+        (!has_status).then(|| format!("{command} status=progress"))
+    }
+
+    /// `cp -r a b` becomes rsync; a single-file `cp a b` goes through pv
+    fn cp(&self, words: &[&str]) -> Option<String> {
+        let (flags, paths): (Vec<&str>, Vec<&str>) =
+            words[1..].iter().partition(|w| w.starts_with('-'));
+        if paths.len() != 2 {
+            return None;
+        }
+        let recursive = flags
+            .iter()
+            .any(|f| *f == "--recursive" || (!f.starts_with("--") && f.contains(['r', 'R', 'a'])));
+
+        if recursive && self.has_rsync {
+            Some(format!(
+                "rsync -a --info=progress2 {} {}",
+                paths[0], paths[1]
+            ))
+        } else if !recursive && flags.is_empty() && self.has_pv {
+            Some(format!("pv {} > {}", paths[0], paths[1]))
+        } else {
+            None
+        }
+    }
+
+    /// Streams the archive through pv: `tar -czf out.tgz dir` -> `tar -czf - dir | pv > out.tgz`
+    fn tar(&self, words: &[&str]) -> Option<String> {
+        if !self.has_pv || words.len() < 3 {
+            return None;
+        }
+        let flags = words[1].trim_start_matches('-');
+        // Only the bundled form with f last, so the next word is the archive
+        if !flags.ends_with('f') || flags.len() < 2 {
+            return None;
+        }
+        let archive = words[2];
+        let rest = words[3..].join(" ");
+
+        if flags.contains('c') {
+            Some(format!("tar {} - {rest} | pv > {archive}", words[1]))
+        } else if flags.contains('x') {
+            Some(
+                format!("pv {archive} | tar {} - {rest}", words[1])
+                    .trim_end()
+                    .to_string(),
+            )
+        } else {
+            None
+        }
+    }
+}
+
+impl Transform for ProgressFlags {
+    fn name(&self) -> &str {
+        "progress"
+    }
+
+    fn apply(&self, command: &str) -> String {
+        // Only plain single commands; quoting or chaining makes rewrites unsafe
+        let simple = !command.contains(['|', ';', '&', '>', '<', '\'', '"', '`', '$']);
+        let words: Vec<&str> = command.split_whitespace().collect();
+        if !simple || words.is_empty() {
+            return command.to_string();
+        }
+
+        let rewritten = match words[0] {
+            "rsync" => Self::rsync(&words, command),
+            "dd" => Self::dd(&words, command),
+            "cp" => self.cp(&words),
+            "tar" => self.tar(&words),
+            _ => None,
+        };
+        rewritten.unwrap_or_else(|| command.to_string())
+    }
+}
+
+/// Under WSL, rewrites Windows paths to `/mnt/<drive>`, copies with `clip.exe`,
+/// and opens files with `wslview` or `explorer.exe`
+pub struct WslInterop {
+    /// `None` outside WSL, where nothing is rewritten
+    opener: Option<&'static str>,
+}
+
+impl WslInterop {
+    pub fn detect() -> Self {
+        let opener = Platform::detect().wsl.map(|_| match which("wslview") {
+            Ok(_) => "wslview",
+            Err(_) => "explorer.exe",
+        });
+        Self { opener }
+    }
+
+    /// `C:\Users\me\notes.txt` or `C:/Users/me` -> `/mnt/c/Users/me/...`
+    fn windows_paths(command: &str) -> String {
+        let path =
+            Regex::new(r#"(^|[\s"'=])([A-Za-z]):[\\/]([^\s"'|;&<>]*)"#).expect("valid regex");
+        path.replace_all(command, |caps: &regex::Captures| {
+            format!(
+                "{}/mnt/{}/{}",
+                &caps[1],
+                caps[2].to_ascii_lowercase(),
+                caps[3].replace('\\', "/")
+            )
+        })
+        .into_owned()
+    }
+
+    /// Copying to the Linux clipboard tools goes nowhere visible; `clip.exe` reaches Windows
+    fn clipboard(command: &str) -> String {
+        let copy = Regex::new(r"\|\s*(?:pbcopy|wl-copy|xclip|xsel)\b[^|;&]*").expect("valid regex");
+        copy.replace_all(command, |caps: &regex::Captures| {
+            let segment = &caps[0];
+            // Reading the clipboard back isn't something clip.exe does
+            let reads = segment
+                .split_whitespace()
+                .any(|word| matches!(word, "-o" | "-out" | "--output"));
+            match reads {
+                true => segment.to_string(),
+                false => "| clip.exe".to_string(),
+            }
+        })
+        .into_owned()
+    }
+}
+
+impl Transform for WslInterop {
+    fn name(&self) -> &str {
+        "wsl"
+    }
+
+    fn apply(&self, command: &str) -> String {
+        let Some(opener) = self.opener else {
+            return command.to_string();
+        };
+        let command = Self::clipboard(&Self::windows_paths(command));
+        let open = Regex::new(r"(^|[|;&]\s*)(?:xdg-open|open)(\s)").expect("valid regex");
+        open.replace_all(&command, format!("${{1}}{opener}${{2}}"))
+            .into_owned()
+    }
+}
+
+/// User-defined regex replacement from config
+pub struct RegexReplace {
+    name: String,
+    pattern: Regex,
+    replacement: String,
+}
+
+impl RegexReplace {
+    pub fn new(rule: &ReplaceRule) -> Result<Self, regex::Error> {
+        Ok(Self {
+            name: format!("replace '{}'", rule.pattern),
+            pattern: Regex::new(&rule.pattern)?,
+            replacement: rule.replacement.clone(),
+        })
+    }
+}
+
+impl Transform for RegexReplace {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn apply(&self, command: &str) -> String {
+        self.pattern
+            .replace_all(command, self.replacement.as_str())
+            .to_string()
+    }
+}
+
+/// Prefixes commands with environment variables when run inside a project
+pub struct EnvPrefix {
+    prefix: String,
+    commands: Vec<String>,
+}
+
+impl EnvPrefix {
+    /// Returns the transform if the current directory is inside the rule's path
+    pub fn for_current_dir(rule: &EnvPrefixRule) -> Option<Self> {
+        let cwd = std::env::current_dir().ok()?;
+        let path = match rule.path.strip_prefix("~/") {
+            Some(rest) => dirs::home_dir()?.join(rest),
+            None => std::path::PathBuf::from(&rule.path),
+        };
+
+        cwd.starts_with(&path).then(|| Self {
+            prefix: rule.prefix.trim().to_string(),
+            commands: rule.commands.clone(),
+        })
+    }
+}
+
+impl Transform for EnvPrefix {
+    fn name(&self) -> &str {
+        "env_prefix"
+    }
+
+    fn apply(&self, command: &str) -> String {
+        if command.starts_with(&self.prefix) {
+            return command.to_string();
+        }
+
+        let executable = command.split_whitespace().next().unwrap_or("");
+        if self.commands.is_empty() || self.commands.iter().any(|c| c == executable) {
+            format!("{} {command}", self.prefix)
+        } else {
+            command.to_string()
+        }
+    }
+}
+
+/// Points pip at the active or project virtualenv, and `sudo` at the node a
+/// version manager selected
+///
+/// `sudo` resets PATH, so `sudo pip` or `sudo npm` reach the system's
+/// interpreter instead of the environment the user is working in.
+pub struct ActiveRuntimes {
+    python: Option<PythonEnvironment>,
+    /// Only node from a version manager, which `sudo` can't find
+    node: Option<NodeEnvironment>,
+}
+
+impl ActiveRuntimes {
+    pub fn detect() -> Self {
+        let runtimes = Runtimes::detect();
+        Self {
+            python: runtimes.python,
+            node: runtimes.node.filter(|node| node.manager.is_some()),
+        }
+    }
+
+    fn python(python: &PythonEnvironment, command: &str) -> String {
+        let pip =
+            Regex::new(r"(^|[|;&(]\s*)(sudo\s+(?:-\S+\s+)*)?(?:pip3?|python3?\s+-m\s+pip)(\s|$)")
+                .expect("valid regex");
+        let command = pip.replace_all(command, |caps: &regex::Captures| {
+            // The user's own environment needs no root; sudo would pick the system python
+            let interpreter = match (caps.get(2), python.user_owned) {
+                (Some(sudo), false) => format!(
+                    "{}{}",
+                    sudo.as_str(),
+                    QuotingCheck::quote_if_needed(
+                        &python
+                            .prefix
+                            .join("bin")
+                            .join("python")
+                            .display()
+                            .to_string()
+                    )
+                ),
+                _ => python.python.clone(),
+            };
+            format!("{}{interpreter} -m pip{}", &caps[1], &caps[3])
+        });
+        // pip refuses --user for a virtualenv's interpreter
+        let user = Regex::new(r"(-m pip install\b[^|;&]*?)\s+--user\b").expect("valid regex");
+        user.replace_all(&command, "$1").into_owned()
+    }
+
+    fn node(node: &NodeEnvironment, command: &str) -> String {
+        // Global installs go to the manager's directory, which the user owns
+        let global = Regex::new(
+            r"(^|[|;&(]\s*)sudo\s+(npm\s+(?:install|i|uninstall|update|link)\s+(?:-g|--global)\b)",
+        )
+        .expect("valid regex");
+        let command = global.replace_all(command, "$1$2");
+        let tools = Regex::new(r"(^|[|;&(]\s*)sudo\s+(npm|npx|yarn|pnpm|corepack)(\s|$)")
+            .expect("valid regex");
+        let command = tools.replace_all(&command, r#"${1}sudo env "PATH=$$PATH" $2$3"#);
+        let binary = QuotingCheck::quote_if_needed(&node.binary.display().to_string());
+        let node_run = Regex::new(r"(^|[|;&(]\s*)sudo\s+node(\s|$)").expect("valid regex");
+        node_run
+            .replace_all(&command, |caps: &regex::Captures| {
+                format!("{}sudo {binary}{}", &caps[1], &caps[2])
+            })
+            .into_owned()
+    }
+}
+
+impl Transform for ActiveRuntimes {
+    fn name(&self) -> &str {
+        "runtimes"
+    }
+
+    fn apply(&self, command: &str) -> String {
+        let mut command = command.to_string();
+        if let Some(python) = &self.python {
+            command = Self::python(python, &command);
+        }
+        if let Some(node) = &self.node {
+            command = Self::node(node, &command);
+        }
+        command
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn whitespace_outside_quotes_is_collapsed() {
+        let t = NormalizeWhitespace;
+        assert_eq!(t.apply("  ls \t -la   /tmp "), "ls -la /tmp");
+        assert_eq!(
+            t.apply(r#"echo "a   b"  'c   d'"#),
+            r#"echo "a   b" 'c   d'"#
+        );
+        assert_eq!(t.apply(r"touch a\  b"), r"touch a\  b");
+    }
+
+    #[test]
+    fn tilde_is_expanded_outside_quotes_only() {
+        let t = ExpandTilde {
+            home: "/home/me".to_string(),
+        };
+        assert_eq!(t.apply("ls ~/src"), "ls /home/me/src");
+        assert_eq!(t.apply("make --prefix=~"), "make --prefix=/home/me");
+        assert_eq!(
+            t.apply("PATH=~/bin:~/.local/bin"),
+            "PATH=/home/me/bin:/home/me/.local/bin"
+        );
+        assert_eq!(t.apply("echo '~/src'"), "echo '~/src'");
+        assert_eq!(t.apply(r#"echo "~" ~"#), r#"echo "~" /home/me"#);
+        assert_eq!(t.apply(r"echo \~"), r"echo \~");
+        assert_eq!(t.apply("cd ~user"), "cd ~user");
+        assert_eq!(t.apply("git log HEAD~2"), "git log HEAD~2");
+    }
+}
@@ -0,0 +1,309 @@
+use regex::Regex;
+use std::ops::Range;
+use std::path::PathBuf;
+
+/// Programs a preview may really run: they only print, or only write the
+/// files that are swapped for copies
+const PREVIEW_SAFE: [&str; 5] = ["sed", "echo", "printf", "cat", "tee"];
+
+/// How a command writes to a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EditKind {
+    /// `sed -i`
+    SedInPlace,
+    /// `> file`
+    Overwrite,
+    /// `>> file`
+    Append,
+    /// `| tee file`
+    Tee,
+    /// `| tee -a file`
+    TeeAppend,
+}
+
+/// A file a command would modify, and where its name appears in the command
+#[derive(Debug, Clone)]
+pub struct FileEdit {
+    pub kind: EditKind,
+    pub path: PathBuf,
+    /// Byte range of the file argument, so it can be swapped for a temp copy
+    pub span: Range<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum TokenKind {
+    Word,
+    Operator,
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    kind: TokenKind,
+    /// Unquoted text
+    text: String,
+    span: Range<usize>,
+}
+
+/// Finds the files a command edits in place, or `None` if it edits none or is too complex
+/// to rewrite safely (command substitution, chained commands, unterminated quotes)
+pub fn detect_edits(command: &str) -> Option<Vec<FileEdit>> {
+    if command.contains("$(") || command.contains('`') {
+        return None;
+    }
+    let tokens = tokenize(command)?;
+    if tokens.iter().any(|t| {
+        t.kind == TokenKind::Operator && matches!(t.text.as_str(), ";" | "&&" | "||" | "&")
+    }) {
+        return None;
+    }
+
+    let mut edits = Vec::new();
+    let segments = tokens.split(|t| t.kind == TokenKind::Operator && t.text == "|");
+    for (index, segment) in segments.enumerate() {
+        let mut words: Vec<&Token> = Vec::new();
+        let mut iter = segment.iter().peekable();
+
+        while let Some(token) = iter.next() {
+            if token.kind == TokenKind::Word {
+                words.push(token);
+                continue;
+            }
+            // Every redirection operator takes the next word as its target
+            let target = iter.next().filter(|t| t.kind == TokenKind::Word)?;
+            let kind = match token.text.as_str() {
+                ">" | "1>" | "&>" | ">|" => EditKind::Overwrite,
+                ">>" | "1>>" | "&>>" => EditKind::Append,
+                _ => continue,
+            };
+            if !target.text.starts_with("/dev/") {
+                edits.push(file_edit(kind, target));
+            }
+        }
+
+        match words.first().map(|w| w.text.as_str()) {
+            Some("sed") => edits.extend(sed_edits(&words[1..])),
+            Some("tee") if index > 0 => edits.extend(tee_edits(&words[1..])),
+            _ => {}
+        }
+    }
+
+    if edits.is_empty() {
+        None
+    } else {
+        Some(edits)
+    }
+}
+
+/// The program that stops `command` from being previewed, because running it
+/// would do more than write the files a preview swaps for copies
+///
+/// Only pipelines of `sed`, `echo`, `printf`, `cat`, and `tee` can be previewed,
+/// and only when sed's script neither writes other files nor runs commands.
+pub fn preview_blocker(command: &str) -> Option<String> {
+    let Some(tokens) = tokenize(command) else {
+        return Some(command.to_string());
+    };
+    // GNU sed's `w` and `W` write to files and `e` runs a command
+    let sed_effects =
+        Regex::new(r"(^|[;{}\n0-9$]|/[gpIiMm0-9]*)\s*[wWe](\s|$)").expect("valid regex");
+
+    let segments = tokens.split(|t| t.kind == TokenKind::Operator && t.text == "|");
+    for (index, segment) in segments.enumerate() {
+        let mut words = Vec::new();
+        let mut iter = segment.iter();
+        while let Some(token) = iter.next() {
+            if token.kind == TokenKind::Word {
+                words.push(token.text.as_str());
+                continue;
+            }
+            let target = iter.next().map(|t| t.text.as_str()).unwrap_or_default();
+            // `>& file` writes a file that isn't swapped for a copy
+            if token.text.ends_with(">&") && target.parse::<u32>().is_err() && target != "-" {
+                return Some(format!("{}{target}", token.text));
+            }
+        }
+
+        let program = words.first().copied().unwrap_or_default();
+        let safe = match program {
+            "tee" => index > 0,
+            "sed" => !words[1..]
+                .iter()
+                .filter(|w| !w.starts_with('-'))
+                .any(|w| sed_effects.is_match(w)),
+            _ => PREVIEW_SAFE.contains(&program),
+        };
+        if !safe {
+            return Some(program.to_string());
+        }
+    }
+    None
+}
+
+fn file_edit(kind: EditKind, token: &Token) -> FileEdit {
+    FileEdit {
+        kind,
+        path: PathBuf::from(&token.text),
+        span: token.span.clone(),
+    }
+}
+
+/// Files named by `sed -i`; without `-i` sed only prints
+fn sed_edits(args: &[&Token]) -> Vec<FileEdit> {
+    let mut in_place = false;
+    let mut has_script = false;
+    let mut files = Vec::new();
+    let mut i = 0;
+
+    while i < args.len() {
+        let arg = args[i].text.as_str();
+        if arg == "-i" || arg == "--in-place" {
+            in_place = true;
+            // BSD sed takes the backup suffix as a separate, often empty, argument
+            if args.get(i + 1).is_some_and(|next| next.text.is_empty()) {
+                i += 1;
+            }
+        } else if arg.starts_with("-i") || arg.starts_with("--in-place=") {
+            in_place = true;
+        } else if arg == "-e" || arg == "-f" || arg == "--expression" || arg == "--file" {
+            has_script = true;
+            i += 1;
+        } else if arg.starts_with('-') && arg.len() > 1 {
+            // Combined short flags such as -Ei
+            if !arg.starts_with("--") && arg[1..].contains('i') {
+                in_place = true;
+            }
+        } else if !has_script {
+            has_script = true;
+        } else {
+            files.push(args[i]);
+        }
+        i += 1;
+    }
+
+    if !in_place {
+        return Vec::new();
+    }
+    files
+        .into_iter()
+        .map(|token| file_edit(EditKind::SedInPlace, token))
+        .collect()
+}
+
+fn tee_edits(args: &[&Token]) -> Vec<FileEdit> {
+    let append = args.iter().any(|a| a.text == "-a" || a.text == "--append");
+    let kind = if append {
+        EditKind::TeeAppend
+    } else {
+        EditKind::Tee
+    };
+    args.iter()
+        .filter(|a| !a.text.starts_with('-') && !a.text.starts_with("/dev/"))
+        .map(|token| file_edit(kind, token))
+        .collect()
+}
+
+/// Splits a command into words and operators, tracking where each came from
+fn tokenize(command: &str) -> Option<Vec<Token>> {
+    let chars: Vec<(usize, char)> = command.char_indices().collect();
+    let end_of = |i: usize| chars.get(i).map_or(command.len(), |(pos, _)| *pos);
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let (start, c) = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+
+        if let Some(op) = operator_at(&chars, i) {
+            i += op.chars().count();
+            tokens.push(Token {
+                kind: TokenKind::Operator,
+                text: op,
+                span: start..end_of(i),
+            });
+            continue;
+        }
+
+        let mut text = String::new();
+        while i < chars.len() {
+            let c = chars[i].1;
+            if c.is_whitespace() || operator_at(&chars, i).is_some() {
+                break;
+            }
+            match c {
+                '\'' => {
+                    i += 1;
+                    loop {
+                        let (_, c) = *chars.get(i)?;
+                        i += 1;
+                        if c == '\'' {
+                            break;
+                        }
+                        text.push(c);
+                    }
+                }
+                '"' => {
+                    i += 1;
+                    loop {
+                        let (_, c) = *chars.get(i)?;
+                        i += 1;
+                        match c {
+                            '"' => break,
+                            '\\' => {
+                                let (_, escaped) = *chars.get(i)?;
+                                i += 1;
+                                text.push(escaped);
+                            }
+                            _ => text.push(c),
+                        }
+                    }
+                }
+                '\\' => {
+                    let (_, escaped) = *chars.get(i + 1)?;
+                    text.push(escaped);
+                    i += 2;
+                }
+                _ => {
+                    text.push(c);
+                    i += 1;
+                }
+            }
+        }
+
+        // A file descriptor number directly before a redirection belongs to the operator
+        if let Some(op) = operator_at(&chars, i).filter(|op| op.starts_with(['>', '<'])) {
+            if !text.is_empty() && text.chars().all(|c| c.is_ascii_digit()) {
+                i += op.chars().count();
+                tokens.push(Token {
+                    kind: TokenKind::Operator,
+                    text: format!("{text}{op}"),
+                    span: start..end_of(i),
+                });
+                continue;
+            }
+        }
+
+        tokens.push(Token {
+            kind: TokenKind::Word,
+            text,
+            span: start..end_of(i),
+        });
+    }
+
+    Some(tokens)
+}
+
+fn operator_at(chars: &[(usize, char)], i: usize) -> Option<String> {
+    const OPERATORS: [&str; 11] = [
+        "&>>", ">>", "&>", ">&", ">|", "&&", "||", ">", "<", "|", ";",
+    ];
+    let &(_, first) = chars.get(i)?;
+    let rest: String = chars[i..].iter().take(3).map(|(_, c)| c).collect();
+    OPERATORS
+        .iter()
+        .find(|op| rest.starts_with(*op))
+        .map(|op| op.to_string())
+        .or_else(|| (first == '&').then(|| "&".to_string()))
+}
@@ -0,0 +1,135 @@
+/// Lines of unchanged context shown around each change
+const CONTEXT_LINES: usize = 3;
+
+/// Size limit (changed old lines × changed new lines) for a line-by-line diff
+const MAX_DIFF_CELLS: usize = 4_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Equal,
+    Delete,
+    Insert,
+}
+
+/// Renders a unified diff between two versions of a file; empty when they are equal
+pub fn unified_diff(old: &str, new: &str, path: &str) -> String {
+    if old == new {
+        return String::new();
+    }
+
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+    let mut output = format!("--- {path}\n+++ {path} (after)\n");
+
+    let Some(ops) = edit_script(&old_lines, &new_lines) else {
+        output.push_str(&format!(
+            "@@ too many changes to diff: {} lines -> {} lines @@\n",
+            old_lines.len(),
+            new_lines.len()
+        ));
+        return output;
+    };
+    for hunk in hunks(&ops) {
+        let (mut old_line, mut new_line) = line_numbers(&ops[..hunk.start]);
+        let (old_count, new_count) = line_numbers(&ops[hunk.clone()]);
+        output.push_str(&format!(
+            "@@ -{},{old_count} +{},{new_count} @@\n",
+            old_line + usize::from(old_count > 0),
+            new_line + usize::from(new_count > 0)
+        ));
+
+        for op in &ops[hunk] {
+            match op {
+                Op::Equal => {
+                    output.push_str(&format!(" {}\n", old_lines[old_line]));
+                    old_line += 1;
+                    new_line += 1;
+                }
+                Op::Delete => {
+                    output.push_str(&format!("-{}\n", old_lines[old_line]));
+                    old_line += 1;
+                }
+                Op::Insert => {
+                    output.push_str(&format!("+{}\n", new_lines[new_line]));
+                    new_line += 1;
+                }
+            }
+        }
+    }
+
+    output
+}
+
+/// Shortest edit script via the longest common subsequence of lines
+fn edit_script(old: &[&str], new: &[&str]) -> Option<Vec<Op>> {
+    // Trim the common prefix and suffix so the table only covers the changed middle
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old_mid = &old[prefix..old.len() - suffix];
+    let new_mid = &new[prefix..new.len() - suffix];
+
+    let (n, m) = (old_mid.len(), new_mid.len());
+    if n * m > MAX_DIFF_CELLS {
+        return None;
+    }
+    let mut lcs = vec![vec![0u32; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if old_mid[i] == new_mid[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut ops = vec![Op::Equal; prefix];
+    let (mut i, mut j) = (0, 0);
+    while i < n || j < m {
+        if i < n && j < m && old_mid[i] == new_mid[j] {
+            ops.push(Op::Equal);
+            i += 1;
+            j += 1;
+        } else if i < n && (j == m || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            // Deletions first, as diff(1) prints them
+            ops.push(Op::Delete);
+            i += 1;
+        } else {
+            ops.push(Op::Insert);
+            j += 1;
+        }
+    }
+    ops.extend(std::iter::repeat_n(Op::Equal, suffix));
+    Some(ops)
+}
+
+/// Ranges of the edit script to print, each change padded with context
+fn hunks(ops: &[Op]) -> Vec<std::ops::Range<usize>> {
+    let mut hunks: Vec<std::ops::Range<usize>> = Vec::new();
+    for (index, op) in ops.iter().enumerate() {
+        if *op == Op::Equal {
+            continue;
+        }
+        let start = index.saturating_sub(CONTEXT_LINES);
+        let end = (index + 1 + CONTEXT_LINES).min(ops.len());
+        match hunks.last_mut() {
+            Some(last) if start <= last.end => last.end = last.end.max(end),
+            _ => hunks.push(start..end),
+        }
+    }
+    hunks
+}
+
+/// Old and new line counts covered by part of an edit script
+fn line_numbers(ops: &[Op]) -> (usize, usize) {
+    ops.iter().fold((0, 0), |(old, new), op| match op {
+        Op::Equal => (old + 1, new + 1),
+        Op::Delete => (old + 1, new),
+        Op::Insert => (old, new + 1),
+    })
+}
@@ -0,0 +1,116 @@
+pub mod detect;
+pub mod diff;
+pub mod sandbox;
+
+pub use detect::{detect_edits, preview_blocker, EditKind, FileEdit};
+pub use diff::unified_diff;
+pub use sandbox::{Sandbox, SandboxOutcome};
+
+use anyhow::{bail, Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// Result of dry-running a file-editing command against temporary copies
+pub struct EditPreview {
+    /// Unified diff per edited file
+    pub diffs: Vec<(PathBuf, String)>,
+}
+
+impl EditPreview {
+    /// Runs `command` with each edited file swapped for a temp copy and diffs the results
+    ///
+    /// Returns `None` when the command doesn't edit files in place. Only the files are
+    /// redirected, so commands that do anything else (e.g. delete or fetch) are refused
+    /// rather than run twice.
+    pub fn run(command: &str) -> Result<Option<Self>> {
+        let Some(edits) = detect_edits(command) else {
+            return Ok(None);
+        };
+        if let Some(program) = preview_blocker(command) {
+            bail!(
+                "'{program}' would really run, so the edits can't be shown without applying them"
+            );
+        }
+
+        let temp_dir =
+            std::env::temp_dir().join(format!("commandy-preview-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&temp_dir)?;
+        let result = Self::run_in(command, &edits, &temp_dir);
+        let _ = fs::remove_dir_all(&temp_dir);
+        result.map(Some)
+    }
+
+    fn run_in(command: &str, edits: &[FileEdit], temp_dir: &Path) -> Result<Self> {
+        let mut copies = Vec::with_capacity(edits.len());
+        for (index, edit) in edits.iter().enumerate() {
+            let name = edit
+                .path
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let copy = temp_dir.join(format!("{index}-{name}"));
+            // Overwrites start from nothing; everything else works on the current content
+            if edit.kind != EditKind::Overwrite && edit.kind != EditKind::Tee && edit.path.exists()
+            {
+                fs::copy(&edit.path, &copy)
+                    .with_context(|| format!("Failed to copy {}", edit.path.display()))?;
+            }
+            copies.push(copy);
+        }
+
+        let mut rewritten = command.to_string();
+        let mut order: Vec<usize> = (0..edits.len()).collect();
+        // Replace from the end so earlier spans stay valid
+        order.sort_by_key(|&i| std::cmp::Reverse(edits[i].span.start));
+        for i in order {
+            rewritten.replace_range(edits[i].span.clone(), &shell_quote(&copies[i]));
+        }
+        if edits
+            .iter()
+            .any(|e| matches!(e.kind, EditKind::Tee | EditKind::TeeAppend))
+        {
+            // tee also echoes to stdout, which would spill into the preview
+            rewritten.push_str(" >/dev/null");
+        }
+
+        let output = Command::new("sh")
+            .args(["-c", &rewritten])
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .output()
+            .context("Failed to run preview")?;
+        if !output.status.success() {
+            bail!(
+                "Preview run failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+
+        let mut diffs = Vec::new();
+        for (edit, copy) in edits.iter().zip(&copies) {
+            let before = fs::read(&edit.path).unwrap_or_default();
+            let after = fs::read(copy).unwrap_or_default();
+            let display = edit.path.display().to_string();
+            let diff = match (String::from_utf8(before), String::from_utf8(after)) {
+                (Ok(before), Ok(after)) => unified_diff(&before, &after, &display),
+                (before, after) if before != after => {
+                    format!("Binary file {display} would change\n")
+                }
+                _ => String::new(),
+            };
+            diffs.push((edit.path.clone(), diff));
+        }
+
+        Ok(Self { diffs })
+    }
+
+    pub fn has_changes(&self) -> bool {
+        self.diffs.iter().any(|(_, diff)| !diff.is_empty())
+    }
+}
+
+fn shell_quote(path: &Path) -> String {
+    format!("'{}'", path.display().to_string().replace('\'', r"'\''"))
+}
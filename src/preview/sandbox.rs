@@ -0,0 +1,150 @@
+use anyhow::{bail, Context, Result};
+use std::path::Path;
+use std::process::{Command, ExitStatus, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::{debug, warn};
+
+use crate::config::{SandboxConfig, SandboxMount};
+
+/// Where a sandboxed run ended up
+pub enum SandboxOutcome {
+    Exited(ExitStatus),
+    TimedOut,
+}
+
+/// Runs commands in a disposable container, with the current directory mounted at /work
+pub struct Sandbox<'a> {
+    config: &'a SandboxConfig,
+}
+
+impl<'a> Sandbox<'a> {
+    pub fn new(config: &'a SandboxConfig) -> Self {
+        Self { config }
+    }
+
+    /// Runs `command` in a fresh container, streaming its output to the terminal
+    pub fn run(&self, command: &str, cwd: &Path) -> Result<SandboxOutcome> {
+        if which::which(&self.config.runtime).is_err() {
+            bail!(
+                "{} is not installed; set [sandbox] runtime to docker or podman",
+                self.config.runtime
+            );
+        }
+
+        // A first pull can take longer than the whole run is allowed
+        self.pull_image()?;
+
+        let name = format!("commandy-sandbox-{}", uuid::Uuid::new_v4().simple());
+        let mut child = Command::new(&self.config.runtime)
+            .args(self.run_args(&name, command, cwd))
+            .stdin(Stdio::null())
+            .spawn()
+            .with_context(|| format!("Failed to start {}", self.config.runtime))?;
+
+        let timeout = Duration::from_secs(self.config.timeout_secs);
+        let started = Instant::now();
+        loop {
+            if let Some(status) = child.try_wait()? {
+                return Ok(SandboxOutcome::Exited(status));
+            }
+            if started.elapsed() > timeout {
+                debug!(container = name, "Sandbox run timed out");
+                // Killing the client alone would leave the container running
+                if let Err(e) = Command::new(&self.config.runtime)
+                    .args(["rm", "-f", &name])
+                    .stdout(Stdio::null())
+                    .stderr(Stdio::null())
+                    .status()
+                {
+                    warn!(container = name, "Failed to remove sandbox container: {e}");
+                }
+                let _ = child.kill();
+                let _ = child.wait();
+                return Ok(SandboxOutcome::TimedOut);
+            }
+            thread::sleep(Duration::from_millis(50));
+        }
+    }
+
+    /// Pulls the image unless it is already present, showing the runtime's progress
+    fn pull_image(&self) -> Result<()> {
+        let present = Command::new(&self.config.runtime)
+            .args(["image", "inspect", &self.config.image])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .is_ok_and(|status| status.success());
+        if present {
+            return Ok(());
+        }
+
+        debug!(image = self.config.image, "Pulling sandbox image");
+        let status = Command::new(&self.config.runtime)
+            .args(["pull", &self.config.image])
+            .stdin(Stdio::null())
+            .status()
+            .with_context(|| format!("Failed to start {}", self.config.runtime))?;
+        if !status.success() {
+            bail!("Failed to pull the sandbox image {}", self.config.image);
+        }
+        Ok(())
+    }
+
+    fn run_args(&self, name: &str, command: &str, cwd: &Path) -> Vec<String> {
+        let mut args = vec![
+            "run".to_string(),
+            "--rm".to_string(),
+            "--name".to_string(),
+            name.to_string(),
+            "--workdir".to_string(),
+            "/work".to_string(),
+        ];
+        // --mount fails on a missing source and takes any path, where --volume
+        // would create the directory and split the path on `:`
+        let source = Self::mount_field(&format!("src={}", cwd.display()));
+        let mount = match self.config.mount {
+            SandboxMount::ReadOnly => Some(format!("type=bind,{source},dst=/work,readonly")),
+            SandboxMount::ReadWrite => Some(format!("type=bind,{source},dst=/work")),
+            SandboxMount::None => None,
+        };
+        if let Some(mount) = mount {
+            args.extend(["--mount".to_string(), mount]);
+        }
+        if !self.config.network {
+            args.extend(["--network".to_string(), "none".to_string()]);
+        }
+        args.extend([
+            self.config.image.clone(),
+            "sh".to_string(),
+            "-c".to_string(),
+            command.to_string(),
+        ]);
+        args
+    }
+
+    /// `--mount` is a CSV line, so a field with a comma or quote is quoted
+    fn mount_field(field: &str) -> String {
+        if field.contains([',', '"']) {
+            format!("\"{}\"", field.replace('"', "\"\""))
+        } else {
+            field.to_string()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mounts_any_directory_read_only() {
+        let config = SandboxConfig::default();
+        let args = Sandbox::new(&config).run_args("c", "ls", Path::new("/tmp/a:b,c"));
+        let mount = args.iter().position(|a| a == "--mount").unwrap();
+        assert_eq!(
+            args[mount + 1],
+            "type=bind,\"src=/tmp/a:b,c\",dst=/work,readonly"
+        );
+    }
+}
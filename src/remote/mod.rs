@@ -0,0 +1,3 @@
+pub mod ssh;
+
+pub use ssh::{RemoteRun, RemoteTarget};
@@ -0,0 +1,232 @@
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Command, ExitStatus, Stdio};
+use std::thread;
+
+/// Executables the probe looks for on the remote host
+const REMOTE_TOOLS: &[&str] = &[
+    "apt",
+    "apt-get",
+    "dnf",
+    "yum",
+    "apk",
+    "pacman",
+    "zypper",
+    "brew",
+    "systemctl",
+    "journalctl",
+    "rc-service",
+    "service",
+    "docker",
+    "podman",
+    "kubectl",
+    "git",
+    "python3",
+    "curl",
+    "wget",
+    "rsync",
+    "tar",
+    "jq",
+    "ip",
+    "ss",
+    "netstat",
+    "ufw",
+    "firewall-cmd",
+    "iptables",
+    "nginx",
+    "lsof",
+    "htop",
+    "free",
+    "du",
+    "df",
+    "ps",
+    "awk",
+    "sed",
+    "grep",
+    "find",
+    "xargs",
+    "sudo",
+];
+
+/// Programs that need a terminal, so they run with `ssh -t` and nothing is captured
+const NEEDS_TERMINAL: &[&str] = &[
+    "sudo", "su", "top", "htop", "vim", "vi", "nano", "less", "more", "man", "watch", "tmux",
+    "screen", "passwd",
+];
+
+/// Seconds ssh waits for the host to answer
+const CONNECT_TIMEOUT_SECS: u32 = 10;
+
+/// How a command run over SSH went
+pub struct RemoteRun {
+    pub status: ExitStatus,
+    /// stdout and stderr as they were printed; empty when the command had the terminal
+    pub output: String,
+}
+
+/// A host reached with the system `ssh`, using the user's own config and keys
+#[derive(Debug, Clone)]
+pub struct RemoteTarget {
+    destination: String,
+}
+
+impl RemoteTarget {
+    /// A target such as `admin@web-1` or a `Host` alias from `~/.ssh/config`
+    pub fn new(destination: &str) -> Result<Self> {
+        let destination = destination.trim();
+        if destination.is_empty()
+            || destination.starts_with('-')
+            || destination.contains(char::is_whitespace)
+        {
+            anyhow::bail!("Invalid SSH target: {destination:?} (expected user@host)");
+        }
+        which::which("ssh").context("--target needs the ssh client installed")?;
+        Ok(Self {
+            destination: destination.to_string(),
+        })
+    }
+
+    pub fn destination(&self) -> &str {
+        &self.destination
+    }
+
+    /// Learns the host's OS, shell, and tools with a single ssh call
+    ///
+    /// Returns environment keys in the same shape as the local detector's.
+    pub fn probe(&self) -> Result<HashMap<String, String>> {
+        let tools = REMOTE_TOOLS.join(" ");
+        let script = format!(
+            r#"echo "kernel=$(uname -s)"
+echo "arch=$(uname -m)"
+echo "shell=${{SHELL:-sh}}"
+if [ -r /etc/os-release ]; then (. /etc/os-release; echo "distro=$NAME"; echo "os_version=$VERSION_ID"); fi
+if command -v sw_vers >/dev/null 2>&1; then echo "distro=macOS"; echo "os_version=$(sw_vers -productVersion)"; fi
+if [ -d /run/systemd/system ]; then echo "init_system=systemd"; fi
+for tool in {tools}; do command -v "$tool" >/dev/null 2>&1 && echo "tool=$tool"; done
+exit 0
+"#
+        );
+
+        let mut child = self
+            .ssh(&[])
+            .args(["sh", "-s"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .context("Failed to start ssh")?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(script.as_bytes())?;
+        }
+        let output = child.wait_with_output()?;
+        if !output.status.success() {
+            anyhow::bail!(
+                "Couldn't reach {} over ssh (exit code {:?})",
+                self.destination,
+                output.status.code()
+            );
+        }
+
+        let mut fields = HashMap::new();
+        let mut tools = Vec::new();
+        for line in String::from_utf8_lossy(&output.stdout).lines() {
+            match line.split_once('=') {
+                Some(("tool", tool)) => tools.push(tool.to_string()),
+                Some((key, value)) if !value.trim().is_empty() => {
+                    fields.insert(key.to_string(), value.trim().to_string());
+                }
+                _ => {}
+            }
+        }
+
+        let kernel = fields
+            .remove("kernel")
+            .unwrap_or_else(|| "unknown".to_string());
+        let arch = fields.get("arch").cloned().unwrap_or_default();
+        let mut details = vec![format!("{} {arch}", kernel.to_lowercase())];
+        if let Some(init) = fields.get("init_system") {
+            details.push(init.clone());
+        }
+        let name = match (fields.get("distro"), fields.get("os_version")) {
+            (Some(distro), Some(version)) => format!("{distro} {version} "),
+            (Some(distro), None) => format!("{distro} "),
+            (None, _) => String::new(),
+        };
+        fields.insert(
+            "os".to_string(),
+            format!(
+                "{name}({}), remote host {}",
+                details.join(", "),
+                self.destination
+            ),
+        );
+        fields.insert("available_tools".to_string(), tools.join(","));
+        fields.insert("remote".to_string(), self.destination.clone());
+        Ok(fields)
+    }
+
+    /// Runs a command on the host, echoing and keeping its output
+    ///
+    /// Commands that need a terminal (sudo, editors, pagers) get one with
+    /// `ssh -t` instead, and their output isn't captured.
+    pub fn run(&self, command: &str) -> Result<RemoteRun> {
+        let program = command.split_whitespace().next().unwrap_or_default();
+        if NEEDS_TERMINAL.contains(&program) {
+            let status = self
+                .ssh(&["-t"])
+                .arg(command)
+                .status()
+                .context("Failed to start ssh")?;
+            return Ok(RemoteRun {
+                status,
+                output: String::new(),
+            });
+        }
+
+        let mut child = self
+            .ssh(&[])
+            .arg(command)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .context("Failed to start ssh")?;
+
+        let stderr = child.stderr.take().map(|stderr| {
+            thread::spawn(move || {
+                let mut captured = String::new();
+                for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+                    eprintln!("{line}");
+                    captured.push_str(&line);
+                    captured.push('\n');
+                }
+                captured
+            })
+        });
+        let mut output = String::new();
+        if let Some(stdout) = child.stdout.take() {
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                println!("{line}");
+                output.push_str(&line);
+                output.push('\n');
+            }
+        }
+        if let Some(captured) = stderr.and_then(|handle| handle.join().ok()) {
+            output.push_str(&captured);
+        }
+
+        Ok(RemoteRun {
+            status: child.wait()?,
+            output,
+        })
+    }
+
+    /// `ssh` with `options` placed before the destination, ready for a remote command
+    fn ssh(&self, options: &[&str]) -> Command {
+        let mut ssh = Command::new("ssh");
+        ssh.args(["-o", &format!("ConnectTimeout={CONNECT_TIMEOUT_SECS}")])
+            .args(options)
+            .args(["--", &self.destination]);
+        ssh
+    }
+}
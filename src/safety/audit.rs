@@ -0,0 +1,259 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::{self, OpenOptions};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+/// `prev` of the first record
+const GENESIS: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// One executed command, chained to the record before it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditRecord {
+    pub seq: u64,
+    pub timestamp: DateTime<Utc>,
+    pub user: String,
+    pub cwd: String,
+    pub command: String,
+    pub exit_code: Option<i32>,
+    /// SSH destination when the command ran on another host
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+    /// Hash of the previous record
+    pub prev: String,
+    /// SHA-256 over `prev` and every field above
+    pub hash: String,
+}
+
+/// Result of checking the chain
+pub enum AuditVerification {
+    Intact {
+        records: usize,
+        last_hash: String,
+    },
+    /// `line` is 1-based; records before it are intact
+    Broken {
+        line: usize,
+        reason: String,
+    },
+}
+
+/// Append-only JSON-lines log of commands commandy executed
+///
+/// Each record carries the hash of the one before it, so editing, removing,
+/// or reordering a line breaks the chain from that point on. Appends never
+/// write blank lines, so a blank line is treated like any other bad record.
+pub struct AuditLog {
+    path: PathBuf,
+}
+
+impl AuditLog {
+    pub fn new(commandy_dir: &Path) -> Self {
+        Self {
+            path: commandy_dir.join("audit.log"),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Adds a record for a command that ran, by the current user in the current directory
+    ///
+    /// The log is locked from reading the last record until the new one is
+    /// written, so concurrent runs can't chain two records to the same one.
+    pub fn append(
+        &self,
+        command: &str,
+        exit_code: Option<i32>,
+        target: Option<&str>,
+    ) -> Result<AuditRecord> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut options = OpenOptions::new();
+        options.create(true).read(true).append(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let mut file = options
+            .open(&self.path)
+            .with_context(|| format!("Failed to open {}", self.path.display()))?;
+        file.lock()
+            .with_context(|| format!("Failed to lock {}", self.path.display()))?;
+
+        let mut content = String::new();
+        file.read_to_string(&mut content)?;
+        let (seq, prev) = match self.last(&content)? {
+            Some(last) => (last.seq + 1, last.hash),
+            None => (1, GENESIS.to_string()),
+        };
+        let mut record = AuditRecord {
+            seq,
+            timestamp: Utc::now(),
+            user: Self::current_user(),
+            cwd: std::env::current_dir()
+                .map(|dir| dir.display().to_string())
+                .unwrap_or_default(),
+            command: command.to_string(),
+            exit_code,
+            target: target.map(str::to_string),
+            prev,
+            hash: String::new(),
+        };
+        record.hash = Self::hash(&record)?;
+
+        writeln!(file, "{}", serde_json::to_string(&record)?)?;
+        file.unlock()?;
+        Ok(record)
+    }
+
+    /// Every record, oldest first; an unreadable line is an error
+    pub fn records(&self) -> Result<Vec<AuditRecord>> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+        fs::read_to_string(&self.path)?
+            .lines()
+            .enumerate()
+            .map(|(i, line)| {
+                serde_json::from_str(line).with_context(|| {
+                    format!("{} line {} is not a record", self.path.display(), i + 1)
+                })
+            })
+            .collect()
+    }
+
+    /// The last record in `content`, the log's contents
+    fn last(&self, content: &str) -> Result<Option<AuditRecord>> {
+        let Some(line) = content.lines().next_back() else {
+            return Ok(None);
+        };
+        let record = serde_json::from_str(line).with_context(|| {
+            format!(
+                "The last line of {} is not a record; run `commandy audit verify`",
+                self.path.display()
+            )
+        })?;
+        Ok(Some(record))
+    }
+
+    /// Recomputes every hash and checks each record points at the one before it
+    pub fn verify(&self) -> Result<AuditVerification> {
+        if !self.path.exists() {
+            return Ok(AuditVerification::Intact {
+                records: 0,
+                last_hash: GENESIS.to_string(),
+            });
+        }
+
+        let content = fs::read_to_string(&self.path)?;
+        let mut expected_prev = GENESIS.to_string();
+        let mut records = 0;
+        for (i, line) in content.lines().enumerate() {
+            let line_number = i + 1;
+            let expected_seq = line_number as u64;
+            let broken = |reason: String| {
+                Ok(AuditVerification::Broken {
+                    line: line_number,
+                    reason,
+                })
+            };
+
+            let record: AuditRecord = match serde_json::from_str(line) {
+                Ok(record) => record,
+                Err(e) => return broken(format!("not a valid record: {e}")),
+            };
+            if record.seq != expected_seq {
+                return broken(format!(
+                    "sequence number {} where {expected_seq} was expected",
+                    record.seq
+                ));
+            }
+            if record.prev != expected_prev {
+                return broken("does not follow the previous record".to_string());
+            }
+            if Self::hash(&record)? != record.hash {
+                return broken("hash doesn't match the record's contents".to_string());
+            }
+
+            expected_prev = record.hash;
+            records += 1;
+        }
+
+        Ok(AuditVerification::Intact {
+            records,
+            last_hash: expected_prev,
+        })
+    }
+
+    fn hash(record: &AuditRecord) -> Result<String> {
+        // A JSON array keeps field boundaries unambiguous whatever the command contains
+        let fields = serde_json::to_string(&(
+            &record.prev,
+            record.seq,
+            &record.timestamp,
+            &record.user,
+            &record.cwd,
+            &record.command,
+            record.exit_code,
+            &record.target,
+        ))?;
+        let digest = Sha256::digest(fields.as_bytes());
+        Ok(digest.iter().map(|b| format!("{b:02x}")).collect())
+    }
+
+    fn current_user() -> String {
+        ["USER", "USERNAME", "LOGNAME"]
+            .iter()
+            .find_map(|name| std::env::var(name).ok().filter(|user| !user.is_empty()))
+            .or_else(|| {
+                let output = std::process::Command::new("whoami").output().ok()?;
+                let user = String::from_utf8_lossy(&output.stdout).trim().to_string();
+                (output.status.success() && !user.is_empty()).then_some(user)
+            })
+            .unwrap_or_else(|| "unknown".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn concurrent_appends_keep_the_chain() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::thread::scope(|scope| {
+            for i in 0..8 {
+                let log = AuditLog::new(dir.path());
+                scope.spawn(move || log.append(&format!("echo {i}"), Some(0), None).unwrap());
+            }
+        });
+
+        let log = AuditLog::new(dir.path());
+        assert!(matches!(
+            log.verify().unwrap(),
+            AuditVerification::Intact { records: 8, .. }
+        ));
+    }
+
+    #[test]
+    fn blank_line_is_a_bad_record() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let log = AuditLog::new(dir.path());
+        log.append("ls", Some(0), None).unwrap();
+        let mut file = OpenOptions::new().append(true).open(log.path()).unwrap();
+        writeln!(file).unwrap();
+
+        assert!(log.records().is_err());
+        assert!(log.append("pwd", Some(0), None).is_err());
+        assert!(matches!(
+            log.verify().unwrap(),
+            AuditVerification::Broken { line: 2, .. }
+        ));
+    }
+}
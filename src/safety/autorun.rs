@@ -0,0 +1,82 @@
+use crate::config::AutoExecuteConfig;
+
+use super::{RiskClassifier, RiskTier};
+
+/// Shell syntax that writes files, runs more than one command, or runs
+/// something built at run time; only plain pipelines are run unasked
+const SHELL_FEATURES: &[&str] = &[">", "<", ";", "&", "`", "$(", "\n", "||"];
+
+/// Arguments that make an otherwise read-only program write, delete, or run other programs
+const WRITING_ARGS: &[&str] = &[
+    "-delete", "-exec", "-execdir", "-ok", "-okdir", "-fprint", "-fprint0", "-fprintf", "-fls",
+    "--output", "--pre",
+];
+
+/// Decides which suggestions run without the picker, under `[auto_execute]`
+///
+/// Only the top suggestion qualifies, and only when it is confident enough,
+/// classified safe, made only of allowed read-only programs that are installed,
+/// and free of redirections and command substitution.
+#[derive(Debug, Clone)]
+pub struct AutoRunPolicy {
+    min_confidence: f32,
+    allow: Vec<String>,
+}
+
+impl AutoRunPolicy {
+    /// `None` unless `[auto_execute]` is enabled
+    pub fn new(config: &AutoExecuteConfig) -> Option<Self> {
+        config.enabled.then(|| Self {
+            min_confidence: config.min_confidence,
+            allow: config.allow.clone(),
+        })
+    }
+
+    /// Why `command` must go through the picker, or `None` if it can run now
+    pub fn refusal(&self, command: &str, confidence: f32) -> Option<String> {
+        if confidence < self.min_confidence {
+            return Some(format!(
+                "confidence {:.0}% is below {:.0}%",
+                confidence * 100.0,
+                self.min_confidence * 100.0
+            ));
+        }
+        if let Some(feature) = SHELL_FEATURES.iter().find(|f| command.contains(*f)) {
+            return Some(format!("uses `{feature}`"));
+        }
+        if RiskClassifier::classify(command).tier != RiskTier::Safe {
+            return Some("not classified as safe".to_string());
+        }
+
+        for segment in command.split('|').map(str::trim) {
+            if !self
+                .allow
+                .iter()
+                .any(|allowed| Self::starts_with(segment, allowed))
+            {
+                return Some(format!("`{segment}` is not in [auto_execute] allow"));
+            }
+            let words: Vec<&str> = segment.split_whitespace().collect();
+            if let Some(arg) = words.iter().find(|word| {
+                WRITING_ARGS
+                    .iter()
+                    .any(|w| word.split('=').next() == Some(*w))
+            }) {
+                return Some(format!("`{arg}` can write or run other programs"));
+            }
+            let program = words.first().copied().unwrap_or_default();
+            if which::which(program).is_err() {
+                return Some(format!("`{program}` is not installed"));
+            }
+        }
+        None
+    }
+
+    /// `git log --oneline` starts with `git log`, but `git logs` doesn't
+    fn starts_with(segment: &str, allowed: &str) -> bool {
+        let mut words = segment.split_whitespace();
+        allowed
+            .split_whitespace()
+            .all(|expected| words.next() == Some(expected))
+    }
+}
@@ -0,0 +1,15 @@
+pub mod audit;
+pub mod autorun;
+pub mod payload;
+pub mod policy;
+pub mod rerun;
+pub mod risk;
+pub mod unpack;
+
+pub use audit::{AuditLog, AuditRecord, AuditVerification};
+pub use autorun::AutoRunPolicy;
+pub use payload::{PayloadFinding, PayloadScanner};
+pub use policy::RiskPolicy;
+pub use rerun::RunContext;
+pub use risk::{Risk, RiskClassifier, RiskTier};
+pub use unpack::{ScriptUnpacker, UnpackedScript};
@@ -0,0 +1,224 @@
+use std::sync::OnceLock;
+
+use regex::Regex;
+
+/// Programs that run a script read from stdin or given as text
+const INTERPRETERS: &str =
+    r"(?:ba|z|k|da|fi)?sh|python[23]?|perl|ruby|node|php|iex|invoke-expression";
+
+/// Programs that fetch a URL
+const DOWNLOADERS: &str =
+    r"curl|wget|fetch|iwr|irm|invoke-webrequest|invoke-restmethod|\.downloadstring";
+
+/// Ways of turning encoded text back into something runnable
+const DECODERS: &[(&str, &str)] = &[
+    (r"\bbase64\s+(?:-\w*d\w*|--decode)\b", "base64"),
+    (
+        r"\bopenssl\s+(?:enc\s+-\w+\s+)?(?:base64|enc)\s+.*-d\b",
+        "base64",
+    ),
+    (r"\bxxd\s+-\w*r", "hex"),
+    (
+        r"(?:b64decode|frombase64string|fromhex|unhexlify)",
+        "encoded",
+    ),
+    (r"\b(?:gunzip|zcat|gzip\s+-\w*d)\b", "compressed"),
+    (r"\brev\b", "reversed"),
+    (r"\btr\s+'?a-za-z'?\s+'?n-za-mn-za-m", "rot13"),
+];
+
+/// Characters that show as nothing or reorder the text around them
+const INVISIBLE: &[(char, char)] = &[
+    ('\u{00ad}', '\u{00ad}'),
+    ('\u{200b}', '\u{200f}'),
+    ('\u{2028}', '\u{202e}'),
+    ('\u{2060}', '\u{2069}'),
+    ('\u{feff}', '\u{feff}'),
+];
+
+/// Something in a command that hides what it really runs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PayloadFinding {
+    /// A downloaded script run straight from the pipe, without being saved or read
+    RemoteScript {
+        url: Option<String>,
+        interpreter: String,
+    },
+    /// Encoded or escaped text decoded and run
+    Obfuscated(String),
+    /// Characters that display differently from what the shell receives
+    HiddenText(String),
+}
+
+impl PayloadFinding {
+    pub fn describe(&self) -> String {
+        match self {
+            PayloadFinding::RemoteScript {
+                url: Some(url),
+                interpreter,
+            } => format!("Runs a script downloaded from {url} with {interpreter}, unread"),
+            PayloadFinding::RemoteScript { interpreter, .. } => {
+                format!("Runs a downloaded script with {interpreter}, unread")
+            }
+            PayloadFinding::Obfuscated(detail) | PayloadFinding::HiddenText(detail) => {
+                detail.clone()
+            }
+        }
+    }
+}
+
+/// Spots curl-pipe-to-shell commands and payloads disguised by encoding or
+/// invisible characters, the usual shapes of malicious copy-paste snippets
+pub struct PayloadScanner;
+
+impl PayloadScanner {
+    pub fn scan(text: &str) -> Vec<PayloadFinding> {
+        let mut findings = Vec::new();
+        findings.extend(Self::remote_script(text));
+        findings.extend(
+            Self::obfuscation(text)
+                .into_iter()
+                .map(PayloadFinding::Obfuscated),
+        );
+        findings.extend(
+            Self::hidden_text(text)
+                .into_iter()
+                .map(PayloadFinding::HiddenText),
+        );
+        findings
+    }
+
+    /// A download piped or substituted into an interpreter
+    pub fn remote_script(text: &str) -> Option<PayloadFinding> {
+        static PATTERNS: OnceLock<Vec<Regex>> = OnceLock::new();
+        static URL: OnceLock<Regex> = OnceLock::new();
+        let lower = text.to_lowercase();
+        let patterns = PATTERNS.get_or_init(|| {
+            [
+            // curl -fsSL https://x | sudo bash -s --
+            format!(
+                r"\b(?:{DOWNLOADERS})\b[^;&\n]*?\|\s*(?:sudo\s+(?:-\S+\s+)*)?(?:\S*/)?({INTERPRETERS})\b"
+            ),
+            // bash <(curl ...), source <(wget -O- ...)
+            format!(r"\b({INTERPRETERS}|source|\.)\s+(?:-\S+\s+)*<\(\s*(?:{DOWNLOADERS})\b"),
+            // sh -c "$(curl ...)"
+            format!(r#"\b({INTERPRETERS})\s+(?:-\S+\s+)*-c\s+["']?\$\(\s*(?:{DOWNLOADERS})\b"#),
+            // iex (iwr https://x), iex (New-Object Net.WebClient).DownloadString(...)
+            format!(r"\b(iex|invoke-expression)\s*\(+[^)]*?(?:{DOWNLOADERS})"),
+            ]
+            .iter()
+            .map(|pattern| Regex::new(pattern).expect("valid regex"))
+            .collect()
+        });
+
+        let interpreter = patterns
+            .iter()
+            .find_map(|regex| regex.captures(&lower).map(|caps| caps[1].to_string()))?;
+        let url = URL
+            .get_or_init(|| Regex::new(r#"https?://[^\s'"|;)<>]+"#).expect("valid regex"))
+            .find(text)
+            .map(|url| url.as_str().to_string());
+        Some(PayloadFinding::RemoteScript { url, interpreter })
+    }
+
+    fn obfuscation(text: &str) -> Vec<String> {
+        static RUNS: OnceLock<Regex> = OnceLock::new();
+        static DECODED: OnceLock<Vec<(Regex, &str)>> = OnceLock::new();
+        static ENCODED_POWERSHELL: OnceLock<Regex> = OnceLock::new();
+        static BLOB: OnceLock<Regex> = OnceLock::new();
+        static ESCAPES: OnceLock<Regex> = OnceLock::new();
+        let lower = text.to_lowercase();
+        let mut findings = Vec::new();
+
+        let runs = RUNS.get_or_init(|| {
+            Regex::new(&format!(
+                r#"\|\s*(?:sudo\s+)?(?:\S*/)?(?:{INTERPRETERS})\b|\beval\b|\b(?:{INTERPRETERS})\s+(?:-\S+\s+)*(?:-c\s+["']?\$\(|<\()|\bexec\s*\("#
+            ))
+            .expect("valid regex")
+        });
+        if runs.is_match(&lower) {
+            let decoders = DECODED.get_or_init(|| {
+                DECODERS
+                    .iter()
+                    .map(|(pattern, encoding)| {
+                        (Regex::new(pattern).expect("valid regex"), *encoding)
+                    })
+                    .collect()
+            });
+            let decoded = decoders.iter().find(|(regex, _)| regex.is_match(&lower));
+            if let Some((_, encoding)) = decoded {
+                findings.push(format!("Decodes {encoding} text and runs the result"));
+            }
+        }
+
+        let encoded_powershell = ENCODED_POWERSHELL.get_or_init(|| {
+            Regex::new(
+                r"\b(?:powershell|pwsh)(?:\.exe)?\b.*\s-e(?:nc|ncodedcommand)?\s+[a-z0-9+/=]{16,}",
+            )
+            .expect("valid regex")
+        });
+        if encoded_powershell.is_match(&lower) {
+            findings.push("Runs a base64-encoded PowerShell command".to_string());
+        }
+
+        let blob =
+            BLOB.get_or_init(|| Regex::new(r"[A-Za-z0-9+/]{80,}={0,2}").expect("valid regex"));
+        if blob.is_match(text) {
+            findings.push("Contains a long encoded blob".to_string());
+        }
+
+        let escapes = ESCAPES.get_or_init(|| {
+            Regex::new(r"\\(?:x[0-9a-fA-F]{2}|[0-7]{3}|u[0-9a-fA-F]{4})").expect("valid regex")
+        });
+        if escapes.find_iter(text).count() >= 8 {
+            findings.push("Spells out text with character escapes".to_string());
+        }
+
+        if text.contains("${IFS}") || text.contains("$IFS") {
+            findings.push("Uses $IFS in place of spaces".to_string());
+        }
+        findings
+    }
+
+    fn hidden_text(text: &str) -> Vec<String> {
+        let mut findings = Vec::new();
+        let invisible = text
+            .chars()
+            .find(|c| Self::is_invisible(*c) && !matches!(c, '\u{1b}' | '\r'));
+        if let Some(c) = invisible {
+            findings.push(format!(
+                "Contains invisible Unicode characters (U+{:04X})",
+                c as u32
+            ));
+        }
+        if text.contains('\u{1b}') {
+            findings.push("Contains terminal escape sequences that can hide text".to_string());
+        }
+        // CRLF line endings are harmless; a `\r` elsewhere returns to the start of the line
+        let bare_return = text
+            .trim_end()
+            .split("\r\n")
+            .any(|line| line.contains('\r'));
+        if bare_return {
+            findings.push("Contains a carriage return that can hide part of a line".to_string());
+        }
+        let lookalike = text.split_whitespace().any(|word| {
+            word.chars().any(|c| c.is_ascii_alphabetic())
+                && word
+                    .chars()
+                    .any(|c| matches!(c, '\u{0370}'..='\u{03ff}' | '\u{0400}'..='\u{04ff}'))
+        });
+        if lookalike {
+            findings.push("Mixes Latin letters with Greek or Cyrillic lookalikes".to_string());
+        }
+        findings
+    }
+
+    /// Whether a character is invisible, or a control character other than a newline or tab
+    pub fn is_invisible(c: char) -> bool {
+        (c.is_control() && !matches!(c, '\n' | '\t'))
+            || INVISIBLE
+                .iter()
+                .any(|(start, end)| (*start..=*end).contains(&c))
+    }
+}
@@ -0,0 +1,37 @@
+use crate::config::{ProfileConfig, RiskAction};
+
+use super::{Risk, RiskClassifier, RiskTier};
+
+/// A profile's limits on running commands, by risk tier
+///
+/// Checked when a command is about to run rather than when it's shown, so
+/// every way of running a suggestion goes through it.
+#[derive(Debug, Clone)]
+pub struct RiskPolicy {
+    profile: String,
+    config: ProfileConfig,
+}
+
+impl RiskPolicy {
+    pub fn new(profile: &str, config: ProfileConfig) -> Self {
+        Self {
+            profile: profile.to_string(),
+            config,
+        }
+    }
+
+    pub fn profile(&self) -> &str {
+        &self.profile
+    }
+
+    /// What the profile allows for a command, and the risk that decided it
+    pub fn check(&self, command: &str) -> (RiskAction, Risk) {
+        let risk = RiskClassifier::classify(command);
+        let action = match risk.tier {
+            RiskTier::Safe => RiskAction::Run,
+            RiskTier::Privileged => self.config.privileged,
+            RiskTier::Destructive => self.config.destructive.max(self.config.privileged),
+        };
+        (action, risk)
+    }
+}
@@ -0,0 +1,53 @@
+use std::path::PathBuf;
+
+use crate::context::ProjectScanner;
+use crate::utils::PathDisplay;
+
+/// Where suggestions were made: the current directory and the git branch there
+///
+/// Captured when the picker opens and compared again before a command runs, so
+/// a command written for one checkout doesn't run in another after a switch in
+/// a different terminal.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RunContext {
+    cwd: Option<PathBuf>,
+    branch: Option<String>,
+}
+
+impl RunContext {
+    pub fn capture() -> Self {
+        let cwd = std::env::current_dir().ok();
+        let branch = cwd.as_deref().and_then(ProjectScanner::git_branch);
+        Self { cwd, branch }
+    }
+
+    /// What differs from `earlier`, one line each, such as
+    /// `git branch is now feature (was main)`
+    pub fn changes_since(&self, earlier: &Self) -> Vec<String> {
+        let mut changes = Vec::new();
+        if self.cwd != earlier.cwd {
+            let describe = |cwd: &Option<PathBuf>| {
+                cwd.as_deref()
+                    .map_or_else(|| "gone".to_string(), PathDisplay::shorten)
+            };
+            changes.push(format!(
+                "current directory is now {} (was {})",
+                describe(&self.cwd),
+                describe(&earlier.cwd)
+            ));
+        }
+        if self.branch != earlier.branch {
+            let describe = |branch: &Option<String>| {
+                branch
+                    .clone()
+                    .unwrap_or_else(|| "not a git checkout".to_string())
+            };
+            changes.push(format!(
+                "git branch is now {} (was {})",
+                describe(&self.branch),
+                describe(&earlier.branch)
+            ));
+        }
+        changes
+    }
+}
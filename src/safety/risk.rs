@@ -0,0 +1,115 @@
+use std::sync::OnceLock;
+
+use regex::Regex;
+
+use super::PayloadScanner;
+use crate::utils::{CommandValidator, PrivilegeAnalyzer};
+
+/// Commands that throw away work or data without deleting files by name
+const DESTRUCTIVE_PATTERNS: &[(&str, &str)] = &[
+    (r"\bgit\s+reset\s+--hard\b", "Discards uncommitted changes"),
+    (r"\bgit\s+clean\s+-\w*f", "Deletes untracked files"),
+    (
+        r"\bgit\s+push\b.*\s(?:--force\b|-f\b)",
+        "Overwrites the remote branch",
+    ),
+    (r"\bfind\b.*\s-delete\b", "Deletes every file find matches"),
+    (
+        r"\bxargs\s+(?:-\S+\s+)*rm\b",
+        "Deletes the files it's given",
+    ),
+    (r"\bkubectl\s+delete\b", "Deletes Kubernetes resources"),
+    (
+        r"\bdocker\s+(?:system\s+prune|volume\s+(?:rm|prune))\b",
+        "Deletes Docker data",
+    ),
+    (
+        r"(?i)\bdrop\s+(?:table|database)\b",
+        "Drops database tables",
+    ),
+    (r"\btruncate\s+-s\s*0\b", "Empties files"),
+];
+
+/// How much harm a command can do, in increasing order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum RiskTier {
+    Safe,
+    /// Needs root or elevates itself
+    Privileged,
+    /// Deletes data, or runs code nobody has read
+    Destructive,
+}
+
+impl RiskTier {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            RiskTier::Safe => "safe",
+            RiskTier::Privileged => "privileged",
+            RiskTier::Destructive => "destructive",
+        }
+    }
+}
+
+/// A command's risk tier and every reason found for it
+#[derive(Debug, Clone)]
+pub struct Risk {
+    pub tier: RiskTier,
+    pub reasons: Vec<(RiskTier, String)>,
+}
+
+/// Sorts commands into risk tiers with the privilege analyzer, the
+/// validator's dangerous patterns, and the payload scanner
+pub struct RiskClassifier;
+
+impl RiskClassifier {
+    pub fn classify(command: &str) -> Risk {
+        let mut reasons = Vec::new();
+
+        for finding in PayloadScanner::scan(command) {
+            reasons.push((RiskTier::Destructive, finding.describe()));
+        }
+
+        let validator = CommandValidator::new();
+        if !validator.is_safe_command(command) {
+            reasons.push((
+                RiskTier::Destructive,
+                "Matches a known dangerous pattern".to_string(),
+            ));
+        }
+        for segment in command.split(['|', ';', '&', '\n']) {
+            let segment = segment.trim();
+            if validator.is_destructive_command(segment) {
+                let name = validator.extract_command_name(segment).unwrap_or_default();
+                reasons.push((
+                    RiskTier::Destructive,
+                    format!("Runs {name}, which can't be undone"),
+                ));
+            }
+        }
+        static DESTRUCTIVE: OnceLock<Vec<(Regex, &str)>> = OnceLock::new();
+        let destructive = DESTRUCTIVE.get_or_init(|| {
+            DESTRUCTIVE_PATTERNS
+                .iter()
+                .map(|(pattern, reason)| (Regex::new(pattern).expect("valid regex"), *reason))
+                .collect()
+        });
+        for (regex, reason) in destructive {
+            if regex.is_match(command) {
+                reasons.push((RiskTier::Destructive, reason.to_string()));
+            }
+        }
+
+        if PrivilegeAnalyzer::is_elevated(command) {
+            reasons.push((RiskTier::Privileged, "Runs with sudo".to_string()));
+        } else if let Some(reason) = PrivilegeAnalyzer::root_reason(command) {
+            reasons.push((RiskTier::Privileged, format!("Needs root: {reason}")));
+        }
+
+        let tier = reasons
+            .iter()
+            .map(|(tier, _)| *tier)
+            .max()
+            .unwrap_or(RiskTier::Safe);
+        Risk { tier, reasons }
+    }
+}
@@ -0,0 +1,117 @@
+use anyhow::{Context, Result};
+use regex::Regex;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::{PayloadFinding, PayloadScanner};
+use crate::utils::HttpClient;
+
+/// A script from a `curl | sh` command, saved so it can be read before it runs
+pub struct UnpackedScript {
+    pub url: String,
+    pub path: PathBuf,
+    pub content: String,
+    pub sha256: String,
+    /// The original command rewritten to run the saved file, when that's possible
+    pub local_command: Option<String>,
+}
+
+impl UnpackedScript {
+    /// The first `count` lines of the script
+    pub fn head(&self, count: usize) -> Vec<&str> {
+        self.content.lines().take(count).collect()
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.content.lines().count()
+    }
+
+    pub fn remove(&self) {
+        if let Err(e) = fs::remove_file(&self.path) {
+            tracing::warn!("Failed to remove {}: {e}", self.path.display());
+        }
+    }
+}
+
+/// Downloads the script a `curl ... | sh` style command would run blindly
+pub struct ScriptUnpacker;
+
+impl ScriptUnpacker {
+    /// Saves the remote script to a temp file, or `None` if the command doesn't run one
+    pub fn unpack(command: &str) -> Result<Option<UnpackedScript>> {
+        let Some(PayloadFinding::RemoteScript { url: Some(url), .. }) =
+            PayloadScanner::remote_script(command)
+        else {
+            return Ok(None);
+        };
+
+        let extension = Path::new(url.split(['?', '#']).next().unwrap_or(&url))
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .filter(|ext| ext.len() <= 4 && ext.chars().all(|c| c.is_ascii_alphanumeric()))
+            .unwrap_or("sh");
+        let path = std::env::temp_dir().join(format!(
+            "commandy-script-{}.{extension}",
+            uuid::Uuid::new_v4()
+        ));
+        HttpClient::download_file(&url, &path)?;
+        let bytes =
+            fs::read(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+
+        Ok(Some(UnpackedScript {
+            sha256: format!("{:x}", Sha256::digest(&bytes)),
+            content: String::from_utf8_lossy(&bytes).into_owned(),
+            local_command: Self::local_command(command, &path),
+            url,
+            path,
+        }))
+    }
+
+    /// The command with its download swapped for the saved file
+    ///
+    /// `curl -fsSL URL | sudo bash -s -- --yes` becomes `sudo bash FILE --yes`;
+    /// `bash <(curl URL)` and `sh -c "$(curl URL)"` become `bash FILE` and `sh FILE`.
+    pub fn local_command(command: &str, path: &Path) -> Option<String> {
+        let file = path.to_string_lossy();
+        if file.contains(|c: char| c.is_whitespace() || "'\"$`\\".contains(c)) {
+            return None;
+        }
+
+        let substituted = Regex::new(r#"(?:<\(\s*|-c\s+["']?\$\(\s*)(?:curl|wget)\b[^)]*\)["']?"#)
+            .expect("valid regex");
+        if substituted.is_match(command) {
+            return Some(substituted.replace(command, file.as_ref()).into_owned());
+        }
+
+        let piped =
+            Regex::new(r"^(.*?)\b(?:curl|wget)\b[^|;&]*\|\s*([^|;&]+)(.*)$").expect("valid regex");
+        let caps = piped.captures(command)?;
+        let words: Vec<&str> = caps[2].split_whitespace().collect();
+        let interpreter = words.iter().position(|word| {
+            let name = word.rsplit('/').next().unwrap_or(word);
+            matches!(
+                name,
+                "sh" | "bash" | "zsh" | "dash" | "ksh" | "python" | "python3" | "perl" | "ruby"
+            )
+        })?;
+
+        let mut local: Vec<&str> = words[..=interpreter].to_vec();
+        let mut rest = words[interpreter + 1..].iter().peekable();
+        // Flags before the script's own arguments; `-s`, `-`, and `--` only made it read stdin
+        while let Some(word) = rest.next_if(|word| word.starts_with('-')) {
+            match *word {
+                "-s" => {}
+                "-" | "--" => break,
+                flag => local.push(flag),
+            }
+        }
+        local.push(file.as_ref());
+        local.extend(rest.copied());
+        let after = match caps[3].trim() {
+            "" => String::new(),
+            after => format!(" {after}"),
+        };
+        Some(format!("{}{}{after}", &caps[1], local.join(" ")))
+    }
+}
@@ -0,0 +1,101 @@
+use anyhow::{bail, Context, Result};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::net::IpAddr;
+use std::path::PathBuf;
+use uuid::Uuid;
+
+/// File in `~/.commandy` holding the token
+pub const TOKEN_FILE: &str = "auth-token";
+
+/// The secret `commandy serve` and the daemon's llama-server require as
+/// `Authorization: Bearer <token>`
+///
+/// Without it any local process, or any user on a shared machine, could drive
+/// the model through the port. It is generated on first use and kept in
+/// `~/.commandy/auth-token`, readable only by its owner.
+pub struct AuthToken;
+
+impl AuthToken {
+    pub fn path() -> Result<PathBuf> {
+        let home = dirs::home_dir().context("Could not find home directory")?;
+        Ok(home.join(".commandy").join(TOKEN_FILE))
+    }
+
+    /// The saved token, if one has been generated
+    pub fn load() -> Option<String> {
+        let text = fs::read_to_string(Self::path().ok()?).ok()?;
+        let token = text.trim();
+        (!token.is_empty()).then(|| token.to_string())
+    }
+
+    /// The saved token, generating one the first time
+    pub fn load_or_create() -> Result<String> {
+        let path = Self::path()?;
+        if let Some(token) = Self::load() {
+            Self::restrict(&path)?;
+            return Ok(token);
+        }
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let token = format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple());
+        let mut options = OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let mut file = options
+            .open(&path)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        file.write_all(token.as_bytes())?;
+        Self::restrict(&path)?;
+        Ok(token)
+    }
+
+    /// Whether `given` is the token, comparing in constant time
+    pub fn matches(given: &str, token: &str) -> bool {
+        given.len() == token.len()
+            && given
+                .bytes()
+                .zip(token.bytes())
+                .fold(0, |diff, (a, b)| diff | (a ^ b))
+                == 0
+    }
+
+    /// Takes group and other permissions off a token file that was created or copied by hand
+    fn restrict(path: &std::path::Path) -> Result<()> {
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(path)?.permissions().mode();
+            if mode & 0o077 != 0 {
+                fs::set_permissions(path, fs::Permissions::from_mode(0o600))
+                    .with_context(|| format!("Failed to restrict {}", path.display()))?;
+            }
+        }
+        #[cfg(not(unix))]
+        let _ = path;
+        Ok(())
+    }
+}
+
+/// Where the local servers may listen
+pub struct Exposure;
+
+impl Exposure {
+    /// Refuses addresses other machines can reach unless `allow_remote` is set
+    /// in `section`, such as `[server]`
+    pub fn check(ip: IpAddr, allow_remote: bool, section: &str) -> Result<()> {
+        if ip.is_loopback() || allow_remote {
+            return Ok(());
+        }
+        bail!(
+            "Refusing to listen on {ip}, which other machines can reach; \
+            set allow_remote = true under {section} to allow it"
+        )
+    }
+}
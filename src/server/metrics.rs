@@ -0,0 +1,208 @@
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::daemon::DaemonState;
+use crate::server::QueueStats;
+
+/// Upper bounds, in seconds, of the request latency histogram's buckets
+const LATENCY_BUCKETS: [f64; 10] = [0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0];
+
+/// Routes counted under their own name; anything else is counted as "other"
+/// so scanners probing random paths can't add series without end
+const ROUTES: [&str; 6] = [
+    "/health", "/suggest", "/explain", "/fix", "/stats", "/metrics",
+];
+
+/// Type and help text of each metric, one per line
+const METRICS: &str = "\
+commandy_build_info gauge Version of the running commandy
+commandy_requests_total counter Requests answered, by route and status
+commandy_request_duration_seconds histogram Time to answer a request, including the wait for a queue slot
+commandy_requests_by_category_total counter Suggest requests per query category
+commandy_uptime_seconds gauge Seconds since the server started
+commandy_requests_cancelled_total counter Requests whose client disconnected before the answer
+commandy_cache_hits_total counter Suggest requests answered from the cache
+commandy_cache_misses_total counter Suggest requests that needed the model
+commandy_queue_depth gauge Requests waiting for a slot
+commandy_requests_active gauge Requests being generated
+commandy_queue_concurrency gauge Requests that can be generated at once
+commandy_requests_rejected_total counter Requests turned away as busy
+commandy_queue_wait_seconds_max gauge Longest wait for a slot so far
+commandy_model_available gauge Whether a model backend was found at startup
+commandy_daemon_up gauge Whether commandy daemon is running
+commandy_model_loaded gauge Whether the daemon's llama-server passed its last health check
+commandy_model_loading gauge Whether the daemon's llama-server is still loading the model
+commandy_daemon_restarts_total counter Times the daemon restarted llama-server
+commandy_model_memory_bytes gauge Memory held by the daemon's llama-server";
+
+/// Requests and latencies of one route
+#[derive(Default)]
+struct RouteMetrics {
+    by_status: BTreeMap<u16, u64>,
+    /// Requests that finished within each of `LATENCY_BUCKETS`
+    buckets: [u64; LATENCY_BUCKETS.len()],
+    count: u64,
+    seconds: f64,
+}
+
+#[derive(Default)]
+struct MetricsState {
+    routes: BTreeMap<&'static str, RouteMetrics>,
+    cancelled: u64,
+    cache_hits: u64,
+    cache_misses: u64,
+}
+
+/// Whether the model can answer, as `/metrics` reports it
+pub struct ModelStatus {
+    /// A llama.cpp binary or server was found when the server started
+    pub available: bool,
+    /// What `commandy daemon` last saw, when it is running
+    pub daemon: Option<DaemonState>,
+}
+
+/// Counters for `GET /metrics`, in the Prometheus text format
+pub struct Metrics {
+    started: Instant,
+    state: Mutex<MetricsState>,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            state: Mutex::new(MetricsState::default()),
+        }
+    }
+
+    /// Counts a request that was answered, with the time it took including any wait for the queue
+    pub fn record(&self, route: &str, status: u16, elapsed: Duration) {
+        let route = ROUTES.into_iter().find(|r| *r == route).unwrap_or("other");
+        let seconds = elapsed.as_secs_f64();
+        let mut state = self.state.lock().unwrap();
+        let metrics = state.routes.entry(route).or_default();
+        *metrics.by_status.entry(status).or_default() += 1;
+        for (bucket, bound) in metrics.buckets.iter_mut().zip(LATENCY_BUCKETS) {
+            if seconds <= bound {
+                *bucket += 1;
+            }
+        }
+        metrics.count += 1;
+        metrics.seconds += seconds;
+    }
+
+    /// Counts a request whose client hung up before it was answered
+    pub fn record_cancelled(&self) {
+        self.state.lock().unwrap().cancelled += 1;
+    }
+
+    /// Counts a suggest request that was allowed to use the cache
+    pub fn record_cache(&self, hit: bool) {
+        let mut state = self.state.lock().unwrap();
+        match hit {
+            true => state.cache_hits += 1,
+            false => state.cache_misses += 1,
+        }
+    }
+
+    /// Every metric, with the queue's counters and the model's state as of now
+    pub fn render(&self, queue: &QueueStats, model: &ModelStatus) -> String {
+        let state = self.state.lock().unwrap();
+        let mut out = String::new();
+
+        Self::header(&mut out, "commandy_build_info");
+        out.push_str(&format!(
+            "commandy_build_info{{version=\"{}\"}} 1\n",
+            env!("CARGO_PKG_VERSION")
+        ));
+
+        Self::header(&mut out, "commandy_requests_total");
+        for (route, metrics) in &state.routes {
+            for (status, count) in &metrics.by_status {
+                out.push_str(&format!(
+                    "commandy_requests_total{{route=\"{route}\",status=\"{status}\"}} {count}\n"
+                ));
+            }
+        }
+        Self::header(&mut out, "commandy_request_duration_seconds");
+        for (route, metrics) in &state.routes {
+            let name = "commandy_request_duration_seconds";
+            for (count, bound) in metrics.buckets.iter().zip(LATENCY_BUCKETS) {
+                out.push_str(&format!(
+                    "{name}_bucket{{route=\"{route}\",le=\"{bound}\"}} {count}\n"
+                ));
+            }
+            out.push_str(&format!(
+                "{name}_bucket{{route=\"{route}\",le=\"+Inf\"}} {}\n",
+                metrics.count
+            ));
+            out.push_str(&format!(
+                "{name}_sum{{route=\"{route}\"}} {:.6}\n",
+                metrics.seconds
+            ));
+            out.push_str(&format!(
+                "{name}_count{{route=\"{route}\"}} {}\n",
+                metrics.count
+            ));
+        }
+        Self::header(&mut out, "commandy_requests_by_category_total");
+        let categories: BTreeMap<_, _> = queue.requests_by_category.iter().collect();
+        for (category, count) in categories {
+            out.push_str(&format!(
+                "commandy_requests_by_category_total{{category=\"{category}\"}} {count}\n"
+            ));
+        }
+
+        let flag = |on: bool| f64::from(u8::from(on));
+        let mut values = vec![
+            (
+                "commandy_uptime_seconds",
+                self.started.elapsed().as_secs_f64(),
+            ),
+            ("commandy_requests_cancelled_total", state.cancelled as f64),
+            ("commandy_cache_hits_total", state.cache_hits as f64),
+            ("commandy_cache_misses_total", state.cache_misses as f64),
+            ("commandy_queue_depth", queue.queued as f64),
+            ("commandy_requests_active", queue.active as f64),
+            ("commandy_queue_concurrency", queue.concurrency as f64),
+            ("commandy_requests_rejected_total", queue.rejected as f64),
+            (
+                "commandy_queue_wait_seconds_max",
+                queue.max_wait_ms as f64 / 1000.0,
+            ),
+            ("commandy_model_available", flag(model.available)),
+            ("commandy_daemon_up", flag(model.daemon.is_some())),
+        ];
+        if let Some(daemon) = &model.daemon {
+            values.push(("commandy_model_loaded", flag(daemon.healthy)));
+            values.push(("commandy_model_loading", flag(daemon.loading)));
+            values.push(("commandy_daemon_restarts_total", f64::from(daemon.restarts)));
+            if let Some(rss) = daemon.rss_mb {
+                values.push(("commandy_model_memory_bytes", (rss * 1024 * 1024) as f64));
+            }
+        }
+        for (metric, value) in values {
+            Self::header(&mut out, metric);
+            out.push_str(&format!("{metric} {value}\n"));
+        }
+        out
+    }
+
+    /// The `# HELP` and `# TYPE` lines of `name`, from `METRICS`
+    fn header(out: &mut String, name: &str) {
+        let declared = METRICS
+            .lines()
+            .find_map(|line| line.strip_prefix(name)?.strip_prefix(' '))
+            .and_then(|line| line.split_once(' '));
+        if let Some((kind, help)) = declared {
+            out.push_str(&format!("# HELP {name} {help}\n# TYPE {name} {kind}\n"));
+        }
+    }
+}
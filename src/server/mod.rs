@@ -0,0 +1,10 @@
+pub mod auth;
+pub mod http;
+pub mod metrics;
+pub mod queue;
+pub mod routes;
+
+pub use auth::{AuthToken, Exposure};
+pub use metrics::{Metrics, ModelStatus};
+pub use queue::{QueueStats, RequestQueue};
+pub use routes::ApiServer;
@@ -0,0 +1,251 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::oneshot;
+
+use crate::config::ServerConfig;
+use crate::context::Category;
+
+/// Weight of the newest sample in the moving average of service time
+const SERVICE_TIME_SMOOTHING: f64 = 0.2;
+
+/// Why a request was turned away instead of queued
+#[derive(Debug, Clone, Serialize)]
+pub struct Busy {
+    pub reason: &'static str,
+    pub queued: usize,
+    pub estimated_wait_ms: u64,
+}
+
+/// Counters reported by `GET /stats` and `commandy stats`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct QueueStats {
+    pub concurrency: usize,
+    pub active: usize,
+    pub queued: usize,
+    pub completed: u64,
+    pub rejected: u64,
+    pub avg_service_ms: u64,
+    pub avg_wait_ms: u64,
+    pub max_wait_ms: u64,
+    pub requests_by_client: HashMap<String, u64>,
+    /// Suggest requests per query category
+    #[serde(default)]
+    pub requests_by_category: HashMap<String, u64>,
+}
+
+struct Waiter {
+    client: String,
+    /// Receives the slot; if the waiter has gone away the permit drops and frees it
+    wake: oneshot::Sender<Permit>,
+}
+
+struct QueueState {
+    active: usize,
+    active_by_client: HashMap<String, usize>,
+    waiting: VecDeque<Waiter>,
+    stats: QueueStats,
+    avg_service_ms: f64,
+    total_wait_ms: u64,
+}
+
+/// Bounded queue in front of the model that keeps one busy shell from starving others
+///
+/// Up to `concurrency` requests run at once. When a slot frees up it goes to
+/// the waiting client with the fewest requests already running, oldest first.
+#[derive(Clone)]
+pub struct RequestQueue {
+    config: ServerConfig,
+    state: Arc<Mutex<QueueState>>,
+}
+
+/// Holds a slot until dropped
+pub struct Permit {
+    /// None once the slot has been given back by other means
+    queue: Option<RequestQueue>,
+    client: String,
+    started: Instant,
+}
+
+impl RequestQueue {
+    pub fn new(config: &ServerConfig) -> Self {
+        let config = ServerConfig {
+            concurrency: config.concurrency.max(1),
+            ..config.clone()
+        };
+        let stats = QueueStats {
+            concurrency: config.concurrency,
+            ..QueueStats::default()
+        };
+
+        Self {
+            config,
+            state: Arc::new(Mutex::new(QueueState {
+                active: 0,
+                active_by_client: HashMap::new(),
+                waiting: VecDeque::new(),
+                stats,
+                avg_service_ms: 0.0,
+                total_wait_ms: 0,
+            })),
+        }
+    }
+
+    /// Waits for a slot, or returns `Busy` right away if the queue is full
+    pub async fn acquire(&self, client: &str) -> Result<Permit, Busy> {
+        let queued_at = Instant::now();
+
+        let receiver = {
+            let mut state = self.state.lock().unwrap();
+            *state
+                .stats
+                .requests_by_client
+                .entry(client.to_string())
+                .or_default() += 1;
+
+            if state.active < self.config.concurrency && state.waiting.is_empty() {
+                Self::start(&mut state, client);
+                return Ok(self.permit(client));
+            }
+
+            let client_queued = state.waiting.iter().filter(|w| w.client == client).count();
+            let reason = if state.waiting.len() >= self.config.max_queue {
+                Some("queue full")
+            } else if client_queued >= self.config.max_queued_per_client {
+                Some("too many queued requests from this client")
+            } else {
+                None
+            };
+            if let Some(reason) = reason {
+                state.stats.rejected += 1;
+                return Err(Busy {
+                    reason,
+                    queued: state.waiting.len(),
+                    estimated_wait_ms: self.estimate_wait(&state, state.waiting.len()),
+                });
+            }
+
+            let (wake, receiver) = oneshot::channel();
+            state.waiting.push_back(Waiter {
+                client: client.to_string(),
+                wake,
+            });
+            state.stats.queued = state.waiting.len();
+            receiver
+        };
+
+        let permit = receiver.await.map_err(|_| Busy {
+            reason: "server shutting down",
+            queued: 0,
+            estimated_wait_ms: 0,
+        })?;
+
+        let waited_ms = queued_at.elapsed().as_millis() as u64;
+        let mut state = self.state.lock().unwrap();
+        state.total_wait_ms += waited_ms;
+        state.stats.max_wait_ms = state.stats.max_wait_ms.max(waited_ms);
+        Ok(permit)
+    }
+
+    /// Estimated time until a request at the back of the queue would start
+    pub fn estimated_wait(&self) -> Duration {
+        let state = self.state.lock().unwrap();
+        Duration::from_millis(self.estimate_wait(&state, state.waiting.len()))
+    }
+
+    pub fn stats(&self) -> QueueStats {
+        let state = self.state.lock().unwrap();
+        let mut stats = state.stats.clone();
+        stats.active = state.active;
+        stats.queued = state.waiting.len();
+        stats.avg_service_ms = state.avg_service_ms.round() as u64;
+        stats.avg_wait_ms = state
+            .total_wait_ms
+            .checked_div(stats.completed)
+            .unwrap_or(0);
+        stats
+    }
+
+    /// Counts a suggest request towards the category breakdown
+    pub fn record_category(&self, category: Category) {
+        let mut state = self.state.lock().unwrap();
+        *state
+            .stats
+            .requests_by_category
+            .entry(category.slug().to_string())
+            .or_default() += 1;
+    }
+
+    fn permit(&self, client: &str) -> Permit {
+        Permit {
+            queue: Some(self.clone()),
+            client: client.to_string(),
+            started: Instant::now(),
+        }
+    }
+
+    fn estimate_wait(&self, state: &QueueState, position: usize) -> u64 {
+        let rounds = (position / self.config.concurrency + 1) as f64;
+        (state.avg_service_ms * rounds).round() as u64
+    }
+
+    fn start(state: &mut QueueState, client: &str) {
+        state.active += 1;
+        *state
+            .active_by_client
+            .entry(client.to_string())
+            .or_default() += 1;
+    }
+
+    fn finish(state: &mut QueueState, client: &str) {
+        state.active -= 1;
+        if let Some(count) = state.active_by_client.get_mut(client) {
+            *count -= 1;
+            if *count == 0 {
+                state.active_by_client.remove(client);
+            }
+        }
+    }
+
+    fn release(&self, client: &str, service_time: Duration) {
+        let mut state = self.state.lock().unwrap();
+        Self::finish(&mut state, client);
+
+        let sample = service_time.as_millis() as f64;
+        state.avg_service_ms = if state.stats.completed == 0 {
+            sample
+        } else {
+            state.avg_service_ms * (1.0 - SERVICE_TIME_SMOOTHING) + sample * SERVICE_TIME_SMOOTHING
+        };
+        state.stats.completed += 1;
+
+        // Hand the slot to the least-served waiting client, skipping abandoned waiters
+        while state.active < self.config.concurrency {
+            let next = (0..state.waiting.len()).min_by_key(|&i| {
+                let client = &state.waiting[i].client;
+                (state.active_by_client.get(client).copied().unwrap_or(0), i)
+            });
+            let Some(index) = next else {
+                break;
+            };
+
+            let waiter = state.waiting.remove(index).expect("index in range");
+            Self::start(&mut state, &waiter.client);
+            if let Err(mut permit) = waiter.wake.send(self.permit(&waiter.client)) {
+                // Waiter gone; undo by hand since dropping would re-enter the lock
+                permit.queue = None;
+                Self::finish(&mut state, &waiter.client);
+            }
+        }
+        state.stats.queued = state.waiting.len();
+    }
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        if let Some(queue) = self.queue.take() {
+            queue.release(&self.client, self.started.elapsed());
+        }
+    }
+}
@@ -0,0 +1,307 @@
+use anyhow::Result;
+use serde::Deserialize;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::io::{AsyncReadExt, BufReader};
+use tokio::net::tcp::OwnedReadHalf;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::Mutex;
+use tracing::{debug, info};
+
+use crate::cli::{CommandHandler, PromptOptions, SuggestionSource};
+use crate::config::ServerConfig;
+use crate::context::TaxonomyClassifier;
+use crate::daemon::DaemonFiles;
+use crate::server::auth::{AuthToken, Exposure};
+use crate::server::http::{Request, Response};
+use crate::server::metrics::{Metrics, ModelStatus};
+use crate::server::queue::{Permit, RequestQueue};
+
+#[derive(Debug, Deserialize)]
+struct SuggestRequest {
+    prompt: String,
+    #[serde(default)]
+    max_suggestions: Option<usize>,
+    /// Existing command to refine, as when piping commandy into commandy
+    #[serde(default)]
+    base_command: Option<String>,
+    #[serde(default)]
+    no_cache: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct ExplainRequest {
+    command: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct FixRequest {
+    command: String,
+    #[serde(default)]
+    error_output: Option<String>,
+}
+
+/// JSON API for editor integrations, served by `commandy serve`
+pub struct ApiServer {
+    /// One handler per concurrency slot, each with its own cache connection
+    handlers: Vec<Mutex<CommandHandler>>,
+    queue: RequestQueue,
+    metrics: Metrics,
+    /// A backend was found when the server started
+    has_model: bool,
+    /// Required as a bearer token on every request but `/health`
+    token: String,
+    allow_remote: bool,
+}
+
+impl ApiServer {
+    pub fn new(mut handler: CommandHandler, token: String, config: &ServerConfig) -> Result<Self> {
+        let has_model = handler.has_model();
+        handler.set_model_lock(false);
+        handler.set_use_daemon(config.use_daemon);
+        // Every slot runs under the first one's --read-only and --profile
+        let read_only = handler.is_read_only();
+        let profile = handler.profile().map(str::to_string);
+        let mut handlers = vec![Mutex::new(handler)];
+        for _ in 1..config.concurrency.max(1) {
+            let mut handler = CommandHandler::new()?;
+            handler.set_model_lock(false);
+            handler.set_use_daemon(config.use_daemon);
+            handler.set_read_only(read_only);
+            handler.set_profile(profile.as_deref())?;
+            handlers.push(Mutex::new(handler));
+        }
+
+        Ok(Self {
+            handlers,
+            queue: RequestQueue::new(config),
+            metrics: Metrics::new(),
+            has_model,
+            token,
+            allow_remote: config.allow_remote,
+        })
+    }
+
+    /// Accepts connections until the process is stopped
+    pub async fn run(self, addr: SocketAddr) -> Result<()> {
+        Exposure::check(addr.ip(), self.allow_remote, "[server]")?;
+        let listener = TcpListener::bind(addr).await?;
+        info!("Listening on http://{addr}");
+        eprintln!(
+            "commandy API listening on http://{}",
+            listener.local_addr()?
+        );
+
+        let server = Arc::new(self);
+        loop {
+            let (stream, peer) = listener.accept().await?;
+            let server = server.clone();
+            tokio::spawn(async move {
+                if let Err(e) = server.handle_connection(stream, peer).await {
+                    debug!("Connection from {peer} failed: {e}");
+                }
+            });
+        }
+    }
+
+    async fn handle_connection(&self, stream: TcpStream, peer: SocketAddr) -> Result<()> {
+        let (reader, mut writer) = stream.into_split();
+        let mut reader = BufReader::new(reader);
+
+        let response = match Request::read(&mut reader).await {
+            Ok(Some(request)) => {
+                let route = request.route().to_string();
+                let started = Instant::now();
+                // A client that hangs up mid-generation (e.g. the editor sent a
+                // newer request) cancels it; dropping the route future kills
+                // llama.cpp and gives up any queue slot
+                let response = tokio::select! {
+                    response = self.route(request, peer) => response,
+                    _ = Self::disconnected(&mut reader) => {
+                        debug!("Client {peer} disconnected, cancelled request");
+                        self.metrics.record_cancelled();
+                        return Ok(());
+                    }
+                };
+                self.metrics
+                    .record(&route, response.status, started.elapsed());
+                response
+            }
+            Ok(None) => return Ok(()),
+            Err(e) => Response::error(400, &e.to_string()),
+        };
+
+        response.write(&mut writer).await
+    }
+
+    /// Resolves once the connection is reset
+    ///
+    /// End of input isn't a cancel: `nc -N` and some HTTP libraries shut down
+    /// their sending side once the request is out and still read the answer.
+    async fn disconnected(reader: &mut BufReader<OwnedReadHalf>) {
+        let mut buf = [0u8; 256];
+        loop {
+            match reader.read(&mut buf).await {
+                Err(_) => return,
+                Ok(0) => std::future::pending::<()>().await,
+                // Pipelined bytes are ignored; one request per connection
+                Ok(_) => continue,
+            }
+        }
+    }
+
+    async fn route(&self, request: Request, peer: SocketAddr) -> Response {
+        debug!("{} {}", request.method, request.path);
+
+        if request.route() == "/health" {
+            return Response::json(
+                200,
+                &serde_json::json!({ "status": "ok", "version": env!("CARGO_PKG_VERSION") }),
+            );
+        }
+
+        if !self.is_authorized(&request) {
+            return Response::error(401, "Missing or invalid bearer token");
+        }
+
+        match (request.method.as_str(), request.route()) {
+            ("GET", "/stats") => return Response::json(200, &self.queue.stats()),
+            ("GET", "/metrics") => return self.metrics(),
+            ("POST", "/suggest" | "/explain" | "/fix") => {}
+            (_, "/suggest" | "/explain" | "/fix" | "/stats" | "/metrics") => {
+                return Response::error(405, "Method not allowed");
+            }
+            _ => return Response::error(404, "Not found"),
+        }
+
+        // Editors can identify themselves; otherwise each address is one client
+        let client = request
+            .header("x-commandy-client")
+            .map(str::to_string)
+            .unwrap_or_else(|| peer.ip().to_string());
+        let permit = match self.queue.acquire(&client).await {
+            Ok(permit) => permit,
+            Err(busy) => {
+                return Response::json(
+                    429,
+                    &serde_json::json!({
+                        "error": "busy",
+                        "reason": busy.reason,
+                        "queued": busy.queued,
+                        "estimated_wait_ms": busy.estimated_wait_ms,
+                    }),
+                )
+            }
+        };
+
+        let result = match request.route() {
+            "/suggest" => self.suggest(&request, &permit).await,
+            "/explain" => self.explain(&request, &permit).await,
+            _ => self.fix(&request, &permit).await,
+        };
+
+        result.unwrap_or_else(|e| Response::error(500, &e.to_string()))
+    }
+
+    /// Takes a free handler; holding a permit guarantees one is available
+    async fn checkout(&self, _permit: &Permit) -> tokio::sync::MutexGuard<'_, CommandHandler> {
+        for handler in &self.handlers {
+            if let Ok(guard) = handler.try_lock() {
+                return guard;
+            }
+        }
+        self.handlers[0].lock().await
+    }
+
+    /// Counters in the Prometheus text format, for scraping with existing monitoring
+    fn metrics(&self) -> Response {
+        let model = ModelStatus {
+            available: self.has_model,
+            daemon: DaemonFiles::new().ok().and_then(|files| files.running()),
+        };
+        Response::text(
+            200,
+            "text/plain; version=0.0.4",
+            self.metrics.render(&self.queue.stats(), &model),
+        )
+    }
+
+    fn is_authorized(&self, request: &Request) -> bool {
+        request
+            .header("authorization")
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .is_some_and(|given| AuthToken::matches(given.trim(), &self.token))
+    }
+
+    async fn suggest(&self, request: &Request, permit: &Permit) -> Result<Response> {
+        let body: SuggestRequest = match serde_json::from_slice(&request.body) {
+            Ok(body) => body,
+            Err(e) => return Ok(Response::error(400, &format!("Invalid request: {e}"))),
+        };
+
+        let mut handler = self.checkout(permit).await;
+        let options = PromptOptions {
+            no_cache: body.no_cache,
+            explain: false,
+            max_suggestions: body.max_suggestions.unwrap_or(handler.max_suggestions()),
+            verbose: false,
+            base_command: body.base_command,
+            draft: false,
+            attachments: Vec::new(),
+            page_size: None,
+            exclude: Vec::new(),
+            vary: None,
+        };
+        let use_cache = !options.no_cache;
+        let suggestions = handler.handle_prompt(&body.prompt, options).await?;
+        if use_cache {
+            let hit = suggestions
+                .iter()
+                .any(|s| s.source == SuggestionSource::Cache);
+            self.metrics.record_cache(hit);
+        }
+
+        let category = TaxonomyClassifier::classify(&body.prompt).category;
+        self.queue.record_category(category);
+
+        Ok(Response::json(
+            200,
+            &serde_json::json!({ "suggestions": suggestions, "category": category.slug() }),
+        ))
+    }
+
+    async fn explain(&self, request: &Request, permit: &Permit) -> Result<Response> {
+        let body: ExplainRequest = match serde_json::from_slice(&request.body) {
+            Ok(body) => body,
+            Err(e) => return Ok(Response::error(400, &format!("Invalid request: {e}"))),
+        };
+
+        let mut handler = self.checkout(permit).await;
+        match handler.explain_command(&body.command).await {
+            Ok(explanation) => Ok(Response::json(
+                200,
+                &serde_json::json!({ "command": body.command, "explanation": explanation }),
+            )),
+            Err(e) if !handler.has_model() => Ok(Response::error(503, &e.to_string())),
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn fix(&self, request: &Request, permit: &Permit) -> Result<Response> {
+        let body: FixRequest = match serde_json::from_slice(&request.body) {
+            Ok(body) => body,
+            Err(e) => return Ok(Response::error(400, &format!("Invalid request: {e}"))),
+        };
+
+        let mut handler = self.checkout(permit).await;
+        let suggestions = handler
+            .suggest_fixes(&body.command, body.error_output.as_deref())
+            .await?;
+
+        Ok(Response::json(
+            200,
+            &serde_json::json!({ "suggestions": suggestions }),
+        ))
+    }
+}
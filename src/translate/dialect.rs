@@ -0,0 +1,37 @@
+use crate::utils::ShellDetector;
+
+/// Shell syntax families that translation rules know about
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dialect {
+    /// bash, zsh, sh, and other Bourne-style shells
+    Posix,
+    Fish,
+    PowerShell,
+}
+
+impl Dialect {
+    /// The dialect of a shell name such as `bash`, `fish`, or `pwsh`
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "bash" | "zsh" | "sh" | "dash" | "ksh" | "posix" => Some(Self::Posix),
+            "fish" => Some(Self::Fish),
+            "powershell" | "pwsh" => Some(Self::PowerShell),
+            _ => None,
+        }
+    }
+
+    /// The dialect of the shell commandy runs in, Bourne-style when unknown
+    pub fn detect() -> (String, Self) {
+        let shell = ShellDetector::detect_shell();
+        let dialect = Self::from_name(&shell).unwrap_or(Self::Posix);
+        (shell, dialect)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Posix => "bash",
+            Self::Fish => "fish",
+            Self::PowerShell => "PowerShell",
+        }
+    }
+}
@@ -0,0 +1,5 @@
+pub mod dialect;
+pub mod rules;
+
+pub use dialect::Dialect;
+pub use rules::ShellTranslator;
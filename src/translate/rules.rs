@@ -0,0 +1,1030 @@
+use super::Dialect;
+
+/// Words that start constructs the rules leave to the model
+const UNSUPPORTED_WORDS: &[&str] = &[
+    "for", "while", "until", "if", "then", "else", "elif", "fi", "do", "done", "case", "esac",
+    "select", "function", "{", "}", "[[", "coproc", "declare", "local", "typeset", "readonly",
+    "trap", "eval",
+];
+
+/// Characters PowerShell reads literally in an unquoted argument
+const POWERSHELL_BARE: &str = "-_./:=+*?~%^\\";
+
+enum Token {
+    Word(String),
+    Operator(&'static str),
+    Comment(String),
+}
+
+/// A piece of a shell word
+enum Part {
+    /// Literal text with the quote it appeared in; `\` marks escaped characters
+    Text(String, Option<char>),
+    /// `$NAME` or `${NAME}`
+    Variable { name: String, quoted: bool },
+    /// `$?`, `$$`, `$@`, `$1`, and the other special parameters
+    Special { name: char, quoted: bool },
+    /// `$(...)` or a backquoted command
+    Substitution { command: String, quoted: bool },
+}
+
+impl Part {
+    fn double_quoted(&self) -> bool {
+        match self {
+            Part::Text(_, quote) => *quote == Some('"'),
+            Part::Variable { quoted, .. }
+            | Part::Special { quoted, .. }
+            | Part::Substitution { quoted, .. } => *quoted,
+        }
+    }
+}
+
+/// Rule-based translation of bash commands into fish and PowerShell
+///
+/// Covers variables, exports, command substitution, redirections, and, for
+/// PowerShell, the common file utilities. Loops, conditionals, heredocs,
+/// parameter expansion operators and other constructs the rules can't map
+/// safely give `None`, leaving the command to the model.
+pub struct ShellTranslator;
+
+impl ShellTranslator {
+    /// The command rewritten for `to`, or `None` when the rules don't cover it
+    pub fn translate(command: &str, from: Dialect, to: Dialect) -> Option<String> {
+        if from != Dialect::Posix || to == Dialect::Posix {
+            return None;
+        }
+        Self::translate_script(command, to)
+    }
+
+    fn translate_script(script: &str, to: Dialect) -> Option<String> {
+        let joined = script.trim().replace("\\\n", " ");
+        let lines = joined
+            .lines()
+            .map(|line| Self::translate_line(line, to))
+            .collect::<Option<Vec<_>>>()?;
+        Some(lines.join("\n"))
+    }
+
+    fn translate_line(line: &str, to: Dialect) -> Option<String> {
+        let mut pieces = Vec::new();
+        let mut words = Vec::new();
+        for token in Self::tokens(line)? {
+            match token {
+                Token::Word(word) => {
+                    words.push(word);
+                    continue;
+                }
+                Token::Operator(_) | Token::Comment(_) if !words.is_empty() => {
+                    pieces.push(Self::simple_command(&words, to)?);
+                    words.clear();
+                }
+                _ => {}
+            }
+            match token {
+                Token::Operator(op) => pieces.push(Self::operator(op, to).to_string()),
+                Token::Comment(comment) => pieces.push(comment),
+                Token::Word(_) => {}
+            }
+        }
+        if !words.is_empty() {
+            pieces.push(Self::simple_command(&words, to)?);
+        }
+
+        let mut translated = String::new();
+        for piece in pieces {
+            if !translated.is_empty() && piece != ";" {
+                translated.push(' ');
+            }
+            translated.push_str(&piece);
+        }
+        Some(translated)
+    }
+
+    /// Words, control operators and a trailing comment; `None` for subshells,
+    /// process substitution, arrays, and unbalanced quotes
+    fn tokens(line: &str) -> Option<Vec<Token>> {
+        let chars: Vec<char> = line.chars().collect();
+        let mut tokens = Vec::new();
+        let mut word = String::new();
+        let mut quote: Option<char> = None;
+        let mut depth = 0usize;
+        let mut backquoted = false;
+
+        let flush = |word: &mut String, tokens: &mut Vec<Token>| {
+            if !word.is_empty() {
+                tokens.push(Token::Word(std::mem::take(word)));
+            }
+        };
+
+        let mut i = 0;
+        while i < chars.len() {
+            let c = chars[i];
+            let next = chars.get(i + 1).copied();
+            if quote == Some('\'') {
+                word.push(c);
+                if c == '\'' {
+                    quote = None;
+                }
+                i += 1;
+                continue;
+            }
+
+            match c {
+                '\\' => {
+                    word.push(c);
+                    if let Some(next) = next {
+                        word.push(next);
+                        i += 1;
+                    }
+                }
+                '\'' if quote.is_none() => {
+                    quote = Some(c);
+                    word.push(c);
+                }
+                '"' => {
+                    quote = if quote.is_none() { Some(c) } else { None };
+                    word.push(c);
+                }
+                '`' => {
+                    backquoted = !backquoted;
+                    word.push(c);
+                }
+                '$' if next == Some('(') => {
+                    depth += 1;
+                    word.push_str("$(");
+                    i += 1;
+                }
+                '(' if depth > 0 => {
+                    depth += 1;
+                    word.push(c);
+                }
+                ')' if depth > 0 => {
+                    depth -= 1;
+                    word.push(c);
+                }
+                _ if quote.is_some() || depth > 0 || backquoted => word.push(c),
+                '(' | ')' => return None,
+                '#' if word.is_empty() => {
+                    flush(&mut word, &mut tokens);
+                    tokens.push(Token::Comment(chars[i..].iter().collect()));
+                    break;
+                }
+                c if c.is_whitespace() => flush(&mut word, &mut tokens),
+                '|' => {
+                    flush(&mut word, &mut tokens);
+                    let op = match next {
+                        Some('|') => "||",
+                        Some('&') => "|&",
+                        _ => "|",
+                    };
+                    i += op.len() - 1;
+                    tokens.push(Token::Operator(op));
+                }
+                // Part of a redirection such as `2>&1` or `&>file`
+                '&' if word.ends_with(['>', '<']) || next == Some('>') => word.push(c),
+                '&' => {
+                    flush(&mut word, &mut tokens);
+                    let op = if next == Some('&') { "&&" } else { "&" };
+                    i += op.len() - 1;
+                    tokens.push(Token::Operator(op));
+                }
+                ';' => {
+                    if next == Some(';') {
+                        return None;
+                    }
+                    flush(&mut word, &mut tokens);
+                    tokens.push(Token::Operator(";"));
+                }
+                _ => word.push(c),
+            }
+            i += 1;
+        }
+
+        if quote.is_some() || depth > 0 || backquoted {
+            return None;
+        }
+        flush(&mut word, &mut tokens);
+        Some(tokens)
+    }
+
+    fn operator(op: &'static str, to: Dialect) -> &'static str {
+        match (op, to) {
+            ("|&", Dialect::Fish) => "&|",
+            ("|&", Dialect::PowerShell) => "2>&1 |",
+            _ => op,
+        }
+    }
+
+    fn simple_command(words: &[String], to: Dialect) -> Option<String> {
+        let first = words[0].as_str();
+        let appends = first
+            .split_once("+=")
+            .is_some_and(|(name, _)| Self::is_name(name));
+        if UNSUPPORTED_WORDS.contains(&first) || appends {
+            return None;
+        }
+
+        let assignments = words
+            .iter()
+            .take_while(|word| Self::assignment(word).is_some())
+            .count();
+        match to {
+            Dialect::Fish => Self::fish_command(words, assignments),
+            Dialect::PowerShell => Self::powershell_command(words, assignments),
+            Dialect::Posix => Some(words.join(" ")),
+        }
+    }
+
+    fn fish_command(words: &[String], assignments: usize) -> Option<String> {
+        let Some(command) = words.get(assignments) else {
+            // Only assignments, which set shell variables
+            let sets = words
+                .iter()
+                .map(|word| {
+                    let (name, value) = Self::assignment(word)?;
+                    Self::fish_set("", name, Some(value))
+                })
+                .collect::<Option<Vec<_>>>()?;
+            return Some(sets.join("; "));
+        };
+        let args = &words[assignments + 1..];
+
+        if assignments == 0 {
+            match command.as_str() {
+                "export" if !args.is_empty() => {
+                    let sets = args
+                        .iter()
+                        .map(|arg| match Self::assignment(arg) {
+                            Some((name, value)) => Self::fish_set("-gx ", name, Some(value)),
+                            None if Self::is_name(arg) => Self::fish_set("-gx ", arg, None),
+                            None => None,
+                        })
+                        .collect::<Option<Vec<_>>>()?;
+                    return Some(sets.join("; "));
+                }
+                "unset" if !args.is_empty() => {
+                    let names: Vec<&str> = args
+                        .iter()
+                        .map(String::as_str)
+                        .filter(|arg| *arg != "-v")
+                        .collect();
+                    if !names.iter().all(|name| Self::is_name(name)) {
+                        return None;
+                    }
+                    return Some(format!("set -e {}", names.join(" ")));
+                }
+                _ => {}
+            }
+        }
+
+        // fish 3.1 and later take `NAME=value command` as is
+        let mut translated = Vec::with_capacity(words.len());
+        for word in &words[..assignments] {
+            let (name, value) = Self::assignment(word)?;
+            translated.push(format!("{name}={}", Self::fish_word(value)?));
+        }
+        translated.push(match command.as_str() {
+            "." => "source".to_string(),
+            _ => Self::fish_word(command)?,
+        });
+        for arg in args {
+            translated.push(Self::fish_word(arg)?);
+        }
+        Some(translated.join(" "))
+    }
+
+    /// `set` for an assignment; fish splits variables ending in PATH on `:`
+    fn fish_set(scope: &str, name: &str, value: Option<&str>) -> Option<String> {
+        let value = match value {
+            Some(value) => value.to_string(),
+            // `export NAME` exports the current value
+            None => format!("${name}"),
+        };
+        let parts = Self::parts(&value)?;
+        let values = if name.ends_with("PATH") {
+            Self::split_on_colons(parts)
+                .into_iter()
+                .map(|mut parts| {
+                    // A whole list like "$PATH" keeps its entries separate
+                    if let [Part::Variable { quoted, .. }] = parts.as_mut_slice() {
+                        *quoted = false;
+                    }
+                    Self::fish_parts(&parts)
+                })
+                .collect::<Option<Vec<_>>>()?
+        } else {
+            vec![Self::fish_parts(&parts)?]
+        };
+
+        let mut set = format!("set {scope}{name}");
+        for value in values.iter().filter(|value| !value.is_empty()) {
+            set.push(' ');
+            set.push_str(value);
+        }
+        Some(set)
+    }
+
+    fn fish_word(word: &str) -> Option<String> {
+        let (redirect, target) = Self::split_redirect(word);
+        let op = redirect.trim_start_matches(|c: char| c.is_ascii_digit());
+        if matches!(op, "<<" | "<<<" | "<>" | ">|") {
+            return None;
+        }
+        // Duplicating a descriptor, as in `2>&1`, reads the same in fish
+        if op.ends_with('&') {
+            return Some(word.to_string());
+        }
+        Some(format!(
+            "{redirect}{}",
+            Self::fish_parts(&Self::parts(target)?)?
+        ))
+    }
+
+    fn fish_parts(parts: &[Part]) -> Option<String> {
+        let mut out = String::new();
+        let mut open = false;
+        for (i, part) in parts.iter().enumerate() {
+            let quoted = part.double_quoted();
+            if quoted != open {
+                out.push('"');
+                open = quoted;
+            }
+            // A name running into the text after it needs a separator in fish
+            let runs_on = parts.get(i + 1).is_some_and(|next| {
+                next.double_quoted() == quoted
+                    && matches!(next, Part::Text(text, _) if text.starts_with(|c: char| c.is_ascii_alphanumeric() || c == '_'))
+            });
+
+            match part {
+                Part::Text(text, None) => {
+                    // Sequence expressions like {1..5}
+                    if text.contains('{') && text.contains("..") {
+                        return None;
+                    }
+                    out.push_str(text);
+                }
+                Part::Text(text, Some('\\')) => {
+                    for c in text.chars() {
+                        // fish reads `\n`, `\t` and friends as escapes
+                        if !c.is_ascii_alphanumeric() {
+                            out.push('\\');
+                        }
+                        out.push(c);
+                    }
+                }
+                Part::Text(text, Some('\'')) => {
+                    let escaped = text.replace('\\', "\\\\").replace('\'', "\\'");
+                    out.push_str(&format!("'{escaped}'"));
+                }
+                Part::Text(text, _) => {
+                    let escaped = text
+                        .replace('\\', "\\\\")
+                        .replace('"', "\\\"")
+                        .replace('$', "\\$");
+                    out.push_str(&escaped);
+                }
+                Part::Variable { name, quoted } => match (runs_on, quoted) {
+                    (true, false) => out.push_str(&format!("{{${name}}}")),
+                    (true, true) => out.push_str(&format!("${name}\"\"")),
+                    _ => out.push_str(&format!("${name}")),
+                },
+                Part::Special { name, quoted } => {
+                    out.push_str(&match name {
+                        '?' => "$status".to_string(),
+                        '$' => "$fish_pid".to_string(),
+                        '!' => "$last_pid".to_string(),
+                        '@' | '*' => "$argv".to_string(),
+                        '#' if *quoted => "$(count $argv)".to_string(),
+                        '#' => "(count $argv)".to_string(),
+                        '1'..='9' => format!("$argv[{name}]"),
+                        _ => return None,
+                    });
+                }
+                Part::Substitution { command, quoted } => {
+                    let inner = Self::translate_script(command, Dialect::Fish)?;
+                    // fish 3.4 and later expand `$(...)` inside double quotes
+                    match quoted {
+                        true => out.push_str(&format!("$({inner})")),
+                        false => out.push_str(&format!("({inner})")),
+                    }
+                }
+            }
+        }
+        if open {
+            out.push('"');
+        }
+        Some(out)
+    }
+
+    fn powershell_command(words: &[String], assignments: usize) -> Option<String> {
+        let Some(command) = words.get(assignments) else {
+            let sets = words
+                .iter()
+                .map(|word| {
+                    let (name, value) = Self::assignment(word)?;
+                    Self::powershell_set(name, value)
+                })
+                .collect::<Option<Vec<_>>>()?;
+            return Some(sets.join("; "));
+        };
+        // PowerShell has no per-command environment prefix
+        if assignments > 0 {
+            return None;
+        }
+        let args = &words[1..];
+
+        match command.as_str() {
+            "export" if !args.is_empty() => {
+                let sets = args
+                    .iter()
+                    .map(|arg| {
+                        let (name, value) = Self::assignment(arg)?;
+                        Self::powershell_set(name, value)
+                    })
+                    .collect::<Option<Vec<_>>>()?;
+                return Some(sets.join("; "));
+            }
+            "unset" if !args.is_empty() => {
+                let removes = args
+                    .iter()
+                    .filter(|arg| *arg != "-v")
+                    .map(|name| Self::is_name(name).then(|| format!("Remove-Item Env:{name}")))
+                    .collect::<Option<Vec<_>>>()?;
+                return Some(removes.join("; "));
+            }
+            "sudo" | "source" | "." | "exec" | "export" | "unset" => return None,
+            _ => {}
+        }
+
+        let mut translated = Vec::with_capacity(words.len());
+        let mut redirects = Vec::new();
+        let mut iter = words.iter();
+        while let Some(word) = iter.next() {
+            let (redirect, target) = Self::split_redirect(word);
+            if redirect.is_empty() {
+                translated.push(Self::powershell_word(word, false)?);
+                continue;
+            }
+            let target = match target {
+                "" => iter.next()?.as_str(),
+                target => target,
+            };
+            redirects.push(Self::powershell_redirect(redirect, target)?);
+        }
+
+        let mut translated = Self::powershell_cmdlet(translated)?;
+        translated.extend(redirects);
+        Some(translated.join(" "))
+    }
+
+    /// `$env:NAME = value`; bash variables become environment variables
+    fn powershell_set(name: &str, value: &str) -> Option<String> {
+        let parts = Self::parts(value)?;
+        if !name.ends_with("PATH") {
+            return Some(format!(
+                "$env:{name} = {}",
+                Self::powershell_parts(&parts, true)?
+            ));
+        }
+
+        // Join path lists with the separator of the machine it runs on
+        let entries = Self::split_on_colons(parts)
+            .iter()
+            .filter(|parts| !parts.is_empty())
+            .map(|parts| Self::powershell_parts(parts, true))
+            .collect::<Option<Vec<_>>>()?;
+        Some(format!(
+            "$env:{name} = {}",
+            entries.join(" + [IO.Path]::PathSeparator + ")
+        ))
+    }
+
+    fn powershell_redirect(redirect: &str, target: &str) -> Option<String> {
+        let op = redirect.trim_start_matches(|c: char| c.is_ascii_digit());
+        let redirect = match op {
+            ">" | ">>" => redirect.to_string(),
+            "&>" => "*>".to_string(),
+            "&>>" => "*>>".to_string(),
+            ">&" if redirect == "2>&" && target == "1" => return Some("2>&1".to_string()),
+            _ => return None,
+        };
+        let target = match target {
+            "/dev/null" => "$null".to_string(),
+            target => Self::powershell_word(target, false)?,
+        };
+        Some(format!("{redirect}{target}"))
+    }
+
+    fn powershell_word(word: &str, expression: bool) -> Option<String> {
+        Self::powershell_parts(&Self::parts(word)?, expression)
+    }
+
+    /// A word as a PowerShell argument, or as a value when `expression` is set
+    fn powershell_parts(parts: &[Part], expression: bool) -> Option<String> {
+        let expansions = parts
+            .iter()
+            .filter(|part| !matches!(part, Part::Text(..)))
+            .count();
+        if let ([part], 1) = (parts, expansions) {
+            return Self::powershell_expansion(part);
+        }
+        // PowerShell has no brace expansion
+        let braces = parts.iter().any(|part| {
+            matches!(part, Part::Text(text, None) if text.contains('{') && text.contains([',', '.']))
+        });
+        if braces {
+            return None;
+        }
+
+        if expansions == 0 {
+            let text: String = parts
+                .iter()
+                .filter_map(|part| match part {
+                    Part::Text(text, _) => Some(text.as_str()),
+                    _ => None,
+                })
+                .collect();
+            let bare = !expression
+                && !text.is_empty()
+                && parts.iter().all(|part| matches!(part, Part::Text(_, None)))
+                && text
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || POWERSHELL_BARE.contains(c));
+            return Some(match bare {
+                true => text,
+                false => format!("'{}'", text.replace('\'', "''")),
+            });
+        }
+
+        let mut out = String::from("\"");
+        for part in parts {
+            match part {
+                Part::Text(text, _) => {
+                    for c in text.chars() {
+                        if matches!(c, '`' | '"' | '$') {
+                            out.push('`');
+                        }
+                        out.push(c);
+                    }
+                }
+                Part::Variable { name, .. } => out.push_str(&format!("${{env:{name}}}")),
+                Part::Special { .. } => {
+                    out.push_str(&format!("$({})", Self::powershell_expansion(part)?))
+                }
+                Part::Substitution { .. } => out.push_str(&Self::powershell_expansion(part)?),
+            }
+        }
+        out.push('"');
+        Some(out)
+    }
+
+    fn powershell_expansion(part: &Part) -> Option<String> {
+        Some(match part {
+            Part::Variable { name, .. } => format!("$env:{name}"),
+            Part::Special { name, .. } => match name {
+                '?' => "$LASTEXITCODE".to_string(),
+                '$' => "$PID".to_string(),
+                '@' | '*' => "$args".to_string(),
+                '#' => "$args.Count".to_string(),
+                '1'..='9' => format!("$args[{}]", *name as u8 - b'1'),
+                _ => return None,
+            },
+            Part::Substitution { command, .. } => {
+                format!(
+                    "$({})",
+                    Self::translate_script(command, Dialect::PowerShell)?
+                )
+            }
+            Part::Text(..) => return None,
+        })
+    }
+
+    /// Common file utilities as cmdlets, so they also work on Windows
+    ///
+    /// Other commands are left alone; a known utility with flags the table
+    /// doesn't cover gives `None`.
+    fn powershell_cmdlet(words: Vec<String>) -> Option<Vec<String>> {
+        let Some((command, args)) = words.split_first() else {
+            return Some(words);
+        };
+        let (flags, operands): (Vec<&String>, Vec<&String>) = args
+            .iter()
+            .partition(|arg| arg.starts_with('-') && arg.len() > 1);
+        // Letters of the short flags, if they're all among `allowed`
+        let letters = |allowed: &str| -> Option<String> {
+            let mut letters = String::new();
+            for flag in &flags {
+                let flag = flag.strip_prefix('-')?;
+                if !flag.chars().all(|c| allowed.contains(c)) {
+                    return None;
+                }
+                letters.push_str(flag);
+            }
+            Some(letters)
+        };
+        let paths = || {
+            operands
+                .iter()
+                .map(|operand| operand.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        let cmdlet = |name: &str, switches: &[(&str, bool)], rest: Vec<String>| {
+            let mut cmdlet = vec![name.to_string()];
+            for (switch, on) in switches {
+                if *on {
+                    cmdlet.push(switch.to_string());
+                }
+            }
+            cmdlet.extend(rest.into_iter().filter(|arg| !arg.is_empty()));
+            cmdlet
+        };
+
+        Some(match command.as_str() {
+            "ls" => {
+                let letters = letters("laA1hR")?;
+                let hidden = letters.contains(['a', 'A']);
+                let switches = [("-Recurse", letters.contains('R')), ("-Force", hidden)];
+                cmdlet("Get-ChildItem", &switches, vec![paths()])
+            }
+            "cat" if flags.is_empty() && !operands.is_empty() => {
+                cmdlet("Get-Content", &[], vec![paths()])
+            }
+            "rm" if !operands.is_empty() => {
+                let letters = letters("rRf")?;
+                let recurse = letters.contains(['r', 'R']);
+                let switches = [("-Recurse", recurse), ("-Force", letters.contains('f'))];
+                cmdlet("Remove-Item", &switches, vec![paths()])
+            }
+            "cp" if operands.len() == 2 => {
+                let recurse = letters("rR")?.contains(['r', 'R']);
+                let rest = operands.iter().map(|operand| operand.to_string()).collect();
+                cmdlet("Copy-Item", &[("-Recurse", recurse)], rest)
+            }
+            "mv" if flags.is_empty() && operands.len() == 2 => {
+                let rest = operands.iter().map(|operand| operand.to_string()).collect();
+                cmdlet("Move-Item", &[], rest)
+            }
+            "mkdir" if !operands.is_empty() => {
+                let parents = letters("p")?.contains('p');
+                let switches = [("-ItemType Directory", true), ("-Force", parents)];
+                cmdlet("New-Item", &switches, vec![paths()])
+            }
+            "which" if flags.is_empty() && operands.len() == 1 => {
+                cmdlet("Get-Command", &[], vec![paths()])
+            }
+            "sleep" if flags.is_empty() && operands.len() == 1 => {
+                cmdlet("Start-Sleep", &[], vec![paths()])
+            }
+            "grep" if matches!(operands.len(), 1 | 2) => {
+                let letters = letters("iv")?;
+                let switches = [
+                    ("-CaseSensitive", !letters.contains('i')),
+                    ("-NotMatch", letters.contains('v')),
+                ];
+                let mut rest = vec![operands[0].to_string()];
+                if let Some(path) = operands.get(1) {
+                    rest.push(format!("-Path {path}"));
+                }
+                cmdlet("Select-String", &switches, rest)
+            }
+            "head" | "tail" => {
+                let (count, path) = match args {
+                    [] => ("10", None),
+                    [path] if !path.starts_with('-') => ("10", Some(path)),
+                    [flag, count] if flag == "-n" => (count.as_str(), None),
+                    [flag, count, path] if flag == "-n" => (count.as_str(), Some(path)),
+                    [flag] => (flag.strip_prefix('-')?, None),
+                    [flag, path] => (flag.strip_prefix('-')?, Some(path)),
+                    _ => return None,
+                };
+                if !count.chars().all(|c| c.is_ascii_digit()) {
+                    return None;
+                }
+                let head = command == "head";
+                match path {
+                    Some(path) => {
+                        let limit = if head { "-TotalCount" } else { "-Tail" };
+                        vec![
+                            "Get-Content".to_string(),
+                            path.to_string(),
+                            limit.to_string(),
+                            count.to_string(),
+                        ]
+                    }
+                    None => {
+                        let limit = if head { "-First" } else { "-Last" };
+                        vec![
+                            "Select-Object".to_string(),
+                            limit.to_string(),
+                            count.to_string(),
+                        ]
+                    }
+                }
+            }
+            "wc" if flags.len() == 1 && flags[0] == "-l" && operands.len() <= 1 => {
+                let measure = "Measure-Object -Line".to_string();
+                match operands.first() {
+                    Some(path) => vec![format!("Get-Content {path} |"), measure],
+                    None => vec![measure],
+                }
+            }
+            "echo" if flags.is_empty() => words,
+            "echo" | "cat" | "rm" | "cp" | "mv" | "mkdir" | "which" | "sleep" | "grep" | "wc" => {
+                return None
+            }
+            _ => words,
+        })
+    }
+
+    /// Parts split into separate values at unquoted or double-quoted `:`
+    fn split_on_colons(parts: Vec<Part>) -> Vec<Vec<Part>> {
+        let mut values = vec![Vec::new()];
+        for part in parts {
+            match part {
+                Part::Text(text, quote @ (None | Some('"'))) if text.contains(':') => {
+                    let mut pieces = text.split(':').peekable();
+                    while let Some(piece) = pieces.next() {
+                        if !piece.is_empty() {
+                            values
+                                .last_mut()
+                                .unwrap()
+                                .push(Part::Text(piece.to_string(), quote));
+                        }
+                        if pieces.peek().is_some() {
+                            values.push(Vec::new());
+                        }
+                    }
+                }
+                part => values.last_mut().unwrap().push(part),
+            }
+        }
+        values
+    }
+
+    /// The parts of a bash word; `None` for expansions the rules don't map,
+    /// such as `${NAME:-default}` or `$((1 + 2))`
+    fn parts(word: &str) -> Option<Vec<Part>> {
+        let chars: Vec<char> = word.chars().collect();
+        let mut parts = Vec::new();
+        let push = |parts: &mut Vec<Part>, c: char, quote: Option<char>| {
+            if let Some(Part::Text(text, q)) = parts.last_mut() {
+                if *q == quote {
+                    text.push(c);
+                    return;
+                }
+            }
+            parts.push(Part::Text(c.to_string(), quote));
+        };
+
+        let mut in_double = false;
+        let mut opened_at = 0;
+        let mut i = 0;
+        while i < chars.len() {
+            let c = chars[i];
+            let quote = in_double.then_some('"');
+            match c {
+                '\'' if !in_double => {
+                    let end = i + 1 + chars[i + 1..].iter().position(|&d| d == '\'')?;
+                    if end == i + 1 {
+                        parts.push(Part::Text(String::new(), Some('\'')));
+                    }
+                    for &d in &chars[i + 1..end] {
+                        push(&mut parts, d, Some('\''));
+                    }
+                    i = end;
+                }
+                '"' => {
+                    if in_double && opened_at == parts.len() {
+                        parts.push(Part::Text(String::new(), Some('"')));
+                    }
+                    in_double = !in_double;
+                    opened_at = parts.len();
+                }
+                '\\' => {
+                    let next = *chars.get(i + 1)?;
+                    match in_double {
+                        true if matches!(next, '"' | '$' | '`' | '\\') => {
+                            push(&mut parts, next, quote)
+                        }
+                        true => {
+                            push(&mut parts, c, quote);
+                            push(&mut parts, next, quote);
+                        }
+                        false => push(&mut parts, next, Some('\\')),
+                    }
+                    i += 1;
+                }
+                '`' => {
+                    let end = i + 1 + chars[i + 1..].iter().position(|&d| d == '`')?;
+                    parts.push(Part::Substitution {
+                        command: chars[i + 1..end].iter().collect(),
+                        quoted: in_double,
+                    });
+                    i = end;
+                }
+                '$' => match chars.get(i + 1).copied() {
+                    Some('(') => {
+                        if chars.get(i + 2) == Some(&'(') {
+                            return None;
+                        }
+                        let end = Self::closing_paren(&chars, i + 2)?;
+                        parts.push(Part::Substitution {
+                            command: chars[i + 2..end].iter().collect(),
+                            quoted: in_double,
+                        });
+                        i = end;
+                    }
+                    Some('{') => {
+                        let end = i + 2 + chars[i + 2..].iter().position(|&d| d == '}')?;
+                        let name: String = chars[i + 2..end].iter().collect();
+                        if !Self::is_name(&name) {
+                            return None;
+                        }
+                        parts.push(Part::Variable {
+                            name,
+                            quoted: in_double,
+                        });
+                        i = end;
+                    }
+                    Some(n) if n == '_' || n.is_ascii_alphabetic() => {
+                        let name: String = chars[i + 1..]
+                            .iter()
+                            .take_while(|c| c.is_ascii_alphanumeric() || **c == '_')
+                            .collect();
+                        i += name.len();
+                        parts.push(Part::Variable {
+                            name,
+                            quoted: in_double,
+                        });
+                    }
+                    Some(n)
+                        if n.is_ascii_digit() || matches!(n, '?' | '$' | '@' | '*' | '#' | '!') =>
+                    {
+                        parts.push(Part::Special {
+                            name: n,
+                            quoted: in_double,
+                        });
+                        i += 1;
+                    }
+                    // $'...' and $"..." quoting
+                    Some('\'' | '"') if !in_double => return None,
+                    _ => push(&mut parts, c, quote),
+                },
+                _ => push(&mut parts, c, quote),
+            }
+            i += 1;
+        }
+
+        (!in_double).then_some(parts)
+    }
+
+    /// Index of the `)` closing a substitution whose body starts at `start`
+    fn closing_paren(chars: &[char], start: usize) -> Option<usize> {
+        let mut depth = 1;
+        let mut quote: Option<char> = None;
+        let mut escaped = false;
+        for (i, &c) in chars.iter().enumerate().skip(start) {
+            if escaped {
+                escaped = false;
+                continue;
+            }
+            match (c, quote) {
+                ('\\', q) if q != Some('\'') => escaped = true,
+                ('\'' | '"', None) => quote = Some(c),
+                (c, Some(q)) if c == q => quote = None,
+                ('(', None) => depth += 1,
+                (')', None) => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Some(i);
+                    }
+                }
+                _ => {}
+            }
+        }
+        None
+    }
+
+    /// Redirection operator at the start of a word, and what it applies to
+    fn split_redirect(word: &str) -> (&str, &str) {
+        let digits = word.len() - word.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+        let rest = &word[digits..];
+        for op in [
+            "&>>", "&>", ">>", ">&", ">|", "<<<", "<<", "<>", "<&", ">", "<",
+        ] {
+            if rest.starts_with(op) {
+                return word.split_at(digits + op.len());
+            }
+        }
+        ("", word)
+    }
+
+    fn assignment(word: &str) -> Option<(&str, &str)> {
+        word.split_once('=').filter(|(name, _)| Self::is_name(name))
+    }
+
+    fn is_name(name: &str) -> bool {
+        name.starts_with(|c: char| c == '_' || c.is_ascii_alphabetic())
+            && name.chars().all(|c| c == '_' || c.is_ascii_alphanumeric())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fish(command: &str) -> Option<String> {
+        ShellTranslator::translate(command, Dialect::Posix, Dialect::Fish)
+    }
+
+    fn powershell(command: &str) -> Option<String> {
+        ShellTranslator::translate(command, Dialect::Posix, Dialect::PowerShell)
+    }
+
+    #[test]
+    fn variables_and_exports() {
+        assert_eq!(
+            fish("export PATH=$HOME/bin:$PATH").as_deref(),
+            Some("set -gx PATH $HOME/bin $PATH")
+        );
+        assert_eq!(fish("unset FOO").as_deref(), Some("set -e FOO"));
+        assert_eq!(fish("echo $?").as_deref(), Some("echo $status"));
+        assert_eq!(
+            powershell("export PATH=$HOME/bin:$PATH").as_deref(),
+            Some(r#"$env:PATH = "${env:HOME}/bin" + [IO.Path]::PathSeparator + $env:PATH"#)
+        );
+        assert_eq!(
+            powershell("unset FOO").as_deref(),
+            Some("Remove-Item Env:FOO")
+        );
+        assert_eq!(powershell("echo $?").as_deref(), Some("echo $LASTEXITCODE"));
+    }
+
+    #[test]
+    fn substitution_and_quoting() {
+        assert_eq!(
+            fish(r#"echo "$HOME" $(date +%F)"#).as_deref(),
+            Some(r#"echo "$HOME" (date +%F)"#)
+        );
+        assert_eq!(fish("echo 'it''s'").as_deref(), Some("echo 'its'"));
+        assert_eq!(fish("echo hi # note").as_deref(), Some("echo hi # note"));
+    }
+
+    #[test]
+    fn pipelines_lists_and_redirections() {
+        assert_eq!(
+            fish("cat a.txt | grep -i error && echo ok").as_deref(),
+            Some("cat a.txt | grep -i error && echo ok")
+        );
+        assert_eq!(
+            powershell("cat a.txt | grep -i error && echo ok").as_deref(),
+            Some("Get-Content a.txt | Select-String error && echo ok")
+        );
+        assert_eq!(
+            powershell("ls -la > out.txt 2>&1").as_deref(),
+            Some("Get-ChildItem -Force >out.txt 2>&1")
+        );
+        assert_eq!(
+            powershell("cd /tmp; ls").as_deref(),
+            Some("cd /tmp; Get-ChildItem")
+        );
+    }
+
+    #[test]
+    fn powershell_file_utilities() {
+        assert_eq!(
+            powershell("rm -rf build").as_deref(),
+            Some("Remove-Item -Recurse -Force build")
+        );
+        assert_eq!(
+            powershell("mkdir -p a/b").as_deref(),
+            Some("New-Item -ItemType Directory -Force a/b")
+        );
+        assert_eq!(
+            powershell("cp -r src dst").as_deref(),
+            Some("Copy-Item -Recurse src dst")
+        );
+        assert_eq!(powershell("which git").as_deref(), Some("Get-Command git"));
+        assert_eq!(
+            powershell("grep foo *.log | wc -l").as_deref(),
+            Some("Select-String -CaseSensitive foo -Path *.log | Measure-Object -Line")
+        );
+    }
+
+    #[test]
+    fn constructs_left_to_the_model() {
+        assert_eq!(fish("for f in *; do echo $f; done"), None);
+        assert_eq!(fish("echo ${VAR:-x}"), None);
+        assert_eq!(powershell("echo ${VAR:-x}"), None);
+        assert_eq!(powershell("A=1 B=2 make"), None);
+        assert_eq!(
+            ShellTranslator::translate("set -gx A 1", Dialect::Fish, Dialect::PowerShell),
+            None
+        );
+        assert_eq!(
+            ShellTranslator::translate("ls", Dialect::Posix, Dialect::Posix),
+            None
+        );
+    }
+}
@@ -0,0 +1,486 @@
+use anyhow::{bail, Context, Result};
+use regex::Regex;
+use std::fs;
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use tracing::{debug, info};
+
+use crate::ai::{DeviceProbe, GpuBackend};
+use crate::utils::http::HttpClient;
+
+const LLAMA_CPP_REPO: &str = "ggml-org/llama.cpp";
+
+/// llama.cpp release the published flavors are downloaded from, as in install.sh
+const LLAMA_CPP_BUILD: &str = "b6265";
+
+/// Name of the llama.cpp binary inside each flavor's directory
+const BINARY_NAME: &str = if cfg!(windows) {
+    "llama-cpp.exe"
+} else {
+    "llama-cpp"
+};
+
+/// llama-server, kept next to the flavor's binary for `commandy daemon`
+const SERVER_NAME: &str = if cfg!(windows) {
+    "llama-server.exe"
+} else {
+    "llama-server"
+};
+
+/// Lines of build output kept to explain a failed build
+const BUILD_LOG_TAIL: usize = 20;
+
+/// Where the CLI sits in a llama.cpp release archive, newest layout first
+const ARCHIVE_BINARIES: &[&str] = &["build/bin/llama-cli", "llama-cli", "build/bin/main", "main"];
+
+/// A llama.cpp build, installed side by side with the others in `~/.commandy/bin/<flavor>`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flavor {
+    Cpu,
+    Avx2,
+    Cuda,
+    Metal,
+    Vulkan,
+}
+
+impl Flavor {
+    /// Fastest first
+    pub const ALL: [Self; 5] = [Self::Cuda, Self::Metal, Self::Vulkan, Self::Avx2, Self::Cpu];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Cpu => "cpu",
+            Self::Avx2 => "avx2",
+            Self::Cuda => "cuda",
+            Self::Metal => "metal",
+            Self::Vulkan => "vulkan",
+        }
+    }
+
+    pub fn parse(name: &str) -> Result<Self> {
+        let name = name.trim().to_lowercase();
+        Self::ALL
+            .into_iter()
+            .find(|f| f.as_str() == name)
+            .with_context(|| {
+                format!("Unknown llama.cpp flavor '{name}' (expected cpu, avx2, cuda, metal, or vulkan)")
+            })
+    }
+
+    /// What upstream publishes for this machine: Metal on Apple silicon, the CPU build elsewhere
+    pub fn default_for_platform() -> Self {
+        match (std::env::consts::OS, std::env::consts::ARCH) {
+            ("macos", "aarch64") => Self::Metal,
+            _ => Self::Cpu,
+        }
+    }
+
+    /// The GPU a flavor needs to be any faster than the CPU build
+    fn gpu(self) -> Option<GpuBackend> {
+        match self {
+            Self::Cuda => Some(GpuBackend::Cuda),
+            Self::Metal => Some(GpuBackend::Metal),
+            Self::Vulkan => Some(GpuBackend::Vulkan),
+            Self::Cpu | Self::Avx2 => None,
+        }
+    }
+
+    /// CMake options that select the flavor's backend; curl is on so `-hf` can download models
+    fn cmake_flags(self) -> &'static [&'static str] {
+        match self {
+            Self::Cpu => &["-DGGML_NATIVE=ON"],
+            Self::Avx2 => &[
+                "-DGGML_NATIVE=OFF",
+                "-DGGML_AVX=ON",
+                "-DGGML_AVX2=ON",
+                "-DGGML_FMA=ON",
+                "-DGGML_F16C=ON",
+            ],
+            Self::Cuda => &["-DGGML_CUDA=ON"],
+            Self::Metal => &["-DGGML_METAL=ON"],
+            Self::Vulkan => &["-DGGML_VULKAN=ON"],
+        }
+    }
+
+    /// The llama.cpp release archive for this flavor and platform, when upstream publishes one
+    fn asset(self) -> Option<String> {
+        let platform = match (self, std::env::consts::OS, std::env::consts::ARCH) {
+            (Self::Metal, "macos", "aarch64") => "macos-arm64",
+            (Self::Cpu, "macos", "x86_64") => "macos-x64",
+            (Self::Cpu, "linux", "x86_64") => "ubuntu-x64",
+            (Self::Vulkan, "linux", "x86_64") => "ubuntu-vulkan-x64",
+            _ => return None,
+        };
+        Some(format!("llama-{LLAMA_CPP_BUILD}-bin-{platform}.zip"))
+    }
+}
+
+/// Installs llama.cpp builds into `~/.commandy/bin/<flavor>` and picks between them
+pub struct LlamaInstaller {
+    bin_dir: PathBuf,
+    /// llama.cpp checkout that builds from source reuse
+    source_dir: PathBuf,
+}
+
+impl LlamaInstaller {
+    pub fn new() -> Result<Self> {
+        let home = dirs::home_dir().context("Could not find home directory")?;
+        let commandy_dir = home.join(".commandy");
+        Ok(Self {
+            bin_dir: commandy_dir.join("bin"),
+            source_dir: commandy_dir.join("src").join("llama.cpp"),
+        })
+    }
+
+    pub fn dir(&self, flavor: Flavor) -> PathBuf {
+        self.bin_dir.join(flavor.as_str())
+    }
+
+    pub fn binary(&self, flavor: Flavor) -> PathBuf {
+        self.dir(flavor).join(BINARY_NAME)
+    }
+
+    /// Flavors with a binary in place, fastest first
+    pub fn installed(&self) -> Vec<Flavor> {
+        Flavor::ALL
+            .into_iter()
+            .filter(|f| self.binary(*f).exists())
+            .collect()
+    }
+
+    /// Downloads the published build of `flavor` and returns its version line
+    ///
+    /// The new build is unpacked and run next to the old one, which is only
+    /// replaced once the new binary answers `--version`.
+    pub fn install(&self, flavor: Flavor) -> Result<String> {
+        let Some(asset) = flavor.asset() else {
+            bail!(
+                "llama.cpp publishes no {} build for {}-{}; add --build to compile it from source, \
+                or put your own llama-cli at {}",
+                flavor.as_str(),
+                std::env::consts::OS,
+                std::env::consts::ARCH,
+                self.binary(flavor).display()
+            );
+        };
+        let url = format!(
+            "https://github.com/{LLAMA_CPP_REPO}/releases/download/{LLAMA_CPP_BUILD}/{asset}"
+        );
+
+        let version = self.put_in_place(flavor, |staging| self.unpack(&url, staging))?;
+        info!("Installed llama.cpp {} build: {version}", flavor.as_str());
+        Ok(version)
+    }
+
+    /// Compiles `flavor` from the llama.cpp release source and installs it
+    ///
+    /// The checkout in ~/.commandy/src/llama.cpp and its build directory per
+    /// flavor are kept, so building again only recompiles what changed.
+    /// `progress` hears each step and how far compilation is, in 10% steps.
+    pub fn build(&self, flavor: Flavor, progress: &mut dyn FnMut(&str)) -> Result<String> {
+        for tool in ["git", "cmake"] {
+            if which::which(tool).is_err() {
+                bail!("Building llama.cpp needs {tool}, a C++ compiler, and the libcurl headers");
+            }
+        }
+
+        if self.source_dir.join(".git").exists() {
+            let checked_out =
+                Self::run_quiet(Command::new("git").arg("-C").arg(&self.source_dir).args([
+                    "checkout",
+                    "--quiet",
+                    LLAMA_CPP_BUILD,
+                ]));
+            if checked_out.is_err() {
+                progress(&format!("Fetching llama.cpp {LLAMA_CPP_BUILD}..."));
+                Self::run_quiet(
+                    Command::new("git")
+                        .arg("-C")
+                        .arg(&self.source_dir)
+                        .args(["fetch", "--quiet", "--depth", "1", "origin", "tag"])
+                        .arg(LLAMA_CPP_BUILD),
+                )?;
+                Self::run_quiet(Command::new("git").arg("-C").arg(&self.source_dir).args([
+                    "checkout",
+                    "--quiet",
+                    LLAMA_CPP_BUILD,
+                ]))?;
+            }
+        } else {
+            progress(&format!("Cloning llama.cpp {LLAMA_CPP_BUILD}..."));
+            if let Some(parent) = self.source_dir.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            Self::run_quiet(
+                Command::new("git")
+                    .args([
+                        "clone",
+                        "--quiet",
+                        "--depth",
+                        "1",
+                        "--branch",
+                        LLAMA_CPP_BUILD,
+                    ])
+                    .arg(format!("https://github.com/{LLAMA_CPP_REPO}"))
+                    .arg(&self.source_dir),
+            )?;
+        }
+
+        let build_dir = self.source_dir.join(format!("build-{}", flavor.as_str()));
+        progress(&format!("Configuring the {} build...", flavor.as_str()));
+        Self::run_quiet(
+            Command::new("cmake")
+                .arg("-S")
+                .arg(&self.source_dir)
+                .arg("-B")
+                .arg(&build_dir)
+                .args([
+                    "-DCMAKE_BUILD_TYPE=Release",
+                    "-DBUILD_SHARED_LIBS=OFF",
+                    "-DLLAMA_CURL=ON",
+                    "-DLLAMA_BUILD_TESTS=OFF",
+                ])
+                .args(flavor.cmake_flags()),
+        )?;
+
+        let jobs = std::thread::available_parallelism().map_or(2, |n| n.get());
+        progress(&format!("Building llama.cpp {}...", flavor.as_str()));
+        Self::compile(&build_dir, jobs, flavor, progress)?;
+
+        let built = [
+            "bin/llama-cli",
+            "bin/Release/llama-cli.exe",
+            "bin/llama-cli.exe",
+        ]
+        .iter()
+        .map(|name| build_dir.join(name))
+        .find(|path| path.is_file())
+        .context("The build finished but produced no llama-cli")?;
+        let version = self.put_in_place(flavor, |staging| {
+            fs::create_dir_all(staging)?;
+            fs::copy(&built, staging.join(BINARY_NAME))?;
+            let server = built.with_file_name(SERVER_NAME);
+            if server.is_file() {
+                fs::copy(&server, staging.join(SERVER_NAME))?;
+            }
+            Ok(())
+        })?;
+        info!("Built llama.cpp {} build: {version}", flavor.as_str());
+        Ok(version)
+    }
+
+    /// First line of the binary's `--version`, or why it doesn't run
+    pub fn verify(binary: &Path) -> Result<String> {
+        let output = Command::new(binary)
+            .arg("--version")
+            .output()
+            .with_context(|| format!("Failed to execute {}", binary.display()))?;
+        let text = format!(
+            "{}{}",
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        );
+        if !output.status.success() {
+            bail!("{} --version failed: {}", binary.display(), text.trim());
+        }
+        Ok(text
+            .lines()
+            .find(|line| line.starts_with("version"))
+            .or_else(|| text.lines().next())
+            .unwrap_or("unknown version")
+            .trim()
+            .to_string())
+    }
+
+    /// The fastest installed flavor that runs here: GPU builds only count when
+    /// they find a GPU of their kind
+    pub fn fastest(&self) -> Option<Flavor> {
+        self.installed().into_iter().find(|flavor| {
+            let binary = self.binary(*flavor);
+            if let Err(e) = Self::verify(&binary) {
+                debug!("Skipping llama.cpp {} build: {e}", flavor.as_str());
+                return false;
+            }
+            match flavor.gpu() {
+                Some(backend) => DeviceProbe::run(&binary).is_ok_and(|probe| {
+                    !probe.devices.is_empty() && probe.backends.contains(&backend)
+                }),
+                None => true,
+            }
+        })
+    }
+
+    /// Fills a staging directory with `fill`, checks its binary runs, and only
+    /// then replaces ~/.commandy/bin/<flavor> with it
+    fn put_in_place(
+        &self,
+        flavor: Flavor,
+        fill: impl FnOnce(&Path) -> Result<()>,
+    ) -> Result<String> {
+        fs::create_dir_all(&self.bin_dir)
+            .with_context(|| format!("Failed to create {}", self.bin_dir.display()))?;
+        let staging = self
+            .bin_dir
+            .join(format!(".{}-{}", flavor.as_str(), std::process::id()));
+        let result = fill(&staging).and_then(|_| {
+            let version = Self::verify(&staging.join(BINARY_NAME))?;
+            let target = self.dir(flavor);
+            if target.exists() {
+                fs::remove_dir_all(&target)
+                    .with_context(|| format!("Failed to remove {}", target.display()))?;
+            }
+            fs::rename(&staging, &target)
+                .with_context(|| format!("Failed to move the build to {}", target.display()))?;
+            Ok(version)
+        });
+        if staging.exists() {
+            let _ = fs::remove_dir_all(&staging);
+        }
+        result
+    }
+
+    /// Runs the build, reporting cmake's `[ 42%]` progress as it streams past
+    fn compile(
+        build_dir: &Path,
+        jobs: usize,
+        flavor: Flavor,
+        progress: &mut dyn FnMut(&str),
+    ) -> Result<()> {
+        let mut child = Command::new("cmake")
+            .arg("--build")
+            .arg(build_dir)
+            .args([
+                "--config",
+                "Release",
+                "--target",
+                "llama-cli",
+                "llama-server",
+                "-j",
+            ])
+            .arg(jobs.to_string())
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .context("Failed to execute cmake")?;
+
+        // Drained on its own thread so a chatty compiler can't fill the pipe and stall the build
+        let mut stderr_pipe = child.stderr.take().context("cmake stderr not captured")?;
+        let stderr = std::thread::spawn(move || {
+            let mut text = String::new();
+            let _ = stderr_pipe.read_to_string(&mut text);
+            text
+        });
+
+        let percent = Regex::new(r"^\[\s*(\d+)%\]").expect("valid regex");
+        let mut reported = None;
+        let mut tail: Vec<String> = Vec::new();
+        let stdout = child.stdout.take().context("cmake stdout not captured")?;
+        for line in BufReader::new(stdout).lines().map_while(|line| line.ok()) {
+            if let Some(done) = percent
+                .captures(&line)
+                .and_then(|c| c[1].parse::<u32>().ok())
+            {
+                let step = done / 10 * 10;
+                if reported != Some(step) {
+                    reported = Some(step);
+                    progress(&format!(
+                        "Building llama.cpp {} ({step}%)...",
+                        flavor.as_str()
+                    ));
+                }
+            }
+            tail.push(line);
+            if tail.len() > BUILD_LOG_TAIL {
+                tail.remove(0);
+            }
+        }
+
+        let status = child.wait()?;
+        let stderr = stderr.join().unwrap_or_default();
+        if !status.success() {
+            let errors: Vec<&str> = stderr.lines().rev().take(BUILD_LOG_TAIL).collect();
+            let output = match errors.is_empty() {
+                true => tail.join("\n"),
+                false => errors.into_iter().rev().collect::<Vec<_>>().join("\n"),
+            };
+            bail!("Building llama.cpp failed:\n{output}");
+        }
+        Ok(())
+    }
+
+    /// Runs a setup step, returning its error output if it fails
+    fn run_quiet(command: &mut Command) -> Result<()> {
+        debug!("Running {command:?}");
+        let output = command
+            .stdin(Stdio::null())
+            .output()
+            .with_context(|| format!("Failed to execute {:?}", command.get_program()))?;
+        if !output.status.success() {
+            bail!(
+                "{} failed: {}",
+                command.get_program().to_string_lossy(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        Ok(())
+    }
+
+    /// Downloads the release archive and copies llama-cli and its shared
+    /// libraries into `dest`
+    fn unpack(&self, url: &str, dest: &Path) -> Result<()> {
+        let archive = dest.with_extension("zip");
+        let extracted = dest.with_extension("extract");
+        let result = (|| {
+            HttpClient::download_file(url, &archive)?;
+            let output = Command::new("unzip")
+                .arg("-q")
+                .arg(&archive)
+                .arg("-d")
+                .arg(&extracted)
+                .output()
+                .context("Failed to execute unzip. Is it installed?")?;
+            if !output.status.success() {
+                bail!(
+                    "Failed to extract {url}: {}",
+                    String::from_utf8_lossy(&output.stderr).trim()
+                );
+            }
+
+            let binary = ARCHIVE_BINARIES
+                .iter()
+                .map(|name| extracted.join(name))
+                .find(|path| path.is_file())
+                .context("Could not find llama-cli in the llama.cpp archive")?;
+            fs::create_dir_all(dest)?;
+            fs::copy(&binary, dest.join(BINARY_NAME))?;
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                fs::set_permissions(dest.join(BINARY_NAME), fs::Permissions::from_mode(0o755))?;
+            }
+
+            // Each flavor keeps its own ggml libraries, so builds don't load each other's,
+            // and its own llama-server for `commandy daemon`
+            if let Some(lib_dir) = binary.parent() {
+                for entry in fs::read_dir(lib_dir)?.flatten() {
+                    let name = entry.file_name().to_string_lossy().to_string();
+                    if name.ends_with(".so")
+                        || name.contains(".so.")
+                        || name.ends_with(".dylib")
+                        || name == SERVER_NAME
+                    {
+                        fs::copy(entry.path(), dest.join(&name))?;
+                    }
+                }
+            }
+            Ok(())
+        })();
+        let _ = fs::remove_file(&archive);
+        if extracted.exists() {
+            let _ = fs::remove_dir_all(&extracted);
+        }
+        result
+    }
+}
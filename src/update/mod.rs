@@ -0,0 +1,5 @@
+pub mod llama;
+pub mod self_update;
+
+pub use llama::{Flavor, LlamaInstaller};
+pub use self_update::{SelfUpdater, UpdateCheck};
@@ -0,0 +1,202 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::{debug, info};
+
+use crate::utils::http::HttpClient;
+
+const RELEASES_API_URL: &str = "https://api.github.com/repos/aptro/commandy/releases/latest";
+
+#[derive(Debug, Deserialize)]
+struct Release {
+    tag_name: String,
+    assets: Vec<ReleaseAsset>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReleaseAsset {
+    name: String,
+    browser_download_url: String,
+}
+
+/// Result of comparing the running binary against the latest release
+#[derive(Debug)]
+pub enum UpdateCheck {
+    UpToDate { current: String },
+    Available { current: String, latest: String },
+}
+
+/// Updates the commandy binary from GitHub releases
+pub struct SelfUpdater {
+    current_version: String,
+}
+
+impl Default for SelfUpdater {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SelfUpdater {
+    pub fn new() -> Self {
+        Self {
+            current_version: env!("CARGO_PKG_VERSION").to_string(),
+        }
+    }
+
+    /// Checks whether a newer release is available without downloading it
+    pub fn check(&self) -> Result<UpdateCheck> {
+        let release = self.fetch_latest_release()?;
+        Ok(self.compare(&release))
+    }
+
+    /// Downloads, verifies and installs the latest release over the running binary
+    pub fn update(&self) -> Result<UpdateCheck> {
+        let release = self.fetch_latest_release()?;
+        let check = self.compare(&release);
+        if matches!(check, UpdateCheck::UpToDate { .. }) {
+            return Ok(check);
+        }
+
+        let asset_name = Self::platform_asset_name()?;
+        let binary_asset = release
+            .assets
+            .iter()
+            .find(|a| a.name == asset_name)
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Release {} has no binary for this platform ({asset_name})",
+                    release.tag_name
+                )
+            })?;
+
+        let checksum_name = format!("{asset_name}.sha256");
+        let checksum_asset = release
+            .assets
+            .iter()
+            .find(|a| a.name == checksum_name)
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Release {} has no checksum file ({checksum_name}); refusing to update",
+                    release.tag_name
+                )
+            })?;
+
+        let expected_checksum = Self::parse_checksum(&HttpClient::fetch_text(
+            &checksum_asset.browser_download_url,
+        )?)?;
+
+        let current_exe = std::env::current_exe()
+            .and_then(|p| p.canonicalize())
+            .context("Could not determine the path of the running binary")?;
+        let staging_path = Self::staging_path(&current_exe)?;
+
+        HttpClient::download_file(&binary_asset.browser_download_url, &staging_path)?;
+
+        if let Err(e) = Self::verify_checksum(&staging_path, &expected_checksum) {
+            let _ = fs::remove_file(&staging_path);
+            return Err(e);
+        }
+
+        Self::replace_binary(&staging_path, &current_exe)?;
+        info!("Updated commandy binary at {current_exe:?}");
+
+        Ok(check)
+    }
+
+    fn fetch_latest_release(&self) -> Result<Release> {
+        let body = HttpClient::fetch_text(RELEASES_API_URL)?;
+        serde_json::from_str(&body).context("Failed to parse GitHub release information")
+    }
+
+    fn compare(&self, release: &Release) -> UpdateCheck {
+        let latest = release.tag_name.trim_start_matches('v').to_string();
+        debug!("Current version {}, latest {latest}", self.current_version);
+
+        if Self::parse_version(&latest) > Self::parse_version(&self.current_version) {
+            UpdateCheck::Available {
+                current: self.current_version.clone(),
+                latest,
+            }
+        } else {
+            UpdateCheck::UpToDate {
+                current: self.current_version.clone(),
+            }
+        }
+    }
+
+    fn parse_version(version: &str) -> Vec<u64> {
+        version
+            .split(['.', '-'])
+            .map_while(|part| part.parse().ok())
+            .collect()
+    }
+
+    /// Maps the running platform onto the release asset names produced by CI
+    fn platform_asset_name() -> Result<String> {
+        let target = match (std::env::consts::OS, std::env::consts::ARCH) {
+            ("macos", "x86_64") => "x86_64-apple-darwin",
+            ("macos", "aarch64") => "aarch64-apple-darwin",
+            ("linux", "x86_64") => "x86_64-unknown-linux-gnu",
+            ("linux", "aarch64") => "aarch64-unknown-linux-gnu",
+            (os, arch) => {
+                return Err(anyhow::anyhow!(
+                    "Self-update is not supported on {os}-{arch}"
+                ))
+            }
+        };
+
+        Ok(format!("commandy-{target}"))
+    }
+
+    fn parse_checksum(content: &str) -> Result<String> {
+        let checksum = content
+            .split_whitespace()
+            .next()
+            .unwrap_or("")
+            .to_lowercase();
+
+        if checksum.len() != 64 || !checksum.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(anyhow::anyhow!("Malformed checksum file"));
+        }
+
+        Ok(checksum)
+    }
+
+    fn verify_checksum(path: &Path, expected: &str) -> Result<()> {
+        let bytes = fs::read(path)?;
+        let actual = format!("{:x}", Sha256::digest(&bytes));
+
+        if actual != expected {
+            return Err(anyhow::anyhow!(
+                "Checksum mismatch for downloaded binary (expected {expected}, got {actual})"
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Stages the download next to the current binary so the final rename stays on one filesystem
+    fn staging_path(current_exe: &Path) -> Result<PathBuf> {
+        let dir = current_exe
+            .parent()
+            .ok_or_else(|| anyhow::anyhow!("Binary has no parent directory"))?;
+        Ok(dir.join(format!(".commandy-update-{}", std::process::id())))
+    }
+
+    fn replace_binary(staged: &Path, current_exe: &Path) -> Result<()> {
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(staged, fs::Permissions::from_mode(0o755))?;
+        }
+
+        fs::rename(staged, current_exe).with_context(|| {
+            format!("Failed to replace {current_exe:?}; check write permissions")
+        })?;
+
+        Ok(())
+    }
+}
@@ -4,6 +4,8 @@ use std::env;
 use std::process::Command;
 use which::which;
 
+use super::Platform;
+
 pub struct EnvironmentDetector;
 
 impl Default for EnvironmentDetector {
@@ -20,9 +22,8 @@ impl EnvironmentDetector {
     pub fn detect_environment(&self) -> Result<HashMap<String, String>> {
         let mut env_info = HashMap::new();
 
-        // Basic system information
-        env_info.insert("os".to_string(), env::consts::OS.to_string());
-        env_info.insert("arch".to_string(), env::consts::ARCH.to_string());
+        // Distro, version, WSL, and init system, not just the OS family
+        env_info.extend(Platform::detect().environment());
 
         // Shell information
         if let Ok(shell) = env::var("SHELL") {
@@ -0,0 +1,144 @@
+use anyhow::{Context, Result};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use tracing::debug;
+
+/// Minimal HTTP helper backed by the system `curl` binary
+pub struct HttpClient;
+
+impl HttpClient {
+    /// Fetches a URL and returns the response body as text
+    pub fn fetch_text(url: &str) -> Result<String> {
+        Self::fetch_text_with_headers(url, &[])
+    }
+
+    /// Fetches a URL with extra request headers (e.g. "Authorization: Bearer ...")
+    pub fn fetch_text_with_headers(url: &str, headers: &[String]) -> Result<String> {
+        debug!("Fetching {url}");
+
+        // Headers go through stdin so secrets don't show up in the process list
+        let mut child = Command::new("curl")
+            .args(["-fsSL", "-H", "User-Agent: commandy", "-H", "@-", url])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .context("Failed to execute curl. Is it installed?")?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(headers.join("\n").as_bytes())?;
+        }
+        let output = child.wait_with_output()?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(anyhow::anyhow!(
+                "Request to {url} failed: {}",
+                stderr.trim()
+            ));
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+
+    /// Posts a JSON body and returns the response body as text
+    pub fn post_json(url: &str, body: &str) -> Result<String> {
+        Self::post_json_with_headers(url, body, &[])
+    }
+
+    /// Posts a JSON body with extra request headers
+    pub fn post_json_with_headers(url: &str, body: &str, headers: &[String]) -> Result<String> {
+        debug!("Posting {} bytes to {url}", body.len());
+
+        // The body goes through stdin, since prompts can exceed argument limits,
+        // so headers go through a file only we can read instead of the process list
+        let header_file = match headers.is_empty() {
+            true => None,
+            false => Some(HeaderFile::write(headers)?),
+        };
+        let mut command = Command::new("curl");
+        command.args([
+            "-fsSL",
+            "-H",
+            "User-Agent: commandy",
+            "-H",
+            "Content-Type: application/json",
+        ]);
+        if let Some(file) = &header_file {
+            command.arg("-H").arg(format!("@{}", file.0.display()));
+        }
+        let mut child = command
+            .args(["--data-binary", "@-", url])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .context("Failed to execute curl. Is it installed?")?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(body.as_bytes())?;
+        }
+        let output = child.wait_with_output()?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(anyhow::anyhow!(
+                "Request to {url} failed: {}",
+                stderr.trim()
+            ));
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+
+    /// Downloads a URL to the given destination path
+    pub fn download_file(url: &str, dest: &Path) -> Result<()> {
+        debug!("Downloading {url} to {dest:?}");
+
+        let output = Command::new("curl")
+            .args(["-fsSL", "-H", "User-Agent: commandy", "-o"])
+            .arg(dest)
+            .arg(url)
+            .stdin(Stdio::null())
+            .output()
+            .context("Failed to execute curl. Is it installed?")?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(anyhow::anyhow!(
+                "Download of {url} failed: {}",
+                stderr.trim()
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Request headers in a temporary file readable only by us, removed when dropped
+struct HeaderFile(PathBuf);
+
+impl HeaderFile {
+    fn write(headers: &[String]) -> Result<Self> {
+        let path = std::env::temp_dir().join(format!("commandy-headers-{}", uuid::Uuid::new_v4()));
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let mut file = options
+            .open(&path)
+            .context("Failed to write request headers")?;
+        let guard = Self(path);
+        file.write_all(headers.join("\n").as_bytes())?;
+        Ok(guard)
+    }
+}
+
+impl Drop for HeaderFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
@@ -0,0 +1,216 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::fs::{self, OpenOptions};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::filter::EnvFilter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, Layer};
+
+use crate::config::LogConfig;
+
+const LOG_FILE_PREFIX: &str = "commandy";
+const LOG_FILE_SUFFIX: &str = "log";
+
+/// One line of a JSON log file, as read back by `commandy doctor --logs`
+#[derive(Debug, Deserialize)]
+pub struct LogEntry {
+    #[serde(default)]
+    pub timestamp: String,
+    pub level: String,
+    #[serde(default)]
+    pub target: String,
+    #[serde(default)]
+    pub fields: serde_json::Map<String, serde_json::Value>,
+}
+
+impl LogEntry {
+    pub fn message(&self) -> &str {
+        self.fields
+            .get("message")
+            .and_then(|m| m.as_str())
+            .unwrap_or_default()
+    }
+
+    /// Structured fields other than the message, rendered as key=value
+    pub fn extra_fields(&self) -> String {
+        self.fields
+            .iter()
+            .filter(|(key, _)| key.as_str() != "message")
+            .map(|(key, value)| match value.as_str() {
+                Some(s) => format!("{key}={s:?}"),
+                None => format!("{key}={value}"),
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    fn is_problem(&self) -> bool {
+        matches!(self.level.as_str(), "WARN" | "ERROR")
+    }
+}
+
+/// Sets up tracing: terse errors on stderr and JSON lines on disk
+pub struct Logging;
+
+impl Logging {
+    /// Installs the global subscriber; `log_file` overrides the rotating log directory
+    pub fn init(
+        config: &LogConfig,
+        log_file: Option<&Path>,
+        verbose: bool,
+        quiet: bool,
+    ) -> Result<()> {
+        // RUST_LOG still controls what reaches the terminal; --quiet keeps it clear
+        let stderr_filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| match quiet {
+            true => EnvFilter::new("off"),
+            false => EnvFilter::new("error"),
+        });
+        let stderr_layer = fmt::layer()
+            .with_writer(std::io::stderr)
+            .with_target(false)
+            .with_filter(stderr_filter);
+
+        let level = if verbose {
+            "debug"
+        } else {
+            config.level.as_str()
+        };
+        let file_filter = || {
+            EnvFilter::try_new(format!("warn,commandy={level}"))
+                .with_context(|| format!("Invalid log level '{level}'"))
+        };
+
+        let registry = tracing_subscriber::registry().with(stderr_layer);
+        let result = match log_file {
+            Some(path) => {
+                let file = OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .with_context(|| format!("Failed to open log file {}", path.display()))?;
+                registry
+                    .with(Self::file_layer(Mutex::new(file)).with_filter(file_filter()?))
+                    .try_init()
+            }
+            None if config.enabled => match Self::rolling_appender(config) {
+                Ok(appender) => registry
+                    .with(Self::file_layer(appender).with_filter(file_filter()?))
+                    .try_init(),
+                // Logging must never stop commandy from running
+                Err(e) => {
+                    eprintln!("Warning: file logging disabled: {e}");
+                    registry.try_init()
+                }
+            },
+            None => registry.try_init(),
+        };
+
+        result.context("Failed to initialize logging")
+    }
+
+    /// Directory holding the rotated log files
+    pub fn log_dir(config: &LogConfig) -> Result<PathBuf> {
+        match &config.path {
+            Some(path) => Ok(Self::expand_home(path)),
+            None => Ok(dirs::home_dir()
+                .ok_or_else(|| anyhow::anyhow!("Could not find home directory"))?
+                .join(".commandy")
+                .join("logs")),
+        }
+    }
+
+    /// Every parseable entry in the log files, oldest first
+    pub fn entries(config: &LogConfig, log_file: Option<&Path>) -> Result<Vec<LogEntry>> {
+        let files = match log_file {
+            Some(path) => vec![path.to_path_buf()],
+            None => Self::log_files(&Self::log_dir(config)?)?,
+        };
+
+        let mut entries: Vec<LogEntry> = Vec::new();
+        for file in files {
+            let Ok(content) = fs::read_to_string(&file) else {
+                continue;
+            };
+            entries.extend(
+                content
+                    .lines()
+                    .filter_map(|line| serde_json::from_str::<LogEntry>(line).ok()),
+            );
+        }
+        Ok(entries)
+    }
+
+    /// Most recent warnings and errors, oldest first
+    pub fn recent_problems(
+        config: &LogConfig,
+        log_file: Option<&Path>,
+        limit: usize,
+    ) -> Result<Vec<LogEntry>> {
+        let mut problems: Vec<LogEntry> = Self::entries(config, log_file)?
+            .into_iter()
+            .filter(LogEntry::is_problem)
+            .collect();
+
+        let excess = problems.len().saturating_sub(limit);
+        problems.drain(..excess);
+        Ok(problems)
+    }
+
+    fn file_layer<S, W>(writer: W) -> impl Layer<S>
+    where
+        S: tracing::Subscriber + for<'a> tracing_subscriber::registry::LookupSpan<'a>,
+        W: for<'w> fmt::MakeWriter<'w> + Send + Sync + 'static,
+    {
+        fmt::layer()
+            .json()
+            .with_current_span(false)
+            .with_span_list(false)
+            .with_ansi(false)
+            .with_writer(writer)
+    }
+
+    /// Daily files, keeping the newest `max_files`. Writes are synchronous so
+    /// nothing is lost when commandy exits through process::exit.
+    fn rolling_appender(config: &LogConfig) -> Result<RollingFileAppender> {
+        let dir = Self::log_dir(config)?;
+        fs::create_dir_all(&dir)
+            .with_context(|| format!("Failed to create log directory {}", dir.display()))?;
+
+        RollingFileAppender::builder()
+            .rotation(Rotation::DAILY)
+            .filename_prefix(LOG_FILE_PREFIX)
+            .filename_suffix(LOG_FILE_SUFFIX)
+            .max_log_files(config.max_files.max(1))
+            .build(&dir)
+            .context("Failed to create rolling log file")
+    }
+
+    /// Log files in the directory, oldest first (names end in the date)
+    fn log_files(dir: &Path) -> Result<Vec<PathBuf>> {
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut files: Vec<PathBuf> = fs::read_dir(dir)?
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(|name| name.starts_with(LOG_FILE_PREFIX))
+            })
+            .collect();
+        files.sort();
+        Ok(files)
+    }
+
+    fn expand_home(path: &Path) -> PathBuf {
+        match (path.strip_prefix("~"), dirs::home_dir()) {
+            (Ok(rest), Some(home)) => home.join(rest),
+            _ => path.to_path_buf(),
+        }
+    }
+}
@@ -0,0 +1,121 @@
+use std::process::Command;
+
+/// Physical memory available to the system, in megabytes
+#[derive(Debug, Clone, Copy)]
+pub struct MemoryInfo {
+    pub total_mb: u64,
+    pub available_mb: u64,
+    /// Swap not in use, which a model can spill into slowly without being killed
+    pub swap_free_mb: u64,
+}
+
+impl MemoryInfo {
+    /// Detects system memory, returning None on unsupported platforms
+    pub fn detect() -> Option<Self> {
+        if cfg!(target_os = "linux") {
+            Self::detect_linux()
+        } else if cfg!(target_os = "macos") {
+            Self::detect_macos()
+        } else {
+            None
+        }
+    }
+
+    /// Resident memory of a process in MB, from `ps`
+    pub fn process_rss_mb(pid: u32) -> Option<u64> {
+        let output = Command::new("ps")
+            .args(["-o", "rss=", "-p", &pid.to_string()])
+            .output()
+            .ok()?;
+        let rss_kb: u64 = String::from_utf8_lossy(&output.stdout)
+            .trim()
+            .parse()
+            .ok()?;
+        Some(rss_kb / 1024)
+    }
+
+    /// Whether a process with this id is running
+    pub fn process_running(pid: u32) -> bool {
+        Command::new("ps")
+            .args(["-o", "pid=", "-p", &pid.to_string()])
+            .output()
+            .is_ok_and(|output| output.status.success())
+    }
+
+    fn detect_linux() -> Option<Self> {
+        let meminfo = std::fs::read_to_string("/proc/meminfo").ok()?;
+        let read_kb = |key: &str| -> Option<u64> {
+            meminfo
+                .lines()
+                .find(|line| line.starts_with(key))?
+                .split_whitespace()
+                .nth(1)?
+                .parse()
+                .ok()
+        };
+
+        let total_kb = read_kb("MemTotal:")?;
+        let available_kb = read_kb("MemAvailable:").unwrap_or(total_kb);
+
+        Some(Self {
+            total_mb: total_kb / 1024,
+            available_mb: available_kb / 1024,
+            swap_free_mb: read_kb("SwapFree:").unwrap_or(0) / 1024,
+        })
+    }
+
+    fn detect_macos() -> Option<Self> {
+        let output = Command::new("sysctl")
+            .args(["-n", "hw.memsize"])
+            .output()
+            .ok()?;
+        let total_bytes: u64 = String::from_utf8_lossy(&output.stdout)
+            .trim()
+            .parse()
+            .ok()?;
+
+        // vm_stat reports pages; free + inactive approximates what can be reclaimed
+        let available_mb = Command::new("vm_stat")
+            .output()
+            .ok()
+            .and_then(|out| {
+                let text = String::from_utf8_lossy(&out.stdout).to_string();
+                let page_size: u64 = text
+                    .lines()
+                    .next()?
+                    .split("page size of ")
+                    .nth(1)?
+                    .split_whitespace()
+                    .next()?
+                    .parse()
+                    .ok()?;
+                let pages = |key: &str| -> u64 {
+                    text.lines()
+                        .find(|l| l.starts_with(key))
+                        .and_then(|l| l.split(':').nth(1))
+                        .and_then(|v| v.trim().trim_end_matches('.').parse().ok())
+                        .unwrap_or(0)
+                };
+                Some((pages("Pages free") + pages("Pages inactive")) * page_size / 1024 / 1024)
+            })
+            .unwrap_or(total_bytes / 1024 / 1024);
+
+        // "total = 2048.00M  used = 512.00M  free = 1536.00M  (encrypted)"
+        let swap_free_mb = Command::new("sysctl")
+            .args(["-n", "vm.swapusage"])
+            .output()
+            .ok()
+            .and_then(|out| {
+                let text = String::from_utf8_lossy(&out.stdout).to_string();
+                let free = text.split("free = ").nth(1)?.split_whitespace().next()?;
+                free.trim_end_matches('M').parse::<f64>().ok()
+            })
+            .map_or(0, |mb| mb as u64);
+
+        Some(Self {
+            total_mb: total_bytes / 1024 / 1024,
+            available_mb,
+            swap_free_mb,
+        })
+    }
+}
@@ -1,7 +1,31 @@
 pub mod environment;
+pub mod http;
+pub mod logging;
+pub mod memory;
+pub mod paths;
+pub mod platform;
+pub mod privilege;
+pub mod redact;
+pub mod runtimes;
 pub mod shell;
+pub mod shims;
+pub mod timeline;
+pub mod tool_docs;
 pub mod validation;
+pub mod word_diff;
 
 pub use environment::EnvironmentDetector;
+pub use http::HttpClient;
+pub use logging::{LogEntry, Logging};
+pub use memory::MemoryInfo;
+pub use paths::PathDisplay;
+pub use platform::{InitSystem, Platform};
+pub use privilege::PrivilegeAnalyzer;
+pub use redact::Redactor;
+pub use runtimes::Runtimes;
 pub use shell::ShellDetector;
+pub use shims::VersionShims;
+pub use timeline::Timeline;
+pub use tool_docs::ToolDocs;
 pub use validation::CommandValidator;
+pub use word_diff::{WordChange, WordDiff};
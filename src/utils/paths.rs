@@ -0,0 +1,49 @@
+use std::path::Path;
+
+/// Paths longer than this lose their middle directories
+const MAX_PATH_CHARS: usize = 60;
+
+/// Paths written the way a person would say where they are
+pub struct PathDisplay;
+
+impl PathDisplay {
+    /// `$HOME` written as `~`
+    pub fn tilde(path: &Path) -> String {
+        let Some(home) = dirs::home_dir() else {
+            return path.display().to_string();
+        };
+        match path.strip_prefix(&home) {
+            Ok(rest) if rest.as_os_str().is_empty() => "~".to_string(),
+            Ok(rest) => format!("~/{}", rest.display()),
+            Err(_) => path.display().to_string(),
+        }
+    }
+
+    /// `~/work/.../service/src/handlers`: home as `~`, and for long paths the
+    /// first directory and as many of the last as fit
+    pub fn shorten(path: &Path) -> String {
+        let full = Self::tilde(path);
+        if full.chars().count() <= MAX_PATH_CHARS {
+            return full;
+        }
+
+        // "" before an absolute path's first slash, or "~"
+        let parts: Vec<&str> = full.split('/').collect();
+        let head = parts[..2.min(parts.len())].join("/");
+        let mut tail: Vec<&str> = Vec::new();
+        let mut length = head.chars().count() + "/...".len();
+        for part in parts[2.min(parts.len())..].iter().rev() {
+            let added = part.chars().count() + 1;
+            if !tail.is_empty() && length + added > MAX_PATH_CHARS {
+                break;
+            }
+            length += added;
+            tail.push(part);
+        }
+        if tail.len() + 2 >= parts.len() {
+            return full;
+        }
+        tail.reverse();
+        format!("{head}/.../{}", tail.join("/"))
+    }
+}
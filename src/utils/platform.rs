@@ -0,0 +1,193 @@
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+/// The service manager running as PID 1
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InitSystem {
+    Systemd,
+    OpenRc,
+    Runit,
+    SysVinit,
+    Launchd,
+}
+
+impl InitSystem {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            InitSystem::Systemd => "systemd",
+            InitSystem::OpenRc => "openrc",
+            InitSystem::Runit => "runit",
+            InitSystem::SysVinit => "sysvinit",
+            InitSystem::Launchd => "launchd",
+        }
+    }
+}
+
+/// What the machine is, in the detail that decides which commands are correct:
+/// `apt` or `dnf`, `systemctl` or `rc-service`, GNU or BSD flags
+#[derive(Debug, Clone)]
+pub struct Platform {
+    /// `linux`, `macos`, `windows`, ...
+    pub os: &'static str,
+    pub arch: &'static str,
+    /// Distribution or product name, such as `Ubuntu` or `macOS`
+    pub distro: Option<String>,
+    pub version: Option<String>,
+    /// Distributions this one is based on, from `ID_LIKE`
+    pub family: Option<String>,
+    /// WSL version when running under Windows Subsystem for Linux
+    pub wsl: Option<u8>,
+    pub init: Option<InitSystem>,
+}
+
+impl Platform {
+    pub fn detect() -> Self {
+        let os = env::consts::OS;
+        let mut platform = Self {
+            os,
+            arch: env::consts::ARCH,
+            distro: None,
+            version: None,
+            family: None,
+            wsl: None,
+            init: None,
+        };
+
+        match os {
+            "linux" => {
+                if let Ok(release) = fs::read_to_string("/etc/os-release") {
+                    let fields = Self::parse_os_release(&release);
+                    platform.distro = fields.get("NAME").cloned();
+                    platform.version = fields
+                        .get("VERSION_ID")
+                        .or_else(|| fields.get("BUILD_ID"))
+                        .cloned();
+                    platform.family = fields.get("ID_LIKE").cloned();
+                }
+                platform.wsl = Self::detect_wsl();
+                platform.init = Self::detect_linux_init();
+            }
+            "macos" => {
+                platform.distro = Some("macOS".to_string());
+                platform.version = Command::new("sw_vers")
+                    .arg("-productVersion")
+                    .output()
+                    .ok()
+                    .filter(|output| output.status.success())
+                    .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+                    .filter(|version| !version.is_empty());
+                platform.init = Some(InitSystem::Launchd);
+            }
+            _ => {}
+        }
+        platform
+    }
+
+    /// One line for prompts, such as `Ubuntu 22.04 (linux x86_64, WSL2, systemd)`
+    pub fn describe(&self) -> String {
+        let mut details = vec![format!("{} {}", self.os, self.arch)];
+        if let Some(family) = &self.family {
+            details.push(format!("like {family}"));
+        }
+        if let Some(wsl) = self.wsl {
+            details.push(format!("WSL{wsl}"));
+        }
+        if let Some(init) = self.init {
+            details.push(init.as_str().to_string());
+        }
+
+        let name = match (&self.distro, &self.version) {
+            (Some(distro), Some(version)) => format!("{distro} {version}"),
+            (Some(distro), None) => distro.clone(),
+            (None, _) => return details.join(", "),
+        };
+        format!("{name} ({})", details.join(", "))
+    }
+
+    /// Environment keys: `os` holds the description, the rest the parts
+    pub fn environment(&self) -> HashMap<String, String> {
+        let mut env_info = HashMap::new();
+        env_info.insert("os".to_string(), self.describe());
+        env_info.insert("arch".to_string(), self.arch.to_string());
+        if let Some(distro) = &self.distro {
+            env_info.insert("distro".to_string(), distro.clone());
+        }
+        if let Some(version) = &self.version {
+            env_info.insert("os_version".to_string(), version.clone());
+        }
+        if let Some(wsl) = self.wsl {
+            env_info.insert("wsl".to_string(), wsl.to_string());
+        }
+        if let Some(init) = self.init {
+            env_info.insert("init_system".to_string(), init.as_str().to_string());
+        }
+        env_info
+    }
+
+    /// This machine's hostname, without a domain
+    pub fn hostname() -> Option<String> {
+        let name = fs::read_to_string("/proc/sys/kernel/hostname")
+            .ok()
+            .or_else(|| env::var("HOSTNAME").ok())
+            .or_else(|| env::var("COMPUTERNAME").ok())
+            .or_else(|| {
+                let output = Command::new("hostname").output().ok()?;
+                output
+                    .status
+                    .success()
+                    .then(|| String::from_utf8_lossy(&output.stdout).to_string())
+            })?;
+        let name = name.trim().split('.').next().unwrap_or_default();
+        (!name.is_empty()).then(|| name.to_string())
+    }
+
+    fn parse_os_release(content: &str) -> HashMap<String, String> {
+        content
+            .lines()
+            .filter_map(|line| line.split_once('='))
+            .map(|(key, value)| {
+                let value = value.trim().trim_matches(['"', '\'']);
+                (key.trim().to_string(), value.to_string())
+            })
+            .filter(|(_, value)| !value.is_empty())
+            .collect()
+    }
+
+    /// `Some(1)` or `Some(2)` under WSL, from the kernel release string
+    fn detect_wsl() -> Option<u8> {
+        let release = fs::read_to_string("/proc/sys/kernel/osrelease").ok()?;
+        let release = release.to_lowercase();
+        if release.contains("wsl2") || release.contains("microsoft-standard") {
+            Some(2)
+        } else if release.contains("microsoft") {
+            Some(1)
+        } else if env::var_os("WSL_DISTRO_NAME").is_some() {
+            Some(2)
+        } else {
+            None
+        }
+    }
+
+    fn detect_linux_init() -> Option<InitSystem> {
+        if Path::new("/run/systemd/system").exists() {
+            return Some(InitSystem::Systemd);
+        }
+        if Path::new("/run/openrc").exists() || Path::new("/sbin/openrc-run").exists() {
+            return Some(InitSystem::OpenRc);
+        }
+        if Path::new("/run/runit").exists() || Path::new("/etc/runit/runsvdir").exists() {
+            return Some(InitSystem::Runit);
+        }
+        // Containers and minimal systems run something else as PID 1
+        match fs::read_to_string("/proc/1/comm").ok()?.trim() {
+            "systemd" => Some(InitSystem::Systemd),
+            "openrc-init" => Some(InitSystem::OpenRc),
+            "runit" | "runsvdir" => Some(InitSystem::Runit),
+            "init" if Path::new("/etc/inittab").exists() => Some(InitSystem::SysVinit),
+            _ => None,
+        }
+    }
+}
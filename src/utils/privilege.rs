@@ -0,0 +1,281 @@
+use regex::Regex;
+
+use crate::preview::{detect_edits, EditKind};
+
+/// System locations only root may write to
+const PROTECTED_PATHS: [&str; 10] = [
+    "/etc", "/usr", "/boot", "/opt", "/var", "/lib", "/bin", "/sbin", "/sys", "/proc",
+];
+
+/// Writable places under the protected paths: the shared temp directory and Homebrew's prefixes
+const WRITABLE_EXCEPTIONS: [&str; 3] = ["/var/tmp", "/usr/local/Cellar", "/opt/homebrew"];
+
+/// Package managers whose mutating verbs need root
+const PACKAGE_MANAGERS: [&str; 7] = ["apt", "apt-get", "dnf", "yum", "pacman", "zypper", "apk"];
+const PACKAGE_VERBS: [&str; 11] = [
+    "install",
+    "remove",
+    "purge",
+    "upgrade",
+    "update",
+    "autoremove",
+    "add",
+    "del",
+    "-S",
+    "-R",
+    "-Syu",
+];
+
+const SYSTEMCTL_VERBS: [&str; 10] = [
+    "start",
+    "stop",
+    "restart",
+    "reload",
+    "enable",
+    "disable",
+    "mask",
+    "unmask",
+    "daemon-reload",
+    "edit",
+];
+
+/// Executables that only do anything useful as root
+const ROOT_EXECUTABLES: [&str; 15] = [
+    "useradd",
+    "usermod",
+    "userdel",
+    "groupadd",
+    "groupdel",
+    "mount",
+    "umount",
+    "fdisk",
+    "modprobe",
+    "iptables",
+    "ufw",
+    "shutdown",
+    "reboot",
+    "update-grub",
+    "visudo",
+];
+
+/// Commands whose file arguments are written to
+const WRITING_EXECUTABLES: [&str; 9] = [
+    "cp", "mv", "rm", "ln", "mkdir", "touch", "chmod", "chown", "install",
+];
+
+/// Whether, and why, a command needs root
+pub struct PrivilegeAnalyzer;
+
+impl PrivilegeAnalyzer {
+    /// Reason the command needs elevated privileges, or `None` if it runs as a normal user
+    pub fn requires_root(command: &str) -> Option<String> {
+        if Self::running_as_root() {
+            return None;
+        }
+        Self::root_reason(command)
+    }
+
+    /// Reason the command would need root, even when it's already running as root
+    pub fn root_reason(command: &str) -> Option<String> {
+        if Self::is_elevated(command) {
+            return Some("runs with sudo".to_string());
+        }
+
+        Self::segments(command)
+            .find_map(|words| Self::verb_reason(words[0], &words[1..]))
+            .or_else(|| Self::write_reason(command))
+    }
+
+    /// Whether the command already elevates itself with sudo or doas
+    pub fn is_elevated(command: &str) -> bool {
+        Self::segments(command).any(|words| matches!(words[0], "sudo" | "doas"))
+    }
+
+    /// Words of each command in a pipeline or list; empty commands are skipped
+    fn segments(command: &str) -> impl Iterator<Item = Vec<&str>> {
+        command
+            .split(['|', ';', '&'])
+            .map(|segment| segment.split_whitespace().collect::<Vec<_>>())
+            .filter(|words| !words.is_empty())
+    }
+
+    fn verb_reason(executable: &str, args: &[&str]) -> Option<String> {
+        if PACKAGE_MANAGERS.contains(&executable) && args.iter().any(|a| PACKAGE_VERBS.contains(a))
+        {
+            return Some(format!("changes system packages with {executable}"));
+        }
+        if executable == "systemctl"
+            && !args.contains(&"--user")
+            && args.iter().any(|a| SYSTEMCTL_VERBS.contains(a))
+        {
+            return Some("changes a system service".to_string());
+        }
+        if ROOT_EXECUTABLES.contains(&executable) {
+            return Some(format!("{executable} needs root"));
+        }
+        if executable == "sysctl" && args.contains(&"-w") {
+            return Some("changes kernel parameters".to_string());
+        }
+        if WRITING_EXECUTABLES.contains(&executable) {
+            let paths: Vec<&&str> = args.iter().filter(|a| !a.starts_with('-')).collect();
+            // cp, mv, ln, and install only write to their destination
+            let targets: Vec<&&str> = match executable {
+                "cp" | "mv" | "ln" | "install" => paths.last().into_iter().copied().collect(),
+                _ => paths,
+            };
+            if let Some(path) = targets.into_iter().find(|p| Self::is_protected(p)) {
+                return Some(format!("writes to {path}"));
+            }
+        }
+        None
+    }
+
+    /// Redirections, `tee`, and `sed -i` into protected paths
+    fn write_reason(command: &str) -> Option<String> {
+        detect_edits(command)?
+            .into_iter()
+            .find(|edit| Self::is_protected(&edit.path.to_string_lossy()))
+            .map(|edit| format!("writes to {}", edit.path.display()))
+    }
+
+    pub fn is_protected(path: &str) -> bool {
+        let under = |root: &str| path == root || path.starts_with(&format!("{root}/"));
+        PROTECTED_PATHS.iter().any(|root| under(root))
+            && !WRITABLE_EXCEPTIONS.iter().any(|root| under(root))
+    }
+
+    /// Rewrites a command to run with sudo, or `None` if it is too complex to rewrite
+    ///
+    /// Redirections become `| sudo tee`, since `sudo cmd > file` opens the file as the user.
+    pub fn with_sudo(command: &str) -> Option<String> {
+        if command.starts_with("sudo ") {
+            return Some(command.to_string());
+        }
+        if command.contains([';', '&', '`', '$']) {
+            return None;
+        }
+
+        let edits = detect_edits(command).unwrap_or_default();
+        let protected_edit = edits
+            .iter()
+            .find(|e| Self::is_protected(&e.path.to_string_lossy()));
+
+        match protected_edit.map(|e| e.kind) {
+            Some(EditKind::Overwrite | EditKind::Append) => {
+                let redirect = Regex::new(r"^(.*?)\s*(>>?)\s*(\S+)$").expect("valid regex");
+                let caps = redirect.captures(command)?;
+                let append = if &caps[2] == ">>" { " -a" } else { "" };
+                Some(format!(
+                    "{} | sudo tee{append} {} >/dev/null",
+                    &caps[1], &caps[3]
+                ))
+            }
+            Some(EditKind::Tee | EditKind::TeeAppend) => {
+                let tee = Regex::new(r"\|\s*tee\b").expect("valid regex");
+                Some(tee.replace(command, "| sudo tee").to_string())
+            }
+            // Elevating one stage of a pipeline isn't something to guess at
+            _ if command.contains('|') => None,
+            _ => Some(format!("sudo {command}")),
+        }
+    }
+
+    #[cfg(unix)]
+    fn running_as_root() -> bool {
+        // SAFETY: geteuid has no preconditions and cannot fail
+        unsafe { libc::geteuid() == 0 }
+    }
+
+    #[cfg(not(unix))]
+    fn running_as_root() -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PrivilegeAnalyzer;
+
+    #[test]
+    fn protected_paths() {
+        assert!(PrivilegeAnalyzer::is_protected("/etc/hosts"));
+        assert!(PrivilegeAnalyzer::is_protected("/etc"));
+        assert!(PrivilegeAnalyzer::is_protected("/usr/local/bin/tool"));
+        assert!(!PrivilegeAnalyzer::is_protected("/etcetera/file"));
+        assert!(!PrivilegeAnalyzer::is_protected("/home/user/notes.txt"));
+        assert!(!PrivilegeAnalyzer::is_protected("relative/etc/file"));
+    }
+
+    #[test]
+    fn writable_exceptions() {
+        assert!(!PrivilegeAnalyzer::is_protected("/var/tmp/build.log"));
+        assert!(!PrivilegeAnalyzer::is_protected("/opt/homebrew/bin/jq"));
+        assert!(!PrivilegeAnalyzer::is_protected("/usr/local/Cellar/jq"));
+        assert!(PrivilegeAnalyzer::is_protected("/var/log/syslog"));
+    }
+
+    #[test]
+    fn writing_commands() {
+        assert_eq!(
+            PrivilegeAnalyzer::root_reason("cp tool /usr/local/bin/tool").as_deref(),
+            Some("writes to /usr/local/bin/tool")
+        );
+        // Only the destination of cp is written
+        assert_eq!(PrivilegeAnalyzer::root_reason("cp /etc/hosts ."), None);
+        assert_eq!(PrivilegeAnalyzer::root_reason("touch /var/tmp/x"), None);
+        assert!(PrivilegeAnalyzer::root_reason("apt-get install jq").is_some());
+        assert!(PrivilegeAnalyzer::root_reason("systemctl restart nginx").is_some());
+        assert_eq!(
+            PrivilegeAnalyzer::root_reason("systemctl --user restart app"),
+            None
+        );
+    }
+
+    #[test]
+    fn redirects() {
+        assert_eq!(
+            PrivilegeAnalyzer::root_reason("echo 3 > /proc/sys/vm/drop_caches").as_deref(),
+            Some("writes to /proc/sys/vm/drop_caches")
+        );
+        assert!(PrivilegeAnalyzer::root_reason("echo x >> /etc/hosts").is_some());
+        assert!(PrivilegeAnalyzer::root_reason("cat hosts | tee /etc/hosts").is_some());
+        assert!(PrivilegeAnalyzer::root_reason("sed -i 's/a/b/' /etc/fstab").is_some());
+        assert_eq!(PrivilegeAnalyzer::root_reason("ls > /tmp/listing"), None);
+        assert_eq!(PrivilegeAnalyzer::root_reason("ls 2>/dev/null"), None);
+    }
+
+    #[test]
+    fn sudo_wrapper() {
+        assert_eq!(
+            PrivilegeAnalyzer::with_sudo("echo x > /etc/motd").as_deref(),
+            Some("echo x | sudo tee /etc/motd >/dev/null")
+        );
+        assert_eq!(
+            PrivilegeAnalyzer::with_sudo("echo x >> /etc/motd").as_deref(),
+            Some("echo x | sudo tee -a /etc/motd >/dev/null")
+        );
+        assert_eq!(
+            PrivilegeAnalyzer::with_sudo("cat hosts | tee /etc/hosts").as_deref(),
+            Some("cat hosts | sudo tee /etc/hosts")
+        );
+        assert_eq!(
+            PrivilegeAnalyzer::with_sudo("apt install jq").as_deref(),
+            Some("sudo apt install jq")
+        );
+        assert_eq!(
+            PrivilegeAnalyzer::with_sudo("sudo apt install jq").as_deref(),
+            Some("sudo apt install jq")
+        );
+        assert_eq!(PrivilegeAnalyzer::with_sudo("ps aux | grep x"), None);
+        assert_eq!(PrivilegeAnalyzer::with_sudo("cd /etc && ls"), None);
+    }
+
+    #[test]
+    fn elevated_commands() {
+        assert!(PrivilegeAnalyzer::is_elevated(
+            "sudo systemctl restart nginx"
+        ));
+        assert!(PrivilegeAnalyzer::is_elevated("ls | doas tee /etc/x"));
+        assert!(!PrivilegeAnalyzer::is_elevated("echo sudo"));
+    }
+}
@@ -0,0 +1,61 @@
+use regex::Regex;
+
+use crate::utils::Platform;
+
+/// Names too common in ordinary text to replace wherever they appear
+const COMMON_NAMES: &[&str] = &["root", "admin", "user", "localhost", "ubuntu", "debian"];
+
+/// Removes secrets and personal details from text that leaves the machine or reaches the model
+pub struct Redactor;
+
+impl Redactor {
+    /// Masks passwords, tokens, and credentials as `***`
+    pub fn secrets(text: &str) -> String {
+        let flag = Regex::new(
+            r#"(?i)(--?[a-z0-9-]*(?:password|passwd|passphrase|pass|token|secret|api[-_]?key|authorization))([=\s]+)("[^"]*"|'[^']*'|\S+)"#,
+        )
+        .expect("valid regex");
+        let assignment = Regex::new(
+            r#"(?i)\b([a-z0-9_]*(?:password|passwd|token|secret|api_?key|credentials?)[a-z0-9_]*)=("[^"]*"|'[^']*'|\S+)"#,
+        )
+        .expect("valid regex");
+        let header = Regex::new(r"(?i)\b(authorization:\s*(?:bearer|basic|token)?\s*)[^\s'\x22]+")
+            .expect("valid regex");
+        let url_credentials =
+            Regex::new(r"([a-z][a-z0-9+.-]*://[^/\s:@]+):[^@\s/]+@").expect("valid regex");
+        let mysql_password = Regex::new(r"(\bmysql\b.*\s-p)\S+").expect("valid regex");
+        let known_tokens = Regex::new(
+            r"\b(?:gh[pousr]_[A-Za-z0-9]{20,}|glpat-[A-Za-z0-9_-]{20,}|xox[abprs]-[A-Za-z0-9-]{10,}|sk-[A-Za-z0-9_-]{20,}|AKIA[0-9A-Z]{16})\b",
+        )
+        .expect("valid regex");
+
+        let text = flag.replace_all(text, "$1$2***");
+        let text = assignment.replace_all(&text, "$1=***");
+        let text = header.replace_all(&text, "$1***");
+        let text = url_credentials.replace_all(&text, "$1:***@");
+        let text = mysql_password.replace_all(&text, "$1***");
+        known_tokens.replace_all(&text, "***").into_owned()
+    }
+
+    /// Secrets masked, the home directory as `~`, and the user and host names
+    /// as `<user>` and `<host>`
+    pub fn personal(text: &str) -> String {
+        let mut text = Self::secrets(text);
+        if let Some(home) = dirs::home_dir().filter(|home| home.as_os_str().len() > 1) {
+            text = text.replace(&home.display().to_string(), "~");
+        }
+
+        let user = std::env::var("USER")
+            .or_else(|_| std::env::var("USERNAME"))
+            .ok();
+        for (name, placeholder) in [(user, "<user>"), (Platform::hostname(), "<host>")] {
+            let Some(name) = name.filter(|n| n.len() >= 3 && !COMMON_NAMES.contains(&n.as_str()))
+            else {
+                continue;
+            };
+            let word = Regex::new(&format!(r"\b{}\b", regex::escape(&name))).expect("valid regex");
+            text = word.replace_all(&text, placeholder).into_owned();
+        }
+        text
+    }
+}
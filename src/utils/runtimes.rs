@@ -0,0 +1,316 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use crate::utils::PathDisplay;
+
+/// Directories a project keeps its virtualenv in
+const PROJECT_VENVS: &[&str] = &[".venv", "venv"];
+
+/// Node version managers, by a directory in the path of the node they run
+const NODE_MANAGERS: &[(&str, &str)] = &[
+    ("/.nvm/", "nvm"),
+    ("/.volta/", "volta"),
+    ("/fnm/", "fnm"),
+    ("/.fnm/", "fnm"),
+    ("/.asdf/", "asdf"),
+    ("/mise/", "mise"),
+    ("/.nodenv/", "nodenv"),
+];
+
+/// Files naming the node version a project wants
+const NODE_VERSION_FILES: &[&str] = &[".nvmrc", ".node-version"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PythonEnvKind {
+    /// Activated with `source .venv/bin/activate`, or by uv, poetry, and the like
+    Virtualenv,
+    Conda,
+    /// The project has a virtualenv, but the shell hasn't activated it
+    Inactive,
+}
+
+/// The Python environment that `pip install` should reach
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PythonEnvironment {
+    pub kind: PythonEnvKind,
+    /// Relative to the current directory when below it
+    pub location: String,
+    pub prefix: PathBuf,
+    /// Its interpreter, as commands should name it
+    pub python: String,
+    /// Conda environment name
+    pub name: Option<String>,
+    pub version: Option<String>,
+    /// Whether the user owns it, so installing needs no sudo
+    pub user_owned: bool,
+}
+
+/// The node on PATH and the manager that put it there
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NodeEnvironment {
+    /// `None` for a system install
+    pub manager: Option<String>,
+    pub version: Option<String>,
+    /// Full path of the node binary, for `sudo`, which resets PATH
+    pub binary: PathBuf,
+    /// The version the project's `.nvmrc` or `.node-version` asks for
+    pub wanted: Option<(String, String)>,
+}
+
+impl NodeEnvironment {
+    /// Whether the active node is a different major version than the project wants
+    pub fn mismatched(&self) -> bool {
+        let (Some(version), Some((_, wanted))) = (&self.version, &self.wanted) else {
+            return false;
+        };
+        let wanted = wanted.trim_start_matches('v');
+        // Aliases such as `lts/iron` or `node` can't be compared
+        if !wanted.starts_with(|c: char| c.is_ascii_digit()) {
+            return false;
+        }
+        let version = version.trim_start_matches('v');
+        version != wanted && !version.starts_with(&format!("{wanted}."))
+    }
+}
+
+/// Active language environments: the virtualenv or conda env `python` runs
+/// in, and the node a version manager selected
+///
+/// Suggestions that ignore them install packages into the wrong place, or run
+/// a different node under `sudo`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Runtimes {
+    pub python: Option<PythonEnvironment>,
+    pub node: Option<NodeEnvironment>,
+}
+
+impl Runtimes {
+    pub fn detect() -> Self {
+        let cwd = env::current_dir().ok();
+        Self {
+            python: Self::python(cwd.as_deref()),
+            node: Self::node(cwd.as_deref()),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.python.is_none() && self.node.is_none()
+    }
+
+    /// Lines for the prompt
+    pub fn describe(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        if let Some(python) = &self.python {
+            let version = python
+                .version
+                .as_ref()
+                .map(|v| format!(" (Python {v})"))
+                .unwrap_or_default();
+            lines.push(match python.kind {
+                PythonEnvKind::Virtualenv => format!(
+                    "Python: virtualenv {}{version} is active; install packages with `python -m pip`, without sudo or --user",
+                    python.location
+                ),
+                PythonEnvKind::Conda => format!(
+                    "Python: conda environment {}{version} is active; install with `conda install` or `python -m pip`",
+                    python.name.as_deref().unwrap_or(&python.location)
+                ),
+                PythonEnvKind::Inactive => format!(
+                    "Python: the project's virtualenv {}{version} isn't activated; install into it with `{} -m pip`",
+                    python.location, python.python
+                ),
+            });
+        }
+        if let Some(node) = &self.node {
+            let version = node.version.as_deref().unwrap_or("node");
+            let mut line = match &node.manager {
+                Some(manager) => {
+                    format!("Node: {version} from {manager}; npm install -g needs no sudo")
+                }
+                None => format!("Node: {version} (system install)"),
+            };
+            if let (true, Some((file, wanted))) = (node.mismatched(), &node.wanted) {
+                let switch = match node.manager.as_deref() {
+                    Some("nvm") => "nvm use",
+                    Some("fnm") => "fnm use",
+                    Some("volta") => "volta install node@<version>",
+                    _ => "switching node versions",
+                };
+                line.push_str(&format!(
+                    ", but {file} asks for {wanted}: run `{switch}` first"
+                ));
+            }
+            lines.push(line);
+        }
+        lines
+    }
+
+    fn python(cwd: Option<&Path>) -> Option<PythonEnvironment> {
+        if let Some(prefix) = env::var_os("VIRTUAL_ENV").map(PathBuf::from) {
+            return Some(Self::python_environment(
+                PythonEnvKind::Virtualenv,
+                &prefix,
+                cwd,
+                "python".to_string(),
+            ));
+        }
+        if let Some(prefix) = env::var_os("CONDA_PREFIX").map(PathBuf::from) {
+            let mut environment =
+                Self::python_environment(PythonEnvKind::Conda, &prefix, cwd, "python".to_string());
+            environment.name = env::var("CONDA_DEFAULT_ENV").ok();
+            return Some(environment);
+        }
+
+        // A project virtualenv nobody activated, in this directory or one above it
+        let cwd = cwd?;
+        let home = dirs::home_dir();
+        for dir in cwd.ancestors() {
+            if home.as_deref() == Some(dir) {
+                break;
+            }
+            for name in PROJECT_VENVS {
+                let prefix = dir.join(name);
+                let interpreter = prefix.join("bin").join("python");
+                if interpreter.is_file() {
+                    // Shortened paths can't be run, so one above here stays absolute
+                    let python = match interpreter.strip_prefix(cwd) {
+                        Ok(relative) => relative.display().to_string(),
+                        Err(_) => interpreter.display().to_string(),
+                    };
+                    return Some(Self::python_environment(
+                        PythonEnvKind::Inactive,
+                        &prefix,
+                        Some(cwd),
+                        python,
+                    ));
+                }
+            }
+        }
+        None
+    }
+
+    fn python_environment(
+        kind: PythonEnvKind,
+        prefix: &Path,
+        cwd: Option<&Path>,
+        python: String,
+    ) -> PythonEnvironment {
+        // Virtualenvs record the interpreter they were made from
+        let version = fs::read_to_string(prefix.join("pyvenv.cfg"))
+            .ok()
+            .and_then(|cfg| {
+                cfg.lines().find_map(|line| {
+                    let (key, value) = line.split_once('=')?;
+                    matches!(key.trim(), "version" | "version_info")
+                        .then(|| value.trim().to_string())
+                })
+            });
+        PythonEnvironment {
+            kind,
+            location: match cwd {
+                Some(cwd) => Self::label(cwd, prefix),
+                None => PathDisplay::shorten(prefix),
+            },
+            prefix: prefix.to_path_buf(),
+            python,
+            name: None,
+            version,
+            user_owned: Self::user_owned(prefix),
+        }
+    }
+
+    /// Owned by whoever owns the home directory, on Unix; under it elsewhere
+    fn user_owned(prefix: &Path) -> bool {
+        let Some(home) = dirs::home_dir() else {
+            return false;
+        };
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            match (fs::metadata(prefix), fs::metadata(&home)) {
+                (Ok(prefix), Ok(home)) => prefix.uid() == home.uid(),
+                _ => false,
+            }
+        }
+        #[cfg(not(unix))]
+        {
+            prefix.starts_with(home)
+        }
+    }
+
+    fn node(cwd: Option<&Path>) -> Option<NodeEnvironment> {
+        let binary = which::which("node").ok()?;
+        let path = binary.to_string_lossy();
+        let manager = NODE_MANAGERS
+            .iter()
+            .find(|(dir, _)| path.contains(dir))
+            .map(|(_, manager)| manager.to_string());
+        // nvm and fnm keep each version in its own directory; shims need asking
+        let version = Regex::new(r"/v?(\d+\.\d+\.\d+)/")
+            .expect("valid regex")
+            .captures(&path)
+            .map(|caps| format!("v{}", &caps[1]))
+            .or_else(|| Self::node_version(&binary));
+        // Behind shims, the real binary is what sudo should run
+        let binary = match manager.as_deref() {
+            Some("asdf" | "mise" | "volta" | "nodenv") => {
+                Self::resolved_node(&binary).unwrap_or(binary)
+            }
+            _ => binary,
+        };
+
+        let wanted = cwd.and_then(|cwd| {
+            let home = dirs::home_dir();
+            cwd.ancestors()
+                .take_while(|dir| home.as_deref() != Some(*dir))
+                .flat_map(|dir| NODE_VERSION_FILES.iter().map(move |file| dir.join(file)))
+                .find_map(|path| {
+                    let wanted = fs::read_to_string(&path).ok()?;
+                    let wanted = wanted.lines().next()?.trim().to_string();
+                    (!wanted.is_empty()).then(|| (Self::label(cwd, &path), wanted))
+                })
+        });
+
+        Some(NodeEnvironment {
+            manager,
+            version,
+            binary,
+            wanted,
+        })
+    }
+
+    fn node_version(binary: &Path) -> Option<String> {
+        let output = Command::new(binary)
+            .arg("--version")
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output()
+            .ok()?;
+        let version = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        (output.status.success() && !version.is_empty()).then_some(version)
+    }
+
+    /// The node binary a shim runs, from `process.execPath`
+    fn resolved_node(shim: &Path) -> Option<PathBuf> {
+        let output = Command::new(shim)
+            .args(["-p", "process.execPath"])
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output()
+            .ok()?;
+        let path = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        (output.status.success() && !path.is_empty()).then(|| PathBuf::from(path))
+    }
+
+    /// A path relative to the current directory when below it, else shortened
+    fn label(cwd: &Path, path: &Path) -> String {
+        match path.strip_prefix(cwd) {
+            Ok(relative) => relative.display().to_string(),
+            Err(_) => PathDisplay::shorten(path),
+        }
+    }
+}
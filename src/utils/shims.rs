@@ -0,0 +1,71 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::{Mutex, OnceLock};
+
+/// Version managers, by the directory their shims are installed in
+const MANAGERS: &[(&str, &str)] = &[
+    ("/.asdf/shims/", "asdf"),
+    ("/mise/shims/", "mise"),
+    ("/.pyenv/shims/", "pyenv"),
+    ("/.rbenv/shims/", "rbenv"),
+    ("/.nodenv/shims/", "nodenv"),
+    ("/.goenv/shims/", "goenv"),
+    ("/.jenv/shims/", "jenv"),
+];
+
+/// Version manager shims, which are on PATH even where they can't run
+///
+/// asdf, mise, pyenv, and the like install one shim per tool for every
+/// version they have. A shim fails with "no version set" in a directory
+/// whose `.tool-versions` or `mise.toml` doesn't select that tool, so being
+/// on PATH isn't enough.
+pub struct VersionShims;
+
+impl VersionShims {
+    /// Whether `name` is on PATH and, if it is a shim, has a version selected here
+    pub fn runnable(name: &str) -> bool {
+        match which::which(name) {
+            Ok(path) => Self::selects_version(&path, name),
+            Err(_) => false,
+        }
+    }
+
+    /// False only for a shim whose manager says no version applies in this directory
+    ///
+    /// Answers are remembered for the rest of the run, since asking the manager
+    /// starts a process.
+    pub fn selects_version(path: &Path, name: &str) -> bool {
+        let Some(manager) = Self::manager(path) else {
+            return true;
+        };
+        static ANSWERS: OnceLock<Mutex<HashMap<String, bool>>> = OnceLock::new();
+        let answers = ANSWERS.get_or_init(Default::default);
+        if let Some(&answer) = answers.lock().unwrap_or_else(|e| e.into_inner()).get(name) {
+            return answer;
+        }
+
+        // A manager that can't be asked gets the benefit of the doubt
+        let answer = Command::new(manager)
+            .args(["which", name])
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .map_or(true, |status| status.success());
+        answers
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(name.to_string(), answer);
+        answer
+    }
+
+    /// The version manager that installed the shim at `path`, if it is one
+    pub fn manager(path: &Path) -> Option<&'static str> {
+        let path = path.to_string_lossy();
+        MANAGERS
+            .iter()
+            .find(|(dir, _)| path.contains(dir))
+            .map(|(_, manager)| *manager)
+    }
+}
@@ -0,0 +1,87 @@
+use std::time::{Duration, Instant};
+
+/// One step of a request, with what it found
+#[derive(Debug, Clone)]
+struct Step {
+    name: String,
+    elapsed: Duration,
+    detail: String,
+    /// Steps within this one, such as each context provider
+    parts: Vec<Step>,
+}
+
+/// Where the time of a request went, step by step, for `--verbose`
+#[derive(Debug, Clone)]
+pub struct Timeline {
+    started: Instant,
+    steps: Vec<Step>,
+}
+
+impl Default for Timeline {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Timeline {
+    pub fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            steps: Vec::new(),
+        }
+    }
+
+    /// Runs `f` as a step called `name`
+    pub fn time<T>(&mut self, name: &str, f: impl FnOnce() -> T) -> T {
+        let started = Instant::now();
+        let result = f();
+        self.push(name, started.elapsed(), "");
+        result
+    }
+
+    /// Adds a step timed elsewhere, such as one that awaits
+    pub fn push(&mut self, name: &str, elapsed: Duration, detail: impl Into<String>) {
+        self.steps.push(Step {
+            name: name.to_string(),
+            elapsed,
+            detail: detail.into(),
+            parts: Vec::new(),
+        });
+    }
+
+    /// Says what the last step found
+    pub fn detail(&mut self, detail: impl Into<String>) {
+        if let Some(step) = self.steps.last_mut() {
+            step.detail = detail.into();
+        }
+    }
+
+    /// Lists the steps of `inner` under the last step
+    pub fn nest(&mut self, inner: &Timeline) {
+        if let Some(step) = self.steps.last_mut() {
+            step.parts.extend(inner.steps.iter().cloned());
+        }
+    }
+
+    /// The steps in order with their milliseconds, then the total since the timeline began
+    pub fn render(&self) -> String {
+        let mut lines = vec!["Timeline:".to_string()];
+        let mut add = |step: &Step, indent: usize| {
+            let name = format!("{}{}", "  ".repeat(indent), step.name);
+            let line = format!("{:>10}  {name:<22} {}", Self::ms(step.elapsed), step.detail);
+            lines.push(line.trim_end().to_string());
+        };
+        for step in &self.steps {
+            add(step, 0);
+            for part in &step.parts {
+                add(part, 1);
+            }
+        }
+        lines.push(format!("{:>10}  total", Self::ms(self.started.elapsed())));
+        lines.join("\n")
+    }
+
+    fn ms(elapsed: Duration) -> String {
+        format!("{:.1} ms", elapsed.as_secs_f64() * 1000.0)
+    }
+}
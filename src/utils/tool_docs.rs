@@ -0,0 +1,108 @@
+use regex::Regex;
+use std::io::Read;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::utils::validation::SHELL_BUILTINS;
+use crate::utils::CommandValidator;
+
+/// Programs that act on `--help` instead of printing it on some systems
+const NO_HELP_PROBE: &[&str] = &[
+    "reboot", "shutdown", "halt", "poweroff", "init", "telinit", "suspend", "logout",
+];
+
+/// How long `--help` may take before it is given up on
+const HELP_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Documentation of the programs that suggestions run
+///
+/// The man page is read first, since it is what the user would look at,
+/// and `--help` only when there is none.
+pub struct ToolDocs;
+
+impl ToolDocs {
+    /// The program a command is mostly about: its first executable that isn't a
+    /// builtin, by name
+    ///
+    /// Scripts run by relative path, like `./deploy.sh`, have no docs to read.
+    pub fn tool(command: &str) -> Option<String> {
+        CommandValidator::new()
+            .executables(command)
+            .into_iter()
+            // Builtins have no man page of their own
+            .find(|executable| !SHELL_BUILTINS.contains(&executable.as_str()))
+            .and_then(|executable| match executable.rsplit_once('/') {
+                Some((_, name)) if executable.starts_with('/') => Some(name.to_string()),
+                Some(_) => None,
+                None => Some(executable),
+            })
+            .filter(|tool| !tool.is_empty() && !tool.starts_with(['$', '(', '`']))
+    }
+
+    /// The man page, or else `--help` output, without its blank lines and indentation
+    pub fn read(tool: &str) -> Option<String> {
+        let text = Self::man_page(tool).or_else(|| Self::help_output(tool))?;
+        // Runs of blank lines and indentation cost tokens without saying anything
+        let condensed = Regex::new(r"\n\s*\n+")
+            .expect("valid regex")
+            .replace_all(text.trim(), "\n\n");
+        let condensed = Regex::new(r"(?m)^[ \t]+")
+            .expect("valid regex")
+            .replace_all(&condensed, "  ");
+        Some(condensed.into_owned())
+    }
+
+    fn man_page(tool: &str) -> Option<String> {
+        let output = Command::new("man")
+            .arg(tool)
+            .env("MANPAGER", "cat")
+            .env("PAGER", "cat")
+            .env("MANWIDTH", "100")
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output()
+            .ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        // Bold and underline are printed as a character, a backspace, and the character again
+        let text = String::from_utf8_lossy(&output.stdout);
+        let text = Regex::new(r".\x08")
+            .expect("valid regex")
+            .replace_all(&text, "");
+        (!text.trim().is_empty()).then(|| text.into_owned())
+    }
+
+    /// `tool --help`, for installed programs that don't have a man page
+    fn help_output(tool: &str) -> Option<String> {
+        if NO_HELP_PROBE.contains(&tool) {
+            return None;
+        }
+        let path = which::which(tool).ok()?;
+        let mut child = Command::new(path)
+            .arg("--help")
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .ok()?;
+
+        let started = Instant::now();
+        while child.try_wait().ok()?.is_none() {
+            if started.elapsed() > HELP_TIMEOUT {
+                let _ = child.kill();
+                let _ = child.wait();
+                return None;
+            }
+            thread::sleep(Duration::from_millis(20));
+        }
+        // Some programs print their help to stderr
+        let mut text = String::new();
+        child.stdout.take()?.read_to_string(&mut text).ok()?;
+        if text.trim().is_empty() {
+            child.stderr.take()?.read_to_string(&mut text).ok()?;
+        }
+        (!text.trim().is_empty()).then_some(text)
+    }
+}
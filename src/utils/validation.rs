@@ -1,6 +1,26 @@
 use regex::Regex;
 use std::collections::HashSet;
 
+use crate::ai::ResponseParser;
+use crate::utils::VersionShims;
+
+/// Shell builtins that are valid executables without being on PATH
+pub(crate) const SHELL_BUILTINS: &[&str] = &[
+    "cd", "echo", "export", "source", ".", "alias", "pwd", "test", "[", "printf", "read", "set",
+    "unset", "exit", "type", "eval", "exec", "true", "false",
+];
+
+/// Words that wrap the real executable
+const COMMAND_PREFIXES: &[&str] = &["sudo", "env", "time", "nohup", "nice", "command"];
+
+/// Reserved words that come before the executable of a compound command's segment
+const LEADING_KEYWORDS: &[&str] = &[
+    "if", "then", "else", "elif", "while", "until", "do", "!", "{",
+];
+
+/// Reserved words whose segment has no executable to look up
+const SKIPPED_KEYWORDS: &[&str] = &["for", "select", "case", "done", "fi", "esac", "}"];
+
 pub struct CommandValidator;
 
 impl CommandValidator {
@@ -8,6 +28,75 @@ impl CommandValidator {
         Self
     }
 
+    /// First pipeline or list segment whose executable isn't a builtin or on PATH
+    ///
+    /// Lines of a multi-line command are segments too; heredoc bodies are skipped.
+    pub fn missing_executable(&self, command: &str) -> Option<String> {
+        Self::segments(command)
+            .iter()
+            .map(String::as_str)
+            .find_map(|segment| {
+                let executable = Self::segment_executable(segment);
+                match executable {
+                    Some(keyword) if SKIPPED_KEYWORDS.contains(&keyword) => None,
+                    // A keyword alone on its line, like `then` or `do`
+                    None if segment
+                        .split_whitespace()
+                        .all(|word| LEADING_KEYWORDS.contains(&word)) =>
+                    {
+                        None
+                    }
+                    Some(executable)
+                        if SHELL_BUILTINS.contains(&executable)
+                            || VersionShims::runnable(executable) =>
+                    {
+                        None
+                    }
+                    Some(executable) => Some(executable.to_string()),
+                    None => Some(segment.to_string()),
+                }
+            })
+    }
+
+    /// Checks the executable of every pipeline and list segment
+    pub fn executables_exist(&self, command: &str) -> bool {
+        self.missing_executable(command).is_none()
+    }
+
+    /// The executable of each pipeline and list segment, in order
+    pub fn executables(&self, command: &str) -> Vec<String> {
+        Self::segments(command)
+            .iter()
+            .filter_map(|segment| Self::segment_executable(segment))
+            .filter(|executable| !SKIPPED_KEYWORDS.contains(executable))
+            .map(str::to_string)
+            .collect()
+    }
+
+    /// Pipeline and list segments, with heredoc bodies left out
+    fn segments(command: &str) -> Vec<String> {
+        let (shell, _) = ResponseParser::split_heredoc(command);
+        // Redirections like 2>&1 aren't list separators
+        shell
+            .replace("\\\n", " ")
+            .replace(">&", ">")
+            .replace("&>", ">")
+            .split(['|', ';', '&', '\n'])
+            .map(str::trim)
+            .filter(|segment| !segment.is_empty())
+            .map(str::to_string)
+            .collect()
+    }
+
+    /// The first word of a segment that isn't an assignment, prefix, or leading keyword
+    fn segment_executable(segment: &str) -> Option<&str> {
+        segment.split_whitespace().find(|word| {
+            !word.contains('=')
+                && !COMMAND_PREFIXES.contains(word)
+                && !LEADING_KEYWORDS.contains(word)
+        })
+    }
+
     pub fn is_safe_command(&self, command: &str) -> bool {
         let dangerous_patterns = self.get_dangerous_patterns();
 
@@ -0,0 +1,48 @@
+/// A word of a command and whether a change kept, removed, or added it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WordChange {
+    Same(String),
+    Removed(String),
+    Added(String),
+}
+
+/// Word-by-word differences between two commands, for showing what a fix changed
+pub struct WordDiff;
+
+impl WordDiff {
+    /// The words of `old` and `new` in order, marked by a longest common subsequence
+    pub fn diff(old: &str, new: &str) -> Vec<WordChange> {
+        let old: Vec<&str> = old.split_whitespace().collect();
+        let new: Vec<&str> = new.split_whitespace().collect();
+
+        // lengths[i][j]: longest common run of old[i..] and new[j..]
+        let mut lengths = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+        for i in (0..old.len()).rev() {
+            for j in (0..new.len()).rev() {
+                lengths[i][j] = match old[i] == new[j] {
+                    true => lengths[i + 1][j + 1] + 1,
+                    false => lengths[i + 1][j].max(lengths[i][j + 1]),
+                };
+            }
+        }
+
+        let mut changes = Vec::new();
+        let (mut i, mut j) = (0, 0);
+        while i < old.len() && j < new.len() {
+            if old[i] == new[j] {
+                changes.push(WordChange::Same(old[i].to_string()));
+                i += 1;
+                j += 1;
+            } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+                changes.push(WordChange::Removed(old[i].to_string()));
+                i += 1;
+            } else {
+                changes.push(WordChange::Added(new[j].to_string()));
+                j += 1;
+            }
+        }
+        changes.extend(old[i..].iter().map(|w| WordChange::Removed(w.to_string())));
+        changes.extend(new[j..].iter().map(|w| WordChange::Added(w.to_string())));
+        changes
+    }
+}
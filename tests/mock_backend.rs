@@ -0,0 +1,168 @@
+//! End-to-end runs of the binary against the mock backend: canned model
+//! responses go through parsing, post-processing, ranking, and the safety checks.
+
+use assert_cmd::prelude::*;
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use tempfile::TempDir;
+
+use commandy::config::DefaultConfig;
+
+/// A home, fixture directory, and working directory of its own for each test
+struct Harness {
+    home: TempDir,
+    fixtures: TempDir,
+    cwd: TempDir,
+}
+
+impl Harness {
+    /// Answers every suggestion prompt with `response`
+    fn new(response: &str) -> Self {
+        let harness = Self {
+            home: TempDir::new().unwrap(),
+            fixtures: TempDir::new().unwrap(),
+            cwd: TempDir::new().unwrap(),
+        };
+        if !response.is_empty() {
+            let fixture = serde_json::json!({ "match": "Request", "response": response });
+            fs::write(harness.fixtures.path().join("01.json"), fixture.to_string()).unwrap();
+        }
+        harness
+    }
+
+    /// Writes the default config followed by `extra`
+    fn config(&self, extra: &str) {
+        let dir = self.home.path().join(".commandy");
+        fs::create_dir_all(&dir).unwrap();
+        let config = DefaultConfig::create_default_config_file();
+        fs::write(dir.join("config.toml"), format!("{config}\n{extra}")).unwrap();
+    }
+
+    fn cwd(&self) -> &Path {
+        self.cwd.path()
+    }
+
+    fn commandy(&self, args: &[&str]) -> Command {
+        let mut cmd = Command::cargo_bin("commandy").unwrap();
+        cmd.args(args)
+            .current_dir(self.cwd())
+            .env("HOME", self.home.path())
+            .env("COMMANDY_BACKEND", "mock")
+            .env("COMMANDY_FIXTURES", self.fixtures.path())
+            .env_remove("COMMANDY_PROFILE")
+            .stdin(Stdio::null());
+        cmd
+    }
+
+    /// The commands `batch --output json` suggests for one request
+    fn batch(&self, request: &str) -> Vec<serde_json::Value> {
+        let requests = self.cwd().join("requests.txt");
+        fs::write(&requests, format!("{request}\n")).unwrap();
+        let output = self
+            .commandy(&["batch", requests.to_str().unwrap(), "--output", "json"])
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{output:?}");
+        let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        report["items"][0]["suggestions"]
+            .as_array()
+            .cloned()
+            .unwrap_or_default()
+    }
+}
+
+#[test]
+fn parses_and_ranks_model_output() {
+    let harness = Harness::new(
+        "Here are some commands you could use:\n\
+         rm -rf /\n\
+         ls -la\n\
+         ls  -la\n\
+         find . -type f -name '*.log'\n",
+    );
+    let suggestions = harness.batch("list files");
+
+    let commands: Vec<&str> = suggestions
+        .iter()
+        .map(|s| s["command"].as_str().unwrap())
+        .collect();
+    // Prose is skipped, `rm -rf /` rejected, and the respaced duplicate merged
+    assert_eq!(commands, ["ls -la", "find . -type f -name '*.log'"]);
+    for suggestion in &suggestions {
+        assert_eq!(suggestion["source"], "model");
+        assert!(suggestion["ranking"]["score"].as_f64().unwrap() > 0.0);
+    }
+}
+
+#[test]
+fn pick_prints_the_chosen_suggestion() {
+    let harness = Harness::new("ls -la\nfind . -type f -name '*.log'\ndu -sh .");
+    harness
+        .commandy(&["--no-cache", "--pick", "2", "list files"])
+        .assert()
+        .success()
+        .stdout("find . -type f -name '*.log'\n");
+}
+
+#[test]
+fn pick_past_the_last_suggestion_exits_3() {
+    let harness = Harness::new("ls -la");
+    harness
+        .commandy(&["--no-cache", "--pick", "4", "list files"])
+        .assert()
+        .code(3)
+        .stdout("");
+}
+
+#[test]
+fn guards_variables_in_destructive_commands() {
+    let harness = Harness::new("rm -rf $BUILD_DIR/*");
+    harness
+        .commandy(&["--no-cache", "--pick", "1", "clean the build"])
+        .assert()
+        .success()
+        .stdout("rm -rf \"${BUILD_DIR:?}\"/*\n");
+}
+
+#[test]
+fn profile_refuses_destructive_command() {
+    let harness = Harness::new("rm -rf build");
+    harness.config("[profiles.prod]\ndestructive = \"display_only\"\n");
+    fs::create_dir(harness.cwd().join("build")).unwrap();
+
+    harness
+        .commandy(&[
+            "--no-cache",
+            "--profile",
+            "prod",
+            "--pick",
+            "1",
+            "--execute",
+            "clean the build",
+        ])
+        .assert()
+        .code(5);
+    assert!(harness.cwd().join("build").exists());
+}
+
+#[test]
+fn inspect_reports_risk() {
+    let harness = Harness::new("");
+    let output = harness
+        .commandy(&["inspect", "rm -rf $DIR/"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Risk: destructive"), "{stdout}");
+    assert!(stdout.contains("can't be undone"), "{stdout}");
+}
+
+#[test]
+fn missing_fixture_is_backend_unavailable() {
+    let harness = Harness::new("");
+    harness
+        .commandy(&["--no-cache", "--pick", "1", "frobnicate the quux"])
+        .assert()
+        .code(4);
+}